@@ -79,7 +79,7 @@ if ($DryRun) {
     } else {
         Write-Host "  3. GitHub Actions would create a stable release"
     }
-    Write-Host "  4. Build binaries for all platforms"
+    Write-Host "  4. Build the release archives and their .sha256 checksums (see RELEASE_GUIDE.md)"
     exit 0
 }
 
@@ -0,0 +1,317 @@
+//! Bundles downloaded as archives (`url`).
+//!
+//! The archive is downloaded (or read from a `file://` URL) into a scratch
+//! directory, checked against its `sha256`, unpacked, and its contents are copied
+//! into the bundle directory.
+
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::flatten::mirror;
+
+/// Directory inside a bundle directory where archives are downloaded and unpacked
+pub const DOWNLOAD_DIR: &str = ".downloads";
+
+/// Archive formats bundles can be installed from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    TarGz,
+    Tar,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Detects the format from the file name at the end of a URL
+    pub fn from_url(url: &str) -> Result<Self> {
+        let path = url.split(['?', '#']).next().unwrap_or(url).to_lowercase();
+
+        if path.ends_with(".tar.gz") || path.ends_with(".tgz") {
+            Ok(ArchiveFormat::TarGz)
+        } else if path.ends_with(".tar") {
+            Ok(ArchiveFormat::Tar)
+        } else if path.ends_with(".zip") {
+            Ok(ArchiveFormat::Zip)
+        } else {
+            anyhow::bail!(
+                "Unsupported archive '{}': expected a .tar.gz, .tgz, .tar or .zip file",
+                url
+            )
+        }
+    }
+}
+
+/// Returns true if `value` looks like a SHA-256 checksum (64 hex digits)
+pub fn is_sha256(value: &str) -> bool {
+    value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Downloads the archive at `url`, verifies it against `sha256` and copies its
+/// contents (or those of `subdirectory` inside it) into `target`. `work_dir` is a
+/// scratch directory, removed afterwards. Returns the archive's SHA-256 checksum.
+///
+/// An archive holding a single top-level directory (as release tarballs usually do)
+/// is unpacked from inside that directory.
+pub fn install_archive(
+    url: &str,
+    sha256: Option<&str>,
+    subdirectory: Option<&Path>,
+    work_dir: &Path,
+    target: &Path,
+) -> Result<String> {
+    let format = ArchiveFormat::from_url(url)?;
+
+    with_work_dir(work_dir, |work_dir| {
+        let archive_path = work_dir.join("archive");
+        let checksum = download(url, &archive_path)?;
+        if let Some(expected) = sha256 {
+            if !checksum.eq_ignore_ascii_case(expected) {
+                anyhow::bail!(
+                    "Checksum mismatch for {}: expected sha256 {}, got {}",
+                    url,
+                    expected,
+                    checksum
+                );
+            }
+        }
+
+        let unpacked = work_dir.join("unpacked");
+        unpack(&archive_path, format, &unpacked)?;
+
+        let root = archive_root(&unpacked)?;
+        install_unpacked(&root, subdirectory, url, target)?;
+        Ok(checksum)
+    })
+}
+
+/// Runs `f` in an empty scratch directory at `work_dir`, removed afterwards
+pub fn with_work_dir<T>(work_dir: &Path, f: impl FnOnce(&Path) -> Result<T>) -> Result<T> {
+    if work_dir.exists() {
+        fs::remove_dir_all(work_dir)
+            .with_context(|| format!("Failed to clear {}", work_dir.display()))?;
+    }
+    fs::create_dir_all(work_dir)
+        .with_context(|| format!("Failed to create directory: {}", work_dir.display()))?;
+
+    let result = f(work_dir);
+    // Best effort: a leftover scratch directory is cleared the next time it is used
+    let _ = fs::remove_dir_all(work_dir);
+    result
+}
+
+/// Copies the unpacked files below `root` (or those of `subdirectory` inside it)
+/// into `target`. `source` names where they came from, for errors.
+pub fn install_unpacked(
+    root: &Path,
+    subdirectory: Option<&Path>,
+    source: &str,
+    target: &Path,
+) -> Result<()> {
+    let root = match subdirectory {
+        Some(subdirectory) => {
+            let root = root.join(subdirectory);
+            if !root.is_dir() {
+                anyhow::bail!(
+                    "Path '{}' does not exist in {}",
+                    subdirectory.display(),
+                    source
+                );
+            }
+            root
+        }
+        None => root.to_path_buf(),
+    };
+
+    mirror(&root, target)
+}
+
+/// Downloads `url` to `destination`, returning the SHA-256 checksum (hex) of its content
+fn download(url: &str, destination: &Path) -> Result<String> {
+    info!("Downloading {}", url);
+
+    let reader: Box<dyn Read> = if let Some(path) = file_url_path(url) {
+        Box::new(File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?)
+    } else {
+        let response = ureq::get(url)
+            .call()
+            .with_context(|| format!("Failed to download {}", url))?;
+        Box::new(response.into_reader())
+    };
+
+    save_hashed(reader, destination).with_context(|| format!("Failed to download {}", url))
+}
+
+/// Writes everything `reader` yields to `destination`, returning the SHA-256
+/// checksum (hex) of the content
+pub fn save_hashed(mut reader: impl Read, destination: &Path) -> Result<String> {
+    let mut file = File::create(destination)
+        .with_context(|| format!("Failed to create {}", destination.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        file.write_all(&buffer[..read])
+            .with_context(|| format!("Failed to write {}", destination.display()))?;
+    }
+
+    Ok(hex(&hasher.finalize()))
+}
+
+/// Returns the SHA-256 checksum (hex) of `bytes`
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Returns the local path of a `file://` URL
+fn file_url_path(url: &str) -> Option<PathBuf> {
+    url::Url::parse(url)
+        .ok()
+        .filter(|parsed| parsed.scheme() == "file")
+        .and_then(|parsed| parsed.to_file_path().ok())
+}
+
+/// Unpacks an archive into `destination`. Entries that would escape it are skipped
+/// (tar) or rejected (zip).
+pub fn unpack(archive_path: &Path, format: ArchiveFormat, destination: &Path) -> Result<()> {
+    let file = File::open(archive_path)?;
+
+    match format {
+        ArchiveFormat::TarGz => tar::Archive::new(GzDecoder::new(file)).unpack(destination),
+        ArchiveFormat::Tar => tar::Archive::new(file).unpack(destination),
+        ArchiveFormat::Zip => zip::ZipArchive::new(file)
+            .and_then(|mut archive| archive.extract(destination))
+            .map_err(std::io::Error::other),
+    }
+    .context("Failed to unpack archive")
+}
+
+/// Returns the directory holding an unpacked archive's files: its only top-level
+/// directory if it has exactly one entry, otherwise the unpack directory itself
+fn archive_root(unpacked: &Path) -> Result<PathBuf> {
+    let entries = fs::read_dir(unpacked)?.collect::<Result<Vec<_>, _>>()?;
+
+    match entries.as_slice() {
+        [only] if only.file_type()?.is_dir() => Ok(only.path()),
+        _ => Ok(unpacked.to_path_buf()),
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Writes a .tar.gz holding `files` below a single `assets-1.0.0` directory
+    fn create_tarball(path: &Path, files: &[(&str, &str)]) {
+        let encoder = flate2::write::GzEncoder::new(
+            File::create(path).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut builder = tar::Builder::new(encoder);
+        for (name, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(
+                    &mut header,
+                    format!("assets-1.0.0/{}", name),
+                    content.as_bytes(),
+                )
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    fn file_url(path: &Path) -> String {
+        url::Url::from_file_path(path).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_archive_format_from_url() {
+        let format = |url| ArchiveFormat::from_url(url).unwrap();
+        assert_eq!(
+            format("https://example.com/a-1.2.0.tar.gz"),
+            ArchiveFormat::TarGz
+        );
+        assert_eq!(
+            format("https://example.com/a.TGZ?token=x"),
+            ArchiveFormat::TarGz
+        );
+        assert_eq!(format("file:///tmp/a.tar"), ArchiveFormat::Tar);
+        assert_eq!(format("https://example.com/a.zip#top"), ArchiveFormat::Zip);
+        assert!(ArchiveFormat::from_url("https://example.com/a.rar").is_err());
+    }
+
+    #[test]
+    fn test_install_archive_strips_top_level_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive = temp_dir.path().join("assets-1.0.0.tar.gz");
+        create_tarball(
+            &archive,
+            &[
+                ("logo.svg", "<svg/>"),
+                ("icons/home.svg", "<svg>home</svg>"),
+            ],
+        );
+        let target = temp_dir.path().join("bundle");
+        let work_dir = temp_dir.path().join("work");
+
+        let checksum =
+            install_archive(&file_url(&archive), None, None, &work_dir, &target).unwrap();
+
+        assert!(is_sha256(&checksum));
+        assert_eq!(
+            fs::read_to_string(target.join("logo.svg")).unwrap(),
+            "<svg/>"
+        );
+        assert!(target.join("icons").join("home.svg").exists());
+        assert!(!work_dir.exists());
+
+        // The same checksum verifies; only the subdirectory is installed when given
+        let icons = temp_dir.path().join("icons");
+        install_archive(
+            &file_url(&archive),
+            Some(&checksum.to_uppercase()),
+            Some(Path::new("icons")),
+            &work_dir,
+            &icons,
+        )
+        .unwrap();
+        assert!(icons.join("home.svg").exists());
+        assert!(!icons.join("logo.svg").exists());
+    }
+
+    #[test]
+    fn test_install_archive_checksum_mismatch() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive = temp_dir.path().join("assets.tar.gz");
+        create_tarball(&archive, &[("logo.svg", "<svg/>")]);
+        let target = temp_dir.path().join("bundle");
+
+        let error = install_archive(
+            &file_url(&archive),
+            Some(&"0".repeat(64)),
+            None,
+            &temp_dir.path().join("work"),
+            &target,
+        )
+        .unwrap_err();
+
+        assert!(error.to_string().contains("Checksum mismatch"));
+        assert!(!target.exists());
+    }
+}
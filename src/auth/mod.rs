@@ -0,0 +1,404 @@
+//! Credentials for HTTPS git remotes.
+//!
+//! Tokens are read from `FPM_TOKEN_<HOST>` (e.g. `FPM_TOKEN_GITHUB_COM`) for a
+//! single host, or from `FPM_GIT_TOKEN` for every host, so CI can install private
+//! bundles without an interactive credential helper. Tokens stored with
+//! `fpm login` live in the system keychain and are used when neither is set; the
+//! keychain also holds SSH key passphrases. The git2 backend finally falls back to
+//! the user name and password for the host in `~/.netrc`.
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cache::home_dir;
+
+/// Environment variable holding a token used for every HTTPS host
+pub const GIT_TOKEN_ENV: &str = "FPM_GIT_TOKEN";
+
+/// Prefix of the environment variables holding a token for a single host
+pub const HOST_TOKEN_PREFIX: &str = "FPM_TOKEN_";
+
+/// User name sent with tokens; GitHub, GitLab and Bitbucket ignore it for tokens
+pub const TOKEN_USERNAME: &str = "x-access-token";
+
+/// Environment variable overriding the location of the netrc file, as with curl
+pub const NETRC_ENV: &str = "NETRC";
+
+/// Keychain service under which `fpm login` stores tokens, one entry per host
+pub const KEYRING_SERVICE: &str = "fpm";
+
+/// Returns the environment variable holding the token for `host`:
+/// `github.com` becomes `FPM_TOKEN_GITHUB_COM`
+pub fn host_token_var(host: &str) -> String {
+    let name: String = host
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}{}", HOST_TOKEN_PREFIX, name)
+}
+
+/// Returns the token for an HTTPS `url`, or None for other URLs and hosts
+/// without a token
+pub fn token_for_url(url: &str) -> Option<String> {
+    token_for_url_with(url, |name| {
+        std::env::var(name).ok().filter(|value| !value.is_empty())
+    })
+}
+
+/// Looks up the token for `url` with `lookup` resolving variable names, falling
+/// back to the keychain
+fn token_for_url_with(url: &str, lookup: impl Fn(&str) -> Option<String>) -> Option<String> {
+    let host = https_host(url)?;
+    lookup(&host_token_var(&host))
+        .or_else(|| lookup(GIT_TOKEN_ENV))
+        .or_else(|| stored_credential(&host))
+}
+
+/// Normalizes the host given to `fpm login`/`fpm logout`: a bare host name
+/// (`github.com`) or a URL on that host (`https://github.com/org`)
+pub fn parse_host(host: &str) -> Result<String> {
+    let host = host.trim();
+    if host.contains("://") {
+        return https_host(host).with_context(|| {
+            format!("'{}' is not an HTTPS URL; give the host name instead", host)
+        });
+    }
+
+    if host.is_empty() || host.contains(['/', '@', ' ']) {
+        anyhow::bail!(
+            "Invalid host '{}', expected a name such as github.com",
+            host
+        );
+    }
+    Ok(host.to_ascii_lowercase())
+}
+
+/// Returns the keychain account holding the passphrase of an SSH key
+pub fn ssh_key_account(key: &Path) -> String {
+    let key = std::fs::canonicalize(key).unwrap_or_else(|_| key.to_path_buf());
+    format!("ssh:{}", key.display())
+}
+
+/// Stores a secret in the system keychain under `account`: a host name for
+/// tokens, or `ssh_key_account` for SSH key passphrases
+#[cfg(feature = "keyring")]
+pub fn store_credential(account: &str, secret: &str) -> Result<()> {
+    keychain::entry(account)?
+        .set_password(secret)
+        .with_context(|| format!("Failed to store the secret for {} in the keychain", account))
+}
+
+/// Stores a secret in the system keychain under `account`: a host name for
+/// tokens, or `ssh_key_account` for SSH key passphrases
+#[cfg(not(feature = "keyring"))]
+pub fn store_credential(account: &str, _secret: &str) -> Result<()> {
+    anyhow::bail!(
+        "Cannot store the secret for {}: this fpm was built without keychain support",
+        account
+    )
+}
+
+/// Removes the secret stored under `account` from the system keychain.
+/// Returns false if none was stored.
+#[cfg(feature = "keyring")]
+pub fn delete_credential(account: &str) -> Result<bool> {
+    match keychain::entry(account)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(e).with_context(|| {
+            format!(
+                "Failed to remove the secret for {} from the keychain",
+                account
+            )
+        }),
+    }
+}
+
+/// Removes the secret stored under `account` from the system keychain.
+/// Returns false if none was stored.
+#[cfg(not(feature = "keyring"))]
+pub fn delete_credential(_account: &str) -> Result<bool> {
+    Ok(false)
+}
+
+/// Returns the secret stored under `account`. A keychain that can't be reached
+/// (such as on a headless machine) is treated as holding no secrets.
+pub fn stored_credential(account: &str) -> Option<String> {
+    keychain::stored(account)
+}
+
+/// Returns the `host[:port]` of an HTTPS URL
+pub fn https_host(url: &str) -> Option<String> {
+    let parsed = url::Url::parse(url).ok()?;
+    if parsed.scheme() != "https" {
+        return None;
+    }
+
+    let host = parsed.host_str()?;
+    Some(match parsed.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    })
+}
+
+/// A `machine` (or `default`) entry of a netrc file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetrcEntry {
+    /// Host the entry is for, or None for the `default` entry
+    pub machine: Option<String>,
+    pub login: Option<String>,
+    pub password: Option<String>,
+}
+
+/// Returns the netrc file: `$NETRC`, or `.netrc` in the home directory (also
+/// `_netrc` on Windows)
+pub fn netrc_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(NETRC_ENV).filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+
+    let home = home_dir()?;
+    let names: &[&str] = if cfg!(windows) {
+        &[".netrc", "_netrc"]
+    } else {
+        &[".netrc"]
+    };
+    names
+        .iter()
+        .map(|name| home.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Parses the entries of a netrc file. `macdef` macros are skipped, and so are
+/// `account` values, which git doesn't use.
+pub fn parse_netrc(content: &str) -> Vec<NetrcEntry> {
+    let mut tokens = Vec::new();
+    let mut in_macro = false;
+    for line in content.lines() {
+        // A macro definition runs until the next empty line
+        if in_macro {
+            in_macro = !line.trim().is_empty();
+            continue;
+        }
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+        for token in line.split_whitespace() {
+            if token == "macdef" {
+                in_macro = true;
+                break;
+            }
+            tokens.push(token);
+        }
+    }
+
+    let mut entries: Vec<NetrcEntry> = Vec::new();
+    let mut tokens = tokens.into_iter();
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" | "default" => entries.push(NetrcEntry {
+                machine: match token {
+                    "machine" => tokens.next().map(str::to_string),
+                    _ => None,
+                },
+                login: None,
+                password: None,
+            }),
+            "login" | "password" | "account" => {
+                let value = tokens.next().map(str::to_string);
+                if let Some(entry) = entries.last_mut() {
+                    match token {
+                        "login" => entry.login = value,
+                        "password" => entry.password = value,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    entries
+}
+
+/// Returns the user name and password for an HTTPS `url` in `entries`: the entry
+/// for its host, or the `default` entry
+fn netrc_credentials_in(entries: &[NetrcEntry], url: &str) -> Option<(String, String)> {
+    let parsed = url::Url::parse(url).ok()?;
+    if parsed.scheme() != "https" {
+        return None;
+    }
+    let host = parsed.host_str()?;
+
+    let entry = entries
+        .iter()
+        .find(|entry| {
+            entry
+                .machine
+                .as_deref()
+                .is_some_and(|machine| machine.eq_ignore_ascii_case(host))
+        })
+        .or_else(|| entries.iter().find(|entry| entry.machine.is_none()))?;
+    let password = entry.password.clone()?;
+    let login = match (&entry.login, parsed.username()) {
+        (Some(login), _) => login.clone(),
+        (None, "") => TOKEN_USERNAME.to_string(),
+        (None, username) => username.to_string(),
+    };
+    Some((login, password))
+}
+
+/// Returns the user name and password for an HTTPS `url` from the netrc file
+pub fn netrc_credentials(url: &str) -> Option<(String, String)> {
+    let content = fs::read_to_string(netrc_path()?).ok()?;
+    netrc_credentials_in(&parse_netrc(&content), url)
+}
+
+/// Returns the git config entry (key and value) that sends the token for `url`
+/// with every request to its host, or None if there is no token for it
+pub fn extra_header(url: &str) -> Option<(String, String)> {
+    let host = https_host(url)?;
+    let token = token_for_url(url)?;
+    let credentials =
+        base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", TOKEN_USERNAME, token));
+
+    Some((
+        format!("http.https://{}/.extraHeader", host),
+        format!("Authorization: Basic {}", credentials),
+    ))
+}
+
+#[cfg(feature = "keyring")]
+mod keychain {
+    use anyhow::{Context, Result};
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+    use tracing::debug;
+
+    use super::KEYRING_SERVICE;
+
+    /// Secrets already looked up, so each account is looked up once per run
+    static LOOKED_UP: Mutex<BTreeMap<String, Option<String>>> = Mutex::new(BTreeMap::new());
+
+    pub fn entry(account: &str) -> Result<keyring::Entry> {
+        keyring::Entry::new(KEYRING_SERVICE, account)
+            .with_context(|| format!("Failed to open the keychain entry for {}", account))
+    }
+
+    pub fn stored(account: &str) -> Option<String> {
+        let mut looked_up = LOOKED_UP.lock().unwrap_or_else(|e| e.into_inner());
+        looked_up
+            .entry(account.to_string())
+            .or_insert_with(|| match entry(account).map(|entry| entry.get_password()) {
+                Ok(Ok(secret)) => Some(secret),
+                Ok(Err(keyring::Error::NoEntry)) => None,
+                Ok(Err(e)) => {
+                    debug!("Keychain lookup for {} failed: {}", account, e);
+                    None
+                }
+                Err(e) => {
+                    debug!("{:#}", e);
+                    None
+                }
+            })
+            .clone()
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+mod keychain {
+    pub fn stored(_account: &str) -> Option<String> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn test_host_token_var() {
+        assert_eq!(host_token_var("github.com"), "FPM_TOKEN_GITHUB_COM");
+        assert_eq!(
+            host_token_var("git.example-corp.io:8443"),
+            "FPM_TOKEN_GIT_EXAMPLE_CORP_IO_8443"
+        );
+    }
+
+    #[test]
+    fn test_parse_host() {
+        assert_eq!(parse_host("GitHub.com").unwrap(), "github.com");
+        assert_eq!(
+            parse_host("https://git.example.com:8443/org/repo.git").unwrap(),
+            "git.example.com:8443"
+        );
+        assert!(parse_host("git@github.com:org/repo.git").is_err());
+        assert!(parse_host("ssh://github.com/org").is_err());
+    }
+
+    #[test]
+    fn test_token_for_url_prefers_host_token() {
+        let lookup = |name: &str| match name {
+            "FPM_TOKEN_GITHUB_COM" => Some("host-token".to_string()),
+            "FPM_GIT_TOKEN" => Some("any-host-token".to_string()),
+            _ => None,
+        };
+
+        assert_eq!(
+            token_for_url_with("https://github.com/example/icons.git", lookup),
+            Some("host-token".to_string())
+        );
+        assert_eq!(
+            token_for_url_with("https://gitlab.com/example/icons.git", lookup),
+            Some("any-host-token".to_string())
+        );
+        // Tokens are never sent over SSH or plain HTTP
+        assert_eq!(
+            token_for_url_with("git@github.com:example/icons.git", lookup),
+            None
+        );
+        assert_eq!(
+            token_for_url_with("http://github.com/example/icons.git", lookup),
+            None
+        );
+    }
+
+    #[test]
+    fn test_netrc_credentials() {
+        let entries = parse_netrc(
+            "# CI credentials\n\
+            machine github.com login ci-bot password ghp_xxx\n\
+            macdef init\n\
+            machine not-an-entry\n\
+            \n\
+            machine git.example.com\n  login deploy\n  password secret\n\
+            default login anonymous password guest\n",
+        );
+        assert_eq!(entries.len(), 3);
+
+        assert_eq!(
+            netrc_credentials_in(&entries, "https://GitHub.com/example/icons.git"),
+            Some(("ci-bot".to_string(), "ghp_xxx".to_string()))
+        );
+        assert_eq!(
+            netrc_credentials_in(&entries, "https://git.example.com:8443/design.git"),
+            Some(("deploy".to_string(), "secret".to_string()))
+        );
+        assert_eq!(
+            netrc_credentials_in(&entries, "https://gitlab.com/example/icons.git"),
+            Some(("anonymous".to_string(), "guest".to_string()))
+        );
+        assert_eq!(
+            netrc_credentials_in(&entries, "git@github.com:example/icons.git"),
+            None
+        );
+    }
+}
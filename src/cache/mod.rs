@@ -0,0 +1,389 @@
+//! Global cache of bare repository mirrors.
+//!
+//! Bundles that share a git repository borrow objects from a single mirror
+//! (via git alternates) instead of each downloading the full history.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::git::{alternates_file, GitOperations};
+use crate::settings::Settings;
+
+/// Environment variable overriding the cache location
+pub const CACHE_DIR_ENV: &str = "FPM_CACHE_DIR";
+
+/// Subdirectory of the cache holding repository mirrors
+const REPOS_DIR: &str = "repos";
+
+/// Subdirectory of the cache holding checkouts of registry indexes
+const REGISTRY_DIR: &str = "registry";
+
+/// File inside a mirror touched whenever an install uses it
+const LAST_USED_FILE: &str = "fpm-last-used";
+
+/// File inside a mirror listing the clones that borrow its objects, one path per line
+const DEPENDENTS_FILE: &str = "fpm-dependents";
+
+/// Returns the user's home directory
+pub fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Returns the root directory of the fpm cache: the one of `settings`, or else the
+/// platform's cache directory
+pub fn cache_dir(settings: &Settings) -> Result<PathBuf> {
+    if let Some(dir) = &settings.cache_dir {
+        return Ok(dir.clone());
+    }
+
+    if let Some(dir) = std::env::var_os(CACHE_DIR_ENV).filter(|d| !d.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+
+    if let Some(dir) = std::env::var_os("XDG_CACHE_HOME").filter(|d| !d.is_empty()) {
+        return Ok(PathBuf::from(dir).join("fpm"));
+    }
+
+    if let Some(dir) = std::env::var_os("LOCALAPPDATA").filter(|d| !d.is_empty()) {
+        return Ok(PathBuf::from(dir).join("fpm").join("cache"));
+    }
+
+    let home = home_dir().context(
+        "Could not determine the cache directory. Set FPM_CACHE_DIR or cache_dir in the fpm \
+        config to choose one explicitly.",
+    )?;
+
+    Ok(home.join(".cache").join("fpm"))
+}
+
+/// Returns the directory holding repository mirrors
+pub fn repos_dir(settings: &Settings) -> Result<PathBuf> {
+    Ok(cache_dir(settings)?.join(REPOS_DIR))
+}
+
+/// Returns the mirror location for a repository URL inside `repos_dir`
+pub fn mirror_path(repos_dir: &Path, url: &str) -> PathBuf {
+    repos_dir.join(mirror_dir_name(url))
+}
+
+/// Returns the location of the checkout of the registry index at `url`
+pub fn registry_index_path(settings: &Settings, url: &str) -> Result<PathBuf> {
+    let name = mirror_dir_name(url);
+    let name = name.trim_end_matches(".git");
+    Ok(cache_dir(settings)?.join(REGISTRY_DIR).join(name))
+}
+
+/// Lists all repository mirrors currently in the cache
+pub fn list_mirrors(settings: &Settings) -> Result<Vec<PathBuf>> {
+    let dir = repos_dir(settings)?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut mirrors = Vec::new();
+    for entry in std::fs::read_dir(&dir)
+        .with_context(|| format!("Failed to read cache directory: {}", dir.display()))?
+    {
+        let path = entry?.path();
+        if path.is_dir() {
+            mirrors.push(path);
+        }
+    }
+    mirrors.sort();
+
+    Ok(mirrors)
+}
+
+/// Ensures an up-to-date bare mirror of `url` exists in the cache and returns its path
+pub fn ensure_mirror(
+    git_ops: &dyn GitOperations,
+    settings: &Settings,
+    url: &str,
+    ssh_key: Option<&Path>,
+) -> Result<PathBuf> {
+    let path = mirror_path(&repos_dir(settings)?, url);
+
+    git_ops
+        .sync_mirror(url, &path, ssh_key)
+        .with_context(|| format!("Failed to update cached mirror of {}", url))?;
+    mark_used(&path)?;
+
+    Ok(path)
+}
+
+/// A repository mirror in the cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedMirror {
+    pub path: PathBuf,
+    /// Disk usage in bytes
+    pub size: u64,
+    /// When an install last used the mirror
+    pub last_used: SystemTime,
+}
+
+/// Lists all repository mirrors in the cache with their size and last use
+pub fn list_cached_mirrors(settings: &Settings) -> Result<Vec<CachedMirror>> {
+    list_mirrors(settings)?
+        .into_iter()
+        .map(|path| {
+            let size = dir_size(&path)?;
+            let last_used = last_used(&path)?;
+            Ok(CachedMirror {
+                path,
+                size,
+                last_used,
+            })
+        })
+        .collect()
+}
+
+/// Records that the clone at `clone` borrows objects from `mirror`, so the clone can
+/// be given its own copy of them before the mirror is removed
+pub fn add_dependent(mirror: &Path, clone: &Path) -> Result<()> {
+    if !mirror.is_dir() {
+        return Ok(());
+    }
+    let clone = std::path::absolute(clone)?;
+    if read_dependents(mirror).contains(&clone) {
+        return Ok(());
+    }
+
+    // Appended rather than rewritten, as aliases of a repository install in parallel
+    let file = mirror.join(DEPENDENTS_FILE);
+    let mut dependents = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&file)
+        .with_context(|| format!("Failed to open {}", file.display()))?;
+    writeln!(dependents, "{}", clone.display())
+        .with_context(|| format!("Failed to write {}", file.display()))
+}
+
+/// The recorded clones that still borrow objects from `mirror`
+pub fn dependents(mirror: &Path) -> Vec<PathBuf> {
+    let objects = mirror.join("objects");
+    let objects = fs::canonicalize(&objects).unwrap_or(objects);
+
+    let mut dependents = read_dependents(mirror);
+    dependents.retain(|clone| {
+        fs::read_to_string(alternates_file(clone)).is_ok_and(|content| {
+            content.lines().any(|line| {
+                let line = Path::new(line.trim());
+                fs::canonicalize(line).unwrap_or_else(|_| line.to_path_buf()) == objects
+            })
+        })
+    });
+    dependents.dedup();
+    dependents
+}
+
+fn read_dependents(mirror: &Path) -> Vec<PathBuf> {
+    let mut dependents: Vec<PathBuf> = fs::read_to_string(mirror.join(DEPENDENTS_FILE))
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(PathBuf::from)
+        .collect();
+    dependents.sort();
+    dependents
+}
+
+/// Deletes a mirror from the cache
+pub fn remove_mirror(path: &Path) -> Result<()> {
+    fs::remove_dir_all(path)
+        .with_context(|| format!("Failed to remove cached mirror: {}", path.display()))
+}
+
+/// Picks the mirrors to prune: those unused for longer than `max_age`, then the
+/// least recently used ones until the rest fit in `max_size` bytes
+pub fn select_for_gc(
+    mirrors: &[CachedMirror],
+    max_age: Option<Duration>,
+    max_size: Option<u64>,
+    now: SystemTime,
+) -> Vec<&CachedMirror> {
+    let mut by_last_use: Vec<&CachedMirror> = mirrors.iter().collect();
+    by_last_use.sort_by_key(|mirror| mirror.last_used);
+
+    let mut remaining: u64 = mirrors.iter().map(|mirror| mirror.size).sum();
+    let mut selected = Vec::new();
+
+    for mirror in by_last_use {
+        let too_old = max_age.is_some_and(|max_age| {
+            now.duration_since(mirror.last_used)
+                .is_ok_and(|age| age > max_age)
+        });
+        let too_big = max_size.is_some_and(|max_size| remaining > max_size);
+
+        if too_old || too_big {
+            remaining -= mirror.size;
+            selected.push(mirror);
+        }
+    }
+
+    selected
+}
+
+/// Records that an install just used a mirror
+fn mark_used(path: &Path) -> Result<()> {
+    if !path.is_dir() {
+        return Ok(());
+    }
+
+    let marker = path.join(LAST_USED_FILE);
+    fs::write(&marker, "").with_context(|| format!("Failed to write {}", marker.display()))
+}
+
+/// When a mirror was last used; mirrors never marked fall back to their own
+/// modification time
+fn last_used(path: &Path) -> Result<SystemTime> {
+    let marker = path.join(LAST_USED_FILE);
+    let metadata = fs::metadata(&marker)
+        .or_else(|_| fs::metadata(path))
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    Ok(metadata.modified()?)
+}
+
+/// Total size of the files below a directory
+pub fn dir_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in walkdir::WalkDir::new(path) {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            size += entry.metadata()?.len();
+        }
+    }
+
+    Ok(size)
+}
+
+/// Builds a readable, collision-free directory name for a repository URL:
+/// the last path segment followed by a stable hash of the normalized URL.
+fn mirror_dir_name(url: &str) -> String {
+    let normalized = normalize_url(url);
+    format!(
+        "{}-{:016x}.git",
+        repository_name(url),
+        fnv1a_64(normalized.as_bytes())
+    )
+}
+
+/// Returns the last path segment of a repository URL (without `.git`), with
+/// characters that don't belong in a directory name replaced
+pub fn repository_name(url: &str) -> String {
+    let normalized = normalize_url(url);
+
+    let last_segment = normalized.rsplit(['/', ':', '\\']).next().unwrap_or("repo");
+    let readable: String = last_segment
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if readable.is_empty() {
+        "repo".to_string()
+    } else {
+        readable
+    }
+}
+
+/// Normalizes a URL so trivially different spellings map to the same mirror
+pub fn normalize_url(url: &str) -> String {
+    let trimmed = url.trim().trim_end_matches('/');
+    trimmed.strip_suffix(".git").unwrap_or(trimmed).to_string()
+}
+
+/// FNV-1a hash; stable across Rust versions unlike the std hasher
+pub fn fnv1a_64(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn mirror(name: &str, size: u64, days_ago: u64, now: SystemTime) -> CachedMirror {
+        CachedMirror {
+            path: PathBuf::from(name),
+            size,
+            last_used: now - Duration::from_secs(days_ago * 24 * 60 * 60),
+        }
+    }
+
+    fn names(selected: Vec<&CachedMirror>) -> Vec<String> {
+        selected
+            .iter()
+            .map(|mirror| mirror.path.display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_select_for_gc() {
+        let now = SystemTime::now();
+        let mirrors = vec![
+            mirror("fresh", 300, 1, now),
+            mirror("stale", 100, 40, now),
+            mirror("recent", 200, 5, now),
+        ];
+        let days = |days: u64| Duration::from_secs(days * 24 * 60 * 60);
+
+        assert!(select_for_gc(&mirrors, None, None, now).is_empty());
+        assert_eq!(
+            names(select_for_gc(&mirrors, Some(days(30)), None, now)),
+            vec!["stale"]
+        );
+        // Least recently used go first until the rest fits
+        assert_eq!(
+            names(select_for_gc(&mirrors, None, Some(350), now)),
+            vec!["stale", "recent"]
+        );
+        assert_eq!(
+            names(select_for_gc(&mirrors, Some(days(3)), Some(1000), now)),
+            vec!["stale", "recent"]
+        );
+    }
+
+    #[test]
+    fn test_mirror_dir_name_is_readable_and_stable() {
+        let name = mirror_dir_name("https://github.com/example/icons.git");
+        assert!(name.starts_with("icons-"));
+        assert!(name.ends_with(".git"));
+        assert_eq!(
+            name,
+            mirror_dir_name("https://github.com/example/icons.git")
+        );
+    }
+
+    #[test]
+    fn test_mirror_dir_name_normalizes_trivial_differences() {
+        assert_eq!(
+            mirror_dir_name("https://github.com/example/icons.git"),
+            mirror_dir_name("https://github.com/example/icons/")
+        );
+    }
+
+    #[test]
+    fn test_mirror_dir_name_distinguishes_repositories() {
+        assert_ne!(
+            mirror_dir_name("https://github.com/team-a/icons.git"),
+            mirror_dir_name("https://github.com/team-b/icons.git")
+        );
+        assert!(mirror_dir_name("git@github.com:org/icons.git").starts_with("icons-"));
+    }
+}
@@ -0,0 +1,201 @@
+//! Content checksums of installed bundles.
+//!
+//! An install hashes every file of a git bundle and records a checksum of the whole
+//! tree in fpm.lock, and the hash of each file in the bundle directory's
+//! checksums.toml. `fpm verify` hashes the files again to find bundles that were
+//! changed or corrupted after they were installed, and which of their files differ.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::archive::sha256_hex;
+use crate::flatten::is_managed;
+use crate::types::{InstallState, CHECKSUM_FILE};
+
+/// SHA-256 of each file of a bundle, keyed by its path relative to the bundle
+/// with `/` separators
+pub type FileHashes = BTreeMap<String, String>;
+
+/// The file hashes recorded for the bundles of a bundle directory
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RecordedHashes {
+    #[serde(default)]
+    bundles: BTreeMap<String, FileHashes>,
+}
+
+/// Hashes the files of a bundle, leaving out the .git and .fpm directories at its
+/// top. Symbolic links are hashed by their target, without following them.
+pub fn hash_tree(root: &Path) -> Result<FileHashes> {
+    let mut files = FileHashes::new();
+    for entry in walkdir::WalkDir::new(root)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|entry| !is_managed(entry.path().strip_prefix(root).unwrap_or(entry.path())))
+    {
+        let entry = entry?;
+        if entry.file_type().is_dir() {
+            continue;
+        }
+
+        let content = if entry.path_is_symlink() {
+            fs::read_link(entry.path())?
+                .to_string_lossy()
+                .into_owned()
+                .into_bytes()
+        } else {
+            fs::read(entry.path())
+                .with_context(|| format!("Failed to read {}", entry.path().display()))?
+        };
+        let relative = entry.path().strip_prefix(root)?;
+        let name: Vec<String> = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        files.insert(name.join("/"), sha256_hex(&content));
+    }
+
+    Ok(files)
+}
+
+/// Checksum of a whole bundle, e.g. "sha256:9f86d0…": the SHA-256 of its file
+/// hashes and paths in the format of `sha256sum`
+pub fn tree_checksum(files: &FileHashes) -> String {
+    let listing: String = files
+        .iter()
+        .map(|(path, hash)| format!("{}  {}\n", hash, path))
+        .collect();
+    format!("sha256:{}", sha256_hex(listing.as_bytes()))
+}
+
+/// Loads the file hashes recorded for the bundle `name` of a bundle directory
+pub fn load_file_hashes(bundle_dir: &Path, name: &str) -> Result<Option<FileHashes>> {
+    Ok(load_recorded(bundle_dir)?.bundles.remove(name))
+}
+
+/// Records the file hashes of the bundle `name` of a bundle directory. Hashes of
+/// bundles `state` no longer lists are dropped.
+pub fn record_file_hashes(
+    bundle_dir: &Path,
+    state: &InstallState,
+    name: &str,
+    files: FileHashes,
+) -> Result<()> {
+    let mut recorded = load_recorded(bundle_dir)?;
+    recorded
+        .bundles
+        .retain(|bundle, _| state.bundles.contains_key(bundle));
+    recorded.bundles.insert(name.to_string(), files);
+
+    let path = bundle_dir.join(CHECKSUM_FILE);
+    let content = toml::to_string(&recorded).context("Failed to serialize checksums")?;
+    fs::write(&path, content)
+        .with_context(|| format!("Failed to write checksums: {}", path.display()))
+}
+
+fn load_recorded(bundle_dir: &Path) -> Result<RecordedHashes> {
+    let path = bundle_dir.join(CHECKSUM_FILE);
+    if !path.exists() {
+        return Ok(RecordedHashes::default());
+    }
+
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read checksums: {}", path.display()))?;
+    toml::from_str(&content)
+        .with_context(|| format!("Failed to parse checksums: {}", path.display()))
+}
+
+/// How a file differs from when its bundle was installed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileChange {
+    Modified,
+    Added,
+    Removed,
+}
+
+impl fmt::Display for FileChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileChange::Modified => write!(f, "modified"),
+            FileChange::Added => write!(f, "added"),
+            FileChange::Removed => write!(f, "removed"),
+        }
+    }
+}
+
+/// Lists the files that differ between the recorded and the current hashes, by path
+pub fn compare(recorded: &FileHashes, current: &FileHashes) -> Vec<(String, FileChange)> {
+    let mut changes: Vec<(String, FileChange)> = recorded
+        .iter()
+        .filter_map(|(path, hash)| match current.get(path) {
+            None => Some((path.clone(), FileChange::Removed)),
+            Some(current) if current != hash => Some((path.clone(), FileChange::Modified)),
+            Some(_) => None,
+        })
+        .collect();
+    changes.extend(
+        current
+            .keys()
+            .filter(|path| !recorded.contains_key(*path))
+            .map(|path| (path.clone(), FileChange::Added)),
+    );
+    changes.sort_by(|a, b| a.0.cmp(&b.0));
+    changes
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::types::BUNDLE_DIR;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_hash_tree_skips_git_and_nested_bundles() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("svg")).unwrap();
+        fs::write(root.join("svg").join("logo.svg"), "<svg/>").unwrap();
+        fs::write(root.join("README.md"), "icons").unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join(".git").join("HEAD"), "ref: refs/heads/main").unwrap();
+        fs::create_dir_all(root.join(BUNDLE_DIR).join("fonts")).unwrap();
+        fs::write(root.join(BUNDLE_DIR).join("fonts").join("a.ttf"), "font").unwrap();
+
+        let files = hash_tree(root).unwrap();
+        let paths: Vec<&str> = files.keys().map(String::as_str).collect();
+        assert_eq!(paths, vec!["README.md", "svg/logo.svg"]);
+        assert_eq!(files["README.md"], sha256_hex(b"icons"));
+
+        // Nested bundles don't count towards the checksum either
+        let checksum = tree_checksum(&files);
+        assert!(checksum.starts_with("sha256:"));
+        fs::write(root.join(BUNDLE_DIR).join("fonts").join("a.ttf"), "other").unwrap();
+        assert_eq!(tree_checksum(&hash_tree(root).unwrap()), checksum);
+        fs::write(root.join("README.md"), "changed").unwrap();
+        assert_ne!(tree_checksum(&hash_tree(root).unwrap()), checksum);
+    }
+
+    #[test]
+    fn test_compare_lists_changed_files() {
+        let hashes = |entries: &[(&str, &str)]| -> FileHashes {
+            entries
+                .iter()
+                .map(|(path, hash)| (path.to_string(), hash.to_string()))
+                .collect()
+        };
+        let recorded = hashes(&[("a.svg", "1"), ("b.svg", "2"), ("c.svg", "3")]);
+        let current = hashes(&[("a.svg", "1"), ("b.svg", "9"), ("d.svg", "4")]);
+
+        assert_eq!(
+            compare(&recorded, &current),
+            vec![
+                ("b.svg".to_string(), FileChange::Modified),
+                ("c.svg".to_string(), FileChange::Removed),
+                ("d.svg".to_string(), FileChange::Added),
+            ]
+        );
+    }
+}
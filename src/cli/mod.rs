@@ -1,5 +1,14 @@
-use clap::{Parser, Subcommand};
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use clap_complete::{ArgValueCandidates, Shell};
 use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::commands::cache::{parse_age, parse_size};
+use crate::commands::completions::{bundle_candidates, script_candidates};
+use crate::commands::log;
+use crate::config::find_manifest;
+use crate::settings::{ColorMode, GitBackend, OutputFormat, Settings};
+use crate::types::BumpLevel;
 
 /// fpm - A file package manager that resembles Git and NPM, but for files in general.
 ///
@@ -11,56 +20,845 @@ use std::path::PathBuf;
 #[command(about = "File Package Manager - A file package manager using git as backend")]
 #[command(long_about = None)]
 pub struct Cli {
-    /// Path to the bundle.toml manifest file
-    #[arg(short, long, default_value = "bundle.toml")]
-    pub manifest_path: PathBuf,
+    /// Path to the bundle.toml manifest file (defaults to the nearest bundle.toml in
+    /// the current directory or its parents)
+    #[arg(short, long)]
+    pub manifest_path: Option<PathBuf>,
+
+    /// When to color the output
+    #[arg(long, global = true, value_name = "WHEN")]
+    pub color: Option<ColorMode>,
+
+    /// Git implementation to use
+    #[arg(long, global = true, value_name = "BACKEND")]
+    pub git_backend: Option<GitBackend>,
+
+    /// Location of the global cache
+    #[arg(long, global = true, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// How install and push report their results; json prints a result record per
+    /// bundle instead of progress lines
+    #[arg(long, global = true, value_name = "FORMAT")]
+    pub output: Option<OutputFormat>,
 
     #[command(subcommand)]
     pub command: Commands,
 }
 
+impl Cli {
+    /// The manifest to work on: the one given with --manifest-path, or else the
+    /// nearest bundle.toml above the current directory. Without either, bundle.toml
+    /// in the current directory, so commands report it as missing.
+    pub fn manifest_path(&self) -> PathBuf {
+        if let Some(manifest_path) = &self.manifest_path {
+            return manifest_path.clone();
+        }
+
+        std::env::current_dir()
+            .ok()
+            .and_then(|dir| find_manifest(&dir))
+            .unwrap_or_else(|| PathBuf::from("bundle.toml"))
+    }
+
+    /// Settings given as command line flags
+    pub fn settings(&self) -> Settings {
+        Settings {
+            git_backend: self.git_backend,
+            cache_dir: self.cache_dir.clone(),
+            color: self.color,
+            output: self.output,
+            ..Default::default()
+        }
+    }
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Install bundles from the manifest file
     ///
     /// Fetches all bundles specified in bundle.toml from their git repositories
     /// and places them in .fpm subdirectories.
-    Install,
+    Install {
+        /// Only install this bundle and the bundles nested in it, leaving the others
+        /// as they are. Nested bundles are named by install path, e.g. ui-kit/base-styles
+        #[arg(short, long, add = ArgValueCandidates::new(bundle_candidates))]
+        bundle: Option<String>,
+
+        /// Install exactly the commits in fpm.lock; fail if it is missing or out of sync
+        #[arg(long)]
+        frozen: bool,
+
+        /// Number of bundles to fetch concurrently (the jobs setting, or 4 by default)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+
+        /// Keep installing the other bundles when one fails, reporting every
+        /// failure at the end
+        #[arg(long)]
+        keep_going: bool,
+
+        /// Delete installed bundles and install them from scratch, discarding local
+        /// changes (bundles with changes are skipped otherwise)
+        #[arg(long)]
+        force: bool,
+
+        /// Fetch every bundle from its source even if fpm.lock says it is up to date
+        /// (downloads again, and fetches the branches of clones)
+        #[arg(long)]
+        refresh: bool,
+
+        /// Stash the uncommitted changes of bundles, update them and apply the changes
+        /// again; conflicting files are reported instead of failing the install
+        #[arg(long, conflicts_with = "force")]
+        autostash: bool,
+
+        /// Only install the bundles in this group (repeatable). Groups are set with
+        /// `group = "..."` on the bundles of bundle.toml
+        #[arg(short, long = "group", value_name = "GROUP")]
+        groups: Vec<String>,
+
+        /// Leave out the bundles in this group (repeatable), e.g. `--exclude-group dev`
+        #[arg(long = "exclude-group", value_name = "GROUP")]
+        exclude_groups: Vec<String>,
+
+        /// Also install this optional bundle (repeatable). Nested bundles are named by
+        /// install path, e.g. ui-kit/textures
+        #[arg(long = "with", value_name = "BUNDLE", add = ArgValueCandidates::new(bundle_candidates))]
+        with: Vec<String>,
+
+        /// Install every bundle.toml in the current directory and below (skipping
+        /// .gitignored paths), summarizing the results per manifest
+        #[arg(long, conflicts_with = "bundle")]
+        all: bool,
+
+        /// Install nested bundles that several bundles depend on once, in .fpm/.shared,
+        /// and link them into each (`dedupe = true` in bundle.toml does the same)
+        #[arg(long)]
+        dedupe: bool,
+
+        /// Install bundles that require incompatible versions of the same nested
+        /// bundle, giving each its version, instead of failing
+        #[arg(long)]
+        allow_duplicates: bool,
+
+        /// Don't run the [hooks] of bundle.toml or of installed bundles
+        #[arg(long)]
+        no_hooks: bool,
+    },
+
+    /// Add a bundle to the manifest
+    ///
+    /// Checks that the repository is reachable and has the requested version or
+    /// branch, then appends a [bundles.<name>] entry to bundle.toml. A bundle given
+    /// by name only is looked up in the configured registry.
+    Add {
+        /// Name of the bundle (its directory under .fpm)
+        name: String,
+
+        /// Git repository URL (SSH or HTTPS) or shorthand such as github:org/repo.
+        /// Looked up by the bundle's name in the configured registry if omitted
+        git: Option<String>,
+
+        /// Version or semver requirement, resolved against the repository's tags
+        /// (defaults to "*", tracking the branch)
+        #[arg(long)]
+        version: Option<String>,
+
+        /// Branch to track
+        #[arg(short, long)]
+        branch: Option<String>,
+
+        /// Subdirectory within the repository
+        #[arg(long)]
+        path: Option<PathBuf>,
+
+        /// SSH private key used to access the repository
+        #[arg(long)]
+        ssh_key: Option<PathBuf>,
+
+        /// Install bundles right after adding the entry
+        #[arg(short, long)]
+        install: bool,
+    },
+
+    /// Search the registry for bundles
+    ///
+    /// Lists the bundles of the configured registry whose name, description or
+    /// keywords contain the term, with their latest version and repository.
+    Search {
+        /// Text to look for
+        term: String,
+    },
+
+    /// Remove a bundle from the manifest and delete its installed files
+    ///
+    /// Deletes the [bundles.<name>] entry from bundle.toml, removes .fpm/<name> and
+    /// drops the bundle (and the bundles nested in it) from fpm.lock. Refuses to remove
+    /// a bundle with uncommitted local changes unless --force is given.
+    Remove {
+        /// Name of the bundle to remove
+        #[arg(add = ArgValueCandidates::new(bundle_candidates))]
+        name: String,
+
+        /// Remove the bundle even if it has uncommitted local changes
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Use a local working copy in place of an installed bundle
+    ///
+    /// Replaces the installed bundle with a link to the working copy, so changes to
+    /// the bundle show up in this project right away. Installs keep the link, and
+    /// fpm.lock is left as it is. Without a path, lists the linked bundles.
+    Link {
+        /// Working copy of the bundle
+        path: Option<PathBuf>,
+
+        /// Bundle to link (defaults to the name in the working copy's bundle.toml, or
+        /// its directory name). Nested bundles are named by install path
+        #[arg(short, long, add = ArgValueCandidates::new(bundle_candidates))]
+        bundle: Option<String>,
+
+        /// Link even if the installed bundle has uncommitted local changes
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Remove a link made with `fpm link` and install the bundle from its source again
+    Unlink {
+        /// Bundle to unlink (all linked bundles if omitted)
+        #[arg(add = ArgValueCandidates::new(bundle_candidates))]
+        bundle: Option<String>,
+    },
+
+    /// Install strictly from fpm.lock (same as `install --frozen`)
+    ///
+    /// Fails if fpm.lock is missing or out of sync with bundle.toml and never resolves
+    /// branch tips, giving reproducible CI builds.
+    Ci {
+        /// Number of bundles to fetch concurrently (the jobs setting, or 4 by default)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+
+        /// Keep installing the other bundles when one fails, reporting every
+        /// failure at the end
+        #[arg(long)]
+        keep_going: bool,
+
+        /// Only install the bundles in this group (repeatable). Groups are set with
+        /// `group = "..."` on the bundles of bundle.toml
+        #[arg(short, long = "group", value_name = "GROUP")]
+        groups: Vec<String>,
+
+        /// Leave out the bundles in this group (repeatable), e.g. `--exclude-group dev`
+        #[arg(long = "exclude-group", value_name = "GROUP")]
+        exclude_groups: Vec<String>,
+
+        /// Also install this optional bundle (repeatable). Nested bundles are named by
+        /// install path, e.g. ui-kit/textures
+        #[arg(long = "with", value_name = "BUNDLE", add = ArgValueCandidates::new(bundle_candidates))]
+        with: Vec<String>,
+
+        /// Install nested bundles that several bundles depend on once, in .fpm/.shared,
+        /// and link them into each (`dedupe = true` in bundle.toml does the same)
+        #[arg(long)]
+        dedupe: bool,
+
+        /// Install bundles that require incompatible versions of the same nested
+        /// bundle, giving each its version, instead of failing
+        #[arg(long)]
+        allow_duplicates: bool,
+
+        /// Don't run the [hooks] of bundle.toml or of installed bundles
+        #[arg(long)]
+        no_hooks: bool,
+    },
+
+    /// Move bundles forward to the latest versions their manifests allow
+    ///
+    /// Re-resolves versions (respecting semver ranges) and branch tips instead of
+    /// using the commits in fpm.lock, installs the result, rewrites the lockfile and
+    /// reports each bundle's old and new ref, including nested bundles.
+    Update {
+        /// Bundle to update, with the bundles nested in it (updates all if not specified).
+        /// Nested bundles are named by install path, e.g. ui-kit/base-styles
+        #[arg(add = ArgValueCandidates::new(bundle_candidates))]
+        bundle: Option<String>,
+
+        /// Number of bundles to fetch concurrently (the jobs setting, or 4 by default)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+
+        /// Keep installing the other bundles when one fails, reporting every
+        /// failure at the end
+        #[arg(long)]
+        keep_going: bool,
+
+        /// Delete the updated bundles and install them from scratch, discarding local
+        /// changes (bundles with changes are skipped otherwise)
+        #[arg(long)]
+        force: bool,
+
+        /// Stash the uncommitted changes of bundles, update them and apply the changes
+        /// again; conflicting files are reported instead of failing the update
+        #[arg(long, conflicts_with = "force")]
+        autostash: bool,
+
+        /// Install nested bundles that several bundles depend on once, in .fpm/.shared,
+        /// and link them into each (`dedupe = true` in bundle.toml does the same)
+        #[arg(long)]
+        dedupe: bool,
+
+        /// Install bundles that require incompatible versions of the same nested
+        /// bundle, giving each its version, instead of failing
+        #[arg(long)]
+        allow_duplicates: bool,
+
+        /// Don't run the [hooks] of bundle.toml or of installed bundles
+        #[arg(long)]
+        no_hooks: bool,
+    },
+
+    /// List bundles with newer versions or commits available
+    ///
+    /// Looks up every bundle's remote (tags for versioned bundles, the branch tip
+    /// otherwise) and prints the installed, wanted and latest refs. Exits with an
+    /// error if any bundle is outdated, so it can gate CI.
+    Outdated,
+
+    /// Fetch the remote of every bundle without changing installed files
+    ///
+    /// Fetches the branch each installed bundle tracks into its remote-tracking ref
+    /// and reports which bundles have new upstream commits. Working trees are left
+    /// alone; review the changes with `fpm diff --remote` and apply them with
+    /// `fpm update`.
+    Fetch {
+        /// Number of bundles to fetch concurrently (the jobs setting, or 4 by default)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+    },
 
     /// Publish bundles to their remote repositories
     ///
     /// Pushes local bundle changes to the configured git remotes.
     /// Requires version increment if changes have been made.
-    Publish,
+    /// With --oci, uploads the root directory as an OCI artifact instead.
+    /// Refuses to publish a version that is already tagged on the remote.
+    Publish {
+        /// Registry reference to publish to, e.g. ghcr.io/org/bundle:1.2.0
+        #[arg(long, value_name = "REFERENCE")]
+        oci: Option<String>,
+
+        /// Publish even if the version is already tagged on the remote, moving its tag
+        #[arg(long)]
+        force: bool,
+
+        /// Create the remote repositories via the host API first (GitHub or GitLab),
+        /// with the manifest's description
+        #[arg(long, conflicts_with = "oci")]
+        create_remote: bool,
+
+        /// Create the remote repositories as private
+        #[arg(long, requires = "create_remote")]
+        private: bool,
+    },
+
+    /// Mark a published version of a source bundle as yanked
+    ///
+    /// Pushes a yanked/<tag> tag next to the version's tag on every remote the bundle
+    /// is published to. The version stays installable, but installs resolving to it
+    /// warn that it was yanked.
+    Yank {
+        /// Version to yank, e.g. 1.2.0
+        version: String,
+
+        /// Why the version was yanked, recorded in the tag's annotation
+        #[arg(short, long)]
+        reason: Option<String>,
+    },
+
+    /// Commit changes in installed bundles without pushing them
+    ///
+    /// Commits local modifications of installed bundles as push does, bumping their
+    /// versions, and leaves pushing them to a later `fpm push`.
+    Commit {
+        /// Name of a specific bundle to commit (commits all bundles if not specified)
+        #[arg(short, long, add = ArgValueCandidates::new(bundle_candidates))]
+        bundle: Option<String>,
+
+        /// Commit message for the changes (defaults to the commit_message template)
+        #[arg(short, long)]
+        message: Option<String>,
+
+        /// Part of the version to increment when it wasn't changed by hand, overriding
+        /// the bundles' bump setting (patch by default)
+        #[arg(long, value_enum)]
+        bump: Option<BumpLevel>,
+
+        /// Commit the versions as they are, without incrementing any
+        #[arg(long, conflicts_with = "bump")]
+        no_bump: bool,
+
+        /// Commit only the changes of these files and directories of the bundle,
+        /// leaving its other changes uncommitted
+        #[arg(long, value_name = "PATH", num_args = 1.., requires = "bundle")]
+        paths: Vec<String>,
+    },
 
     /// Push changes in installed bundles back to their source repositories
     ///
-    /// Commits and pushes local modifications made to installed bundles.
-    /// Starts from the current manifest and recursively pushes all nested bundles
-    /// (deepest first, then parent bundles). Requires write access to the source repositories.
+    /// Commits and pushes local modifications made to installed bundles, along with
+    /// commits made earlier with `fpm commit`. Starts from the current manifest and
+    /// recursively pushes all nested bundles (deepest first, then parent bundles).
+    /// Requires write access to the source repositories.
     Push {
         /// Name of a specific bundle to push (pushes all bundles if not specified)
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCandidates::new(bundle_candidates))]
         bundle: Option<String>,
 
-        /// Commit message for the changes
+        /// Commit message for the changes (defaults to the commit_message template)
         #[arg(short, long)]
         message: Option<String>,
+
+        /// Show the files, commit message, version bump and branch of every bundle
+        /// with changes, without committing or pushing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Part of the version to increment when it wasn't changed by hand, overriding
+        /// the bundles' bump setting (patch by default)
+        #[arg(long, value_enum)]
+        bump: Option<BumpLevel>,
+
+        /// Push the versions as they are, without incrementing any (same as --bump off)
+        #[arg(long, conflicts_with = "bump")]
+        no_bump: bool,
+
+        /// Create this branch and push to it instead of the branch each bundle was
+        /// installed from, e.g. when that branch is protected
+        #[arg(long, value_name = "NAME")]
+        branch: Option<String>,
+
+        /// Open a pull request (a merge request on GitLab) from --branch into the
+        /// branch each bundle was installed from, printing its URL
+        #[arg(long, requires = "branch")]
+        pr: bool,
+
+        /// Show the diffstat of every bundle with changes and ask whether to push it,
+        /// and with which commit message
+        #[arg(short, long, conflicts_with = "dry_run")]
+        interactive: bool,
+
+        /// Commit and push only the changes of these files and directories of the
+        /// bundle, leaving its other changes uncommitted
+        #[arg(long, value_name = "PATH", num_args = 1.., requires = "bundle")]
+        paths: Vec<String>,
     },
 
     /// Show status of all bundles
     ///
     /// Displays whether bundles are synced, unsynced, or are source bundles.
-    Status,
+    Status {
+        /// Print a JSON document with every bundle's name, path, status, depth,
+        /// commit and branch instead of the table
+        #[arg(long)]
+        json: bool,
+
+        /// Exit with an error if any bundle is unsynced, missing, drifted or at a
+        /// version the manifest doesn't ask for, e.g. to fail a CI build
+        #[arg(long)]
+        check: bool,
+
+        /// Fetch the branch each bundle tracks and show how many commits it is
+        /// ahead of and behind it
+        #[arg(long)]
+        remote: bool,
+
+        /// Deepest level of nested bundles to show (0 shows the top-level bundles only)
+        #[arg(long, value_name = "N")]
+        depth: Option<usize>,
+
+        /// Only show this bundle and the bundles nested in it; nested bundles are
+        /// named by their install path, e.g. ui-kit/base-styles
+        #[arg(short, long, add = ArgValueCandidates::new(bundle_candidates))]
+        bundle: Option<String>,
+
+        /// Number of bundles to check concurrently (the jobs setting, or 4 by default)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+
+        /// Check every bundle.toml in the current directory and below (skipping
+        /// .gitignored paths)
+        #[arg(long, conflicts_with_all = ["bundle", "json"])]
+        all: bool,
+    },
+
+    /// Show the dependency tree of installed bundles
+    ///
+    /// Walks .fpm recursively and prints the bundle hierarchy like `cargo tree`.
+    /// Repositories that appear more than once are marked with (*), and bundles that
+    /// aren't synced are labelled with their status.
+    Tree,
+
+    /// Explain why a bundle is installed
+    ///
+    /// Prints every dependency path from bundle.toml to the named bundle, so you can
+    /// see which parent bundles pull in a deeply nested one.
+    Why {
+        /// Bundle name, or install path such as ui-kit/base-styles for a single occurrence
+        #[arg(add = ArgValueCandidates::new(bundle_candidates))]
+        bundle: String,
+    },
+
+    /// Show everything known about a bundle
+    ///
+    /// Prints the bundle's source, the ref it was installed from and the commit
+    /// checked out, its status and size on disk, the metadata of its bundle.toml and
+    /// the bundles it declares.
+    Info {
+        /// Bundle name, or install path such as ui-kit/base-styles if it is installed
+        /// more than once
+        #[arg(add = ArgValueCandidates::new(bundle_candidates))]
+        bundle: String,
+    },
+
+    /// Export the dependency graph of installed bundles
+    ///
+    /// Emits every bundle, including nested ones, as a node and every bundle.toml
+    /// entry as an edge labelled with the requested version or branch. A bundle
+    /// several bundles depend on is one node with an edge from each.
+    Graph {
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = GraphFormat::Dot)]
+        format: GraphFormat,
+
+        /// Write the graph to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Verify installed bundles against the checksums in fpm.lock
+    ///
+    /// Hashes the files of every locked bundle and compares them with the checksum
+    /// recorded when it was installed, listing the files changed, added or removed
+    /// since. Fails if any bundle was modified.
+    Verify,
+
+    /// Diagnose problems with the setup fpm runs in
+    ///
+    /// Checks git and its version, the SSH agent, the credentials configured for
+    /// HTTPS remotes, that the remote of every bundle can be reached, that
+    /// bundle.toml is valid and fpm.lock matches it, and that the project and the
+    /// cache are writable, suggesting a fix for each problem found. Fails if any
+    /// check fails.
+    Doctor,
+
+    /// Check bundle.toml for mistakes loading it doesn't catch
+    ///
+    /// Reports unknown keys, invalid version requirements, include and exclude
+    /// patterns pointing outside the bundle, and bundles installing the same source
+    /// under different names. Fails if any problem is found.
+    Validate {
+        /// Also check that the repository of every git bundle can be reached and
+        /// has a version matching its requirement
+        #[arg(long)]
+        online: bool,
+    },
+
+    /// Sign the contents of a source bundle
+    ///
+    /// Lists the hash of every file of the root directory in .fpm-contents and signs
+    /// the listing with an SSH or minisign key. Installs check the signature when the
+    /// consumer sets trusted_keys.
+    Sign {
+        /// Private key to sign with: an SSH key, or a minisign secret key
+        /// (defaults to the ssh_key setting)
+        #[arg(long, value_name = "PATH")]
+        key: Option<PathBuf>,
+    },
+
+    /// Report the licenses of installed bundles
+    ///
+    /// Lists every installed bundle, nested ones included, grouped by the license its
+    /// bundle.toml declares. With --deny, fails if a bundle can only be used under a
+    /// denied license.
+    Licenses {
+        /// Print the licenses as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Licenses no bundle may require, as SPDX identifiers (comma-separated or repeated)
+        #[arg(long, value_delimiter = ',')]
+        deny: Vec<String>,
+    },
+
+    /// Show per-file changes in installed bundles
+    ///
+    /// Compares each bundle's working tree against its local HEAD, or with --remote,
+    /// against the freshly fetched remote branch tip to preview incoming upstream changes.
+    Diff {
+        /// Name of a specific bundle to diff (diffs all bundles if not specified)
+        #[arg(add = ArgValueCandidates::new(bundle_candidates))]
+        bundle: Option<String>,
+
+        /// Fetch the remote branch and compare against its tip instead of local HEAD
+        #[arg(long)]
+        remote: bool,
+
+        /// Print the full diff of the changed files after their stats
+        #[arg(short, long)]
+        patch: bool,
+    },
+
+    /// Show the recent commits of an installed bundle
+    ///
+    /// Lists author, date and message of each commit without having to cd into
+    /// .fpm/<name>. Commits that only changed files the bundle doesn't install
+    /// (outside its path, include and exclude patterns) are dimmed.
+    Log {
+        /// Name of the bundle
+        #[arg(add = ArgValueCandidates::new(bundle_candidates))]
+        bundle: String,
+
+        /// Number of commits to show
+        #[arg(short = 'n', long, default_value_t = log::DEFAULT_COUNT)]
+        count: usize,
+    },
+
+    /// Run housekeeping on installed bundle clones and the shared cache
+    ///
+    /// Prunes stale remote branches, packs loose objects (git gc --auto) and writes
+    /// the commit-graph so long-lived clones stay fast.
+    Maintain {
+        /// Only maintain installed bundles, leaving the shared cache untouched
+        #[arg(long)]
+        no_cache: bool,
+    },
+
+    /// Inspect and prune the shared cache of repository mirrors
+    ///
+    /// Bundles that share a repository borrow its objects from a bare mirror in the
+    /// cache (~/.cache/fpm, or FPM_CACHE_DIR).
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+
+    /// Read and change fpm settings
+    ///
+    /// Changes go to the project's .fpm/config.toml, or with --global to the global
+    /// ~/.config/fpm/config.toml. Without --global, get and list show the settings in
+    /// effect, combining both files, the environment and command line flags.
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+
+    /// Print environment variables pointing at installed bundles
+    ///
+    /// Emits one FPM_BUNDLE_<NAME> variable per installed bundle (nested bundles are
+    /// joined with a double underscore, e.g. FPM_BUNDLE_UI_KIT__BASE_STYLES) plus
+    /// FPM_BUNDLE_DIR and the FPM_BUNDLE_PATHS list, so build scripts don't need to
+    /// hardcode .fpm paths.
+    Env {
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = EnvFormat::Shell)]
+        format: EnvFormat,
+
+        /// Write the definitions to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Run a script from the [scripts] table of bundle.toml
+    ///
+    /// Runs the script with the platform's shell in the directory of bundle.toml,
+    /// with the variables of `fpm env` (FPM_BUNDLE_DIR, FPM_BUNDLE_<NAME> and
+    /// FPM_BUNDLE_PATHS) set. Lists the scripts if no name is given.
+    Run {
+        /// Name of the script
+        #[arg(add = ArgValueCandidates::new(script_candidates))]
+        name: Option<String>,
+
+        /// Arguments appended to the script's command
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+
+    /// Update fpm to its latest release
+    ///
+    /// Downloads the release built for this platform from GitHub, verifies it
+    /// against the checksum published with it and replaces the running executable.
+    SelfUpdate {
+        /// Install this version instead of the latest, e.g. to go back to an earlier one
+        version: Option<String>,
+
+        /// Only report whether a newer release is available
+        #[arg(long, conflicts_with = "version")]
+        check: bool,
+    },
+
+    /// Print the script enabling tab completion in a shell
+    ///
+    /// Completes commands and flags, and the bundle and script names of the
+    /// bundle.toml in the current directory or its parents. Load it in the shell's
+    /// startup file, e.g. `source <(fpm completions bash)` in ~/.bashrc.
+    Completions {
+        /// Shell to complete in
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+
+    /// Store a token for an HTTPS host, or an SSH key passphrase, in the system keychain
+    ///
+    /// Prompts for the token (or reads it from stdin). Install, update and push then
+    /// use it for repositories on that host unless FPM_TOKEN_<HOST> or FPM_GIT_TOKEN
+    /// is set. With --ssh-key, stores the passphrase of that key instead, used unless
+    /// FPM_SSH_PASSPHRASE is set.
+    #[command(group(ArgGroup::new("credential").required(true)))]
+    Login {
+        /// Host name, e.g. github.com
+        #[arg(group = "credential")]
+        host: Option<String>,
+
+        /// Path to an SSH private key protected by a passphrase
+        #[arg(long, group = "credential")]
+        ssh_key: Option<PathBuf>,
+    },
+
+    /// Remove the token stored for an HTTPS host, or the passphrase of an SSH key
+    #[command(group(ArgGroup::new("credential").required(true)))]
+    Logout {
+        /// Host name, e.g. github.com
+        #[arg(group = "credential")]
+        host: Option<String>,
+
+        /// Path to an SSH private key
+        #[arg(long, group = "credential")]
+        ssh_key: Option<PathBuf>,
+    },
+
+    /// Turn an existing directory into a source bundle and publish it
+    ///
+    /// Writes a bundle.toml whose root points at the directory, initializes a git
+    /// repository there with the fpm remote, optionally creates the remote repository
+    /// through the host API, and makes the initial publish.
+    Adopt {
+        /// Directory of existing files to adopt (inside the manifest directory)
+        dir: PathBuf,
+
+        /// Git URL the bundle is published to
+        #[arg(short, long)]
+        remote: String,
+
+        /// Create the remote repository via the host API first (GitHub or GitLab)
+        #[arg(long)]
+        create_remote: bool,
+
+        /// Create the remote repository as private
+        #[arg(long, requires = "create_remote")]
+        private: bool,
+
+        /// Description for the bundle manifest and the created repository
+        #[arg(short, long)]
+        description: Option<String>,
+    },
+}
+
+/// Subcommands of `fpm cache`
+#[derive(Subcommand, Debug)]
+pub enum CacheCommand {
+    /// Show the cache location, the number of mirrors and their size
+    Stats,
+
+    /// Remove every mirror from the cache
+    Clean,
+
+    /// Remove mirrors that haven't been used recently
+    #[command(group(ArgGroup::new("limit").required(true).multiple(true)))]
+    Gc {
+        /// Remove mirrors unused for longer than this, e.g. 30d, 12h or 2w
+        #[arg(long, value_parser = parse_age, group = "limit")]
+        max_age: Option<Duration>,
+
+        /// Then remove the least recently used mirrors until the cache fits in this
+        /// size, e.g. 500M or 2G
+        #[arg(long, value_parser = parse_size, group = "limit")]
+        max_size: Option<u64>,
+    },
+}
+
+/// Subcommands of `fpm config`
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print the value of a setting, e.g. git_backend or url.<base>.instead_of
+    Get {
+        key: String,
+
+        /// Read the global config file only
+        #[arg(long)]
+        global: bool,
+    },
+
+    /// Change a setting
+    Set {
+        key: String,
+
+        value: String,
+
+        /// Change the global config file instead of the project's
+        #[arg(long)]
+        global: bool,
+    },
+
+    /// Print all settings that are set
+    List {
+        /// Read the global config file only
+        #[arg(long)]
+        global: bool,
+    },
+}
+
+/// Output formats supported by `fpm env`
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnvFormat {
+    /// POSIX shell `export` statements
+    Shell,
+    /// KEY="value" lines for .env files
+    Dotenv,
+    /// CMake `set()` commands
+    Cmake,
+    /// A JSON object
+    Json,
+}
+
+/// Output formats supported by `fpm graph`
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// A Graphviz digraph
+    Dot,
+    /// A Mermaid flowchart
+    Mermaid,
 }
 
 #[cfg(test)]
 mod unit_tests {
     use super::*;
-    use clap::CommandFactory;
+    use clap::{CommandFactory, Parser};
 
     #[test]
     fn verify_cli() {
         Cli::command().debug_assert();
     }
+
+    #[test]
+    fn test_output_flag_after_subcommand() {
+        let cli = Cli::try_parse_from(["fpm", "install", "--output", "json"]).unwrap();
+        assert_eq!(cli.output, Some(OutputFormat::Json));
+        assert!(matches!(cli.command, Commands::Install { .. }));
+
+        let cli = Cli::try_parse_from(["fpm", "--output", "json", "push"]).unwrap();
+        assert_eq!(cli.output, Some(OutputFormat::Json));
+    }
 }
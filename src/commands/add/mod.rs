@@ -0,0 +1,265 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::commands::install::{self, check_for_conflicts, InstallOptions};
+use crate::config::{expand_shorthand, load_manifest_with_settings, parse_manifest_with_settings};
+use crate::git::{git_operations_for, GitOperations};
+use crate::registry::{load_registry, RegistryIndex};
+use crate::resolve::{resolve_ref, tracked_branch};
+use crate::settings::Settings;
+use crate::types::BundleDependency;
+
+/// Version written when none is given: track the branch
+const ANY_VERSION: &str = "*";
+
+/// Options of the add command
+#[derive(Debug, Clone, Default)]
+pub struct AddOptions<'a> {
+    /// Version or semver requirement, resolved against the remote's tags
+    pub version: Option<&'a str>,
+    /// Branch to track instead of a version
+    pub branch: Option<&'a str>,
+    /// Subdirectory within the repository
+    pub path: Option<&'a Path>,
+    /// SSH private key used to access the repository
+    pub ssh_key: Option<&'a Path>,
+    /// Install the bundles right after adding the entry
+    pub install: bool,
+    /// Settings from the config files and the environment
+    pub settings: Settings,
+}
+
+/// Serialized form of a single `[bundles.<name>]` table
+#[derive(Serialize)]
+struct BundleEntry<'a> {
+    bundles: BTreeMap<&'a str, &'a BundleDependency>,
+}
+
+/// Executes the add command with the default git operations. Without a `git` URL,
+/// the bundle is looked up by name in the configured registry.
+pub fn execute(
+    manifest_path: &Path,
+    name: &str,
+    git: Option<&str>,
+    options: &AddOptions,
+) -> Result<()> {
+    let git_ops = git_operations_for(&options.settings);
+    match git {
+        Some(git) => execute_with_git(manifest_path, name, git, options, git_ops),
+        None => {
+            let index = load_registry(git_ops.as_ref(), &options.settings)?;
+            add_from_registry(manifest_path, name, &index, options, git_ops)
+        }
+    }
+}
+
+/// Adds the bundle listed as `name` in a registry index. Unless a version or
+/// branch is given, the latest version the index lists is required, e.g. "^1.2.0".
+pub fn add_from_registry(
+    manifest_path: &Path,
+    name: &str,
+    index: &RegistryIndex,
+    options: &AddOptions,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<()> {
+    let entry = index.bundles.get(name).with_context(|| {
+        format!(
+            "Bundle '{}' is not in the registry. Search it with 'fpm search {}', or give \
+            its git URL.",
+            name, name
+        )
+    })?;
+
+    let latest = entry
+        .latest_version()
+        .map(|version| format!("^{}", version));
+    let mut options = options.clone();
+    if options.version.is_none() && options.branch.is_none() {
+        options.version = latest.as_deref();
+    }
+
+    execute_with_git(manifest_path, name, &entry.git, &options, git_ops)
+}
+
+/// Executes the add command with a custom GitOperations implementation
+/// This enables dependency injection for testing
+pub fn execute_with_git(
+    manifest_path: &Path,
+    name: &str,
+    git: &str,
+    options: &AddOptions,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<()> {
+    let manifest_path = if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    };
+
+    let manifest = load_manifest_with_settings(&manifest_path, &options.settings)?;
+    if manifest.bundles.contains_key(name) {
+        anyhow::bail!(
+            "Bundle '{}' already exists in {}. Edit it there or remove it first.",
+            name,
+            manifest_path.display()
+        );
+    }
+
+    let new_name = name.to_string();
+    let mut names: Vec<&String> = manifest.bundles.keys().collect();
+    names.push(&new_name);
+    check_for_conflicts(&names)?;
+
+    let dependency = BundleDependency {
+        version: options.version.unwrap_or(ANY_VERSION).to_string(),
+        git: git.to_string(),
+        path: options.path.map(Path::to_path_buf),
+        branch: options.branch.map(str::to_string),
+        ssh_key: options.ssh_key.map(Path::to_path_buf),
+        ..Default::default()
+    };
+
+    // The manifest keeps the shorthand; the remote is checked at its full URL
+    let mut expanded = dependency.clone();
+    expand_shorthand(
+        &mut expanded,
+        manifest.protocol.or(options.settings.protocol),
+    )?;
+
+    println!("{} {}", "Checking".cyan(), expanded.git);
+    check_remote(git_ops.as_ref(), &expanded)?;
+
+    append_bundle(
+        &manifest_path,
+        name,
+        &dependency,
+        &expanded,
+        &options.settings,
+    )?;
+    println!(
+        "{} {} to {}",
+        "Added".green(),
+        name,
+        manifest_path.display()
+    );
+
+    if options.install {
+        let install_options = InstallOptions {
+            settings: options.settings.clone(),
+            ..Default::default()
+        };
+        install::execute_with_git(&manifest_path, &install_options, git_ops)?;
+    }
+
+    Ok(())
+}
+
+/// Verifies the repository is reachable and has the requested version or branch
+fn check_remote(git_ops: &dyn GitOperations, dependency: &BundleDependency) -> Result<()> {
+    if dependency.version_requirement().is_some() {
+        resolve_ref(git_ops, dependency)?;
+        return Ok(());
+    }
+
+    let branch = tracked_branch(git_ops, dependency);
+    let tip = git_ops
+        .remote_branch_commit(&dependency.git, &branch, dependency.ssh_key.as_deref())
+        .with_context(|| format!("Failed to reach {}", dependency.git))?;
+
+    if tip.is_none() {
+        anyhow::bail!(
+            "Branch '{}' not found on {}. Use --branch to pick another branch.",
+            branch,
+            dependency.git
+        );
+    }
+
+    Ok(())
+}
+
+/// Appends a `[bundles.<name>]` table to the manifest, keeping the rest of the
+/// file (comments, ordering) untouched
+fn append_bundle(
+    manifest_path: &Path,
+    name: &str,
+    dependency: &BundleDependency,
+    expanded: &BundleDependency,
+    settings: &Settings,
+) -> Result<()> {
+    let content = fs::read_to_string(manifest_path)
+        .with_context(|| format!("Failed to read manifest file: {}", manifest_path.display()))?;
+
+    let new_content = with_bundle_entry(&content, name, dependency, expanded, settings)?;
+
+    fs::write(manifest_path, new_content)
+        .with_context(|| format!("Failed to write manifest: {}", manifest_path.display()))
+}
+
+/// Returns the manifest content with the bundle entry appended.
+/// `expanded` is the entry as parsing the result should yield it.
+fn with_bundle_entry(
+    content: &str,
+    name: &str,
+    dependency: &BundleDependency,
+    expanded: &BundleDependency,
+    settings: &Settings,
+) -> Result<String> {
+    let entry = BundleEntry {
+        bundles: BTreeMap::from([(name, dependency)]),
+    };
+    let table = toml::to_string_pretty(&entry).context("Failed to serialize bundle entry")?;
+
+    let separator = if content.is_empty() || content.ends_with("\n\n") {
+        ""
+    } else if content.ends_with('\n') {
+        "\n"
+    } else {
+        "\n\n"
+    };
+    let new_content = format!("{}{}{}", content, separator, table);
+
+    // Make sure the result still is a valid manifest declaring the new bundle
+    let manifest = parse_manifest_with_settings(&new_content, settings)
+        .context("Adding the bundle would make bundle.toml invalid; please add it by hand")?;
+    if manifest.bundles.get(name) != Some(expanded) {
+        anyhow::bail!("Adding the bundle would make bundle.toml invalid; please add it by hand");
+    }
+
+    Ok(new_content)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn test_with_bundle_entry_keeps_existing_content() {
+        let content = "# Design assets\nfpm_version = \"0.1.0\"\nidentifier = \"fpm-bundle\"\n\n\
+            [bundles.icons]\nversion = \"*\"\ngit = \"https://github.com/example/icons.git\"\n";
+        let dependency = BundleDependency {
+            version: "^1.2".to_string(),
+            git: "https://github.com/example/fonts.git".to_string(),
+            ..Default::default()
+        };
+
+        let new_content = with_bundle_entry(
+            content,
+            "fonts",
+            &dependency,
+            &dependency,
+            &Settings::default(),
+        )
+        .unwrap();
+
+        assert!(new_content.starts_with(content));
+        assert!(new_content.contains("\n\n[bundles.fonts]\nversion = \"^1.2\"\n"));
+        let manifest = crate::config::parse_manifest(&new_content).unwrap();
+        assert_eq!(manifest.bundles.len(), 2);
+        assert_eq!(manifest.bundles["fonts"], dependency);
+    }
+}
@@ -0,0 +1,158 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use crate::commands::publish;
+use crate::config::{load_manifest_with_settings, save_manifest};
+use crate::forge::create_repository;
+use crate::git::{git_operations_for, init_bundle_for_publish, GitOperations};
+use crate::settings::Settings;
+use crate::types::BundleManifest;
+use crate::version::VERSION;
+
+/// Version given to a freshly adopted bundle
+const INITIAL_VERSION: &str = "0.1.0";
+
+/// Options for adopting a directory
+pub struct AdoptOptions<'a> {
+    /// Git URL the bundle is published to
+    pub remote: &'a str,
+    /// Create the remote repository through the host API first
+    pub create_remote: bool,
+    /// Create the remote repository as private
+    pub private: bool,
+    pub description: Option<&'a str>,
+    /// Settings from the config files and the environment
+    pub settings: Settings,
+}
+
+/// Executes the adopt command with the default git operations
+pub fn execute(manifest_path: &Path, dir: &Path, options: &AdoptOptions) -> Result<()> {
+    let git_ops = git_operations_for(&options.settings);
+    execute_with_git(manifest_path, dir, options, git_ops)
+}
+
+/// Executes the adopt command with a custom GitOperations implementation
+/// This enables dependency injection for testing
+pub fn execute_with_git(
+    manifest_path: &Path,
+    dir: &Path,
+    options: &AdoptOptions,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<()> {
+    let manifest_path = if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    };
+    let root_dir = if dir.is_relative() {
+        std::env::current_dir()?.join(dir)
+    } else {
+        dir.to_path_buf()
+    };
+
+    if !root_dir.is_dir() {
+        anyhow::bail!("'{}' is not a directory", root_dir.display());
+    }
+
+    println!("{} {}", "Adopting".cyan(), root_dir.display());
+
+    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+    let root = relative_root(parent_dir, &root_dir)?;
+
+    let mut manifest = if manifest_path.exists() {
+        let manifest = load_manifest_with_settings(&manifest_path, &options.settings)?;
+        if let Some(existing) = &manifest.root {
+            anyhow::bail!(
+                "{} already defines root '{}'. Each manifest can only describe one source bundle.",
+                manifest_path.display(),
+                existing.display()
+            );
+        }
+        manifest
+    } else {
+        let mut manifest = BundleManifest::new(VERSION);
+        manifest.version = Some(INITIAL_VERSION.to_string());
+        manifest
+    };
+
+    manifest.root = Some(root.clone());
+    if let Some(description) = options.description {
+        manifest.description = Some(description.to_string());
+    }
+
+    save_manifest(&manifest, &manifest_path)?;
+    println!(
+        "  {} {} (root = \"{}\")",
+        "Wrote".green(),
+        manifest_path.display(),
+        root.display()
+    );
+
+    if options.create_remote {
+        create_repository(
+            options.remote,
+            manifest.description.as_deref(),
+            options.private,
+        )?;
+        println!("  {} {}", "Created remote".green(), options.remote);
+    }
+
+    init_bundle_for_publish(git_ops.as_ref(), &root_dir, options.remote)?;
+
+    let publish_options = publish::PublishOptions {
+        settings: options.settings.clone(),
+        ..Default::default()
+    };
+    publish::execute_with_git(&manifest_path, &publish_options, git_ops)
+}
+
+/// Path of `root_dir` relative to the manifest directory, as stored in `root`
+fn relative_root(parent_dir: &Path, root_dir: &Path) -> Result<PathBuf> {
+    let relative = root_dir.strip_prefix(parent_dir).with_context(|| {
+        format!(
+            "'{}' must be inside the manifest directory '{}'",
+            root_dir.display(),
+            parent_dir.display()
+        )
+    })?;
+
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        anyhow::bail!(
+            "'{}' must be inside the manifest directory '{}'",
+            root_dir.display(),
+            parent_dir.display()
+        );
+    }
+
+    if relative.as_os_str().is_empty() {
+        return Ok(PathBuf::from("."));
+    }
+
+    Ok(relative.to_path_buf())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_root() {
+        let parent = Path::new("/work/design");
+
+        assert_eq!(
+            relative_root(parent, Path::new("/work/design/assets/icons")).unwrap(),
+            PathBuf::from("assets/icons")
+        );
+        assert_eq!(
+            relative_root(parent, Path::new("/work/design")).unwrap(),
+            PathBuf::from(".")
+        );
+        assert!(relative_root(parent, Path::new("/work/other")).is_err());
+        assert!(relative_root(parent, Path::new("/work/design/../other")).is_err());
+    }
+}
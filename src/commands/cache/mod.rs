@@ -0,0 +1,223 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::time::{Duration, SystemTime};
+
+use crate::cache::{
+    cache_dir, dependents, list_cached_mirrors, remove_mirror, select_for_gc, CachedMirror,
+};
+use crate::git::{git_operations_for, GitOperations};
+use crate::settings::Settings;
+
+/// Limits for `fpm cache gc`
+#[derive(Debug, Clone, Default)]
+pub struct GcOptions {
+    /// Remove mirrors not used for longer than this
+    pub max_age: Option<Duration>,
+    /// Remove the least recently used mirrors until the cache is at most this many bytes
+    pub max_size: Option<u64>,
+}
+
+/// Prints the cache location, the number of mirrors and their size
+pub fn stats(settings: &Settings) -> Result<()> {
+    let mirrors = list_cached_mirrors(settings)?;
+    let total: u64 = mirrors.iter().map(|mirror| mirror.size).sum();
+
+    println!("{} {}", "Cache:".cyan(), cache_dir(settings)?.display());
+    println!("{} {}", "Mirrors:".cyan(), mirrors.len());
+    println!("{} {}", "Size:".cyan(), format_size(total));
+
+    if mirrors.is_empty() {
+        return Ok(());
+    }
+
+    println!();
+    let now = SystemTime::now();
+    for mirror in &mirrors {
+        println!(
+            "  {:<50} {:>10}  {}",
+            mirror_name(mirror),
+            format_size(mirror.size),
+            format_age(now, mirror).dimmed()
+        );
+    }
+
+    Ok(())
+}
+
+/// Removes every mirror from the cache
+pub fn clean(settings: &Settings) -> Result<()> {
+    let mirrors = list_cached_mirrors(settings)?;
+    remove_all(
+        &mirrors.iter().collect::<Vec<_>>(),
+        git_operations_for(settings).as_ref(),
+    )
+}
+
+/// Removes the mirrors that are unused for too long or don't fit in the size limit
+pub fn gc(options: &GcOptions, settings: &Settings) -> Result<()> {
+    if options.max_age.is_none() && options.max_size.is_none() {
+        anyhow::bail!("Specify --max-age and/or --max-size to choose which mirrors to remove");
+    }
+
+    let mirrors = list_cached_mirrors(settings)?;
+    let selected = select_for_gc(
+        &mirrors,
+        options.max_age,
+        options.max_size,
+        SystemTime::now(),
+    );
+    remove_all(&selected, git_operations_for(settings).as_ref())
+}
+
+/// Removes mirrors from the cache. The aliased bundles borrowing objects from a
+/// mirror get their own copy of them first; a mirror is kept if that fails.
+fn remove_all(mirrors: &[&CachedMirror], git_ops: &dyn GitOperations) -> Result<()> {
+    if mirrors.is_empty() {
+        println!("{} Nothing to remove.", "Note:".cyan());
+        return Ok(());
+    }
+
+    let mut freed = 0;
+    for mirror in mirrors {
+        for clone in dependents(&mirror.path) {
+            git_ops.dissociate_repository(&clone).with_context(|| {
+                format!(
+                    "Failed to copy the objects {} borrows from the cached mirror {}; the \
+                    mirror was kept",
+                    clone.display(),
+                    mirror_name(mirror)
+                )
+            })?;
+            println!("  {} {}", "Detached".green(), clone.display());
+        }
+        remove_mirror(&mirror.path)?;
+        freed += mirror.size;
+        println!("  {} {}", "Removed".green(), mirror_name(mirror));
+    }
+
+    println!();
+    println!(
+        "{} {} mirror(s), freed {}",
+        "Removed".green().bold(),
+        mirrors.len(),
+        format_size(freed)
+    );
+    Ok(())
+}
+
+/// Parses an age such as "30d", "12h", "2w" or "90m" (a bare number is in days)
+pub fn parse_age(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: u64 = number
+        .parse()
+        .with_context(|| format!("Invalid age '{}', expected e.g. 30d or 12h", value))?;
+    let seconds = match unit {
+        "m" => 60,
+        "h" => 60 * 60,
+        "" | "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => anyhow::bail!(
+            "Invalid age unit '{}', expected m (minutes), h, d or w",
+            unit
+        ),
+    };
+
+    Ok(Duration::from_secs(number * seconds))
+}
+
+/// Parses a size such as "500M", "2G" or "750KB" (a bare number is in bytes)
+pub fn parse_size(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: u64 = number
+        .parse()
+        .with_context(|| format!("Invalid size '{}', expected e.g. 500M or 2G", value))?;
+    let multiplier: u64 = match unit.to_ascii_uppercase().trim_end_matches("IB") {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        _ => anyhow::bail!("Invalid size unit '{}', expected K, M or G", unit),
+    };
+
+    Ok(number * multiplier)
+}
+
+/// Formats a size in bytes for display, e.g. "1.5 MiB"
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+fn format_age(now: SystemTime, mirror: &CachedMirror) -> String {
+    let days = now
+        .duration_since(mirror.last_used)
+        .map(|age| age.as_secs() / (24 * 60 * 60))
+        .unwrap_or(0);
+
+    match days {
+        0 => "used today".to_string(),
+        1 => "used 1 day ago".to_string(),
+        days => format!("used {} days ago", days),
+    }
+}
+
+fn mirror_name(mirror: &CachedMirror) -> String {
+    mirror
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("30d").unwrap(), Duration::from_secs(30 * 86400));
+        assert_eq!(parse_age("12h").unwrap(), Duration::from_secs(12 * 3600));
+        assert_eq!(parse_age("2w").unwrap(), Duration::from_secs(14 * 86400));
+        assert_eq!(parse_age("7").unwrap(), Duration::from_secs(7 * 86400));
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("3y").is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("500M").unwrap(), 500 * 1024 * 1024);
+        assert_eq!(parse_size("2GiB").unwrap(), 2 * 1024 * 1024 * 1024);
+        assert_eq!(parse_size("750kb").unwrap(), 750 * 1024);
+        assert!(parse_size("10T").is_err());
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MiB");
+    }
+}
@@ -0,0 +1,14 @@
+use anyhow::Result;
+use std::path::Path;
+
+use crate::commands::push::{self, PushOptions};
+
+/// Executes the commit command: commits the changes of installed bundles the way
+/// push does, version bumps included, and leaves pushing them to `fpm push`
+pub fn execute(manifest_path: &Path, options: &PushOptions) -> Result<()> {
+    let options = PushOptions {
+        commit_only: true,
+        ..options.clone()
+    };
+    push::execute(manifest_path, &options)
+}
@@ -0,0 +1,83 @@
+use anyhow::{Context, Result};
+use clap_complete::engine::CompletionCandidate;
+use clap_complete::env::Shells;
+use clap_complete::Shell;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use crate::config::{find_manifest, load_manifest};
+use crate::lockfile::{load_lockfile, lockfile_path};
+
+/// Environment variable naming the shell fpm completes the command line for,
+/// instead of running it
+pub const COMPLETE_ENV: &str = "COMPLETE";
+
+/// Name of the program the completions are registered for
+const BIN_NAME: &str = "fpm";
+
+/// Prints the script registering fpm's completions with `shell`. Rather than
+/// listing every argument, the script asks fpm for the completions of each command
+/// line, so bundle and script names can come from the manifest being worked on.
+pub fn execute(shell: Shell) -> Result<()> {
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(&shell.to_string())
+        .with_context(|| format!("Completions are not supported for {}", shell))?;
+
+    let mut stdout = std::io::stdout().lock();
+    completer
+        .write_registration(COMPLETE_ENV, BIN_NAME, BIN_NAME, BIN_NAME, &mut stdout)
+        .context("Failed to write the completion script")
+}
+
+/// Bundles of the nearest bundle.toml: the names it declares, and the install paths
+/// of its fpm.lock, which include the nested bundles
+pub fn bundle_candidates() -> Vec<CompletionCandidate> {
+    let Some(manifest_path) = nearest_manifest() else {
+        return Vec::new();
+    };
+
+    let mut names = BTreeSet::new();
+    if let Ok(manifest) = load_manifest(&manifest_path) {
+        names.extend(manifest.bundles.into_keys());
+    }
+    if let Ok(Some(lockfile)) = lockfile_path(&manifest_path).and_then(|path| load_lockfile(&path))
+    {
+        names.extend(lockfile.bundles.into_keys());
+    }
+
+    names.into_iter().map(CompletionCandidate::new).collect()
+}
+
+/// Scripts of the nearest bundle.toml, described by their commands
+pub fn script_candidates() -> Vec<CompletionCandidate> {
+    let Some(manifest) = nearest_manifest().and_then(|path| load_manifest(&path).ok()) else {
+        return Vec::new();
+    };
+
+    manifest
+        .scripts
+        .into_iter()
+        .map(|(name, command)| CompletionCandidate::new(name).help(Some(command.into())))
+        .collect()
+}
+
+/// The bundle.toml commands run in the current directory work on, as
+/// --manifest-path is not known while completing
+fn nearest_manifest() -> Option<PathBuf> {
+    find_manifest(&std::env::current_dir().ok()?)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn test_every_shell_has_a_completer() {
+        let shells = Shells::builtins();
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell] {
+            let completer = shells.completer(&shell.to_string());
+            assert!(completer.is_some(), "no completer for {}", shell);
+        }
+    }
+}
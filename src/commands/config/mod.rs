@@ -0,0 +1,397 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::settings::{global_config_path, load_settings_file, project_config_path, Settings};
+
+/// Settings holding a single value
+const KEYS: &[&str] = &[
+    "git_backend",
+    "ssh_key",
+    "depth",
+    "protocol",
+    "cache_dir",
+    "color",
+    "output",
+    "jobs",
+    "retries",
+    "retry_backoff",
+    "retry_jitter",
+    "connect_timeout",
+    "timeout",
+    "lock_timeout",
+    "signing_key",
+    "signing_format",
+    "commit_message",
+    "author",
+    "bump",
+    "signatures",
+    "trusted_keys",
+    "registry",
+    "bundle_hooks",
+];
+
+/// Settings of an SSH host
+const SSH_HOST_KEYS: &[&str] = &["key", "options"];
+
+/// Settings holding a number, with their smallest allowed value
+const NUMBER_KEYS: &[(&str, i64)] = &[
+    ("depth", 1),
+    ("jobs", 1),
+    ("retries", 0),
+    ("retry_backoff", 0),
+    ("connect_timeout", 1),
+    ("timeout", 1),
+    ("lock_timeout", 0),
+];
+
+/// Settings holding true or false
+const BOOL_KEYS: &[&str] = &["retry_jitter"];
+
+/// Settings holding a list, set to a single value
+const LIST_KEYS: &[&str] = &["trusted_keys"];
+
+/// A setting named on the command line
+#[derive(Debug, PartialEq)]
+enum SettingKey<'a> {
+    /// One of `KEYS`
+    Value(&'a str),
+    /// `url.<base>.instead_of`, the prefixes rewritten to `base`
+    UrlRule(&'a str),
+    /// `ssh.<host>.key` or `ssh.<host>.options`, a setting of one SSH host
+    SshHost(&'a str, &'a str),
+}
+
+impl<'a> SettingKey<'a> {
+    fn parse(key: &'a str) -> Result<Self> {
+        if let Some(base) = key
+            .strip_prefix("url.")
+            .and_then(|rest| rest.strip_suffix(".instead_of"))
+            .filter(|base| !base.is_empty())
+        {
+            return Ok(SettingKey::UrlRule(base));
+        }
+
+        if let Some(rest) = key.strip_prefix("ssh.") {
+            if let Some((host, field)) = rest
+                .rsplit_once('.')
+                .filter(|(host, field)| !host.is_empty() && SSH_HOST_KEYS.contains(field))
+            {
+                return Ok(SettingKey::SshHost(host, field));
+            }
+        }
+
+        if KEYS.contains(&key) {
+            return Ok(SettingKey::Value(key));
+        }
+
+        anyhow::bail!(
+            "Unknown setting '{}'. Known settings: {}, url.<base>.instead_of, \
+            ssh.<host>.key, ssh.<host>.options",
+            key,
+            KEYS.join(", ")
+        );
+    }
+}
+
+/// Prints the value of a setting. Without `global`, prints the value in effect,
+/// combining the config files, the environment and the command line flags.
+pub fn get(key: &str, global: bool, settings: &Settings) -> Result<()> {
+    let key = SettingKey::parse(key)?;
+    let settings = if global {
+        load_global()?.1
+    } else {
+        settings.clone()
+    };
+
+    let values = lookup(&settings, &key)?;
+    if values.is_empty() {
+        anyhow::bail!("Setting '{}' is not set", display_key(&key));
+    }
+    for value in values {
+        println!("{}", value);
+    }
+
+    Ok(())
+}
+
+/// Changes a setting in the project's .fpm/config.toml, or with `global` in the
+/// global config file
+pub fn set(manifest_path: &Path, key: &str, value: &str, global: bool) -> Result<()> {
+    let key = SettingKey::parse(key)?;
+    let path = if global {
+        global_path()?
+    } else {
+        project_config_path(manifest_path)
+    };
+
+    let content = if path.exists() {
+        fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?
+    } else {
+        String::new()
+    };
+    let new_content = with_setting(&content, &key, value)?;
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    fs::write(&path, new_content)
+        .with_context(|| format!("Failed to write config file: {}", path.display()))?;
+
+    println!(
+        "{} {} = {} in {}",
+        "Set".green(),
+        display_key(&key),
+        value,
+        path.display()
+    );
+
+    Ok(())
+}
+
+/// Prints all settings that are set. Without `global`, prints the settings in
+/// effect, combining the config files, the environment and the command line flags.
+pub fn list(global: bool, settings: &Settings) -> Result<()> {
+    let settings = if global {
+        let (path, settings) = load_global()?;
+        println!("{}", format!("# {}", path.display()).dimmed());
+        settings
+    } else {
+        settings.clone()
+    };
+
+    let content = toml::to_string(&settings).context("Failed to serialize settings")?;
+    print!("{}", content);
+
+    Ok(())
+}
+
+/// Returns the global config file location
+fn global_path() -> Result<PathBuf> {
+    global_config_path().context(
+        "Could not determine the global config file location. Set XDG_CONFIG_HOME or HOME.",
+    )
+}
+
+/// Returns the global config file location with the settings it holds
+fn load_global() -> Result<(PathBuf, Settings)> {
+    let path = global_path()?;
+    let settings = load_settings_file(&path)?.unwrap_or_default();
+    Ok((path, settings))
+}
+
+/// Formats a key the way it is written on the command line
+fn display_key(key: &SettingKey) -> String {
+    match key {
+        SettingKey::Value(name) => name.to_string(),
+        SettingKey::UrlRule(base) => format!("url.{}.instead_of", base),
+        SettingKey::SshHost(host, field) => format!("ssh.{}.{}", host, field),
+    }
+}
+
+/// Returns the values of a setting (several for URL rules, none if it is unset)
+fn lookup(settings: &Settings, key: &SettingKey) -> Result<Vec<String>> {
+    let name = match key {
+        SettingKey::UrlRule(base) => {
+            return Ok(settings
+                .url
+                .get(*base)
+                .map(|rule| rule.instead_of.clone())
+                .unwrap_or_default());
+        }
+        SettingKey::SshHost(host, field) => {
+            let Some(ssh_host) = settings.ssh.get(*host) else {
+                return Ok(Vec::new());
+            };
+            return Ok(match *field {
+                "key" => ssh_host
+                    .key
+                    .iter()
+                    .map(|key| key.display().to_string())
+                    .collect(),
+                _ => ssh_host.options.clone(),
+            });
+        }
+        SettingKey::Value("trusted_keys") => return Ok(settings.trusted_keys.clone()),
+        SettingKey::Value(name) => name,
+    };
+
+    let table = toml::Table::try_from(settings).context("Failed to serialize settings")?;
+    let value = match table.get(*name) {
+        None => return Ok(Vec::new()),
+        Some(toml::Value::String(value)) => value.clone(),
+        Some(value) => value.to_string(),
+    };
+    Ok(vec![value])
+}
+
+/// Returns the config file content with the setting changed, keeping the rest of
+/// the file (comments, ordering) untouched
+fn with_setting(content: &str, key: &SettingKey, value: &str) -> Result<String> {
+    let mut document: toml_edit::DocumentMut =
+        content.parse().context("Failed to parse the config file")?;
+
+    match key {
+        SettingKey::Value(name) => {
+            document[name] = setting_value(name, value)?;
+        }
+        SettingKey::UrlRule(base) => {
+            let rule = table_entry(&mut document, "url", base)?;
+            let mut instead_of = toml_edit::Array::new();
+            instead_of.push(value);
+            rule["instead_of"] = toml_edit::value(instead_of);
+        }
+        SettingKey::SshHost(host, "key") => {
+            table_entry(&mut document, "ssh", host)?["key"] = toml_edit::value(value);
+        }
+        SettingKey::SshHost(host, field) => {
+            let mut options = toml_edit::Array::new();
+            options.push(value);
+            table_entry(&mut document, "ssh", host)?[*field] = toml_edit::value(options);
+        }
+    }
+
+    // Make sure the result still is a valid config file
+    let new_content = document.to_string();
+    toml::from_str::<Settings>(&new_content).with_context(|| {
+        format!(
+            "Invalid value '{}' for setting '{}'",
+            value,
+            display_key(key)
+        )
+    })?;
+
+    Ok(new_content)
+}
+
+/// Parses the value of a single-value setting into the type the config file holds
+fn setting_value(name: &str, value: &str) -> Result<toml_edit::Item> {
+    if let Some((_, minimum)) = NUMBER_KEYS.iter().find(|(key, _)| *key == name) {
+        let number: i64 = value
+            .parse()
+            .with_context(|| format!("Setting '{}' must be a number", name))?;
+        if number < *minimum {
+            anyhow::bail!("Setting '{}' must be at least {}", name, minimum);
+        }
+        return Ok(toml_edit::value(number));
+    }
+
+    if BOOL_KEYS.contains(&name) {
+        let flag: bool = value
+            .parse()
+            .with_context(|| format!("Setting '{}' must be true or false", name))?;
+        return Ok(toml_edit::value(flag));
+    }
+
+    if LIST_KEYS.contains(&name) {
+        let mut list = toml_edit::Array::new();
+        list.push(value);
+        return Ok(toml_edit::value(list));
+    }
+
+    Ok(toml_edit::value(value))
+}
+
+/// Returns the `[<table>."<name>"]` table of a config file, creating it if needed
+fn table_entry<'d>(
+    document: &'d mut toml_edit::DocumentMut,
+    table: &str,
+    name: &str,
+) -> Result<&'d mut toml_edit::Item> {
+    let parent = document.entry(table).or_insert_with(|| {
+        let mut parent = toml_edit::Table::new();
+        parent.set_implicit(true);
+        toml_edit::Item::Table(parent)
+    });
+    Ok(parent
+        .as_table_like_mut()
+        .with_context(|| format!("Setting '{}' in the config file must be a table", table))?
+        .entry(name)
+        .or_insert(toml_edit::table()))
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::settings::GitBackend;
+
+    #[test]
+    fn test_with_setting_keeps_existing_content() {
+        let content = "# Company defaults\njobs = 2\n";
+
+        let new_content = with_setting(content, &SettingKey::Value("git_backend"), "git2").unwrap();
+        let new_content = with_setting(&new_content, &SettingKey::Value("jobs"), "8").unwrap();
+        let new_content = with_setting(
+            &new_content,
+            &SettingKey::parse("url.https://mirror.example.com/.instead_of").unwrap(),
+            "https://github.com/",
+        )
+        .unwrap();
+
+        assert!(new_content.starts_with("# Company defaults\njobs = 8\n"));
+        let settings: Settings = toml::from_str(&new_content).unwrap();
+        assert_eq!(settings.git_backend, Some(GitBackend::Git2));
+        assert_eq!(settings.jobs, Some(8));
+        assert_eq!(
+            lookup(
+                &settings,
+                &SettingKey::UrlRule("https://mirror.example.com/")
+            )
+            .unwrap(),
+            vec!["https://github.com/".to_string()]
+        );
+        assert_eq!(
+            lookup(&settings, &SettingKey::Value("git_backend")).unwrap(),
+            vec!["git2".to_string()]
+        );
+        assert!(lookup(&settings, &SettingKey::Value("depth"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_with_setting_rejects_invalid_values() {
+        let error = format!(
+            "{:#}",
+            with_setting("", &SettingKey::Value("color"), "sometimes").unwrap_err()
+        );
+        assert!(
+            error.contains("Invalid value 'sometimes' for setting 'color'"),
+            "Got: {}",
+            error
+        );
+
+        let error = with_setting("", &SettingKey::Value("depth"), "0").unwrap_err();
+        assert!(error.to_string().contains("must be at least 1"));
+        assert!(with_setting("", &SettingKey::Value("retries"), "0").is_ok());
+
+        let error = with_setting("", &SettingKey::Value("retry_jitter"), "yes").unwrap_err();
+        assert!(error.to_string().contains("must be true or false"));
+
+        let error = SettingKey::parse("colour").unwrap_err();
+        assert!(error.to_string().contains("Unknown setting 'colour'"));
+    }
+
+    #[test]
+    fn test_with_setting_ssh_host() {
+        let key = SettingKey::parse("ssh.github.com.key").unwrap();
+        assert_eq!(key, SettingKey::SshHost("github.com", "key"));
+
+        let new_content = with_setting("", &key, "/keys/work_ed25519").unwrap();
+        let new_content = with_setting(
+            &new_content,
+            &SettingKey::parse("ssh.github.com.options").unwrap(),
+            "ServerAliveInterval=30",
+        )
+        .unwrap();
+
+        let settings: Settings = toml::from_str(&new_content).unwrap();
+        let github = &settings.ssh["github.com"];
+        assert_eq!(github.key, Some(PathBuf::from("/keys/work_ed25519")));
+        assert_eq!(github.options, vec!["ServerAliveInterval=30".to_string()]);
+        assert!(SettingKey::parse("ssh.github.com.user").is_err());
+    }
+}
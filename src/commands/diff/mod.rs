@@ -0,0 +1,259 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::config::load_manifest;
+use crate::flatten::{flattened_source, mirror};
+use crate::git::{git_operations_for, GitOperations};
+use crate::resolve::tracked_branch;
+use crate::settings::Settings;
+use crate::source::source_for;
+use crate::types::{FileDiffStat, BUNDLE_DIR};
+
+/// Executes the diff command with the default git operations
+pub fn execute(
+    manifest_path: &Path,
+    bundle_name: Option<&str>,
+    remote: bool,
+    patch: bool,
+    settings: &Settings,
+) -> Result<()> {
+    let git_ops = git_operations_for(settings);
+    execute_with_git(manifest_path, bundle_name, remote, patch, git_ops)
+}
+
+/// Executes the diff command with a custom GitOperations implementation
+/// This enables dependency injection for testing
+pub fn execute_with_git(
+    manifest_path: &Path,
+    bundle_name: Option<&str>,
+    remote: bool,
+    patch: bool,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<()> {
+    let manifest_path = if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    };
+
+    let manifest = load_manifest(&manifest_path)?;
+    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+    let bundle_dir = parent_dir.join(BUNDLE_DIR);
+
+    let mut names: Vec<&String> = if let Some(name) = bundle_name {
+        let (name, _) = manifest.bundles.get_key_value(name).with_context(|| {
+            format!(
+                "Bundle '{}' not found in manifest. Available bundles: {:?}",
+                name,
+                manifest.bundles.keys().collect::<Vec<_>>()
+            )
+        })?;
+        vec![name]
+    } else {
+        manifest.bundles.keys().collect()
+    };
+    names.sort();
+
+    for name in names {
+        let dependency = &manifest.bundles[name];
+        let bundle_path = bundle_dir.join(name);
+
+        let bundle_source = source_for(dependency, git_ops.as_ref());
+        if !bundle_source.can_push() {
+            println!(
+                "{} {} ({} bundle)",
+                "Skipping".yellow(),
+                name,
+                bundle_source.kind()
+            );
+            continue;
+        }
+
+        // A flattened bundle is diffed in its checkout, after copying its files back
+        let source = flattened_source(&bundle_path);
+        let repo_path = source
+            .as_ref()
+            .map_or(bundle_path.clone(), |source| source.checkout.clone());
+
+        if !bundle_path.exists() || !git_ops.is_repository(&repo_path) {
+            println!("{} {} (not installed)", "Skipping".yellow(), name);
+            continue;
+        }
+        if let Some(source) = &source {
+            mirror(&bundle_path, &source.source_dir())
+                .with_context(|| format!("Failed to copy bundle: {}", name))?;
+        }
+
+        // Local changes are the working tree against HEAD; remote changes are the
+        // commits the remote branch has that HEAD doesn't, so they show as incoming
+        let remote_revision = if remote {
+            let branch = tracked_branch(git_ops.as_ref(), dependency);
+            git_ops
+                .fetch_remote_branch(&repo_path, &branch)
+                .with_context(|| format!("Failed to fetch remote for bundle: {}", name))?;
+            Some(format!("origin/{}", branch))
+        } else {
+            None
+        };
+
+        let mut stats = git_ops
+            .diff_stat(&repo_path, "HEAD", remote_revision.as_deref())
+            .with_context(|| format!("Failed to diff bundle: {}", name))?;
+        if let Some(source) = &source {
+            strip_subdirectory(&mut stats, &source.subdirectory);
+        }
+
+        match &remote_revision {
+            Some(revision) => print_bundle_diff(name, "incoming from", revision, &stats),
+            None => print_bundle_diff(name, "vs", "HEAD", &stats),
+        }
+
+        if patch && !stats.is_empty() {
+            let mut diff = git_ops
+                .diff_patch(&repo_path, "HEAD", remote_revision.as_deref())
+                .with_context(|| format!("Failed to diff bundle: {}", name))?;
+            if let Some(source) = &source {
+                diff = strip_patch_subdirectory(&diff, &source.subdirectory);
+            }
+            print_patch(&diff);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_bundle_diff(name: &str, relation: &str, revision: &str, stats: &[FileDiffStat]) {
+    println!("{} {} {}", name.bold(), relation.dimmed(), revision.cyan());
+
+    if stats.is_empty() {
+        println!("  {}", "No differences.".dimmed());
+        println!();
+        return;
+    }
+
+    for stat in stats {
+        match (stat.insertions, stat.deletions) {
+            (Some(insertions), Some(deletions)) => println!(
+                "  {} | {} {}",
+                stat.path,
+                format!("+{}", insertions).green(),
+                format!("-{}", deletions).red()
+            ),
+            _ => println!("  {} | {}", stat.path, "binary".dimmed()),
+        }
+    }
+
+    let (files, insertions, deletions) = summarize(stats);
+    println!(
+        "  {} file(s) changed, {} insertion(s)(+), {} deletion(s)(-)",
+        files, insertions, deletions
+    );
+    println!();
+}
+
+/// Prints a unified diff, colored like `git diff`
+fn print_patch(patch: &str) {
+    for line in patch.lines() {
+        if line.starts_with("diff ") || line.starts_with("+++ ") || line.starts_with("--- ") {
+            println!("{}", line.bold());
+        } else if line.starts_with("@@") {
+            println!("{}", line.cyan());
+        } else if line.starts_with('+') {
+            println!("{}", line.green());
+        } else if line.starts_with('-') {
+            println!("{}", line.red());
+        } else {
+            println!("{}", line);
+        }
+    }
+    println!();
+}
+
+/// Makes the paths in the file headers of a flattened bundle's patch relative to
+/// the bundle
+fn strip_patch_subdirectory(patch: &str, subdirectory: &Path) -> String {
+    let subdirectory = format!("{}/", subdirectory.to_string_lossy().replace('\\', "/"));
+    patch
+        .lines()
+        .map(|line| {
+            if line.starts_with("diff --git ")
+                || line.starts_with("--- ")
+                || line.starts_with("+++ ")
+            {
+                line.replace(&format!("a/{}", subdirectory), "a/")
+                    .replace(&format!("b/{}", subdirectory), "b/")
+            } else {
+                line.to_string()
+            }
+        })
+        .map(|line| line + "\n")
+        .collect()
+}
+
+/// Makes the paths of a flattened bundle's stats relative to the bundle
+fn strip_subdirectory(stats: &mut [FileDiffStat], subdirectory: &Path) {
+    let prefix = format!("{}/", subdirectory.to_string_lossy().replace('\\', "/"));
+    for stat in stats {
+        if let Some(relative) = stat.path.strip_prefix(&prefix) {
+            stat.path = relative.to_string();
+        }
+    }
+}
+
+/// Totals (files, insertions, deletions) for a set of file stats
+fn summarize(stats: &[FileDiffStat]) -> (usize, usize, usize) {
+    let insertions = stats.iter().filter_map(|s| s.insertions).sum();
+    let deletions = stats.iter().filter_map(|s| s.deletions).sum();
+
+    (stats.len(), insertions, deletions)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_ignores_binary_counts() {
+        let stats = vec![
+            FileDiffStat {
+                path: "styles/base.css".to_string(),
+                insertions: Some(4),
+                deletions: Some(2),
+            },
+            FileDiffStat {
+                path: "icons/logo.png".to_string(),
+                insertions: None,
+                deletions: None,
+            },
+            FileDiffStat {
+                path: "README.md".to_string(),
+                insertions: Some(1),
+                deletions: Some(0),
+            },
+        ];
+
+        assert_eq!(summarize(&stats), (3, 5, 2));
+    }
+
+    #[test]
+    fn test_strip_patch_subdirectory() {
+        let patch = "diff --git a/assets/icons/logo.svg b/assets/icons/logo.svg\n\
+            --- a/assets/icons/logo.svg\n\
+            +++ b/assets/icons/logo.svg\n\
+            @@ -1 +1 @@\n\
+            -a/assets/old\n\
+            +b/assets/new\n";
+
+        assert_eq!(
+            strip_patch_subdirectory(patch, Path::new("assets")),
+            "diff --git a/icons/logo.svg b/icons/logo.svg\n\
+            --- a/icons/logo.svg\n\
+            +++ b/icons/logo.svg\n\
+            @@ -1 +1 @@\n\
+            -a/assets/old\n\
+            +b/assets/new\n"
+        );
+    }
+}
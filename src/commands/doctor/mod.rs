@@ -0,0 +1,554 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Arc;
+
+use crate::auth;
+use crate::cache::cache_dir;
+use crate::config::load_manifest_with_settings;
+use crate::git::{git_operations_for, GitOperations};
+use crate::lockfile::{load_lockfile, lock_key, locked_bundle, lockfile_path};
+use crate::settings::{load_settings, ssh_url_host, GitBackend, Settings};
+use crate::source::source_for;
+use crate::types::{BundleDependency, BundleManifest, BUNDLE_DIR};
+
+/// Oldest git the cli backend fully works with: include filters check out with
+/// `git sparse-checkout set --no-cone`, added in git 2.35
+const MIN_GIT_VERSION: (u64, u64) = (2, 35);
+
+/// Outcome of a check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Ok,
+    /// Something that may get in the way, but doesn't have to
+    Warning,
+    Failed,
+}
+
+/// Result of one check of `fpm doctor`
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What to do about a warning or failure
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warning(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Warning,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn failed(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Failed,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// A bundle whose remote is checked
+struct Remote<'a> {
+    key: String,
+    dependency: &'a BundleDependency,
+}
+
+/// Executes the doctor command with the default git operations.
+/// Fails if any check fails, so it can gate CI.
+pub fn execute(manifest_path: &Path, settings: &Settings) -> Result<()> {
+    let manifest_path = if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    };
+
+    println!(
+        "{} {}",
+        "Checking the environment of".cyan(),
+        manifest_path.display()
+    );
+    println!();
+
+    // A remote that can't be reached is reported right away rather than retried
+    let settings = Settings {
+        retries: Some(0),
+        ..settings.clone()
+    };
+    let checks = run_checks(&manifest_path, &settings, git_operations_for(&settings))?;
+    print_checks(&checks);
+
+    let failed = checks
+        .iter()
+        .filter(|check| check.status == CheckStatus::Failed)
+        .count();
+    if failed > 0 {
+        anyhow::bail!("{} check(s) failed", failed);
+    }
+    Ok(())
+}
+
+/// Runs every check for the project of `manifest_path`. The checks of the lockfile
+/// and remotes are left out when the manifest can't be loaded.
+pub fn run_checks(
+    manifest_path: &Path,
+    settings: &Settings,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<Vec<Check>> {
+    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+    let mut checks = vec![check_settings(manifest_path), check_git(settings)];
+
+    let manifest = if !manifest_path.exists() {
+        checks.push(Check::failed(
+            "manifest",
+            format!("{} not found", manifest_path.display()),
+            "Run fpm in a directory with a bundle.toml (or below one), or pass \
+            --manifest-path",
+        ));
+        None
+    } else {
+        match load_manifest_with_settings(manifest_path, settings) {
+            Ok(manifest) => {
+                checks.push(Check::ok(
+                    "manifest",
+                    format!("{} bundle(s) declared", manifest.bundles.len()),
+                ));
+                Some(manifest)
+            }
+            Err(e) => {
+                checks.push(Check::failed(
+                    "manifest",
+                    format!("{:#}", e),
+                    "Fix bundle.toml as the error says",
+                ));
+                None
+            }
+        }
+    };
+
+    let mut nested_manifests = Vec::new();
+    let mut remotes = Vec::new();
+    if let Some(manifest) = &manifest {
+        checks.push(check_lockfile(manifest_path, manifest, git_ops.as_ref()));
+        collect_nested_manifests(parent_dir, "", manifest, settings, &mut nested_manifests);
+        collect_remotes(git_ops.as_ref(), "", manifest, &mut remotes);
+    }
+    for (key, nested) in &nested_manifests {
+        collect_remotes(git_ops.as_ref(), key, nested, &mut remotes);
+    }
+
+    checks.push(check_ssh_agent(&remotes, settings));
+    checks.push(check_credentials(&remotes));
+    for remote in &remotes {
+        checks.push(check_remote(git_ops.as_ref(), remote));
+    }
+
+    let install_dir = parent_dir.join(BUNDLE_DIR);
+    let install_dir = if install_dir.exists() {
+        install_dir
+    } else {
+        parent_dir.to_path_buf()
+    };
+    checks.push(check_writable(
+        "project",
+        &install_dir,
+        "Make it writable by your user, e.g. check who owns it",
+    ));
+    match cache_dir(settings) {
+        Ok(cache) => checks.push(check_writable(
+            "cache",
+            &cache,
+            "Make it writable by your user, or move the cache with \
+            'fpm config set cache_dir <dir>'",
+        )),
+        Err(e) => checks.push(Check::failed(
+            "cache",
+            format!("{:#}", e),
+            "Set a cache directory with 'fpm config set cache_dir <dir>'",
+        )),
+    }
+
+    Ok(checks)
+}
+
+fn print_checks(checks: &[Check]) {
+    let width = checks
+        .iter()
+        .map(|check| check.name.len())
+        .max()
+        .unwrap_or(0);
+    for check in checks {
+        let mark = match check.status {
+            CheckStatus::Ok => "✓".green(),
+            CheckStatus::Warning => "!".yellow().bold(),
+            CheckStatus::Failed => "✗".red().bold(),
+        };
+        println!("  {} {:<width$}  {}", mark, check.name, check.detail);
+        if let Some(fix) = &check.fix {
+            println!("    {} {}", "→".cyan(), fix);
+        }
+    }
+    println!();
+
+    let count = |status| checks.iter().filter(|c| c.status == status).count();
+    match (count(CheckStatus::Failed), count(CheckStatus::Warning)) {
+        (0, 0) => println!("{}", "Everything looks fine.".green().bold()),
+        (0, warnings) => println!("{}", format!("{} warning(s)", warnings).yellow()),
+        (failed, warnings) => println!(
+            "{}",
+            format!("{} problem(s), {} warning(s)", failed, warnings).red()
+        ),
+    }
+}
+
+/// Checks that the config files and the environment variables fpm reads are valid.
+/// fpm runs with the default settings when they aren't.
+fn check_settings(manifest_path: &Path) -> Check {
+    match load_settings(manifest_path) {
+        Ok(_) => Check::ok("settings", "config files and environment are valid"),
+        Err(e) => Check::failed(
+            "settings",
+            format!("{:#}", e),
+            "Fix the config file or environment variable as the error says; \
+            'fpm config set' still works",
+        ),
+    }
+}
+
+/// Checks that git is installed and recent enough. Only the cli backend needs it.
+fn check_git(settings: &Settings) -> Check {
+    let needed = settings.git_backend.unwrap_or_default() == GitBackend::Cli;
+    let output = Command::new("git").arg("--version").output();
+    let version = match output {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        }
+        _ if needed => {
+            return Check::failed(
+                "git",
+                "git not found",
+                "Install git (https://git-scm.com/downloads) and make sure it is on the \
+                PATH, or use the built-in git with 'fpm config set git_backend git2'",
+            )
+        }
+        _ => return Check::ok("git", "not found (not needed by the git2 backend)"),
+    };
+
+    match parse_git_version(&version) {
+        Some(found) if needed && found < MIN_GIT_VERSION => Check::warning(
+            "git",
+            format!(
+                "{} is older than {}.{}, so include filters may fail to check out",
+                version, MIN_GIT_VERSION.0, MIN_GIT_VERSION.1
+            ),
+            "Update git, or use the built-in git with 'fpm config set git_backend git2'",
+        ),
+        _ => Check::ok("git", version),
+    }
+}
+
+/// Reads the major and minor version from the output of `git --version`, e.g.
+/// "git version 2.41.0.windows.1"
+fn parse_git_version(output: &str) -> Option<(u64, u64)> {
+    let version = output.split_whitespace().nth(2)?;
+    let mut parts = version.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+/// Checks that fpm.lock exists, parses and matches the bundles of the manifest
+fn check_lockfile(
+    manifest_path: &Path,
+    manifest: &BundleManifest,
+    git_ops: &dyn GitOperations,
+) -> Check {
+    let lockfile = match lockfile_path(manifest_path).and_then(|path| load_lockfile(&path)) {
+        Ok(lockfile) => lockfile,
+        Err(e) => {
+            return Check::failed(
+                "lockfile",
+                format!("{:#}", e),
+                "Delete fpm.lock and run 'fpm install' to write it again",
+            )
+        }
+    };
+
+    let locked: Vec<(&String, &BundleDependency)> = manifest
+        .bundles
+        .iter()
+        .filter(|(_, dependency)| source_for(dependency, git_ops).is_locked())
+        .collect();
+    let Some(lockfile) = lockfile else {
+        if locked.is_empty() {
+            return Check::ok("lockfile", "not needed: no bundle is pinned in fpm.lock");
+        }
+        return Check::warning(
+            "lockfile",
+            "fpm.lock is missing, so installs may pick newer commits than before",
+            "Run 'fpm install' to create it, and commit it",
+        );
+    };
+
+    let mut stale: Vec<&str> = locked
+        .iter()
+        .filter(|(name, dependency)| locked_bundle(&lockfile, name, dependency).is_none())
+        .map(|(name, _)| name.as_str())
+        .collect();
+    // Nested bundles of bundles no longer declared go with them
+    stale.extend(
+        lockfile
+            .bundles
+            .keys()
+            .filter(|key| !key.contains('/') && !manifest.bundles.contains_key(*key))
+            .map(String::as_str),
+    );
+    stale.sort();
+
+    if stale.is_empty() {
+        Check::ok(
+            "lockfile",
+            format!("{} bundle(s) locked", lockfile.bundles.len()),
+        )
+    } else {
+        Check::warning(
+            "lockfile",
+            format!(
+                "fpm.lock is out of sync with bundle.toml: {}",
+                stale.join(", ")
+            ),
+            "Run 'fpm install' to update fpm.lock ('fpm ci' fails until then)",
+        )
+    }
+}
+
+/// Collects the manifests of the installed nested bundles, keyed by install path
+fn collect_nested_manifests(
+    dir: &Path,
+    prefix: &str,
+    manifest: &BundleManifest,
+    settings: &Settings,
+    manifests: &mut Vec<(String, BundleManifest)>,
+) {
+    let mut names: Vec<&String> = manifest.bundles.keys().collect();
+    names.sort();
+    for name in names {
+        let bundle_path = dir.join(BUNDLE_DIR).join(name);
+        let Ok(nested) = load_manifest_with_settings(&bundle_path.join("bundle.toml"), settings)
+        else {
+            continue;
+        };
+        let key = lock_key(prefix, name);
+        collect_nested_manifests(&bundle_path, &key, &nested, settings, manifests);
+        manifests.push((key, nested));
+    }
+}
+
+/// Collects the bundles of `manifest` installed from git remotes, skipping remotes
+/// already collected
+fn collect_remotes<'a>(
+    git_ops: &dyn GitOperations,
+    prefix: &str,
+    manifest: &'a BundleManifest,
+    remotes: &mut Vec<Remote<'a>>,
+) {
+    let mut names: Vec<&String> = manifest.bundles.keys().collect();
+    names.sort();
+    for name in names {
+        let dependency = &manifest.bundles[name];
+        if source_for(dependency, git_ops).kind() != "git"
+            || remotes
+                .iter()
+                .any(|remote| remote.dependency.git == dependency.git)
+        {
+            continue;
+        }
+        remotes.push(Remote {
+            key: lock_key(prefix, name),
+            dependency,
+        });
+    }
+}
+
+/// Whether an SSH remote has a key configured, so it doesn't need the agent
+fn has_ssh_key(dependency: &BundleDependency, settings: &Settings) -> bool {
+    dependency.ssh_key.is_some()
+        || settings.ssh_key.is_some()
+        || settings
+            .ssh_host(&dependency.git)
+            .is_some_and(|host| host.key.is_some())
+}
+
+/// Checks that an SSH agent with keys is running when SSH remotes rely on it
+fn check_ssh_agent(remotes: &[Remote], settings: &Settings) -> Check {
+    let needed = remotes.iter().any(|remote| {
+        ssh_url_host(&remote.dependency.git).is_some() && !has_ssh_key(remote.dependency, settings)
+    });
+
+    // ssh-add exits with 1 if the agent has no keys, and 2 if there is no agent
+    let agent = match Command::new("ssh-add").arg("-l").output() {
+        Ok(output) => match output.status.code() {
+            Some(0) => {
+                let keys = String::from_utf8_lossy(&output.stdout).lines().count();
+                Ok(format!("running with {} key(s)", keys))
+            }
+            Some(1) => Err("running without keys"),
+            _ => Err("not running"),
+        },
+        Err(_) => Err("ssh-add not found"),
+    };
+
+    match agent {
+        Ok(detail) => Check::ok("ssh agent", detail),
+        Err(detail) if !needed => Check::ok(
+            "ssh agent",
+            format!("{} (not needed: no SSH remote relies on it)", detail),
+        ),
+        Err(detail) => {
+            let fix = if cfg!(windows) {
+                "Start the agent with 'Start-Service ssh-agent' and add your key with \
+                'ssh-add', or set ssh_key in the fpm configuration"
+            } else {
+                "Start an agent and add your key with 'eval \"$(ssh-agent)\" && ssh-add', \
+                or set ssh_key in the fpm configuration"
+            };
+            Check::warning(
+                "ssh agent",
+                format!("{}, but SSH remotes need a key", detail),
+                fix,
+            )
+        }
+    }
+}
+
+/// Lists the credentials configured for HTTPS remotes: git's credential helpers,
+/// and fpm's tokens and .netrc entries for the hosts of the remotes
+fn check_credentials(remotes: &[Remote]) -> Check {
+    let mut found = Vec::new();
+
+    if let Ok(output) = Command::new("git")
+        .args(["config", "--get-all", "credential.helper"])
+        .output()
+    {
+        let helpers = String::from_utf8_lossy(&output.stdout);
+        let helpers: Vec<&str> = helpers.lines().filter(|h| !h.is_empty()).collect();
+        if !helpers.is_empty() {
+            found.push(format!("git credential helper {}", helpers.join(", ")));
+        }
+    }
+
+    let mut hosts: Vec<String> = remotes
+        .iter()
+        .filter_map(|remote| auth::https_host(&remote.dependency.git))
+        .collect();
+    hosts.sort();
+    hosts.dedup();
+    for host in &hosts {
+        let url = format!("https://{}/", host);
+        if auth::token_for_url(&url).is_some() {
+            found.push(format!("token for {}", host));
+        } else if auth::netrc_credentials(&url).is_some() {
+            found.push(format!(".netrc entry for {}", host));
+        }
+    }
+
+    if found.is_empty() {
+        Check::ok(
+            "credentials",
+            "none configured, so only public HTTPS repositories can be installed",
+        )
+    } else {
+        Check::ok("credentials", found.join("; "))
+    }
+}
+
+/// Checks that the remote of a bundle can be reached and read
+fn check_remote(git_ops: &dyn GitOperations, remote: &Remote) -> Check {
+    let dependency = remote.dependency;
+    let name = format!("remote {}", remote.key);
+    let Err(e) = git_ops.list_remote_tags(&dependency.git, dependency.ssh_key.as_deref()) else {
+        return Check::ok(name, dependency.git.clone());
+    };
+
+    let fix = if let Some(host) = ssh_url_host(&dependency.git) {
+        format!(
+            "Check that your SSH key is loaded (see the ssh agent check) and has access \
+            to the repository; 'ssh -T git@{}' tests the connection",
+            host
+        )
+    } else if let Some(host) = auth::https_host(&dependency.git) {
+        if auth::token_for_url(&dependency.git).is_some() {
+            format!(
+                "Check the URL, and that the token for {} is valid and has access; \
+                'fpm login {}' replaces it",
+                host, host
+            )
+        } else {
+            format!(
+                "Check the URL and your network. If the repository is private, store a \
+                token with 'fpm login {}' or set {}",
+                host,
+                auth::host_token_var(&host)
+            )
+        }
+    } else {
+        "Check the URL, and that the repository exists".to_string()
+    };
+
+    // The first line names the URL and the reason; git's advice follows it
+    let error = format!("{:#}", e);
+    Check::failed(name, error.lines().next().unwrap_or_default(), fix)
+}
+
+/// Checks that files can be created in `dir`, creating it if needed
+fn check_writable(name: &str, dir: &Path, fix: &str) -> Check {
+    let probe = dir.join(format!(".fpm-doctor-{}", std::process::id()));
+    let written = fs::create_dir_all(dir).and_then(|()| fs::write(&probe, b""));
+    let _ = fs::remove_file(&probe);
+
+    match written {
+        Ok(()) => Check::ok(name, format!("{} is writable", dir.display())),
+        Err(e) => Check::failed(
+            name,
+            format!("cannot write to {}: {}", dir.display(), e),
+            fix,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_git_version() {
+        assert_eq!(parse_git_version("git version 2.43.0"), Some((2, 43)));
+        assert_eq!(
+            parse_git_version("git version 2.39.3 (Apple Git-145)"),
+            Some((2, 39))
+        );
+        assert_eq!(
+            parse_git_version("git version 2.41.0.windows.1"),
+            Some((2, 41))
+        );
+        assert_eq!(parse_git_version("command not found"), None);
+    }
+}
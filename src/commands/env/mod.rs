@@ -0,0 +1,340 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::cli::EnvFormat;
+use crate::config::load_manifest;
+use crate::types::BUNDLE_DIR;
+
+/// Prefix of the per-bundle environment variables
+const VAR_PREFIX: &str = "FPM_BUNDLE_";
+
+/// Variable holding the top-level bundle directory
+const BUNDLE_DIR_VAR: &str = "FPM_BUNDLE_DIR";
+
+/// Variable holding the list of all installed bundle paths
+const BUNDLE_PATHS_VAR: &str = "FPM_BUNDLE_PATHS";
+
+/// An installed bundle exposed to the environment
+struct BundleLocation {
+    /// Bundle names from the top-level manifest down to this bundle
+    names: Vec<String>,
+    path: PathBuf,
+}
+
+/// Executes the env command
+pub fn execute(manifest_path: &Path, format: EnvFormat, output: Option<&Path>) -> Result<()> {
+    let manifest_path = if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    };
+
+    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+    let bundle_dir = parent_dir.join(BUNDLE_DIR);
+
+    let mut locations = Vec::new();
+    collect_locations(&manifest_path, &mut Vec::new(), &mut locations)?;
+
+    let rendered = render(format, &bundle_dir, &locations)?;
+
+    match output {
+        Some(path) => {
+            std::fs::write(path, rendered)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            eprintln!(
+                "{} {} bundle path(s) to {}",
+                "Wrote".green(),
+                locations.len(),
+                path.display()
+            );
+        }
+        None => print!("{}", rendered),
+    }
+
+    Ok(())
+}
+
+/// The variables `fpm env` prints, as name and value: FPM_BUNDLE_DIR, one
+/// FPM_BUNDLE_<NAME> per installed bundle and the FPM_BUNDLE_PATHS list
+pub fn bundle_vars(manifest_path: &Path) -> Result<Vec<(String, String)>> {
+    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+    let bundle_dir = parent_dir.join(BUNDLE_DIR);
+
+    let mut locations = Vec::new();
+    collect_locations(manifest_path, &mut Vec::new(), &mut locations)?;
+
+    let mut vars = vec![(
+        BUNDLE_DIR_VAR.to_string(),
+        bundle_dir.to_string_lossy().to_string(),
+    )];
+    vars.extend(location_vars(&locations)?);
+    let list = std::env::join_paths(locations.iter().map(|location| &location.path))
+        .context("Bundle path contains the path list separator")?;
+    vars.push((
+        BUNDLE_PATHS_VAR.to_string(),
+        list.to_string_lossy().to_string(),
+    ));
+
+    Ok(vars)
+}
+
+/// Collects installed bundles declared by a manifest, recursing into nested bundles
+fn collect_locations(
+    manifest_path: &Path,
+    parents: &mut Vec<String>,
+    locations: &mut Vec<BundleLocation>,
+) -> Result<()> {
+    let manifest = load_manifest(manifest_path)?;
+    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+    let bundle_dir = parent_dir.join(BUNDLE_DIR);
+
+    let mut names: Vec<&String> = manifest.bundles.keys().collect();
+    names.sort();
+
+    for name in names {
+        let path = bundle_dir.join(name);
+        if !path.exists() {
+            continue;
+        }
+
+        parents.push(name.clone());
+        locations.push(BundleLocation {
+            names: parents.clone(),
+            path: path.clone(),
+        });
+
+        let nested_manifest_path = path.join("bundle.toml");
+        if nested_manifest_path.exists() {
+            collect_locations(&nested_manifest_path, parents, locations)?;
+        }
+        parents.pop();
+    }
+
+    Ok(())
+}
+
+/// Builds the variable name for a bundle, e.g. ["ui-kit", "base-styles"] ->
+/// FPM_BUNDLE_UI_KIT__BASE_STYLES
+fn var_name(names: &[String]) -> String {
+    let segments: Vec<String> = names
+        .iter()
+        .map(|name| {
+            name.chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() {
+                        c.to_ascii_uppercase()
+                    } else {
+                        '_'
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    format!("{}{}", VAR_PREFIX, segments.join("__"))
+}
+
+/// The variable of every bundle, as name and path. Fails if two bundles get the same
+/// variable (e.g. `ui-kit` and `ui_kit`), or a bundle gets one of the fixed ones.
+fn location_vars(locations: &[BundleLocation]) -> Result<Vec<(String, String)>> {
+    let mut owners: HashMap<String, String> = HashMap::new();
+    let mut vars = Vec::new();
+    for location in locations {
+        let name = var_name(&location.names);
+        let key = location.names.join("/");
+        if name == BUNDLE_DIR_VAR || name == BUNDLE_PATHS_VAR {
+            anyhow::bail!(
+                "Bundle '{}' would be exported as {}, which holds {}. Rename the bundle.",
+                key,
+                name,
+                if name == BUNDLE_DIR_VAR {
+                    "the bundle directory"
+                } else {
+                    "the list of bundle paths"
+                }
+            );
+        }
+        if let Some(other) = owners.insert(name.clone(), key.clone()) {
+            anyhow::bail!(
+                "Bundles '{}' and '{}' would both be exported as {}. Rename one of them.",
+                other,
+                key,
+                name
+            );
+        }
+        vars.push((name, location.path.to_string_lossy().to_string()));
+    }
+    Ok(vars)
+}
+
+fn render(format: EnvFormat, bundle_dir: &Path, locations: &[BundleLocation]) -> Result<String> {
+    let bundle_dir = bundle_dir.to_string_lossy().to_string();
+    let vars = location_vars(locations)?;
+    let paths: Vec<&str> = vars.iter().map(|(_, path)| path.as_str()).collect();
+
+    let mut out = String::new();
+    match format {
+        EnvFormat::Shell => {
+            let list = std::env::join_paths(&paths)
+                .context("Bundle path contains the path list separator")?
+                .to_string_lossy()
+                .to_string();
+            out.push_str(&format!(
+                "export {}={}\n",
+                BUNDLE_DIR_VAR,
+                shell_quote(&bundle_dir)
+            ));
+            for (name, path) in &vars {
+                out.push_str(&format!("export {}={}\n", name, shell_quote(path)));
+            }
+            out.push_str(&format!(
+                "export {}={}\n",
+                BUNDLE_PATHS_VAR,
+                shell_quote(&list)
+            ));
+        }
+        EnvFormat::Dotenv => {
+            let list = std::env::join_paths(&paths)
+                .context("Bundle path contains the path list separator")?
+                .to_string_lossy()
+                .to_string();
+            out.push_str(&format!(
+                "{}={}\n",
+                BUNDLE_DIR_VAR,
+                double_quote(&bundle_dir)
+            ));
+            for (name, path) in &vars {
+                out.push_str(&format!("{}={}\n", name, double_quote(path)));
+            }
+            out.push_str(&format!("{}={}\n", BUNDLE_PATHS_VAR, double_quote(&list)));
+        }
+        EnvFormat::Cmake => {
+            // CMake lists are ';'-separated regardless of platform
+            let list = paths.join(";");
+            out.push_str(&format!(
+                "set({} {})\n",
+                BUNDLE_DIR_VAR,
+                double_quote(&bundle_dir)
+            ));
+            for (name, path) in &vars {
+                out.push_str(&format!("set({} {})\n", name, double_quote(path)));
+            }
+            out.push_str(&format!(
+                "set({} {})\n",
+                BUNDLE_PATHS_VAR,
+                double_quote(&list)
+            ));
+        }
+        EnvFormat::Json => {
+            let mut object = serde_json::Map::new();
+            object.insert(BUNDLE_DIR_VAR.to_string(), bundle_dir.into());
+            for (name, path) in &vars {
+                object.insert(name.clone(), path.clone().into());
+            }
+            object.insert(BUNDLE_PATHS_VAR.to_string(), paths.into());
+            out.push_str(&serde_json::to_string_pretty(&object)?);
+            out.push('\n');
+        }
+    }
+
+    Ok(out)
+}
+
+/// Single-quotes a value for POSIX shells
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Double-quotes a value, escaping backslashes and quotes (dotenv and CMake)
+fn double_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn locations() -> Vec<BundleLocation> {
+        vec![
+            BundleLocation {
+                names: vec!["ui-assets".to_string()],
+                path: PathBuf::from("/work/.fpm/ui-assets"),
+            },
+            BundleLocation {
+                names: vec!["ui-assets".to_string(), "base.styles".to_string()],
+                path: PathBuf::from("/work/.fpm/ui-assets/.fpm/base.styles"),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_var_name_sanitizes_and_joins_nested_names() {
+        assert_eq!(var_name(&["ui-assets".to_string()]), "FPM_BUNDLE_UI_ASSETS");
+        assert_eq!(
+            var_name(&["ui-kit".to_string(), "base.styles".to_string()]),
+            "FPM_BUNDLE_UI_KIT__BASE_STYLES"
+        );
+    }
+
+    #[test]
+    fn test_colliding_variables_are_rejected() {
+        let location = |names: &[&str]| BundleLocation {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            path: PathBuf::from("/work/.fpm").join(names.join("/.fpm/")),
+        };
+
+        let error = location_vars(&[location(&["ui-kit"]), location(&["ui_kit"])])
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("'ui-kit' and 'ui_kit'"), "{}", error);
+        assert!(error.contains("FPM_BUNDLE_UI_KIT"), "{}", error);
+
+        let error = location_vars(&[location(&["a", "b"]), location(&["a--b"])])
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("'a/b' and 'a--b'"), "{}", error);
+
+        for (name, var) in [("dir", BUNDLE_DIR_VAR), ("paths", BUNDLE_PATHS_VAR)] {
+            let error = render(
+                EnvFormat::Shell,
+                Path::new("/work/.fpm"),
+                &[location(&[name])],
+            )
+            .unwrap_err()
+            .to_string();
+            assert!(error.contains(&format!("'{}'", name)), "{}", error);
+            assert!(error.contains(var), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_render_shell_quotes_values() {
+        let rendered = render(EnvFormat::Shell, Path::new("/work/.fpm"), &locations()).unwrap();
+
+        assert!(rendered.contains("export FPM_BUNDLE_DIR='/work/.fpm'\n"));
+        assert!(rendered.contains("export FPM_BUNDLE_UI_ASSETS='/work/.fpm/ui-assets'\n"));
+        assert!(rendered.contains("export FPM_BUNDLE_UI_ASSETS__BASE_STYLES="));
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+
+    #[test]
+    fn test_render_cmake_uses_semicolon_lists() {
+        let rendered = render(EnvFormat::Cmake, Path::new("/work/.fpm"), &locations()).unwrap();
+
+        assert!(rendered.contains("set(FPM_BUNDLE_UI_ASSETS \"/work/.fpm/ui-assets\")\n"));
+        assert!(rendered.contains(
+            "set(FPM_BUNDLE_PATHS \"/work/.fpm/ui-assets;/work/.fpm/ui-assets/.fpm/base.styles\")\n"
+        ));
+    }
+
+    #[test]
+    fn test_render_json_lists_paths() {
+        let rendered = render(EnvFormat::Json, Path::new("/work/.fpm"), &locations()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+
+        assert_eq!(value["FPM_BUNDLE_UI_ASSETS"], "/work/.fpm/ui-assets");
+        assert_eq!(value["FPM_BUNDLE_PATHS"].as_array().unwrap().len(), 2);
+    }
+}
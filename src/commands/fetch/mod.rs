@@ -0,0 +1,105 @@
+use anyhow::Result;
+use colored::Colorize;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::commands::status::{self, StatusEntry, StatusOptions};
+use crate::git::{git_operations_for, GitOperations};
+use crate::settings::Settings;
+
+/// Executes the fetch command with the default git operations
+pub fn execute(manifest_path: &Path, jobs: usize, settings: &Settings) -> Result<()> {
+    let git_ops = git_operations_for(settings);
+    execute_with_git(manifest_path, jobs, settings, git_ops)
+}
+
+/// Executes the fetch command with a custom GitOperations implementation
+/// This enables dependency injection for testing
+pub fn execute_with_git(
+    manifest_path: &Path,
+    jobs: usize,
+    settings: &Settings,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<()> {
+    println!("Fetching bundles from {}", manifest_path.display());
+
+    let entries = fetch_bundles(manifest_path, jobs, settings, git_ops)?;
+    if entries.is_empty() {
+        println!("{}", "No bundles track a branch.".dimmed());
+        return Ok(());
+    }
+
+    for entry in &entries {
+        let indent = "  ".repeat(entry.depth + 1);
+        println!("{}{}: {}", indent, entry.name, describe(entry));
+    }
+
+    let updated = entries
+        .iter()
+        .filter(|entry| entry.behind.is_some_and(|behind| behind > 0))
+        .count();
+    println!();
+    if updated > 0 {
+        println!(
+            "{} bundle(s) have new upstream commits. Run 'fpm diff --remote' to review \
+            them and 'fpm update' to apply them.",
+            updated
+        );
+    } else {
+        println!("{}", "All bundles are up to date.".green());
+    }
+
+    Ok(())
+}
+
+/// Fetches the branch of every installed bundle into its remote-tracking ref, leaving
+/// working trees alone, and returns the bundles that were compared with it. Bundles
+/// installed from a version tag and those that aren't clones are left out; `fpm
+/// outdated` covers newer versions.
+pub fn fetch_bundles(
+    manifest_path: &Path,
+    jobs: usize,
+    settings: &Settings,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<Vec<StatusEntry>> {
+    let options = StatusOptions {
+        remote: true,
+        jobs,
+        settings: settings.clone(),
+        ..Default::default()
+    };
+
+    let entries = status::collect_statuses(manifest_path, git_ops, &options)?;
+    Ok(entries
+        .into_iter()
+        .filter(|entry| entry.behind.is_some())
+        .collect())
+}
+
+/// Describes how a fetched bundle compares with its branch, e.g. "3 new commits on main"
+fn describe(entry: &StatusEntry) -> String {
+    let commits = |count: usize| match count {
+        1 => "1 new commit".to_string(),
+        count => format!("{} new commits", count),
+    };
+    let branch = entry.branch.as_deref().unwrap_or_default();
+
+    match (
+        entry.behind.unwrap_or_default(),
+        entry.ahead.unwrap_or_default(),
+    ) {
+        (0, 0) => "up to date".dimmed().to_string(),
+        (0, ahead) => format!("up to date ({} local commit(s) not pushed)", ahead),
+        (behind, 0) => format!("{} on {}", commits(behind), branch)
+            .yellow()
+            .to_string(),
+        (behind, ahead) => format!(
+            "{} on {} ({} local commit(s) not pushed)",
+            commits(behind),
+            branch,
+            ahead
+        )
+        .yellow()
+        .to_string(),
+    }
+}
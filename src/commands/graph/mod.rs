@@ -0,0 +1,231 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::cli::GraphFormat;
+use crate::config::load_manifest;
+use crate::graph::{build_graph, source_identity, DependencyNode};
+
+/// A bundle in the exported graph. Bundles installing the same source at the same
+/// ref are one node, whichever bundles depend on them.
+struct GraphNode {
+    label: String,
+    installed: bool,
+}
+
+/// A bundle declaration: `from` depends on `to`, asking for `requested`
+struct GraphEdge {
+    from: usize,
+    to: usize,
+    requested: String,
+}
+
+/// The dependency graph with the top-level manifest as node 0
+#[derive(Default)]
+struct Graph {
+    nodes: Vec<GraphNode>,
+    edges: Vec<GraphEdge>,
+}
+
+/// Executes the graph command
+pub fn execute(manifest_path: &Path, format: GraphFormat, output: Option<&Path>) -> Result<()> {
+    let manifest_path = if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    };
+
+    let manifest = load_manifest(&manifest_path)?;
+    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+    let root_name = manifest.name.clone().unwrap_or_else(|| {
+        parent_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| ".".to_string())
+    });
+
+    let nodes = build_graph(&manifest_path)?;
+    let mut graph = Graph::default();
+    graph.nodes.push(GraphNode {
+        label: root_name,
+        installed: true,
+    });
+    let mut ids = HashMap::new();
+    add_nodes(&mut graph, &mut ids, 0, &nodes);
+
+    let rendered = render(format, &graph);
+    match output {
+        Some(path) => {
+            std::fs::write(path, rendered)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            eprintln!(
+                "{} a graph of {} bundle(s) to {}",
+                "Wrote".green(),
+                graph.nodes.len() - 1,
+                path.display()
+            );
+        }
+        None => print!("{}", rendered),
+    }
+
+    Ok(())
+}
+
+/// Adds the bundles declared by node `parent` with an edge from it to each. A
+/// bundle already in the graph gets another edge, but its own bundles aren't
+/// added again.
+fn add_nodes(
+    graph: &mut Graph,
+    ids: &mut HashMap<String, usize>,
+    parent: usize,
+    nodes: &[DependencyNode],
+) {
+    for node in nodes {
+        let manifest_dir = node
+            .path
+            .parent()
+            .and_then(Path::parent)
+            .unwrap_or(Path::new("."));
+        let identity = source_identity(&node.dependency, manifest_dir);
+
+        let (id, added) = match ids.get(&identity) {
+            Some(id) => (*id, false),
+            None => {
+                graph.nodes.push(GraphNode {
+                    label: format!("{}\n{}", node.name, node.dependency.source()),
+                    installed: node.is_installed(),
+                });
+                ids.insert(identity, graph.nodes.len() - 1);
+                (graph.nodes.len() - 1, true)
+            }
+        };
+        graph.edges.push(GraphEdge {
+            from: parent,
+            to: id,
+            requested: node.requested_ref(),
+        });
+
+        if added {
+            add_nodes(graph, ids, id, &node.children);
+        }
+    }
+}
+
+/// Renders the graph as a Graphviz digraph or a Mermaid flowchart. Bundles that
+/// aren't installed are drawn dashed; their own bundles are unknown.
+fn render(format: GraphFormat, graph: &Graph) -> String {
+    let mut out = String::new();
+    match format {
+        GraphFormat::Dot => {
+            out.push_str("digraph bundles {\n");
+            out.push_str("  rankdir=LR;\n");
+            out.push_str("  node [shape=box];\n");
+            for (id, node) in graph.nodes.iter().enumerate() {
+                let style = if node.installed { "" } else { ", style=dashed" };
+                out.push_str(&format!(
+                    "  n{} [label=\"{}\"{}];\n",
+                    id,
+                    dot_escape(&node.label),
+                    style
+                ));
+            }
+            for edge in &graph.edges {
+                out.push_str(&format!(
+                    "  n{} -> n{} [label=\"{}\"];\n",
+                    edge.from,
+                    edge.to,
+                    dot_escape(&edge.requested)
+                ));
+            }
+            out.push_str("}\n");
+        }
+        GraphFormat::Mermaid => {
+            out.push_str("graph LR\n");
+            for (id, node) in graph.nodes.iter().enumerate() {
+                out.push_str(&format!("  n{}[\"{}\"]\n", id, mermaid_escape(&node.label)));
+                if !node.installed {
+                    out.push_str(&format!("  style n{} stroke-dasharray: 5 5\n", id));
+                }
+            }
+            for edge in &graph.edges {
+                out.push_str(&format!(
+                    "  n{} -->|\"{}\"| n{}\n",
+                    edge.from,
+                    mermaid_escape(&edge.requested),
+                    edge.to
+                ));
+            }
+        }
+    }
+    out
+}
+
+/// Escapes a label for a double-quoted DOT string
+fn dot_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Escapes a label for a double-quoted Mermaid string
+fn mermaid_escape(value: &str) -> String {
+    value.replace('"', "#quot;").replace('\n', "<br/>")
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn graph() -> Graph {
+        let node = |label: &str, installed| GraphNode {
+            label: label.to_string(),
+            installed,
+        };
+        let edge = |from, to, requested: &str| GraphEdge {
+            from,
+            to,
+            requested: requested.to_string(),
+        };
+
+        Graph {
+            nodes: vec![
+                node("design", true),
+                node("ui-kit\nhttps://github.com/example/ui-kit.git", true),
+                node(
+                    "base-styles\nhttps://github.com/example/base-styles.git",
+                    false,
+                ),
+            ],
+            // base-styles is declared by the top level and by ui-kit
+            edges: vec![edge(0, 1, "^1.0"), edge(0, 2, "main"), edge(1, 2, "main")],
+        }
+    }
+
+    #[test]
+    fn test_render_dot() {
+        let rendered = render(GraphFormat::Dot, &graph());
+
+        assert!(rendered.starts_with("digraph bundles {\n"));
+        assert!(
+            rendered.contains("  n1 [label=\"ui-kit\\nhttps://github.com/example/ui-kit.git\"];\n")
+        );
+        assert!(rendered.contains(
+            "  n2 [label=\"base-styles\\nhttps://github.com/example/base-styles.git\", style=dashed];\n"
+        ));
+        assert!(rendered.contains("  n0 -> n2 [label=\"main\"];\n  n1 -> n2 [label=\"main\"];\n"));
+        assert!(rendered.ends_with("}\n"));
+    }
+
+    #[test]
+    fn test_render_mermaid() {
+        let rendered = render(GraphFormat::Mermaid, &graph());
+
+        assert!(rendered.starts_with("graph LR\n  n0[\"design\"]\n"));
+        assert!(rendered.contains("  n1[\"ui-kit<br/>https://github.com/example/ui-kit.git\"]\n"));
+        assert!(rendered.contains("  style n2 stroke-dasharray: 5 5\n"));
+        assert!(rendered.contains("  n0 -->|\"^1.0\"| n1\n"));
+        assert_eq!(rendered.matches("-->").count(), 3);
+    }
+}
@@ -0,0 +1,217 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::cache::dir_size;
+use crate::commands::cache::format_size;
+use crate::commands::status::determine_bundle_status;
+use crate::config::load_manifest;
+use crate::git::{git_operations_for, GitOperations};
+use crate::graph::{build_graph, find_paths, DependencyNode};
+use crate::lockfile::{load_lockfile, lockfile_path};
+use crate::settings::Settings;
+use crate::state::load_state;
+use crate::types::{BundleManifest, BundleStatus};
+
+/// Everything known about a bundle, installed or not
+#[derive(Debug, Clone)]
+pub struct BundleInfo {
+    pub name: String,
+    /// Install path below the top-level bundle directory, e.g. "ui-kit/base-styles"
+    pub key: String,
+    pub path: PathBuf,
+    pub source: String,
+    /// What the declaring manifest asks for: a version requirement, branch or rev
+    pub requested: String,
+    /// Branch (or tag) the bundle was installed from, if recorded
+    pub branch: Option<String>,
+    /// Commit checked out, or recorded by the install for bundles that aren't clones
+    pub commit: Option<String>,
+    /// Commit fpm.lock pins the bundle to
+    pub locked_commit: Option<String>,
+    /// None if the bundle isn't installed
+    pub status: Option<BundleStatus>,
+    /// Size of the installed files in bytes
+    pub size: Option<u64>,
+    /// The installed bundle's own manifest
+    pub manifest: Option<BundleManifest>,
+    /// Bundles declared by the installed bundle's manifest
+    pub dependencies: Vec<DependencyInfo>,
+}
+
+/// A bundle declared by the bundle shown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyInfo {
+    pub name: String,
+    pub requested: String,
+    /// None if the bundle isn't installed
+    pub status: Option<BundleStatus>,
+}
+
+/// Executes the info command with the default git operations
+pub fn execute(manifest_path: &Path, name: &str, settings: &Settings) -> Result<()> {
+    let git_ops = git_operations_for(settings);
+    let info = collect_info(manifest_path, name, git_ops)?;
+    print_info(&info);
+    Ok(())
+}
+
+/// Collects what is known about the bundle named `name` (a bundle name or an
+/// install path like "ui-kit/base-styles") with a custom GitOperations implementation
+pub fn collect_info(
+    manifest_path: &Path,
+    name: &str,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<BundleInfo> {
+    let manifest_path = if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    };
+
+    let graph = build_graph(&manifest_path)?;
+    let nodes: Vec<&DependencyNode> = find_paths(&graph, name)
+        .into_iter()
+        .filter_map(|path| path.last().copied())
+        .collect();
+    let node = match nodes.as_slice() {
+        [] => anyhow::bail!(
+            "Bundle '{}' is not declared by {} or any installed bundle",
+            name,
+            manifest_path.display()
+        ),
+        [node] => *node,
+        _ => {
+            let keys: Vec<&str> = nodes.iter().map(|node| node.key.as_str()).collect();
+            anyhow::bail!(
+                "Bundle '{}' is installed at several paths: {}. Name one of them by its install path.",
+                name,
+                keys.join(", ")
+            )
+        }
+    };
+
+    let installed = node.is_installed();
+    let status = match installed {
+        true => Some(determine_bundle_status(
+            git_ops.as_ref(),
+            &node.path,
+            Some(&node.dependency),
+        )?),
+        false => None,
+    };
+
+    // The state recorded by the install lives in the bundle directory containing it
+    let bundle_dir = node.path.parent().context("Invalid bundle path")?;
+    let recorded = load_state(bundle_dir)
+        .unwrap_or_default()
+        .bundles
+        .remove(&node.name);
+    let commit = if installed && git_ops.is_repository(&node.path) {
+        git_ops.head_commit(&node.path).ok()
+    } else {
+        recorded.as_ref().and_then(|recorded| recorded.rev.clone())
+    };
+    let locked_commit = load_lockfile(&lockfile_path(&manifest_path)?)?
+        .and_then(|mut lockfile| lockfile.bundles.remove(&node.key))
+        .map(|locked| locked.commit);
+
+    let mut dependencies = Vec::new();
+    for child in &node.children {
+        let status = match child.is_installed() {
+            true => Some(determine_bundle_status(
+                git_ops.as_ref(),
+                &child.path,
+                Some(&child.dependency),
+            )?),
+            false => None,
+        };
+        dependencies.push(DependencyInfo {
+            name: child.name.clone(),
+            requested: child.requested_ref(),
+            status,
+        });
+    }
+
+    Ok(BundleInfo {
+        name: node.name.clone(),
+        key: node.key.clone(),
+        path: node.path.clone(),
+        source: node.dependency.source(),
+        requested: node.requested_ref(),
+        branch: recorded.map(|recorded| recorded.branch),
+        commit,
+        locked_commit,
+        status,
+        size: installed.then(|| dir_size(&node.path)).transpose()?,
+        manifest: load_manifest(&node.path.join("bundle.toml")).ok(),
+        dependencies,
+    })
+}
+
+fn print_info(info: &BundleInfo) {
+    let manifest = info.manifest.as_ref();
+    let version = manifest.and_then(|manifest| manifest.version.as_deref());
+    match version {
+        Some(version) => println!("{} {}", info.name.bold(), version),
+        None => println!("{}", info.name.bold()),
+    }
+    if let Some(description) = manifest.and_then(|manifest| manifest.description.as_deref()) {
+        println!("{}", description);
+    }
+    println!();
+
+    let field = |label: &str, value: &str| println!("  {:<12}{}", label.cyan(), value);
+    let optional = |label: &str, value: Option<&str>| {
+        if let Some(value) = value {
+            field(label, value);
+        }
+    };
+    if info.key != info.name {
+        field("Install path", &info.key);
+    }
+    field("Source", &info.source);
+    field("Requested", &info.requested);
+    optional("Branch", info.branch.as_deref());
+    optional("Commit", info.commit.as_deref());
+    if info.locked_commit != info.commit {
+        optional("Locked", info.locked_commit.as_deref());
+    }
+    field("Path", &info.path.display().to_string());
+    match info.status {
+        Some(status) => field("Status", &status.to_string()),
+        None => field("Status", "not installed"),
+    }
+    optional("Size", info.size.map(format_size).as_deref());
+
+    if let Some(manifest) = manifest {
+        optional("License", manifest.license.as_deref());
+        if !manifest.authors.is_empty() {
+            field("Authors", &manifest.authors.join(", "));
+        }
+        if !manifest.keywords.is_empty() {
+            field("Keywords", &manifest.keywords.join(", "));
+        }
+        optional("Homepage", manifest.homepage.as_deref());
+        optional("Repository", manifest.repository.as_deref());
+        if let Some(readme) = &manifest.readme {
+            field("Readme", &info.path.join(readme).display().to_string());
+        }
+    }
+
+    if !info.dependencies.is_empty() {
+        println!();
+        println!("{}", "Dependencies:".bold());
+        for dependency in &info.dependencies {
+            let status = match dependency.status {
+                Some(status) => status.to_string(),
+                None => "not installed".to_string(),
+            };
+            println!(
+                "  {} {} [{}]",
+                dependency.name, dependency.requested, status
+            );
+        }
+    }
+}
@@ -1,18 +1,145 @@
 use anyhow::{Context, Result};
 use colored::Colorize;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::path::Path;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
 
-use crate::config::load_manifest;
-use crate::git::{fetch_bundle, GitCliOperations, GitOperations};
-use crate::types::BUNDLE_DIR;
+use crate::cache::{add_dependent, ensure_mirror, fnv1a_64, normalize_url, repository_name};
+use crate::checksum::{hash_tree, record_file_hashes, tree_checksum};
+use crate::config::{find_aliases, load_manifest, load_manifest_with_settings};
+use crate::conflicts::{Requirement, Requirements};
+use crate::flatten::{checkout_path, mirror};
+use crate::git::{discarded_changes, git_operations_for, GitOperations, LocalChanges};
+use crate::graph::{find_cycle, source_identity, Ancestor};
+use crate::hooks::{
+    bundle_hook_allowed, run_command, BUNDLE_DIR_ENV, HOOK_BUNDLE_ENV, HOOK_BUNDLE_PATH_ENV,
+    HOOK_EVENT_ENV,
+};
+use crate::lockfile::{
+    load_lockfile, lock_entry, lock_key, locked_bundle, lockfile_path, save_lockfile,
+};
+use crate::locking::lock_project;
+use crate::report::{self, BundleAction, BundleResult, BundleResults};
+use crate::resolve::ResolvedRef;
+use crate::settings::{HookPolicy, Settings, SignaturePolicy};
+use crate::signature::{check_bundle, SignatureCheck};
+use crate::source::{
+    remove_installed_bundle, source_for, symlink_dir, BundleSource, FetchRequest, Fetched,
+};
+use crate::state::{load_state, record_install, save_state};
+use crate::types::{
+    same_repository, BundleDependency, BundleHooks, BundleManifest, BundlePatch, BundleState,
+    InstallState, InstallStrategy, Lockfile, BUNDLE_DIR,
+};
 
-/// Executes the install command with the default GitCliOperations
-pub fn execute(manifest_path: &Path) -> Result<()> {
-    let git_ops = Arc::new(GitCliOperations::new());
-    execute_with_git(manifest_path, git_ops)
+/// Default number of bundles fetched concurrently
+pub const DEFAULT_JOBS: usize = 4;
+
+/// Directory inside a bundle directory where bundles installed for the first time are
+/// fetched before they are moved into place
+pub const STAGING_DIR: &str = ".staging";
+
+/// Directory inside the top-level .fpm directory holding the nested bundles shared
+/// by several bundles with `--dedupe`
+pub const SHARED_DIR: &str = ".shared";
+
+/// Options controlling an install
+#[derive(Debug, Clone, Default)]
+pub struct InstallOptions {
+    /// Install strictly from fpm.lock: fail if it is missing or out of sync with
+    /// bundle.toml, and never resolve branch tips or rewrite the lockfile
+    pub frozen: bool,
+    /// Bundles whose lock entries are ignored so their versions and branches are
+    /// resolved again
+    pub update: UpdateScope,
+    /// Install only this bundle (by lockfile key) and the bundles nested in it,
+    /// leaving the others as they are
+    pub only: Option<String>,
+    /// Install only the top-level bundles in one of these groups (all bundles if empty)
+    pub groups: Vec<String>,
+    /// Leave out the top-level bundles in these groups
+    pub exclude_groups: Vec<String>,
+    /// Optional bundles to install, named by install path
+    pub with: Vec<String>,
+    /// Maximum number of bundles fetched at the same time (0 or 1 fetches them one by one)
+    pub jobs: usize,
+    /// Carry on with the other bundles when one fails, reporting every failure at
+    /// the end
+    pub keep_going: bool,
+    /// Delete installed bundles, with any uncommitted changes, and install them from
+    /// scratch instead of skipping those with changes
+    pub force: bool,
+    /// Fetch every bundle from its source even if the lockfile says it is up to date
+    pub refresh: bool,
+    /// Stash the uncommitted changes of installed bundles, update them and apply the
+    /// changes again, instead of skipping them
+    pub autostash: bool,
+    /// Install nested bundles several bundles depend on once, in .fpm/.shared, and
+    /// link them into each of those bundles
+    pub dedupe: bool,
+    /// Install bundles that require incompatible versions of the same nested bundle
+    /// instead of failing
+    pub allow_duplicates: bool,
+    /// Don't run the `[hooks]` of the manifest or of installed bundles
+    pub no_hooks: bool,
+    /// Settings from the config files and the environment
+    pub settings: Settings,
+}
+
+/// Which bundles an install re-resolves instead of using their locked commits
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum UpdateScope {
+    /// Honour every lock entry
+    #[default]
+    Nothing,
+    /// Re-resolve every bundle
+    All,
+    /// Re-resolve one bundle (by lockfile key) and the bundles nested in it
+    Bundle(String),
+}
+
+impl UpdateScope {
+    /// Returns true if the bundle with this lockfile key is re-resolved
+    pub fn includes(&self, key: &str) -> bool {
+        match self {
+            UpdateScope::Nothing => false,
+            UpdateScope::All => true,
+            UpdateScope::Bundle(name) => is_within(key, name),
+        }
+    }
+}
+
+/// Returns true if the lockfile key `key` names the bundle `name` or a bundle nested in it
+pub fn is_within(key: &str, name: &str) -> bool {
+    key == name
+        || key
+            .strip_prefix(name)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Returns true if `path` is a link to a nested bundle shared with `--dedupe`
+pub fn is_shared_link(path: &Path) -> bool {
+    fs::read_link(path).is_ok_and(|target| {
+        target
+            .components()
+            .any(|component| component.as_os_str() == SHARED_DIR)
+    })
+}
+
+/// Lockfiles before and after an install
+#[derive(Debug, Clone)]
+pub struct InstallOutcome {
+    /// Lockfile found before the install (empty if there was none)
+    pub previous_lock: Lockfile,
+    /// Lockfile describing what the install checked out
+    pub lock: Lockfile,
+}
+
+/// Executes the install command with the default git operations
+pub fn execute(manifest_path: &Path, options: &InstallOptions) -> Result<()> {
+    let git_ops = git_operations_for(&options.settings);
+    execute_with_git(manifest_path, options, git_ops)
 }
 
 /// Ensures the bundle's .gitignore contains an entry for the .fpm directory
@@ -51,30 +178,588 @@ fn ensure_fpm_in_gitignore(bundle_path: &Path) -> Result<()> {
 
 /// Executes the install command with a custom GitOperations implementation
 /// This enables dependency injection for testing
-pub fn execute_with_git(manifest_path: &Path, git_ops: Arc<dyn GitOperations>) -> Result<()> {
+pub fn execute_with_git(
+    manifest_path: &Path,
+    options: &InstallOptions,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<()> {
+    let results = BundleResults::default();
+    let outcome = install_with_results(manifest_path, options, git_ops, &results);
+    if report::is_json() {
+        report::print("install", manifest_path, results, outcome.as_ref().err())?;
+        return outcome.map(|_| ());
+    }
+    outcome?;
+
+    println!("{}", "All bundles installed successfully!".green().bold());
+    Ok(())
+}
+
+/// Installs the bundles of a manifest and writes fpm.lock (unless frozen),
+/// returning the lockfiles before and after the install
+pub fn install_with_git(
+    manifest_path: &Path,
+    options: &InstallOptions,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<InstallOutcome> {
+    install_with_results(manifest_path, options, git_ops, &BundleResults::default())
+}
+
+/// Like `install_with_git`, also recording the result of every bundle in `results`
+pub fn install_with_results(
+    manifest_path: &Path,
+    options: &InstallOptions,
+    git_ops: Arc<dyn GitOperations>,
+    results: &BundleResults,
+) -> Result<InstallOutcome> {
     let manifest_path = if manifest_path.is_relative() {
         std::env::current_dir()?.join(manifest_path)
     } else {
         manifest_path.to_path_buf()
     };
 
-    println!(
+    // Another fpm run in the project would write to the same bundles
+    let _lock = lock_project(&manifest_path, &options.settings)?;
+
+    report::progress(format_args!(
         "{} {}",
         "Installing bundles from".cyan(),
         manifest_path.display()
+    ));
+
+    let lock_path = lockfile_path(&manifest_path)?;
+    let previous_lock = match load_lockfile(&lock_path)? {
+        Some(lockfile) => lockfile,
+        None if options.frozen => anyhow::bail!(
+            "--frozen requires {}, but it does not exist. Run 'fpm install' to create it.",
+            lock_path.display()
+        ),
+        None => Lockfile::default(),
+    };
+    if let Some(name) = &options.only {
+        check_bundle_exists(&manifest_path, name)?;
+    }
+
+    // Local patch paths are relative to the top-level manifest, but replace sources in
+    // manifests anywhere in the tree
+    let manifest_dir = manifest_path.parent().context("Invalid manifest path")?;
+    let top_manifest = load_manifest_with_settings(&manifest_path, &options.settings)?;
+    let dedupe = options.dedupe || top_manifest.dedupe;
+    let hooks = top_manifest.hooks.clone();
+    let patches: HashMap<String, BundlePatch> = top_manifest
+        .patch
+        .into_iter()
+        .map(|(url, mut patch)| {
+            patch.path = patch.path.map(|path| manifest_dir.join(path));
+            (url, patch)
+        })
+        .collect();
+
+    let links = load_state(&manifest_dir.join(BUNDLE_DIR))?.links;
+
+    let context = InstallContext {
+        git_ops,
+        frozen: options.frozen,
+        update: options.update.clone(),
+        only: options.only.clone(),
+        groups: options.groups.clone(),
+        exclude_groups: options.exclude_groups.clone(),
+        with: options.with.clone(),
+        patches,
+        used_patches: Mutex::new(HashSet::new()),
+        links,
+        dedupe,
+        shared_dir: manifest_dir.join(BUNDLE_DIR).join(SHARED_DIR),
+        shared: Mutex::new(HashMap::new()),
+        allow_duplicates: options.allow_duplicates,
+        signatures: options.settings.signature_policy(),
+        trusted_keys: options.settings.trusted_keys.clone(),
+        settings: &options.settings,
+        requirements: Requirements::default(),
+        previous_lock,
+        lock: Mutex::new(Lockfile::default()),
+        pool: FetchPool::new(options.jobs),
+        mirrors: Mutex::new(()),
+        keep_going: options.keep_going,
+        force: options.force,
+        refresh: options.refresh,
+        autostash: options.autostash,
+        failures: Mutex::new(Vec::new()),
+        installed: Mutex::new(Vec::new()),
+        results,
+    };
+
+    // A frozen install fails on a stale lockfile before fetching anything
+    if options.frozen {
+        let mut mismatches = LockMismatches::default();
+        check_frozen_lock(&context, &manifest_path, "", &mut mismatches)?;
+        mismatches.check(&lock_path)?;
+    }
+
+    if let (Some(command), false) = (&hooks.pre_install, options.no_hooks) {
+        report::progress(format_args!("{} pre_install hook", "Running".cyan()));
+        run_command(
+            command,
+            manifest_dir,
+            &hook_env("pre_install", manifest_dir),
+            false,
+        )
+        .context("pre_install hook failed")?;
+    }
+
+    let installed =
+        install_bundles(&context, &manifest_path, "", &[]).and_then(|()| check_conflicts(&context));
+    if let Err(error) = installed {
+        roll_back(&context);
+        return Err(error);
+    }
+    let mut lock = context.lock.into_inner().unwrap();
+    let failures = context.failures.into_inner().unwrap();
+
+    // A linked working copy doesn't change what fpm.lock pins for the bundle
+    for key in context.links.keys() {
+        lock.bundles.retain(|locked, _| !is_within(locked, key));
+        for (locked, entry) in &context.previous_lock.bundles {
+            if is_within(locked, key) {
+                lock.bundles.insert(locked.clone(), entry.clone());
+            }
+        }
+    }
+
+    // Installs limited to some bundles may not reach the patched ones
+    let complete =
+        options.only.is_none() && options.groups.is_empty() && options.exclude_groups.is_empty();
+    // Shared copies no bundle links to any more are left from earlier installs
+    if complete && failures.is_empty() {
+        let used: HashSet<String> = context.shared.into_inner().unwrap().into_keys().collect();
+        remove_unused_shared(&context.shared_dir, &used)?;
+    }
+    let used_patches = context.used_patches.into_inner().unwrap();
+    let mut unused: Vec<&String> = context
+        .patches
+        .keys()
+        .filter(|url| complete && !used_patches.contains(*url))
+        .collect();
+    unused.sort();
+    for url in unused {
+        report::progress(format_args!(
+            "{} patch for {} was not used: no bundle is installed from it",
+            "Warning:".yellow().bold(),
+            url
+        ));
+    }
+
+    if !failures.is_empty() {
+        // Failed bundles keep their previous lock entries, and so do the bundles
+        // nested in them
+        for (key, entry) in &context.previous_lock.bundles {
+            let failed = failures
+                .iter()
+                .any(|(failed, _)| key == failed || key.starts_with(&format!("{}/", failed)));
+            if failed && !lock.bundles.contains_key(key) {
+                lock.bundles.insert(key.clone(), entry.clone());
+            }
+        }
+        if !options.frozen {
+            save_lockfile(&lock_path, &lock)?;
+        }
+
+        let details: Vec<String> = failures
+            .iter()
+            .map(|(key, error)| format!("  {}: {}", key, error))
+            .collect();
+        anyhow::bail!(
+            "{} bundle(s) failed to install:\n{}",
+            failures.len(),
+            details.join("\n")
+        );
+    }
+
+    if options.frozen {
+        let stale: Vec<&String> = context
+            .previous_lock
+            .bundles
+            .keys()
+            .filter(|key| !lock.bundles.contains_key(*key))
+            .collect();
+        if !stale.is_empty() {
+            anyhow::bail!(
+                "{} is out of sync with bundle.toml: {:?} no longer declared. \
+                Run 'fpm install' to update the lockfile.",
+                lock_path.display(),
+                stale
+            );
+        }
+    } else {
+        save_lockfile(&lock_path, &lock)?;
+    }
+
+    if !options.no_hooks {
+        let installed = context.installed.into_inner().unwrap();
+        run_post_install_hooks(
+            &hooks,
+            manifest_dir,
+            &installed,
+            options.settings.bundle_hooks.unwrap_or_default(),
+        )?;
+    }
+
+    Ok(InstallOutcome {
+        previous_lock: context.previous_lock,
+        lock,
+    })
+}
+
+/// Variables every hook of an install gets
+fn hook_env(event: &str, manifest_dir: &Path) -> Vec<(&'static str, String)> {
+    vec![
+        (HOOK_EVENT_ENV, event.to_string()),
+        (
+            BUNDLE_DIR_ENV,
+            manifest_dir.join(BUNDLE_DIR).to_string_lossy().to_string(),
+        ),
+    ]
+}
+
+/// Runs the `post_install` hooks for every bundle the install fetched or moved to
+/// another commit, in install order: the bundle's own hook, if the `bundle_hooks`
+/// setting allows it, then the top-level manifest's
+fn run_post_install_hooks(
+    hooks: &BundleHooks,
+    manifest_dir: &Path,
+    installed: &[InstalledBundle],
+    policy: HookPolicy,
+) -> Result<()> {
+    for bundle in installed {
+        let path = bundle.bundle_dir.join(&bundle.name);
+        let mut env = hook_env("post_install", manifest_dir);
+        env.push((HOOK_BUNDLE_ENV, bundle.key.clone()));
+        env.push((HOOK_BUNDLE_PATH_ENV, path.to_string_lossy().to_string()));
+
+        let own_hook = load_manifest(&path.join("bundle.toml"))
+            .ok()
+            .and_then(|manifest| manifest.hooks.post_install);
+        if let Some(command) = own_hook {
+            if bundle_hook_allowed(policy, &bundle.key, &command)? {
+                report::progress(format_args!(
+                    "{} post_install hook of {}",
+                    "Running".cyan(),
+                    bundle.key
+                ));
+                run_command(&command, &path, &env, true).with_context(|| {
+                    format!("post_install hook of bundle '{}' failed", bundle.key)
+                })?;
+            }
+        }
+
+        if let Some(command) = &hooks.post_install {
+            report::progress(format_args!(
+                "{} post_install hook for {}",
+                "Running".cyan(),
+                bundle.key
+            ));
+            run_command(command, manifest_dir, &env, false)
+                .with_context(|| format!("post_install hook failed for bundle '{}'", bundle.key))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Fails if bundles require incompatible versions of the same bundle, unless
+/// duplicates are allowed
+fn check_conflicts(context: &InstallContext) -> Result<()> {
+    let conflicts = context.requirements.conflicts();
+    if conflicts.is_empty() || context.allow_duplicates {
+        return Ok(());
+    }
+
+    let details: Vec<String> = conflicts.iter().map(ToString::to_string).collect();
+    anyhow::bail!(
+        "{}\nAlign the requirements, or pass --allow-duplicates to install every version.",
+        details.join("\n")
     );
+}
 
-    let manifest = load_manifest(&manifest_path)?;
-    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+/// State threaded through a recursive install
+struct InstallContext<'a> {
+    git_ops: Arc<dyn GitOperations>,
+    /// Only install commits recorded in the previous lockfile
+    frozen: bool,
+    /// Bundles whose previous lock entries are ignored
+    update: UpdateScope,
+    /// The bundle the install is limited to, with the bundles nested in it
+    only: Option<String>,
+    /// Groups of the top-level bundles to install (all if empty)
+    groups: Vec<String>,
+    /// Groups of the top-level bundles to leave out
+    exclude_groups: Vec<String>,
+    /// Optional bundles asked for with --with
+    with: Vec<String>,
+    /// The top-level manifest's `[patch]` section, with local paths resolved
+    patches: HashMap<String, BundlePatch>,
+    /// Patched repository URLs some bundle was installed from
+    used_patches: Mutex<HashSet<String>>,
+    /// Working copies linked in place of bundles, keyed by install path
+    links: BTreeMap<String, PathBuf>,
+    /// Install nested bundles with the same source once and link them where needed
+    dedupe: bool,
+    /// Where the shared copies of nested bundles are installed
+    shared_dir: PathBuf,
+    /// Install path of the bundle each shared copy was installed for, keyed by the
+    /// copy's directory name. A copy's entry stays locked while it is installed.
+    shared: Mutex<HashMap<String, Arc<Mutex<Option<String>>>>>,
+    /// Don't fail when bundles require incompatible versions of the same bundle
+    allow_duplicates: bool,
+    /// What to do with bundles whose signature doesn't verify
+    signatures: SignaturePolicy,
+    /// Public keys whose bundle signatures are trusted
+    trusted_keys: Vec<String>,
+    /// Settings the install runs with
+    settings: &'a Settings,
+    /// What every git bundle asked for and got, to find version conflicts
+    requirements: Requirements,
+    /// Lockfile found before this install; its commits are checked out
+    previous_lock: Lockfile,
+    /// Lockfile describing what this install checked out
+    lock: Mutex<Lockfile>,
+    /// Limits how many bundles are fetched at once
+    pool: FetchPool,
+    /// Serializes shared mirror updates, which manifests at different levels may share
+    mirrors: Mutex<()>,
+    /// Record failed bundles and carry on instead of stopping at the first failure
+    keep_going: bool,
+    /// Reinstall bundles from scratch, discarding their local changes
+    force: bool,
+    /// Fetch bundles even if their lock entries say nothing changed
+    refresh: bool,
+    /// Update bundles with local changes by stashing the changes
+    autostash: bool,
+    /// Lockfile keys of the bundles that failed, with their errors
+    failures: Mutex<Vec<(String, String)>>,
+    /// Bundles this install installed or moved to another commit, in that order
+    installed: Mutex<Vec<InstalledBundle>>,
+    /// Result of every bundle, keyed by its lockfile key
+    results: &'a BundleResults,
+}
+
+impl InstallContext<'_> {
+    /// Returns true if a top-level bundle in `group` is installed
+    fn selects_group(&self, group: Option<&str>) -> bool {
+        let listed =
+            |groups: &[String]| group.is_some_and(|group| groups.iter().any(|g| g == group));
+        (self.groups.is_empty() || listed(&self.groups)) && !listed(&self.exclude_groups)
+    }
+}
+
+/// A bundle changed by an install, with what is needed to undo the change
+struct InstalledBundle {
+    bundle_dir: PathBuf,
+    name: String,
+    key: String,
+    /// Install state before the install; None if the bundle was installed for the
+    /// first time
+    previous: Option<BundleState>,
+    /// Clone to check the previous commit out in again, if the bundle has one
+    repository: Option<PathBuf>,
+}
 
-    // Check for duplicate bundle names
-    let bundle_names: Vec<&str> = manifest.bundles.keys().map(|s| s.as_str()).collect();
-    let unique_names: HashSet<&str> = bundle_names.iter().copied().collect();
+/// The bundles of one manifest being installed into its .fpm directory
+#[derive(Clone, Copy)]
+struct ManifestInstall<'a> {
+    manifest: &'a BundleManifest,
+    manifest_dir: &'a Path,
+    bundle_dir: &'a Path,
+    shared_stores: &'a HashMap<String, PathBuf>,
+    state: &'a Mutex<InstallState>,
+    /// Install path of the bundle owning the manifest ("" for the top level)
+    prefix: &'a str,
+    depth: usize,
+    /// Bundles from the top level down to the one owning the manifest (empty for the
+    /// top level)
+    ancestors: &'a [Ancestor],
+    /// The bundles are installed as shared copies in .fpm/.shared
+    shared: bool,
+}
 
-    if bundle_names.len() != unique_names.len() {
-        anyhow::bail!("Duplicate bundle names detected. Each bundle must have a unique name.");
+/// Limits the number of bundles fetched at the same time
+struct FetchPool {
+    jobs: usize,
+    available: Mutex<usize>,
+    freed: Condvar,
+}
+
+impl FetchPool {
+    fn new(jobs: usize) -> Self {
+        let jobs = jobs.max(1);
+        Self {
+            jobs,
+            available: Mutex::new(jobs),
+            freed: Condvar::new(),
+        }
     }
 
+    fn is_sequential(&self) -> bool {
+        self.jobs == 1
+    }
+
+    /// Runs `work` once a slot is free
+    fn run<T>(&self, work: impl FnOnce() -> T) -> T {
+        {
+            let mut available = self.available.lock().unwrap();
+            while *available == 0 {
+                available = self.freed.wait(available).unwrap();
+            }
+            *available -= 1;
+        }
+
+        let result = work();
+
+        *self.available.lock().unwrap() += 1;
+        self.freed.notify_one();
+        result
+    }
+}
+
+/// Bundles of the manifest tree fpm.lock doesn't match
+#[derive(Default)]
+struct LockMismatches {
+    /// Bundles missing from the lockfile, or whose source changed
+    missing: Vec<String>,
+    /// Lock entries of bundles no manifest declares any more
+    stale: Vec<String>,
+}
+
+impl LockMismatches {
+    fn check(&self, lock_path: &Path) -> Result<()> {
+        let quote = |keys: &[String]| {
+            keys.iter()
+                .map(|key| format!("'{}'", key))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut problems = Vec::new();
+        if !self.missing.is_empty() {
+            problems.push(format!(
+                "{} missing from the lockfile or changed git URL, branch or version",
+                quote(&self.missing)
+            ));
+        }
+        if !self.stale.is_empty() {
+            problems.push(format!("{} no longer declared", quote(&self.stale)));
+        }
+        if !problems.is_empty() {
+            anyhow::bail!(
+                "{} is out of sync with bundle.toml: {}. Run 'fpm install' to update the lockfile.",
+                lock_path.display(),
+                problems.join("; ")
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Walks the manifest tree the way `install_bundles` does, without fetching or
+/// writing anything, and records the bundles fpm.lock doesn't match. The manifest of
+/// a nested bundle is only known once the bundle is fetched, so nested bundles are
+/// checked here where their bundle is installed at its locked commit already.
+fn check_frozen_lock(
+    context: &InstallContext,
+    manifest_path: &Path,
+    prefix: &str,
+    mismatches: &mut LockMismatches,
+) -> Result<()> {
+    let mut manifest = load_manifest_with_settings(manifest_path, context.settings)?;
+    manifest.apply_bundle_defaults(context.settings);
+    apply_patches(&mut manifest, context);
+    apply_links(&mut manifest, prefix, context);
+    let bundle_dir = manifest_path
+        .parent()
+        .context("Invalid manifest path")?
+        .join(BUNDLE_DIR);
+    let state = load_state(&bundle_dir)?;
+
+    let mut stale: Vec<&String> = context
+        .previous_lock
+        .bundles
+        .keys()
+        .filter(|key| {
+            let name = match prefix {
+                "" => Some(key.as_str()),
+                prefix => key
+                    .strip_prefix(prefix)
+                    .and_then(|rest| rest.strip_prefix('/')),
+            };
+            name.is_some_and(|name| !name.contains('/') && !manifest.bundles.contains_key(name))
+        })
+        .collect();
+    stale.sort();
+    mismatches.stale.extend(stale.into_iter().cloned());
+
+    let mut names: Vec<&String> = manifest.bundles.keys().collect();
+    names.sort();
+    for name in names {
+        let dependency = &manifest.bundles[name];
+        let key = lock_key(prefix, name);
+
+        // The same bundles install_bundle skips
+        if prefix.is_empty() && !context.selects_group(dependency.group.as_deref()) {
+            continue;
+        }
+        let wanted = context.with.contains(&key) || context.only.as_ref() == Some(&key);
+        if dependency.optional && !wanted && !is_present(&bundle_dir.join(name)) {
+            continue;
+        }
+        if let Some(only) = context.only.as_deref() {
+            if !is_within(&key, only) && !is_within(only, &key) {
+                continue;
+            }
+        }
+
+        let source = source_for(dependency, context.git_ops.as_ref());
+        if !source.is_locked() {
+            continue;
+        }
+        let locked = if context.update.includes(&key) {
+            None
+        } else {
+            locked_bundle(&context.previous_lock, &key, dependency)
+        };
+        let Some(locked) = locked else {
+            mismatches.missing.push(key);
+            continue;
+        };
+
+        let installed = state
+            .bundles
+            .get(name.as_str())
+            .and_then(|bundle| bundle.rev.as_deref());
+        let nested_manifest = bundle_dir.join(name).join("bundle.toml");
+        if installed == Some(locked.commit.as_str()) && nested_manifest.exists() {
+            check_frozen_lock(context, &nested_manifest, &key, mismatches)?;
+        }
+    }
+    Ok(())
+}
+
+/// Installs the bundles of one manifest into its .fpm directory, then recurses into
+/// each installed bundle's own manifest. `prefix` is the install path of the bundle
+/// owning the manifest ("" for the top level) and is used for lockfile keys.
+/// Unless the install is sequential, every bundle (with its nested bundles) is
+/// installed on its own thread, with the fetch pool bounding concurrent fetches.
+fn install_bundles(
+    context: &InstallContext,
+    manifest_path: &Path,
+    prefix: &str,
+    ancestors: &[Ancestor],
+) -> Result<()> {
+    let depth = ancestors.len();
+    let mut manifest = load_manifest_with_settings(manifest_path, context.settings)?;
+    manifest.apply_bundle_defaults(context.settings);
+    apply_patches(&mut manifest, context);
+    apply_links(&mut manifest, prefix, context);
+    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+
     let bundle_dir = parent_dir.join(BUNDLE_DIR);
 
     // Create the .fpm directory if it doesn't exist
@@ -87,36 +772,1030 @@ pub fn execute_with_git(manifest_path: &Path, git_ops: Arc<dyn GitOperations>) -
         })?;
     }
 
+    let mut names: Vec<&String> = manifest.bundles.keys().collect();
+    names.sort();
+
     // Check for conflicts before downloading anything
-    check_for_conflicts(&manifest.bundles.keys().collect::<Vec<_>>())?;
+    check_for_conflicts(&names)?;
+    if depth == 0 {
+        check_groups(&manifest, &context.groups)?;
+    }
+    check_optional(&manifest, prefix, &context.with)?;
+
+    let shared_stores = {
+        let _mirrors = context.mirrors.lock().unwrap();
+        prepare_shared_stores(&manifest, context.git_ops.as_ref(), context.settings)?
+    };
+    let state = Mutex::new(load_state(&bundle_dir)?);
+    let level = ManifestInstall {
+        manifest: &manifest,
+        manifest_dir: parent_dir,
+        bundle_dir: &bundle_dir,
+        shared_stores: &shared_stores,
+        state: &state,
+        prefix,
+        depth,
+        ancestors,
+        shared: false,
+    };
+
+    let result = if context.pool.is_sequential() {
+        names
+            .into_iter()
+            .try_for_each(|name| install_or_record(context, &level, name))
+    } else {
+        std::thread::scope(|scope| {
+            let level = &level;
+            let handles: Vec<_> = names
+                .into_iter()
+                .map(|name| scope.spawn(move || install_or_record(context, level, name)))
+                .collect();
+
+            // Wait for every bundle before reporting the first failure
+            let results: Vec<Result<()>> = handles
+                .into_iter()
+                .map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+                .collect();
+            results.into_iter().collect()
+        })
+    };
+
+    // Best effort: whatever is left in the staging directory belongs to bundles
+    // that failed
+    let _ = fs::remove_dir_all(bundle_dir.join(STAGING_DIR));
+    result
+}
+
+/// Installs one bundle. With keep_going, a failure is reported and recorded instead
+/// of returned, so the other bundles are still installed.
+fn install_or_record(context: &InstallContext, level: &ManifestInstall, name: &str) -> Result<()> {
+    let result = install_bundle(context, level, name);
+    let key = lock_key(level.prefix, name);
+
+    // A bundle failing because of a nested bundle was fetched itself, and has
+    // its result already
+    if let Err(error) = &result {
+        if !context.results.contains(&key) {
+            let previous = level.state.lock().unwrap().bundles.get(name).cloned();
+            context.results.record(BundleResult {
+                name: key.clone(),
+                action: BundleAction::Failed,
+                old_commit: previous.and_then(|bundle| bundle.rev),
+                new_commit: None,
+                error: Some(format!("{:#}", error)),
+            });
+        }
+    }
+
+    match result {
+        Err(error) if context.keep_going => {
+            let indent = "  ".repeat(level.depth + 1);
+            report::progress(format_args!(
+                "{}{} {}: {:#}",
+                indent,
+                "Failed".red(),
+                key,
+                error
+            ));
+            context
+                .failures
+                .lock()
+                .unwrap()
+                .push((key, format!("{:#}", error)));
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+/// Fetches one bundle from its source, records it in the lockfile and install state,
+/// and installs its nested bundles
+fn install_bundle(context: &InstallContext, level: &ManifestInstall, name: &str) -> Result<()> {
+    let dependency = &level.manifest.bundles[name];
+    let source = source_for(dependency, context.git_ops.as_ref());
+    let key = lock_key(level.prefix, name);
+    let indent = "  ".repeat(level.depth + 1);
+
+    // Groups select among the bundles of the top-level manifest only; the bundles
+    // nested in a selected one are all installed
+    if level.depth == 0 && !context.selects_group(dependency.group.as_deref()) {
+        keep_lock_entries(context, &key, true);
+        return Ok(());
+    }
+
+    // A bundle nested in itself would be installed again and again
+    let identity = source_identity(dependency, level.manifest_dir);
+    if let Some(cycle) = find_cycle(level.ancestors, &identity, &key) {
+        report::progress(format_args!(
+            "{}{} {}: dependency cycle {}",
+            indent,
+            "Skipping".yellow(),
+            key,
+            cycle
+        ));
+        context.results.record(BundleResult {
+            name: key.clone(),
+            action: BundleAction::Skipped,
+            old_commit: None,
+            new_commit: None,
+            error: Some(format!("dependency cycle {}", cycle)),
+        });
+        return Ok(());
+    }
+
+    // Optional bundles are only installed on demand (naming one with --bundle counts)
+    let wanted = context.with.contains(&key) || context.only.as_ref() == Some(&key);
+    if dependency.optional && !wanted && !is_present(&level.bundle_dir.join(name)) {
+        report::progress(format_args!(
+            "{}{} {} (optional, install it with --with {})",
+            indent,
+            "Skipping".dimmed(),
+            key,
+            key
+        ));
+        keep_lock_entries(context, &key, true);
+        return Ok(());
+    }
+
+    // Bundles outside the selection are left alone; an installed bundle the selected
+    // one is nested in is only walked through
+    if let Some(only) = context
+        .only
+        .as_deref()
+        .filter(|only| !is_within(&key, only))
+    {
+        let bundle_path = level.bundle_dir.join(name);
+        if !is_within(only, &key) {
+            keep_lock_entries(context, &key, true);
+            return Ok(());
+        }
+        if bundle_path.exists() {
+            keep_lock_entries(context, &key, false);
+            return install_nested_bundles(context, level, name, &bundle_path);
+        }
+    }
+
+    // A matching lock entry pins the ref and commit without touching the network
+    let locked = if !source.is_locked() || context.update.includes(&key) {
+        None
+    } else {
+        locked_bundle(&context.previous_lock, &key, dependency)
+    };
+    if source.is_locked() && locked.is_none() && context.frozen {
+        anyhow::bail!(
+            "fpm.lock is out of sync with bundle.toml: bundle '{}' is missing from the \
+            lockfile or its git URL, branch or version changed. \
+            Run 'fpm install' to update the lockfile.",
+            key
+        );
+    }
+
+    let deduplicated = context.dedupe
+        && level.depth > 0
+        && !level.shared
+        && source.kind() == "git"
+        && !context.links.contains_key(&key);
+    if deduplicated {
+        let commit = locked.map(|locked| locked.commit.as_str());
+        return install_shared(context, level, name, commit);
+    }
+
+    let kind_note = match source.kind() {
+        "git" => String::new(),
+        kind => format!(" ({})", kind),
+    };
+    if level.depth == 0 {
+        report::progress(format_args!(
+            "{}{} {}{}",
+            indent,
+            "Fetching".green(),
+            name,
+            kind_note
+        ));
+    } else {
+        report::progress(format_args!(
+            "{}{} (nested) {}{}",
+            indent,
+            "Fetching".blue(),
+            key,
+            kind_note
+        ));
+    }
+
+    let mut previous = level.state.lock().unwrap().bundles.get(name).cloned();
+    let target_path = level.bundle_dir.join(name);
+    // A bundle linked to a shared copy by an install with --dedupe gets its own copy
+    if previous
+        .as_ref()
+        .is_some_and(|previous| previous.strategy == InstallStrategy::Shared)
+    {
+        remove_installed_bundle(level.bundle_dir, name)?;
+        previous = None;
+    }
+    let installed = previous.is_some() && target_path.exists();
+
+    // A bundle installed for the first time is fetched into the staging directory, so
+    // a fetch failing halfway doesn't leave a partial bundle behind
+    let staging_dir = level.bundle_dir.join(STAGING_DIR);
+    let staged = !is_present(&target_path) && !is_present(&checkout_path(level.bundle_dir, name));
+    let request = FetchRequest {
+        name,
+        dependency,
+        bundle_dir: if staged {
+            &staging_dir
+        } else {
+            level.bundle_dir
+        },
+        manifest_dir: level.manifest_dir,
+        previous: previous.as_ref(),
+        locked,
+        shared_store: level
+            .shared_stores
+            .get(&dependency.git)
+            .map(PathBuf::as_path),
+        refresh: context.refresh || context.update.includes(&key),
+        force: context.force,
+    };
+    let mut autostashed = false;
+    let mut conflicts = Vec::new();
+    let fetch = || {
+        if staged {
+            fetch_staged(context, source.as_ref(), &request, level.bundle_dir)
+        } else {
+            source.fetch(&request)
+        }
+    };
+    let fetched = match context.pool.run(fetch) {
+        Ok(fetched) => fetched,
+        Err(error) => match error.downcast_ref::<LocalChanges>() {
+            // Changes to files of a clone can be stashed; edits to the files copied
+            // out of a checkout can't
+            Some(changes) if context.autostash && !changes.files.is_empty() => {
+                let (fetched, conflicted) = context
+                    .pool
+                    .run(|| fetch_with_autostash(context, source.as_ref(), &request, changes))
+                    .with_context(|| format!("Failed to fetch bundle: {}", name))?;
+                autostashed = true;
+                conflicts = conflicted;
+                fetched
+            }
+            Some(changes) => return skip_changed_bundle(context, level, name, changes),
+            None => return Err(error.context(format!("Failed to fetch bundle: {}", name))),
+        },
+    };
+    let conflict_note = (!conflicts.is_empty()).then(|| {
+        format!(
+            "local changes to {} conflict with the update; they are marked in the files \
+            and kept in the stash",
+            conflicts.join(", ")
+        )
+    });
+    if let Some(note) = &conflict_note {
+        report::progress(format_args!(
+            "{}{} {}: {}",
+            indent,
+            "Conflict".yellow(),
+            key,
+            note
+        ));
+    }
+
+    let old_commit = previous.as_ref().and_then(|bundle| bundle.rev.clone());
+    let action = if !installed {
+        BundleAction::Fetched
+    } else if old_commit == fetched.rev {
+        BundleAction::Skipped
+    } else {
+        BundleAction::Updated
+    };
+    context.results.record(BundleResult {
+        name: key.clone(),
+        action,
+        old_commit: old_commit.clone(),
+        new_commit: fetched.rev.clone(),
+        error: conflict_note,
+    });
+
+    let repository = match fetched.strategy {
+        InstallStrategy::Clone => target_path.clone(),
+        _ => checkout_path(level.bundle_dir, name),
+    };
+    // Lets `fpm cache clean` give the clone its own objects before removing the mirror
+    if let Some(store) = request.shared_store {
+        add_dependent(store, &repository)?;
+    }
 
-    for (name, dependency) in &manifest.bundles {
-        println!("  {} {}", "Fetching".green(), name);
+    // Undone if the install fails, except for bundles updated around stashed changes
+    if action != BundleAction::Skipped && !autostashed {
+        let previous =
+            previous.filter(|previous| installed && previous.strategy == fetched.strategy);
+        context.installed.lock().unwrap().push(InstalledBundle {
+            bundle_dir: level.bundle_dir.to_path_buf(),
+            name: name.to_string(),
+            key: key.clone(),
+            previous,
+            repository: source.is_locked().then_some(repository),
+        });
+    }
+
+    if source.kind() != "local" {
+        if let Err(error) = check_signature(context, &key, dependency, &target_path, &indent) {
+            context.results.replace(BundleResult {
+                name: key.clone(),
+                action: BundleAction::Failed,
+                old_commit,
+                new_commit: None,
+                error: Some(format!("{:#}", error)),
+            });
+            return Err(error);
+        }
+    }
 
-        let target_path = bundle_dir.join(name);
+    if fetched.resolved.yanked {
+        report::progress(format_args!(
+            "{}{} bundle '{}' is installed at {}, which its author yanked. \
+            Require another version of it.",
+            indent,
+            "Warning:".yellow().bold(),
+            key,
+            fetched.resolved.name
+        ));
+    }
 
-        fetch_bundle(git_ops.as_ref(), dependency, &target_path)
-            .with_context(|| format!("Failed to fetch bundle: {}", name))?;
+    if let (true, Some(commit)) = (source.kind() == "git", &fetched.rev) {
+        let requirement = Requirement::new(&key, dependency, &fetched.resolved, commit);
+        context.requirements.record(dependency, requirement);
+    }
 
-        // Ensure .fpm is in the bundle's .gitignore to prevent nested bundles
-        // from being pushed to source repositories
+    // Ensure .fpm is in the bundle's .gitignore to prevent nested bundles
+    // from being pushed to source repositories. Flattened bundles never copy
+    // their .fpm directory back, so their sources are left untouched.
+    if fetched.strategy == InstallStrategy::Clone {
         ensure_fpm_in_gitignore(&target_path)?;
+    }
 
-        // Handle nested bundles recursively
-        let nested_manifest_path = target_path.join("bundle.toml");
-        if nested_manifest_path.exists() {
-            install_nested_bundles(&nested_manifest_path, git_ops.clone())?;
+    // Locked bundles are hashed as installed, .gitignore entry included, for
+    // `fpm verify`
+    let files = match (source.is_locked(), &fetched.rev) {
+        (true, Some(commit)) => {
+            let files = hash_tree(&target_path)
+                .with_context(|| format!("Failed to hash bundle: {}", key))?;
+            let mut entry = lock_entry(dependency, &fetched.resolved, commit.clone());
+            entry.checksum = Some(tree_checksum(&files));
+            context
+                .lock
+                .lock()
+                .unwrap()
+                .bundles
+                .insert(key.clone(), entry);
+            Some(files)
         }
+        _ => None,
+    };
 
-        println!("  {} {}", "✓".green(), name);
+    {
+        let mut state = level.state.lock().unwrap();
+        record_bundle_state(
+            level.bundle_dir,
+            &mut state,
+            name,
+            dependency,
+            &fetched.resolved,
+            fetched.rev,
+            fetched.strategy,
+        )?;
+        if let Some(files) = files {
+            record_file_hashes(level.bundle_dir, &state, name, files)?;
+        }
     }
 
-    println!("{}", "All bundles installed successfully!".green().bold());
+    install_nested_bundles(context, level, name, &target_path)
+}
+
+/// Checks the signature of a fetched bundle as the `signatures` setting asks:
+/// bundles that are unsigned or whose signature doesn't verify fail the install
+/// with `require`, and are installed with a warning with `warn`.
+fn check_signature(
+    context: &InstallContext,
+    key: &str,
+    dependency: &BundleDependency,
+    path: &Path,
+    indent: &str,
+) -> Result<()> {
+    if context.signatures == SignaturePolicy::Off {
+        return Ok(());
+    }
+
+    // Filtered bundles hold only some of the signed files
+    let filtered = dependency.include.is_some() || dependency.exclude.is_some();
+    let problem = match check_bundle(path, &context.trusted_keys, filtered)? {
+        SignatureCheck::Verified => return Ok(()),
+        SignatureCheck::Unsigned => "is not signed".to_string(),
+        SignatureCheck::Invalid(reason) => format!("has an invalid signature: {}", reason),
+    };
+    if context.signatures == SignaturePolicy::Require {
+        anyhow::bail!(
+            "Bundle '{}' {}. Only bundles signed with a trusted key are installed \
+            (signatures = \"require\").",
+            key,
+            problem
+        );
+    }
+
+    report::progress(format_args!(
+        "{}{} bundle '{}' {}",
+        indent,
+        "Warning:".yellow().bold(),
+        key,
+        problem
+    ));
     Ok(())
 }
 
-fn check_for_conflicts(names: &[&String]) -> Result<()> {
+/// Installs a nested bundle with `--dedupe`. The first bundle with a given source
+/// installs it as a copy in .fpm/.shared, and every bundle with that source is
+/// linked to the copy. `commit` is the commit fpm.lock pins the bundle to, if any;
+/// bundles pinned to another commit than the copy's get a copy of their own.
+fn install_shared(
+    context: &InstallContext,
+    level: &ManifestInstall,
+    name: &str,
+    commit: Option<&str>,
+) -> Result<()> {
+    let dependency = &level.manifest.bundles[name];
+    let key = lock_key(level.prefix, name);
+    let indent = "  ".repeat(level.depth + 1);
+
+    let mut id = shared_id(dependency, None);
+    let (mut primary, mut copy) = install_shared_copy(context, level, name, &id)?;
+    if let Some(commit) = commit.filter(|commit| copy.rev.as_deref() != Some(*commit)) {
+        id = shared_id(dependency, Some(commit));
+        (primary, copy) = install_shared_copy(context, level, name, &id)?;
+    }
+    if primary != key {
+        report::progress(format_args!(
+            "{}{} (nested) {} (shared with {})",
+            indent,
+            "Linking".blue(),
+            key,
+            primary
+        ));
+    }
+
+    let target_path = level.bundle_dir.join(name);
+    let shared_path = context.shared_dir.join(&id).join(name);
+    let previous = level.state.lock().unwrap().bundles.get(name).cloned();
+    let linked = fs::read_link(&target_path).is_ok_and(|target| target == shared_path);
+    if !linked {
+        if let Err(error) = replace_with_link(context, &target_path, &shared_path) {
+            return match error.downcast_ref::<LocalChanges>() {
+                Some(changes) => skip_changed_bundle(context, level, name, changes),
+                None => Err(error.context(format!(
+                    "Failed to link bundle '{}' to its shared copy",
+                    key
+                ))),
+            };
+        }
+        context.installed.lock().unwrap().push(InstalledBundle {
+            bundle_dir: level.bundle_dir.to_path_buf(),
+            name: name.to_string(),
+            key: key.clone(),
+            previous: None,
+            repository: None,
+        });
+    }
+
+    let old_commit = previous.as_ref().and_then(|bundle| bundle.rev.clone());
+    let action = if previous.is_none() || !linked {
+        BundleAction::Fetched
+    } else if old_commit == copy.rev {
+        BundleAction::Skipped
+    } else {
+        BundleAction::Updated
+    };
+    if primary != key {
+        context.results.record(BundleResult {
+            name: key.clone(),
+            action,
+            old_commit,
+            new_commit: copy.rev.clone(),
+            error: None,
+        });
+
+        // The lock entries of the copy are those of the bundle it was installed for
+        let mut lock = context.lock.lock().unwrap();
+        let entries: Vec<(String, _)> = lock
+            .bundles
+            .iter()
+            .filter(|(locked, _)| is_within(locked, &primary))
+            .map(|(locked, entry)| {
+                (
+                    format!("{}{}", key, &locked[primary.len()..]),
+                    entry.clone(),
+                )
+            })
+            .collect();
+        lock.bundles.extend(entries);
+    }
+
+    let resolved = ResolvedRef {
+        name: copy.branch,
+        version: copy.version,
+        yanked: false,
+    };
+    if let Some(commit) = &copy.rev {
+        let requirement = Requirement::new(&key, dependency, &resolved, commit);
+        context.requirements.record(dependency, requirement);
+    }
+    let mut state = level.state.lock().unwrap();
+    record_bundle_state(
+        level.bundle_dir,
+        &mut state,
+        name,
+        dependency,
+        &resolved,
+        copy.rev,
+        InstallStrategy::Shared,
+    )
+}
+
+/// Installs the shared copy `id` of a nested bundle unless another bundle did.
+/// Returns the install path of the bundle the copy was installed for, with the
+/// copy's install state.
+fn install_shared_copy(
+    context: &InstallContext,
+    level: &ManifestInstall,
+    name: &str,
+    id: &str,
+) -> Result<(String, BundleState)> {
+    let key = lock_key(level.prefix, name);
+    let shared_dir = context.shared_dir.join(id);
+    let slot = context
+        .shared
+        .lock()
+        .unwrap()
+        .entry(id.to_string())
+        .or_default()
+        .clone();
+
+    // Other bundles with the same source wait until the copy is installed
+    let mut primary = slot.lock().unwrap();
+    if primary.is_none() {
+        fs::create_dir_all(&shared_dir)
+            .with_context(|| format!("Failed to create directory: {}", shared_dir.display()))?;
+        let state = Mutex::new(load_state(&shared_dir)?);
+        let shared_level = ManifestInstall {
+            bundle_dir: &shared_dir,
+            state: &state,
+            shared: true,
+            ..*level
+        };
+        let result = install_bundle(context, &shared_level, name);
+        let _ = fs::remove_dir_all(shared_dir.join(STAGING_DIR));
+        result?;
+        *primary = Some(key.clone());
+    }
+
+    let Some(copy) = load_state(&shared_dir)?.bundles.remove(name) else {
+        anyhow::bail!("Shared copy of bundle '{}' was not installed", key);
+    };
+    Ok((primary.clone().unwrap_or(key), copy))
+}
+
+/// Directory name of the shared copy of a nested bundle: bundles with the same
+/// repository, ref, subdirectory and filters share a copy. Those pinned to another
+/// commit than the copy's are told apart by `commit`.
+fn shared_id(dependency: &BundleDependency, commit: Option<&str>) -> String {
+    let identity = format!(
+        "{}\n{:?}\n{}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}",
+        normalize_url(&dependency.git),
+        dependency.branch,
+        dependency.version,
+        dependency.rev,
+        dependency.path,
+        dependency.include,
+        dependency.exclude,
+        dependency.depth,
+        dependency.filter,
+        commit
+    );
+    format!(
+        "{}-{:016x}",
+        repository_name(&dependency.git),
+        fnv1a_64(identity.as_bytes())
+    )
+}
+
+/// Replaces whatever is installed at `target` with a link to `shared`. A clone with
+/// changes an update would discard is kept unless the install is forced.
+fn replace_with_link(context: &InstallContext, target: &Path, shared: &Path) -> Result<()> {
+    let git_ops = context.git_ops.as_ref();
+    if !context.force && !target.is_symlink() && git_ops.is_repository(target) {
+        let files = discarded_changes(git_ops, target, None)?;
+        if !files.is_empty() {
+            return Err(LocalChanges {
+                path: target.to_path_buf(),
+                files,
+            }
+            .into());
+        }
+    }
+
+    let bundle_dir = target.parent().context("Invalid bundle path")?;
+    let name = target
+        .file_name()
+        .context("Invalid bundle path")?
+        .to_string_lossy();
+    if target.is_symlink() {
+        // Links to directories are files on Unix and directories on Windows
+        fs::remove_file(target)
+            .or_else(|_| fs::remove_dir(target))
+            .with_context(|| format!("Failed to remove {}", target.display()))?;
+    }
+    remove_installed_bundle(bundle_dir, &name)?;
+
+    symlink_dir(shared, target).with_context(|| {
+        format!(
+            "Failed to create a link at {}; --dedupe needs symbolic links",
+            target.display()
+        )
+    })
+}
+
+/// Removes the shared copies in `shared_dir` other than the `used` ones
+fn remove_unused_shared(shared_dir: &Path, used: &HashSet<String>) -> Result<()> {
+    let Ok(entries) = fs::read_dir(shared_dir) else {
+        return Ok(());
+    };
+    for entry in entries {
+        let path = entry?.path();
+        let unused = path
+            .file_name()
+            .is_some_and(|id| !used.contains(id.to_string_lossy().as_ref()));
+        if unused && path.is_dir() {
+            fs::remove_dir_all(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+    }
+    if used.is_empty() {
+        fs::remove_dir(shared_dir)
+            .with_context(|| format!("Failed to remove {}", shared_dir.display()))?;
+    }
+    Ok(())
+}
+
+/// Returns true if something (possibly a dangling link) exists at `path`
+fn is_present(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok()
+}
+
+/// Fetches a bundle installed for the first time into the staging directory
+/// (`request.bundle_dir`) and moves it into `bundle_dir` once it is complete
+fn fetch_staged(
+    context: &InstallContext,
+    source: &dyn BundleSource,
+    request: &FetchRequest,
+    bundle_dir: &Path,
+) -> Result<Fetched> {
+    let staging_dir = request.bundle_dir;
+    // Left behind by an install that was killed
+    remove_installed_bundle(staging_dir, request.name)?;
+    fs::create_dir_all(staging_dir)
+        .with_context(|| format!("Failed to create directory: {}", staging_dir.display()))?;
+
+    let fetched = source.fetch(request).and_then(|fetched| {
+        move_into_place(
+            context.git_ops.as_ref(),
+            staging_dir,
+            bundle_dir,
+            request.name,
+        )?;
+        Ok(fetched)
+    });
+    if fetched.is_err() {
+        // Best effort: the staging directory is removed at the end of the install
+        let _ = remove_installed_bundle(staging_dir, request.name);
+    }
+    fetched
+}
+
+/// Moves a staged bundle into the bundle directory: first the checkout it was
+/// flattened from, if any, then the bundle itself
+fn move_into_place(
+    git_ops: &dyn GitOperations,
+    staging_dir: &Path,
+    bundle_dir: &Path,
+    name: &str,
+) -> Result<()> {
+    let moves = [
+        (
+            checkout_path(staging_dir, name),
+            checkout_path(bundle_dir, name),
+        ),
+        (staging_dir.join(name), bundle_dir.join(name)),
+    ];
+    for (from, to) in moves {
+        if !is_present(&from) {
+            continue;
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+
+        if git_ops.is_repository(&from) {
+            git_ops.move_repository(&from, &to)?;
+        } else {
+            fs::rename(&from, &to).with_context(|| {
+                format!("Failed to move {} to {}", from.display(), to.display())
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Undoes what a failed install did, latest change first: bundles installed for the
+/// first time are removed, and bundles moved to another commit are checked out at
+/// their previous commit again. Updated bundles without a clone (downloads) keep
+/// their new files.
+fn roll_back(context: &InstallContext) {
+    let installed = std::mem::take(&mut *context.installed.lock().unwrap());
+    for bundle in installed.iter().rev() {
+        match roll_back_bundle(context, bundle) {
+            Ok(true) => {
+                report::progress(format_args!("  {} {}", "Rolled back".yellow(), bundle.key));
+                let commit = bundle
+                    .previous
+                    .as_ref()
+                    .and_then(|previous| previous.rev.clone());
+                context.results.replace(BundleResult {
+                    name: bundle.key.clone(),
+                    action: BundleAction::Skipped,
+                    old_commit: commit.clone(),
+                    new_commit: commit,
+                    error: Some("rolled back because the install failed".to_string()),
+                });
+            }
+            Ok(false) => report::progress(format_args!(
+                "  {} {}: its previous files can't be restored",
+                "Kept".yellow(),
+                bundle.key
+            )),
+            Err(error) => report::progress(format_args!(
+                "  {} {}: {:#}",
+                "Failed to roll back".red(),
+                bundle.key,
+                error
+            )),
+        }
+    }
+}
+
+/// Restores one bundle changed by a failed install. Returns false if it can't be.
+fn roll_back_bundle(context: &InstallContext, bundle: &InstalledBundle) -> Result<bool> {
+    // Gone with the bundle it was nested in
+    if !bundle.bundle_dir.exists() {
+        return Ok(true);
+    }
+
+    let mut state = load_state(&bundle.bundle_dir)?;
+    match &bundle.previous {
+        None => {
+            remove_installed_bundle(&bundle.bundle_dir, &bundle.name)?;
+            state.bundles.remove(&bundle.name);
+        }
+        Some(previous) => {
+            let (Some(repository), Some(commit)) = (&bundle.repository, &previous.rev) else {
+                return Ok(false);
+            };
+            context
+                .git_ops
+                .checkout_commit(repository, &previous.branch, commit)?;
+            if let (InstallStrategy::Flatten, Some(subdirectory)) =
+                (previous.strategy, &previous.path)
+            {
+                mirror(
+                    &repository.join(subdirectory),
+                    &bundle.bundle_dir.join(&bundle.name),
+                )?;
+            }
+            state.bundles.insert(bundle.name.clone(), previous.clone());
+        }
+    }
+
+    save_state(&bundle.bundle_dir, &state)?;
+    Ok(true)
+}
+
+/// Updates a bundle whose update would discard local changes by stashing them,
+/// fetching and applying them again. Returns what was fetched and the files whose
+/// changes conflicted with the update.
+fn fetch_with_autostash(
+    context: &InstallContext,
+    source: &dyn BundleSource,
+    request: &FetchRequest,
+    changes: &LocalChanges,
+) -> Result<(Fetched, Vec<String>)> {
+    let git_ops = context.git_ops.as_ref();
+    git_ops.stash_changes(&changes.path)?;
+
+    let fetched = match source.fetch(request) {
+        Ok(fetched) => fetched,
+        Err(error) => {
+            // Put the changes back where they were
+            git_ops.apply_stash(&changes.path)?;
+            return Err(error);
+        }
+    };
+
+    let conflicts = git_ops
+        .apply_stash(&changes.path)
+        .context("Failed to apply the local changes again")?;
+    Ok((fetched, conflicts))
+}
+
+/// Leaves a bundle whose update would discard local changes as it is: it keeps its
+/// lock entry and install state, and the bundles nested in it are still installed
+fn skip_changed_bundle(
+    context: &InstallContext,
+    level: &ManifestInstall,
+    name: &str,
+    changes: &LocalChanges,
+) -> Result<()> {
+    let key = lock_key(level.prefix, name);
+    let indent = "  ".repeat(level.depth + 1);
+    report::progress(format_args!(
+        "{}{} {}: {}",
+        indent,
+        "Skipping".yellow(),
+        key,
+        changes
+    ));
+
+    let previous = level.state.lock().unwrap().bundles.get(name).cloned();
+    let commit = previous.and_then(|bundle| bundle.rev);
+    context.results.record(BundleResult {
+        name: key.clone(),
+        action: BundleAction::Skipped,
+        old_commit: commit.clone(),
+        new_commit: commit,
+        error: Some(changes.to_string()),
+    });
+
+    if let Some(entry) = context.previous_lock.bundles.get(&key) {
+        context
+            .lock
+            .lock()
+            .unwrap()
+            .bundles
+            .insert(key.clone(), entry.clone());
+    }
+
+    install_nested_bundles(context, level, name, &level.bundle_dir.join(name))
+}
+
+/// Carries the previous lock entry of a bundle the install leaves alone over to the
+/// new lockfile, with those of the bundles nested in it if `nested` is set
+fn keep_lock_entries(context: &InstallContext, key: &str, nested: bool) {
+    let mut lock = context.lock.lock().unwrap();
+    for (locked_key, entry) in &context.previous_lock.bundles {
+        if locked_key == key || (nested && is_within(locked_key, key)) {
+            lock.bundles.insert(locked_key.clone(), entry.clone());
+        }
+    }
+}
+
+/// Installs the bundles declared by an installed bundle's own manifest, then
+/// reports the bundle as done
+fn install_nested_bundles(
+    context: &InstallContext,
+    level: &ManifestInstall,
+    name: &str,
+    target_path: &Path,
+) -> Result<()> {
+    let nested_manifest_path = target_path.join("bundle.toml");
+    if nested_manifest_path.exists() {
+        let key = lock_key(level.prefix, name);
+        let dependency = &level.manifest.bundles[name];
+        let mut ancestors = level.ancestors.to_vec();
+        ancestors.push((source_identity(dependency, level.manifest_dir), key.clone()));
+        install_bundles(context, &nested_manifest_path, &key, &ancestors)?;
+    }
+
+    if level.depth == 0 {
+        let indent = "  ".repeat(level.depth + 1);
+        report::progress(format_args!("{}{} {}", indent, "✓".green(), name));
+    }
+
+    Ok(())
+}
+
+/// Fails if `name` is neither a bundle of the manifest nor a (nested) bundle in fpm.lock
+pub fn check_bundle_exists(manifest_path: &Path, name: &str) -> Result<()> {
+    let manifest = load_manifest(manifest_path)?;
+    if manifest.bundles.contains_key(name) {
+        return Ok(());
+    }
+
+    let lock = load_lockfile(&lockfile_path(manifest_path)?)?.unwrap_or_default();
+    if lock.bundles.contains_key(name) {
+        return Ok(());
+    }
+
+    anyhow::bail!(
+        "Bundle '{}' not found in bundle.toml or fpm.lock. \
+        Nested bundles are named by their install path, e.g. 'ui-kit/base-styles'.",
+        name
+    )
+}
+
+/// Points the bundles of a manifest that are installed from a patched repository at
+/// the replacement source
+fn apply_patches(manifest: &mut BundleManifest, context: &InstallContext) {
+    for dependency in manifest.bundles.values_mut() {
+        if dependency.git.is_empty() {
+            continue;
+        }
+        let Some((url, patch)) = context
+            .patches
+            .iter()
+            .find(|(url, _)| same_repository(url, &dependency.git))
+        else {
+            continue;
+        };
+
+        patch.apply(dependency);
+        context.used_patches.lock().unwrap().insert(url.clone());
+    }
+}
+
+/// Turns the bundles of a manifest that are linked with `fpm link` into local bundles
+/// pointing at the linked working copy (at its subdirectory, for bundles installing
+/// one)
+fn apply_links(manifest: &mut BundleManifest, prefix: &str, context: &InstallContext) {
+    for (name, dependency) in manifest.bundles.iter_mut() {
+        if let Some(path) = context.links.get(&lock_key(prefix, name)) {
+            let path = match dependency.subdirectory() {
+                Some(subdirectory) => path.join(subdirectory),
+                None => path.clone(),
+            };
+            dependency.git.clear();
+            dependency.url = None;
+            dependency.oci = None;
+            dependency.s3 = None;
+            dependency.path = Some(path);
+        }
+    }
+}
+
+/// Fails if no bundle of the manifest is in one of the groups to install, which is
+/// most likely a misspelled group
+fn check_groups(manifest: &BundleManifest, groups: &[String]) -> Result<()> {
+    for group in groups {
+        let used = manifest
+            .bundles
+            .values()
+            .any(|dependency| dependency.group.as_ref() == Some(group));
+        if !used {
+            anyhow::bail!("No bundle in bundle.toml is in group '{}'", group);
+        }
+    }
+
+    Ok(())
+}
+
+/// Fails if a bundle of this manifest asked for with --with doesn't exist or isn't
+/// optional. `prefix` is the install path of the bundle owning the manifest.
+fn check_optional(manifest: &BundleManifest, prefix: &str, with: &[String]) -> Result<()> {
+    for key in with {
+        let (parent, name) = key.rsplit_once('/').unwrap_or(("", key));
+        if parent != prefix {
+            continue;
+        }
+        match manifest.bundles.get(name) {
+            Some(dependency) if dependency.optional => {}
+            Some(_) => anyhow::bail!(
+                "Bundle '{}' is not optional; 'fpm install' always installs it",
+                key
+            ),
+            None => anyhow::bail!("Bundle '{}' not found in bundle.toml", key),
+        }
+    }
+
+    Ok(())
+}
+
+/// Validates bundle names: no duplicates, no path-like names, and no names that
+/// collide on case-insensitive file systems
+pub fn check_for_conflicts(names: &[&String]) -> Result<()> {
     let mut seen = HashSet::new();
+    // Bundle names become directory names, which may be case-insensitive (Windows, macOS)
+    let mut seen_dirs: HashMap<String, &String> = HashMap::new();
 
     for name in names {
         if !seen.insert(*name) {
@@ -126,38 +1805,73 @@ fn check_for_conflicts(names: &[&String]) -> Result<()> {
                 name
             );
         }
+
+        if name.is_empty()
+            || name.starts_with('.')
+            || name.contains(['/', '\\', ':'])
+            || name.as_str() == ".."
+        {
+            anyhow::bail!(
+                "Invalid bundle name '{}'. Bundle names are used as directory names \
+                and cannot be empty, start with '.', or contain path separators.",
+                name
+            );
+        }
+
+        if let Some(other) = seen_dirs.insert(name.to_lowercase(), name) {
+            anyhow::bail!(
+                "Conflict detected: bundles '{}' and '{}' would be installed into the same \
+                directory on case-insensitive file systems. Please rename one of them.",
+                other,
+                name
+            );
+        }
     }
 
     Ok(())
 }
 
-fn install_nested_bundles(manifest_path: &Path, git_ops: Arc<dyn GitOperations>) -> Result<()> {
-    let manifest = load_manifest(manifest_path)?;
-    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+/// Prepares a shared object store for every repository that is installed under more
+/// than one bundle name (aliases). Returns the mirror path for each shared git URL.
+fn prepare_shared_stores(
+    manifest: &BundleManifest,
+    git_ops: &dyn GitOperations,
+    settings: &Settings,
+) -> Result<HashMap<String, PathBuf>> {
+    let aliases = find_aliases(manifest);
+    let mut stores = HashMap::new();
 
-    let bundle_dir = parent_dir.join(BUNDLE_DIR);
+    for primary in aliases.values() {
+        let dependency = &manifest.bundles[primary];
+        if stores.contains_key(&dependency.git) {
+            continue;
+        }
 
-    if !bundle_dir.exists() {
-        fs::create_dir_all(&bundle_dir)?;
+        let mirror = ensure_mirror(
+            git_ops,
+            settings,
+            &dependency.git,
+            dependency.ssh_key.as_deref(),
+        )?;
+        stores.insert(dependency.git.clone(), mirror);
     }
 
-    for (name, dependency) in &manifest.bundles {
-        println!("    {} (nested) {}", "Fetching".blue(), name);
-
-        let target_path = bundle_dir.join(name);
-        fetch_bundle(git_ops.as_ref(), dependency, &target_path)?;
-
-        // Ensure .fpm is in the bundle's .gitignore
-        ensure_fpm_in_gitignore(&target_path)?;
-
-        // Recursive nested bundles
-        let nested_manifest_path = target_path.join("bundle.toml");
-        if nested_manifest_path.exists() {
-            install_nested_bundles(&nested_manifest_path, git_ops.clone())?;
-        }
-    }
+    Ok(stores)
+}
 
-    Ok(())
+/// Records a freshly installed bundle in the install state of its bundle directory.
+/// The state is saved after every bundle so an interrupted install keeps what succeeded.
+fn record_bundle_state(
+    bundle_dir: &Path,
+    state: &mut InstallState,
+    name: &str,
+    dependency: &BundleDependency,
+    resolved: &ResolvedRef,
+    rev: Option<String>,
+    strategy: InstallStrategy,
+) -> Result<()> {
+    record_install(state, name, dependency, resolved, rev, strategy);
+    save_state(bundle_dir, state)
 }
 
 #[cfg(test)]
@@ -175,6 +1889,38 @@ mod unit_tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_check_for_conflicts_case_insensitive() {
+        let icons = "Icons".to_string();
+        let icons_lower = "icons".to_string();
+        let names = vec![&icons, &icons_lower];
+
+        let result = check_for_conflicts(&names);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("case-insensitive"));
+    }
+
+    #[test]
+    fn test_check_for_conflicts_rejects_path_like_names() {
+        let nested = "../escape".to_string();
+        let hidden = ".hidden".to_string();
+
+        assert!(check_for_conflicts(&[&nested]).is_err());
+        assert!(check_for_conflicts(&[&hidden]).is_err());
+    }
+
+    #[test]
+    fn test_update_scope_includes_nested_bundles() {
+        let scope = UpdateScope::Bundle("ui-kit".to_string());
+
+        assert!(scope.includes("ui-kit"));
+        assert!(scope.includes("ui-kit/base-styles"));
+        assert!(!scope.includes("ui-kit-extra"));
+        assert!(!scope.includes("icons"));
+        assert!(UpdateScope::All.includes("icons"));
+        assert!(!UpdateScope::Nothing.includes("icons"));
+    }
+
     #[test]
     fn test_ensure_fpm_in_gitignore_creates_new() {
         let temp_dir = TempDir::new().unwrap();
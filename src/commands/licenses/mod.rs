@@ -0,0 +1,356 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::config::load_manifest;
+use crate::graph::{build_graph, DependencyNode};
+
+/// The license of an installed bundle
+#[derive(Debug, Clone, Serialize)]
+pub struct LicenseEntry {
+    /// Install path of the bundle, e.g. "ui-kit/base-styles"
+    pub bundle: String,
+    /// License declared by the bundle's own manifest
+    pub license: Option<String>,
+    /// True if the bundle can only be used under a denied license
+    pub denied: bool,
+}
+
+/// Options for the licenses command
+#[derive(Debug, Clone, Default)]
+pub struct LicensesOptions {
+    /// Print a JSON document instead of the report
+    pub json: bool,
+    /// SPDX identifiers of licenses no bundle may require
+    pub deny: Vec<String>,
+}
+
+/// Document printed by `fpm licenses --json`
+#[derive(Serialize)]
+struct LicensesReport<'a> {
+    manifest: String,
+    bundles: &'a [LicenseEntry],
+}
+
+/// Executes the licenses command. Fails if a bundle can only be used under a
+/// denied license.
+pub fn execute(manifest_path: &Path, options: &LicensesOptions) -> Result<()> {
+    let manifest_path = if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    };
+
+    let entries = collect_licenses(&manifest_path, &options.deny)?;
+    if options.json {
+        let report = LicensesReport {
+            manifest: manifest_path.to_string_lossy().to_string(),
+            bundles: &entries,
+        };
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).context("Failed to serialize licenses")?
+        );
+    } else {
+        print_report(&manifest_path, &entries);
+    }
+
+    let denied: Vec<String> = entries
+        .iter()
+        .filter(|entry| entry.denied)
+        .map(|entry| {
+            format!(
+                "  {} ({})",
+                entry.bundle,
+                entry.license.as_deref().unwrap_or_default()
+            )
+        })
+        .collect();
+    if !denied.is_empty() {
+        anyhow::bail!(
+            "{} bundle(s) use denied licenses:\n{}",
+            denied.len(),
+            denied.join("\n")
+        );
+    }
+
+    Ok(())
+}
+
+/// Collects the licenses of the installed bundles, nested ones included, in
+/// install path order. Bundles whose license allows only licenses in `deny` are
+/// marked as denied.
+pub fn collect_licenses(manifest_path: &Path, deny: &[String]) -> Result<Vec<LicenseEntry>> {
+    let nodes = build_graph(manifest_path)?;
+    let mut entries = Vec::new();
+    collect_nodes(&nodes, deny, &mut entries)?;
+    Ok(entries)
+}
+
+fn collect_nodes(
+    nodes: &[DependencyNode],
+    deny: &[String],
+    entries: &mut Vec<LicenseEntry>,
+) -> Result<()> {
+    for node in nodes.iter().filter(|node| node.is_installed()) {
+        let license = load_manifest(&node.path.join("bundle.toml"))
+            .ok()
+            .and_then(|manifest| manifest.license);
+        let denied = match &license {
+            Some(license) if !deny.is_empty() => is_denied(license, deny)
+                .with_context(|| format!("Invalid license of bundle: {}", node.key))?,
+            _ => false,
+        };
+
+        entries.push(LicenseEntry {
+            bundle: node.key.clone(),
+            license,
+            denied,
+        });
+        collect_nodes(&node.children, deny, entries)?;
+    }
+
+    Ok(())
+}
+
+/// Prints the bundles grouped by license, bundles without one last
+fn print_report(manifest_path: &Path, entries: &[LicenseEntry]) {
+    println!(
+        "{} {}",
+        "Licenses of bundles installed for".cyan(),
+        manifest_path.display()
+    );
+    println!();
+
+    if entries.is_empty() {
+        println!("{}", "No bundles installed.".yellow());
+        return;
+    }
+
+    let mut licenses: BTreeMap<&str, Vec<&LicenseEntry>> = BTreeMap::new();
+    let mut undeclared = Vec::new();
+    for entry in entries {
+        match &entry.license {
+            Some(license) => licenses.entry(license).or_default().push(entry),
+            None => undeclared.push(entry),
+        }
+    }
+
+    let license_count = licenses.len();
+    let mut groups: Vec<(String, Vec<&LicenseEntry>)> = licenses
+        .into_iter()
+        .map(|(license, entries)| (license.to_string(), entries))
+        .collect();
+    if !undeclared.is_empty() {
+        groups.push(("(no license declared)".to_string(), undeclared));
+    }
+
+    for (license, entries) in &groups {
+        println!("{} ({})", license.bold(), entries.len());
+        for entry in entries {
+            if entry.denied {
+                println!("  {} {}", entry.bundle, "denied".red());
+            } else {
+                println!("  {}", entry.bundle);
+            }
+        }
+    }
+
+    println!();
+    println!(
+        "Total: {} bundle(s), {} license(s)",
+        entries.len(),
+        license_count
+    );
+}
+
+/// Returns true if the SPDX license expression can't be met without a license in
+/// `deny`: `MIT OR GPL-3.0` is only denied if both are, `MIT AND GPL-3.0` if
+/// either is. Exceptions (`WITH`) and `+` don't change the license compared.
+pub fn is_denied(expression: &str, deny: &[String]) -> Result<bool> {
+    let spaced = expression.replace('(', " ( ").replace(')', " ) ");
+    let tokens: Vec<&str> = spaced.split_whitespace().collect();
+
+    let mut parser = ExpressionParser {
+        tokens: &tokens,
+        position: 0,
+        deny,
+    };
+    let allowed = parser.parse_or()?;
+    if let Some(token) = parser.peek() {
+        anyhow::bail!(
+            "Unexpected '{}' in license expression: {}",
+            token,
+            expression
+        );
+    }
+    Ok(!allowed)
+}
+
+/// Evaluates an SPDX license expression to whether it allows a license outside
+/// the deny-list. AND binds tighter than OR.
+struct ExpressionParser<'a> {
+    tokens: &'a [&'a str],
+    position: usize,
+    deny: &'a [String],
+}
+
+impl<'a> ExpressionParser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).copied()
+    }
+
+    /// Consumes the next token if it is the keyword (or parenthesis) given
+    fn accept(&mut self, keyword: &str) -> bool {
+        let accepted = self
+            .peek()
+            .is_some_and(|token| token.eq_ignore_ascii_case(keyword));
+        if accepted {
+            self.position += 1;
+        }
+        accepted
+    }
+
+    fn parse_or(&mut self) -> Result<bool> {
+        let mut allowed = self.parse_and()?;
+        while self.accept("OR") {
+            allowed |= self.parse_and()?;
+        }
+        Ok(allowed)
+    }
+
+    fn parse_and(&mut self) -> Result<bool> {
+        let mut allowed = self.parse_license()?;
+        while self.accept("AND") {
+            allowed &= self.parse_license()?;
+        }
+        Ok(allowed)
+    }
+
+    fn parse_license(&mut self) -> Result<bool> {
+        if self.accept("(") {
+            let allowed = self.parse_or()?;
+            if !self.accept(")") {
+                anyhow::bail!("Missing ')' in license expression");
+            }
+            return Ok(allowed);
+        }
+
+        let license = match self.peek() {
+            Some(token) if !is_operator(token) => token.trim_end_matches('+'),
+            Some(token) => anyhow::bail!("Expected a license before '{}'", token),
+            None => anyhow::bail!("License expression ends early"),
+        };
+        self.position += 1;
+        if self.accept("WITH") {
+            match self.peek() {
+                Some(token) if !is_operator(token) => self.position += 1,
+                _ => anyhow::bail!("Expected an exception after WITH"),
+            }
+        }
+
+        Ok(!self
+            .deny
+            .iter()
+            .any(|denied| denied.eq_ignore_ascii_case(license)))
+    }
+}
+
+fn is_operator(token: &str) -> bool {
+    ["AND", "OR", "WITH", "(", ")"]
+        .iter()
+        .any(|operator| token.eq_ignore_ascii_case(operator))
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::types::BUNDLE_DIR;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write_manifest(dir: &Path, license: Option<&str>, bundles: &[&str]) {
+        fs::create_dir_all(dir).unwrap();
+        let mut content = "fpm_version = \"0.1.0\"\n".to_string();
+        if let Some(license) = license {
+            content.push_str(&format!("license = \"{}\"\n", license));
+        }
+        for name in bundles {
+            content.push_str(&format!(
+                "\n[bundles.{}]\nversion = \"*\"\ngit = \"https://github.com/example/{}.git\"\n",
+                name, name
+            ));
+        }
+        fs::write(dir.join("bundle.toml"), content).unwrap();
+    }
+
+    #[test]
+    fn test_is_denied() {
+        let deny = vec!["GPL-3.0".to_string(), "CC-BY-NC-4.0".to_string()];
+        let denied = |expression| is_denied(expression, &deny).unwrap();
+
+        assert!(!denied("MIT"));
+        assert!(denied("GPL-3.0"));
+        assert!(denied("gpl-3.0+"));
+        assert!(denied("GPL-3.0 WITH Classpath-exception-2.0"));
+        // Either license may be chosen unless both are denied
+        assert!(!denied("MIT OR GPL-3.0"));
+        assert!(denied("GPL-3.0 OR CC-BY-NC-4.0"));
+        assert!(denied("MIT AND GPL-3.0"));
+        assert!(denied("MIT AND (GPL-3.0 OR CC-BY-NC-4.0)"));
+        assert!(!denied("(MIT OR GPL-3.0) AND Apache-2.0"));
+
+        assert!(is_denied("MIT OR", &deny).is_err());
+        assert!(is_denied("(MIT", &deny).is_err());
+        assert!(is_denied("MIT Apache-2.0", &deny).is_err());
+    }
+
+    #[test]
+    fn test_collect_licenses_includes_nested_bundles() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let bundle_dir = root.join(BUNDLE_DIR);
+
+        write_manifest(root, None, &["fonts", "icons", "ui-kit"]);
+        write_manifest(&bundle_dir.join("icons"), Some("MIT"), &[]);
+        write_manifest(
+            &bundle_dir.join("ui-kit"),
+            Some("Apache-2.0"),
+            &["base-styles"],
+        );
+        write_manifest(
+            &bundle_dir
+                .join("ui-kit")
+                .join(BUNDLE_DIR)
+                .join("base-styles"),
+            Some("CC-BY-NC-4.0"),
+            &[],
+        );
+        // Installed without a manifest of its own
+        fs::create_dir_all(bundle_dir.join("fonts")).unwrap();
+
+        let deny = vec!["CC-BY-NC-4.0".to_string()];
+        let entries = collect_licenses(&root.join("bundle.toml"), &deny).unwrap();
+        let summary: Vec<(&str, Option<&str>, bool)> = entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.bundle.as_str(),
+                    entry.license.as_deref(),
+                    entry.denied,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("fonts", None, false),
+                ("icons", Some("MIT"), false),
+                ("ui-kit", Some("Apache-2.0"), false),
+                ("ui-kit/base-styles", Some("CC-BY-NC-4.0"), true),
+            ]
+        );
+    }
+}
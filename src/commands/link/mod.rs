@@ -0,0 +1,210 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::commands::install::{check_bundle_exists, install_with_git, InstallOptions};
+use crate::commands::remove::find_local_changes;
+use crate::config::{has_manifest, load_manifest};
+use crate::git::{git_operations_for, GitOperations};
+use crate::lockfile::installed_path;
+use crate::locking::lock_project;
+use crate::settings::Settings;
+use crate::source::remove_installed_bundle;
+use crate::state::{load_state, save_state};
+use crate::types::BUNDLE_DIR;
+
+/// Executes the link command with the default git operations
+pub fn link(
+    manifest_path: &Path,
+    working_copy: Option<&Path>,
+    bundle: Option<&str>,
+    force: bool,
+    settings: &Settings,
+) -> Result<()> {
+    let git_ops = git_operations_for(settings);
+    link_with_git(
+        manifest_path,
+        working_copy,
+        bundle,
+        force,
+        settings,
+        git_ops,
+    )
+}
+
+/// Links a working copy in place of an installed bundle, or lists the links if no
+/// working copy is given. The bundle defaults to the name in the working copy's
+/// bundle.toml, or else its directory name.
+pub fn link_with_git(
+    manifest_path: &Path,
+    working_copy: Option<&Path>,
+    bundle: Option<&str>,
+    force: bool,
+    settings: &Settings,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<()> {
+    let manifest_path = absolute(manifest_path)?;
+    let _lock = lock_project(&manifest_path, settings)?;
+    let bundle_dir = project_bundle_dir(&manifest_path)?;
+    let mut state = load_state(&bundle_dir)?;
+
+    let Some(working_copy) = working_copy else {
+        if state.links.is_empty() {
+            println!("{}", "No bundles are linked.".dimmed());
+        }
+        for (key, path) in &state.links {
+            println!("{} -> {}", key, path.display());
+        }
+        return Ok(());
+    };
+
+    let working_copy = fs::canonicalize(working_copy)
+        .with_context(|| format!("Failed to find {}", working_copy.display()))?;
+    if !working_copy.is_dir() {
+        anyhow::bail!("{} is not a directory", working_copy.display());
+    }
+    let key = match bundle {
+        Some(bundle) => bundle.to_string(),
+        None => bundle_name(&working_copy)?,
+    };
+    check_bundle_exists(&manifest_path, &key).with_context(|| match bundle {
+        Some(_) => format!("Failed to link {}", working_copy.display()),
+        None => format!(
+            "{} provides bundle '{}'; name the bundle to link with --bundle",
+            working_copy.display(),
+            key
+        ),
+    })?;
+
+    // The installed bundle is replaced, so local work in it would be lost
+    let target = installed_path(&bundle_dir, &key);
+    if !force {
+        let mut changed = Vec::new();
+        find_local_changes(git_ops.as_ref(), &target, &mut changed)?;
+        if !changed.is_empty() {
+            let paths: Vec<String> = changed.iter().map(|p| p.display().to_string()).collect();
+            anyhow::bail!(
+                "Bundle '{}' has uncommitted local changes in: {}. \
+                Push them with 'fpm push' or use --force to discard them.",
+                key,
+                paths.join(", ")
+            );
+        }
+    }
+    remove_installed(&target)?;
+
+    state.links.insert(key.clone(), working_copy.clone());
+    save_state(&bundle_dir, &state)?;
+
+    let options = InstallOptions {
+        only: Some(key.clone()),
+        settings: settings.clone(),
+        ..Default::default()
+    };
+    install_with_git(&manifest_path, &options, git_ops)?;
+    println!("{} {} -> {}", "Linked".green(), key, working_copy.display());
+
+    Ok(())
+}
+
+/// Executes the unlink command with the default git operations
+pub fn unlink(manifest_path: &Path, bundle: Option<&str>, settings: &Settings) -> Result<()> {
+    let git_ops = git_operations_for(settings);
+    unlink_with_git(manifest_path, bundle, settings, git_ops)
+}
+
+/// Removes the link of a bundle (of every linked bundle if none is given) and
+/// installs the bundle from its source again, at the commit in fpm.lock
+pub fn unlink_with_git(
+    manifest_path: &Path,
+    bundle: Option<&str>,
+    settings: &Settings,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<()> {
+    let manifest_path = absolute(manifest_path)?;
+    let _lock = lock_project(&manifest_path, settings)?;
+    let bundle_dir = project_bundle_dir(&manifest_path)?;
+    let mut state = load_state(&bundle_dir)?;
+
+    let keys: Vec<String> = match bundle {
+        Some(bundle) if state.links.contains_key(bundle) => vec![bundle.to_string()],
+        Some(bundle) => anyhow::bail!("Bundle '{}' is not linked", bundle),
+        None => state.links.keys().cloned().collect(),
+    };
+    if keys.is_empty() {
+        println!("{}", "No bundles are linked.".dimmed());
+        return Ok(());
+    }
+
+    for key in &keys {
+        remove_installed(&installed_path(&bundle_dir, key))?;
+        state.links.remove(key);
+    }
+    save_state(&bundle_dir, &state)?;
+
+    for key in keys {
+        let options = InstallOptions {
+            only: Some(key.clone()),
+            settings: settings.clone(),
+            ..Default::default()
+        };
+        install_with_git(&manifest_path, &options, git_ops.clone())?;
+        println!("{} {}", "Unlinked".green(), key);
+    }
+
+    Ok(())
+}
+
+fn absolute(manifest_path: &Path) -> Result<PathBuf> {
+    Ok(if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    })
+}
+
+fn project_bundle_dir(manifest_path: &Path) -> Result<PathBuf> {
+    Ok(manifest_path
+        .parent()
+        .context("Invalid manifest path")?
+        .join(BUNDLE_DIR))
+}
+
+/// Name of the bundle a working copy provides: the name in its bundle.toml, or
+/// else its directory name
+fn bundle_name(working_copy: &Path) -> Result<String> {
+    if has_manifest(working_copy) {
+        if let Some(name) = load_manifest(&working_copy.join("bundle.toml"))?.name {
+            return Ok(name);
+        }
+    }
+
+    working_copy
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .with_context(|| {
+            format!(
+                "Failed to tell the bundle {} provides; name it with --bundle",
+                working_copy.display()
+            )
+        })
+}
+
+/// Deletes an installed bundle, or the link to a working copy, which is left alone
+fn remove_installed(target: &Path) -> Result<()> {
+    if target.is_symlink() {
+        // Links to directories are files on Unix and directories on Windows
+        return fs::remove_file(target)
+            .or_else(|_| fs::remove_dir(target))
+            .with_context(|| format!("Failed to remove {}", target.display()));
+    }
+
+    let parent = target.parent().context("Invalid bundle path")?;
+    let name = target
+        .file_name()
+        .context("Invalid bundle path")?
+        .to_string_lossy();
+    remove_installed_bundle(parent, &name)
+}
@@ -0,0 +1,178 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::config::load_manifest;
+use crate::date::days_to_civil;
+use crate::filter::{subdirectory_patterns, PathFilter, PathPatterns};
+use crate::flatten::flattened_source;
+use crate::git::{git_operations_for, GitOperations};
+use crate::settings::Settings;
+use crate::types::{BundleDependency, CommitSummary, BUNDLE_DIR};
+
+/// Commits shown when `-n` isn't given
+pub const DEFAULT_COUNT: usize = 10;
+
+/// A commit of a bundle's history
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub commit: CommitSummary,
+    /// False if the commit only changed files the bundle doesn't install (outside
+    /// its `path`, `include` or `exclude` selection)
+    pub touches_installed: bool,
+}
+
+/// Executes the log command with the default git operations
+pub fn execute(
+    manifest_path: &Path,
+    bundle_name: &str,
+    count: usize,
+    settings: &Settings,
+) -> Result<()> {
+    let git_ops = git_operations_for(settings);
+    execute_with_git(manifest_path, bundle_name, count, git_ops)
+}
+
+/// Executes the log command with a custom GitOperations implementation
+/// This enables dependency injection for testing
+pub fn execute_with_git(
+    manifest_path: &Path,
+    bundle_name: &str,
+    count: usize,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<()> {
+    let (entries, filtered) = collect_log(manifest_path, bundle_name, count, git_ops)?;
+
+    if entries.is_empty() {
+        println!("{}", "No commits.".dimmed());
+        return Ok(());
+    }
+
+    for entry in &entries {
+        let commit = &entry.commit;
+        let line = format!(
+            "{} {} {} {}",
+            commit.id.chars().take(7).collect::<String>().yellow(),
+            format_date(commit.time),
+            commit.author.bold(),
+            commit.summary
+        );
+
+        if entry.touches_installed {
+            println!("{}", line);
+        } else {
+            println!("{} {}", line.dimmed(), "(no installed files)".dimmed());
+        }
+    }
+
+    if filtered {
+        println!();
+        println!(
+            "{}",
+            "Dimmed commits only changed files outside the bundle's include filter.".dimmed()
+        );
+    }
+
+    Ok(())
+}
+
+/// Reads the latest `count` commits of an installed bundle. The flag is true if
+/// the bundle installs only part of its repository, so commits may not touch it.
+pub fn collect_log(
+    manifest_path: &Path,
+    bundle_name: &str,
+    count: usize,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<(Vec<LogEntry>, bool)> {
+    let manifest_path = if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    };
+
+    let manifest = load_manifest(&manifest_path)?;
+    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+
+    let dependency = manifest.bundles.get(bundle_name).with_context(|| {
+        format!(
+            "Bundle '{}' not found in manifest. Available bundles: {:?}",
+            bundle_name,
+            manifest.bundles.keys().collect::<Vec<_>>()
+        )
+    })?;
+
+    // A flattened bundle's history is that of its checkout
+    let bundle_path = parent_dir.join(BUNDLE_DIR).join(bundle_name);
+    let source = flattened_source(&bundle_path);
+    let repo_path = source
+        .as_ref()
+        .map_or(bundle_path.clone(), |source| source.checkout.clone());
+
+    if !bundle_path.exists() {
+        anyhow::bail!(
+            "Bundle '{}' is not installed. Run 'fpm install' first.",
+            bundle_name
+        );
+    }
+    if !git_ops.is_repository(&repo_path) {
+        anyhow::bail!(
+            "Bundle '{}' has no commit history (it isn't installed as a git clone)",
+            bundle_name
+        );
+    }
+
+    let filter = match &source {
+        Some(source) => Some(subdirectory_filter(&source.subdirectory, dependency)?),
+        None => PathPatterns::of(dependency)
+            .map(|patterns| PathFilter::new(&patterns))
+            .transpose()?,
+    };
+
+    let commits = git_ops
+        .log(&repo_path, count)
+        .with_context(|| format!("Failed to read the history of bundle: {}", bundle_name))?;
+
+    let entries = commits
+        .into_iter()
+        .map(|commit| LogEntry {
+            touches_installed: filter.as_ref().is_none_or(|filter| {
+                commit
+                    .files
+                    .iter()
+                    .any(|file| filter.is_included(Path::new(file)))
+            }),
+            commit,
+        })
+        .collect();
+
+    Ok((entries, filter.is_some()))
+}
+
+/// Filter selecting the files of a bundle installed from a subdirectory, by their
+/// path in the repository
+fn subdirectory_filter(subdirectory: &Path, dependency: &BundleDependency) -> Result<PathFilter> {
+    let (include, exclude) = subdirectory_patterns(subdirectory, dependency);
+    PathFilter::new(&PathPatterns {
+        include: &include,
+        exclude: &exclude,
+    })
+}
+
+/// Formats a commit time (seconds since the Unix epoch) as a UTC date, e.g. 2025-10-09
+fn format_date(time: i64) -> String {
+    let (year, month, day) = days_to_civil(time.div_euclid(86400));
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(1_760_000_000), "2025-10-09");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+    }
+}
@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::io::{BufRead, IsTerminal};
+use std::path::Path;
+
+use crate::auth::{
+    delete_credential, host_token_var, parse_host, ssh_key_account, store_credential,
+};
+use crate::ssh::{inspect_key, PASSPHRASE_ENV};
+
+/// A secret `fpm login` stores: a token for a host, or an SSH key passphrase
+struct Credential {
+    /// Keychain account the secret is stored under
+    account: String,
+    /// What the secret is, e.g. "token for github.com"
+    description: String,
+    /// Environment variable that takes precedence over the stored secret
+    env_var: String,
+}
+
+impl Credential {
+    fn new(host: Option<&str>, ssh_key: Option<&Path>) -> Result<Self> {
+        if let Some(key) = ssh_key {
+            return Ok(Credential {
+                account: ssh_key_account(key),
+                description: format!("passphrase for {}", key.display()),
+                env_var: PASSPHRASE_ENV.to_string(),
+            });
+        }
+
+        let host = parse_host(host.context("Give a host or --ssh-key")?)?;
+        Ok(Credential {
+            account: host.clone(),
+            description: format!("token for {}", host),
+            env_var: host_token_var(&host),
+        })
+    }
+}
+
+/// Asks for a token for `host`, or the passphrase of `ssh_key`, (or reads it from
+/// stdin when that isn't a terminal) and stores it in the system keychain
+pub fn login(host: Option<&str>, ssh_key: Option<&Path>) -> Result<()> {
+    if let Some(key) = ssh_key {
+        let info = inspect_key(key)
+            .with_context(|| format!("Failed to read SSH private key: {}", key.display()))?;
+        if !info.encrypted {
+            anyhow::bail!("SSH key {} is not protected by a passphrase", key.display());
+        }
+    }
+    let credential = Credential::new(host, ssh_key)?;
+
+    let secret = if std::io::stdin().is_terminal() {
+        let mut prompt = credential.description.clone();
+        prompt[..1].make_ascii_uppercase();
+        rpassword::prompt_password(format!("{}: ", prompt))
+            .with_context(|| format!("Failed to read the {}", credential.description))?
+    } else {
+        let mut line = String::new();
+        std::io::stdin()
+            .lock()
+            .read_line(&mut line)
+            .with_context(|| format!("Failed to read the {} from stdin", credential.description))?;
+        line
+    };
+    // Passphrases may start or end with spaces, tokens don't
+    let secret = match ssh_key {
+        Some(_) => secret.trim_end_matches(['\r', '\n']),
+        None => secret.trim(),
+    };
+    if secret.trim().is_empty() {
+        anyhow::bail!("No {} given", credential.description);
+    }
+
+    store_credential(&credential.account, secret)?;
+    println!(
+        "{} {} in the system keychain",
+        "Stored".green(),
+        credential.description
+    );
+    if std::env::var_os(&credential.env_var).is_some() {
+        println!(
+            "  {} {} is set and takes precedence over the stored {}",
+            "Note:".cyan(),
+            credential.env_var,
+            credential.description
+        );
+    }
+
+    Ok(())
+}
+
+/// Removes the token stored for `host`, or the passphrase stored for `ssh_key`,
+/// from the system keychain
+pub fn logout(host: Option<&str>, ssh_key: Option<&Path>) -> Result<()> {
+    let credential = Credential::new(host, ssh_key)?;
+
+    if delete_credential(&credential.account)? {
+        println!("{} {}", "Removed".green(), credential.description);
+    } else {
+        println!("{} No {} stored", "Note:".cyan(), credential.description);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::cache::list_mirrors;
+use crate::flatten::CHECKOUT_DIR;
+use crate::git::{git_operations_for, GitOperations};
+use crate::locking::lock_project;
+use crate::settings::Settings;
+use crate::types::BUNDLE_DIR;
+
+/// Executes the maintain command with the default git operations
+pub fn execute(manifest_path: &Path, include_cache: bool, settings: &Settings) -> Result<()> {
+    let git_ops = git_operations_for(settings);
+    execute_with_git(manifest_path, include_cache, settings, git_ops)
+}
+
+/// Executes the maintain command with a custom GitOperations implementation
+/// This enables dependency injection for testing
+pub fn execute_with_git(
+    manifest_path: &Path,
+    include_cache: bool,
+    settings: &Settings,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<()> {
+    let manifest_path = if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    };
+
+    let _lock = lock_project(&manifest_path, settings)?;
+
+    println!(
+        "{} {}",
+        "Maintaining bundles for".cyan(),
+        manifest_path.display()
+    );
+
+    let mut failures = 0;
+
+    for repo_path in collect_installed_repositories(&manifest_path, git_ops.as_ref())? {
+        failures += maintain_one(git_ops.as_ref(), &repo_path, false);
+    }
+
+    if include_cache {
+        for mirror_path in list_mirrors(settings)? {
+            // Clones borrow objects from mirrors, so unreachable objects must be kept
+            failures += maintain_one(git_ops.as_ref(), &mirror_path, true);
+        }
+    }
+
+    println!();
+    if failures > 0 {
+        anyhow::bail!("Maintenance failed for {} repositories", failures);
+    }
+
+    println!("{}", "Maintenance complete.".green().bold());
+    Ok(())
+}
+
+/// Runs maintenance on a single repository, returning 1 on failure so callers can count
+fn maintain_one(git_ops: &dyn GitOperations, path: &Path, keep_unreachable: bool) -> usize {
+    match git_ops.maintain_repository(path, keep_unreachable) {
+        Ok(()) => {
+            println!("  {} {}", "✓".green(), path.display());
+            0
+        }
+        Err(e) => {
+            println!("  {} {}: {:#}", "Failed".red(), path.display(), e);
+            1
+        }
+    }
+}
+
+/// Collects every installed bundle clone below the manifest, including nested bundles
+pub fn collect_installed_repositories(
+    manifest_path: &Path,
+    git_ops: &dyn GitOperations,
+) -> Result<Vec<PathBuf>> {
+    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+
+    let mut repositories = Vec::new();
+    collect_repositories_in(git_ops, &parent_dir.join(BUNDLE_DIR), &mut repositories)?;
+
+    Ok(repositories)
+}
+
+fn collect_repositories_in(
+    git_ops: &dyn GitOperations,
+    bundle_dir: &Path,
+    repositories: &mut Vec<PathBuf>,
+) -> Result<()> {
+    if !bundle_dir.exists() {
+        return Ok(());
+    }
+
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(bundle_dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let hidden = path
+            .file_name()
+            .map(|n| n.to_string_lossy().starts_with('.'))
+            .unwrap_or(true);
+        // Hidden directories are fpm's own; links point at local bundles it doesn't own
+        if hidden || path.is_symlink() {
+            continue;
+        }
+
+        if git_ops.is_repository(&path) {
+            repositories.push(path.clone());
+        }
+
+        collect_repositories_in(git_ops, &path.join(BUNDLE_DIR), repositories)?;
+    }
+
+    // Checkouts that flattened bundles were copied from
+    let checkouts = bundle_dir.join(CHECKOUT_DIR);
+    if checkouts.is_dir() {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(&checkouts)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| git_ops.is_repository(path))
+            .collect();
+        paths.sort();
+        repositories.extend(paths);
+    }
+
+    Ok(())
+}
@@ -1,4 +1,33 @@
+pub mod add;
+pub mod adopt;
+pub mod cache;
+pub mod commit;
+pub mod completions;
+pub mod config;
+pub mod diff;
+pub mod doctor;
+pub mod env;
+pub mod fetch;
+pub mod graph;
+pub mod info;
 pub mod install;
+pub mod licenses;
+pub mod link;
+pub mod log;
+pub mod login;
+pub mod maintain;
+pub mod outdated;
 pub mod publish;
 pub mod push;
+pub mod remove;
+pub mod run;
+pub mod search;
+pub mod self_update;
+pub mod sign;
 pub mod status;
+pub mod tree;
+pub mod update;
+pub mod validate;
+pub mod verify;
+pub mod why;
+pub mod yank;
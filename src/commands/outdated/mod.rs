@@ -0,0 +1,337 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::config::load_manifest_with_settings;
+use crate::git::{git_operations_for, GitOperations};
+use crate::lockfile::lock_key;
+use crate::resolve::{latest_tag, select_tag, tracked_branch};
+use crate::settings::Settings;
+use crate::source::source_for;
+use crate::state::load_state;
+use crate::types::{BundleDependency, BUNDLE_DIR};
+
+/// Length of abbreviated commit hashes in the table
+const SHORT_COMMIT_LEN: usize = 7;
+
+/// A branch or tag, with the commit it points at when known
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleRef {
+    pub name: String,
+    pub commit: Option<String>,
+}
+
+/// Installed and available refs of one bundle
+#[derive(Debug, Clone)]
+pub struct OutdatedEntry {
+    /// Install path of the bundle, e.g. "ui-kit/base-styles"
+    pub key: String,
+    pub depth: usize,
+    /// What is installed (None if the bundle isn't installed)
+    pub current: Option<BundleRef>,
+    /// Newest ref the manifest allows (highest tag matching the version requirement,
+    /// or the branch tip)
+    pub wanted: Option<BundleRef>,
+    /// Newest ref available (highest released tag, or the branch tip)
+    pub latest: Option<BundleRef>,
+    /// Bundle is pinned to a commit with `rev` and never moves on its own
+    pub pinned: bool,
+}
+
+impl OutdatedEntry {
+    /// Returns true if a newer ref than the installed one is available
+    pub fn is_outdated(&self) -> bool {
+        if self.pinned {
+            return false;
+        }
+
+        let Some(latest) = &self.latest else {
+            return false;
+        };
+        let Some(current) = &self.current else {
+            return true;
+        };
+
+        // Branch tips are compared by commit, tags by name
+        match (&current.commit, &latest.commit) {
+            (Some(current_commit), Some(latest_commit)) if current.name == latest.name => {
+                current_commit != latest_commit
+            }
+            _ => current.name != latest.name,
+        }
+    }
+}
+
+/// Executes the outdated command with the default git operations.
+/// Fails if any bundle is outdated, so it can gate CI.
+pub fn execute(manifest_path: &Path, settings: &Settings) -> Result<()> {
+    let git_ops = git_operations_for(settings);
+    let entries = execute_with_git(manifest_path, settings, git_ops)?;
+
+    let outdated = entries.iter().filter(|e| e.is_outdated()).count();
+    if outdated > 0 {
+        anyhow::bail!(
+            "{} bundle(s) are outdated. Run 'fpm update' to update them.",
+            outdated
+        );
+    }
+
+    Ok(())
+}
+
+/// Executes the outdated command with a custom GitOperations implementation
+/// This enables dependency injection for testing
+pub fn execute_with_git(
+    manifest_path: &Path,
+    settings: &Settings,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<Vec<OutdatedEntry>> {
+    let manifest_path = if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    };
+
+    println!(
+        "{} {}",
+        "Checking for newer bundle versions in".cyan(),
+        manifest_path.display()
+    );
+    println!();
+
+    let mut entries = Vec::new();
+    collect_entries(
+        git_ops.as_ref(),
+        settings,
+        &manifest_path,
+        "",
+        0,
+        &mut entries,
+    )?;
+
+    if entries.is_empty() {
+        println!("{}", "No bundles found.".yellow());
+        return Ok(entries);
+    }
+
+    println!(
+        "{:<30} {:<20} {:<20} {}",
+        "BUNDLE".bold(),
+        "CURRENT".bold(),
+        "WANTED".bold(),
+        "LATEST".bold()
+    );
+    println!("{}", "-".repeat(90));
+
+    for entry in &entries {
+        let indent = "  ".repeat(entry.depth);
+        let name = entry.key.rsplit('/').next().unwrap_or(&entry.key);
+        let latest = if entry.pinned {
+            "pinned (rev)".to_string().blue()
+        } else if entry.is_outdated() {
+            describe(entry.latest.as_ref()).yellow()
+        } else {
+            describe(entry.latest.as_ref()).green()
+        };
+
+        println!(
+            "{}{:<30} {:<20} {:<20} {}",
+            indent,
+            name,
+            describe(entry.current.as_ref()),
+            describe(entry.wanted.as_ref()),
+            latest
+        );
+    }
+
+    println!();
+    let outdated = entries.iter().filter(|e| e.is_outdated()).count();
+    if outdated == 0 {
+        println!("{}", "All bundles are up to date.".green().bold());
+    } else {
+        println!(
+            "{} of {} bundle(s) outdated",
+            outdated.to_string().yellow(),
+            entries.len()
+        );
+    }
+
+    Ok(entries)
+}
+
+/// Collects entries for the bundles of a manifest, recursing into installed nested bundles
+fn collect_entries(
+    git_ops: &dyn GitOperations,
+    settings: &Settings,
+    manifest_path: &Path,
+    prefix: &str,
+    depth: usize,
+    entries: &mut Vec<OutdatedEntry>,
+) -> Result<()> {
+    let manifest = load_manifest_with_settings(manifest_path, settings)?;
+    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+    let bundle_dir = parent_dir.join(BUNDLE_DIR);
+    let state = load_state(&bundle_dir).unwrap_or_default();
+
+    let mut names: Vec<&String> = manifest.bundles.keys().collect();
+    names.sort();
+
+    for name in names {
+        let dependency = &manifest.bundles[name];
+        let key = lock_key(prefix, name);
+
+        // Only bundles pinned in the lockfile have newer revisions to look for
+        if !source_for(dependency, git_ops).is_locked() {
+            continue;
+        }
+
+        let current = state.bundles.get(name).map(|recorded| BundleRef {
+            name: recorded.branch.clone(),
+            commit: recorded.rev.clone(),
+        });
+        let (wanted, latest) = available_refs(git_ops, dependency)
+            .with_context(|| format!("Failed to check bundle: {}", key))?;
+
+        entries.push(OutdatedEntry {
+            key: key.clone(),
+            depth,
+            current,
+            wanted,
+            latest,
+            pinned: dependency.rev.is_some(),
+        });
+
+        let nested_manifest_path = bundle_dir.join(name).join("bundle.toml");
+        if nested_manifest_path.exists() {
+            collect_entries(
+                git_ops,
+                settings,
+                &nested_manifest_path,
+                &key,
+                depth + 1,
+                entries,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Looks up the wanted and latest refs of a dependency on its remote
+fn available_refs(
+    git_ops: &dyn GitOperations,
+    dependency: &BundleDependency,
+) -> Result<(Option<BundleRef>, Option<BundleRef>)> {
+    let ssh_key = dependency.ssh_key.as_deref();
+
+    match dependency.version_requirement() {
+        Some(version) => {
+            let tags = git_ops.list_remote_tags(&dependency.git, ssh_key)?;
+            let as_ref = |name: String| BundleRef { name, commit: None };
+
+            Ok((
+                select_tag(&tags, version, dependency.allow_prerelease)
+                    .ok()
+                    .map(as_ref),
+                latest_tag(&tags, dependency.allow_prerelease).map(as_ref),
+            ))
+        }
+        None => {
+            let branch = tracked_branch(git_ops, dependency);
+            let tip = git_ops
+                .remote_branch_commit(&dependency.git, &branch, ssh_key)?
+                .map(|commit| BundleRef {
+                    name: branch,
+                    commit: Some(commit),
+                });
+
+            Ok((tip.clone(), tip))
+        }
+    }
+}
+
+/// Formats a ref as `name` or `name (short commit)`
+fn describe(bundle_ref: Option<&BundleRef>) -> String {
+    match bundle_ref {
+        None => "-".to_string(),
+        Some(BundleRef { name, commit: None }) => name.clone(),
+        Some(BundleRef {
+            name,
+            commit: Some(commit),
+        }) => {
+            let short = commit.get(..SHORT_COMMIT_LEN).unwrap_or(commit);
+            format!("{} ({})", name, short)
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn bundle_ref(name: &str, commit: Option<&str>) -> Option<BundleRef> {
+        Some(BundleRef {
+            name: name.to_string(),
+            commit: commit.map(str::to_string),
+        })
+    }
+
+    fn entry(current: Option<BundleRef>, latest: Option<BundleRef>) -> OutdatedEntry {
+        OutdatedEntry {
+            key: "icons".to_string(),
+            depth: 0,
+            current,
+            wanted: latest.clone(),
+            latest,
+            pinned: false,
+        }
+    }
+
+    #[test]
+    fn test_is_outdated_compares_tags_and_branch_tips() {
+        // Tags compare by name, even though the installed commit is known
+        assert!(!entry(
+            bundle_ref("v1.0.0", Some("aaa")),
+            bundle_ref("v1.0.0", None)
+        )
+        .is_outdated());
+        assert!(entry(
+            bundle_ref("v1.0.0", Some("aaa")),
+            bundle_ref("v1.1.0", None)
+        )
+        .is_outdated());
+
+        // Branches compare by commit
+        assert!(!entry(
+            bundle_ref("main", Some("aaa")),
+            bundle_ref("main", Some("aaa"))
+        )
+        .is_outdated());
+        assert!(entry(
+            bundle_ref("main", Some("aaa")),
+            bundle_ref("main", Some("bbb"))
+        )
+        .is_outdated());
+
+        // Not installed yet
+        assert!(entry(None, bundle_ref("main", Some("bbb"))).is_outdated());
+
+        let mut pinned = entry(
+            bundle_ref("main", Some("aaa")),
+            bundle_ref("main", Some("bbb")),
+        );
+        pinned.pinned = true;
+        assert!(!pinned.is_outdated());
+    }
+
+    #[test]
+    fn test_describe_shortens_commits() {
+        assert_eq!(describe(None), "-");
+        assert_eq!(describe(bundle_ref("v1.0.0", None).as_ref()), "v1.0.0");
+        assert_eq!(
+            describe(bundle_ref("main", Some("0123456789abcdef")).as_ref()),
+            "main (0123456)"
+        );
+    }
+}
@@ -3,19 +3,84 @@ use colored::Colorize;
 use std::path::Path;
 use std::sync::Arc;
 
-use crate::config::load_manifest;
-use crate::git::{init_bundle_for_publish, GitCliOperations, GitOperations};
-use crate::types::{DEFAULT_BRANCH, DEFAULT_REMOTE};
-
-/// Executes the publish command with the default GitCliOperations
-pub fn execute(manifest_path: &Path) -> Result<()> {
-    let git_ops = Arc::new(GitCliOperations::new());
-    execute_with_git(manifest_path, git_ops)
+use crate::config::load_manifest_with_settings;
+use crate::forge::create_repository;
+use crate::git::{git_operations_for, init_bundle_for_publish, CommitAuthor, GitOperations};
+use crate::oci::{push_bundle, OciReference};
+use crate::settings::Settings;
+use crate::types::{BundleManifest, DEFAULT_BRANCH, DEFAULT_REMOTE};
+
+/// Options controlling a publish
+#[derive(Debug, Clone, Default)]
+pub struct PublishOptions {
+    /// Upload the root directory to this OCI registry reference instead of pushing it
+    pub oci: Option<String>,
+    /// Publish even if the version is already tagged upstream, moving the tag
+    pub force: bool,
+    /// Create the remote repositories through their host's API first
+    pub create_remote: bool,
+    /// Create the remote repositories as private
+    pub private: bool,
+    /// Settings from the config files and the environment
+    pub settings: Settings,
+}
+
+/// Executes the publish command with the default git operations, or publishes to
+/// the OCI registry reference of the options if given
+pub fn execute(manifest_path: &Path, options: &PublishOptions) -> Result<()> {
+    if let Some(reference) = &options.oci {
+        return publish_to_registry(manifest_path, reference, &options.settings);
+    }
+
+    let git_ops = git_operations_for(&options.settings);
+    execute_with_git(manifest_path, options, git_ops)
+}
+
+/// Uploads the root directory of a source bundle as an OCI artifact
+pub fn publish_to_registry(
+    manifest_path: &Path,
+    reference: &str,
+    settings: &Settings,
+) -> Result<()> {
+    let reference = OciReference::parse(reference)?;
+    let manifest = load_manifest_with_settings(manifest_path, settings)?;
+    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+
+    let Some(root) = &manifest.root else {
+        println!(
+            "{}",
+            "This bundle.toml has no 'root' defined. Nothing to publish.".yellow()
+        );
+        return Ok(());
+    };
+
+    let root_dir = parent_dir.join(root);
+    if !root_dir.exists() {
+        anyhow::bail!(
+            "Root directory '{}' does not exist. Cannot publish.",
+            root_dir.display()
+        );
+    }
+
+    println!(
+        "  {} {} to {}",
+        "Publishing".green(),
+        root_dir.display(),
+        reference
+    );
+    let digest = push_bundle(&reference, &root_dir)?;
+    println!("  {} {}@{}", "✓ Published".green(), reference, digest);
+
+    Ok(())
 }
 
 /// Executes the publish command with a custom GitOperations implementation
 /// This enables dependency injection for testing
-pub fn execute_with_git(manifest_path: &Path, git_ops: Arc<dyn GitOperations>) -> Result<()> {
+pub fn execute_with_git(
+    manifest_path: &Path,
+    options: &PublishOptions,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<()> {
     let manifest_path = if manifest_path.is_relative() {
         std::env::current_dir()?.join(manifest_path)
     } else {
@@ -28,7 +93,7 @@ pub fn execute_with_git(manifest_path: &Path, git_ops: Arc<dyn GitOperations>) -
         manifest_path.display()
     );
 
-    let manifest = load_manifest(&manifest_path)?;
+    let manifest = load_manifest_with_settings(&manifest_path, &options.settings)?;
     let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
 
     // Check if this is a source bundle
@@ -55,29 +120,126 @@ pub fn execute_with_git(manifest_path: &Path, git_ops: Arc<dyn GitOperations>) -
         return Ok(());
     }
 
-    // Find the remote URL from bundles (self-reference pattern)
-    // For a source bundle to be publishable, we need to know where to push
-    // This could be stored in a separate field or inferred
-    let remote_url = get_publish_remote(&manifest_path, git_ops.as_ref())?;
+    let branch = manifest.publish_branch.as_deref().unwrap_or(DEFAULT_BRANCH);
+    let mut targets = publish_targets(&manifest, &manifest_path, &root_dir, git_ops.as_ref())?;
+    let target_count = targets.len();
+
+    if options.create_remote {
+        for target in &targets {
+            create_repository(
+                &target.url,
+                manifest.description.as_deref(),
+                options.private,
+            )?;
+            println!("  {} {}", "Created remote".green(), target.url);
+        }
+    }
+
+    // A mirror whose versions can't be checked is reported and skipped, while the
+    // primary remote must be reachable
+    let mut failed = Vec::new();
+    if let Some(version) = &manifest.version {
+        let mut reachable = Vec::new();
+        for (index, target) in targets.into_iter().enumerate() {
+            let tags = match git_ops.list_remote_tags(&target.url, None) {
+                Ok(tags) => tags,
+                Err(e) if index > 0 => {
+                    println!("  {} {}: {:#}", "Failed".red(), target.url, e);
+                    failed.push(target.url);
+                    continue;
+                }
+                Err(e) => {
+                    return Err(e.context(format!(
+                        "Failed to check the published versions of {}",
+                        target.url
+                    )))
+                }
+            };
+            check_unpublished(&tags, &target.url, version, options.force)?;
+            reachable.push(target);
+        }
+        targets = reachable;
+    }
 
-    publish_bundle(
+    let author = options
+        .settings
+        .author
+        .as_deref()
+        .map(CommitAuthor::parse)
+        .transpose()?;
+    failed.extend(publish_bundle(
         git_ops.as_ref(),
         &root_dir,
-        &remote_url,
-        &manifest.fpm_version,
-    )?;
+        &targets,
+        branch,
+        manifest.version.as_deref(),
+        author.as_ref(),
+        options.force,
+    )?);
 
+    if !failed.is_empty() {
+        anyhow::bail!(
+            "Failed to publish to {} of {} remote(s): {}",
+            failed.len(),
+            target_count,
+            failed.join(", ")
+        );
+    }
     println!("{}", "Published successfully!".green().bold());
     Ok(())
 }
 
-fn get_publish_remote(manifest_path: &Path, git_ops: &dyn GitOperations) -> Result<String> {
-    // Try to read the remote from git config if already initialized
+/// A repository `fpm publish` pushes to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublishTarget {
+    /// Name of its remote in the root directory's repository
+    pub remote: String,
+    pub url: String,
+}
+
+/// The repositories a source bundle is published to: the manifest's
+/// `publish_url`, or else the remote its root directory was set up with, followed
+/// by its `publish_mirrors`
+pub fn publish_targets(
+    manifest: &BundleManifest,
+    manifest_path: &Path,
+    root_dir: &Path,
+    git_ops: &dyn GitOperations,
+) -> Result<Vec<PublishTarget>> {
+    let remote_url = match &manifest.publish_url {
+        Some(url) => url.clone(),
+        None => get_publish_remote(manifest_path, root_dir, git_ops)?,
+    };
+    let mut targets = vec![PublishTarget {
+        remote: DEFAULT_REMOTE.to_string(),
+        url: remote_url,
+    }];
+    for (index, url) in manifest.publish_mirrors.iter().enumerate() {
+        targets.push(PublishTarget {
+            remote: format!("{}-mirror-{}", DEFAULT_REMOTE, index + 1),
+            url: url.clone(),
+        });
+    }
+    Ok(targets)
+}
+
+fn get_publish_remote(
+    manifest_path: &Path,
+    root_dir: &Path,
+    git_ops: &dyn GitOperations,
+) -> Result<String> {
+    // Try to read the remote from git config if already initialized.
+    // The root directory carries the fpm remote once it has been set up for
+    // publishing (e.g. by `fpm adopt`); otherwise fall back to the manifest's repository.
     let parent = manifest_path.parent().context("Invalid manifest path")?;
 
-    if git_ops.is_repository(parent) {
+    for dir in [root_dir, parent] {
+        if !git_ops.is_repository(dir) {
+            continue;
+        }
+
         // Try to get the fpm remote URL
-        if let Ok(repo) = git2::Repository::open(parent) {
+        if let Ok(repo) = git2::Repository::open(dir) {
             if let Ok(remote) = repo.find_remote(DEFAULT_REMOTE) {
                 if let Some(url) = remote.url() {
                     return Ok(url.to_string());
@@ -94,30 +256,121 @@ fn get_publish_remote(manifest_path: &Path, git_ops: &dyn GitOperations) -> Resu
 
     anyhow::bail!(
         "No remote URL configured for publishing. \
-        Please initialize the bundle with a git remote or add a 'publish_url' field."
+        Set 'publish_url' in bundle.toml or initialize the bundle with a git remote."
     )
 }
 
+/// Fails if `version` is among the `tags` of the remote, so a released version is
+/// never silently replaced, unless `force` is set
+fn check_unpublished(tags: &[String], remote_url: &str, version: &str, force: bool) -> Result<()> {
+    let tag = format!("v{}", version);
+    let Some(existing) = tags.iter().find(|t| **t == tag || *t == version) else {
+        return Ok(());
+    };
+
+    if !force {
+        anyhow::bail!(
+            "Version {} is already published to {} (tag {}). \
+            Bump 'version' in bundle.toml, or pass --force to publish it anyway.",
+            version,
+            remote_url,
+            existing
+        );
+    }
+    println!(
+        "{}",
+        format!(
+            "Version {} is already published; --force moves its tag {}.",
+            version, existing
+        )
+        .yellow()
+    );
+    Ok(())
+}
+
+/// Commits and tags the root directory, and pushes it to every target, the first
+/// being the primary one. A failed push doesn't stop the pushes to the other
+/// targets; returns the URLs of those that failed.
 fn publish_bundle(
     git_ops: &dyn GitOperations,
     root_dir: &Path,
-    remote_url: &str,
-    version: &str,
-) -> Result<()> {
-    println!("  {} {}", "Publishing".green(), root_dir.display());
+    targets: &[PublishTarget],
+    branch: &str,
+    version: Option<&str>,
+    author: Option<&CommitAuthor>,
+    force: bool,
+) -> Result<Vec<String>> {
+    println!(
+        "  {} {} ({})",
+        "Publishing".green(),
+        root_dir.display(),
+        branch
+    );
 
-    // Initialize git if needed
-    init_bundle_for_publish(git_ops, root_dir, remote_url)?;
+    // Initialize git if needed, with a remote for every repository published to
+    let (primary, mirrors) = targets
+        .split_first()
+        .context("No remote URL to publish to")?;
+    init_bundle_for_publish(git_ops, root_dir, &primary.url)?;
+    for mirror in mirrors {
+        git_ops.add_remote(root_dir, &mirror.remote, &mirror.url)?;
+    }
 
-    // Commit all changes
-    let commit_message = format!("fpm publish v{}", version);
-    git_ops.commit_all(root_dir, &commit_message)?;
+    // Commit all changes, and tag the commit with the version so consumers can
+    // install it by version
+    let tag = version.map(|version| format!("v{}", version));
+    let commit_message = match &tag {
+        Some(tag) => format!("fpm publish {}", tag),
+        None => "fpm publish".to_string(),
+    };
+    git_ops.commit_all(root_dir, &commit_message, author)?;
+    if let Some(tag) = &tag {
+        git_ops.create_tag(root_dir, tag, &commit_message, author, force)?;
+    }
 
-    // Push to remote
-    git_ops.push(root_dir, DEFAULT_REMOTE, DEFAULT_BRANCH)?;
+    // Push to every remote, the tag after the branch it is on
+    let mut failed = Vec::new();
+    for PublishTarget { remote, url } in targets {
+        let pushed = git_ops
+            .push(root_dir, remote, branch)
+            .and_then(|()| match &tag {
+                Some(tag) => git_ops.push_tag(root_dir, remote, tag, force),
+                None => Ok(()),
+            });
+        match pushed {
+            Ok(()) => println!(
+                "  {} {} to {}",
+                "✓ Published".green(),
+                tag.as_deref().unwrap_or(branch),
+                url
+            ),
+            Err(e) => {
+                println!("  {} {}: {:#}", "Failed".red(), url, e);
+                failed.push(url.clone());
+            }
+        }
+    }
 
-    println!("  {} v{}", "✓ Published".green(), version);
-    Ok(())
+    match &tag {
+        Some(_) if version.is_some_and(is_prerelease) => println!(
+            "{}",
+            "This is a pre-release: only bundles asking for its channel \
+            (e.g. version = \"1.2.0-beta\") or with allow_prerelease install it."
+                .yellow()
+        ),
+        Some(_) => {}
+        None => println!(
+            "{}",
+            "bundle.toml has no version, so no tag was created for it.".yellow()
+        ),
+    }
+
+    Ok(failed)
+}
+
+/// Whether `version` is a semantic version with a pre-release part (`1.2.0-beta.1`)
+fn is_prerelease(version: &str) -> bool {
+    semver::Version::parse(version).is_ok_and(|version| !version.pre.is_empty())
 }
 
 #[cfg(test)]
@@ -1,29 +1,93 @@
 use anyhow::{Context, Result};
 use colored::Colorize;
+use std::cell::{Cell, RefCell};
+use std::io::{BufRead, Write};
 use std::path::Path;
 use std::sync::Arc;
 
-use crate::config::{load_manifest, save_manifest};
-use crate::git::{GitCliOperations, GitOperations};
-use crate::types::{BundleManifest, BUNDLE_DIR, DEFAULT_BRANCH};
+use crate::config::{load_manifest, load_manifest_with_settings, save_manifest};
+use crate::flatten::{flattened_source, mirror, FlattenedSource};
+use crate::forge::{ForgeClient, PullRequest};
+use crate::git::{git_operations_for, CommitAuthor, GitOperations};
+use crate::graph::{find_cycle, source_identity, Ancestor};
+use crate::lockfile::lock_key;
+use crate::locking::lock_project;
+use crate::report::{self, BundleAction, BundleResult};
+use crate::settings::Settings;
+use crate::source::source_for;
+use crate::state::load_state;
+use crate::types::{BumpLevel, BundleManifest, InstallState, BUNDLE_DIR};
 
-/// Executes the push command with the default GitCliOperations
-pub fn execute(
-    manifest_path: &Path,
-    bundle_name: Option<&str>,
-    message: Option<&str>,
-) -> Result<()> {
-    let git_ops = Arc::new(GitCliOperations::new());
-    execute_with_git(manifest_path, bundle_name, message, git_ops)
+/// Commit message of pushes without a message or a configured template
+const DEFAULT_COMMIT_MESSAGE: &str = "fpm push: Update bundle";
+
+/// Options controlling a push
+#[derive(Debug, Clone, Default)]
+pub struct PushOptions {
+    /// Push this bundle (and the bundles nested in it) only
+    pub bundle: Option<String>,
+    /// Commit message; the configured template if not given
+    pub message: Option<String>,
+    /// Report what would be committed and pushed without doing it
+    pub dry_run: bool,
+    /// Part of the version to increment, overriding the bundles' `bump`
+    pub bump: Option<BumpLevel>,
+    /// Push to this new branch instead of the branch each bundle was installed from
+    pub branch: Option<String>,
+    /// Open a pull request from `branch` into the installed branch
+    pub pull_request: bool,
+    /// Show the changes of every bundle and ask whether to push it, and with which
+    /// commit message
+    pub interactive: bool,
+    /// Commit the changes, bumping versions as a push does, without pushing them
+    /// (`fpm commit`)
+    pub commit_only: bool,
+    /// Commit only the changes of these paths, relative to each bundle; every
+    /// change if empty
+    pub paths: Vec<String>,
+    /// Settings from the config files and the environment
+    pub settings: Settings,
+}
+
+/// What a push runs with: its options, and the answers of an interactive push
+struct PushContext<'a> {
+    options: &'a PushOptions,
+    input: RefCell<&'a mut dyn BufRead>,
+    /// Set when the user quits an interactive push; the bundles left are skipped
+    stopped: Cell<bool>,
+}
+
+/// Files of a bundle's diffstat listed when asking whether to push it
+const CONFIRM_FILES: usize = 10;
+
+/// Executes the push command with the default git operations
+pub fn execute(manifest_path: &Path, options: &PushOptions) -> Result<()> {
+    let git_ops = git_operations_for(&options.settings);
+    execute_with_git(manifest_path, options, git_ops)
 }
 
 /// Executes the push command with a custom GitOperations implementation
 /// This enables dependency injection for testing
 pub fn execute_with_git(
     manifest_path: &Path,
-    bundle_name: Option<&str>,
-    message: Option<&str>,
+    options: &PushOptions,
     git_ops: Arc<dyn GitOperations>,
+) -> Result<()> {
+    execute_with_input(
+        manifest_path,
+        options,
+        git_ops,
+        &mut std::io::stdin().lock(),
+    )
+}
+
+/// Executes the push command, reading the answers of an interactive push from
+/// `input`
+pub fn execute_with_input(
+    manifest_path: &Path,
+    options: &PushOptions,
+    git_ops: Arc<dyn GitOperations>,
+    input: &mut dyn BufRead,
 ) -> Result<()> {
     let manifest_path = if manifest_path.is_relative() {
         std::env::current_dir()?.join(manifest_path)
@@ -31,7 +95,54 @@ pub fn execute_with_git(
         manifest_path.to_path_buf()
     };
 
-    let manifest = load_manifest(&manifest_path)?;
+    if options.pull_request && options.branch.is_none() {
+        anyhow::bail!("--pr needs a new branch to push to, given with --branch");
+    }
+    if !options.paths.is_empty() && options.bundle.is_none() {
+        anyhow::bail!("--paths needs the bundle they belong to, given with --bundle");
+    }
+    if options.interactive && (options.dry_run || report::is_json()) {
+        anyhow::bail!("An interactive push can't be combined with --dry-run or JSON output");
+    }
+
+    let _lock = lock_project(&manifest_path, &options.settings)?;
+
+    let context = PushContext {
+        options,
+        input: RefCell::new(input),
+        stopped: Cell::new(false),
+    };
+    let mut stats = PushStats::default();
+    let result = push_bundles(&manifest_path, &context, git_ops, &mut stats);
+    if report::is_json() {
+        let command = if options.commit_only {
+            "commit"
+        } else {
+            "push"
+        };
+        report::print(
+            command,
+            &manifest_path,
+            stats.results,
+            result.as_ref().err(),
+        )?;
+        return result;
+    }
+    result?;
+    print_summary(&stats);
+
+    Ok(())
+}
+
+/// Pushes the bundles of a manifest (all of them, or the one the options name),
+/// counting the results in `stats`
+fn push_bundles(
+    manifest_path: &Path,
+    context: &PushContext,
+    git_ops: Arc<dyn GitOperations>,
+    stats: &mut PushStats,
+) -> Result<()> {
+    let manifest = load_manifest_with_settings(manifest_path, &context.options.settings)?;
     let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
     let bundle_dir = parent_dir.join(BUNDLE_DIR);
 
@@ -40,7 +151,7 @@ pub fn execute_with_git(
     }
 
     // Determine which bundles to push
-    let bundles_to_push: Vec<String> = if let Some(name) = bundle_name {
+    let bundles_to_push: Vec<String> = if let Some(name) = context.options.bundle.as_deref() {
         // Push specific bundle
         if !manifest.bundles.contains_key(name) {
             anyhow::bail!(
@@ -55,74 +166,208 @@ pub fn execute_with_git(
         manifest.bundles.keys().cloned().collect()
     };
 
-    let mut stats = PushStats::default();
+    let state = load_state(&bundle_dir)?;
 
     for name in bundles_to_push {
         let bundle_path = bundle_dir.join(&name);
 
+        let bundle_source = source_for(&manifest.bundles[&name], git_ops.as_ref());
+        if !bundle_source.can_push() {
+            report::progress(format_args!(
+                "  {} {} ({} bundle)",
+                "Skipping".yellow(),
+                name,
+                bundle_source.kind()
+            ));
+            stats.skip(&name, None);
+            continue;
+        }
+
         if !bundle_path.exists() {
-            println!("  {} {} (not installed)", "Skipping".yellow(), name);
-            stats.skipped += 1;
+            report::progress(format_args!(
+                "  {} {} (not installed)",
+                "Skipping".yellow(),
+                name
+            ));
+            stats.skip(&name, None);
             continue;
         }
 
-        if !git_ops.is_repository(&bundle_path) {
-            println!("  {} {} (not a git repository)", "Skipping".yellow(), name);
-            stats.skipped += 1;
+        let source = flattened_source(&bundle_path);
+        if !is_installed_repository(git_ops.as_ref(), &bundle_path, source.as_ref()) {
+            report::progress(format_args!(
+                "  {} {} (not a git repository)",
+                "Skipping".yellow(),
+                name
+            ));
+            stats.skip(&name, None);
             continue;
         }
 
+        let target = PushTarget {
+            url: manifest.bundles[&name].git.clone(),
+            branch: installed_branch(&state, &name, &manifest),
+            author: manifest.bundles[&name].author.clone(),
+            bump: manifest.bundles[&name].bump,
+        };
+
         // Push this bundle and all its nested bundles recursively
+        let identity = source_identity(&manifest.bundles[&name], parent_dir);
         push_bundle_recursive(
             git_ops.as_ref(),
             &name,
             &bundle_path,
-            message,
-            0,
-            &mut stats,
+            &target,
+            context,
+            &[(identity, name.clone())],
+            stats,
         );
     }
 
-    print_summary(&stats);
-
     Ok(())
 }
 
 #[derive(Default)]
 struct PushStats {
     pushed: u32,
+    /// Bundles committed, but not pushed
+    committed: u32,
+    /// Bundles a dry run would push
+    would_push: u32,
+    /// Bundles an interactive push left alone
+    declined: u32,
+    /// Version of every pushed (or committed) bundle, by install path
+    versions: Vec<(String, Option<String>)>,
+    /// URL of every pull request opened, by install path
+    pull_requests: Vec<(String, String)>,
     skipped: u32,
     auth_failed: u32,
     errors: u32,
+    /// Result of every bundle, keyed by its install path
+    results: report::BundleResults,
 }
 
-/// Recursively push a bundle and all its nested bundles
+impl PushStats {
+    /// Counts a bundle left as it was, at `commit` if known
+    fn skip(&mut self, key: &str, commit: Option<String>) {
+        self.skipped += 1;
+        self.results.record(BundleResult {
+            name: key.to_string(),
+            action: BundleAction::Skipped,
+            old_commit: commit.clone(),
+            new_commit: commit,
+            error: None,
+        });
+    }
+}
+
+/// Where and as whom the changes of a bundle are pushed
+struct PushTarget {
+    /// URL of the bundle's repository
+    url: String,
+    /// Branch to push to; None for bundles installed from a version tag
+    branch: Option<String>,
+    /// The bundle's own `author`, "Name <email>"
+    author: Option<String>,
+    /// The bundle's own `bump` level
+    bump: Option<BumpLevel>,
+}
+
+/// Branch a bundle was installed from: the recorded install state wins over the manifest,
+/// which may have been edited since the last install. None when the bundle was installed
+/// from a version tag, which has no branch to push to.
+fn installed_branch(state: &InstallState, name: &str, manifest: &BundleManifest) -> Option<String> {
+    match state.bundles.get(name) {
+        Some(recorded) if recorded.version.is_some() => None,
+        Some(recorded) => Some(recorded.branch.clone()),
+        None => {
+            let dependency = &manifest.bundles[name];
+            if dependency.version_requirement().is_some() {
+                None
+            } else {
+                Some(dependency.branch().to_string())
+            }
+        }
+    }
+}
+
+/// Returns true if the bundle's files are backed by a git repository: its own clone,
+/// or for a flattened bundle the checkout it was copied from
+fn is_installed_repository(
+    git_ops: &dyn GitOperations,
+    bundle_path: &Path,
+    source: Option<&FlattenedSource>,
+) -> bool {
+    match source {
+        Some(source) => git_ops.is_repository(&source.checkout),
+        None => git_ops.is_repository(bundle_path),
+    }
+}
+
+/// Recursively push a bundle and all its nested bundles. `key` is the install path
+/// of the bundle, e.g. `ui-kit/base-styles` for a nested bundle, and `ancestors`
+/// lists the bundles from the top level down to this one.
 fn push_bundle_recursive(
     git_ops: &dyn GitOperations,
-    name: &str,
+    key: &str,
     bundle_path: &Path,
-    message: Option<&str>,
-    depth: usize,
+    target: &PushTarget,
+    context: &PushContext,
+    ancestors: &[Ancestor],
     stats: &mut PushStats,
 ) {
-    let indent = "  ".repeat(depth + 1);
+    let indent = "  ".repeat(ancestors.len());
+    let name = key.rsplit('/').next().unwrap_or(key);
 
     // First, check for and push nested bundles
     let nested_manifest_path = bundle_path.join("bundle.toml");
     if nested_manifest_path.exists() {
-        if let Ok(nested_manifest) = crate::config::load_manifest(&nested_manifest_path) {
+        if let Ok(nested_manifest) =
+            load_manifest_with_settings(&nested_manifest_path, &context.options.settings)
+        {
             let nested_bundle_dir = bundle_path.join(BUNDLE_DIR);
+            let nested_state = load_state(&nested_bundle_dir).unwrap_or_default();
+
+            for (nested_name, nested_dependency) in &nested_manifest.bundles {
+                if !source_for(nested_dependency, git_ops).can_push() {
+                    continue;
+                }
+
+                // A bundle nested in itself is pushed where it first appears
+                let nested_key = lock_key(key, nested_name);
+                let identity = source_identity(nested_dependency, bundle_path);
+                if let Some(cycle) = find_cycle(ancestors, &identity, &nested_key) {
+                    report::progress(format_args!(
+                        "{}  {} {}: dependency cycle {}",
+                        indent,
+                        "Skipping".yellow(),
+                        nested_key,
+                        cycle
+                    ));
+                    continue;
+                }
 
-            for nested_name in nested_manifest.bundles.keys() {
                 let nested_path = nested_bundle_dir.join(nested_name);
+                let nested_source = flattened_source(&nested_path);
 
-                if nested_path.exists() && git_ops.is_repository(&nested_path) {
+                if nested_path.exists()
+                    && is_installed_repository(git_ops, &nested_path, nested_source.as_ref())
+                {
+                    let nested_target = PushTarget {
+                        url: nested_dependency.git.clone(),
+                        branch: installed_branch(&nested_state, nested_name, &nested_manifest),
+                        author: nested_dependency.author.clone(),
+                        bump: nested_dependency.bump,
+                    };
+                    let mut nested_ancestors = ancestors.to_vec();
+                    nested_ancestors.push((identity, nested_key.clone()));
                     push_bundle_recursive(
                         git_ops,
-                        nested_name,
+                        &nested_key,
                         &nested_path,
-                        message,
-                        depth + 1,
+                        &nested_target,
+                        context,
+                        &nested_ancestors,
                         stats,
                     );
                 }
@@ -131,9 +376,73 @@ fn push_bundle_recursive(
     }
 
     // Now push this bundle
-    match push_single_bundle(git_ops, name, bundle_path, message, &indent) {
-        Ok(PushResult::Pushed) => stats.pushed += 1,
-        Ok(PushResult::NoChanges) => stats.skipped += 1,
+    let source = flattened_source(bundle_path);
+    let repository = source
+        .as_ref()
+        .map_or(bundle_path, |source| source.checkout.as_path());
+    let old_commit = git_ops.head_commit(repository).ok();
+    let result = match &source {
+        Some(source) => {
+            push_flattened_bundle(git_ops, name, bundle_path, source, target, context, &indent)
+        }
+        None => push_single_bundle(
+            git_ops,
+            name,
+            bundle_path,
+            "bundle.toml",
+            target,
+            context,
+            &indent,
+        ),
+    };
+    match result {
+        Ok(PushResult::Pushed {
+            new_commit,
+            version,
+            pull_request,
+        }) => {
+            stats.pushed += 1;
+            stats.versions.push((key.to_string(), version));
+            if let Some(url) = pull_request {
+                stats.pull_requests.push((key.to_string(), url));
+            }
+            stats.results.record(BundleResult {
+                name: key.to_string(),
+                action: BundleAction::Pushed,
+                old_commit,
+                new_commit,
+                error: None,
+            });
+        }
+        Ok(PushResult::Committed {
+            new_commit,
+            version,
+        }) => {
+            stats.committed += 1;
+            stats.versions.push((key.to_string(), version));
+            stats.results.record(BundleResult {
+                name: key.to_string(),
+                action: BundleAction::Committed,
+                old_commit,
+                new_commit,
+                error: None,
+            });
+        }
+        Ok(PushResult::NoChanges) => stats.skip(key, old_commit),
+        Ok(PushResult::Declined) => {
+            stats.declined += 1;
+            stats.skip(key, old_commit);
+        }
+        Ok(PushResult::WouldPush { summary }) => {
+            stats.would_push += 1;
+            stats.results.record(BundleResult {
+                name: key.to_string(),
+                action: BundleAction::Skipped,
+                old_commit: old_commit.clone(),
+                new_commit: old_commit,
+                error: Some(summary),
+            });
+        }
         Err(e) => {
             let error_msg = e.to_string().to_lowercase();
             if error_msg.contains("permission denied")
@@ -142,43 +451,79 @@ fn push_bundle_recursive(
                 || error_msg.contains("401")
                 || error_msg.contains("could not read from remote")
             {
-                println!(
+                report::progress(format_args!(
                     "{}⚠ {} {} (no push access - local changes preserved)",
                     indent,
                     "Warning:".yellow().bold(),
                     name
-                );
+                ));
                 stats.auth_failed += 1;
             } else {
-                println!("{}{} {}: {}", indent, "Failed".red(), name, e);
+                report::progress(format_args!("{}{} {}: {}", indent, "Failed".red(), name, e));
                 stats.errors += 1;
             }
+            stats.results.record(BundleResult {
+                name: key.to_string(),
+                action: BundleAction::Failed,
+                old_commit,
+                new_commit: None,
+                error: Some(format!("{:#}", e)),
+            });
         }
     }
 }
 
 enum PushResult {
-    Pushed,
+    /// Changes were committed and pushed as `new_commit`, at the bundle's `version`,
+    /// with the URL of the pull request opened for them
+    Pushed {
+        new_commit: Option<String>,
+        version: Option<String>,
+        pull_request: Option<String>,
+    },
+    /// Changes were committed as `new_commit`, at the bundle's `version`, to be
+    /// pushed later
+    Committed {
+        new_commit: Option<String>,
+        version: Option<String>,
+    },
     NoChanges,
+    /// An interactive push left the bundle's changes alone
+    Declined,
+    /// A dry run found changes to push; says what would be pushed where
+    WouldPush {
+        summary: String,
+    },
 }
 
-/// Bump patch version (0.0.1 -> 0.0.2)
-fn bump_patch_version(version: &str) -> String {
-    let parts: Vec<&str> = version.split('.').collect();
-    if parts.len() == 3 {
-        if let Ok(patch) = parts[2].parse::<u32>() {
-            return format!("{}.{}.{}", parts[0], parts[1], patch + 1);
-        }
+/// Increments one part of a version, resetting the parts after it (1.2.3 -> 1.3.0
+/// for minor). Versions that aren't `major.minor.patch`, or bumped with `Off`, are
+/// returned unchanged.
+fn bump_version(version: &str, level: BumpLevel) -> String {
+    let numbers: Option<Vec<u32>> = version.split('.').map(|part| part.parse().ok()).collect();
+    let Some(&[major, minor, patch]) = numbers.as_deref() else {
+        return version.to_string();
+    };
+
+    match level {
+        BumpLevel::Major => format!("{}.0.0", major + 1),
+        BumpLevel::Minor => format!("{}.{}.0", major, minor + 1),
+        BumpLevel::Patch => format!("{}.{}.{}", major, minor, patch + 1),
+        BumpLevel::Off => version.to_string(),
     }
-    version.to_string()
 }
 
-/// Check if the version was manually changed by comparing working tree to HEAD
-fn version_was_changed(git_ops: &dyn GitOperations, bundle_path: &Path) -> Result<bool> {
-    let manifest_path = bundle_path.join("bundle.toml");
+/// Check if the version was manually changed by comparing working tree to HEAD.
+/// `manifest_file` is the bundle's manifest relative to the repository root.
+fn version_was_changed(
+    git_ops: &dyn GitOperations,
+    repo_path: &Path,
+    manifest_file: &str,
+) -> Result<bool> {
+    let manifest_path = repo_path.join(manifest_file);
 
     // Get the committed version from HEAD
-    let committed_content = git_ops.get_file_from_head(bundle_path, "bundle.toml")?;
+    let committed_content = git_ops.get_file_from_head(repo_path, manifest_file)?;
     let committed_manifest: BundleManifest =
         toml::from_str(&committed_content).context("Failed to parse committed bundle.toml")?;
 
@@ -191,81 +536,676 @@ fn version_was_changed(git_ops: &dyn GitOperations, bundle_path: &Path) -> Resul
     Ok(committed_manifest.version != current_manifest.version)
 }
 
-/// Auto-increment the version in the manifest if it hasn't been manually changed
-fn auto_increment_version_if_needed(
+/// The automatic version bump of a push: the version before and after, or None if
+/// the version was changed by hand (or can't be compared with HEAD) or bumping is off
+fn planned_version_bump(
+    git_ops: &dyn GitOperations,
+    repo_path: &Path,
+    manifest_file: &str,
+    level: BumpLevel,
+) -> Result<Option<(String, String)>> {
+    if level == BumpLevel::Off {
+        return Ok(None);
+    }
+
+    match version_was_changed(git_ops, repo_path, manifest_file) {
+        // Changed by hand, nothing to do
+        Ok(true) => return Ok(None),
+        Ok(false) => {}
+        // Could not compare (maybe no HEAD commit yet), skip auto-increment
+        Err(_) => return Ok(None),
+    }
+
+    let content = std::fs::read_to_string(repo_path.join(manifest_file))?;
+    let manifest: BundleManifest =
+        toml::from_str(&content).context("Failed to parse bundle.toml")?;
+    let old_version = manifest.version.unwrap_or_else(|| "0.0.0".to_string());
+    let new_version = bump_version(&old_version, level);
+    Ok(Some((old_version, new_version)))
+}
+
+/// Writes the bumped version into the bundle's manifest
+fn apply_version_bump(repo_path: &Path, manifest_file: &str, version: &str) -> Result<()> {
+    let manifest_path = repo_path.join(manifest_file);
+    let content = std::fs::read_to_string(&manifest_path)?;
+    let mut manifest: BundleManifest =
+        toml::from_str(&content).context("Failed to parse bundle.toml")?;
+    manifest.version = Some(version.to_string());
+    save_manifest(&manifest, &manifest_path)
+}
+
+/// Push a single bundle's changes to its remote. `manifest_file` is the bundle's
+/// manifest relative to the repository at `bundle_path`. Commits made earlier, e.g.
+/// with `fpm commit`, are pushed too. A dry run reports what would be committed and
+/// pushed instead, and a commit-only run stops after committing.
+fn push_single_bundle(
     git_ops: &dyn GitOperations,
+    name: &str,
     bundle_path: &Path,
+    manifest_file: &str,
+    target: &PushTarget,
+    context: &PushContext,
     indent: &str,
-) -> Result<()> {
-    let manifest_path = bundle_path.join("bundle.toml");
+) -> Result<PushResult> {
+    if context.stopped.get() {
+        return Ok(PushResult::Declined);
+    }
+    let commit_only = context.options.commit_only;
 
-    // Check if version was already changed manually
-    match version_was_changed(git_ops, bundle_path) {
-        Ok(true) => {
-            // Version was manually changed, nothing to do
-            return Ok(());
+    // Check for local changes, of the selected paths only if given, and for commits
+    // that weren't pushed yet
+    let has_changes = match context.options.paths.is_empty() {
+        true => git_ops.has_local_changes(bundle_path)?,
+        false => !selected_files(git_ops, bundle_path, &context.options.paths)?.is_empty(),
+    };
+    // A bundle installed from a tag has no branch of its own, but changes can be pushed
+    // to one checked out in it
+    let installed = match &target.branch {
+        Some(branch) => Some(branch.clone()),
+        None => git_ops.current_branch(bundle_path).ok().flatten(),
+    };
+    let unpushed = match &installed {
+        Some(branch) if !has_changes && !commit_only => {
+            unpushed_commits(git_ops, bundle_path, branch)
         }
-        Ok(false) => {
-            // Version not changed, we need to auto-increment
+        _ => 0,
+    };
+    if !has_changes && unpushed == 0 {
+        report::progress(format_args!(
+            "{}{} {} (no changes)",
+            indent,
+            "Skipping".cyan(),
+            name
+        ));
+        return Ok(PushResult::NoChanges);
+    }
+
+    // A pull request merges the new branch into the one the bundle was installed from
+    let base = match &installed {
+        Some(branch) => Some(branch.as_str()),
+        None if context.options.pull_request => anyhow::bail!(
+            "installed from a version tag, so there is no branch to open a pull request into. \
+            Set `branch` for this bundle in bundle.toml or check one out in it to push changes"
+        ),
+        None => None,
+    };
+    let branch = context.options.branch.as_deref().or(base);
+    let branch = match branch {
+        Some(branch) => branch,
+        // A commit needs no branch to push to
+        None if commit_only => "",
+        None => anyhow::bail!(
+            "installed from a version tag, so there is no branch to push to. \
+            Set `branch` for this bundle in bundle.toml, check one out in it or pass --branch \
+            to push changes"
+        ),
+    };
+    let base = base.filter(|_| context.options.pull_request);
+    let forge = match base {
+        Some(_) => Some(ForgeClient::for_url(&target.url)?),
+        None => None,
+    };
+
+    let mut commit = match has_changes {
+        true => Some(plan_commit(
+            git_ops,
+            name,
+            bundle_path,
+            manifest_file,
+            target,
+            context,
+        )?),
+        false => None,
+    };
+
+    let planned = PlannedPush {
+        name,
+        branch: Some(branch).filter(|_| !commit_only),
+        commit: commit.as_ref(),
+        manifest_file,
+        unpushed,
+    };
+    if context.options.dry_run {
+        return Ok(report_dry_run(&planned, base, indent));
+    }
+
+    if context.options.interactive {
+        match confirm_push(git_ops, context, bundle_path, &planned, indent)? {
+            Some(message) => {
+                if let Some(commit) = &mut commit {
+                    commit.message = message;
+                }
+            }
+            None => return Ok(PushResult::Declined),
+        }
+    }
+
+    let action = if commit_only { "Committing" } else { "Pushing" };
+    report::progress(format_args!("{}{} {}", indent, action.green(), name));
+    if context.options.branch.is_some() && !commit_only {
+        git_ops.create_branch(bundle_path, branch)?;
+    }
+
+    let version = match &commit {
+        Some(commit) => {
+            if let Some((old_version, new_version)) = &commit.bump {
+                apply_version_bump(bundle_path, manifest_file, new_version)?;
+                report::progress(format_args!(
+                    "{}Auto-incremented version: {} -> {}",
+                    indent,
+                    old_version.yellow(),
+                    new_version.green()
+                ));
+            }
+
+            // Commit all changes, or the changed files of the selected paths
+            if context.options.paths.is_empty() {
+                git_ops.commit_all(bundle_path, &commit.message, commit.author.as_ref())?;
+            } else {
+                git_ops.commit_paths(
+                    bundle_path,
+                    &commit.files,
+                    &commit.message,
+                    commit.author.as_ref(),
+                )?;
+            }
+            commit.version()
         }
-        Err(_) => {
-            // Could not compare (maybe no HEAD commit yet), skip auto-increment
-            return Ok(());
+        None => {
+            report::progress(format_args!("{}{} unpushed commit(s)", indent, unpushed));
+            load_manifest(&bundle_path.join(manifest_file))
+                .ok()
+                .and_then(|manifest| manifest.version)
         }
+    };
+
+    if commit_only {
+        report::progress(format_args!("{}{} {}", indent, "✓".green(), name));
+        return Ok(PushResult::Committed {
+            new_commit: git_ops.head_commit(bundle_path).ok(),
+            version,
+        });
     }
 
-    // Load manifest, bump version, save
-    let content = std::fs::read_to_string(&manifest_path)?;
-    let mut manifest: BundleManifest =
-        toml::from_str(&content).context("Failed to parse bundle.toml")?;
+    // Push to origin (the cloned remote) on the branch the bundle was installed from,
+    // or the new one
+    git_ops.push(bundle_path, "origin", branch)?;
+
+    let pull_request = match (&forge, base) {
+        (Some(forge), Some(base)) => {
+            // Titled after the commit, or the latest of those pushed
+            let message = match &commit {
+                Some(commit) => commit.message.clone(),
+                None => git_ops
+                    .log(bundle_path, 1)?
+                    .into_iter()
+                    .next()
+                    .map(|latest| latest.summary)
+                    .unwrap_or_default(),
+            };
+            let (title, body) = message.split_once('\n').unwrap_or((&message, ""));
+            let request = PullRequest {
+                head: branch,
+                base,
+                title,
+                body: body.trim(),
+            };
+            // The branch is pushed either way, so the error says so along with its cause
+            let url = forge.open_pull_request(&request).map_err(|e| {
+                anyhow::anyhow!(
+                    "Pushed to {}, but failed to open a pull request: {:#}",
+                    branch,
+                    e
+                )
+            })?;
+            report::progress(format_args!(
+                "{}{} {}",
+                indent,
+                "Opened pull request".green(),
+                url
+            ));
+            Some(url)
+        }
+        _ => None,
+    };
+
+    report::progress(format_args!("{}{} {}", indent, "✓".green(), name));
+    Ok(PushResult::Pushed {
+        new_commit: git_ops.head_commit(bundle_path).ok(),
+        version,
+        pull_request,
+    })
+}
+
+/// Number of commits of HEAD that origin's `branch` doesn't have, e.g. made with
+/// `fpm commit`. Installs don't always move the remote-tracking branch along, so
+/// it is fetched before counting commits it seems to lack.
+fn unpushed_commits(git_ops: &dyn GitOperations, repo_path: &Path, branch: &str) -> usize {
+    let revision = format!("origin/{}", branch);
+    match git_ops.ahead_behind(repo_path, &revision) {
+        Ok((0, _)) | Err(_) => 0,
+        Ok((ahead, _)) => {
+            if git_ops.fetch_remote_branch(repo_path, branch).is_err() {
+                // Pushing reports why the remote can't be reached
+                return ahead;
+            }
+            git_ops
+                .ahead_behind(repo_path, &revision)
+                .map_or(ahead, |(ahead, _)| ahead)
+        }
+    }
+}
+
+/// The changed files of a bundle that are, or are inside, one of `paths`; every
+/// changed file if none are given
+fn selected_files(
+    git_ops: &dyn GitOperations,
+    bundle_path: &Path,
+    paths: &[String],
+) -> Result<Vec<String>> {
+    let files = git_ops.changed_files(bundle_path)?;
+    if paths.is_empty() {
+        return Ok(files);
+    }
+
+    let paths: Vec<String> = paths
+        .iter()
+        .map(|path| {
+            let path = path.replace('\\', "/");
+            match path.trim_start_matches("./").trim_end_matches('/') {
+                "." => String::new(),
+                path => path.to_string(),
+            }
+        })
+        .collect();
+    Ok(files
+        .into_iter()
+        .filter(|file| {
+            paths.iter().any(|path| {
+                path.is_empty()
+                    || file == path
+                    || file
+                        .strip_prefix(path.as_str())
+                        .is_some_and(|rest| rest.starts_with('/'))
+            })
+        })
+        .collect())
+}
 
-    let old_version = manifest
-        .version
-        .clone()
-        .unwrap_or_else(|| "0.0.0".to_string());
-    let new_version = bump_patch_version(&old_version);
-    manifest.version = Some(new_version.clone());
+/// The commit a push makes of a bundle's changes
+struct PlannedCommit {
+    /// Author of the commit; the repository's identity if None
+    author: Option<CommitAuthor>,
+    /// Version before and after the automatic bump, if any
+    bump: Option<(String, String)>,
+    /// Files the commit includes
+    files: Vec<String>,
+    message: String,
+    /// Version of the bundle's manifest, before any bump
+    manifest_version: Option<String>,
+}
 
-    save_manifest(&manifest, &manifest_path)?;
+impl PlannedCommit {
+    /// The version the bundle is at after the commit
+    fn version(&self) -> Option<String> {
+        self.bump
+            .as_ref()
+            .map(|(_, new_version)| new_version.clone())
+            .or(self.manifest_version.clone())
+    }
+}
 
+/// Plans the commit of a bundle's changes: its author, version bump, files and
+/// message, from the options, the bundle and the settings
+fn plan_commit(
+    git_ops: &dyn GitOperations,
+    name: &str,
+    bundle_path: &Path,
+    manifest_file: &str,
+    target: &PushTarget,
+    context: &PushContext,
+) -> Result<PlannedCommit> {
+    let author = target
+        .author
+        .as_deref()
+        .or(context.options.settings.author.as_deref())
+        .map(CommitAuthor::parse)
+        .transpose()?;
+
+    // Auto-increment version if user forgot to change it
+    let level = context
+        .options
+        .bump
+        .or(target.bump)
+        .or(context.options.settings.bump)
+        .unwrap_or_default();
+    let bump = planned_version_bump(git_ops, bundle_path, manifest_file, level)?;
+    let mut files = selected_files(git_ops, bundle_path, &context.options.paths)?;
+    if bump.is_some() && !files.iter().any(|file| file == manifest_file) {
+        files.push(manifest_file.to_string());
+        files.sort();
+    }
+
+    let manifest = load_manifest(&bundle_path.join(manifest_file)).ok();
+    let message = match &context.options.message {
+        Some(message) => message.clone(),
+        None => {
+            let old_version = git_ops
+                .get_file_from_head(bundle_path, manifest_file)
+                .ok()
+                .and_then(|content| toml::from_str::<BundleManifest>(&content).ok())
+                .and_then(|manifest| manifest.version);
+            let template = manifest
+                .as_ref()
+                .and_then(|manifest| manifest.commit_message.as_deref())
+                .or(context.options.settings.commit_message.as_deref())
+                .unwrap_or(DEFAULT_COMMIT_MESSAGE);
+            let values = CommitValues {
+                bundle: name,
+                version: bump
+                    .as_ref()
+                    .map(|(_, new_version)| new_version.as_str())
+                    .or(manifest
+                        .as_ref()
+                        .and_then(|manifest| manifest.version.as_deref()))
+                    .unwrap_or_default(),
+                old_version: old_version.as_deref().unwrap_or_default(),
+                files_changed: files.len(),
+            };
+            render_commit_message(template, &values)
+        }
+    };
+
+    Ok(PlannedCommit {
+        author,
+        bump,
+        files,
+        message,
+        manifest_version: manifest.and_then(|manifest| manifest.version),
+    })
+}
+
+/// What a push of one bundle would do
+struct PlannedPush<'a> {
+    name: &'a str,
+    /// Branch of origin pushed to; None when only committing
+    branch: Option<&'a str>,
+    /// The commit of the bundle's changes, if it has any
+    commit: Option<&'a PlannedCommit>,
+    /// The bundle's manifest, relative to the repository
+    manifest_file: &'a str,
+    /// Commits made earlier that weren't pushed yet
+    unpushed: usize,
+}
+
+/// Prints what a push would commit and push, for a dry run
+fn report_dry_run(push: &PlannedPush, base: Option<&str>, indent: &str) -> PushResult {
+    let destination = match push.branch {
+        Some(branch) => format!(" to origin/{}", branch),
+        None => String::new(),
+    };
+    let action = if push.branch.is_some() {
+        "Would push"
+    } else {
+        "Would commit"
+    };
+    report::progress(format_args!(
+        "{}{} {}{}",
+        indent,
+        action.green(),
+        push.name,
+        destination
+    ));
+    if let Some(base) = base {
+        report::progress(format_args!("{}  pull request into {}", indent, base));
+    }
+
+    let Some(commit) = push.commit else {
+        report::progress(format_args!(
+            "{}  {} unpushed commit(s)",
+            indent, push.unpushed
+        ));
+        return PushResult::WouldPush {
+            summary: format!(
+                "dry run: would push {} commit(s){}",
+                push.unpushed, destination
+            ),
+        };
+    };
+
+    if let Some((old_version, new_version)) = &commit.bump {
+        report::progress(format_args!(
+            "{}  version {} -> {}",
+            indent,
+            old_version.yellow(),
+            new_version.green()
+        ));
+    }
+    report::progress(format_args!("{}  message \"{}\"", indent, commit.message));
+    report::progress(format_args!("{}  {} file(s):", indent, commit.files.len()));
+    for file in &commit.files {
+        report::progress(format_args!("{}    {}", indent, file));
+    }
+    PushResult::WouldPush {
+        summary: format!(
+            "dry run: {} {} file(s){}",
+            action.to_lowercase(),
+            commit.files.len(),
+            destination
+        ),
+    }
+}
+
+/// Shows the diffstat of a bundle and asks whether to push it. Returns the commit
+/// message to push with (empty without changes to commit), or None if the bundle
+/// is skipped (or the push stopped).
+fn confirm_push(
+    git_ops: &dyn GitOperations,
+    context: &PushContext,
+    repo_path: &Path,
+    push: &PlannedPush,
+    indent: &str,
+) -> Result<Option<String>> {
+    match push.branch {
+        Some(branch) => println!(
+            "{}{} {} origin/{}",
+            indent,
+            push.name.bold(),
+            "->".dimmed(),
+            branch.cyan()
+        ),
+        None => println!("{}{}", indent, push.name.bold()),
+    }
+
+    let Some(commit) = push.commit else {
+        println!("{}  {} unpushed commit(s)", indent, push.unpushed);
+        if !ask_to_push(context, push.name, indent)? {
+            return Ok(None);
+        }
+        return Ok(Some(String::new()));
+    };
+
+    let stats = git_ops
+        .diff_stat(repo_path, "HEAD", None)
+        .with_context(|| format!("Failed to diff bundle: {}", push.name))?;
+    // Untracked files have no stats, and the manifest only changes with the bump
+    for file in commit.files.iter().take(CONFIRM_FILES) {
+        let stat = stats.iter().find(|stat| stat.path == *file);
+        match stat.map(|stat| (stat.insertions, stat.deletions)) {
+            Some((Some(insertions), Some(deletions))) => println!(
+                "{}  {} | {} {}",
+                indent,
+                file,
+                format!("+{}", insertions).green(),
+                format!("-{}", deletions).red()
+            ),
+            Some(_) => println!("{}  {} | {}", indent, file, "binary".dimmed()),
+            None if file == push.manifest_file && commit.bump.is_some() => {
+                println!("{}  {} | {}", indent, file, "version".dimmed())
+            }
+            None => println!("{}  {} | {}", indent, file, "new".dimmed()),
+        }
+    }
+    if commit.files.len() > CONFIRM_FILES {
+        println!(
+            "{}  {}",
+            indent,
+            format!(
+                "... and {} more file(s)",
+                commit.files.len() - CONFIRM_FILES
+            )
+            .dimmed()
+        );
+    }
+    let insertions: usize = stats.iter().filter_map(|stat| stat.insertions).sum();
+    let deletions: usize = stats.iter().filter_map(|stat| stat.deletions).sum();
     println!(
-        "{}Auto-incremented version: {} -> {}",
+        "{}  {} file(s) changed, {} insertion(s)(+), {} deletion(s)(-)",
         indent,
-        old_version.yellow(),
-        new_version.green()
+        commit.files.len(),
+        insertions,
+        deletions
     );
+    if let Some((old_version, new_version)) = &commit.bump {
+        println!(
+            "{}  version {} -> {}",
+            indent,
+            old_version.yellow(),
+            new_version.green()
+        );
+    }
 
-    Ok(())
+    if !ask_to_push(context, push.name, indent)? {
+        return Ok(None);
+    }
+    let message = ask(
+        context,
+        &format!("{}Commit message [{}]: ", indent, commit.message),
+    )?;
+    Ok(Some(if message.is_empty() {
+        commit.message.clone()
+    } else {
+        message
+    }))
 }
 
-/// Push a single bundle's changes to its remote
-fn push_single_bundle(
+/// Asks whether to push a bundle until the answer is yes, no or quit. Quitting
+/// stops the push.
+fn ask_to_push(context: &PushContext, name: &str, indent: &str) -> Result<bool> {
+    let verb = if context.options.commit_only {
+        "Commit"
+    } else {
+        "Push"
+    };
+    loop {
+        let answer = ask(context, &format!("{}{} {}? [y/N/q] ", indent, verb, name))?;
+        match answer.to_lowercase().as_str() {
+            "y" | "yes" => return Ok(true),
+            "" | "n" | "no" => {
+                report::progress(format_args!("{}{} {}", indent, "Skipping".cyan(), name));
+                return Ok(false);
+            }
+            "q" | "quit" => {
+                println!("{}Stopped; the bundles left keep their changes", indent);
+                context.stopped.set(true);
+                return Ok(false);
+            }
+            _ => println!("{}Answer y (yes), n (skip) or q (stop)", indent),
+        }
+    }
+}
+
+/// Prints a question and reads the answer from the context's input, trimmed. The
+/// answer is empty once the input ends.
+fn ask(context: &PushContext, question: &str) -> Result<String> {
+    print!("{}", question);
+    std::io::stdout().flush()?;
+
+    let mut answer = String::new();
+    context
+        .input
+        .borrow_mut()
+        .read_line(&mut answer)
+        .context("Failed to read the answer")?;
+    Ok(answer.trim().to_string())
+}
+
+/// Values of the placeholders of a commit message template
+struct CommitValues<'a> {
+    bundle: &'a str,
+    /// Version being pushed, after any auto-increment
+    version: &'a str,
+    /// Version at the bundle's HEAD
+    old_version: &'a str,
+    files_changed: usize,
+}
+
+/// Fills in the `{bundle}`, `{version}`, `{old_version}` and `{files_changed}`
+/// placeholders of a commit message template. Other text is kept as it is.
+fn render_commit_message(template: &str, values: &CommitValues) -> String {
+    template
+        .replace("{bundle}", values.bundle)
+        .replace("{version}", values.version)
+        .replace("{old_version}", values.old_version)
+        .replace("{files_changed}", &values.files_changed.to_string())
+}
+
+/// Push a bundle installed from a subdirectory: copy its files back into that
+/// subdirectory of the checkout, push the checkout, then copy the result (e.g. an
+/// auto-incremented version) back into the bundle. A dry run restores the
+/// subdirectory afterwards.
+fn push_flattened_bundle(
     git_ops: &dyn GitOperations,
     name: &str,
     bundle_path: &Path,
-    message: Option<&str>,
+    source: &FlattenedSource,
+    target: &PushTarget,
+    context: &PushContext,
     indent: &str,
 ) -> Result<PushResult> {
-    // Check for local changes
-    if !git_ops.has_local_changes(bundle_path)? {
-        println!("{}{} {} (no changes)", indent, "Skipping".cyan(), name);
-        return Ok(PushResult::NoChanges);
+    let source_dir = source.source_dir();
+    if context.options.dry_run {
+        let snapshot = source.checkout.with_file_name(format!("{}.dry-run", name));
+        mirror(&source_dir, &snapshot)?;
+        mirror(bundle_path, &source_dir)?;
+        let manifest_file = source
+            .subdirectory
+            .join("bundle.toml")
+            .to_string_lossy()
+            .replace('\\', "/");
+        let result = push_single_bundle(
+            git_ops,
+            name,
+            &source.checkout,
+            &manifest_file,
+            target,
+            context,
+            indent,
+        );
+        mirror(&snapshot, &source_dir)?;
+        let _ = std::fs::remove_dir_all(&snapshot);
+        return result;
     }
 
-    println!("{}{} {}", indent, "Pushing".green(), name);
-
-    // Auto-increment version if user forgot to change it
-    auto_increment_version_if_needed(git_ops, bundle_path, indent)?;
-
-    // Commit all changes
-    let commit_msg = message.unwrap_or("fpm push: Update bundle");
-    git_ops.commit_all(bundle_path, commit_msg)?;
+    mirror(bundle_path, &source_dir)?;
 
-    // Push to origin (the cloned remote)
-    git_ops.push(bundle_path, "origin", DEFAULT_BRANCH)?;
+    let manifest_file = source
+        .subdirectory
+        .join("bundle.toml")
+        .to_string_lossy()
+        .replace('\\', "/");
+    let result = push_single_bundle(
+        git_ops,
+        name,
+        &source.checkout,
+        &manifest_file,
+        target,
+        context,
+        indent,
+    )?;
 
-    println!("{}{} {}", indent, "✓".green(), name);
-    Ok(PushResult::Pushed)
+    mirror(&source_dir, bundle_path)?;
+    Ok(result)
 }
 
 fn print_summary(stats: &PushStats) {
@@ -274,6 +1214,33 @@ fn print_summary(stats: &PushStats) {
     if stats.pushed > 0 {
         println!("{} {} bundle(s)", "Pushed".green().bold(), stats.pushed);
     }
+    if stats.committed > 0 {
+        println!(
+            "{} {} bundle(s)",
+            "Committed".green().bold(),
+            stats.committed
+        );
+    }
+    for (key, version) in &stats.versions {
+        match version {
+            Some(version) => println!("  {} {}", key, version.green()),
+            None => println!("  {}", key),
+        }
+    }
+    if stats.committed > 0 {
+        println!("Run 'fpm push' to push the commits.");
+    }
+
+    if !stats.pull_requests.is_empty() {
+        println!(
+            "{} {} pull request(s)",
+            "Opened".green().bold(),
+            stats.pull_requests.len()
+        );
+        for (key, url) in &stats.pull_requests {
+            println!("  {} {}", key, url);
+        }
+    }
 
     if stats.auth_failed > 0 {
         println!(
@@ -291,7 +1258,29 @@ fn print_summary(stats: &PushStats) {
         );
     }
 
-    if stats.pushed == 0 && stats.auth_failed == 0 && stats.errors == 0 {
+    if stats.would_push > 0 {
+        println!(
+            "{} {} bundle(s). Nothing was committed or pushed (dry run).",
+            "Would push".green().bold(),
+            stats.would_push
+        );
+    }
+
+    if stats.declined > 0 {
+        println!(
+            "{} {} bundle(s) with their changes",
+            "Left".cyan().bold(),
+            stats.declined
+        );
+    }
+
+    if stats.pushed == 0
+        && stats.committed == 0
+        && stats.would_push == 0
+        && stats.declined == 0
+        && stats.auth_failed == 0
+        && stats.errors == 0
+    {
         println!("{} No bundles had changes to push.", "Note:".cyan());
     }
 }
@@ -302,6 +1291,7 @@ mod unit_tests {
 
     #[test]
     fn test_bump_patch_version() {
+        let bump_patch_version = |version| bump_version(version, BumpLevel::Patch);
         assert_eq!(bump_patch_version("0.0.1"), "0.0.2");
         assert_eq!(bump_patch_version("1.0.0"), "1.0.1");
         assert_eq!(bump_patch_version("1.2.3"), "1.2.4");
@@ -310,4 +1300,35 @@ mod unit_tests {
         assert_eq!(bump_patch_version("invalid"), "invalid");
         assert_eq!(bump_patch_version("1.0"), "1.0");
     }
+
+    #[test]
+    fn test_bump_major_and_minor_version() {
+        assert_eq!(bump_version("1.2.3", BumpLevel::Minor), "1.3.0");
+        assert_eq!(bump_version("1.2.3", BumpLevel::Major), "2.0.0");
+        assert_eq!(bump_version("0.9.12", BumpLevel::Minor), "0.10.0");
+        assert_eq!(bump_version("1.2.3-beta", BumpLevel::Major), "1.2.3-beta");
+    }
+
+    #[test]
+    fn test_render_commit_message() {
+        let values = CommitValues {
+            bundle: "icons",
+            version: "1.2.4",
+            old_version: "1.2.3",
+            files_changed: 3,
+        };
+
+        assert_eq!(
+            render_commit_message(
+                "assets({bundle}): {old_version} -> {version}, {files_changed} file(s)",
+                &values
+            ),
+            "assets(icons): 1.2.3 -> 1.2.4, 3 file(s)"
+        );
+        assert_eq!(
+            render_commit_message(DEFAULT_COMMIT_MESSAGE, &values),
+            DEFAULT_COMMIT_MESSAGE
+        );
+        assert_eq!(render_commit_message("{unknown}", &values), "{unknown}");
+    }
 }
@@ -0,0 +1,209 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::config::{load_manifest, parse_manifest};
+use crate::flatten::{self, checkout_path, flattened_source};
+use crate::git::{git_operations_for, GitOperations};
+use crate::lockfile::{load_lockfile, lockfile_path, save_lockfile};
+use crate::locking::lock_project;
+use crate::settings::Settings;
+use crate::state::{load_state, save_state};
+use crate::types::BUNDLE_DIR;
+
+/// Executes the remove command with the default git operations
+pub fn execute(manifest_path: &Path, name: &str, force: bool, settings: &Settings) -> Result<()> {
+    let git_ops = git_operations_for(settings);
+    execute_with_git(manifest_path, name, force, settings, git_ops)
+}
+
+/// Executes the remove command with a custom GitOperations implementation
+/// This enables dependency injection for testing
+pub fn execute_with_git(
+    manifest_path: &Path,
+    name: &str,
+    force: bool,
+    settings: &Settings,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<()> {
+    let manifest_path = if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    };
+
+    let _lock = lock_project(&manifest_path, settings)?;
+
+    let manifest = load_manifest(&manifest_path)?;
+    if !manifest.bundles.contains_key(name) {
+        anyhow::bail!("Bundle '{}' not found in {}", name, manifest_path.display());
+    }
+
+    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+    let bundle_dir = parent_dir.join(BUNDLE_DIR);
+    let target_path = bundle_dir.join(name);
+
+    // Refuse before touching anything, so local work is never lost by accident
+    if !force {
+        let mut changed = Vec::new();
+        find_local_changes(git_ops.as_ref(), &target_path, &mut changed)?;
+        if !changed.is_empty() {
+            let paths: Vec<String> = changed.iter().map(|p| p.display().to_string()).collect();
+            anyhow::bail!(
+                "Bundle '{}' has uncommitted local changes in: {}. \
+                Push them with 'fpm push' or use --force to discard them.",
+                name,
+                paths.join(", ")
+            );
+        }
+    }
+
+    remove_manifest_entry(&manifest_path, name)?;
+    println!(
+        "{} {} from {}",
+        "Removed".green(),
+        name,
+        manifest_path.display()
+    );
+
+    if target_path.exists() {
+        fs::remove_dir_all(&target_path)
+            .with_context(|| format!("Failed to remove {}", target_path.display()))?;
+        println!("{} {}", "Deleted".green(), target_path.display());
+    }
+
+    let checkout = checkout_path(&bundle_dir, name);
+    if checkout.exists() {
+        fs::remove_dir_all(&checkout)
+            .with_context(|| format!("Failed to remove {}", checkout.display()))?;
+    }
+
+    let mut state = load_state(&bundle_dir)?;
+    if state.bundles.remove(name).is_some() {
+        save_state(&bundle_dir, &state)?;
+    }
+
+    // Drop the bundle and everything nested in it from the lockfile
+    let lock_path = lockfile_path(&manifest_path)?;
+    if let Some(mut lockfile) = load_lockfile(&lock_path)? {
+        let nested_prefix = format!("{}/", name);
+        lockfile
+            .bundles
+            .retain(|key, _| key != name && !key.starts_with(&nested_prefix));
+        save_lockfile(&lock_path, &lockfile)?;
+    }
+
+    Ok(())
+}
+
+/// Collects the installed bundle clones below `path` (itself included) that have
+/// uncommitted changes
+pub fn find_local_changes(
+    git_ops: &dyn GitOperations,
+    path: &Path,
+    changed: &mut Vec<PathBuf>,
+) -> Result<()> {
+    // A linked local bundle is the user's own directory, which removing leaves alone
+    if !path.exists() || path.is_symlink() {
+        return Ok(());
+    }
+
+    let has_changes = match flattened_source(path) {
+        Some(source) => {
+            git_ops.is_repository(&source.checkout)
+                && flatten::has_local_changes(git_ops, path, &source)?
+        }
+        None => git_ops.is_repository(path) && git_ops.has_local_changes(path)?,
+    };
+    if has_changes {
+        changed.push(path.to_path_buf());
+    }
+
+    let nested_bundle_dir = path.join(BUNDLE_DIR);
+    if nested_bundle_dir.is_dir() {
+        for entry in fs::read_dir(&nested_bundle_dir)? {
+            let nested_path = entry?.path();
+            if nested_path.is_dir() {
+                find_local_changes(git_ops, &nested_path, changed)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes `[bundles.<name>]` from the manifest, keeping the rest of the file
+/// (comments, ordering) untouched
+fn remove_manifest_entry(manifest_path: &Path, name: &str) -> Result<()> {
+    let content = fs::read_to_string(manifest_path)
+        .with_context(|| format!("Failed to read manifest file: {}", manifest_path.display()))?;
+
+    let new_content = without_bundle_entry(&content, name)?;
+
+    fs::write(manifest_path, new_content)
+        .with_context(|| format!("Failed to write manifest: {}", manifest_path.display()))
+}
+
+/// Returns the manifest content without the bundle entry
+fn without_bundle_entry(content: &str, name: &str) -> Result<String> {
+    let mut document: toml_edit::DocumentMut =
+        content.parse().context("Failed to parse bundle.toml")?;
+
+    let removed = document
+        .get_mut("bundles")
+        .and_then(|bundles| bundles.as_table_like_mut())
+        .and_then(|bundles| bundles.remove(name));
+    if removed.is_none() {
+        anyhow::bail!("Bundle '{}' not found in bundle.toml", name);
+    }
+
+    let new_content = document.to_string();
+    parse_manifest(&new_content)?;
+
+    Ok(new_content)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn test_without_bundle_entry_keeps_other_content() {
+        let content = r#"# Design assets
+fpm_version = "0.1.0"
+identifier = "fpm-bundle"
+
+# Icons for the web app
+[bundles.icons]
+version = "*"
+git = "https://github.com/example/icons.git"
+
+[bundles.fonts]
+version = "^1.0"
+git = "https://github.com/example/fonts.git"
+"#;
+
+        let new_content = without_bundle_entry(content, "fonts").unwrap();
+
+        assert!(new_content.starts_with("# Design assets\n"));
+        assert!(new_content.contains("# Icons for the web app\n[bundles.icons]"));
+        assert!(!new_content.contains("fonts"));
+
+        assert!(without_bundle_entry(content, "missing").is_err());
+    }
+
+    #[test]
+    fn test_without_bundle_entry_handles_inline_tables() {
+        let content = r#"fpm_version = "0.1.0"
+identifier = "fpm-bundle"
+
+[bundles]
+icons = { version = "*", git = "https://github.com/example/icons.git" }
+"#;
+
+        let new_content = without_bundle_entry(content, "icons").unwrap();
+        assert!(parse_manifest(&new_content).unwrap().bundles.is_empty());
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::path::Path;
+
+use crate::commands::env::bundle_vars;
+use crate::config::load_manifest;
+use crate::hooks::{quote_arg, run_command};
+
+/// Environment variable holding the name of the script being run
+pub const SCRIPT_ENV: &str = "FPM_SCRIPT";
+
+/// Executes the run command: runs the script `name` of the manifest's `[scripts]`
+/// with `args` appended, or lists the scripts if no name is given
+pub fn execute(manifest_path: &Path, name: Option<&str>, args: &[String]) -> Result<()> {
+    let manifest_path = if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    };
+
+    let manifest = load_manifest(&manifest_path)?;
+    let Some(name) = name else {
+        if manifest.scripts.is_empty() {
+            println!(
+                "{}",
+                format!("No [scripts] in {}", manifest_path.display()).yellow()
+            );
+        }
+        for (name, command) in &manifest.scripts {
+            println!("{}", name.bold());
+            println!("    {}", command);
+        }
+        return Ok(());
+    };
+
+    let Some(script) = manifest.scripts.get(name) else {
+        let names: Vec<&str> = manifest.scripts.keys().map(String::as_str).collect();
+        anyhow::bail!(
+            "No script '{}' in {}. Available scripts: {}",
+            name,
+            manifest_path.display(),
+            if names.is_empty() {
+                "none".to_string()
+            } else {
+                names.join(", ")
+            }
+        );
+    };
+
+    let command = script_command(script, args);
+    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+    let mut env = vec![(SCRIPT_ENV.to_string(), name.to_string())];
+    env.extend(bundle_vars(&manifest_path)?);
+    let env: Vec<(&str, String)> = env
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.clone()))
+        .collect();
+
+    eprintln!("{} {}", ">".dimmed(), command.dimmed());
+    run_command(&command, parent_dir, &env, false)
+        .with_context(|| format!("Script '{}' failed", name))
+}
+
+/// The script's command with the extra arguments appended, quoted for the shell
+fn script_command(script: &str, args: &[String]) -> String {
+    let mut command = script.to_string();
+    for arg in args {
+        command.push(' ');
+        command.push_str(&quote_arg(arg));
+    }
+    command
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_script_command_quotes_arguments() {
+        let args = vec![
+            "--out".to_string(),
+            "dist dir".to_string(),
+            "it's".to_string(),
+        ];
+        assert_eq!(
+            script_command("svgo -r assets", &args),
+            r#"svgo -r assets '--out' 'dist dir' 'it'\''s'"#
+        );
+        assert_eq!(script_command("make", &[]), "make");
+    }
+}
@@ -0,0 +1,44 @@
+use anyhow::Result;
+use colored::Colorize;
+
+use crate::git::git_operations_for;
+use crate::registry::{load_registry, registry_url, RegistryIndex};
+use crate::settings::Settings;
+
+/// Executes the search command: lists the bundles of the configured registry
+/// matching `term`
+pub fn execute(term: &str, settings: &Settings) -> Result<()> {
+    let git_ops = git_operations_for(settings);
+    let index = load_registry(git_ops.as_ref(), settings)?;
+    print_results(&index, term, registry_url(settings)?);
+    Ok(())
+}
+
+fn print_results(index: &RegistryIndex, term: &str, url: &str) {
+    let matches = index.search(term);
+    if matches.is_empty() {
+        println!(
+            "{}",
+            format!("No bundles matching '{}' in {}", term, url).yellow()
+        );
+        return;
+    }
+
+    for (name, entry) in &matches {
+        match entry.latest_version() {
+            Some(version) => print!("{} {}", name.bold(), version),
+            None => print!("{}", name.bold()),
+        }
+        match &entry.description {
+            Some(description) => println!(" - {}", description),
+            None => println!(),
+        }
+        println!("    {}", entry.git.dimmed());
+    }
+
+    println!();
+    println!(
+        "{} bundle(s) found. Add one with 'fpm add <name>'.",
+        matches.len()
+    );
+}
@@ -0,0 +1,340 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use semver::Version;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::archive::{is_sha256, save_hashed, unpack, with_work_dir, ArchiveFormat};
+use crate::cache::cache_dir;
+use crate::forge::{github_release, Release};
+use crate::settings::Settings;
+
+/// GitHub repository fpm is released from
+const RELEASE_REPOSITORY: &str = "DragonAxeSoftware/fpm";
+
+/// Version of the running fpm
+const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Name of the fpm executable in release archives
+const BIN_NAME: &str = if cfg!(windows) { "fpm.exe" } else { "fpm" };
+
+/// Executes the self-update command: installs `version`, or the latest release if
+/// it is newer than the running fpm. With `check`, only reports whether there is
+/// a newer release.
+pub fn execute(version: Option<&str>, check: bool, settings: &Settings) -> Result<()> {
+    let tag = version.map(|version| format!("v{}", version.trim().trim_start_matches('v')));
+    let release = github_release(RELEASE_REPOSITORY, tag.as_deref())?;
+    let release_version = release.tag_name.trim_start_matches('v');
+
+    if version.is_none() && !is_newer(release_version, CURRENT_VERSION) {
+        println!(
+            "{}",
+            format!("fpm {} is up to date.", CURRENT_VERSION).green()
+        );
+        return Ok(());
+    }
+    if check {
+        println!(
+            "fpm {} is available (this is {}). Run 'fpm self-update' to install it.",
+            release_version.green().bold(),
+            CURRENT_VERSION
+        );
+        return Ok(());
+    }
+
+    let current_exe = std::env::current_exe()
+        .and_then(fs::canonicalize)
+        .context("Failed to locate the running fpm executable")?;
+    println!(
+        "{} fpm {} (this is {})",
+        "Updating to".cyan(),
+        release_version,
+        CURRENT_VERSION
+    );
+
+    let work_dir = cache_dir(settings)?.join("self-update");
+    with_work_dir(&work_dir, |work_dir| {
+        let executable = download_release(&release, work_dir)?;
+        replace_executable(&executable, &current_exe)
+    })?;
+
+    println!(
+        "  {} fpm {} to {}",
+        "✓ Installed".green(),
+        release_version,
+        current_exe.display()
+    );
+    Ok(())
+}
+
+/// Target triple of the release archives built for this platform
+fn release_target() -> Option<&'static str> {
+    if cfg!(all(target_os = "windows", target_arch = "x86_64")) {
+        Some("x86_64-pc-windows-msvc")
+    } else if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
+        Some("x86_64-unknown-linux-gnu")
+    } else if cfg!(all(target_os = "linux", target_arch = "aarch64")) {
+        Some("aarch64-unknown-linux-gnu")
+    } else if cfg!(all(target_os = "macos", target_arch = "x86_64")) {
+        Some("x86_64-apple-darwin")
+    } else if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
+        Some("aarch64-apple-darwin")
+    } else {
+        None
+    }
+}
+
+/// Name of the release archive for `target`: a zip on Windows, a tarball elsewhere
+fn asset_name(target: &str) -> String {
+    format!("{}{}", archive_stem(target), archive_extension(target))
+}
+
+/// Name of the release archive for `target` without its extension
+fn archive_stem(target: &str) -> String {
+    format!("fpm-{}", target)
+}
+
+fn archive_extension(target: &str) -> &'static str {
+    if target.contains("windows") {
+        ".zip"
+    } else {
+        ".tar.gz"
+    }
+}
+
+/// Names the checksum of the archive for `target` may be published under: next to
+/// the archive (`fpm-<target>.zip.sha256`), or as the release workflow names it
+/// (`fpm-<target>.sha256`)
+fn checksum_names(target: &str) -> [String; 2] {
+    [
+        format!("{}.sha256", asset_name(target)),
+        format!("{}.sha256", archive_stem(target)),
+    ]
+}
+
+/// Whether `candidate` is a higher version than `current`. Versions that aren't
+/// semantic versions are never newer.
+fn is_newer(candidate: &str, current: &str) -> bool {
+    match (Version::parse(candidate), Version::parse(current)) {
+        (Ok(candidate), Ok(current)) => candidate > current,
+        _ => false,
+    }
+}
+
+/// Reads the checksum of `asset` from a checksum file, in the format of
+/// `sha256sum`: lines of a checksum and a file name, or a lone checksum
+fn parse_checksum(content: &str, asset: &str) -> Option<String> {
+    let entries: Vec<(&str, Option<&str>)> = content
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let checksum = parts.next()?;
+            Some((
+                checksum,
+                parts.next().map(|name| name.trim_start_matches('*')),
+            ))
+        })
+        .collect();
+
+    let (checksum, _) = entries.iter().find(|(_, name)| match name {
+        Some(name) => *name == asset,
+        None => entries.len() == 1,
+    })?;
+    is_sha256(checksum).then(|| checksum.to_lowercase())
+}
+
+/// Downloads the archive of `release` for this platform into `work_dir`, verifies
+/// it against the checksum published next to it and unpacks it. Returns the path
+/// of the unpacked executable.
+fn download_release(release: &Release, work_dir: &Path) -> Result<PathBuf> {
+    let target = release_target().context(
+        "fpm releases have no build for this platform. Install fpm with 'cargo install' instead.",
+    )?;
+    let name = asset_name(target);
+    let find_asset = |name: &str| release.assets.iter().find(|asset| asset.name == name);
+
+    let Some(archive) = find_asset(&name) else {
+        let published: Vec<&str> = release
+            .assets
+            .iter()
+            .map(|asset| asset.name.as_str())
+            .filter(|name| name.starts_with("fpm-") && !name.ends_with(".sha256"))
+            .collect();
+        anyhow::bail!(
+            "Release {} has no build for {} ({} not found; published: {}). Install fpm \
+            with 'cargo install' instead.",
+            release.tag_name,
+            target,
+            name,
+            if published.is_empty() {
+                "none".to_string()
+            } else {
+                published.join(", ")
+            }
+        );
+    };
+    let checksum_asset = checksum_names(target)
+        .iter()
+        .find_map(|name| find_asset(name))
+        .with_context(|| {
+            format!(
+                "Release {} publishes no checksum for {}, so the download can't be \
+                verified; fpm was left as it is. Download it by hand from the release page.",
+                release.tag_name, name
+            )
+        })?;
+
+    let checksum_path = work_dir.join("sha256");
+    download(&checksum_asset.browser_download_url, &checksum_path)?;
+    let expected = parse_checksum(&fs::read_to_string(&checksum_path)?, &name)
+        .with_context(|| format!("{} holds no checksum for {}", checksum_asset.name, name))?;
+
+    let archive_path = work_dir.join("archive");
+    let checksum = download(&archive.browser_download_url, &archive_path)?;
+    if checksum != expected {
+        anyhow::bail!(
+            "Checksum mismatch for {}: expected sha256 {}, got {}. The download may be \
+            corrupted or tampered with; fpm was left as it is.",
+            name,
+            expected,
+            checksum
+        );
+    }
+
+    let unpacked = work_dir.join("unpacked");
+    unpack(&archive_path, ArchiveFormat::from_url(&name)?, &unpacked)?;
+    WalkDir::new(&unpacked)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .find(|entry| entry.file_type().is_file() && entry.file_name() == BIN_NAME)
+        .map(|entry| entry.into_path())
+        .with_context(|| format!("{} holds no {}", name, BIN_NAME))
+}
+
+/// Downloads `url` to `destination`, returning the SHA-256 checksum of its content
+fn download(url: &str, destination: &Path) -> Result<String> {
+    let response = ureq::get(url)
+        .set("User-Agent", concat!("fpm/", env!("CARGO_PKG_VERSION")))
+        .call()
+        .with_context(|| format!("Failed to download {}", url))?;
+    save_hashed(response.into_reader(), destination)
+        .with_context(|| format!("Failed to download {}", url))
+}
+
+/// Puts `executable` in place of `current`. The new executable is copied next to
+/// the current one first, so both renames stay on one file system. The current
+/// executable is moved aside rather than overwritten, as Windows doesn't allow
+/// replacing a running program, and is removed if the system lets it.
+fn replace_executable(executable: &Path, current: &Path) -> Result<()> {
+    let file_name = current
+        .file_name()
+        .context("Invalid executable path")?
+        .to_string_lossy();
+    let staged = current.with_file_name(format!("{}.new", file_name));
+    let previous = current.with_file_name(format!("{}.old", file_name));
+
+    // Left over from an update on Windows
+    if previous.exists() {
+        let _ = fs::remove_file(&previous);
+    }
+
+    fs::copy(executable, &staged).with_context(|| {
+        format!(
+            "Failed to write {}. Run the update with permission to change it.",
+            staged.display()
+        )
+    })?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+    }
+
+    fs::rename(current, &previous)
+        .with_context(|| format!("Failed to move {} aside", current.display()))?;
+    if let Err(e) = fs::rename(&staged, current) {
+        let _ = fs::rename(&previous, current);
+        let _ = fs::remove_file(&staged);
+        return Err(e).with_context(|| format!("Failed to replace {}", current.display()));
+    }
+    let _ = fs::remove_file(&previous);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("0.2.0", "0.1.1-beta.1"));
+        assert!(is_newer("0.1.1", "0.1.1-beta.1"));
+        assert!(!is_newer("0.1.1-beta.1", "0.1.1-beta.1"));
+        assert!(!is_newer("0.1.0", "0.1.1-beta.1"));
+        assert!(!is_newer("latest", "0.1.0"));
+    }
+
+    #[test]
+    fn test_asset_name() {
+        assert_eq!(
+            asset_name("x86_64-pc-windows-msvc"),
+            "fpm-x86_64-pc-windows-msvc.zip"
+        );
+        assert_eq!(
+            asset_name("aarch64-apple-darwin"),
+            "fpm-aarch64-apple-darwin.tar.gz"
+        );
+    }
+
+    #[test]
+    fn test_checksum_names() {
+        assert_eq!(
+            checksum_names("x86_64-unknown-linux-gnu"),
+            [
+                "fpm-x86_64-unknown-linux-gnu.tar.gz.sha256",
+                "fpm-x86_64-unknown-linux-gnu.sha256"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_checksum() {
+        let checksum = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+        let asset = "fpm-x86_64-pc-windows-msvc.zip";
+
+        assert_eq!(
+            parse_checksum(&format!("{}  {}\n", checksum, asset), asset).as_deref(),
+            Some(checksum)
+        );
+        assert_eq!(
+            parse_checksum(&checksum.to_uppercase(), asset).as_deref(),
+            Some(checksum)
+        );
+        let listing = format!(
+            "{} *fpm-x86_64-apple-darwin.tar.gz\n{} *{}\n",
+            "0".repeat(64),
+            checksum,
+            asset
+        );
+        assert_eq!(parse_checksum(&listing, asset).as_deref(), Some(checksum));
+        assert_eq!(parse_checksum("not a checksum", asset), None);
+    }
+
+    #[test]
+    fn test_replace_executable() {
+        let dir = TempDir::new().unwrap();
+        let current = dir.path().join("fpm");
+        let executable = dir.path().join("download");
+        fs::write(&current, "old").unwrap();
+        fs::write(&executable, "new").unwrap();
+
+        replace_executable(&executable, &current).unwrap();
+
+        assert_eq!(fs::read_to_string(&current).unwrap(), "new");
+        assert!(!dir.path().join("fpm.new").exists());
+        assert!(!dir.path().join("fpm.old").exists());
+    }
+}
@@ -0,0 +1,52 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::path::Path;
+
+use crate::config::load_manifest;
+use crate::settings::Settings;
+use crate::signature::{sign_bundle, CONTENTS_FILE};
+
+/// Executes the sign command: signs the root directory of a source bundle with
+/// `key`, or the `ssh_key` setting if not given
+pub fn execute(manifest_path: &Path, key: Option<&Path>, settings: &Settings) -> Result<()> {
+    let manifest_path = if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    };
+
+    let manifest = load_manifest(&manifest_path)?;
+    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+
+    let Some(root) = &manifest.root else {
+        println!(
+            "{}",
+            "This bundle.toml has no 'root' defined. Nothing to sign.".yellow()
+        );
+        return Ok(());
+    };
+
+    let root_dir = parent_dir.join(root);
+    if !root_dir.exists() {
+        anyhow::bail!(
+            "Root directory '{}' does not exist. Cannot sign.",
+            root_dir.display()
+        );
+    }
+
+    let key = key.or(settings.ssh_key.as_deref()).context(
+        "No signing key given. Pass --key or set 'ssh_key' with 'fpm config set ssh_key <path>'.",
+    )?;
+
+    println!("  {} {}", "Signing".green(), root_dir.display());
+    let signature = sign_bundle(&root_dir, key)?;
+    println!(
+        "  {} {} and {}",
+        "✓ Wrote".green(),
+        root_dir.join(CONTENTS_FILE).display(),
+        signature.display()
+    );
+    println!("Commit both files with the bundle and publish it to share the signature.");
+
+    Ok(())
+}
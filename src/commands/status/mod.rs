@@ -1,66 +1,220 @@
 use anyhow::{Context, Result};
 use colored::Colorize;
-use std::path::Path;
-use std::sync::Arc;
-
-use crate::config::load_manifest;
-use crate::git::{GitCliOperations, GitOperations};
-use crate::types::{BundleStatus, BUNDLE_DIR};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+use crate::commands::install::is_shared_link;
+use crate::config::{find_aliases, load_manifest, load_manifest_with_settings};
+use crate::flatten::flattened_source;
+use crate::git::{git_operations_for, GitOperations};
+use crate::lockfile::lock_key;
+use crate::resolve::version_matches;
+use crate::settings::Settings;
+use crate::source::{git_bundle_status, source_for};
+use crate::state::load_state;
+use crate::types::{BundleDependency, BundleManifest, BundleState, BundleStatus, BUNDLE_DIR};
 
 /// Status entry for display
+#[derive(Debug, Clone, Serialize)]
 pub struct StatusEntry {
     pub name: String,
     pub path: String,
     pub status: BundleStatus,
     pub depth: usize,
+    /// Commit checked out, if the bundle is a clone or the install recorded one
+    pub commit: Option<String>,
+    /// Branch (or tag) the bundle was installed from, if recorded
+    pub branch: Option<String>,
+    /// Version declared by the installed bundle's own manifest
+    pub version: Option<String>,
+    /// Version or version requirement the manifest declaring the bundle asks for
+    pub required_version: Option<String>,
+    /// License declared by the installed bundle's own manifest
+    pub license: Option<String>,
+    /// Commits the bundle is ahead of its remote branch (with `--remote` only)
+    pub ahead: Option<usize>,
+    /// Commits the bundle is behind its remote branch (with `--remote` only)
+    pub behind: Option<usize>,
+    /// Name of the bundle this one shares a repository with, if it is an alias
+    pub alias_of: Option<String>,
+}
+
+/// Options for the status command
+#[derive(Debug, Clone, Default)]
+pub struct StatusOptions {
+    /// Print a JSON document instead of the table
+    pub json: bool,
+    /// Fail when any bundle is unsynced (including missing), drifted or mismatched
+    pub check: bool,
+    /// Fetch the branch each bundle tracks and count the commits it is ahead and behind
+    pub remote: bool,
+    /// Deepest level of nested bundles shown (0 shows the top-level bundles only)
+    pub depth: Option<usize>,
+    /// Only show this bundle (by install path, e.g. `ui-kit/base-styles`) and the
+    /// bundles nested in it
+    pub bundle: Option<String>,
+    /// Maximum number of bundles checked concurrently (0 or 1 checks them one at a time)
+    pub jobs: usize,
+    /// Settings from the config files and the environment
+    pub settings: Settings,
+}
+
+impl StatusOptions {
+    /// Returns true if the bundle with this install path is shown
+    fn includes(&self, key: &str) -> bool {
+        match &self.bundle {
+            Some(bundle) => {
+                key == bundle
+                    || key
+                        .strip_prefix(bundle.as_str())
+                        .is_some_and(|rest| rest.starts_with('/'))
+            }
+            None => true,
+        }
+    }
+
+    /// Returns true if the bundles nested in the one at `key`, one level below
+    /// `depth`, may be shown
+    fn descends_into(&self, key: &str, depth: usize) -> bool {
+        if self.depth.is_some_and(|limit| depth >= limit) {
+            return false;
+        }
+
+        // Bundles above the selected one are walked through without being shown
+        self.includes(key)
+            || self.bundle.as_ref().is_some_and(|bundle| {
+                bundle
+                    .strip_prefix(key)
+                    .is_some_and(|rest| rest.starts_with('/'))
+            })
+    }
 }
 
-/// Executes the status command with the default GitCliOperations
-pub fn execute(manifest_path: &Path) -> Result<()> {
-    let git_ops = Arc::new(GitCliOperations::new());
-    execute_with_git(manifest_path, git_ops)
+/// Document printed by `fpm status --json`
+#[derive(Serialize)]
+struct StatusReport<'a> {
+    manifest: String,
+    bundles: &'a [StatusEntry],
+}
+
+/// Executes the status command with the default git operations
+pub fn execute(manifest_path: &Path, options: &StatusOptions) -> Result<()> {
+    let git_ops = git_operations_for(&options.settings);
+    execute_with_git(manifest_path, options, git_ops)
 }
 
 /// Executes the status command with a custom GitOperations implementation
 /// This enables dependency injection for testing
-pub fn execute_with_git(manifest_path: &Path, git_ops: Arc<dyn GitOperations>) -> Result<()> {
+pub fn execute_with_git(
+    manifest_path: &Path,
+    options: &StatusOptions,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<()> {
     let manifest_path = if manifest_path.is_relative() {
         std::env::current_dir()?.join(manifest_path)
     } else {
         manifest_path.to_path_buf()
     };
 
+    let entries = collect_statuses(&manifest_path, git_ops, options)?;
+    if let (Some(bundle), true) = (&options.bundle, entries.is_empty()) {
+        anyhow::bail!(
+            "Bundle '{}' not found. Nested bundles are named by their install path, \
+            e.g. 'ui-kit/base-styles'.",
+            bundle
+        );
+    }
+
+    if options.json {
+        println!("{}", status_json(&manifest_path, &entries)?);
+    } else {
+        print_statuses(&manifest_path, &entries);
+    }
+
+    if options.check {
+        check_statuses(&entries)?;
+    }
+
+    Ok(())
+}
+
+/// Fails if any bundle is unsynced (which includes declared bundles that are not
+/// installed), drifted from its pinned commit or at a version the manifest doesn't
+/// ask for
+pub fn check_statuses(entries: &[StatusEntry]) -> Result<()> {
+    let failing: Vec<String> = entries
+        .iter()
+        .filter(|entry| {
+            matches!(
+                entry.status,
+                BundleStatus::Unsynced | BundleStatus::Drifted | BundleStatus::VersionMismatch
+            )
+        })
+        .map(|entry| format!("  {} ({})", entry.name, entry.status))
+        .collect();
+
+    if !failing.is_empty() {
+        anyhow::bail!(
+            "{} bundle(s) out of sync with their sources:\n{}\n\
+            Run 'fpm install' to install missing bundles, and push or discard local changes.",
+            failing.len(),
+            failing.join("\n")
+        );
+    }
+
+    Ok(())
+}
+
+/// Prints the status table and a summary of the counts
+fn print_statuses(manifest_path: &Path, entries: &[StatusEntry]) {
     println!("{} {}", "Bundle status for".cyan(), manifest_path.display());
     println!();
 
-    let entries = collect_all_statuses(&manifest_path, git_ops)?;
-
     // Display status
     if entries.is_empty() {
         println!("{}", "No bundles found.".yellow());
     } else {
         println!(
-            "{:<30} {:<10} {}",
+            "{:<30} {:<10} {:<24} {:<16} {}",
             "BUNDLE".bold(),
             "STATUS".bold(),
+            "VERSION".bold(),
+            "LICENSE".bold(),
             "PATH".bold()
         );
-        println!("{}", "-".repeat(70));
+        println!("{}", "-".repeat(111));
 
-        for entry in &entries {
+        for entry in entries {
             let indent = "  ".repeat(entry.depth);
             let status_colored = match entry.status {
                 BundleStatus::Synced => entry.status.to_string().green(),
                 BundleStatus::Unsynced => entry.status.to_string().yellow(),
                 BundleStatus::Source => entry.status.to_string().blue(),
+                BundleStatus::Drifted => entry.status.to_string().magenta(),
+                BundleStatus::Local => entry.status.to_string().cyan(),
+                BundleStatus::VersionMismatch => entry.status.to_string().red(),
             };
 
+            let alias_note = entry
+                .alias_of
+                .as_ref()
+                .map(|primary| format!(" (alias of {})", primary))
+                .unwrap_or_default();
+
             println!(
-                "{}{:<30} {:<10} {}",
+                "{}{:<30} {:<10} {:<24} {:<16} {}{}{}",
                 indent,
                 entry.name,
                 status_colored,
-                entry.path.dimmed()
+                version_column(entry),
+                entry.license.as_deref().unwrap_or("-"),
+                entry.path.dimmed(),
+                alias_note.cyan(),
+                remote_note(entry).yellow()
             );
         }
     }
@@ -79,21 +233,93 @@ pub fn execute_with_git(manifest_path: &Path, git_ops: Arc<dyn GitOperations>) -
         .iter()
         .filter(|e| e.status == BundleStatus::Source)
         .count();
+    let drifted_count = entries
+        .iter()
+        .filter(|e| e.status == BundleStatus::Drifted)
+        .count();
+    let local_count = entries
+        .iter()
+        .filter(|e| e.status == BundleStatus::Local)
+        .count();
+    let mismatch_count = entries
+        .iter()
+        .filter(|e| e.status == BundleStatus::VersionMismatch)
+        .count();
 
     println!(
-        "Total: {} synced, {} unsynced, {} source",
+        "Total: {} synced, {} unsynced, {} source, {} drifted, {} local, {} mismatched",
         synced_count.to_string().green(),
         unsynced_count.to_string().yellow(),
-        source_count.to_string().blue()
+        source_count.to_string().blue(),
+        drifted_count.to_string().magenta(),
+        local_count.to_string().cyan(),
+        mismatch_count.to_string().red()
     );
+}
 
-    Ok(())
+/// Shows the installed version next to the one the manifest asks for, e.g.
+/// "1.2.0 (wants ^2.0)"
+fn version_column(entry: &StatusEntry) -> String {
+    let installed = entry.version.as_deref().unwrap_or("-");
+    match &entry.required_version {
+        Some(required) => format!("{} (wants {})", installed, required),
+        None => installed.to_string(),
+    }
+}
+
+/// Describes how a bundle compares with its remote branch, e.g. " (behind by 3 commits)"
+fn remote_note(entry: &StatusEntry) -> String {
+    let commits = |count: usize| match count {
+        1 => "1 commit".to_string(),
+        count => format!("{} commits", count),
+    };
+
+    match (entry.ahead, entry.behind) {
+        (Some(0), Some(0)) | (None, _) | (_, None) => String::new(),
+        (Some(ahead), Some(0)) => format!(" (ahead by {})", commits(ahead)),
+        (Some(0), Some(behind)) => format!(" (behind by {})", commits(behind)),
+        (Some(ahead), Some(behind)) => {
+            format!(
+                " (ahead by {}, behind by {})",
+                commits(ahead),
+                commits(behind)
+            )
+        }
+    }
+}
+
+/// Renders the statuses as the JSON document printed by `fpm status --json`. Every
+/// entry has the same keys, with null for values that don't apply.
+pub fn status_json(manifest_path: &Path, entries: &[StatusEntry]) -> Result<String> {
+    let report = StatusReport {
+        manifest: manifest_path.to_string_lossy().to_string(),
+        bundles: entries,
+    };
+    serde_json::to_string_pretty(&report).context("Failed to serialize bundle statuses")
+}
+
+/// Returns the commit checked out at `path`, if it is a repository
+fn checked_out_commit(git_ops: &dyn GitOperations, path: &Path) -> Option<String> {
+    if path.exists() && git_ops.is_repository(path) {
+        git_ops.head_commit(path).ok()
+    } else {
+        None
+    }
 }
 
 /// Collects all bundle statuses without printing (useful for testing)
 pub fn collect_all_statuses(
     manifest_path: &Path,
     git_ops: Arc<dyn GitOperations>,
+) -> Result<Vec<StatusEntry>> {
+    collect_statuses(manifest_path, git_ops, &StatusOptions::default())
+}
+
+/// Collects the bundle statuses selected by `options` without printing
+pub fn collect_statuses(
+    manifest_path: &Path,
+    git_ops: Arc<dyn GitOperations>,
+    options: &StatusOptions,
 ) -> Result<Vec<StatusEntry>> {
     let manifest_path = if manifest_path.is_relative() {
         std::env::current_dir()?.join(manifest_path)
@@ -101,13 +327,13 @@ pub fn collect_all_statuses(
         manifest_path.to_path_buf()
     };
 
-    let manifest = load_manifest(&manifest_path)?;
+    let manifest = load_manifest_with_settings(&manifest_path, &options.settings)?;
     let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
 
     let mut entries = Vec::new();
 
     // Check if the current bundle is a source bundle
-    if manifest.is_source_bundle() {
+    if manifest.is_source_bundle() && options.bundle.is_none() {
         let root_path = parent_dir.join(manifest.root.as_ref().unwrap());
         let status = determine_source_status(git_ops.as_ref(), &root_path)?;
 
@@ -116,13 +342,39 @@ pub fn collect_all_statuses(
             path: root_path.to_string_lossy().to_string(),
             status,
             depth: 0,
+            commit: checked_out_commit(git_ops.as_ref(), &root_path),
+            branch: None,
+            version: manifest.version.clone(),
+            required_version: None,
+            license: manifest.license.clone(),
+            ahead: None,
+            behind: None,
+            alias_of: None,
         });
     }
 
-    // Check all bundles in .fpm directory
+    // Check all bundles in .fpm directory, and the declared ones missing from it
     let bundle_dir = parent_dir.join(BUNDLE_DIR);
-    if bundle_dir.exists() {
-        collect_bundle_statuses(git_ops.as_ref(), &bundle_dir, 0, &mut entries)?;
+    let mut checks = Vec::new();
+    collect_bundle_statuses(
+        &bundle_dir,
+        Some(&manifest),
+        "",
+        0,
+        options,
+        &mut entries,
+        &mut checks,
+    )?;
+
+    // Asking git about each bundle is the slow part, so it runs concurrently once
+    // the tree has been walked
+    let results = run_checks(git_ops.as_ref(), &checks, options)?;
+    for (check, result) in checks.iter().zip(results) {
+        let entry = &mut entries[check.index];
+        entry.status = result.status;
+        entry.commit = result.commit;
+        entry.ahead = result.ahead;
+        entry.behind = result.behind;
     }
 
     Ok(entries)
@@ -144,45 +396,92 @@ fn determine_source_status(git_ops: &dyn GitOperations, path: &Path) -> Result<B
     Ok(BundleStatus::Source)
 }
 
-fn determine_bundle_status(git_ops: &dyn GitOperations, path: &Path) -> Result<BundleStatus> {
+/// Determines the status of an installed bundle. `dependency` is its entry in the
+/// manifest declaring it, if the manifest could be read.
+pub fn determine_bundle_status(
+    git_ops: &dyn GitOperations,
+    path: &Path,
+    dependency: Option<&BundleDependency>,
+) -> Result<BundleStatus> {
     if !path.exists() {
         return Ok(BundleStatus::Unsynced);
     }
+    // Besides shared copies, only local bundles and working copies linked with
+    // `fpm link` are symlinks
+    if path.is_symlink() && !is_shared_link(path) {
+        return Ok(BundleStatus::Local);
+    }
 
-    // Check if it has a manifest with root (making it a source)
-    let manifest_path = path.join("bundle.toml");
-    if manifest_path.exists() {
-        if let Ok(manifest) = load_manifest(&manifest_path) {
-            if manifest.is_source_bundle() {
-                return Ok(BundleStatus::Source);
-            }
+    let status = match dependency {
+        Some(dependency) => source_for(dependency, git_ops).status(path, dependency)?,
+        // Without a manifest entry, the bundle can only be a clone
+        None => git_bundle_status(git_ops, path, None)?,
+    };
+
+    // A synced bundle may still be at a version the manifest doesn't ask for, e.g.
+    // after the requirement was changed without reinstalling
+    let required = dependency.and_then(BundleDependency::version_requirement);
+    if let (BundleStatus::Synced, Some(required), Some(version)) =
+        (status, required, installed_version(path))
+    {
+        let allow_prerelease = dependency.is_some_and(|dependency| dependency.allow_prerelease);
+        if version_matches(&version, required, allow_prerelease) == Some(false) {
+            return Ok(BundleStatus::VersionMismatch);
         }
     }
 
-    if !git_ops.is_repository(path) {
-        return Ok(BundleStatus::Unsynced);
-    }
+    Ok(status)
+}
 
-    if git_ops.has_local_changes(path)? {
-        return Ok(BundleStatus::Unsynced);
-    }
+/// Version declared by an installed bundle's own manifest, if it has one
+pub fn installed_version(path: &Path) -> Option<String> {
+    load_manifest(&path.join("bundle.toml")).ok()?.version
+}
+
+/// Installed bundle whose status has to be asked from git
+struct BundleCheck {
+    /// Index of the bundle's entry in the collected statuses
+    index: usize,
+    /// Install path of the bundle
+    key: String,
+    path: PathBuf,
+    dependency: Option<BundleDependency>,
+    recorded: Option<BundleState>,
+}
 
-    Ok(BundleStatus::Synced)
+/// What git said about a bundle
+struct CheckResult {
+    status: BundleStatus,
+    commit: Option<String>,
+    ahead: Option<usize>,
+    behind: Option<usize>,
 }
 
+/// Collects the statuses of the bundles in `bundle_dir`. `manifest` is the manifest
+/// declaring them, if it could be read, and `prefix` the install path of the bundle
+/// owning it ("" for the top level). Installed bundles get a placeholder status and
+/// a check in `checks` to fill it in.
 fn collect_bundle_statuses(
-    git_ops: &dyn GitOperations,
     bundle_dir: &Path,
+    manifest: Option<&BundleManifest>,
+    prefix: &str,
     depth: usize,
+    options: &StatusOptions,
     entries: &mut Vec<StatusEntry>,
+    checks: &mut Vec<BundleCheck>,
 ) -> Result<()> {
-    if !bundle_dir.exists() {
-        return Ok(());
-    }
+    let aliases: HashMap<String, String> = manifest.map(find_aliases).unwrap_or_default();
+    let state = load_state(bundle_dir).unwrap_or_default();
+
+    let mut seen = HashSet::new();
 
     // Read immediate children only (bundle directories)
-    for entry in std::fs::read_dir(bundle_dir)? {
-        let entry = entry?;
+    let children = if bundle_dir.exists() {
+        std::fs::read_dir(bundle_dir)?.collect::<std::io::Result<Vec<_>>>()?
+    } else {
+        Vec::new()
+    };
+    for entry in children {
         let path = entry.path();
 
         if !path.is_dir() {
@@ -199,25 +498,229 @@ fn collect_bundle_statuses(
             continue;
         }
 
-        let status = determine_bundle_status(git_ops, &path)?;
+        seen.insert(name.clone());
+        let key = lock_key(prefix, &name);
+        let nested_manifest =
+            load_manifest_with_settings(&path.join("bundle.toml"), &options.settings).ok();
+
+        if options.includes(&key) {
+            let dependency = manifest.and_then(|manifest| manifest.bundles.get(&name));
+            let recorded = state.bundles.get(&name);
+
+            checks.push(BundleCheck {
+                index: entries.len(),
+                key: key.clone(),
+                path: path.clone(),
+                dependency: dependency.cloned(),
+                recorded: recorded.cloned(),
+            });
+            entries.push(StatusEntry {
+                name: name.clone(),
+                path: path.to_string_lossy().to_string(),
+                // Filled in by the check
+                status: BundleStatus::Unsynced,
+                depth,
+                commit: None,
+                branch: recorded.map(|bundle| bundle.branch.clone()),
+                version: nested_manifest
+                    .as_ref()
+                    .and_then(|nested| nested.version.clone()),
+                required_version: required_version(dependency),
+                license: nested_manifest
+                    .as_ref()
+                    .and_then(|nested| nested.license.clone()),
+                ahead: None,
+                behind: None,
+                alias_of: aliases.get(&name).cloned(),
+            });
+        }
+
+        // Check for nested bundles, installed or declared
+        if !options.descends_into(&key, depth) {
+            continue;
+        }
+        let nested_bundle_dir = path.join(BUNDLE_DIR);
+        if nested_bundle_dir.exists() || nested_manifest.is_some() {
+            collect_bundle_statuses(
+                &nested_bundle_dir,
+                nested_manifest.as_ref(),
+                &key,
+                depth + 1,
+                options,
+                entries,
+                checks,
+            )?;
+        }
+    }
+
+    // Bundles recorded by a previous install but missing on disk need a reinstall
+    for (name, recorded) in state
+        .bundles
+        .iter()
+        .filter(|(name, _)| !seen.contains(*name) && options.includes(&lock_key(prefix, name)))
+    {
+        let path = bundle_dir.join(name);
 
         entries.push(StatusEntry {
             name: name.clone(),
             path: path.to_string_lossy().to_string(),
-            status,
+            status: BundleStatus::Unsynced,
             depth,
+            commit: recorded.rev.clone(),
+            branch: Some(recorded.branch.clone()),
+            version: None,
+            required_version: required_version(
+                manifest.and_then(|manifest| manifest.bundles.get(name)),
+            ),
+            license: None,
+            ahead: None,
+            behind: None,
+            alias_of: aliases.get(name).cloned(),
         });
+    }
 
-        // Check for nested bundles
-        let nested_bundle_dir = path.join(BUNDLE_DIR);
-        if nested_bundle_dir.exists() {
-            collect_bundle_statuses(git_ops, &nested_bundle_dir, depth + 1, entries)?;
+    // Bundles declared by the manifest but never installed, except optional ones
+    if let Some(manifest) = manifest {
+        let mut missing: Vec<&String> = manifest
+            .bundles
+            .iter()
+            .filter(|(name, dependency)| {
+                !dependency.optional
+                    && !seen.contains(*name)
+                    && !state.bundles.contains_key(*name)
+                    && options.includes(&lock_key(prefix, name))
+            })
+            .map(|(name, _)| name)
+            .collect();
+        missing.sort();
+
+        for name in missing {
+            entries.push(StatusEntry {
+                name: name.clone(),
+                path: bundle_dir.join(name).to_string_lossy().to_string(),
+                status: BundleStatus::Unsynced,
+                depth,
+                commit: None,
+                branch: None,
+                version: None,
+                required_version: required_version(manifest.bundles.get(name)),
+                license: None,
+                ahead: None,
+                behind: None,
+                alias_of: aliases.get(name).cloned(),
+            });
         }
     }
 
     Ok(())
 }
 
+/// Runs the checks on up to `options.jobs` threads. The results are in the order of
+/// the checks whichever finishes first, and the error is that of the first failing
+/// check.
+fn run_checks(
+    git_ops: &dyn GitOperations,
+    checks: &[BundleCheck],
+    options: &StatusOptions,
+) -> Result<Vec<CheckResult>> {
+    let workers = options.jobs.min(checks.len());
+    if workers <= 1 {
+        return checks
+            .iter()
+            .map(|check| run_check(git_ops, check, options))
+            .collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<CheckResult>>>> =
+        Mutex::new(checks.iter().map(|_| None).collect());
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(check) = checks.get(index) else {
+                    break;
+                };
+                let result = run_check(git_ops, check, options);
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every check runs"))
+        .collect()
+}
+
+/// Asks git for the status of one bundle
+fn run_check(
+    git_ops: &dyn GitOperations,
+    check: &BundleCheck,
+    options: &StatusOptions,
+) -> Result<CheckResult> {
+    let status = determine_bundle_status(git_ops, &check.path, check.dependency.as_ref())?;
+
+    let (ahead, behind) = match &check.recorded {
+        Some(recorded) if options.remote && status != BundleStatus::Local => {
+            compare_with_remote(git_ops, &check.path, recorded)
+                .unwrap_or_else(|error| {
+                    warn!(
+                        "Failed to compare {} with its remote: {:#}",
+                        check.key, error
+                    );
+                    None
+                })
+                .unzip()
+        }
+        _ => (None, None),
+    };
+
+    Ok(CheckResult {
+        status,
+        commit: checked_out_commit(git_ops, &check.path).or_else(|| {
+            check
+                .recorded
+                .as_ref()
+                .and_then(|bundle| bundle.rev.clone())
+        }),
+        ahead,
+        behind,
+    })
+}
+
+/// Version or version requirement a dependency asks for, if it doesn't track a
+/// branch or commit
+fn required_version(dependency: Option<&BundleDependency>) -> Option<String> {
+    dependency
+        .and_then(BundleDependency::version_requirement)
+        .map(str::to_string)
+}
+
+/// Fetches the branch an installed bundle tracks and counts the commits its
+/// checkout is ahead of and behind it. None for bundles installed from a version
+/// tag and bundles that aren't clones (archives, registries).
+fn compare_with_remote(
+    git_ops: &dyn GitOperations,
+    path: &Path,
+    recorded: &BundleState,
+) -> Result<Option<(usize, usize)>> {
+    if recorded.version.is_some() {
+        return Ok(None);
+    }
+
+    let repository = flattened_source(path).map_or(path.to_path_buf(), |source| source.checkout);
+    if !git_ops.is_repository(&repository) {
+        return Ok(None);
+    }
+
+    git_ops.fetch_remote_branch(&repository, &recorded.branch)?;
+    let counts = git_ops.ahead_behind(&repository, &format!("origin/{}", recorded.branch))?;
+    Ok(Some(counts))
+}
+
 #[cfg(test)]
 mod unit_tests {
     use super::*;
@@ -229,9 +732,58 @@ mod unit_tests {
             path: "/path/to/bundle".to_string(),
             status: BundleStatus::Synced,
             depth: 0,
+            commit: None,
+            branch: None,
+            version: None,
+            required_version: None,
+            license: None,
+            ahead: None,
+            behind: None,
+            alias_of: None,
         };
 
         assert_eq!(entry.name, "test-bundle");
         assert_eq!(entry.status, BundleStatus::Synced);
     }
+
+    #[test]
+    fn test_status_json() {
+        let entries = vec![StatusEntry {
+            name: "icons".to_string(),
+            path: "/work/.fpm/icons".to_string(),
+            status: BundleStatus::Drifted,
+            depth: 1,
+            commit: Some("abc123".to_string()),
+            branch: Some("v1.2.0".to_string()),
+            version: Some("1.2.0".to_string()),
+            required_version: Some("^1.2".to_string()),
+            license: Some("MIT".to_string()),
+            ahead: None,
+            behind: None,
+            alias_of: None,
+        }];
+
+        let json = status_json(Path::new("/work/bundle.toml"), &entries).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "manifest": "/work/bundle.toml",
+                "bundles": [{
+                    "name": "icons",
+                    "path": "/work/.fpm/icons",
+                    "status": "drifted",
+                    "depth": 1,
+                    "commit": "abc123",
+                    "branch": "v1.2.0",
+                    "version": "1.2.0",
+                    "required_version": "^1.2",
+                    "license": "MIT",
+                    "ahead": null,
+                    "behind": null,
+                    "alias_of": null
+                }]
+            })
+        );
+    }
 }
@@ -0,0 +1,114 @@
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::commands::status::determine_bundle_status;
+use crate::config::load_manifest;
+use crate::git::{git_operations_for, GitOperations};
+use crate::graph::{build_graph, DependencyNode};
+use crate::settings::Settings;
+use crate::types::BundleStatus;
+
+/// Executes the tree command with the default git operations
+pub fn execute(manifest_path: &Path, settings: &Settings) -> Result<()> {
+    let git_ops = git_operations_for(settings);
+    execute_with_git(manifest_path, git_ops)?;
+    Ok(())
+}
+
+/// Executes the tree command with a custom GitOperations implementation
+/// This enables dependency injection for testing. Returns the printed lines.
+pub fn execute_with_git(
+    manifest_path: &Path,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<Vec<String>> {
+    let manifest_path = if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    };
+
+    let manifest = load_manifest(&manifest_path)?;
+    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+    let root_name = manifest.name.clone().unwrap_or_else(|| {
+        parent_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| ".".to_string())
+    });
+
+    let nodes = build_graph(&manifest_path)?;
+
+    let mut lines = vec![root_name];
+    let mut seen = HashSet::new();
+    render_nodes(git_ops.as_ref(), &nodes, "", &mut seen, &mut lines)?;
+
+    for line in &lines {
+        println!("{}", line);
+    }
+
+    if lines.iter().any(|line| line.ends_with("(*)")) {
+        println!();
+        println!("(*) repository already shown above; its bundles are not repeated");
+    }
+
+    Ok(lines)
+}
+
+/// Renders nodes below `prefix` like `cargo tree`. Repositories that already
+/// appeared are marked with (*) and not expanded again.
+fn render_nodes(
+    git_ops: &dyn GitOperations,
+    nodes: &[DependencyNode],
+    prefix: &str,
+    seen: &mut HashSet<String>,
+    lines: &mut Vec<String>,
+) -> Result<()> {
+    for (index, node) in nodes.iter().enumerate() {
+        let last = index + 1 == nodes.len();
+        let (connector, child_prefix) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+
+        let source = node.dependency.source();
+        let duplicate = !seen.insert(source.clone());
+        let mut line = format!(
+            "{}{}{} {} ({})",
+            prefix,
+            connector,
+            node.name,
+            node.requested_ref(),
+            source
+        );
+        if let Some(marker) = status_marker(git_ops, node)? {
+            line.push_str(&format!(" [{}]", marker));
+        }
+        if duplicate {
+            line.push_str(" (*)");
+        }
+        lines.push(line);
+
+        if !duplicate {
+            let child_prefix = format!("{}{}", prefix, child_prefix);
+            render_nodes(git_ops, &node.children, &child_prefix, seen, lines)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns a marker for bundles that aren't simply synced
+fn status_marker(git_ops: &dyn GitOperations, node: &DependencyNode) -> Result<Option<String>> {
+    if !node.is_installed() {
+        return Ok(Some("not installed".to_string()));
+    }
+
+    let status = determine_bundle_status(git_ops, &node.path, Some(&node.dependency))?;
+    Ok(match status {
+        BundleStatus::Synced => None,
+        other => Some(other.to_string()),
+    })
+}
@@ -0,0 +1,267 @@
+use anyhow::Result;
+use colored::Colorize;
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::commands::install::{
+    check_bundle_exists, install_with_git, InstallOptions, UpdateScope,
+};
+use crate::git::{git_operations_for, GitOperations};
+use crate::settings::Settings;
+use crate::types::{LockedBundle, Lockfile};
+
+/// Length of abbreviated commit hashes in the report
+const SHORT_COMMIT_LEN: usize = 7;
+
+/// How a bundle's lock entry changed during an update
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BundleChange {
+    /// Newly installed bundle
+    Added(LockedBundle),
+    /// Bundle no longer declared by any manifest
+    Removed(LockedBundle),
+    /// Bundle moved to another ref or commit
+    Updated {
+        from: LockedBundle,
+        to: LockedBundle,
+    },
+    /// Bundle is still at the same commit
+    Unchanged(LockedBundle),
+}
+
+/// How an update installs the bundles it moves forward
+#[derive(Debug, Clone, Default)]
+pub struct UpdateOptions {
+    /// Number of bundles fetched concurrently
+    pub jobs: usize,
+    /// Bundles that fail keep their lock entries while the others are updated
+    pub keep_going: bool,
+    /// Reinstall bundles with uncommitted changes from scratch instead of skipping them
+    pub force: bool,
+    /// Stash the uncommitted changes of bundles, update them and apply the changes
+    /// again instead of skipping them
+    pub autostash: bool,
+    /// Install nested bundles with the same source once and link them where needed
+    pub dedupe: bool,
+    /// Install bundles requiring incompatible versions of the same bundle
+    pub allow_duplicates: bool,
+    /// Don't run the `[hooks]` of the manifest or of installed bundles
+    pub no_hooks: bool,
+    /// Settings from the config files and the environment
+    pub settings: Settings,
+}
+
+/// Executes the update command with the default git operations
+pub fn execute(manifest_path: &Path, bundle: Option<&str>, options: &UpdateOptions) -> Result<()> {
+    let git_ops = git_operations_for(&options.settings);
+    execute_with_git(manifest_path, bundle, options, git_ops)?;
+    Ok(())
+}
+
+/// Executes the update command with a custom GitOperations implementation
+/// This enables dependency injection for testing. Returns the change of every
+/// bundle in scope, keyed by its lockfile key.
+pub fn execute_with_git(
+    manifest_path: &Path,
+    bundle: Option<&str>,
+    options: &UpdateOptions,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<Vec<(String, BundleChange)>> {
+    let manifest_path = if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    };
+
+    let update = match bundle {
+        Some(name) => {
+            check_bundle_exists(&manifest_path, name)?;
+            UpdateScope::Bundle(name.to_string())
+        }
+        None => UpdateScope::All,
+    };
+
+    let install_options = InstallOptions {
+        update: update.clone(),
+        only: bundle.map(str::to_string),
+        jobs: options.jobs,
+        keep_going: options.keep_going,
+        force: options.force,
+        autostash: options.autostash,
+        dedupe: options.dedupe,
+        allow_duplicates: options.allow_duplicates,
+        no_hooks: options.no_hooks,
+        settings: options.settings.clone(),
+        ..Default::default()
+    };
+    let outcome = install_with_git(&manifest_path, &install_options, git_ops)?;
+
+    let changes = collect_changes(&outcome.previous_lock, &outcome.lock, &update);
+    print_changes(&changes);
+
+    Ok(changes)
+}
+
+/// Compares the lock entries in scope before and after an update
+fn collect_changes(
+    previous: &Lockfile,
+    current: &Lockfile,
+    scope: &UpdateScope,
+) -> Vec<(String, BundleChange)> {
+    let keys: BTreeSet<&String> = previous
+        .bundles
+        .keys()
+        .chain(current.bundles.keys())
+        .filter(|key| scope.includes(key))
+        .collect();
+
+    keys.into_iter()
+        .filter_map(|key| {
+            let change = match (previous.bundles.get(key), current.bundles.get(key)) {
+                (None, Some(to)) => BundleChange::Added(to.clone()),
+                (Some(from), None) => BundleChange::Removed(from.clone()),
+                (Some(from), Some(to)) if from == to => BundleChange::Unchanged(to.clone()),
+                (Some(from), Some(to)) => BundleChange::Updated {
+                    from: from.clone(),
+                    to: to.clone(),
+                },
+                (None, None) => return None,
+            };
+            Some((key.clone(), change))
+        })
+        .collect()
+}
+
+fn print_changes(changes: &[(String, BundleChange)]) {
+    println!();
+
+    let mut changed = 0;
+    for (key, change) in changes {
+        match change {
+            BundleChange::Added(to) => {
+                changed += 1;
+                println!("  {} {} {}", "Added".green(), key, describe(to));
+            }
+            BundleChange::Removed(from) => {
+                changed += 1;
+                println!("  {} {} {}", "Removed".red(), key, describe(from));
+            }
+            BundleChange::Updated { from, to } => {
+                changed += 1;
+                println!(
+                    "  {} {} {} -> {}",
+                    "Updated".green(),
+                    key,
+                    describe(from),
+                    describe(to)
+                );
+            }
+            BundleChange::Unchanged(at) => {
+                println!("  {} {} {}", "Unchanged".dimmed(), key, describe(at));
+            }
+        }
+    }
+
+    if changed == 0 {
+        println!("{}", "All bundles are up to date.".green().bold());
+    } else {
+        println!(
+            "{}",
+            format!("{} bundle(s) changed.", changed).green().bold()
+        );
+    }
+}
+
+/// Describes a lock entry as `ref (short commit)`
+fn describe(locked: &LockedBundle) -> String {
+    let short = locked
+        .commit
+        .get(..SHORT_COMMIT_LEN)
+        .unwrap_or(&locked.commit);
+    format!("{} ({})", locked.branch, short)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn locked(branch: &str, commit: &str) -> LockedBundle {
+        LockedBundle {
+            git: "https://github.com/example/ui-kit.git".to_string(),
+            branch: branch.to_string(),
+            version: None,
+            commit: commit.to_string(),
+            checksum: None,
+        }
+    }
+
+    #[test]
+    fn test_collect_changes_classifies_entries() {
+        let mut previous = Lockfile::default();
+        previous
+            .bundles
+            .insert("icons".to_string(), locked("main", "aaa"));
+        previous
+            .bundles
+            .insert("ui-kit".to_string(), locked("v1.0.0", "bbb"));
+        previous
+            .bundles
+            .insert("ui-kit/old".to_string(), locked("main", "ccc"));
+
+        let mut current = Lockfile::default();
+        current
+            .bundles
+            .insert("icons".to_string(), locked("main", "aaa"));
+        current
+            .bundles
+            .insert("ui-kit".to_string(), locked("v1.1.0", "ddd"));
+        current
+            .bundles
+            .insert("ui-kit/new".to_string(), locked("main", "eee"));
+
+        let changes = collect_changes(&previous, &current, &UpdateScope::All);
+        assert_eq!(
+            changes,
+            vec![
+                (
+                    "icons".to_string(),
+                    BundleChange::Unchanged(locked("main", "aaa"))
+                ),
+                (
+                    "ui-kit".to_string(),
+                    BundleChange::Updated {
+                        from: locked("v1.0.0", "bbb"),
+                        to: locked("v1.1.0", "ddd"),
+                    }
+                ),
+                (
+                    "ui-kit/new".to_string(),
+                    BundleChange::Added(locked("main", "eee"))
+                ),
+                (
+                    "ui-kit/old".to_string(),
+                    BundleChange::Removed(locked("main", "ccc"))
+                ),
+            ]
+        );
+
+        // A single-bundle update only reports that bundle and its nested bundles
+        let scoped = collect_changes(
+            &previous,
+            &current,
+            &UpdateScope::Bundle("ui-kit".to_string()),
+        );
+        assert_eq!(scoped.len(), 3);
+        assert!(scoped.iter().all(|(key, _)| key.starts_with("ui-kit")));
+    }
+
+    #[test]
+    fn test_describe_shortens_commit() {
+        assert_eq!(
+            describe(&locked("v1.0.0", "0123456789abcdef")),
+            "v1.0.0 (0123456)"
+        );
+        assert_eq!(describe(&locked("main", "abc")), "main (abc)");
+    }
+}
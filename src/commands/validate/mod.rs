@@ -0,0 +1,419 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use semver::{Version, VersionReq};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::config::parse_manifest_with_settings;
+use crate::git::{git_operations_for, GitOperations};
+use crate::resolve::select_tag;
+use crate::settings::{ssh_url_host, Settings};
+use crate::types::{BundleDependency, BundleManifest};
+
+/// A problem `fpm validate` found in a manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    /// Bundle the problem is about, if any
+    pub bundle: Option<String>,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            bundle: None,
+            message: message.into(),
+        }
+    }
+
+    fn bundle(name: &str, message: impl Into<String>) -> Self {
+        Self {
+            bundle: Some(name.to_string()),
+            message: message.into(),
+        }
+    }
+}
+
+/// Executes the validate command with the default git operations.
+/// Fails if any problem is found, so it can gate CI.
+pub fn execute(manifest_path: &Path, online: bool, settings: &Settings) -> Result<()> {
+    let git_ops = git_operations_for(settings);
+    let problems = validate_manifest(manifest_path, online, settings, git_ops)?;
+
+    println!("{} {}", "Validating".cyan(), manifest_path.display());
+    println!();
+
+    if problems.is_empty() {
+        println!("{}", "No problems found.".green().bold());
+        return Ok(());
+    }
+
+    for problem in &problems {
+        match &problem.bundle {
+            Some(bundle) => println!(
+                "  {} bundle '{}': {}",
+                "✗".red().bold(),
+                bundle,
+                problem.message
+            ),
+            None => println!("  {} {}", "✗".red().bold(), problem.message),
+        }
+    }
+    println!();
+    anyhow::bail!(
+        "{} problem(s) found in {}",
+        problems.len(),
+        manifest_path.display()
+    )
+}
+
+/// Checks a manifest beyond what loading it checks. With `online`, the repository
+/// of every git bundle is also looked up, and its version requirement checked
+/// against the published versions.
+pub fn validate_manifest(
+    manifest_path: &Path,
+    online: bool,
+    settings: &Settings,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<Vec<Problem>> {
+    let content = fs::read_to_string(manifest_path)
+        .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+
+    let (manifest, mut problems) = lint(&content, settings);
+    if let (Some(manifest), true) = (&manifest, online) {
+        problems.extend(check_remotes(manifest, git_ops.as_ref()));
+    }
+    Ok(problems)
+}
+
+/// Checks the content of a bundle.toml without looking anything up. Returns the
+/// manifest, if it could be read, along with the problems found.
+pub fn lint(content: &str, settings: &Settings) -> (Option<BundleManifest>, Vec<Problem>) {
+    let mut problems = Vec::new();
+
+    // serde skips unknown keys, so a misspelled one silently does nothing
+    let mut unknown = Vec::new();
+    let raw: BundleManifest =
+        match serde_ignored::deserialize(toml::Deserializer::new(content), |path| {
+            unknown.push(path.to_string())
+        }) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                problems.push(Problem::new(format!(
+                    "bundle.toml can't be read: {}",
+                    e.to_string().trim()
+                )));
+                return (None, problems);
+            }
+        };
+    problems.extend(
+        unknown
+            .into_iter()
+            .map(|key| Problem::new(format!("unknown key '{}'", key))),
+    );
+
+    // The checks made when loading the manifest; the others go on with it as written
+    let manifest = match parse_manifest_with_settings(content, settings) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            problems.push(Problem::new(format!("{:#}", e)));
+            raw
+        }
+    };
+
+    let mut names: Vec<&String> = manifest.bundles.keys().collect();
+    names.sort();
+    for name in &names {
+        let dependency = &manifest.bundles[*name];
+        if let Some(version) = dependency.version_requirement() {
+            if let Err(e) = parse_version(version) {
+                problems.push(Problem::bundle(
+                    name,
+                    format!("invalid version requirement '{}': {}", version, e),
+                ));
+            }
+        }
+        for pattern in patterns(dependency) {
+            if escapes_root(pattern) {
+                problems.push(Problem::bundle(
+                    name,
+                    format!(
+                        "pattern '{}' points outside the bundle; include and exclude \
+                        patterns are relative to the bundle root",
+                        pattern
+                    ),
+                ));
+            }
+        }
+    }
+
+    problems.extend(duplicate_sources(&manifest));
+    (Some(manifest), problems)
+}
+
+/// Parses a version as `version` takes it: an exact version or a requirement
+fn parse_version(version: &str) -> std::result::Result<(), semver::Error> {
+    if Version::parse(version).is_ok() {
+        return Ok(());
+    }
+    VersionReq::parse(version).map(|_| ())
+}
+
+fn patterns(dependency: &BundleDependency) -> impl Iterator<Item = &String> {
+    dependency
+        .include
+        .iter()
+        .chain(dependency.exclude.iter())
+        .flatten()
+}
+
+/// Whether a pattern climbs out of the bundle root, or is an absolute Windows path
+fn escapes_root(pattern: &str) -> bool {
+    let has_drive = pattern
+        .as_bytes()
+        .get(1..3)
+        .is_some_and(|rest| rest[0] == b':' && (rest[1] == b'/' || rest[1] == b'\\'))
+        && pattern.as_bytes()[0].is_ascii_alphabetic();
+    has_drive || pattern.split(['/', '\\']).any(|segment| segment == "..")
+}
+
+/// Bundles installing the same source (the same subdirectory of the same repository,
+/// archive, artifact or S3 prefix) under different names
+fn duplicate_sources(manifest: &BundleManifest) -> Vec<Problem> {
+    let mut sources: BTreeMap<String, Vec<&String>> = BTreeMap::new();
+    for (name, dependency) in &manifest.bundles {
+        let Some(source) = source_identity(dependency) else {
+            continue;
+        };
+        sources.entry(source).or_default().push(name);
+    }
+
+    sources
+        .into_values()
+        .filter(|names| names.len() > 1)
+        .map(|mut names| {
+            names.sort();
+            let (first, others) = names.split_first().unwrap();
+            let others: Vec<String> = others.iter().map(|name| format!("'{}'", name)).collect();
+            Problem::bundle(
+                first,
+                format!(
+                    "installs the same source as {}; give it one name, or tell them apart \
+                    with path",
+                    others.join(", ")
+                ),
+            )
+        })
+        .collect()
+}
+
+/// What a bundle installs, spelled the same however its URL is written: the
+/// HTTPS, SSH and scp-like URLs of a repository are the same source, and case,
+/// `.git` and a trailing slash don't matter
+fn source_identity(dependency: &BundleDependency) -> Option<String> {
+    let source = if !dependency.git.is_empty() {
+        repository_identity(&dependency.git)
+    } else {
+        dependency
+            .url
+            .as_ref()
+            .or(dependency.oci.as_ref())
+            .or(dependency.s3.as_ref())?
+            .trim()
+            .to_string()
+    };
+    let path = dependency
+        .path
+        .as_ref()
+        .map(|path| path.to_string_lossy().trim_matches('/').to_string())
+        .unwrap_or_default();
+    Some(format!("{}#{}", source, path))
+}
+
+fn repository_identity(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+
+    let location = match url::Url::parse(url) {
+        Ok(parsed) if parsed.has_host() => {
+            format!("{}{}", parsed.host_str().unwrap_or_default(), parsed.path())
+        }
+        // scp-like syntax: [user@]host:path
+        _ => match url.split_once(':') {
+            Some((host, path)) if ssh_url_host(url).is_some() => format!(
+                "{}/{}",
+                host.rsplit('@').next().unwrap_or(host),
+                path.trim_start_matches('/')
+            ),
+            _ => url.to_string(),
+        },
+    };
+    location.to_lowercase()
+}
+
+/// Looks up the repository of every git bundle, and checks that a published
+/// version matches its version requirement
+fn check_remotes(manifest: &BundleManifest, git_ops: &dyn GitOperations) -> Vec<Problem> {
+    let mut names: Vec<&String> = manifest.bundles.keys().collect();
+    names.sort();
+
+    let mut problems = Vec::new();
+    for name in names {
+        let dependency = &manifest.bundles[name];
+        if dependency.git.is_empty() {
+            continue;
+        }
+
+        let tags = match git_ops.list_remote_tags(&dependency.git, dependency.ssh_key.as_deref()) {
+            Ok(tags) => tags,
+            Err(e) => {
+                let error = format!("{:#}", e);
+                problems.push(Problem::bundle(
+                    name,
+                    format!(
+                        "can't reach {}: {}",
+                        dependency.git,
+                        error.lines().next().unwrap_or_default()
+                    ),
+                ));
+                continue;
+            }
+        };
+
+        let Some(version) = dependency.version_requirement() else {
+            continue;
+        };
+        if parse_version(version).is_err() {
+            continue;
+        }
+        if let Err(e) = select_tag(&tags, version, dependency.allow_prerelease) {
+            problems.push(Problem::bundle(name, format!("{:#}", e)));
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    const HEADER: &str = "fpm_version = \"0.1.0\"\nidentifier = \"fpm-bundle\"\n";
+
+    fn messages(content: &str) -> Vec<String> {
+        let (_, problems) = lint(&format!("{}{}", HEADER, content), &Settings::default());
+        problems
+            .into_iter()
+            .map(|problem| match problem.bundle {
+                Some(bundle) => format!("{}: {}", bundle, problem.message),
+                None => problem.message,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_manifest_has_no_problems() {
+        let problems = messages(
+            r#"
+            [bundles.icons]
+            git = "https://github.com/example/icons.git"
+            version = "^1.2"
+            include = ["svg", "./png/**"]
+            exclude = ["/drafts"]
+            "#,
+        );
+        assert!(problems.is_empty(), "{:?}", problems);
+    }
+
+    #[test]
+    fn test_lint_reports_unknown_keys() {
+        let problems = messages(
+            r#"
+            descripton = "Icons"
+
+            [bundles.icons]
+            git = "https://github.com/example/icons.git"
+            brnach = "main"
+            "#,
+        );
+        assert_eq!(
+            problems,
+            vec![
+                "unknown key 'descripton'".to_string(),
+                "unknown key 'bundles.icons.brnach'".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_reports_invalid_versions_and_escaping_patterns() {
+        let problems = messages(
+            r#"
+            [bundles.icons]
+            git = "https://github.com/example/icons.git"
+            version = "^1.x.y"
+            include = ["../secrets", "svg"]
+            exclude = ["C:/temp"]
+            "#,
+        );
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert!(problems[0].starts_with("icons: invalid version requirement '^1.x.y'"));
+        assert!(problems[1].contains("'../secrets'"));
+        assert!(problems[2].contains("'C:/temp'"));
+    }
+
+    #[test]
+    fn test_lint_reports_duplicate_sources() {
+        let problems = messages(
+            r#"
+            [bundles.icons]
+            git = "https://github.com/Example/icons.git"
+
+            [bundles.icons-ssh]
+            git = "git@github.com:example/icons"
+
+            [bundles.icons-png]
+            git = "https://github.com/example/icons"
+            path = "png"
+
+            [bundles.fonts]
+            git = "ssh://git@github.com/example/fonts.git"
+            "#,
+        );
+        assert_eq!(
+            problems,
+            vec![
+                "icons: installs the same source as 'icons-ssh'; give it one name, or \
+                tell them apart with path"
+                    .to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_reports_revs_that_are_not_commit_ids() {
+        let problems = messages(
+            r#"
+            [bundles.icons]
+            git = "https://github.com/example/icons.git"
+            rev = ""
+            "#,
+        );
+        assert_eq!(
+            problems,
+            vec![
+                "Invalid fpm manifest: rev '' of bundle 'icons' must be a commit id of at \
+                least 7 lowercase hex digits"
+                    .to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_reports_unreadable_manifests() {
+        let problems = messages("[bundles.icons\n");
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("bundle.toml can't be read"));
+    }
+}
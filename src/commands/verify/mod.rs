@@ -0,0 +1,206 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::fmt;
+use std::path::Path;
+
+use crate::checksum::{
+    compare, hash_tree, load_file_hashes, tree_checksum, FileChange, FileHashes,
+};
+use crate::commands::install::is_shared_link;
+use crate::lockfile::{installed_path, load_lockfile, lockfile_path};
+use crate::types::BUNDLE_DIR;
+
+/// How an installed bundle compares with the checksum in fpm.lock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyStatus {
+    /// The files are those installed
+    Ok,
+    /// Files were changed, added or removed since the install
+    Modified,
+    /// The bundle isn't installed, e.g. an optional bundle or one of a group left
+    /// out (`fpm status` reports bundles that should be)
+    NotInstalled,
+    /// The bundle is linked to a working copy with `fpm link`
+    Linked,
+    /// fpm.lock has no checksum for the bundle
+    Unrecorded,
+}
+
+impl fmt::Display for VerifyStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyStatus::Ok => write!(f, "ok"),
+            VerifyStatus::Modified => write!(f, "modified"),
+            VerifyStatus::NotInstalled => write!(f, "not installed"),
+            VerifyStatus::Linked => write!(f, "linked"),
+            VerifyStatus::Unrecorded => write!(f, "unrecorded"),
+        }
+    }
+}
+
+/// Result of verifying one bundle
+#[derive(Debug, Clone)]
+pub struct VerifyEntry {
+    /// Install path of the bundle, e.g. "ui-kit/base-styles"
+    pub bundle: String,
+    pub status: VerifyStatus,
+    /// Files that differ from the install, if the install recorded their hashes
+    pub changes: Option<Vec<(String, FileChange)>>,
+}
+
+/// Executes the verify command. Fails if any bundle was modified.
+pub fn execute(manifest_path: &Path) -> Result<()> {
+    let manifest_path = if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    };
+
+    let entries = verify_bundles(&manifest_path)?;
+    print_entries(&manifest_path, &entries);
+
+    let failed: Vec<&str> = entries
+        .iter()
+        .filter(|entry| entry.status == VerifyStatus::Modified)
+        .map(|entry| entry.bundle.as_str())
+        .collect();
+    if !failed.is_empty() {
+        anyhow::bail!(
+            "{} bundle(s) differ from the checksums in fpm.lock: {}. \
+            Run 'fpm install --force' to restore them.",
+            failed.len(),
+            failed.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Hashes the installed files of every bundle in fpm.lock and compares them with
+/// the recorded checksums, in install path order
+pub fn verify_bundles(manifest_path: &Path) -> Result<Vec<VerifyEntry>> {
+    let lock_path = lockfile_path(manifest_path)?;
+    let lockfile = load_lockfile(&lock_path)?.with_context(|| {
+        format!(
+            "{} does not exist. Run 'fpm install' to create it.",
+            lock_path.display()
+        )
+    })?;
+    let bundle_dir = manifest_path
+        .parent()
+        .context("Invalid manifest path")?
+        .join(BUNDLE_DIR);
+
+    let mut entries = Vec::new();
+    for (key, locked) in &lockfile.bundles {
+        let path = installed_path(&bundle_dir, key);
+        let mut entry = VerifyEntry {
+            bundle: key.clone(),
+            status: VerifyStatus::Ok,
+            changes: None,
+        };
+
+        if is_linked(&bundle_dir, key) {
+            entry.status = VerifyStatus::Linked;
+        } else if !path.exists() {
+            entry.status = VerifyStatus::NotInstalled;
+        } else if let Some(checksum) = &locked.checksum {
+            let files =
+                hash_tree(&path).with_context(|| format!("Failed to hash bundle: {}", key))?;
+            if tree_checksum(&files) != *checksum {
+                entry.status = VerifyStatus::Modified;
+                entry.changes = recorded_hashes(&path)?.map(|recorded| compare(&recorded, &files));
+            }
+        } else {
+            entry.status = VerifyStatus::Unrecorded;
+        }
+
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+/// Returns true if the bundle, or one it is nested in, is linked to a working copy
+fn is_linked(bundle_dir: &Path, key: &str) -> bool {
+    key.match_indices('/')
+        .map(|(index, _)| &key[..index])
+        .chain([key])
+        .any(|key| {
+            let path = installed_path(bundle_dir, key);
+            path.is_symlink() && !is_shared_link(&path)
+        })
+}
+
+/// File hashes the install recorded for the bundle at `path`. They are kept next to
+/// the bundle's actual directory, which for a shared bundle is its shared copy.
+fn recorded_hashes(path: &Path) -> Result<Option<FileHashes>> {
+    let path = path.canonicalize()?;
+    let (Some(bundle_dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Ok(None);
+    };
+    load_file_hashes(bundle_dir, &name.to_string_lossy())
+}
+
+/// Prints the result of every bundle, with the files of modified ones
+fn print_entries(manifest_path: &Path, entries: &[VerifyEntry]) {
+    println!(
+        "{} {}",
+        "Verifying bundles of".cyan(),
+        manifest_path.display()
+    );
+    println!();
+
+    if entries.is_empty() {
+        println!("{}", "No bundles locked.".yellow());
+        return;
+    }
+
+    for entry in entries {
+        let status = match entry.status {
+            VerifyStatus::Ok => entry.status.to_string().green(),
+            VerifyStatus::Modified => entry.status.to_string().red(),
+            VerifyStatus::NotInstalled => entry.status.to_string().dimmed(),
+            VerifyStatus::Linked => entry.status.to_string().cyan(),
+            VerifyStatus::Unrecorded => entry.status.to_string().yellow(),
+        };
+        println!("  {:<14} {}", status, entry.bundle);
+
+        match &entry.changes {
+            Some(changes) if !changes.is_empty() => {
+                for (file, change) in changes {
+                    println!("      {:<10} {}", change.to_string().yellow(), file);
+                }
+            }
+            // The files match the last install here, so fpm.lock was written by an
+            // install of other files, e.g. on another machine
+            Some(_) => println!(
+                "      {}",
+                "files match the last install, but not the checksum in fpm.lock".dimmed()
+            ),
+            None if entry.status == VerifyStatus::Modified => {
+                println!("      {}", "no file hashes recorded".dimmed())
+            }
+            None => {}
+        }
+    }
+
+    let count = |status: VerifyStatus| {
+        entries
+            .iter()
+            .filter(|entry| entry.status == status)
+            .count()
+    };
+    println!();
+    println!(
+        "Total: {} ok, {} modified, {} not installed, {} linked, {} unrecorded",
+        count(VerifyStatus::Ok).to_string().green(),
+        count(VerifyStatus::Modified).to_string().red(),
+        count(VerifyStatus::NotInstalled),
+        count(VerifyStatus::Linked).to_string().cyan(),
+        count(VerifyStatus::Unrecorded).to_string().yellow()
+    );
+    if count(VerifyStatus::Unrecorded) > 0 {
+        println!("Run 'fpm install' to record checksums of the unrecorded bundles.");
+    }
+}
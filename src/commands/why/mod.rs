@@ -0,0 +1,48 @@
+use anyhow::Result;
+use colored::Colorize;
+use std::path::Path;
+
+use crate::graph::{build_graph, find_paths, DependencyNode};
+
+/// Executes the why command: prints every dependency path from the manifest to
+/// the bundle named `name` (a bundle name or an install path like "ui-kit/base-styles")
+pub fn execute(manifest_path: &Path, name: &str) -> Result<()> {
+    let manifest_path = if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    };
+
+    let graph = build_graph(&manifest_path)?;
+    let paths = find_paths(&graph, name);
+
+    if paths.is_empty() {
+        anyhow::bail!(
+            "Bundle '{}' is not declared by {} or any installed bundle",
+            name,
+            manifest_path.display()
+        );
+    }
+
+    println!("{} is required by {} path(s):", name.bold(), paths.len());
+    println!();
+
+    for path in &paths {
+        println!("  {}", describe_path(path));
+        if let Some(target) = path.last() {
+            println!("    {}", target.path.display().to_string().dimmed());
+        }
+    }
+
+    Ok(())
+}
+
+/// Formats a dependency path as `bundle.toml -> ui-kit (^1.0) -> base-styles (main)`
+fn describe_path(path: &[&DependencyNode]) -> String {
+    let steps: Vec<String> = path
+        .iter()
+        .map(|node| format!("{} ({})", node.name, node.requested_ref()))
+        .collect();
+
+    format!("bundle.toml -> {}", steps.join(" -> "))
+}
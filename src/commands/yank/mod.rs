@@ -0,0 +1,107 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::commands::publish::publish_targets;
+use crate::config::load_manifest_with_settings;
+use crate::git::{git_operations_for, CommitAuthor, GitOperations};
+use crate::resolve::yank_tag;
+use crate::settings::Settings;
+
+/// Executes the yank command with the default git operations
+pub fn execute(
+    manifest_path: &Path,
+    version: &str,
+    reason: Option<&str>,
+    settings: &Settings,
+) -> Result<()> {
+    let git_ops = git_operations_for(settings);
+    execute_with_git(manifest_path, version, reason, settings, git_ops)
+}
+
+/// Marks a published version of a source bundle as yanked, so installs resolving
+/// to it warn about it. The mark is a `yanked/<tag>` tag pushed to every remote the
+/// bundle is published to, annotated with `reason`.
+pub fn execute_with_git(
+    manifest_path: &Path,
+    version: &str,
+    reason: Option<&str>,
+    settings: &Settings,
+    git_ops: Arc<dyn GitOperations>,
+) -> Result<()> {
+    let manifest_path = if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    };
+
+    let manifest = load_manifest_with_settings(&manifest_path, settings)?;
+    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+
+    let Some(root) = &manifest.root else {
+        anyhow::bail!(
+            "This bundle.toml has no 'root' defined. Only published source bundles can be yanked."
+        );
+    };
+    let root_dir = parent_dir.join(root);
+    if !git_ops.is_repository(&root_dir) {
+        anyhow::bail!(
+            "Root directory '{}' is not a git repository. Publish the bundle with 'fpm publish' first.",
+            root_dir.display()
+        );
+    }
+
+    let version = version.trim().trim_start_matches('v');
+    let targets = publish_targets(&manifest, &manifest_path, &root_dir, git_ops.as_ref())?;
+    let primary = &targets[0];
+
+    let tags = git_ops
+        .list_remote_tags(&primary.url, None)
+        .with_context(|| format!("Failed to list the published versions of {}", primary.url))?;
+    let prefixed = format!("v{}", version);
+    let tag = tags
+        .iter()
+        .find(|tag| **tag == prefixed)
+        .or_else(|| tags.iter().find(|tag| *tag == version))
+        .with_context(|| {
+            format!(
+                "Version {} is not published to {}: no tag '{}' or '{}'",
+                version, primary.url, prefixed, version
+            )
+        })?;
+
+    let marker = yank_tag(tag);
+    if tags.contains(&marker) {
+        println!(
+            "{}",
+            format!("Version {} is already yanked.", version).yellow()
+        );
+        return Ok(());
+    }
+
+    println!("{} {} of {}", "Yanking".cyan(), tag, root_dir.display());
+
+    // Only the marker's name matters to installs; it is created at HEAD, as the
+    // yanked version's commit may not be in the local repository
+    let message = match reason {
+        Some(reason) => format!("fpm yank {}: {}", tag, reason),
+        None => format!("fpm yank {}", tag),
+    };
+    let author = settings
+        .author
+        .as_deref()
+        .map(CommitAuthor::parse)
+        .transpose()?;
+    git_ops.create_tag(&root_dir, &marker, &message, author.as_ref(), true)?;
+
+    for target in &targets {
+        git_ops.add_remote(&root_dir, &target.remote, &target.url)?;
+        git_ops
+            .push_tag(&root_dir, &target.remote, &marker, false)
+            .with_context(|| format!("Failed to push {} to {}", marker, target.url))?;
+        println!("  {} {} on {}", "✓ Yanked".green(), tag, target.url);
+    }
+
+    Ok(())
+}
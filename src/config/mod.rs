@@ -1,16 +1,30 @@
 use anyhow::{Context, Result};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 
-use crate::types::{BundleManifest, FPM_IDENTIFIER};
+use crate::archive::{is_sha256, ArchiveFormat};
+use crate::filter::{PathFilter, PathPatterns};
+use crate::interpolate::interpolate;
+use crate::oci::OciReference;
+use crate::s3::S3Location;
+use crate::settings::Settings;
+use crate::shorthand::{self, GitProtocol};
+use crate::types::{BundleDependency, BundleManifest, FPM_IDENTIFIER};
 use crate::version::check_manifest_compatibility;
 
 /// Loads and parses a bundle.toml manifest file
 pub fn load_manifest(path: &Path) -> Result<BundleManifest> {
+    load_manifest_with_settings(path, &Settings::default())
+}
+
+/// Loads and parses a bundle.toml manifest file, expanding shorthand sources with
+/// the protocol of `settings` when the manifest sets none
+pub fn load_manifest_with_settings(path: &Path, settings: &Settings) -> Result<BundleManifest> {
     let content = fs::read_to_string(path)
         .with_context(|| format!("Failed to read manifest file: {}", path.display()))?;
 
-    let manifest = parse_manifest(&content)?;
+    let manifest = parse_manifest_with_settings(&content, settings)?;
 
     // Check version compatibility and warn if needed
     check_manifest_compatibility(&manifest.fpm_version);
@@ -18,9 +32,120 @@ pub fn load_manifest(path: &Path) -> Result<BundleManifest> {
     Ok(manifest)
 }
 
+/// Replaces `${VAR}` references in the git URL, branch and SSH key of every bundle
+/// with the values of the environment variables
+pub fn interpolate_variables(manifest: &mut BundleManifest) -> Result<()> {
+    for (name, dependency) in manifest.bundles.iter_mut() {
+        interpolate_dependency(dependency)
+            .with_context(|| format!("Invalid fpm manifest: bundle '{}'", name))?;
+    }
+    Ok(())
+}
+
+/// Expands variables and shorthands in the `[patch]` section: in the patched URLs
+/// and in the git URLs and branches replacing them
+pub fn expand_patches(manifest: &mut BundleManifest, protocol: Option<GitProtocol>) -> Result<()> {
+    let protocol = manifest.protocol.or(protocol);
+    let expand_url = |url: &str| -> Result<String> {
+        let url = interpolate(url)?;
+        Ok(shorthand::expand(&url, protocol.unwrap_or_default())?.unwrap_or(url))
+    };
+
+    let patches = std::mem::take(&mut manifest.patch);
+    for (url, mut patch) in patches {
+        let context = || format!("Invalid fpm manifest: patch for '{}'", url);
+        if let Some(git) = &mut patch.git {
+            *git = expand_url(git).with_context(context)?;
+        }
+        if let Some(branch) = &mut patch.branch {
+            *branch = interpolate(branch).with_context(context)?;
+        }
+        let url = expand_url(&url).with_context(context)?;
+        manifest.patch.insert(url, patch);
+    }
+    Ok(())
+}
+
+/// Expands variables and shorthands in `publish_url` and `publish_mirrors`, and
+/// variables in `publish_branch`
+pub fn expand_publish_target(
+    manifest: &mut BundleManifest,
+    protocol: Option<GitProtocol>,
+) -> Result<()> {
+    let protocol = manifest.protocol.or(protocol);
+    let expand = |url: &mut String, field: &str| -> Result<()> {
+        let context = || format!("Invalid fpm manifest: {}", field);
+        let expanded = interpolate(url).with_context(context)?;
+        *url = shorthand::expand(&expanded, protocol.unwrap_or_default())
+            .with_context(context)?
+            .unwrap_or(expanded);
+        Ok(())
+    };
+    if let Some(url) = &mut manifest.publish_url {
+        expand(url, "publish_url")?;
+    }
+    for url in &mut manifest.publish_mirrors {
+        expand(url, "publish_mirrors")?;
+    }
+    if let Some(branch) = &mut manifest.publish_branch {
+        *branch = interpolate(branch).context("Invalid fpm manifest: publish_branch")?;
+    }
+    Ok(())
+}
+
+fn interpolate_dependency(dependency: &mut BundleDependency) -> Result<()> {
+    dependency.git = interpolate(&dependency.git)?;
+    if let Some(branch) = &mut dependency.branch {
+        *branch = interpolate(branch)?;
+    }
+    if let Some(ssh_key) = dependency.ssh_key.as_mut() {
+        if let Some(path) = ssh_key.to_str() {
+            *ssh_key = interpolate(path)?.into();
+        }
+    }
+    Ok(())
+}
+
+/// Replaces shorthand sources such as `github:org/repo` with full URLs, using the
+/// manifest's protocol or else the configured one
+pub fn expand_shorthands(
+    manifest: &mut BundleManifest,
+    protocol: Option<GitProtocol>,
+) -> Result<()> {
+    let protocol = manifest.protocol.or(protocol);
+    for (name, dependency) in manifest.bundles.iter_mut() {
+        expand_shorthand(dependency, protocol)
+            .with_context(|| format!("Invalid fpm manifest: bundle '{}'", name))?;
+    }
+    Ok(())
+}
+
+/// Replaces a shorthand git source with its full URL.
+/// Bundles with an SSH key always use SSH; the others use `protocol`.
+pub fn expand_shorthand(
+    dependency: &mut BundleDependency,
+    protocol: Option<GitProtocol>,
+) -> Result<()> {
+    let protocol = if dependency.ssh_key.is_some() {
+        GitProtocol::Ssh
+    } else {
+        protocol.unwrap_or_default()
+    };
+    if let Some(url) = shorthand::expand(&dependency.git, protocol)? {
+        dependency.git = url;
+    }
+    Ok(())
+}
+
 /// Parses a manifest from TOML string content
 pub fn parse_manifest(content: &str) -> Result<BundleManifest> {
-    let manifest: BundleManifest =
+    parse_manifest_with_settings(content, &Settings::default())
+}
+
+/// Parses a manifest from TOML string content, expanding shorthand sources with the
+/// protocol of `settings` when the manifest sets none
+pub fn parse_manifest_with_settings(content: &str, settings: &Settings) -> Result<BundleManifest> {
+    let mut manifest: BundleManifest =
         toml::from_str(content).context("Failed to parse bundle.toml")?;
 
     if !manifest.is_valid_fpm_manifest() {
@@ -31,9 +156,197 @@ pub fn parse_manifest(content: &str) -> Result<BundleManifest> {
         );
     }
 
+    if manifest.depth == Some(0) {
+        anyhow::bail!("Invalid fpm manifest: depth must be at least 1");
+    }
+    if [&manifest.hooks.pre_install, &manifest.hooks.post_install]
+        .into_iter()
+        .flatten()
+        .any(|command| command.trim().is_empty())
+    {
+        anyhow::bail!("Invalid fpm manifest: hooks must not be empty commands");
+    }
+    for (name, command) in &manifest.scripts {
+        if name.trim().is_empty() || command.trim().is_empty() {
+            anyhow::bail!(
+                "Invalid fpm manifest: script '{}' needs a name and a command",
+                name
+            );
+        }
+    }
+    if manifest
+        .keywords
+        .iter()
+        .any(|keyword| keyword.trim().is_empty())
+    {
+        anyhow::bail!("Invalid fpm manifest: keywords must not be empty");
+    }
+    if manifest
+        .readme
+        .as_deref()
+        .is_some_and(|readme| readme.is_absolute())
+    {
+        anyhow::bail!("Invalid fpm manifest: readme must be a path relative to bundle.toml");
+    }
+    interpolate_variables(&mut manifest)?;
+    expand_shorthands(&mut manifest, settings.protocol)?;
+    expand_patches(&mut manifest, settings.protocol)?;
+    expand_publish_target(&mut manifest, settings.protocol)?;
+    if manifest.publish_url.is_some()
+        || manifest.publish_branch.is_some()
+        || !manifest.publish_mirrors.is_empty()
+    {
+        if manifest.root.is_none() {
+            anyhow::bail!(
+                "Invalid fpm manifest: publish_url, publish_branch and publish_mirrors need a root directory to publish"
+            );
+        }
+        if manifest
+            .publish_url
+            .as_deref()
+            .is_some_and(|url| url.trim().is_empty())
+        {
+            anyhow::bail!("Invalid fpm manifest: publish_url must not be empty");
+        }
+        if manifest
+            .publish_mirrors
+            .iter()
+            .any(|url| url.trim().is_empty())
+        {
+            anyhow::bail!("Invalid fpm manifest: publish_mirrors must not contain empty URLs");
+        }
+        if let Some(branch) = &manifest.publish_branch {
+            if !is_branch_name(branch) {
+                anyhow::bail!(
+                    "Invalid fpm manifest: publish_branch '{}' is not a valid branch name",
+                    branch
+                );
+            }
+        }
+    }
+    for (url, patch) in &manifest.patch {
+        if patch.git.is_some() == patch.path.is_some() {
+            anyhow::bail!(
+                "Invalid fpm manifest: patch for '{}' needs either git or path",
+                url
+            );
+        }
+        if patch.path.is_some() && (patch.branch.is_some() || patch.rev.is_some()) {
+            anyhow::bail!(
+                "Invalid fpm manifest: patch for '{}' can only set branch or rev with git",
+                url
+            );
+        }
+        if let Some(rev) = &patch.rev {
+            if !is_commit_id(rev) {
+                anyhow::bail!(
+                    "Invalid fpm manifest: rev '{}' of the patch for '{}' must be a commit id \
+                    of at least 7 lowercase hex digits",
+                    rev,
+                    url
+                );
+            }
+        }
+    }
+    for (name, dependency) in &manifest.bundles {
+        if dependency.depth == Some(0) {
+            anyhow::bail!(
+                "Invalid fpm manifest: depth of bundle '{}' must be at least 1",
+                name
+            );
+        }
+        if let Some(rev) = &dependency.rev {
+            if !is_commit_id(rev) {
+                anyhow::bail!(
+                    "Invalid fpm manifest: rev '{}' of bundle '{}' must be a commit id of at \
+                    least 7 lowercase hex digits",
+                    rev,
+                    name
+                );
+            }
+        }
+        let sources = [
+            !dependency.git.is_empty(),
+            dependency.url.is_some(),
+            dependency.oci.is_some(),
+            dependency.s3.is_some(),
+        ];
+        if sources.iter().filter(|set| **set).count() > 1 {
+            anyhow::bail!(
+                "Invalid fpm manifest: bundle '{}' must have only one of git, url, oci and s3",
+                name
+            );
+        }
+        if let Some(url) = &dependency.url {
+            ArchiveFormat::from_url(url)
+                .with_context(|| format!("Invalid fpm manifest: bundle '{}'", name))?;
+        }
+        if let Some(oci) = &dependency.oci {
+            OciReference::parse(oci)
+                .with_context(|| format!("Invalid fpm manifest: bundle '{}'", name))?;
+        }
+        if let Some(s3) = &dependency.s3 {
+            S3Location::parse(s3)
+                .with_context(|| format!("Invalid fpm manifest: bundle '{}'", name))?;
+        }
+        if let Some(sha256) = &dependency.sha256 {
+            if !is_sha256(sha256) {
+                anyhow::bail!(
+                    "Invalid fpm manifest: sha256 of bundle '{}' must be 64 hex digits",
+                    name
+                );
+            }
+        }
+        if dependency.is_local() && dependency.path.is_none() {
+            anyhow::bail!(
+                "Invalid fpm manifest: bundle '{}' needs a git URL or a local path",
+                name
+            );
+        }
+        if let Some(subdirectory) = dependency.subdirectory() {
+            let inside = subdirectory
+                .components()
+                .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+            if !inside {
+                anyhow::bail!(
+                    "Invalid fpm manifest: path '{}' of bundle '{}' must be a relative \
+                    path inside the repository",
+                    subdirectory.display(),
+                    name
+                );
+            }
+        }
+        if let Some(patterns) = PathPatterns::of(dependency) {
+            PathFilter::new(&patterns)
+                .with_context(|| format!("Invalid fpm manifest: bundle '{}'", name))?;
+        }
+    }
+
     Ok(manifest)
 }
 
+/// Whether a rev is a full or abbreviated commit id. Revs are matched against
+/// commits by prefix, so a shorter one (an empty one above all) matches too much.
+fn is_commit_id(rev: &str) -> bool {
+    (7..=64).contains(&rev.len()) && rev.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f'))
+}
+
+/// Checks a branch name against the rules of `git check-ref-format --branch`
+fn is_branch_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(['-', '/'])
+        && !name.ends_with(['/', '.'])
+        && !name.ends_with(".lock")
+        && !name.contains("..")
+        && !name.contains("//")
+        && !name.contains("@{")
+        && name != "@"
+        && !name.split('/').any(|part| part.starts_with('.'))
+        && !name
+            .chars()
+            .any(|c| c.is_control() || " ~^:?*[\\".contains(c))
+}
+
 /// Saves a manifest to a file
 pub fn save_manifest(manifest: &BundleManifest, path: &Path) -> Result<()> {
     let content = toml::to_string_pretty(manifest).context("Failed to serialize manifest")?;
@@ -59,10 +372,46 @@ pub fn has_manifest(dir: &Path) -> bool {
     load_manifest(&manifest_path).is_ok()
 }
 
+/// Finds the nearest bundle.toml in `dir` or one of its parent directories, the way
+/// git and cargo find their projects from a subdirectory
+pub fn find_manifest(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|ancestor| ancestor.join("bundle.toml"))
+        .find(|manifest_path| manifest_path.is_file())
+}
+
+/// Finds bundles that are aliases of the same repository.
+/// Returns a map from each alias name to the primary bundle name (the
+/// alphabetically first bundle using that git URL).
+pub fn find_aliases(manifest: &BundleManifest) -> HashMap<String, String> {
+    let mut by_url: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (name, dependency) in manifest.bundles.iter().filter(|(_, d)| !d.is_local()) {
+        by_url
+            .entry(dependency.git.as_str())
+            .or_default()
+            .push(name.as_str());
+    }
+
+    let mut aliases = HashMap::new();
+    for mut names in by_url.into_values() {
+        if names.len() < 2 {
+            continue;
+        }
+
+        names.sort();
+        let primary = names[0];
+        for alias in &names[1..] {
+            aliases.insert(alias.to_string(), primary.to_string());
+        }
+    }
+
+    aliases
+}
+
 #[cfg(test)]
 mod unit_tests {
     use super::*;
-    use crate::types::BundleDependency;
+    use crate::types::SourceKind;
 
     #[test]
     fn test_parse_valid_manifest() {
@@ -98,10 +447,454 @@ mod unit_tests {
             .contains("Invalid fpm manifest"));
     }
 
+    #[test]
+    fn test_parse_publish_target() {
+        let content = r#"
+            fpm_version = "0.1.0"
+            identifier = "fpm-bundle"
+            root = "assets"
+            publish_url = "github:example/icons"
+            publish_branch = "release/assets"
+            publish_mirrors = ["gitlab:example/icons"]
+        "#;
+
+        let manifest = parse_manifest(content).unwrap();
+        assert_eq!(
+            manifest.publish_url.as_deref(),
+            Some("https://github.com/example/icons.git")
+        );
+        assert_eq!(manifest.publish_branch.as_deref(), Some("release/assets"));
+        assert_eq!(
+            manifest.publish_mirrors,
+            vec!["https://gitlab.com/example/icons.git"]
+        );
+
+        let error = |content: &str| format!("{:#}", parse_manifest(content).unwrap_err());
+        assert!(error(&content.replace("root = \"assets\"", "")).contains("root directory"));
+        assert!(error(&content.replace("github:example/icons", " ")).contains("empty"));
+        assert!(error(&content.replace("gitlab:example/icons", "")).contains("empty URLs"));
+        for branch in [
+            "release assets",
+            "../assets",
+            "assets.lock",
+            "-assets",
+            "a..b",
+        ] {
+            let invalid = content.replace("release/assets", branch);
+            assert!(
+                error(&invalid).contains("not a valid branch name"),
+                "{}",
+                branch
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_metadata() {
+        let manifest = parse_manifest(
+            r#"
+            fpm_version = "0.1.0"
+            identifier = "fpm-bundle"
+            name = "icons"
+            authors = ["Jane Doe <jane@example.com>"]
+            keywords = ["icons", "svg"]
+            homepage = "https://icons.example.com"
+            repository = "https://github.com/example/icons"
+            readme = "docs/README.md"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(manifest.authors, vec!["Jane Doe <jane@example.com>"]);
+        assert_eq!(manifest.keywords, vec!["icons", "svg"]);
+        assert_eq!(
+            manifest.homepage.as_deref(),
+            Some("https://icons.example.com")
+        );
+        assert_eq!(
+            manifest.repository.as_deref(),
+            Some("https://github.com/example/icons")
+        );
+        assert_eq!(manifest.readme, Some(PathBuf::from("docs/README.md")));
+
+        // Metadata that isn't set isn't written either
+        let serialized = toml::to_string_pretty(&BundleManifest::new("0.1.0")).unwrap();
+        assert!(!serialized.contains("authors"));
+        assert!(!serialized.contains("readme"));
+
+        let error = parse_manifest(
+            r#"
+            fpm_version = "0.1.0"
+            identifier = "fpm-bundle"
+            keywords = ["icons", " "]
+            "#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("keywords"));
+    }
+
+    #[test]
+    fn test_parse_clone_depth() {
+        let content = r#"
+            fpm_version = "0.1.0"
+            identifier = "fpm-bundle"
+            depth = 1
+
+            [bundles.icons]
+            version = "1.0.0"
+            git = "https://github.com/example/icons.git"
+
+            [bundles.history]
+            version = "1.0.0"
+            git = "https://github.com/example/history.git"
+            depth = 50
+        "#;
+
+        let mut manifest = parse_manifest(content).unwrap();
+        assert_eq!(manifest.bundles["icons"].depth, None);
+
+        manifest.apply_bundle_defaults(&Settings::default());
+        assert_eq!(manifest.bundles["icons"].depth, Some(1));
+        assert_eq!(manifest.bundles["history"].depth, Some(50));
+
+        let zero_depth = content.replace("depth = 50", "depth = 0");
+        let result = parse_manifest(&zero_depth);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("depth of bundle 'history' must be at least 1"));
+    }
+
+    #[test]
+    fn test_parse_shorthand_sources() {
+        let content = r#"
+            fpm_version = "0.1.0"
+            identifier = "fpm-bundle"
+
+            [bundles.example]
+            version = "*"
+            git = "github:DragonAxeSoftware/fpm-example-1"
+
+            [bundles.private]
+            version = "*"
+            git = "gitlab:company/design/tokens"
+            ssh_key = "~/.ssh/deploy_key"
+        "#;
+
+        let manifest = parse_manifest(content).unwrap();
+        assert_eq!(
+            manifest.bundles["example"].git,
+            "https://github.com/DragonAxeSoftware/fpm-example-1.git"
+        );
+        assert_eq!(
+            manifest.bundles["private"].git,
+            "git@gitlab.com:company/design/tokens.git"
+        );
+
+        let over_ssh = content.replace(
+            "identifier = \"fpm-bundle\"",
+            "identifier = \"fpm-bundle\"\n            protocol = \"ssh\"",
+        );
+        let manifest = parse_manifest(&over_ssh).unwrap();
+        assert_eq!(
+            manifest.bundles["example"].git,
+            "git@github.com:DragonAxeSoftware/fpm-example-1.git"
+        );
+
+        let missing_owner = content.replace("DragonAxeSoftware/", "");
+        let error = format!("{:#}", parse_manifest(&missing_owner).unwrap_err());
+        assert!(
+            error.contains("bundle 'example'") && error.contains("<owner>/<repository>"),
+            "Got: {}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_parse_environment_variables() {
+        std::env::set_var("FPM_TEST_GIT_HOST", "git.example.com");
+        let content = r#"
+            fpm_version = "0.1.0"
+            identifier = "fpm-bundle"
+
+            [bundles.icons]
+            version = "*"
+            git = "https://${FPM_TEST_GIT_HOST}/design/icons.git"
+            branch = "${FPM_TEST_GIT_HOST}"
+        "#;
+
+        let manifest = parse_manifest(content).unwrap();
+        assert_eq!(
+            manifest.bundles["icons"].git,
+            "https://git.example.com/design/icons.git"
+        );
+        assert_eq!(
+            manifest.bundles["icons"].branch.as_deref(),
+            Some("git.example.com")
+        );
+
+        let unset = content.replace("FPM_TEST_GIT_HOST}/", "FPM_TEST_UNSET_HOST}/");
+        let error = format!("{:#}", parse_manifest(&unset).unwrap_err());
+        assert!(
+            error.contains("bundle 'icons'") && error.contains("'FPM_TEST_UNSET_HOST' is not set"),
+            "Got: {}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_parse_patch_section() {
+        let content = r#"
+            fpm_version = "0.1.0"
+            identifier = "fpm-bundle"
+
+            [patch."github:example/base-styles"]
+            git = "github:me/base-styles"
+            branch = "fix-colors"
+        "#;
+
+        let manifest = parse_manifest(content).unwrap();
+        let patch = &manifest.patch["https://github.com/example/base-styles.git"];
+        assert_eq!(
+            patch.git.as_deref(),
+            Some("https://github.com/me/base-styles.git")
+        );
+
+        let both = content.replace("branch = \"fix-colors\"", "path = \"../base-styles\"");
+        let error = parse_manifest(&both).unwrap_err().to_string();
+        assert!(error.contains("needs either git or path"), "Got: {}", error);
+    }
+
+    #[test]
+    fn test_parse_rejects_revs_that_are_not_commit_ids() {
+        let content = r#"
+            fpm_version = "0.1.0"
+            identifier = "fpm-bundle"
+
+            [bundles.icons]
+            version = "1.0.0"
+            git = "https://github.com/example/icons.git"
+            rev = "REV"
+        "#;
+
+        let manifest = parse_manifest(&content.replace("REV", "3f2a9c1")).unwrap();
+        assert_eq!(manifest.bundles["icons"].rev.as_deref(), Some("3f2a9c1"));
+
+        for rev in ["", "3f2a9c", "3F2A9C1", "main", "v1.0.0", "3f2a9c1 "] {
+            let error = parse_manifest(&content.replace("REV", rev))
+                .unwrap_err()
+                .to_string();
+            assert!(
+                error.contains("must be a commit id of at least 7 lowercase hex digits"),
+                "{:?}: {}",
+                rev,
+                error
+            );
+        }
+
+        let patch = r#"
+            fpm_version = "0.1.0"
+            identifier = "fpm-bundle"
+
+            [patch."https://github.com/example/icons.git"]
+            git = "https://github.com/me/icons.git"
+            rev = ""
+        "#;
+        let error = parse_manifest(patch).unwrap_err().to_string();
+        assert!(error.contains("rev '' of the patch"), "Got: {}", error);
+    }
+
+    #[test]
+    fn test_find_manifest_in_parent_directories() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = temp_dir.path().join("project");
+        let subdirectory = project.join("assets").join("icons");
+        fs::create_dir_all(&subdirectory).unwrap();
+        fs::write(project.join("bundle.toml"), "fpm_version = \"0.1.0\"\n").unwrap();
+
+        assert_eq!(
+            find_manifest(&subdirectory),
+            Some(project.join("bundle.toml"))
+        );
+        assert_eq!(find_manifest(&project), Some(project.join("bundle.toml")));
+    }
+
+    #[test]
+    fn test_parse_invalid_include_pattern() {
+        let content = r#"
+            fpm_version = "0.1.0"
+            identifier = "fpm-bundle"
+
+            [bundles.icons]
+            version = "1.0.0"
+            git = "https://github.com/example/icons.git"
+            include = ["icons/**/*.svg", "raw/[psd"]
+        "#;
+
+        let error = format!("{:#}", parse_manifest(content).unwrap_err());
+        assert!(error.contains("bundle 'icons'"), "Got: {}", error);
+        assert!(
+            error.contains("Invalid include pattern 'raw/[psd'"),
+            "Got: {}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_parse_subdirectory_path() {
+        let content = r#"
+            fpm_version = "0.1.0"
+            identifier = "fpm-bundle"
+
+            [bundles.icons]
+            version = "1.0.0"
+            git = "https://github.com/example/design.git"
+            path = "assets/icons"
+
+            [bundles.design]
+            version = "1.0.0"
+            git = "https://github.com/example/design.git"
+            path = "./"
+        "#;
+
+        let manifest = parse_manifest(content).unwrap();
+        assert_eq!(
+            manifest.bundles["icons"].subdirectory(),
+            Some(Path::new("assets/icons"))
+        );
+        assert_eq!(manifest.bundles["design"].subdirectory(), None);
+
+        let escaping = content.replace("assets/icons", "../secrets");
+        let error = parse_manifest(&escaping).unwrap_err().to_string();
+        assert!(
+            error.contains("path '../secrets' of bundle 'icons'"),
+            "Got: {}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_parse_local_path() {
+        let content = r#"
+            fpm_version = "0.1.0"
+            identifier = "fpm-bundle"
+
+            [bundles.shared]
+            path = "../shared-assets"
+
+            [bundles.drafts]
+            path = "../drafts"
+        "#;
+
+        let manifest = parse_manifest(content).unwrap();
+        let shared = &manifest.bundles["shared"];
+        assert!(shared.is_local());
+        assert_eq!(shared.local_path(), Some(Path::new("../shared-assets")));
+        assert_eq!(shared.subdirectory(), None);
+        assert!(find_aliases(&manifest).is_empty());
+
+        let without_source = content.replace("path = \"../drafts\"", "version = \"1.0.0\"");
+        let error = parse_manifest(&without_source).unwrap_err().to_string();
+        assert!(
+            error.contains("bundle 'drafts' needs a git URL or a local path"),
+            "Got: {}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_parse_archive_url() {
+        let content = r#"
+            fpm_version = "0.1.0"
+            identifier = "fpm-bundle"
+
+            [bundles.assets]
+            url = "https://example.com/assets-1.2.0.tar.gz"
+            sha256 = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+        "#;
+
+        let manifest = parse_manifest(content).unwrap();
+        assert_eq!(
+            manifest.bundles["assets"].source_kind(),
+            SourceKind::Archive
+        );
+
+        let short_checksum = content.replace("9f86d081", "");
+        let error = parse_manifest(&short_checksum).unwrap_err().to_string();
+        assert!(error.contains("must be 64 hex digits"), "Got: {}", error);
+
+        let unknown_format = content.replace(".tar.gz", ".rar");
+        let error = format!("{:#}", parse_manifest(&unknown_format).unwrap_err());
+        assert!(error.contains("Unsupported archive"), "Got: {}", error);
+    }
+
+    #[test]
+    fn test_parse_oci_reference() {
+        let content = r#"
+            fpm_version = "0.1.0"
+            identifier = "fpm-bundle"
+
+            [bundles.assets]
+            oci = "ghcr.io/example/assets:1.2.0"
+        "#;
+
+        let manifest = parse_manifest(content).unwrap();
+        assert_eq!(manifest.bundles["assets"].source_kind(), SourceKind::Oci);
+        assert_eq!(
+            manifest.bundles["assets"].source(),
+            "ghcr.io/example/assets:1.2.0"
+        );
+
+        let without_registry = content.replace("ghcr.io/", "");
+        let error = format!("{:#}", parse_manifest(&without_registry).unwrap_err());
+        assert!(
+            error.contains("must start with a registry host"),
+            "Got: {}",
+            error
+        );
+
+        let two_sources = content.replace(
+            "oci =",
+            "git = \"https://github.com/example/assets.git\"\n            oci =",
+        );
+        let error = parse_manifest(&two_sources).unwrap_err().to_string();
+        assert!(
+            error.contains("must have only one of git, url, oci and s3"),
+            "Got: {}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_parse_s3_location() {
+        let content = r#"
+            fpm_version = "0.1.0"
+            identifier = "fpm-bundle"
+
+            [bundles.dataset]
+            s3 = "s3://datasets/vision/v2"
+        "#;
+
+        let manifest = parse_manifest(content).unwrap();
+        assert_eq!(manifest.bundles["dataset"].source_kind(), SourceKind::S3);
+
+        let wrong_scheme = content.replace("s3://", "https://");
+        let error = format!("{:#}", parse_manifest(&wrong_scheme).unwrap_err());
+        assert!(
+            error.contains("expected s3://bucket/prefix"),
+            "Got: {}",
+            error
+        );
+    }
+
     #[test]
     fn test_roundtrip_manifest() {
         let mut manifest = BundleManifest::new("0.1.0");
         manifest.description = Some("Test description".to_string());
+        manifest.authors = vec!["Jane Doe <jane@example.com>".to_string()];
+        manifest.keywords = vec!["icons".to_string(), "svg".to_string()];
+        manifest.homepage = Some("https://icons.example.com".to_string());
+        manifest.repository = Some("https://github.com/example/icons".to_string());
+        manifest.readme = Some(PathBuf::from("README.md"));
         manifest.bundles.insert(
             "test-bundle".to_string(),
             BundleDependency {
@@ -111,6 +904,7 @@ mod unit_tests {
                 branch: None,
                 ssh_key: None,
                 include: None,
+                ..Default::default()
             },
         );
 
@@ -119,4 +913,31 @@ mod unit_tests {
 
         assert_eq!(manifest, deserialized);
     }
+
+    #[test]
+    fn test_find_aliases() {
+        let content = r#"
+            fpm_version = "0.1.0"
+            identifier = "fpm-bundle"
+
+            [bundles.icons-v1]
+            version = "1.0.0"
+            git = "https://github.com/example/icons.git"
+            branch = "v1.0.0"
+
+            [bundles.icons-next]
+            version = "2.0.0"
+            git = "https://github.com/example/icons.git"
+
+            [bundles.styles]
+            version = "1.0.0"
+            git = "https://github.com/example/styles.git"
+        "#;
+
+        let manifest = parse_manifest(content).unwrap();
+        let aliases = find_aliases(&manifest);
+
+        assert_eq!(aliases.len(), 1);
+        assert_eq!(aliases.get("icons-v1"), Some(&"icons-next".to_string()));
+    }
 }
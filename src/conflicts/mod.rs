@@ -0,0 +1,227 @@
+//! Version conflicts across the dependency tree.
+//!
+//! Bundles nested at any depth may depend on the same repository. When their
+//! requirements can't be met by one version, e.g. `^1` and `^2` of base-styles,
+//! each would get its own, different copy. An install records what every bundle
+//! asked for and what it got, and reports such conflicts with the chain of bundles
+//! leading to each requirement.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::cache::normalize_url;
+use crate::resolve::{version_matches, ResolvedRef};
+use crate::types::BundleDependency;
+
+/// What a bundle asked for and what was installed for it
+#[derive(Debug, Clone)]
+pub struct Requirement {
+    /// Install path of the bundle, e.g. "ui-kit/base-styles"
+    pub key: String,
+    /// What the bundle asks for (see `BundleDependency::requested_ref`)
+    pub requested: String,
+    /// Pinned commit, if the bundle asks for one
+    pub rev: Option<String>,
+    /// Version requirement, if the bundle asks for one
+    pub version: Option<String>,
+    /// Whether the version requirement accepts pre-releases
+    pub allow_prerelease: bool,
+    /// Branch the bundle names explicitly, if any
+    pub branch: Option<String>,
+    /// Branch or tag installed
+    pub installed: String,
+    /// Commit installed
+    pub commit: String,
+}
+
+impl Requirement {
+    /// Describes what the bundle installed as `key` asked for and got
+    pub fn new(
+        key: &str,
+        dependency: &BundleDependency,
+        resolved: &ResolvedRef,
+        commit: &str,
+    ) -> Self {
+        Self {
+            key: key.to_string(),
+            requested: dependency.requested_ref(),
+            rev: dependency.rev.clone(),
+            version: dependency.version_requirement().map(str::to_string),
+            allow_prerelease: dependency.allow_prerelease,
+            branch: dependency.branch.clone(),
+            installed: resolved.name.clone(),
+            commit: commit.to_string(),
+        }
+    }
+
+    /// Returns true if what was installed for `other` meets this requirement. A
+    /// bundle asking for no version or branch (`version = "*"`) takes any.
+    fn accepts(&self, other: &Requirement) -> bool {
+        if let Some(rev) = &self.rev {
+            return other.commit.starts_with(rev.as_str());
+        }
+        if let Some(version) = &self.version {
+            return version_matches(&other.installed, version, self.allow_prerelease)
+                .unwrap_or(false);
+        }
+        self.branch
+            .as_ref()
+            .is_none_or(|branch| other.installed == *branch)
+    }
+
+    /// Returns true if one version meets both requirements
+    fn is_compatible(&self, other: &Requirement) -> bool {
+        self.accepts(other) || other.accepts(self)
+    }
+
+    /// Install path of the bundle whose manifest declares this one ("" for the
+    /// top level)
+    fn declared_by(&self) -> &str {
+        self.key.rsplit_once('/').map_or("", |(parent, _)| parent)
+    }
+}
+
+/// Bundles of one repository whose requirements no single version meets
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    /// Repository URL, without a trailing `.git`
+    pub repository: String,
+    pub requirements: Vec<Requirement>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Bundles require incompatible versions of {}:",
+            self.repository
+        )?;
+        for requirement in &self.requirements {
+            write!(
+                f,
+                "\n  {} requires {} (installed {})",
+                requirement.key.replace('/', " -> "),
+                requirement.requested,
+                requirement.installed
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A repository URL (without a trailing `.git`) with the subdirectory installed
+/// from it. Different subdirectories of one repository are different bundles.
+type Repository = (String, Option<PathBuf>);
+
+/// The requirements recorded during an install, by repository
+#[derive(Debug, Default)]
+pub struct Requirements {
+    recorded: Mutex<HashMap<Repository, Vec<Requirement>>>,
+}
+
+impl Requirements {
+    /// Records what a git bundle asked for and got
+    pub fn record(&self, dependency: &BundleDependency, requirement: Requirement) {
+        let repository = (
+            normalize_url(&dependency.git),
+            dependency.subdirectory().map(PathBuf::from),
+        );
+        self.recorded
+            .lock()
+            .unwrap()
+            .entry(repository)
+            .or_default()
+            .push(requirement);
+    }
+
+    /// Returns the repositories two manifests require incompatible versions of:
+    /// none of the versions one manifest got meets a requirement of the other. The
+    /// bundles of one manifest may differ, as aliases install several versions on
+    /// purpose.
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let recorded = self.recorded.lock().unwrap();
+        let repositories: BTreeMap<_, _> = recorded.iter().collect();
+
+        let mut conflicts = Vec::new();
+        for ((repository, _), requirements) in repositories {
+            let mut manifests: BTreeMap<&str, Vec<&Requirement>> = BTreeMap::new();
+            for requirement in requirements {
+                manifests
+                    .entry(requirement.declared_by())
+                    .or_default()
+                    .push(requirement);
+            }
+
+            let manifests: Vec<Vec<&Requirement>> = manifests.into_values().collect();
+            let mut conflicting: BTreeMap<&str, &Requirement> = BTreeMap::new();
+            for (i, first) in manifests.iter().enumerate() {
+                for second in &manifests[i + 1..] {
+                    let compatible = first
+                        .iter()
+                        .any(|a| second.iter().any(|b| a.is_compatible(b)));
+                    if !compatible {
+                        for requirement in first.iter().chain(second) {
+                            conflicting.insert(&requirement.key, requirement);
+                        }
+                    }
+                }
+            }
+
+            if !conflicting.is_empty() {
+                conflicts.push(Conflict {
+                    repository: repository.clone(),
+                    requirements: conflicting.into_values().cloned().collect(),
+                });
+            }
+        }
+        conflicts
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn dependency(version: &str) -> BundleDependency {
+        BundleDependency {
+            version: version.to_string(),
+            git: "https://github.com/example/base-styles.git".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn requirement(key: &str, version: &str, tag: &str) -> Requirement {
+        let resolved = ResolvedRef {
+            name: tag.to_string(),
+            version: Some(version.to_string()),
+            yanked: false,
+        };
+        Requirement::new(key, &dependency(version), &resolved, tag)
+    }
+
+    #[test]
+    fn test_conflicts_between_manifests() {
+        let requirements = Requirements::default();
+        let styles = dependency("^1");
+        let record = |key: &str, version: &str, tag: &str| {
+            requirements.record(&styles, requirement(key, version, tag));
+        };
+
+        record("ui-kit/base-styles", "^1", "v1.4.0");
+        // v1.4.0 also meets ^1.2, so one version is enough
+        record("icons/base-styles", "^1.2", "v1.3.0");
+        // Aliases declared side by side are meant to differ
+        record("ui-kit/styles-next", "^2", "v2.0.0");
+        assert!(requirements.conflicts().is_empty());
+
+        record("fonts/base-styles", "^3", "v3.0.0");
+        let conflicts = requirements.conflicts();
+        assert_eq!(conflicts.len(), 1);
+        let message = conflicts[0].to_string();
+        assert!(message.contains("fonts -> base-styles requires ^3 (installed v3.0.0)"));
+        assert!(message.contains("ui-kit -> base-styles requires ^1 (installed v1.4.0)"));
+        assert!(message.contains("icons -> base-styles requires ^1.2 (installed v1.3.0)"));
+    }
+}
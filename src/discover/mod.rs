@@ -0,0 +1,194 @@
+//! Finding every manifest in a project tree.
+//!
+//! Repositories with several asset directories have a bundle.toml in each. `--all`
+//! runs a command against every one of them: the tree is searched below the current
+//! directory, skipping .git, installed bundles (.fpm) and paths the .gitignore files
+//! along the way exclude.
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::filter::{PathFilter, PathPatterns};
+use crate::report;
+use crate::types::BUNDLE_DIR;
+
+/// Returns the bundle.toml files in `root` and the directories below it, sorted by path
+pub fn find_manifests(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut manifests = Vec::new();
+    let mut ignores = Vec::new();
+    walk(root, &mut ignores, &mut manifests)?;
+    manifests.sort();
+    Ok(manifests)
+}
+
+/// Runs `command` against every manifest below `root`, with a header naming each,
+/// then summarizes the results. Fails if the command failed for any manifest.
+pub fn for_each_manifest(root: &Path, mut command: impl FnMut(&Path) -> Result<()>) -> Result<()> {
+    let manifests = find_manifests(root)?;
+    if manifests.is_empty() {
+        anyhow::bail!("No bundle.toml found in {} or below", root.display());
+    }
+
+    let mut failures = Vec::new();
+    for manifest_path in &manifests {
+        let shown = manifest_path.strip_prefix(root).unwrap_or(manifest_path);
+        report::progress(format_args!("{} {}", "==>".cyan().bold(), shown.display()));
+        if let Err(error) = command(manifest_path) {
+            report::progress(format_args!("{} {:#}", "Error:".red().bold(), error));
+            failures.push((shown.display().to_string(), error));
+        }
+        report::progress(format_args!(""));
+    }
+
+    let succeeded = manifests.len() - failures.len();
+    report::progress(format_args!(
+        "{} manifest(s): {} succeeded, {} failed",
+        manifests.len(),
+        succeeded,
+        failures.len()
+    ));
+    if !failures.is_empty() {
+        let details: Vec<String> = failures
+            .iter()
+            .map(|(path, error)| format!("  {}: {:#}", path, error))
+            .collect();
+        anyhow::bail!(
+            "{} manifest(s) failed:\n{}",
+            failures.len(),
+            details.join("\n")
+        );
+    }
+
+    Ok(())
+}
+
+/// Collects the manifests in `dir` and below. `ignores` holds the patterns of the
+/// .gitignore files of `dir` and its parents, with the directory each applies to.
+fn walk(
+    dir: &Path,
+    ignores: &mut Vec<(PathBuf, PathFilter)>,
+    manifests: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let manifest_path = dir.join("bundle.toml");
+    if manifest_path.is_file() {
+        manifests.push(manifest_path);
+    }
+
+    let gitignore = read_gitignore(dir)?;
+    let pushed = gitignore.is_some();
+    if let Some(filter) = gitignore {
+        ignores.push((dir.to_path_buf(), filter));
+    }
+
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory: {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        // Links are left alone, so linked bundles and loops aren't searched
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let name = entry.file_name();
+        if name == ".git" || name == BUNDLE_DIR {
+            continue;
+        }
+
+        let path = entry.path();
+        let ignored = ignores.iter().any(|(base, filter)| {
+            path.strip_prefix(base)
+                .is_ok_and(|relative| !filter.is_included(relative))
+        });
+        if !ignored {
+            walk(&path, ignores, manifests)?;
+        }
+    }
+
+    if pushed {
+        ignores.pop();
+    }
+    Ok(())
+}
+
+/// Reads the .gitignore of a directory as exclude patterns. Negated patterns (`!`)
+/// aren't supported and are skipped.
+fn read_gitignore(dir: &Path) -> Result<Option<PathFilter>> {
+    let path = dir.join(".gitignore");
+    let Ok(content) = fs::read_to_string(&path) else {
+        return Ok(None);
+    };
+
+    let exclude: Vec<String> = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('!'))
+        .map(str::to_string)
+        .collect();
+    let filter = PathFilter::new(&PathPatterns {
+        include: &[],
+        exclude: &exclude,
+    })
+    .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    Ok(Some(filter))
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_manifest(dir: &Path) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join("bundle.toml"), "fpm_version = \"0.1.0\"\n").unwrap();
+    }
+
+    #[test]
+    fn test_find_manifests_honors_gitignore() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+
+        write_manifest(root);
+        write_manifest(&root.join("design"));
+        write_manifest(&root.join("games").join("sprites"));
+        // Installed bundles, ignored directories and those under them are skipped
+        write_manifest(&root.join("design").join(BUNDLE_DIR).join("icons"));
+        write_manifest(&root.join("build").join("copy"));
+        write_manifest(&root.join("games").join("tmp").join("scratch"));
+        fs::write(root.join(".gitignore"), "# output\nbuild/\n").unwrap();
+        fs::write(root.join("games").join(".gitignore"), "tmp\n").unwrap();
+
+        let manifests = find_manifests(root).unwrap();
+        assert_eq!(
+            manifests,
+            vec![
+                root.join("bundle.toml"),
+                root.join("design").join("bundle.toml"),
+                root.join("games").join("sprites").join("bundle.toml"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_for_each_manifest_reports_failures() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        write_manifest(&root.join("a"));
+        write_manifest(&root.join("b"));
+
+        let mut visited = Vec::new();
+        let error = for_each_manifest(root, |manifest_path| {
+            visited.push(manifest_path.to_path_buf());
+            if manifest_path.starts_with(root.join("a")) {
+                anyhow::bail!("broken");
+            }
+            Ok(())
+        })
+        .unwrap_err();
+
+        // A failing manifest doesn't stop the others
+        assert_eq!(visited.len(), 2);
+        assert!(error.to_string().contains("1 manifest(s) failed"));
+    }
+}
@@ -0,0 +1,306 @@
+//! Include and exclude patterns selecting which files of a bundle are installed.
+
+use anyhow::{Context, Result};
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::{Component, Path};
+
+use crate::types::BundleDependency;
+
+/// The `include` and `exclude` lists of a dependency.
+///
+/// Include patterns are relative to the bundle root. Exclude patterns follow
+/// .gitignore rules: without a slash they match at any depth (`*.psd`), with a
+/// slash they are relative to the bundle root (`tests/**`). Both may use globs:
+/// `*` and `?` stay within one path segment, `**` spans directories.
+///
+/// A path is installed when it (or a parent directory) matches an include pattern,
+/// or there is no include list, and neither it nor a parent directory matches an
+/// exclude pattern. Exclude always wins.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PathPatterns<'a> {
+    pub include: &'a [String],
+    pub exclude: &'a [String],
+}
+
+impl<'a> PathPatterns<'a> {
+    /// Returns the patterns of a dependency, or None if it installs every file
+    pub fn of(dependency: &'a BundleDependency) -> Option<Self> {
+        let patterns = Self {
+            include: dependency.include.as_deref().unwrap_or_default(),
+            exclude: dependency.exclude.as_deref().unwrap_or_default(),
+        };
+
+        (!patterns.include.is_empty() || !patterns.exclude.is_empty()).then_some(patterns)
+    }
+
+    /// Converts the patterns to non-cone sparse-checkout patterns. Include patterns
+    /// are anchored at the repository root, so "assets" matches the top-level assets
+    /// directory (or file) and everything in it; excludes are negated patterns
+    /// listed last, so they take precedence.
+    pub fn sparse_patterns(&self) -> Vec<String> {
+        let mut patterns: Vec<String> = if self.include.is_empty() {
+            vec!["/*".to_string()]
+        } else {
+            self.include
+                .iter()
+                .map(|pattern| format!("/{}", normalize(pattern)))
+                .collect()
+        };
+
+        patterns.extend(
+            self.exclude
+                .iter()
+                .map(|pattern| format!("!{}", pattern.trim_start_matches("./"))),
+        );
+
+        patterns
+    }
+}
+
+/// Returns the include and exclude lists of a dependency installed from a
+/// subdirectory, rebased onto the repository root so they select the same files
+/// in a sparse checkout of the whole repository. Without an include list the whole
+/// subdirectory is included; excludes without a slash already match at any depth.
+pub fn subdirectory_patterns(
+    subdirectory: &Path,
+    dependency: &BundleDependency,
+) -> (Vec<String>, Vec<String>) {
+    let root = subdirectory
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let include = match dependency.include.as_deref() {
+        Some(include) if !include.is_empty() => include
+            .iter()
+            .map(|pattern| format!("{}/{}", root, normalize(pattern)))
+            .collect(),
+        _ => vec![root.clone()],
+    };
+
+    let exclude = dependency
+        .exclude
+        .iter()
+        .flatten()
+        .map(|pattern| {
+            let trimmed = pattern.trim_start_matches("./").trim_end_matches('/');
+            if trimmed.contains('/') {
+                format!("{}/{}", root, normalize(trimmed))
+            } else {
+                pattern.clone()
+            }
+        })
+        .collect();
+
+    (include, exclude)
+}
+
+/// Matches bundle paths against the include and exclude patterns of a dependency
+#[derive(Debug, Clone)]
+pub struct PathFilter {
+    /// None includes every path
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl PathFilter {
+    pub fn new(patterns: &PathPatterns) -> Result<Self> {
+        let include = if patterns.include.is_empty() {
+            None
+        } else {
+            let mut builder = GlobSetBuilder::new();
+            for pattern in patterns.include {
+                builder.add(
+                    compile(normalize(pattern))
+                        .with_context(|| format!("Invalid include pattern '{}'", pattern))?,
+                );
+            }
+            Some(
+                builder
+                    .build()
+                    .context("Failed to build include patterns")?,
+            )
+        };
+
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns.exclude {
+            builder.add(
+                compile(&exclude_glob(pattern))
+                    .with_context(|| format!("Invalid exclude pattern '{}'", pattern))?,
+            );
+        }
+        let exclude = builder
+            .build()
+            .context("Failed to build exclude patterns")?;
+
+        Ok(Self { include, exclude })
+    }
+
+    /// Returns true if the path (relative to the bundle root) is installed
+    pub fn is_included(&self, path: &Path) -> bool {
+        let mut ancestors = path
+            .ancestors()
+            .filter(|ancestor| !ancestor.as_os_str().is_empty());
+
+        let included = match &self.include {
+            Some(include) => ancestors.clone().any(|ancestor| include.is_match(ancestor)),
+            None => true,
+        };
+
+        included && !ancestors.any(|ancestor| self.exclude.is_match(ancestor))
+    }
+}
+
+/// Compiles one pattern with gitignore-like rules, so it matches the same paths
+/// as the sparse checkout built from it
+fn compile(pattern: &str) -> Result<Glob> {
+    Ok(GlobBuilder::new(pattern).literal_separator(true).build()?)
+}
+
+/// Strips the leading "./" or "/" and trailing "/" of a pattern
+fn normalize(pattern: &str) -> &str {
+    pattern
+        .trim_start_matches("./")
+        .trim_start_matches('/')
+        .trim_end_matches('/')
+}
+
+/// Returns the glob matching the same paths as an exclude pattern: patterns
+/// without a slash match at any depth
+fn exclude_glob(pattern: &str) -> String {
+    let trimmed = pattern.trim_start_matches("./").trim_end_matches('/');
+    if trimmed.contains('/') {
+        normalize(trimmed).to_string()
+    } else {
+        format!("**/{}", trimmed)
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn strings(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|p| p.to_string()).collect()
+    }
+
+    fn filter(include: &[&str], exclude: &[&str]) -> PathFilter {
+        let include = strings(include);
+        let exclude = strings(exclude);
+        PathFilter::new(&PathPatterns {
+            include: &include,
+            exclude: &exclude,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_plain_paths_include_their_contents() {
+        let filter = filter(&["folder2", "./docs/guide.md"], &[]);
+
+        assert!(filter.is_included(Path::new("folder2")));
+        assert!(filter.is_included(Path::new("folder2/nested/file.txt")));
+        assert!(filter.is_included(Path::new("docs/guide.md")));
+        assert!(!filter.is_included(Path::new("docs/other.md")));
+        assert!(!filter.is_included(Path::new("folder1/folder2")));
+    }
+
+    #[test]
+    fn test_glob_patterns() {
+        let filter = filter(&["assets/**/*.png", "docs/*"], &[]);
+
+        assert!(filter.is_included(Path::new("assets/logo.png")));
+        assert!(filter.is_included(Path::new("assets/icons/small/home.png")));
+        assert!(!filter.is_included(Path::new("assets/logo.psd")));
+        assert!(filter.is_included(Path::new("docs/readme.md")));
+        assert!(filter.is_included(Path::new("docs/api/index.md")));
+        assert!(!filter.is_included(Path::new("readme.md")));
+    }
+
+    #[test]
+    fn test_exclude_takes_precedence() {
+        let exclude_only = filter(&[], &["tests/**", "*.psd"]);
+
+        assert!(exclude_only.is_included(Path::new("readme.md")));
+        assert!(!exclude_only.is_included(Path::new("tests/unit/test.rs")));
+        assert!(!exclude_only.is_included(Path::new("logo.psd")));
+        assert!(!exclude_only.is_included(Path::new("art/raw/logo.psd")));
+        assert!(exclude_only.is_included(Path::new("art/docs/tests/notes.md")));
+
+        let both = filter(&["icons"], &["*.png", "/icons/legacy"]);
+
+        assert!(both.is_included(Path::new("icons/home.svg")));
+        assert!(!both.is_included(Path::new("icons/home.png")));
+        assert!(!both.is_included(Path::new("icons/legacy/old.svg")));
+        assert!(!both.is_included(Path::new("readme.md")));
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let include = strings(&["assets/["]);
+        let error = PathFilter::new(&PathPatterns {
+            include: &include,
+            exclude: &[],
+        })
+        .unwrap_err();
+        assert!(error.to_string().contains("Invalid include pattern"));
+
+        let exclude = strings(&["{a,b"]);
+        let error = PathFilter::new(&PathPatterns {
+            include: &[],
+            exclude: &exclude,
+        })
+        .unwrap_err();
+        assert!(error.to_string().contains("Invalid exclude pattern"));
+    }
+
+    #[test]
+    fn test_subdirectory_patterns() {
+        let mut dependency = BundleDependency {
+            version: "1.0.0".to_string(),
+            git: "https://github.com/example/design.git".to_string(),
+            ..Default::default()
+        };
+        let subdirectory = Path::new("./assets/icons");
+
+        let (include, exclude) = subdirectory_patterns(subdirectory, &dependency);
+        assert_eq!(include, vec!["assets/icons"]);
+        assert!(exclude.is_empty());
+
+        dependency.include = Some(strings(&["svg", "./png/**/*.png"]));
+        dependency.exclude = Some(strings(&["*.psd", "/svg/legacy/", "build/"]));
+        let (include, exclude) = subdirectory_patterns(subdirectory, &dependency);
+        assert_eq!(
+            include,
+            vec!["assets/icons/svg", "assets/icons/png/**/*.png"]
+        );
+        assert_eq!(exclude, vec!["*.psd", "assets/icons/svg/legacy", "build/"]);
+    }
+
+    #[test]
+    fn test_sparse_patterns() {
+        let include = strings(&["folder2", "./docs/guide.md", "/icons/", "assets/**/*.png"]);
+        let patterns = PathPatterns {
+            include: &include,
+            exclude: &[],
+        };
+        assert_eq!(
+            patterns.sparse_patterns(),
+            vec!["/folder2", "/docs/guide.md", "/icons", "/assets/**/*.png"]
+        );
+
+        let exclude = strings(&["tests/**", "./*.psd"]);
+        let patterns = PathPatterns {
+            include: &[],
+            exclude: &exclude,
+        };
+        assert_eq!(
+            patterns.sparse_patterns(),
+            vec!["/*", "!tests/**", "!*.psd"]
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Bundles installed from a subdirectory of their source repository (`path`).
+//!
+//! The repository is cloned with a sparse checkout into a hidden checkout under
+//! `.fpm/.checkouts/<name>`, and the subdirectory is mirrored into `.fpm/<name>`.
+//! Pushing mirrors the bundle back into the subdirectory and commits in the checkout.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::git::GitOperations;
+use crate::state::load_state;
+use crate::types::{InstallStrategy, BUNDLE_DIR};
+
+/// Directory inside a bundle directory holding the checkouts of flattened bundles
+pub const CHECKOUT_DIR: &str = ".checkouts";
+
+/// Returns the checkout a flattened bundle of `bundle_dir` is copied from
+pub fn checkout_path(bundle_dir: &Path, name: &str) -> PathBuf {
+    bundle_dir.join(CHECKOUT_DIR).join(name)
+}
+
+/// A flattened bundle: the checkout it was installed from and the subdirectory
+/// of that checkout holding its files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlattenedSource {
+    pub checkout: PathBuf,
+    pub subdirectory: PathBuf,
+}
+
+impl FlattenedSource {
+    /// Directory in the checkout mirrored into the bundle
+    pub fn source_dir(&self) -> PathBuf {
+        self.checkout.join(&self.subdirectory)
+    }
+}
+
+/// Looks up where an installed bundle was flattened from, according to the install
+/// state of its bundle directory. Returns None for bundles that are plain clones.
+pub fn flattened_source(bundle_path: &Path) -> Option<FlattenedSource> {
+    let bundle_dir = bundle_path.parent()?;
+    let name = bundle_path.file_name()?.to_str()?;
+
+    let state = load_state(bundle_dir).ok()?;
+    let recorded = state.bundles.get(name)?;
+    if recorded.strategy != InstallStrategy::Flatten {
+        return None;
+    }
+
+    Some(FlattenedSource {
+        checkout: checkout_path(bundle_dir, name),
+        subdirectory: recorded.path.clone()?,
+    })
+}
+
+/// Makes `to` hold the same files as `from`: new and changed files are copied and
+/// files missing from `from` are deleted. The .git and .fpm directories at the top
+/// of either side are left alone, so nested bundles survive.
+pub fn mirror(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)
+        .with_context(|| format!("Failed to create directory: {}", to.display()))?;
+
+    // Delete stale entries first; reversed, every directory comes after its contents
+    let existing = walkdir::WalkDir::new(to)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|entry| !is_managed(entry.path().strip_prefix(to).unwrap_or(entry.path())))
+        .collect::<Result<Vec<_>, _>>()?;
+    for entry in existing.into_iter().rev() {
+        let relative = entry.path().strip_prefix(to)?;
+        let source = from.join(relative);
+        let is_dir = entry.file_type().is_dir();
+
+        if source.exists() && source.is_dir() == is_dir {
+            continue;
+        }
+
+        if is_dir {
+            fs::remove_dir_all(entry.path())
+        } else {
+            fs::remove_file(entry.path())
+        }
+        .with_context(|| format!("Failed to remove: {}", entry.path().display()))?;
+    }
+
+    for entry in walkdir::WalkDir::new(from)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|entry| !is_managed(entry.path().strip_prefix(from).unwrap_or(entry.path())))
+    {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(from)?;
+        let target = to.join(relative);
+
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)
+                .with_context(|| format!("Failed to create directory: {}", target.display()))?;
+        } else if !same_content(entry.path(), &target)? {
+            fs::copy(entry.path(), &target).with_context(|| {
+                format!(
+                    "Failed to copy {} to {}",
+                    entry.path().display(),
+                    target.display()
+                )
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns true if `a` and `b` hold different files (ignoring empty directories,
+/// and the .git and .fpm directories at their top)
+pub fn differs(a: &Path, b: &Path) -> Result<bool> {
+    let files_a = list_files(a)?;
+    if files_a != list_files(b)? {
+        return Ok(true);
+    }
+
+    for relative in &files_a {
+        if !same_content(&a.join(relative), &b.join(relative))? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Returns true if a flattened bundle has changes that are not committed in its
+/// checkout: edits in the bundle directory, or uncommitted changes in the checkout
+pub fn has_local_changes(
+    git_ops: &dyn GitOperations,
+    bundle_path: &Path,
+    source: &FlattenedSource,
+) -> Result<bool> {
+    Ok(differs(&source.source_dir(), bundle_path)?
+        || git_ops.has_local_changes(&source.checkout)?)
+}
+
+/// True for paths fpm manages itself: the .git and .fpm directories at the top
+pub fn is_managed(relative: &Path) -> bool {
+    relative
+        .components()
+        .next()
+        .is_some_and(|first| first.as_os_str() == ".git" || first.as_os_str() == BUNDLE_DIR)
+}
+
+fn list_files(dir: &Path) -> Result<BTreeSet<PathBuf>> {
+    let mut files = BTreeSet::new();
+    if !dir.exists() {
+        return Ok(files);
+    }
+
+    for entry in walkdir::WalkDir::new(dir)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|entry| !is_managed(entry.path().strip_prefix(dir).unwrap_or(entry.path())))
+    {
+        let entry = entry?;
+        if !entry.file_type().is_dir() {
+            files.insert(entry.path().strip_prefix(dir)?.to_path_buf());
+        }
+    }
+
+    Ok(files)
+}
+
+fn same_content(a: &Path, b: &Path) -> Result<bool> {
+    if !b.is_file() {
+        return Ok(false);
+    }
+
+    let metadata_a = fs::metadata(a)?;
+    let metadata_b = fs::metadata(b)?;
+    if metadata_a.len() != metadata_b.len() {
+        return Ok(false);
+    }
+
+    Ok(fs::read(a)? == fs::read(b)?)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_mirror_keeps_managed_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let from = temp_dir.path().join("checkout/icons");
+        let to = temp_dir.path().join("bundle");
+
+        write(&from.join("home.svg"), "home");
+        write(&from.join("small/back.svg"), "back");
+        write(&to.join("home.svg"), "old home");
+        write(&to.join("stale/removed.svg"), "removed");
+        write(&to.join("small"), "a file where a directory belongs");
+        write(&to.join(".fpm/nested/bundle.toml"), "nested");
+        write(&to.join(".git/HEAD"), "ref");
+
+        mirror(&from, &to).unwrap();
+
+        assert_eq!(fs::read_to_string(to.join("home.svg")).unwrap(), "home");
+        assert_eq!(
+            fs::read_to_string(to.join("small/back.svg")).unwrap(),
+            "back"
+        );
+        assert!(!to.join("stale").exists());
+        assert!(to.join(".fpm/nested/bundle.toml").exists());
+        assert!(to.join(".git/HEAD").exists());
+        assert!(!differs(&from, &to).unwrap());
+    }
+
+    #[test]
+    fn test_differs() {
+        let temp_dir = TempDir::new().unwrap();
+        let a = temp_dir.path().join("a");
+        let b = temp_dir.path().join("b");
+
+        write(&a.join("readme.md"), "hello");
+        write(&b.join("readme.md"), "hello");
+        write(&b.join(".fpm/state.toml"), "ignored");
+        fs::create_dir_all(b.join("empty")).unwrap();
+        assert!(!differs(&a, &b).unwrap());
+
+        write(&b.join("readme.md"), "hallo");
+        assert!(differs(&a, &b).unwrap());
+
+        write(&b.join("readme.md"), "hello");
+        write(&b.join("new.md"), "new");
+        assert!(differs(&a, &b).unwrap());
+    }
+}
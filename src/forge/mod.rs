@@ -0,0 +1,546 @@
+//! Repository hosting (forge) APIs.
+//!
+//! Used to create remote repositories for new source bundles (projects on
+//! GitLab), to open pull requests (merge requests on GitLab) for pushed branches,
+//! and to look up the GitHub releases `fpm self-update` installs. They use the
+//! token configured for the host (see `auth`), or else one from `FPM_GITHUB_TOKEN`
+//! or `GITHUB_TOKEN` for GitHub and `FPM_GITLAB_TOKEN` or `GITLAB_TOKEN` for GitLab.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::auth;
+
+/// GitHub REST API base URL
+const GITHUB_API: &str = "https://api.github.com";
+
+/// Environment variables checked (in order) for a GitHub token
+const GITHUB_TOKEN_VARS: &[&str] = &["FPM_GITHUB_TOKEN", "GITHUB_TOKEN"];
+
+/// Environment variables checked (in order) for a GitLab token
+const GITLAB_TOKEN_VARS: &[&str] = &["FPM_GITLAB_TOKEN", "GITLAB_TOKEN"];
+
+/// Hosting services fpm can open pull requests on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForgeKind {
+    GitHub,
+    GitLab,
+}
+
+/// A repository on a forge, e.g. `gitlab.com` and `design/assets/icons`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForgeRepository {
+    pub kind: ForgeKind,
+    pub host: String,
+    /// Path of the repository on the host, without `.git`
+    pub path: String,
+}
+
+/// A pull request to open: merges `head` into `base`
+#[derive(Debug, Clone)]
+pub struct PullRequest<'a> {
+    pub head: &'a str,
+    pub base: &'a str,
+    pub title: &'a str,
+    pub body: &'a str,
+}
+
+/// Owner and name of a repository hosted on a forge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepositoryId {
+    pub owner: String,
+    pub name: String,
+}
+
+/// A release of a GitHub repository
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+/// A file attached to a release
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+/// Creates the repository behind `url` on its host, in the organization, group or
+/// user account the URL names. An already existing repository is not an error.
+pub fn create_repository(url: &str, description: Option<&str>, private: bool) -> Result<()> {
+    let repository = parse_forge_url(url).with_context(|| {
+        format!(
+            "Cannot create '{}': creating remotes is only supported for GitHub and GitLab repositories",
+            url
+        )
+    })?;
+    let token = forge_token(&repository).with_context(|| {
+        format!(
+            "Creating a repository on {} requires a token: run 'fpm login {}' or set {}",
+            repository.host,
+            repository.host,
+            auth::host_token_var(&repository.host)
+        )
+    })?;
+
+    match repository.kind {
+        ForgeKind::GitHub => create_github_repository(&repository, &token, description, private),
+        ForgeKind::GitLab => create_gitlab_project(&repository, &token, description, private),
+    }
+}
+
+fn create_github_repository(
+    repository: &ForgeRepository,
+    token: &str,
+    description: Option<&str>,
+    private: bool,
+) -> Result<()> {
+    let (owner, name) = repository
+        .path
+        .split_once('/')
+        .context("GitHub repositories are named owner/name")?;
+    let id = RepositoryId {
+        owner: owner.to_string(),
+        name: name.to_string(),
+    };
+    info!("Creating GitHub repository {}/{}", id.owner, id.name);
+
+    let body = serde_json::json!({
+        "name": id.name,
+        "description": description.unwrap_or_default(),
+        "private": private,
+    });
+
+    // Organization repositories are created under /orgs; for a personal account
+    // that endpoint answers 404 and the repository is created under /user instead.
+    let org_url = format!("{}/orgs/{}/repos", GITHUB_API, id.owner);
+    let result = match github_post(&org_url, token, &body) {
+        Err(e) if matches!(*e, ureq::Error::Status(404, _)) => {
+            let user_url = format!("{}/user/repos", GITHUB_API);
+            github_post(&user_url, token, &body)
+        }
+        result => result,
+    };
+
+    check_create_response(result, &id)
+}
+
+/// Creates a GitLab project in the group or user namespace of its path
+fn create_gitlab_project(
+    repository: &ForgeRepository,
+    token: &str,
+    description: Option<&str>,
+    private: bool,
+) -> Result<()> {
+    let api = format!("https://{}/api/v4", repository.host);
+    let (namespace, name) = repository
+        .path
+        .rsplit_once('/')
+        .context("GitLab projects are named namespace/name")?;
+    info!(
+        "Creating GitLab project {} on {}",
+        repository.path, repository.host
+    );
+
+    // Projects are created in a namespace given by its id
+    let namespace_url = format!("{}/namespaces/{}", api, namespace.replace('/', "%2F"));
+    let namespace_id = match gitlab_call(ureq::get(&namespace_url), token).call() {
+        Ok(response) => {
+            let found: serde_json::Value = response
+                .into_json()
+                .context("Failed to read the GitLab namespace")?;
+            found["id"]
+                .as_u64()
+                .with_context(|| format!("The GitLab namespace '{}' has no id", namespace))?
+        }
+        Err(ureq::Error::Status(404, _)) => anyhow::bail!(
+            "Cannot create {}: no group or user '{}' on {}",
+            repository.path,
+            namespace,
+            repository.host
+        ),
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Failed to reach the API of {}", repository.host))
+        }
+    };
+
+    let body = serde_json::json!({
+        "name": name,
+        "path": name,
+        "namespace_id": namespace_id,
+        "description": description.unwrap_or_default(),
+        "visibility": if private { "private" } else { "public" },
+    });
+    match gitlab_call(ureq::post(&format!("{}/projects", api)), token).send_json(body) {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(code, response)) => {
+            let message = response.into_string().unwrap_or_default();
+            // 400 naming the path as taken: the project already exists
+            if code == 400 && message.contains("has already been taken") {
+                return Ok(());
+            }
+            anyhow::bail!(
+                "{} refused to create {} (HTTP {}): {}",
+                repository.host,
+                repository.path,
+                code,
+                message.trim()
+            )
+        }
+        Err(e) => Err(e).with_context(|| format!("Failed to reach the API of {}", repository.host)),
+    }
+}
+
+fn gitlab_call(request: ureq::Request, token: &str) -> ureq::Request {
+    request
+        .set("PRIVATE-TOKEN", token)
+        .set("User-Agent", concat!("fpm/", env!("CARGO_PKG_VERSION")))
+}
+
+fn github_post(
+    url: &str,
+    token: &str,
+    body: &serde_json::Value,
+) -> std::result::Result<ureq::Response, Box<ureq::Error>> {
+    ureq::post(url)
+        .set("Authorization", &format!("Bearer {}", token))
+        .set("Accept", "application/vnd.github+json")
+        .set("User-Agent", concat!("fpm/", env!("CARGO_PKG_VERSION")))
+        .send_json(body)
+        .map_err(Box::new)
+}
+
+fn check_create_response(
+    result: std::result::Result<ureq::Response, Box<ureq::Error>>,
+    id: &RepositoryId,
+) -> Result<()> {
+    let error = match result {
+        Ok(_) => return Ok(()),
+        Err(e) => *e,
+    };
+
+    match error {
+        // 422: the repository already exists
+        ureq::Error::Status(422, _) => Ok(()),
+        ureq::Error::Status(code, response) => {
+            let message = response.into_string().unwrap_or_default();
+            anyhow::bail!(
+                "GitHub refused to create {}/{} (HTTP {}): {}",
+                id.owner,
+                id.name,
+                code,
+                message.trim()
+            )
+        }
+        e => Err(e).context("Failed to reach the GitHub API"),
+    }
+}
+
+/// Client for the API of the forge hosting one repository
+#[derive(Debug, Clone)]
+pub struct ForgeClient {
+    pub repository: ForgeRepository,
+    token: String,
+}
+
+impl ForgeClient {
+    /// Client for the repository behind `url`. Fails for repositories not on GitHub
+    /// or GitLab, and when no token is configured for the host.
+    pub fn for_url(url: &str) -> Result<Self> {
+        let repository = parse_forge_url(url).with_context(|| {
+            format!(
+                "Cannot open a pull request for '{}': only GitHub and GitLab repositories are supported",
+                url
+            )
+        })?;
+        let token = forge_token(&repository).with_context(|| {
+            format!(
+                "Opening a pull request on {} requires a token: run 'fpm login {}' or set {}",
+                repository.host,
+                repository.host,
+                auth::host_token_var(&repository.host)
+            )
+        })?;
+
+        Ok(Self { repository, token })
+    }
+
+    /// Opens a pull request and returns its web URL
+    pub fn open_pull_request(&self, request: &PullRequest) -> Result<String> {
+        let ForgeClient { repository, token } = self;
+        info!(
+            "Opening a pull request from {} into {} on {}",
+            request.head, request.base, repository.path
+        );
+
+        let (call, body, url_field) = match repository.kind {
+            ForgeKind::GitHub => (
+                ureq::post(&format!("{}/repos/{}/pulls", GITHUB_API, repository.path))
+                    .set("Authorization", &format!("Bearer {}", token))
+                    .set("Accept", "application/vnd.github+json"),
+                serde_json::json!({
+                    "title": request.title,
+                    "body": request.body,
+                    "head": request.head,
+                    "base": request.base,
+                }),
+                "html_url",
+            ),
+            ForgeKind::GitLab => (
+                ureq::post(&format!(
+                    "https://{}/api/v4/projects/{}/merge_requests",
+                    repository.host,
+                    repository.path.replace('/', "%2F")
+                ))
+                .set("PRIVATE-TOKEN", token),
+                serde_json::json!({
+                    "title": request.title,
+                    "description": request.body,
+                    "source_branch": request.head,
+                    "target_branch": request.base,
+                }),
+                "web_url",
+            ),
+        };
+
+        let response = match call
+            .set("User-Agent", concat!("fpm/", env!("CARGO_PKG_VERSION")))
+            .send_json(body)
+        {
+            Ok(response) => response,
+            Err(ureq::Error::Status(code, response)) => {
+                let message = response.into_string().unwrap_or_default();
+                anyhow::bail!(
+                    "{} refused to open a pull request on {} (HTTP {}): {}",
+                    repository.host,
+                    repository.path,
+                    code,
+                    message.trim()
+                )
+            }
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to reach the API of {}", repository.host))
+            }
+        };
+
+        let created: serde_json::Value = response
+            .into_json()
+            .context("Failed to read the created pull request")?;
+        created[url_field]
+            .as_str()
+            .map(str::to_string)
+            .with_context(|| format!("The response of {} has no {}", repository.host, url_field))
+    }
+}
+
+/// Looks up the release tagged `tag` of the GitHub repository `path` (owner/name),
+/// or its latest release that isn't a pre-release. Releases are public, so a token
+/// is only sent if there is one, raising the API's rate limit.
+pub fn github_release(path: &str, tag: Option<&str>) -> Result<Release> {
+    let url = match tag {
+        Some(tag) => format!("{}/repos/{}/releases/tags/{}", GITHUB_API, path, tag),
+        None => format!("{}/repos/{}/releases/latest", GITHUB_API, path),
+    };
+    let repository = ForgeRepository {
+        kind: ForgeKind::GitHub,
+        host: "github.com".to_string(),
+        path: path.to_string(),
+    };
+
+    let mut request = ureq::get(&url)
+        .set("Accept", "application/vnd.github+json")
+        .set("User-Agent", concat!("fpm/", env!("CARGO_PKG_VERSION")));
+    if let Some(token) = forge_token(&repository) {
+        request = request.set("Authorization", &format!("Bearer {}", token));
+    }
+
+    match request.call() {
+        Ok(response) => response
+            .into_json()
+            .with_context(|| format!("Failed to read the release of {}", path)),
+        Err(ureq::Error::Status(404, _)) => match tag {
+            Some(tag) => anyhow::bail!("{} has no release {}", path, tag),
+            None => anyhow::bail!("{} has no release yet", path),
+        },
+        Err(e) => Err(e).context("Failed to reach the GitHub API"),
+    }
+}
+
+/// Token for the API of a forge: the one configured for its host, or one of the
+/// forge's own environment variables
+fn forge_token(repository: &ForgeRepository) -> Option<String> {
+    let vars = match repository.kind {
+        ForgeKind::GitHub => GITHUB_TOKEN_VARS,
+        ForgeKind::GitLab => GITLAB_TOKEN_VARS,
+    };
+    auth::token_for_url(&format!("https://{}/", repository.host)).or_else(|| {
+        vars.iter()
+            .find_map(|var| std::env::var(var).ok().filter(|t| !t.is_empty()))
+    })
+}
+
+/// Identifies the forge and repository of an HTTPS or SSH URL. github.com is
+/// GitHub, and hosts with "gitlab" in their name are GitLab.
+pub fn parse_forge_url(url: &str) -> Option<ForgeRepository> {
+    let url = url.trim();
+    let (host, path) = match url.split_once("://") {
+        Some((scheme, rest)) => {
+            if !matches!(scheme, "https" | "http" | "ssh") {
+                return None;
+            }
+            let (authority, path) = rest.split_once('/')?;
+            let host = authority.rsplit('@').next()?;
+            // An SSH port doesn't carry over to the API
+            let host = if scheme == "ssh" {
+                host.split(':').next()?
+            } else {
+                host
+            };
+            (host, path)
+        }
+        None => {
+            let (authority, path) = url.split_once(':')?;
+            if authority.contains('/') || authority.len() < 2 {
+                return None;
+            }
+            (authority.rsplit('@').next()?, path)
+        }
+    };
+
+    let kind = if host == "github.com" {
+        ForgeKind::GitHub
+    } else if host.split(['.', ':']).any(|label| label.contains("gitlab")) {
+        ForgeKind::GitLab
+    } else {
+        return None;
+    };
+
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let segments = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .count();
+    if segments < 2 || (kind == ForgeKind::GitHub && segments != 2) {
+        return None;
+    }
+
+    Some(ForgeRepository {
+        kind,
+        host: host.to_string(),
+        path: path.to_string(),
+    })
+}
+
+/// Extracts owner and repository name from a GitHub HTTPS or SSH URL
+pub fn parse_github_url(url: &str) -> Option<RepositoryId> {
+    let url = url.trim();
+    let path = url
+        .strip_prefix("https://github.com/")
+        .or_else(|| url.strip_prefix("http://github.com/"))
+        .or_else(|| url.strip_prefix("ssh://git@github.com/"))
+        .or_else(|| url.strip_prefix("git@github.com:"))?;
+
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+
+    let (owner, name) = path.split_once('/')?;
+    if owner.is_empty() || name.is_empty() || name.contains('/') {
+        return None;
+    }
+
+    Some(RepositoryId {
+        owner: owner.to_string(),
+        name: name.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_github_url_variants() {
+        let expected = Some(RepositoryId {
+            owner: "DragonAxeSoftware".to_string(),
+            name: "fpm-example-1".to_string(),
+        });
+
+        assert_eq!(
+            parse_github_url("https://github.com/DragonAxeSoftware/fpm-example-1.git"),
+            expected
+        );
+        assert_eq!(
+            parse_github_url("git@github.com:DragonAxeSoftware/fpm-example-1.git"),
+            expected
+        );
+        assert_eq!(
+            parse_github_url("ssh://git@github.com/DragonAxeSoftware/fpm-example-1"),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_parse_github_url_rejects_other_hosts() {
+        assert_eq!(
+            parse_github_url("https://gitlab.com/group/project.git"),
+            None
+        );
+        assert_eq!(parse_github_url("https://github.com/only-owner"), None);
+        assert_eq!(parse_github_url("/srv/git/assets.git"), None);
+    }
+
+    #[test]
+    fn test_create_repository_rejects_other_hosts() {
+        let error = create_repository("https://bitbucket.org/design/icons.git", None, false)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("GitHub and GitLab"), "Got: {}", error);
+    }
+
+    #[test]
+    fn test_parse_forge_url() {
+        let repository = |kind, host: &str, path: &str| {
+            Some(ForgeRepository {
+                kind,
+                host: host.to_string(),
+                path: path.to_string(),
+            })
+        };
+
+        assert_eq!(
+            parse_forge_url("git@github.com:DragonAxeSoftware/fpm-example-1.git"),
+            repository(
+                ForgeKind::GitHub,
+                "github.com",
+                "DragonAxeSoftware/fpm-example-1"
+            )
+        );
+        assert_eq!(
+            parse_forge_url("https://gitlab.com/design/assets/icons.git"),
+            repository(ForgeKind::GitLab, "gitlab.com", "design/assets/icons")
+        );
+        assert_eq!(
+            parse_forge_url("ssh://git@gitlab.example.com:2222/design/icons"),
+            repository(ForgeKind::GitLab, "gitlab.example.com", "design/icons")
+        );
+        assert_eq!(
+            parse_forge_url("https://gitlab.example.com:8443/design/icons.git"),
+            repository(ForgeKind::GitLab, "gitlab.example.com:8443", "design/icons")
+        );
+
+        assert_eq!(parse_forge_url("https://github.com/a/b/c"), None);
+        assert_eq!(parse_forge_url("https://gitlab.com/icons"), None);
+        assert_eq!(
+            parse_forge_url("https://bitbucket.org/design/icons.git"),
+            None
+        );
+        assert_eq!(parse_forge_url("/srv/git/assets.git"), None);
+        assert_eq!(parse_forge_url("C:/repos/assets"), None);
+    }
+}
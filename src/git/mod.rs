@@ -1,45 +1,281 @@
 use anyhow::{Context, Result};
 use git2::{
-    build::RepoBuilder, Cred, FetchOptions, PushOptions, RemoteCallbacks, Repository,
-    RepositoryInitOptions,
+    build::RepoBuilder, Cred, CredentialType, FetchOptions, PushOptions, RemoteCallbacks,
+    Repository, RepositoryInitOptions,
 };
-use std::path::Path;
-use tracing::{debug, info};
-
-use crate::types::{BundleDependency, DEFAULT_BRANCH, DEFAULT_REMOTE};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::auth::{self, TOKEN_USERNAME};
+use crate::filter::{subdirectory_patterns, PathFilter, PathPatterns};
+use crate::retry::RetryPolicy;
+use crate::settings::{CommitSigning, GitBackend, Settings, SigningFormat};
+use crate::ssh;
+use crate::types::{BundleDependency, CommitSummary, FileDiffStat, DEFAULT_BRANCH, DEFAULT_REMOTE};
+
+/// Options controlling how a bundle repository is cloned
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CloneOptions<'a> {
+    /// Branch (or tag) to check out
+    pub branch: &'a str,
+    /// Optional SSH private key used for authentication
+    pub ssh_key: Option<&'a Path>,
+    /// Optional local repository to borrow objects from (git alternates)
+    pub reference: Option<&'a Path>,
+    /// Only fetch this many commits of history (shallow clone)
+    pub depth: Option<u32>,
+    /// Partial clone filter spec, e.g. "blob:none"
+    pub filter: Option<&'a str>,
+    /// Only check out the paths these patterns select (sparse checkout); others are
+    /// never written to disk
+    pub sparse: Option<PathPatterns<'a>>,
+}
 
 /// Trait for git operations - allows mocking in tests
 pub trait GitOperations: Send + Sync {
-    fn clone_repository(
+    fn clone_repository(&self, url: &str, path: &Path, options: &CloneOptions) -> Result<()>;
+    /// Fetches `branch` from origin and resets the working tree to it, discarding
+    /// uncommitted changes (see `ensure_no_discarded_changes`). With `depth`, the
+    /// fetch stays shallow instead of downloading the history in between.
+    fn fetch_repository(&self, path: &Path, branch: &str, depth: Option<u32>) -> Result<()>;
+    fn init_repository(&self, path: &Path) -> Result<()>;
+    fn add_remote(&self, path: &Path, name: &str, url: &str) -> Result<()>;
+    /// Stages every change and commits it, as `author` if given and otherwise as the
+    /// repository's configured identity
+    fn commit_all(&self, path: &Path, message: &str, author: Option<&CommitAuthor>) -> Result<()>;
+    /// Stages the changes of `paths` only, new and deleted files included, and
+    /// commits them like `commit_all`. Other changes stay in the working tree.
+    fn commit_paths(
         &self,
-        url: &str,
         path: &Path,
-        branch: &str,
-        ssh_key: Option<&Path>,
+        paths: &[String],
+        message: &str,
+        author: Option<&CommitAuthor>,
     ) -> Result<()>;
-    fn fetch_repository(&self, path: &Path, branch: &str) -> Result<()>;
-    fn init_repository(&self, path: &Path) -> Result<()>;
-    fn add_remote(&self, path: &Path, name: &str, url: &str) -> Result<()>;
-    fn commit_all(&self, path: &Path, message: &str) -> Result<()>;
+    /// Pushes HEAD to `branch` of the remote, whichever local branch (if any) is
+    /// checked out
     fn push(&self, path: &Path, remote: &str, branch: &str) -> Result<()>;
+    /// Creates the annotated tag `name` at HEAD, tagged by `author` if given and
+    /// otherwise by the repository's configured identity. Fails if the tag exists,
+    /// unless `force` is set to move it.
+    fn create_tag(
+        &self,
+        path: &Path,
+        name: &str,
+        message: &str,
+        author: Option<&CommitAuthor>,
+        force: bool,
+    ) -> Result<()>;
+    /// Pushes the tag `name` to the remote, replacing the remote's tag if `force`
+    fn push_tag(&self, path: &Path, remote: &str, name: &str, force: bool) -> Result<()>;
+    /// Creates a branch at HEAD and checks it out, keeping the uncommitted changes
+    fn create_branch(&self, path: &Path, name: &str) -> Result<()>;
     fn has_local_changes(&self, path: &Path) -> Result<bool>;
+    /// Paths of the files that differ from HEAD, untracked files included
+    fn changed_files(&self, path: &Path) -> Result<Vec<String>>;
     fn is_repository(&self, path: &Path) -> bool;
     /// Get file content from HEAD commit
     fn get_file_from_head(&self, repo_path: &Path, file_path: &str) -> Result<String>;
+    /// Full hash of the commit currently checked out
+    fn head_commit(&self, path: &Path) -> Result<String>;
+    /// Name of the local branch checked out; None if HEAD is detached, e.g. at a tag
+    fn current_branch(&self, path: &Path) -> Result<Option<String>>;
+    /// Hard-resets the working tree to `commit`, discarding uncommitted changes (see
+    /// `ensure_no_discarded_changes`). When the commit is not available
+    /// locally, `branch` (and failing that the commit itself) is fetched from origin first.
+    /// Shallow clones only fetch the missing commit, not its history.
+    fn checkout_commit(&self, path: &Path, branch: &str, commit: &str) -> Result<()>;
+    /// Lists the tag names of a remote repository without cloning it
+    fn list_remote_tags(&self, url: &str, ssh_key: Option<&Path>) -> Result<Vec<String>>;
+    /// Commit at the tip of a remote branch without cloning (None if the branch doesn't exist)
+    fn remote_branch_commit(
+        &self,
+        url: &str,
+        branch: &str,
+        ssh_key: Option<&Path>,
+    ) -> Result<Option<String>>;
+    /// Branch the HEAD of a remote repository points to, i.e. its default branch,
+    /// without cloning (None if the remote doesn't tell)
+    fn remote_default_branch(&self, url: &str, ssh_key: Option<&Path>) -> Result<Option<String>>;
+    /// Restricts the working tree to the paths selected by `patterns`, removing
+    /// everything else. Later checkouts and fetches keep the restriction.
+    fn set_sparse_checkout(&self, path: &Path, patterns: &PathPatterns) -> Result<()>;
+    /// Fetch a branch from origin into its remote-tracking ref without touching the working tree
+    fn fetch_remote_branch(&self, path: &Path, branch: &str) -> Result<()>;
+    /// Per-file change stats from revision `from` to revision `to`, or to the working
+    /// tree if `to` is None
+    fn diff_stat(&self, path: &Path, from: &str, to: Option<&str>) -> Result<Vec<FileDiffStat>>;
+    /// Unified diff from revision `from` to revision `to`, or to the working tree if
+    /// `to` is None
+    fn diff_patch(&self, path: &Path, from: &str, to: Option<&str>) -> Result<String>;
+    /// The latest `max_count` commits of HEAD, newest first, with the files each changed
+    fn log(&self, path: &Path, max_count: usize) -> Result<Vec<CommitSummary>>;
+    /// Stashes the uncommitted changes of tracked files, leaving untracked files
+    /// alone. Returns false if there was nothing to stash.
+    fn stash_changes(&self, path: &Path) -> Result<bool>;
+    /// Applies the latest stash to the working tree and drops it. Returns the files
+    /// whose changes conflicted with the working tree; the stash is kept then.
+    fn apply_stash(&self, path: &Path) -> Result<Vec<String>>;
+    /// Moves a repository, with its working tree, to another directory on the same
+    /// file system (`to` must not exist)
+    fn move_repository(&self, from: &Path, to: &Path) -> Result<()>;
+    /// Number of commits HEAD is ahead of and behind the given revision
+    fn ahead_behind(&self, path: &Path, revision: &str) -> Result<(usize, usize)>;
+    /// Creates a bare mirror of `url` at `path`, or refreshes it if it already exists
+    fn sync_mirror(&self, url: &str, path: &Path, ssh_key: Option<&Path>) -> Result<()>;
+    /// Housekeeping for long-lived repositories: prunes stale remote branches, packs
+    /// loose objects and refreshes the commit-graph. When `keep_unreachable` is set,
+    /// unreachable objects are never deleted (other clones may borrow them).
+    fn maintain_repository(&self, path: &Path, keep_unreachable: bool) -> Result<()>;
+    /// Copies the objects a clone borrows from another repository (git alternates)
+    /// into the clone and stops borrowing them, so that repository can be removed
+    fn dissociate_repository(&self, path: &Path) -> Result<()>;
 }
 
 /// Default implementation using git2
-pub struct Git2Operations;
+pub struct Git2Operations {
+    /// Longest a transfer may take before it is aborted
+    timeout: Option<Duration>,
+    /// Key signing the commits `commit_all` creates
+    signing: Option<CommitSigning>,
+}
 
 impl Git2Operations {
     pub fn new() -> Self {
-        Self
+        Self {
+            timeout: None,
+            signing: None,
+        }
+    }
+
+    /// Aborts transfers that take longer than `timeout`
+    pub fn with_timeout(timeout: Option<Duration>) -> Self {
+        Self {
+            timeout,
+            signing: None,
+        }
+    }
+
+    /// Signs the commits `commit_all` creates with `signing`
+    pub fn with_signing(self, signing: Option<CommitSigning>) -> Self {
+        Self { signing, ..self }
+    }
+
+    /// Stages the files matching `pathspecs`, new and deleted ones included, and
+    /// commits them. Whatever else is staged stays staged and out of the commit.
+    fn commit_pathspecs(
+        &self,
+        path: &Path,
+        pathspecs: &[String],
+        message: &str,
+        author: Option<&CommitAuthor>,
+    ) -> Result<()> {
+        let repo = Repository::open(path)
+            .with_context(|| format!("Failed to open repository: {}", path.display()))?;
+
+        let mut index = repo.index()?;
+        index.add_all(pathspecs.iter(), git2::IndexAddOption::DEFAULT, None)?;
+        index.update_all(pathspecs.iter(), None)?;
+        index.write()?;
+
+        // Get parent commit if exists
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+
+        // The committed tree is HEAD's, with the matching files as staged
+        let matcher = git2::Pathspec::new(pathspecs.iter())?;
+        let entry_path = |entry: &git2::IndexEntry| {
+            PathBuf::from(String::from_utf8_lossy(&entry.path).into_owned())
+        };
+        let matches = |entry: &git2::IndexEntry| {
+            matcher.matches_path(&entry_path(entry), git2::PathspecFlags::DEFAULT)
+        };
+        let mut committed = git2::Index::new()?;
+        if let Some(parent) = &parent {
+            committed.read_tree(&parent.tree()?)?;
+        }
+        let removed: Vec<PathBuf> = committed
+            .iter()
+            .filter(|entry| matches(entry))
+            .map(|entry| entry_path(&entry))
+            .filter(|removed| index.get_path(removed, 0).is_none())
+            .collect();
+        for removed in removed {
+            committed.remove_path(&removed)?;
+        }
+        for entry in index.iter().filter(|entry| matches(entry)) {
+            committed.add(&entry)?;
+        }
+        let tree_id = committed.write_tree_to(&repo)?;
+        let tree = repo.find_tree(tree_id)?;
+
+        let sig = signature(&repo, author)?;
+
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+
+        let Some(signing) = &self.signing else {
+            repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)?;
+            return Ok(());
+        };
+
+        // libgit2 doesn't sign: sign the commit object, then move HEAD to it
+        let buffer = repo.commit_create_buffer(&sig, &sig, message, &tree, &parents)?;
+        let content = std::str::from_utf8(&buffer).context("Commit is not valid UTF-8")?;
+        let signature = sign_commit(signing, content)?;
+        let commit = repo.commit_signed(content, &signature, Some("gpgsig"))?;
+        let head = repo.find_reference("HEAD")?;
+        match head.symbolic_target() {
+            Some(branch) => {
+                repo.reference(branch, commit, true, &format!("commit: {}", message))?;
+            }
+            None => repo.set_head_detached(commit)?,
+        }
+
+        Ok(())
     }
 
-    fn get_callbacks<'a>() -> RemoteCallbacks<'a> {
+    fn get_callbacks<'a>(&self) -> RemoteCallbacks<'a> {
         let mut callbacks = RemoteCallbacks::new();
 
-        callbacks.credentials(|_url, username_from_url, _allowed_types| {
+        // libgit2 has no timeouts: stop transfers from the progress callbacks instead
+        if let Some(timeout) = self.timeout {
+            let deadline = Instant::now() + timeout;
+            let within_deadline = move || {
+                let within = Instant::now() < deadline;
+                if !within {
+                    warn!("Transfer timed out after {}s", timeout.as_secs());
+                }
+                within
+            };
+            callbacks.transfer_progress(move |_| within_deadline());
+            callbacks.sideband_progress(move |_| within_deadline());
+        }
+
+        let mut token_offered = false;
+        let mut netrc_offered = false;
+        callbacks.credentials(move |url, username_from_url, allowed_types| {
+            // HTTPS remotes use the token from the environment or keychain, then the
+            // credentials in ~/.netrc. Each is offered once so rejected credentials
+            // fail instead of being retried forever.
+            if allowed_types.contains(CredentialType::USER_PASS_PLAINTEXT) {
+                if !token_offered {
+                    token_offered = true;
+                    if let Some(token) = auth::token_for_url(url) {
+                        return Cred::userpass_plaintext(TOKEN_USERNAME, &token);
+                    }
+                }
+                if !netrc_offered {
+                    netrc_offered = true;
+                    if let Some((login, password)) = auth::netrc_credentials(url) {
+                        return Cred::userpass_plaintext(&login, &password);
+                    }
+                }
+            }
+
             // Try SSH agent first, then fall back to default credentials
             if let Some(username) = username_from_url {
                 Cred::ssh_key_from_agent(username)
@@ -50,6 +286,29 @@ impl Git2Operations {
 
         callbacks
     }
+
+    /// Names and commits of the refs a remote advertises, read without cloning it
+    fn remote_refs(&self, url: &str) -> Result<Vec<(String, git2::Oid)>> {
+        // libgit2 hands out a null ref list for a repository on disk without any
+        // refs, which `Remote::list` turns into an invalid slice
+        if is_empty_local_repository(url) {
+            return Ok(Vec::new());
+        }
+
+        let mut remote = git2::Remote::create_detached(url)
+            .with_context(|| format!("Invalid remote URL: {}", url))?;
+        remote
+            .connect_auth(git2::Direction::Fetch, Some(self.get_callbacks()), None)
+            .with_context(|| format!("Failed to connect to {}", url))?;
+
+        let refs = remote
+            .list()
+            .with_context(|| format!("Failed to list refs of {}", url))?
+            .iter()
+            .map(|head| (head.name().to_string(), head.oid()))
+            .collect();
+        Ok(refs)
+    }
 }
 
 impl Default for Git2Operations {
@@ -59,23 +318,21 @@ impl Default for Git2Operations {
 }
 
 impl GitOperations for Git2Operations {
-    fn clone_repository(
-        &self,
-        url: &str,
-        path: &Path,
-        branch: &str,
-        _ssh_key: Option<&Path>,
-    ) -> Result<()> {
-        // Note: Git2Operations currently ignores ssh_key parameter.
-        // For SSH support with custom keys, use GitCliOperations instead.
+    fn clone_repository(&self, url: &str, path: &Path, options: &CloneOptions) -> Result<()> {
+        // Note: Git2Operations currently ignores the ssh_key, reference, filter and sparse
+        // options. For SSH support with custom keys, shared object stores, partial clones
+        // or sparse checkouts, use GitCliOperations instead.
         info!("Cloning {} to {}", url, path.display());
 
-        let callbacks = Self::get_callbacks();
+        let callbacks = self.get_callbacks();
         let mut fetch_options = FetchOptions::new();
         fetch_options.remote_callbacks(callbacks);
+        if let Some(depth) = options.depth {
+            fetch_options.depth(depth_arg(depth));
+        }
 
         RepoBuilder::new()
-            .branch(branch)
+            .branch(options.branch)
             .fetch_options(fetch_options)
             .clone(url, path)
             .with_context(|| format!("Failed to clone repository: {}", url))?;
@@ -83,7 +340,7 @@ impl GitOperations for Git2Operations {
         Ok(())
     }
 
-    fn fetch_repository(&self, path: &Path, branch: &str) -> Result<()> {
+    fn fetch_repository(&self, path: &Path, branch: &str, depth: Option<u32>) -> Result<()> {
         debug!("Fetching updates for {}", path.display());
 
         let repo = Repository::open(path)
@@ -94,14 +351,24 @@ impl GitOperations for Git2Operations {
             .or_else(|_| repo.find_remote(DEFAULT_REMOTE))
             .context("Failed to find remote")?;
 
-        let callbacks = Self::get_callbacks();
+        let callbacks = self.get_callbacks();
         let mut fetch_options = FetchOptions::new();
         fetch_options.remote_callbacks(callbacks);
+        if let Some(depth) = depth {
+            fetch_options.depth(depth_arg(depth));
+        }
 
         remote
             .fetch(&[branch], Some(&mut fetch_options), None)
             .context("Failed to fetch from remote")?;
 
+        // Reset to the fetched ref, like the CLI implementation
+        let fetched = repo
+            .revparse_single("FETCH_HEAD^{commit}")
+            .context("Failed to find the fetched commit")?;
+        repo.reset(&fetched, git2::ResetType::Hard, None)
+            .context("Failed to reset to fetched branch")?;
+
         Ok(())
     }
 
@@ -135,37 +402,69 @@ impl GitOperations for Git2Operations {
         Ok(())
     }
 
-    fn commit_all(&self, path: &Path, message: &str) -> Result<()> {
+    fn commit_all(&self, path: &Path, message: &str, author: Option<&CommitAuthor>) -> Result<()> {
         debug!("Committing all changes in {}", path.display());
+        self.commit_pathspecs(path, &["*".to_string()], message, author)
+    }
+
+    fn commit_paths(
+        &self,
+        path: &Path,
+        paths: &[String],
+        message: &str,
+        author: Option<&CommitAuthor>,
+    ) -> Result<()> {
+        debug!("Committing {} in {}", paths.join(", "), path.display());
+        self.commit_pathspecs(path, paths, message, author)
+    }
+
+    fn push(&self, path: &Path, remote: &str, branch: &str) -> Result<()> {
+        info!("Pushing to {} branch {}", remote, branch);
 
         let repo = Repository::open(path)
             .with_context(|| format!("Failed to open repository: {}", path.display()))?;
 
-        // Add all files
-        let mut index = repo.index()?;
-        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
-        index.write()?;
+        let mut remote_obj = repo
+            .find_remote(remote)
+            .with_context(|| format!("Remote '{}' not found", remote))?;
 
-        let tree_id = index.write_tree()?;
-        let tree = repo.find_tree(tree_id)?;
+        let callbacks = self.get_callbacks();
+        let mut push_options = PushOptions::new();
+        push_options.remote_callbacks(callbacks);
 
-        // Get signature
-        let sig = repo
-            .signature()
-            .or_else(|_| git2::Signature::now("fpm", "fpm@local"))?;
+        let refspec = format!("HEAD:refs/heads/{}", branch);
+        remote_obj
+            .push(&[&refspec], Some(&mut push_options))
+            .with_context(|| format!("Failed to push to {}/{}", remote, branch))?;
 
-        // Get parent commit if exists
-        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        Ok(())
+    }
 
-        let parents: Vec<&git2::Commit> = parent.iter().collect();
+    fn create_tag(
+        &self,
+        path: &Path,
+        name: &str,
+        message: &str,
+        author: Option<&CommitAuthor>,
+        force: bool,
+    ) -> Result<()> {
+        info!("Creating tag {}", name);
+
+        let repo = Repository::open(path)
+            .with_context(|| format!("Failed to open repository: {}", path.display()))?;
+        let head = repo
+            .head()
+            .and_then(|head| head.peel(git2::ObjectType::Commit))
+            .context("Failed to get HEAD commit")?;
 
-        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)?;
+        repo.tag(name, &head, &signature(&repo, author)?, message, force)
+            .with_context(|| format!("Failed to create tag '{}'", name))?;
 
         Ok(())
     }
 
-    fn push(&self, path: &Path, remote: &str, branch: &str) -> Result<()> {
-        info!("Pushing to {} branch {}", remote, branch);
+    fn push_tag(&self, path: &Path, remote: &str, name: &str, force: bool) -> Result<()> {
+        info!("Pushing tag {} to {}", name, remote);
 
         let repo = Repository::open(path)
             .with_context(|| format!("Failed to open repository: {}", path.display()))?;
@@ -174,14 +473,37 @@ impl GitOperations for Git2Operations {
             .find_remote(remote)
             .with_context(|| format!("Remote '{}' not found", remote))?;
 
-        let callbacks = Self::get_callbacks();
         let mut push_options = PushOptions::new();
-        push_options.remote_callbacks(callbacks);
+        push_options.remote_callbacks(self.get_callbacks());
 
-        let refspec = format!("refs/heads/{}:refs/heads/{}", branch, branch);
+        let refspec = format!(
+            "{}refs/tags/{}:refs/tags/{}",
+            if force { "+" } else { "" },
+            name,
+            name
+        );
         remote_obj
             .push(&[&refspec], Some(&mut push_options))
-            .with_context(|| format!("Failed to push to {}/{}", remote, branch))?;
+            .with_context(|| format!("Failed to push tag {} to {}", name, remote))?;
+
+        Ok(())
+    }
+
+    fn create_branch(&self, path: &Path, name: &str) -> Result<()> {
+        info!("Creating branch {}", name);
+
+        let repo = Repository::open(path)
+            .with_context(|| format!("Failed to open repository: {}", path.display()))?;
+        let head = repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .context("Failed to find the HEAD commit")?;
+        repo.branch(name, &head, false)
+            .with_context(|| format!("Failed to create branch '{}'", name))?;
+
+        // Only HEAD moves, so the working tree and index keep their changes
+        repo.set_head(&format!("refs/heads/{}", name))
+            .with_context(|| format!("Failed to check out branch '{}'", name))?;
 
         Ok(())
     }
@@ -190,11 +512,29 @@ impl GitOperations for Git2Operations {
         let repo = Repository::open(path)
             .with_context(|| format!("Failed to open repository: {}", path.display()))?;
 
-        let statuses = repo.statuses(None)?;
+        // The default options list ignored files too, e.g. the .fpm directory of a
+        // bundle with nested bundles
+        let mut options = git2::StatusOptions::new();
+        options.include_untracked(true);
+        let statuses = repo.statuses(Some(&mut options))?;
 
         Ok(!statuses.is_empty())
     }
 
+    fn changed_files(&self, path: &Path) -> Result<Vec<String>> {
+        let repo = Repository::open(path)
+            .with_context(|| format!("Failed to open repository: {}", path.display()))?;
+
+        let mut options = git2::StatusOptions::new();
+        options.include_untracked(true).recurse_untracked_dirs(true);
+        let statuses = repo.statuses(Some(&mut options))?;
+
+        Ok(statuses
+            .iter()
+            .filter_map(|entry| entry.path().map(str::to_string))
+            .collect())
+    }
+
     fn is_repository(&self, path: &Path) -> bool {
         Repository::open(path).is_ok()
     }
@@ -220,321 +560,1964 @@ impl GitOperations for Git2Operations {
 
         Ok(content.to_string())
     }
-}
 
-/// CLI-based git implementation using the system git command.
-/// This is more reliable for HTTPS authentication as it uses the user's
-/// configured credential helpers.
-pub struct GitCliOperations;
+    fn head_commit(&self, path: &Path) -> Result<String> {
+        let repo = Repository::open(path)
+            .with_context(|| format!("Failed to open repository: {}", path.display()))?;
 
-impl GitCliOperations {
-    pub fn new() -> Self {
-        Self
-    }
+        let commit = repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .context("Failed to get HEAD commit")?;
 
-    fn run_git(&self, args: &[&str], working_dir: Option<&Path>) -> Result<()> {
-        self.run_git_with_ssh_key(args, working_dir, None)
+        Ok(commit.id().to_string())
     }
 
-    /// Runs a git command with optional SSH key authentication.
-    /// When ssh_key is provided, sets GIT_SSH_COMMAND to use the specified key.
-    fn run_git_with_ssh_key(
-        &self,
-        args: &[&str],
-        working_dir: Option<&Path>,
-        ssh_key: Option<&Path>,
-    ) -> Result<()> {
-        let mut cmd = std::process::Command::new("git");
-        cmd.args(args);
-
-        if let Some(dir) = working_dir {
-            cmd.current_dir(dir);
-        }
+    fn current_branch(&self, path: &Path) -> Result<Option<String>> {
+        let repo = Repository::open(path)
+            .with_context(|| format!("Failed to open repository: {}", path.display()))?;
 
-        // Set SSH command if an SSH key is provided
-        if let Some(key_path) = ssh_key {
-            let key_path_str = key_path.to_string_lossy();
-            // Use -o StrictHostKeyChecking=accept-new to auto-accept new host keys
-            let ssh_command = format!(
-                "ssh -i \"{}\" -o StrictHostKeyChecking=accept-new -o BatchMode=yes",
-                key_path_str
-            );
-            cmd.env("GIT_SSH_COMMAND", ssh_command);
-            debug!("Using SSH key: {}", key_path_str);
+        if repo.head_detached()? {
+            return Ok(None);
         }
+        let head = repo.head().context("Failed to get HEAD")?;
+        Ok(head.shorthand().map(str::to_string))
+    }
 
-        let output = cmd.output().context("Failed to execute git command")?;
+    fn checkout_commit(&self, path: &Path, branch: &str, commit: &str) -> Result<()> {
+        debug!("Checking out {} in {}", commit, path.display());
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            anyhow::bail!("Git command failed: {}", stderr);
+        let repo = Repository::open(path)
+            .with_context(|| format!("Failed to open repository: {}", path.display()))?;
+        let object = format!("{}^{{commit}}", commit);
+
+        if repo.revparse_single(&object).is_err() {
+            let mut remote = repo
+                .find_remote("origin")
+                .context("Failed to find remote 'origin'")?;
+
+            let mut fetch_options = FetchOptions::new();
+            fetch_options.remote_callbacks(self.get_callbacks());
+            let refspec = format!("+refs/heads/{}:refs/remotes/origin/{}", branch, branch);
+            remote
+                .fetch(&[&refspec], Some(&mut fetch_options), None)
+                .with_context(|| format!("Failed to fetch origin/{}", branch))?;
+
+            if repo.revparse_single(&object).is_err() {
+                let mut fetch_options = FetchOptions::new();
+                fetch_options.remote_callbacks(self.get_callbacks());
+                if repo.is_shallow() {
+                    fetch_options.depth(1);
+                }
+                remote
+                    .fetch(&[commit], Some(&mut fetch_options), None)
+                    .with_context(|| format!("Failed to fetch commit {}", commit))?;
+            }
         }
 
+        let object = repo
+            .revparse_single(&object)
+            .with_context(|| format!("Commit {} not found on the remote", commit))?;
+        repo.reset(&object, git2::ResetType::Hard, None)
+            .with_context(|| format!("Failed to reset to {}", commit))?;
+
         Ok(())
     }
-}
 
-impl Default for GitCliOperations {
-    fn default() -> Self {
-        Self::new()
+    fn list_remote_tags(&self, url: &str, _ssh_key: Option<&Path>) -> Result<Vec<String>> {
+        debug!("Listing tags of {}", url);
+
+        let tags = self
+            .remote_refs(url)?
+            .iter()
+            .filter_map(|(name, _)| parse_tag_ref(name))
+            .collect();
+
+        Ok(tags)
     }
-}
 
-impl GitOperations for GitCliOperations {
-    fn clone_repository(
+    fn remote_branch_commit(
         &self,
         url: &str,
-        path: &Path,
         branch: &str,
-        ssh_key: Option<&Path>,
-    ) -> Result<()> {
-        info!("Cloning {} to {} (branch: {})", url, path.display(), branch);
+        _ssh_key: Option<&Path>,
+    ) -> Result<Option<String>> {
+        debug!("Looking up {} of {}", branch, url);
 
-        let args = [
-            "clone",
-            "--branch",
-            branch,
-            "--single-branch",
-            url,
-            &path.to_string_lossy(),
-        ];
+        let branch_ref = format!("refs/heads/{}", branch);
+        let commit = self
+            .remote_refs(url)?
+            .into_iter()
+            .find(|(name, _)| *name == branch_ref)
+            .map(|(_, oid)| oid.to_string());
 
-        self.run_git_with_ssh_key(&args, None, ssh_key)
-            .with_context(|| format!("Failed to clone repository: {}", url))
+        Ok(commit)
     }
 
-    fn fetch_repository(&self, path: &Path, branch: &str) -> Result<()> {
-        debug!("Fetching updates for {}", path.display());
+    fn remote_default_branch(&self, url: &str, _ssh_key: Option<&Path>) -> Result<Option<String>> {
+        debug!("Looking up the default branch of {}", url);
 
-        self.run_git(&["fetch", "origin", branch], Some(path))
-            .context("Failed to fetch from remote")?;
+        let mut remote = git2::Remote::create_detached(url)
+            .with_context(|| format!("Invalid remote URL: {}", url))?;
+        remote
+            .connect_auth(git2::Direction::Fetch, Some(self.get_callbacks()), None)
+            .with_context(|| format!("Failed to connect to {}", url))?;
 
-        // Reset to the fetched branch
-        self.run_git(
-            &["reset", "--hard", &format!("origin/{}", branch)],
-            Some(path),
-        )
-        .context("Failed to reset to fetched branch")?;
+        // Fails when the remote doesn't advertise what HEAD points to
+        let Ok(head) = remote.default_branch() else {
+            return Ok(None);
+        };
+        Ok(head
+            .as_str()
+            .and_then(|head| head.strip_prefix("refs/heads/"))
+            .map(str::to_string))
+    }
+
+    fn set_sparse_checkout(&self, path: &Path, patterns: &PathPatterns) -> Result<()> {
+        // libgit2 has no sparse checkout: delete the files that aren't selected
+        apply_path_filter(path, patterns)
+    }
+
+    fn fetch_remote_branch(&self, path: &Path, branch: &str) -> Result<()> {
+        debug!("Fetching origin/{} for {}", branch, path.display());
+
+        let repo = Repository::open(path)
+            .with_context(|| format!("Failed to open repository: {}", path.display()))?;
+
+        let mut remote = repo
+            .find_remote("origin")
+            .context("Failed to find remote 'origin'")?;
+
+        let callbacks = self.get_callbacks();
+        let mut fetch_options = FetchOptions::new();
+        fetch_options.remote_callbacks(callbacks);
+
+        let refspec = format!("+refs/heads/{}:refs/remotes/origin/{}", branch, branch);
+        remote
+            .fetch(&[&refspec], Some(&mut fetch_options), None)
+            .with_context(|| format!("Failed to fetch origin/{}", branch))?;
 
         Ok(())
     }
 
-    fn init_repository(&self, path: &Path) -> Result<()> {
-        info!("Initializing git repository at {}", path.display());
+    fn diff_stat(&self, path: &Path, from: &str, to: Option<&str>) -> Result<Vec<FileDiffStat>> {
+        let repo = Repository::open(path)
+            .with_context(|| format!("Failed to open repository: {}", path.display()))?;
+        let diff = diff_revisions(&repo, from, to)?;
+
+        let mut stats = Vec::new();
+        for idx in 0..diff.deltas().len() {
+            let Some(patch) = git2::Patch::from_diff(&diff, idx)? else {
+                continue;
+            };
+
+            let delta = patch.delta();
+            let file_path = delta
+                .new_file()
+                .path()
+                .or_else(|| delta.old_file().path())
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            if delta.flags().is_binary() {
+                stats.push(FileDiffStat {
+                    path: file_path,
+                    insertions: None,
+                    deletions: None,
+                });
+            } else {
+                let (_, insertions, deletions) = patch.line_stats()?;
+                stats.push(FileDiffStat {
+                    path: file_path,
+                    insertions: Some(insertions),
+                    deletions: Some(deletions),
+                });
+            }
+        }
 
-        std::fs::create_dir_all(path)?;
-        self.run_git(&["init", "-b", DEFAULT_BRANCH], Some(path))
-            .with_context(|| format!("Failed to initialize repository: {}", path.display()))
+        Ok(stats)
     }
 
-    fn add_remote(&self, path: &Path, name: &str, url: &str) -> Result<()> {
-        debug!("Adding remote {} -> {}", name, url);
+    fn diff_patch(&self, path: &Path, from: &str, to: Option<&str>) -> Result<String> {
+        let repo = Repository::open(path)
+            .with_context(|| format!("Failed to open repository: {}", path.display()))?;
+        let diff = diff_revisions(&repo, from, to)?;
 
-        // Try to add, if it fails try to set-url
-        if self
-            .run_git(&["remote", "add", name, url], Some(path))
-            .is_err()
-        {
-            self.run_git(&["remote", "set-url", name, url], Some(path))?;
+        let mut patch = String::new();
+        diff.print(git2::DiffFormat::Patch, |_, _, line| {
+            // Content lines come without their +/-/space prefix
+            if matches!(line.origin(), '+' | '-' | ' ') {
+                patch.push(line.origin());
+            }
+            patch.push_str(&String::from_utf8_lossy(line.content()));
+            true
+        })?;
+
+        Ok(patch)
+    }
+
+    fn log(&self, path: &Path, max_count: usize) -> Result<Vec<CommitSummary>> {
+        let repo = Repository::open(path)
+            .with_context(|| format!("Failed to open repository: {}", path.display()))?;
+
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push_head().context("Failed to find HEAD")?;
+        revwalk.set_sorting(git2::Sort::TIME)?;
+
+        let mut commits = Vec::new();
+        for oid in revwalk.take(max_count) {
+            let commit = repo.find_commit(oid?)?;
+            let parent_tree = match commit.parents().next() {
+                Some(parent) => Some(parent.tree()?),
+                None => None,
+            };
+            let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+            let files = diff
+                .deltas()
+                .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+                .map(|path| path.to_string_lossy().to_string())
+                .collect();
+
+            commits.push(CommitSummary {
+                id: commit.id().to_string(),
+                author: commit.author().name().unwrap_or_default().to_string(),
+                time: commit.time().seconds(),
+                summary: commit.summary().unwrap_or_default().to_string(),
+                files,
+            });
         }
 
-        Ok(())
+        Ok(commits)
     }
 
-    fn commit_all(&self, path: &Path, message: &str) -> Result<()> {
-        debug!("Committing all changes in {}", path.display());
+    fn stash_changes(&self, path: &Path) -> Result<bool> {
+        let mut repo = Repository::open(path)
+            .with_context(|| format!("Failed to open repository: {}", path.display()))?;
+        let signature = repo
+            .signature()
+            .or_else(|_| git2::Signature::now("fpm", "fpm@local"))?;
 
-        self.run_git(&["add", "-A"], Some(path))?;
-        self.run_git(&["commit", "-m", message], Some(path))?;
+        match repo.stash_save(&signature, "fpm autostash", None) {
+            Ok(_) => Ok(true),
+            Err(error) if error.code() == git2::ErrorCode::NotFound => Ok(false),
+            Err(error) => Err(error).context("Failed to stash local changes"),
+        }
+    }
 
-        Ok(())
+    fn apply_stash(&self, path: &Path) -> Result<Vec<String>> {
+        let mut repo = Repository::open(path)
+            .with_context(|| format!("Failed to open repository: {}", path.display()))?;
+
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout.allow_conflicts(true);
+        let mut options = git2::StashApplyOptions::new();
+        options.checkout_options(checkout);
+        let applied = repo.stash_apply(0, Some(&mut options));
+
+        let mut conflicts = Vec::new();
+        for conflict in repo.index()?.conflicts()? {
+            let conflict = conflict?;
+            if let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) {
+                conflicts.push(String::from_utf8_lossy(&entry.path).to_string());
+            }
+        }
+
+        match applied {
+            Ok(()) if conflicts.is_empty() => {
+                repo.stash_drop(0).context("Failed to drop the stash")?;
+                Ok(conflicts)
+            }
+            Ok(()) => Ok(conflicts),
+            Err(error) if error.code() == git2::ErrorCode::Conflict => {
+                // libgit2 refuses to apply over changed files: report the files the
+                // stash touches
+                let stash = repo.revparse_single("stash@{0}")?.peel_to_commit()?;
+                let base = stash.parent(0)?.tree()?;
+                let diff = repo.diff_tree_to_tree(Some(&base), Some(&stash.tree()?), None)?;
+                Ok(diff
+                    .deltas()
+                    .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+                    .map(|path| path.to_string_lossy().to_string())
+                    .collect())
+            }
+            Err(error) => Err(error).context("Failed to apply the stash"),
+        }
     }
 
-    fn push(&self, path: &Path, remote: &str, branch: &str) -> Result<()> {
-        info!("Pushing to {} branch {}", remote, branch);
+    fn move_repository(&self, from: &Path, to: &Path) -> Result<()> {
+        move_directory(from, to)
+    }
 
-        self.run_git(&["push", "-u", remote, branch], Some(path))
-            .with_context(|| format!("Failed to push to {}/{}", remote, branch))
+    fn ahead_behind(&self, path: &Path, revision: &str) -> Result<(usize, usize)> {
+        let repo = Repository::open(path)
+            .with_context(|| format!("Failed to open repository: {}", path.display()))?;
+
+        let head = repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .context("Failed to resolve HEAD")?;
+        let other = repo
+            .revparse_single(revision)
+            .and_then(|obj| obj.peel_to_commit())
+            .with_context(|| format!("Failed to resolve revision '{}'", revision))?;
+
+        repo.graph_ahead_behind(head.id(), other.id())
+            .with_context(|| format!("Failed to compare HEAD with '{}'", revision))
     }
 
-    fn has_local_changes(&self, path: &Path) -> Result<bool> {
-        let output = std::process::Command::new("git")
-            .args(["status", "--porcelain"])
-            .current_dir(path)
-            .output()
-            .context("Failed to check git status")?;
+    fn sync_mirror(&self, url: &str, path: &Path, _ssh_key: Option<&Path>) -> Result<()> {
+        let callbacks = self.get_callbacks();
+        let mut fetch_options = FetchOptions::new();
+        fetch_options.remote_callbacks(callbacks);
 
-        Ok(!output.stdout.is_empty())
+        if !path.exists() {
+            info!("Mirroring {} to {}", url, path.display());
+
+            RepoBuilder::new()
+                .bare(true)
+                .fetch_options(fetch_options)
+                .clone(url, path)
+                .with_context(|| format!("Failed to mirror repository: {}", url))?;
+
+            return Ok(());
+        }
+
+        debug!("Refreshing mirror {}", path.display());
+
+        let repo = Repository::open_bare(path)
+            .with_context(|| format!("Failed to open mirror: {}", path.display()))?;
+        let mut remote = repo
+            .find_remote("origin")
+            .context("Failed to find remote 'origin'")?;
+
+        remote
+            .fetch(
+                &["+refs/heads/*:refs/heads/*", "+refs/tags/*:refs/tags/*"],
+                Some(&mut fetch_options),
+                None,
+            )
+            .with_context(|| format!("Failed to refresh mirror of {}", url))?;
+
+        Ok(())
+    }
+
+    fn maintain_repository(&self, path: &Path, _keep_unreachable: bool) -> Result<()> {
+        // Note: libgit2 has no gc or commit-graph support; only stale remote branches
+        // are pruned here. Use GitCliOperations for full maintenance.
+        debug!("Pruning remote branches in {}", path.display());
+
+        let repo = Repository::open(path)
+            .with_context(|| format!("Failed to open repository: {}", path.display()))?;
+
+        let Ok(mut remote) = repo.find_remote("origin") else {
+            return Ok(());
+        };
+
+        remote
+            .connect_auth(git2::Direction::Fetch, Some(self.get_callbacks()), None)
+            .context("Failed to connect to remote")?;
+        remote
+            .prune(Some(self.get_callbacks()))
+            .context("Failed to prune remote branches")?;
+        remote.disconnect()?;
+
+        Ok(())
+    }
+
+    fn dissociate_repository(&self, path: &Path) -> Result<()> {
+        // Note: libgit2 can't repack, so the borrowed object files are copied as they are
+        copy_alternate_objects(path)
+    }
+}
+
+/// CLI-based git implementation using the system git command.
+/// This is more reliable for HTTPS authentication as it uses the user's
+/// configured credential helpers.
+pub struct GitCliOperations {
+    /// Settings holding the SSH key and per-host SSH settings used when an
+    /// operation doesn't name its own key
+    settings: Settings,
+}
+
+impl GitCliOperations {
+    pub fn new() -> Self {
+        Self {
+            settings: Settings::default(),
+        }
+    }
+
+    /// Uses `ssh_key` for every operation that doesn't name its own key
+    pub fn with_ssh_key(ssh_key: Option<PathBuf>) -> Self {
+        Self {
+            settings: Settings {
+                ssh_key,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Uses the SSH key and per-host SSH settings of `settings`
+    pub fn with_settings(settings: Settings) -> Self {
+        Self { settings }
+    }
+
+    /// Stages the changes of `paths`, or of every file if empty, and commits them.
+    /// With `paths`, whatever else is staged stays staged and out of the commit.
+    fn commit(
+        &self,
+        path: &Path,
+        paths: &[String],
+        message: &str,
+        author: Option<&CommitAuthor>,
+    ) -> Result<()> {
+        let add: Vec<&str> = ["add", "-A", "--"]
+            .into_iter()
+            .chain(paths.iter().map(String::as_str))
+            .collect();
+        self.run_git(&add, Some(path))?;
+
+        let mut config = Vec::new();
+        if let Some(author) = author {
+            config.push(format!("user.name={}", author.name));
+            config.push(format!("user.email={}", author.email));
+        }
+        let mut commit = vec!["commit".to_string()];
+        if let Some(signing) = self.settings.commit_signing() {
+            config.push(match signing.format {
+                SigningFormat::Gpg => "gpg.format=openpgp".to_string(),
+                SigningFormat::Ssh => "gpg.format=ssh".to_string(),
+            });
+            commit.push(format!("--gpg-sign={}", signing.key));
+        }
+        let args: Vec<&str> = config
+            .iter()
+            .flat_map(|entry| ["-c", entry.as_str()])
+            .chain(commit.iter().map(String::as_str))
+            .chain(["-m", message])
+            .chain(
+                (!paths.is_empty())
+                    .then_some("--")
+                    .into_iter()
+                    .chain(paths.iter().map(String::as_str)),
+            )
+            .collect();
+        self.run_git(&args, Some(path))?;
+
+        Ok(())
+    }
+
+    /// Returns true if the repository at `path` is a shallow clone
+    fn is_shallow(&self, path: &Path) -> bool {
+        self.run_git_output(&["rev-parse", "--is-shallow-repository"], Some(path))
+            .is_ok_and(|output| output.trim() == "true")
+    }
+
+    fn run_git(&self, args: &[&str], working_dir: Option<&Path>) -> Result<()> {
+        self.run_git_with_ssh_key(args, working_dir, None)?;
+        Ok(())
+    }
+
+    /// Runs a git command and returns its stdout
+    fn run_git_output(&self, args: &[&str], working_dir: Option<&Path>) -> Result<String> {
+        self.run_git_with_ssh_key(args, working_dir, None)
+    }
+
+    /// Returns the URLs a command talks to: those among its arguments and the
+    /// remotes of its repository
+    fn remote_urls(&self, args: &[&str], working_dir: Option<&Path>) -> Vec<String> {
+        let mut urls: Vec<String> = args
+            .iter()
+            .filter(|arg| arg.contains("://") || arg.contains('@'))
+            .map(|arg| arg.to_string())
+            .collect();
+
+        // Read in-process, so commands don't spawn git once more to look them up
+        let config = working_dir.and_then(|dir| {
+            Repository::discover(dir)
+                .and_then(|repo| repo.config())
+                .ok()
+        });
+        if let Some(config) = &config {
+            if let Ok(entries) = config.entries(Some(r"^remote\..*\.url$")) {
+                let _ = entries.for_each(|entry| urls.extend(entry.value().map(str::to_string)));
+            }
+        }
+
+        urls
+    }
+
+    /// Returns the extra header entries authenticating the HTTPS URLs a command
+    /// talks to
+    fn token_headers(&self, urls: &[String]) -> Vec<(String, String)> {
+        let headers: BTreeMap<String, String> = urls
+            .iter()
+            .filter_map(|url| auth::extra_header(url))
+            .collect();
+        headers.into_iter().collect()
+    }
+
+    /// Returns the ssh command using `key` and the extra `options`, or None when
+    /// ssh's defaults apply
+    fn ssh_command(
+        &self,
+        args: &[&str],
+        key: Option<&Path>,
+        options: &[String],
+    ) -> Result<Option<(String, Option<String>)>> {
+        if key.is_none() && options.is_empty() {
+            return Ok(None);
+        }
+
+        let mut command = String::from("ssh");
+        let mut passphrase = None;
+        if let Some(key_path) = key {
+            let key_path_str = key_path.to_string_lossy();
+            if contacts_remote(args) {
+                passphrase = ssh::passphrase_for(key_path)?;
+            }
+            // Use -o StrictHostKeyChecking=accept-new to auto-accept new host keys.
+            // Without a passphrase to give, ssh must not prompt for anything.
+            command.push_str(&format!(
+                " -i \"{}\" -o StrictHostKeyChecking=accept-new",
+                key_path_str
+            ));
+            if passphrase.is_none() {
+                command.push_str(" -o BatchMode=yes");
+            }
+            debug!("Using SSH key: {}", key_path_str);
+        }
+        for option in options {
+            command.push_str(&format!(" -o \"{}\"", option));
+        }
+
+        Ok(Some((command, passphrase)))
+    }
+
+    /// Runs a git command with optional SSH key authentication and returns its stdout.
+    /// When ssh_key is provided or configured, sets GIT_SSH_COMMAND to use the key.
+    fn run_git_with_ssh_key(
+        &self,
+        args: &[&str],
+        working_dir: Option<&Path>,
+        ssh_key: Option<&Path>,
+    ) -> Result<String> {
+        let mut cmd = std::process::Command::new("git");
+        cmd.args(args);
+
+        if let Some(dir) = working_dir {
+            cmd.current_dir(dir);
+        }
+
+        let urls = self.remote_urls(args, working_dir);
+
+        // Set SSH command if an SSH key is provided or configured, preferring the
+        // settings of the host the command talks to over the global key
+        let host = urls.iter().find_map(|url| self.settings.ssh_host(url));
+        let key = ssh_key
+            .or(host.and_then(|host| host.key.as_deref()))
+            .or(self.settings.ssh_key.as_deref());
+        let mut options = host.map(|host| host.options.clone()).unwrap_or_default();
+        if let Some(seconds) = self.settings.connect_timeout {
+            options.push(format!("ConnectTimeout={}", seconds));
+        }
+        if let Some((ssh_command, passphrase)) = self.ssh_command(args, key, &options)? {
+            cmd.env("GIT_SSH_COMMAND", ssh_command);
+
+            // ssh asks the fpm executable for the passphrase, which prints it
+            if let Some(passphrase) = passphrase {
+                let askpass =
+                    std::env::current_exe().context("Failed to locate the fpm executable")?;
+                cmd.env("SSH_ASKPASS", askpass)
+                    .env("SSH_ASKPASS_REQUIRE", "force")
+                    .env(ssh::ASKPASS_ENV, passphrase);
+            }
+        }
+
+        // Send tokens from the environment to HTTPS remotes. They are passed as
+        // extra headers through the environment, so they never appear in the command
+        // line or end up in a repository's config.
+        let mut config = self.token_headers(&urls);
+        // Give up on HTTP transfers that stall for longer than the connect timeout
+        if let Some(seconds) = self.settings.connect_timeout {
+            config.push(("http.lowSpeedLimit".to_string(), "1".to_string()));
+            config.push(("http.lowSpeedTime".to_string(), seconds.to_string()));
+        }
+        if !config.is_empty() {
+            let offset: usize = std::env::var("GIT_CONFIG_COUNT")
+                .ok()
+                .and_then(|count| count.parse().ok())
+                .unwrap_or(0);
+            for (i, (key, value)) in config.iter().enumerate() {
+                cmd.env(format!("GIT_CONFIG_KEY_{}", offset + i), key);
+                cmd.env(format!("GIT_CONFIG_VALUE_{}", offset + i), value);
+            }
+            cmd.env("GIT_CONFIG_COUNT", (offset + config.len()).to_string());
+        }
+
+        let timeout = self
+            .settings
+            .timeout
+            .filter(|_| contacts_remote(args))
+            .map(Duration::from_secs);
+        let output = output_with_timeout(cmd, timeout)?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Git command failed: {}", stderr);
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
+
+impl Default for GitCliOperations {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GitOperations for GitCliOperations {
+    fn clone_repository(&self, url: &str, path: &Path, options: &CloneOptions) -> Result<()> {
+        info!(
+            "Cloning {} to {} (branch: {})",
+            url,
+            path.display(),
+            options.branch
+        );
+
+        let path_str = path.to_string_lossy();
+        let reference_str = options.reference.map(|r| r.to_string_lossy());
+        let depth_str = options.depth.map(|depth| format!("--depth={}", depth));
+        let filter_str = options.filter.map(|filter| format!("--filter={}", filter));
+
+        let mut args = vec!["clone", "--branch", options.branch, "--single-branch"];
+        if options.sparse.is_some() {
+            // Check out after the sparse patterns are set so excluded paths are never written
+            args.push("--no-checkout");
+        }
+        if let Some(depth) = &depth_str {
+            args.push(depth);
+        }
+        // Later fetches reuse the filter, which git records as remote.origin.partialclonefilter
+        if let Some(filter) = &filter_str {
+            args.push(filter);
+        }
+        if let Some(reference) = &reference_str {
+            // Borrow objects from the shared store instead of downloading them again
+            args.push("--reference-if-able");
+            args.push(reference);
+        }
+        args.push(url);
+        args.push(&path_str);
+
+        self.run_git_with_ssh_key(&args, None, options.ssh_key)
+            .with_context(|| format!("Failed to clone repository: {}", url))?;
+
+        if let Some(patterns) = &options.sparse {
+            self.set_sparse_checkout(path, patterns)?;
+            self.run_git_with_ssh_key(&["checkout"], Some(path), options.ssh_key)
+                .with_context(|| format!("Failed to check out {}", options.branch))?;
+        }
+
+        Ok(())
+    }
+
+    fn fetch_repository(&self, path: &Path, branch: &str, depth: Option<u32>) -> Result<()> {
+        debug!("Fetching updates for {}", path.display());
+
+        let depth_str = depth.map(|depth| format!("--depth={}", depth));
+        let mut args = vec!["fetch"];
+        if let Some(depth) = &depth_str {
+            args.push(depth);
+        }
+        args.extend(["origin", branch]);
+        self.run_git(&args, Some(path))
+            .context("Failed to fetch from remote")?;
+
+        // Reset to the fetched ref (FETCH_HEAD works for branches and tags alike)
+        self.run_git(&["reset", "--hard", "FETCH_HEAD"], Some(path))
+            .context("Failed to reset to fetched branch")?;
+
+        Ok(())
+    }
+
+    fn init_repository(&self, path: &Path) -> Result<()> {
+        info!("Initializing git repository at {}", path.display());
+
+        std::fs::create_dir_all(path)?;
+        self.run_git(&["init", "-b", DEFAULT_BRANCH], Some(path))
+            .with_context(|| format!("Failed to initialize repository: {}", path.display()))
+    }
+
+    fn add_remote(&self, path: &Path, name: &str, url: &str) -> Result<()> {
+        debug!("Adding remote {} -> {}", name, url);
+
+        // Try to add, if it fails try to set-url
+        if self
+            .run_git(&["remote", "add", name, url], Some(path))
+            .is_err()
+        {
+            self.run_git(&["remote", "set-url", name, url], Some(path))?;
+        }
+
+        Ok(())
+    }
+
+    fn commit_all(&self, path: &Path, message: &str, author: Option<&CommitAuthor>) -> Result<()> {
+        debug!("Committing all changes in {}", path.display());
+
+        self.commit(path, &[], message, author)
+    }
+
+    fn commit_paths(
+        &self,
+        path: &Path,
+        paths: &[String],
+        message: &str,
+        author: Option<&CommitAuthor>,
+    ) -> Result<()> {
+        debug!("Committing {} in {}", paths.join(", "), path.display());
+        self.commit(path, paths, message, author)
+    }
+    fn push(&self, path: &Path, remote: &str, branch: &str) -> Result<()> {
+        info!("Pushing to {} branch {}", remote, branch);
+
+        let refspec = format!("HEAD:refs/heads/{}", branch);
+        self.run_git(&["push", remote, &refspec], Some(path))
+            .with_context(|| format!("Failed to push to {}/{}", remote, branch))
+    }
+
+    fn create_tag(
+        &self,
+        path: &Path,
+        name: &str,
+        message: &str,
+        author: Option<&CommitAuthor>,
+        force: bool,
+    ) -> Result<()> {
+        info!("Creating tag {}", name);
+
+        let mut config = Vec::new();
+        if let Some(author) = author {
+            config.push(format!("user.name={}", author.name));
+            config.push(format!("user.email={}", author.email));
+        }
+        let args: Vec<&str> = config
+            .iter()
+            .flat_map(|entry| ["-c", entry.as_str()])
+            .chain(["tag", "-a", name, "-m", message])
+            .chain(force.then_some("--force"))
+            .collect();
+        self.run_git(&args, Some(path))
+            .with_context(|| format!("Failed to create tag '{}'", name))
+    }
+
+    fn push_tag(&self, path: &Path, remote: &str, name: &str, force: bool) -> Result<()> {
+        info!("Pushing tag {} to {}", name, remote);
+
+        let refspec = format!("{}refs/tags/{}", if force { "+" } else { "" }, name);
+        self.run_git(&["push", remote, &refspec], Some(path))
+            .with_context(|| format!("Failed to push tag {} to {}", name, remote))
+    }
+
+    fn create_branch(&self, path: &Path, name: &str) -> Result<()> {
+        info!("Creating branch {}", name);
+
+        self.run_git(&["checkout", "-b", name], Some(path))
+            .with_context(|| format!("Failed to create branch '{}'", name))?;
+
+        Ok(())
+    }
+
+    fn has_local_changes(&self, path: &Path) -> Result<bool> {
+        let output = std::process::Command::new("git")
+            .args(["status", "--porcelain"])
+            .current_dir(path)
+            .output()
+            .context("Failed to check git status")?;
+
+        Ok(!output.stdout.is_empty())
+    }
+
+    fn changed_files(&self, path: &Path) -> Result<Vec<String>> {
+        let output = self
+            .run_git_output(&["status", "--porcelain", "-z", "-uall"], Some(path))
+            .context("Failed to check git status")?;
+
+        Ok(parse_status(&output))
+    }
+
+    fn is_repository(&self, path: &Path) -> bool {
+        path.join(".git").exists()
+    }
+
+    fn get_file_from_head(&self, repo_path: &Path, file_path: &str) -> Result<String> {
+        let output = std::process::Command::new("git")
+            .args(["show", &format!("HEAD:{}", file_path)])
+            .current_dir(repo_path)
+            .output()
+            .context("Failed to run git show")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to get file from HEAD: {}", stderr);
+        }
+
+        let content =
+            String::from_utf8(output.stdout).context("File content is not valid UTF-8")?;
+
+        Ok(content)
+    }
+
+    fn head_commit(&self, path: &Path) -> Result<String> {
+        let output = self
+            .run_git_output(&["rev-parse", "HEAD"], Some(path))
+            .context("Failed to get HEAD commit")?;
+
+        Ok(output.trim().to_string())
+    }
+
+    fn current_branch(&self, path: &Path) -> Result<Option<String>> {
+        // symbolic-ref fails quietly (-q) when HEAD is detached
+        let output = std::process::Command::new("git")
+            .args(["symbolic-ref", "-q", "--short", "HEAD"])
+            .current_dir(path)
+            .output()
+            .context("Failed to get the current branch")?;
+
+        let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Ok(Some(branch).filter(|branch| output.status.success() && !branch.is_empty()))
+    }
+
+    fn checkout_commit(&self, path: &Path, branch: &str, commit: &str) -> Result<()> {
+        debug!("Checking out {} in {}", commit, path.display());
+
+        let object = format!("{}^{{commit}}", commit);
+        let has_commit = || {
+            self.run_git(&["cat-file", "-e", &object], Some(path))
+                .is_ok()
+        };
+
+        if !has_commit() {
+            self.fetch_remote_branch(path, branch)?;
+
+            if !has_commit() && self.is_shallow(path) {
+                // Fetch just the commit; abbreviated hashes can't be fetched directly,
+                // so fall back to the branch's full history
+                let fetched = self.run_git(&["fetch", "--depth=1", "origin", commit], Some(path));
+                if fetched.is_err() {
+                    let refspec = format!("+refs/heads/{}:refs/remotes/origin/{}", branch, branch);
+                    self.run_git(&["fetch", "--unshallow", "origin", &refspec], Some(path))
+                        .with_context(|| format!("Commit {} not found on the remote", commit))?;
+                }
+            } else if !has_commit() {
+                self.run_git(&["fetch", "origin", commit], Some(path))
+                    .with_context(|| format!("Commit {} not found on the remote", commit))?;
+            }
+        }
+
+        self.run_git(&["reset", "--hard", commit], Some(path))
+            .with_context(|| format!("Failed to reset to {}", commit))
+    }
+
+    fn list_remote_tags(&self, url: &str, ssh_key: Option<&Path>) -> Result<Vec<String>> {
+        debug!("Listing tags of {}", url);
+
+        let output = self
+            .run_git_with_ssh_key(&["ls-remote", "--tags", "--refs", url], None, ssh_key)
+            .with_context(|| format!("Failed to list tags of {}", url))?;
+
+        Ok(output
+            .lines()
+            .filter_map(|line| line.split('\t').nth(1))
+            .filter_map(parse_tag_ref)
+            .collect())
+    }
+
+    fn remote_branch_commit(
+        &self,
+        url: &str,
+        branch: &str,
+        ssh_key: Option<&Path>,
+    ) -> Result<Option<String>> {
+        debug!("Looking up {} of {}", branch, url);
+
+        let branch_ref = format!("refs/heads/{}", branch);
+        let output = self
+            .run_git_with_ssh_key(&["ls-remote", "--heads", url, &branch_ref], None, ssh_key)
+            .with_context(|| format!("Failed to list branches of {}", url))?;
+
+        Ok(output
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .find(|(_, name)| *name == branch_ref)
+            .map(|(commit, _)| commit.to_string()))
+    }
+
+    fn remote_default_branch(&self, url: &str, ssh_key: Option<&Path>) -> Result<Option<String>> {
+        debug!("Looking up the default branch of {}", url);
+
+        let output = self
+            .run_git_with_ssh_key(&["ls-remote", "--symref", url, "HEAD"], None, ssh_key)
+            .with_context(|| format!("Failed to look up HEAD of {}", url))?;
+
+        Ok(parse_symref_head(&output))
+    }
+
+    fn set_sparse_checkout(&self, path: &Path, patterns: &PathPatterns) -> Result<()> {
+        debug!("Restricting {} to {:?}", path.display(), patterns);
+
+        let sparse_patterns = patterns.sparse_patterns();
+        let mut args = vec!["sparse-checkout", "set", "--no-cone", "--"];
+        args.extend(sparse_patterns.iter().map(String::as_str));
+        self.run_git(&args, Some(path))
+            .context("Failed to set sparse checkout")
+    }
+
+    fn fetch_remote_branch(&self, path: &Path, branch: &str) -> Result<()> {
+        debug!("Fetching origin/{} for {}", branch, path.display());
+
+        let refspec = format!("+refs/heads/{}:refs/remotes/origin/{}", branch, branch);
+        self.run_git(&["fetch", "origin", &refspec], Some(path))
+            .with_context(|| format!("Failed to fetch origin/{}", branch))
+    }
+
+    fn diff_stat(&self, path: &Path, from: &str, to: Option<&str>) -> Result<Vec<FileDiffStat>> {
+        let mut args = vec!["diff", "--numstat", from];
+        args.extend(to);
+        let output = self
+            .run_git_output(&args, Some(path))
+            .with_context(|| format!("Failed to diff against '{}'", from))?;
+
+        Ok(parse_numstat(&output))
+    }
+
+    fn diff_patch(&self, path: &Path, from: &str, to: Option<&str>) -> Result<String> {
+        let mut args = vec!["diff", "--no-color", from];
+        args.extend(to);
+        self.run_git_output(&args, Some(path))
+            .with_context(|| format!("Failed to diff against '{}'", from))
+    }
+
+    fn log(&self, path: &Path, max_count: usize) -> Result<Vec<CommitSummary>> {
+        let max_count = max_count.to_string();
+        let output = self
+            .run_git_output(
+                &[
+                    "log",
+                    "-n",
+                    &max_count,
+                    "--format=%x1e%H%x1f%an%x1f%at%x1f%s",
+                    "--name-only",
+                    "HEAD",
+                ],
+                Some(path),
+            )
+            .context("Failed to read the commit history")?;
+
+        parse_log(&output)
+    }
+
+    fn stash_changes(&self, path: &Path) -> Result<bool> {
+        let stash_tip = || {
+            self.run_git_output(&["rev-parse", "-q", "--verify", "refs/stash"], Some(path))
+                .ok()
+        };
+
+        let before = stash_tip();
+        self.run_git(&["stash", "push", "-m", "fpm autostash"], Some(path))
+            .context("Failed to stash local changes")?;
+        Ok(stash_tip() != before)
+    }
+
+    fn apply_stash(&self, path: &Path) -> Result<Vec<String>> {
+        let Err(error) = self.run_git(&["stash", "pop"], Some(path)) else {
+            return Ok(Vec::new());
+        };
+
+        // git keeps the stash when it conflicts, and marks the conflicted files
+        let conflicts: Vec<String> = self
+            .run_git_output(&["diff", "--name-only", "--diff-filter=U"], Some(path))?
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect();
+        if conflicts.is_empty() {
+            return Err(error.context("Failed to apply the stash"));
+        }
+
+        Ok(conflicts)
+    }
+
+    fn move_repository(&self, from: &Path, to: &Path) -> Result<()> {
+        move_directory(from, to)
+    }
+
+    fn ahead_behind(&self, path: &Path, revision: &str) -> Result<(usize, usize)> {
+        let range = format!("HEAD...{}", revision);
+        let output = self
+            .run_git_output(&["rev-list", "--left-right", "--count", &range], Some(path))
+            .with_context(|| format!("Failed to compare HEAD with '{}'", revision))?;
+
+        parse_ahead_behind(&output)
+    }
+
+    fn sync_mirror(&self, url: &str, path: &Path, ssh_key: Option<&Path>) -> Result<()> {
+        if !path.exists() {
+            info!("Mirroring {} to {}", url, path.display());
+
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).with_context(|| {
+                    format!("Failed to create cache directory: {}", parent.display())
+                })?;
+            }
+
+            let path_str = path.to_string_lossy();
+            self.run_git_with_ssh_key(&["clone", "--mirror", url, &path_str], None, ssh_key)
+                .with_context(|| format!("Failed to mirror repository: {}", url))?;
+
+            return Ok(());
+        }
+
+        debug!("Refreshing mirror {}", path.display());
+
+        self.run_git_with_ssh_key(&["fetch", "--prune", "origin"], Some(path), ssh_key)
+            .with_context(|| format!("Failed to refresh mirror of {}", url))?;
+
+        Ok(())
+    }
+
+    fn maintain_repository(&self, path: &Path, keep_unreachable: bool) -> Result<()> {
+        debug!("Running maintenance in {}", path.display());
+
+        let has_origin = self
+            .run_git(&["remote", "get-url", "origin"], Some(path))
+            .is_ok();
+        if has_origin {
+            self.run_git(&["remote", "prune", "origin"], Some(path))
+                .context("Failed to prune stale remote branches")?;
+        }
+
+        let mut gc_args = vec!["gc", "--auto", "--quiet"];
+        if keep_unreachable {
+            gc_args.splice(0..0, ["-c", "gc.pruneExpire=never"]);
+        }
+        self.run_git(&gc_args, Some(path))
+            .context("Failed to run git gc")?;
+
+        self.run_git(&["commit-graph", "write", "--reachable"], Some(path))
+            .context("Failed to write commit-graph")?;
+
+        Ok(())
+    }
+
+    fn dissociate_repository(&self, path: &Path) -> Result<()> {
+        let alternates = alternates_file(path);
+        if !alternates.exists() {
+            return Ok(());
+        }
+        debug!("Copying borrowed objects into {}", path.display());
+
+        // Without --local, repack also packs the objects found through the alternates
+        self.run_git(&["repack", "-a", "-d", "--quiet"], Some(path))
+            .context("Failed to copy borrowed objects")?;
+        std::fs::remove_file(&alternates)
+            .with_context(|| format!("Failed to remove {}", alternates.display()))
+    }
+}
+
+/// Identity commits are made as
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitAuthor {
+    pub name: String,
+    pub email: String,
+}
+
+impl CommitAuthor {
+    /// Parses an identity written as "Name <email>"
+    pub fn parse(value: &str) -> Result<Self> {
+        let parsed = value
+            .trim()
+            .strip_suffix('>')
+            .and_then(|rest| rest.rsplit_once('<'))
+            .map(|(name, email)| (name.trim(), email.trim()))
+            .filter(|(name, email)| !name.is_empty() && email.contains('@'));
+        let Some((name, email)) = parsed else {
+            anyhow::bail!(
+                "Invalid author '{}'. Expected 'Name <email>', e.g. 'Assets Bot <assets-bot@example.com>'.",
+                value
+            );
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            email: email.to_string(),
+        })
+    }
+}
+
+/// Signs the content of a commit object with gpg or ssh-keygen, as git does for
+/// `commit -S`. Returns the armored signature.
+fn sign_commit(signing: &CommitSigning, content: &str) -> Result<String> {
+    let mut command = match signing.format {
+        SigningFormat::Gpg => {
+            let mut command = std::process::Command::new("gpg");
+            command.args(["--detach-sign", "--armor", "--local-user", &signing.key]);
+            command
+        }
+        SigningFormat::Ssh => {
+            let mut command = std::process::Command::new("ssh-keygen");
+            command.args(["-Y", "sign", "-n", "git", "-f", &signing.key]);
+            command
+        }
+    };
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {} to sign the commit", program))?;
+    child
+        .stdin
+        .take()
+        .context("Failed to pass the commit to the signing program")?
+        .write_all(content.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to sign the commit with {}: {}",
+            signing.key,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    String::from_utf8(output.stdout).context("Commit signature is not valid UTF-8")
+}
+
+/// Returns the git implementation `settings` ask for, with their URL rewrite rules
+/// and retries
+pub fn git_operations_for(settings: &Settings) -> Arc<dyn GitOperations> {
+    let mut git_ops: Arc<dyn GitOperations> = match settings.git_backend.unwrap_or_default() {
+        GitBackend::Cli => Arc::new(GitCliOperations::with_settings(settings.clone())),
+        GitBackend::Git2 => Arc::new(
+            Git2Operations::with_timeout(settings.timeout.map(Duration::from_secs))
+                .with_signing(settings.commit_signing()),
+        ),
+    };
+    if !settings.url.is_empty() {
+        git_ops = Arc::new(RewritingGitOperations::new(git_ops, settings.clone()));
+    }
+
+    let policy = RetryPolicy::from_settings(settings);
+    if policy.retries > 0 {
+        git_ops = Arc::new(RetryingGitOperations::new(git_ops, policy));
+    }
+    git_ops
+}
+
+/// Wraps another implementation, applying the configured URL rewrite rules to
+/// every URL before it is cloned, queried or added as a remote
+pub struct RewritingGitOperations {
+    inner: Arc<dyn GitOperations>,
+    settings: Settings,
+}
+
+impl RewritingGitOperations {
+    pub fn new(inner: Arc<dyn GitOperations>, settings: Settings) -> Self {
+        Self { inner, settings }
+    }
+
+    fn rewrite(&self, url: &str) -> String {
+        let rewritten = self.settings.rewrite_url(url);
+        if rewritten != url {
+            debug!("Rewriting {} to {}", url, rewritten);
+        }
+        rewritten
+    }
+}
+
+impl GitOperations for RewritingGitOperations {
+    fn clone_repository(&self, url: &str, path: &Path, options: &CloneOptions) -> Result<()> {
+        self.inner
+            .clone_repository(&self.rewrite(url), path, options)
+    }
+
+    fn fetch_repository(&self, path: &Path, branch: &str, depth: Option<u32>) -> Result<()> {
+        self.inner.fetch_repository(path, branch, depth)
+    }
+
+    fn init_repository(&self, path: &Path) -> Result<()> {
+        self.inner.init_repository(path)
+    }
+
+    fn add_remote(&self, path: &Path, name: &str, url: &str) -> Result<()> {
+        self.inner.add_remote(path, name, &self.rewrite(url))
+    }
+
+    fn commit_all(&self, path: &Path, message: &str, author: Option<&CommitAuthor>) -> Result<()> {
+        self.inner.commit_all(path, message, author)
+    }
+
+    fn commit_paths(
+        &self,
+        path: &Path,
+        paths: &[String],
+        message: &str,
+        author: Option<&CommitAuthor>,
+    ) -> Result<()> {
+        self.inner.commit_paths(path, paths, message, author)
+    }
+
+    fn push(&self, path: &Path, remote: &str, branch: &str) -> Result<()> {
+        self.inner.push(path, remote, branch)
+    }
+
+    fn create_tag(
+        &self,
+        path: &Path,
+        name: &str,
+        message: &str,
+        author: Option<&CommitAuthor>,
+        force: bool,
+    ) -> Result<()> {
+        self.inner.create_tag(path, name, message, author, force)
+    }
+
+    fn push_tag(&self, path: &Path, remote: &str, name: &str, force: bool) -> Result<()> {
+        self.inner.push_tag(path, remote, name, force)
+    }
+
+    fn create_branch(&self, path: &Path, name: &str) -> Result<()> {
+        self.inner.create_branch(path, name)
+    }
+
+    fn has_local_changes(&self, path: &Path) -> Result<bool> {
+        self.inner.has_local_changes(path)
+    }
+
+    fn changed_files(&self, path: &Path) -> Result<Vec<String>> {
+        self.inner.changed_files(path)
+    }
+
+    fn is_repository(&self, path: &Path) -> bool {
+        self.inner.is_repository(path)
+    }
+
+    fn get_file_from_head(&self, repo_path: &Path, file_path: &str) -> Result<String> {
+        self.inner.get_file_from_head(repo_path, file_path)
+    }
+
+    fn head_commit(&self, path: &Path) -> Result<String> {
+        self.inner.head_commit(path)
+    }
+
+    fn current_branch(&self, path: &Path) -> Result<Option<String>> {
+        self.inner.current_branch(path)
+    }
+
+    fn checkout_commit(&self, path: &Path, branch: &str, commit: &str) -> Result<()> {
+        self.inner.checkout_commit(path, branch, commit)
+    }
+
+    fn list_remote_tags(&self, url: &str, ssh_key: Option<&Path>) -> Result<Vec<String>> {
+        self.inner.list_remote_tags(&self.rewrite(url), ssh_key)
+    }
+
+    fn remote_branch_commit(
+        &self,
+        url: &str,
+        branch: &str,
+        ssh_key: Option<&Path>,
+    ) -> Result<Option<String>> {
+        self.inner
+            .remote_branch_commit(&self.rewrite(url), branch, ssh_key)
+    }
+
+    fn remote_default_branch(&self, url: &str, ssh_key: Option<&Path>) -> Result<Option<String>> {
+        self.inner
+            .remote_default_branch(&self.rewrite(url), ssh_key)
+    }
+
+    fn set_sparse_checkout(&self, path: &Path, patterns: &PathPatterns) -> Result<()> {
+        self.inner.set_sparse_checkout(path, patterns)
+    }
+
+    fn fetch_remote_branch(&self, path: &Path, branch: &str) -> Result<()> {
+        self.inner.fetch_remote_branch(path, branch)
+    }
+
+    fn diff_stat(&self, path: &Path, from: &str, to: Option<&str>) -> Result<Vec<FileDiffStat>> {
+        self.inner.diff_stat(path, from, to)
+    }
+
+    fn diff_patch(&self, path: &Path, from: &str, to: Option<&str>) -> Result<String> {
+        self.inner.diff_patch(path, from, to)
+    }
+
+    fn log(&self, path: &Path, max_count: usize) -> Result<Vec<CommitSummary>> {
+        self.inner.log(path, max_count)
+    }
+
+    fn stash_changes(&self, path: &Path) -> Result<bool> {
+        self.inner.stash_changes(path)
+    }
+
+    fn apply_stash(&self, path: &Path) -> Result<Vec<String>> {
+        self.inner.apply_stash(path)
+    }
+
+    fn move_repository(&self, from: &Path, to: &Path) -> Result<()> {
+        self.inner.move_repository(from, to)
+    }
+
+    fn ahead_behind(&self, path: &Path, revision: &str) -> Result<(usize, usize)> {
+        self.inner.ahead_behind(path, revision)
+    }
+
+    fn sync_mirror(&self, url: &str, path: &Path, ssh_key: Option<&Path>) -> Result<()> {
+        self.inner.sync_mirror(&self.rewrite(url), path, ssh_key)
+    }
+
+    fn maintain_repository(&self, path: &Path, keep_unreachable: bool) -> Result<()> {
+        self.inner.maintain_repository(path, keep_unreachable)
+    }
+
+    fn dissociate_repository(&self, path: &Path) -> Result<()> {
+        self.inner.dissociate_repository(path)
+    }
+}
+
+/// Wraps another implementation, retrying the operations that talk to a remote
+/// when they fail with a transient error
+pub struct RetryingGitOperations {
+    inner: Arc<dyn GitOperations>,
+    policy: RetryPolicy,
+}
+
+impl RetryingGitOperations {
+    pub fn new(inner: Arc<dyn GitOperations>, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+}
+
+impl GitOperations for RetryingGitOperations {
+    fn clone_repository(&self, url: &str, path: &Path, options: &CloneOptions) -> Result<()> {
+        let existed = path.exists();
+        self.policy.run(&format!("Clone of {}", url), || {
+            let result = self.inner.clone_repository(url, path, options);
+            // Don't let a partial clone make the next attempt fail
+            if result.is_err() && !existed && path.exists() {
+                let _ = std::fs::remove_dir_all(path);
+            }
+            result
+        })
+    }
+
+    fn fetch_repository(&self, path: &Path, branch: &str, depth: Option<u32>) -> Result<()> {
+        self.policy
+            .run(&format!("Fetch of {}", path.display()), || {
+                self.inner.fetch_repository(path, branch, depth)
+            })
+    }
+
+    fn init_repository(&self, path: &Path) -> Result<()> {
+        self.inner.init_repository(path)
+    }
+
+    fn add_remote(&self, path: &Path, name: &str, url: &str) -> Result<()> {
+        self.inner.add_remote(path, name, url)
+    }
+
+    fn commit_all(&self, path: &Path, message: &str, author: Option<&CommitAuthor>) -> Result<()> {
+        self.inner.commit_all(path, message, author)
+    }
+
+    fn commit_paths(
+        &self,
+        path: &Path,
+        paths: &[String],
+        message: &str,
+        author: Option<&CommitAuthor>,
+    ) -> Result<()> {
+        self.inner.commit_paths(path, paths, message, author)
+    }
+
+    fn push(&self, path: &Path, remote: &str, branch: &str) -> Result<()> {
+        self.policy.run(&format!("Push of {}", path.display()), || {
+            self.inner.push(path, remote, branch)
+        })
+    }
+
+    fn create_tag(
+        &self,
+        path: &Path,
+        name: &str,
+        message: &str,
+        author: Option<&CommitAuthor>,
+        force: bool,
+    ) -> Result<()> {
+        self.inner.create_tag(path, name, message, author, force)
+    }
+
+    fn push_tag(&self, path: &Path, remote: &str, name: &str, force: bool) -> Result<()> {
+        self.policy.run(&format!("Push of tag {}", name), || {
+            self.inner.push_tag(path, remote, name, force)
+        })
+    }
+
+    fn create_branch(&self, path: &Path, name: &str) -> Result<()> {
+        self.inner.create_branch(path, name)
+    }
+
+    fn has_local_changes(&self, path: &Path) -> Result<bool> {
+        self.inner.has_local_changes(path)
+    }
+
+    fn changed_files(&self, path: &Path) -> Result<Vec<String>> {
+        self.inner.changed_files(path)
+    }
+
+    fn is_repository(&self, path: &Path) -> bool {
+        self.inner.is_repository(path)
+    }
+
+    fn get_file_from_head(&self, repo_path: &Path, file_path: &str) -> Result<String> {
+        self.inner.get_file_from_head(repo_path, file_path)
+    }
+
+    fn head_commit(&self, path: &Path) -> Result<String> {
+        self.inner.head_commit(path)
+    }
+
+    fn current_branch(&self, path: &Path) -> Result<Option<String>> {
+        self.inner.current_branch(path)
+    }
+
+    fn checkout_commit(&self, path: &Path, branch: &str, commit: &str) -> Result<()> {
+        self.policy
+            .run(&format!("Checkout of {}", path.display()), || {
+                self.inner.checkout_commit(path, branch, commit)
+            })
+    }
+
+    fn list_remote_tags(&self, url: &str, ssh_key: Option<&Path>) -> Result<Vec<String>> {
+        self.policy.run(&format!("Tag lookup of {}", url), || {
+            self.inner.list_remote_tags(url, ssh_key)
+        })
+    }
+
+    fn remote_branch_commit(
+        &self,
+        url: &str,
+        branch: &str,
+        ssh_key: Option<&Path>,
+    ) -> Result<Option<String>> {
+        self.policy.run(&format!("Branch lookup of {}", url), || {
+            self.inner.remote_branch_commit(url, branch, ssh_key)
+        })
+    }
+
+    fn remote_default_branch(&self, url: &str, ssh_key: Option<&Path>) -> Result<Option<String>> {
+        self.policy
+            .run(&format!("Default branch lookup of {}", url), || {
+                self.inner.remote_default_branch(url, ssh_key)
+            })
+    }
+
+    fn set_sparse_checkout(&self, path: &Path, patterns: &PathPatterns) -> Result<()> {
+        self.inner.set_sparse_checkout(path, patterns)
+    }
+
+    fn fetch_remote_branch(&self, path: &Path, branch: &str) -> Result<()> {
+        self.policy
+            .run(&format!("Fetch of {}", path.display()), || {
+                self.inner.fetch_remote_branch(path, branch)
+            })
+    }
+
+    fn diff_stat(&self, path: &Path, from: &str, to: Option<&str>) -> Result<Vec<FileDiffStat>> {
+        self.inner.diff_stat(path, from, to)
+    }
+
+    fn diff_patch(&self, path: &Path, from: &str, to: Option<&str>) -> Result<String> {
+        self.inner.diff_patch(path, from, to)
+    }
+
+    fn log(&self, path: &Path, max_count: usize) -> Result<Vec<CommitSummary>> {
+        self.inner.log(path, max_count)
+    }
+
+    fn stash_changes(&self, path: &Path) -> Result<bool> {
+        self.inner.stash_changes(path)
+    }
+
+    fn apply_stash(&self, path: &Path) -> Result<Vec<String>> {
+        self.inner.apply_stash(path)
+    }
+
+    fn move_repository(&self, from: &Path, to: &Path) -> Result<()> {
+        self.inner.move_repository(from, to)
+    }
+
+    fn ahead_behind(&self, path: &Path, revision: &str) -> Result<(usize, usize)> {
+        self.inner.ahead_behind(path, revision)
+    }
+
+    fn sync_mirror(&self, url: &str, path: &Path, ssh_key: Option<&Path>) -> Result<()> {
+        let existed = path.exists();
+        self.policy.run(&format!("Mirror of {}", url), || {
+            let result = self.inner.sync_mirror(url, path, ssh_key);
+            if result.is_err() && !existed && path.exists() {
+                let _ = std::fs::remove_dir_all(path);
+            }
+            result
+        })
+    }
+
+    fn maintain_repository(&self, path: &Path, keep_unreachable: bool) -> Result<()> {
+        self.inner.maintain_repository(path, keep_unreachable)
     }
 
-    fn is_repository(&self, path: &Path) -> bool {
-        path.join(".git").exists()
+    fn dissociate_repository(&self, path: &Path) -> Result<()> {
+        self.inner.dissociate_repository(path)
     }
+}
 
-    fn get_file_from_head(&self, repo_path: &Path, file_path: &str) -> Result<String> {
-        let output = std::process::Command::new("git")
-            .args(["show", &format!("HEAD:{}", file_path)])
-            .current_dir(repo_path)
-            .output()
-            .context("Failed to run git show")?;
+/// File listing the object directories a clone borrows objects from
+pub fn alternates_file(path: &Path) -> PathBuf {
+    path.join(".git")
+        .join("objects")
+        .join("info")
+        .join("alternates")
+}
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            anyhow::bail!("Failed to get file from HEAD: {}", stderr);
+/// Copies the object files of the repositories a clone borrows from into its own
+/// object directory, then removes its alternates file
+fn copy_alternate_objects(path: &Path) -> Result<()> {
+    let alternates = alternates_file(path);
+    let Ok(content) = std::fs::read_to_string(&alternates) else {
+        return Ok(());
+    };
+    let objects = path.join(".git").join("objects");
+
+    let sources = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    for source in sources {
+        // Relative entries are relative to the object directory
+        let source = objects.join(source);
+        for entry in walkdir::WalkDir::new(&source) {
+            let entry =
+                entry.with_context(|| format!("Failed to read objects of {}", source.display()))?;
+            let relative = entry.path().strip_prefix(&source)?;
+            if !entry.file_type().is_file() || relative.starts_with("info") {
+                continue;
+            }
+            let target = objects.join(relative);
+            if target.exists() {
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::copy(entry.path(), &target)
+                .with_context(|| format!("Failed to copy {}", entry.path().display()))?;
         }
+    }
 
-        let content =
-            String::from_utf8(output.stdout).context("File content is not valid UTF-8")?;
+    std::fs::remove_file(&alternates)
+        .with_context(|| format!("Failed to remove {}", alternates.display()))
+}
 
-        Ok(content)
-    }
+/// Converts a clone depth to the signed value libgit2 expects
+/// Runs `cmd` and collects its output. With a `timeout`, the command is killed
+/// once it runs longer.
+fn output_with_timeout(
+    mut cmd: std::process::Command,
+    timeout: Option<Duration>,
+) -> Result<std::process::Output> {
+    let Some(timeout) = timeout else {
+        return cmd.output().context("Failed to execute git command");
+    };
+
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to execute git command")?;
+
+    // Read the pipes while waiting, so a chatty command can't block on a full pipe
+    let read = |pipe: Option<Box<dyn Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut buffer = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut buffer);
+            }
+            buffer
+        })
+    };
+    let stdout = read(child.stdout.take().map(|pipe| Box::new(pipe) as _));
+    let stderr = read(child.stderr.take().map(|pipe| Box::new(pipe) as _));
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait().context("Failed to wait for git")? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!("Git command timed out after {}s", timeout.as_secs());
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+
+    Ok(std::process::Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
 }
 
-/// Applies include filter to a bundle directory
-/// If include is specified, copies only the listed paths to a temporary location,
-/// then replaces the bundle contents with the filtered version
-fn apply_include_filter(bundle_path: &Path, include_patterns: &[String]) -> Result<()> {
-    use std::fs;
-    use std::time::SystemTime;
+/// Checks if a git command talks to a remote, and so may need an SSH passphrase
+fn contacts_remote(args: &[&str]) -> bool {
+    matches!(
+        args,
+        ["clone" | "fetch" | "push" | "pull" | "ls-remote", ..] | ["remote", "prune", ..]
+    )
+}
 
-    debug!(
-        "Applying include filter to {}: {:?}",
-        bundle_path.display(),
-        include_patterns
-    );
+fn depth_arg(depth: u32) -> i32 {
+    i32::try_from(depth).unwrap_or(i32::MAX)
+}
 
-    // Create a unique temporary directory in the system temp to avoid conflicts
-    let timestamp = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap_or_else(|_| std::time::Duration::from_secs(0))
-        .as_millis();
-    let bundle_name = bundle_path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("bundle");
-    let temp_name = format!("fpm_filter_{}_{}", bundle_name, timestamp);
-    let temp_path = std::env::temp_dir().join(temp_name);
-
-    fs::create_dir_all(&temp_path).context("Failed to create temporary directory for filtering")?;
-
-    // Copy only the included paths
-    for pattern in include_patterns {
-        let source = bundle_path.join(pattern);
-        let dest = temp_path.join(pattern);
-
-        // Create parent directories if needed
-        if let Some(parent) = dest.parent() {
-            fs::create_dir_all(parent)
-                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
-        }
-
-        // Copy file or directory - let the operation handle existence checks
-        // This avoids TOCTOU (time-of-check-time-of-use) race conditions
-        if let Ok(metadata) = fs::metadata(&source) {
-            if metadata.is_file() {
-                fs::copy(&source, &dest)
-                    .with_context(|| format!("Failed to copy file: {}", source.display()))?;
-            } else if metadata.is_dir() {
-                copy_dir_recursive(&source, &dest)
-                    .with_context(|| format!("Failed to copy directory: {}", source.display()))?;
-            }
-        } else {
-            // Log warning but continue - the path might not exist
-            debug!("Include pattern '{}' not found in bundle", pattern);
+/// Parses `git status --porcelain -z` output into the changed paths. Renames and
+/// copies are followed by their original path, which is skipped.
+fn parse_status(output: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut entries = output.split('\0').filter(|entry| entry.len() > 3);
+    while let Some(entry) = entries.next() {
+        let (status, path) = entry.split_at(3);
+        if status.contains(['R', 'C']) {
+            entries.next();
         }
+        paths.push(path.to_string());
     }
+    paths
+}
 
-    // Remove all contents from the bundle directory except .git
-    for entry in fs::read_dir(bundle_path)? {
-        let entry = entry?;
-        let path = entry.path();
-        let name = entry.file_name();
+/// Diff from the tree of revision `from` to the tree of `to`, or to the working tree
+/// (with the index) if `to` is None
+fn diff_revisions<'r>(
+    repo: &'r Repository,
+    from: &str,
+    to: Option<&str>,
+) -> Result<git2::Diff<'r>> {
+    let tree = |revision: &str| {
+        repo.revparse_single(revision)
+            .and_then(|obj| obj.peel_to_tree())
+            .with_context(|| format!("Failed to resolve revision '{}'", revision))
+    };
+
+    let from = tree(from)?;
+    Ok(match to {
+        Some(to) => repo.diff_tree_to_tree(Some(&from), Some(&tree(to)?), None)?,
+        None => repo.diff_tree_to_workdir_with_index(Some(&from), None)?,
+    })
+}
 
-        // Skip .git directory
-        if name == ".git" {
-            continue;
-        }
+/// Parses `git diff --numstat` output. Binary files are reported with "-" counts.
+fn parse_numstat(output: &str) -> Vec<FileDiffStat> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            let insertions = parts.next()?;
+            let deletions = parts.next()?;
+            let path = parts.next()?;
+
+            Some(FileDiffStat {
+                path: path.to_string(),
+                insertions: insertions.parse().ok(),
+                deletions: deletions.parse().ok(),
+            })
+        })
+        .collect()
+}
 
-        if path.is_dir() {
-            fs::remove_dir_all(&path)?;
-        } else {
-            fs::remove_file(&path)?;
-        }
-    }
+/// Parses `git log --format=%x1e%H%x1f%an%x1f%at%x1f%s --name-only` output: one
+/// record per commit, a header line followed by the changed files
+fn parse_log(output: &str) -> Result<Vec<CommitSummary>> {
+    output
+        .split('\x1e')
+        .filter(|record| !record.trim().is_empty())
+        .map(|record| {
+            let mut lines = record.lines();
+            let header = lines.next().unwrap_or_default();
+            let fields: Vec<&str> = header.split('\x1f').collect();
+            let [id, author, time, summary] = fields[..] else {
+                anyhow::bail!("Unexpected git log output: {}", header);
+            };
+
+            Ok(CommitSummary {
+                id: id.to_string(),
+                author: author.to_string(),
+                time: time
+                    .parse()
+                    .with_context(|| format!("Invalid commit time: {}", time))?,
+                summary: summary.to_string(),
+                files: lines
+                    .filter(|line| !line.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            })
+        })
+        .collect()
+}
 
-    // Copy filtered contents back to the bundle directory
-    for entry in fs::read_dir(&temp_path)? {
-        let entry = entry?;
-        let source = entry.path();
-        let dest = bundle_path.join(entry.file_name());
+/// Parses `git rev-list --left-right --count` output: commits ahead, then behind
+fn parse_ahead_behind(output: &str) -> Result<(usize, usize)> {
+    let counts: Vec<usize> = output
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .with_context(|| format!("Unexpected rev-list output: {}", output.trim()))?;
+
+    match counts[..] {
+        [ahead, behind] => Ok((ahead, behind)),
+        _ => anyhow::bail!("Unexpected rev-list output: {}", output.trim()),
+    }
+}
 
-        if source.is_file() {
-            fs::copy(&source, &dest)?;
-        } else if source.is_dir() {
-            copy_dir_recursive(&source, &dest)?;
-        }
+/// Extracts the tag name from a full ref ("refs/tags/v1.0.0" -> "v1.0.0"),
+/// ignoring peeled entries ("refs/tags/v1.0.0^{}")
+fn parse_tag_ref(name: &str) -> Option<String> {
+    let tag = name.strip_prefix("refs/tags/")?;
+    if tag.ends_with("^{}") {
+        return None;
     }
+    Some(tag.to_string())
+}
 
-    // Clean up temp directory
-    fs::remove_dir_all(&temp_path)?;
+/// Whether `url` is a repository on disk without any refs, i.e. nothing pushed yet
+fn is_empty_local_repository(url: &str) -> bool {
+    let path = url.strip_prefix("file://").unwrap_or(url);
+    let Ok(repo) = Repository::open(path) else {
+        return false;
+    };
+    repo.head().is_err()
+        && repo
+            .references()
+            .map(|mut refs| refs.next().is_none())
+            .unwrap_or(false)
+}
 
-    Ok(())
+/// Signature of `author`, or else of the repository's configured identity
+fn signature(repo: &Repository, author: Option<&CommitAuthor>) -> Result<git2::Signature<'static>> {
+    let signature = match author {
+        Some(author) => git2::Signature::now(&author.name, &author.email)?,
+        None => repo
+            .signature()
+            .or_else(|_| git2::Signature::now("fpm", "fpm@local"))?,
+    };
+    Ok(signature)
+}
+
+/// Extracts the default branch from `git ls-remote --symref <url> HEAD`, whose
+/// first line reads "ref: refs/heads/main\tHEAD"
+fn parse_symref_head(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (target, name) = line.strip_prefix("ref: ")?.split_once('\t')?;
+        let branch = target.strip_prefix("refs/heads/")?;
+        (name == "HEAD").then(|| branch.to_string())
+    })
+}
+
+/// Renames a repository directory; git keeps no absolute paths to the working tree,
+/// so both backends move clones the same way
+fn move_directory(from: &Path, to: &Path) -> Result<()> {
+    std::fs::rename(from, to)
+        .with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))
 }
 
-/// Recursively copies a directory
-fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
+/// Applies the include and exclude patterns to a bundle directory by deleting every
+/// file they don't select, then the directories left empty. The .git directory is kept.
+/// Used by Git2Operations, which can't do sparse checkouts.
+fn apply_path_filter(bundle_path: &Path, patterns: &PathPatterns) -> Result<()> {
     use std::fs;
 
-    fs::create_dir_all(dst)
-        .with_context(|| format!("Failed to create directory: {}", dst.display()))?;
+    debug!(
+        "Applying path filter to {}: {:?}",
+        bundle_path.display(),
+        patterns
+    );
 
-    for entry in fs::read_dir(src)? {
-        let entry = entry?;
-        let src_path = entry.path();
-        let dst_path = dst.join(entry.file_name());
+    let filter = PathFilter::new(patterns)?;
+
+    // Collect first: deleting entries while a directory is being read can skip others
+    let entries = walkdir::WalkDir::new(bundle_path)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .collect::<Result<Vec<_>, _>>()?;
+    // Reversed, every directory comes after its contents
+    for entry in entries.into_iter().rev() {
+        let relative = entry.path().strip_prefix(bundle_path)?;
+        if filter.is_included(relative) {
+            continue;
+        }
 
-        if src_path.is_file() {
-            fs::copy(&src_path, &dst_path)
-                .with_context(|| format!("Failed to copy file: {}", src_path.display()))?;
-        } else if src_path.is_dir() {
-            copy_dir_recursive(&src_path, &dst_path)?;
+        if entry.file_type().is_dir() {
+            // Keep directories still holding included files
+            let is_empty = fs::read_dir(entry.path())?.next().is_none();
+            if is_empty {
+                fs::remove_dir(entry.path()).with_context(|| {
+                    format!("Failed to remove directory: {}", entry.path().display())
+                })?;
+            }
+        } else {
+            fs::remove_file(entry.path())
+                .with_context(|| format!("Failed to remove file: {}", entry.path().display()))?;
         }
     }
 
     Ok(())
 }
 
-/// Clones or updates a bundle from its git source
+/// Clones or updates a bundle from its git source, checking out `git_ref` (a branch or tag).
+/// When `reference` is given, a new clone borrows objects from that repository.
+/// When `commit` is given (e.g. from the lockfile), exactly that commit is checked out
+/// instead of the ref tip; an existing clone already at that commit is left untouched.
+/// Updating an existing clone fails with [`LocalChanges`] when it would discard
+/// uncommitted changes.
 pub fn fetch_bundle(
     git_ops: &dyn GitOperations,
     dependency: &BundleDependency,
     target_path: &Path,
+    git_ref: &str,
+    reference: Option<&Path>,
+    commit: Option<&str>,
 ) -> Result<()> {
-    let branch = dependency.branch();
+    let branch = git_ref;
+    // A rev in the manifest pins the bundle whatever else was requested
+    let commit = dependency.rev.as_deref().or(commit);
     let is_new_clone = !git_ops.is_repository(target_path);
+    // A bundle installed from a subdirectory only checks out that subdirectory
+    let rebased = dependency
+        .subdirectory()
+        .map(|subdirectory| subdirectory_patterns(subdirectory, dependency));
+    let patterns = match &rebased {
+        Some((include, exclude)) => Some(PathPatterns { include, exclude }),
+        None => PathPatterns::of(dependency),
+    };
 
     if is_new_clone {
         // Clone the repository
-        let ssh_key = dependency.ssh_key.as_deref();
-        git_ops.clone_repository(&dependency.git, target_path, branch, ssh_key)?;
-
-        // Apply include filter if specified - only on initial clone
-        // This avoids issues with changing include lists on existing repos
-        if let Some(include) = &dependency.include {
-            if !include.is_empty() {
-                apply_include_filter(target_path, include)?;
-            }
+        let options = CloneOptions {
+            branch,
+            ssh_key: dependency.ssh_key.as_deref(),
+            reference,
+            depth: dependency.depth,
+            filter: dependency.filter.as_deref(),
+            sparse: patterns,
+        };
+        git_ops.clone_repository(&dependency.git, target_path, &options)?;
+
+        if let Some(commit) = commit {
+            git_ops.checkout_commit(target_path, branch, commit)?;
+        }
+    } else if let Some(commit) = commit {
+        // Pinned: only touch the clone when it is not at the pinned commit yet
+        let at_commit = git_ops
+            .head_commit(target_path)
+            .map(|head| head.starts_with(commit))
+            .unwrap_or(false);
+        if !at_commit {
+            ensure_no_discarded_changes(git_ops, target_path, patterns.as_ref())?;
+            git_ops.checkout_commit(target_path, branch, commit)?;
         }
     } else {
         // Repository exists, fetch updates
-        git_ops.fetch_repository(target_path, branch)?;
-        // Note: We don't re-apply the filter on fetch to avoid unexpected file deletions
-        // if the include list changes. Users can delete and re-install to get a fresh filtered copy.
+        ensure_no_discarded_changes(git_ops, target_path, patterns.as_ref())?;
+        git_ops.fetch_repository(target_path, branch, dependency.depth)?;
+    }
+
+    // Apply the include and exclude lists last: they may have changed since the clone,
+    // and a sparse checkout keeps unselected paths out of every later checkout and fetch
+    if let Some(patterns) = &patterns {
+        git_ops.set_sparse_checkout(target_path, patterns)?;
     }
 
     Ok(())
 }
 
+/// Files of a clone with uncommitted changes that resetting it to another commit
+/// would discard: modified, deleted and staged files the bundle installs (selected
+/// by `patterns`). Untracked files survive a reset, and .gitignore is left out
+/// because install maintains it itself.
+pub fn discarded_changes(
+    git_ops: &dyn GitOperations,
+    path: &Path,
+    patterns: Option<&PathPatterns>,
+) -> Result<Vec<String>> {
+    let filter = patterns.map(PathFilter::new).transpose()?;
+
+    Ok(git_ops
+        .diff_stat(path, "HEAD", None)?
+        .into_iter()
+        .map(|stat| stat.path)
+        .filter(|file| file != ".gitignore")
+        .filter(|file| {
+            filter
+                .as_ref()
+                .is_none_or(|filter| filter.is_included(Path::new(file)))
+        })
+        .collect())
+}
+
+/// Error of an update that would discard uncommitted changes of an installed
+/// bundle. Install skips such bundles unless forced.
+#[derive(Debug, thiserror::Error)]
+#[error("{}", describe_local_changes(path, files))]
+pub struct LocalChanges {
+    /// Bundle directory
+    pub path: PathBuf,
+    /// Changed files, if known
+    pub files: Vec<String>,
+}
+
+fn describe_local_changes(path: &Path, files: &[String]) -> String {
+    let files: String = files.iter().map(|file| format!("\n  {}", file)).collect();
+    format!(
+        "{} has local changes that updating it would discard{}{}\n\
+        Push them with 'fpm push' or revert them, or pass --force to discard them.",
+        path.display(),
+        if files.is_empty() { "." } else { ":" },
+        files
+    )
+}
+
+/// Fails with [`LocalChanges`] if updating the clone at `path` would discard
+/// uncommitted changes, so a designer's edits are never silently thrown away
+pub fn ensure_no_discarded_changes(
+    git_ops: &dyn GitOperations,
+    path: &Path,
+    patterns: Option<&PathPatterns>,
+) -> Result<()> {
+    let files = discarded_changes(git_ops, path, patterns)?;
+    if files.is_empty() {
+        return Ok(());
+    }
+
+    Err(LocalChanges {
+        path: path.to_path_buf(),
+        files,
+    }
+    .into())
+}
+
 /// Initializes a bundle directory for publishing
 pub fn init_bundle_for_publish(
     git_ops: &dyn GitOperations,
@@ -553,6 +2536,7 @@ pub fn init_bundle_for_publish(
 #[cfg(test)]
 mod unit_tests {
     use super::*;
+    use std::fs;
     use std::sync::RwLock;
 
     struct MockGitOperations {
@@ -570,13 +2554,7 @@ mod unit_tests {
     }
 
     impl GitOperations for MockGitOperations {
-        fn clone_repository(
-            &self,
-            url: &str,
-            path: &Path,
-            _branch: &str,
-            _ssh_key: Option<&Path>,
-        ) -> Result<()> {
+        fn clone_repository(&self, url: &str, path: &Path, _options: &CloneOptions) -> Result<()> {
             self.cloned_repos
                 .write()
                 .unwrap()
@@ -584,7 +2562,7 @@ mod unit_tests {
             Ok(())
         }
 
-        fn fetch_repository(&self, _path: &Path, _branch: &str) -> Result<()> {
+        fn fetch_repository(&self, _path: &Path, _branch: &str, _depth: Option<u32>) -> Result<()> {
             Ok(())
         }
 
@@ -596,7 +2574,22 @@ mod unit_tests {
             Ok(())
         }
 
-        fn commit_all(&self, _path: &Path, _message: &str) -> Result<()> {
+        fn commit_all(
+            &self,
+            _path: &Path,
+            _message: &str,
+            _author: Option<&CommitAuthor>,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        fn commit_paths(
+            &self,
+            _path: &Path,
+            _paths: &[String],
+            _message: &str,
+            _author: Option<&CommitAuthor>,
+        ) -> Result<()> {
             Ok(())
         }
 
@@ -604,10 +2597,33 @@ mod unit_tests {
             Ok(())
         }
 
+        fn create_tag(
+            &self,
+            _path: &Path,
+            _name: &str,
+            _message: &str,
+            _author: Option<&CommitAuthor>,
+            _force: bool,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        fn push_tag(&self, _path: &Path, _remote: &str, _name: &str, _force: bool) -> Result<()> {
+            Ok(())
+        }
+
+        fn create_branch(&self, _path: &Path, _name: &str) -> Result<()> {
+            Ok(())
+        }
+
         fn has_local_changes(&self, _path: &Path) -> Result<bool> {
             Ok(false)
         }
 
+        fn changed_files(&self, _path: &Path) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+
         fn is_repository(&self, _path: &Path) -> bool {
             self.is_repo
         }
@@ -616,6 +2632,92 @@ mod unit_tests {
             // Mock: return empty string (will cause version comparison to fail gracefully)
             anyhow::bail!("Mock: no HEAD commit")
         }
+
+        fn head_commit(&self, _path: &Path) -> Result<String> {
+            anyhow::bail!("Mock: no HEAD commit")
+        }
+
+        fn current_branch(&self, _path: &Path) -> Result<Option<String>> {
+            Ok(None)
+        }
+
+        fn checkout_commit(&self, _path: &Path, _branch: &str, _commit: &str) -> Result<()> {
+            Ok(())
+        }
+
+        fn list_remote_tags(&self, _url: &str, _ssh_key: Option<&Path>) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        fn remote_branch_commit(
+            &self,
+            _url: &str,
+            _branch: &str,
+            _ssh_key: Option<&Path>,
+        ) -> Result<Option<String>> {
+            Ok(None)
+        }
+
+        fn remote_default_branch(
+            &self,
+            _url: &str,
+            _ssh_key: Option<&Path>,
+        ) -> Result<Option<String>> {
+            Ok(None)
+        }
+
+        fn set_sparse_checkout(&self, _path: &Path, _patterns: &PathPatterns) -> Result<()> {
+            Ok(())
+        }
+
+        fn fetch_remote_branch(&self, _path: &Path, _branch: &str) -> Result<()> {
+            Ok(())
+        }
+
+        fn diff_stat(
+            &self,
+            _path: &Path,
+            _from: &str,
+            _to: Option<&str>,
+        ) -> Result<Vec<FileDiffStat>> {
+            Ok(Vec::new())
+        }
+
+        fn diff_patch(&self, _path: &Path, _from: &str, _to: Option<&str>) -> Result<String> {
+            Ok(String::new())
+        }
+
+        fn log(&self, _path: &Path, _max_count: usize) -> Result<Vec<CommitSummary>> {
+            Ok(Vec::new())
+        }
+
+        fn stash_changes(&self, _path: &Path) -> Result<bool> {
+            Ok(false)
+        }
+
+        fn apply_stash(&self, _path: &Path) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        fn move_repository(&self, _from: &Path, _to: &Path) -> Result<()> {
+            Ok(())
+        }
+
+        fn ahead_behind(&self, _path: &Path, _revision: &str) -> Result<(usize, usize)> {
+            Ok((0, 0))
+        }
+
+        fn sync_mirror(&self, _url: &str, _path: &Path, _ssh_key: Option<&Path>) -> Result<()> {
+            Ok(())
+        }
+
+        fn maintain_repository(&self, _path: &Path, _keep_unreachable: bool) -> Result<()> {
+            Ok(())
+        }
+
+        fn dissociate_repository(&self, _path: &Path) -> Result<()> {
+            Ok(())
+        }
     }
 
     #[test]
@@ -628,10 +2730,11 @@ mod unit_tests {
             branch: None,
             ssh_key: None,
             include: None,
+            ..Default::default()
         };
 
         let target = Path::new("/tmp/test-bundle");
-        fetch_bundle(&mock, &dep, target).unwrap();
+        fetch_bundle(&mock, &dep, target, "main", None, None).unwrap();
 
         let cloned = mock.cloned_repos.read().unwrap();
         assert_eq!(cloned.len(), 1);
@@ -648,10 +2751,11 @@ mod unit_tests {
             branch: None,
             ssh_key: None,
             include: None,
+            ..Default::default()
         };
 
         let target = Path::new("/tmp/test-bundle");
-        fetch_bundle(&mock, &dep, target).unwrap();
+        fetch_bundle(&mock, &dep, target, "main", None, None).unwrap();
 
         // Should not clone since repo exists
         let cloned = mock.cloned_repos.read().unwrap();
@@ -659,7 +2763,39 @@ mod unit_tests {
     }
 
     #[test]
-    fn test_apply_include_filter() {
+    fn test_rewriting_git_operations_clones_mirror() {
+        let mock = Arc::new(MockGitOperations::new(false));
+        let settings: Settings = toml::from_str(
+            r#"
+            [url."https://mirror.example.com/github/"]
+            instead_of = ["https://github.com/"]
+            "#,
+        )
+        .unwrap();
+        let git_ops = RewritingGitOperations::new(mock.clone(), settings);
+
+        let options = CloneOptions {
+            branch: "main",
+            ..Default::default()
+        };
+        let target = Path::new("/tmp/test-bundle");
+        git_ops
+            .clone_repository("https://github.com/test/repo.git", target, &options)
+            .unwrap();
+        git_ops
+            .clone_repository("https://gitlab.com/test/repo.git", target, &options)
+            .unwrap();
+
+        let cloned = mock.cloned_repos.read().unwrap();
+        assert_eq!(
+            cloned[0].0,
+            "https://mirror.example.com/github/test/repo.git"
+        );
+        assert_eq!(cloned[1].0, "https://gitlab.com/test/repo.git");
+    }
+
+    #[test]
+    fn test_apply_path_filter() {
         use std::fs;
         use tempfile::TempDir;
 
@@ -685,16 +2821,26 @@ mod unit_tests {
         fs::write(folder1.join("file1.txt"), "content1").unwrap();
         fs::write(folder2.join("file2.txt"), "content2").unwrap();
         fs::write(folder3.join("file3.txt"), "content3").unwrap();
+        fs::write(folder3.join("file3.bak"), "backup").unwrap();
         fs::write(bundle_path.join("root_file.txt"), "root content").unwrap();
 
-        // Apply filter to keep only folder2 and folder3
+        // Apply filter to keep only folder2 and folder3, without backups
         let include = vec!["folder2".to_string(), "folder3".to_string()];
-        super::apply_include_filter(&bundle_path, &include).unwrap();
+        let exclude = vec!["*.bak".to_string()];
+        let patterns = PathPatterns {
+            include: &include,
+            exclude: &exclude,
+        };
+        super::apply_path_filter(&bundle_path, &patterns).unwrap();
 
         // Check results
         assert!(!folder1.exists(), "folder1 should be removed");
         assert!(folder2.exists(), "folder2 should be kept");
         assert!(folder3.exists(), "folder3 should be kept");
+        assert!(
+            !folder3.join("file3.bak").exists(),
+            "excluded file3.bak should be removed"
+        );
         assert!(
             !bundle_path.join("root_file.txt").exists(),
             "root_file.txt should be removed"
@@ -717,34 +2863,373 @@ mod unit_tests {
     }
 
     #[test]
-    fn test_copy_dir_recursive() {
-        use std::fs;
-        use tempfile::TempDir;
+    fn test_parse_tag_ref() {
+        assert_eq!(
+            parse_tag_ref("refs/tags/v1.2.3"),
+            Some("v1.2.3".to_string())
+        );
+        assert_eq!(parse_tag_ref("refs/tags/v1.2.3^{}"), None);
+        assert_eq!(parse_tag_ref("refs/heads/main"), None);
+    }
 
-        let temp_dir = TempDir::new().unwrap();
-        let src = temp_dir.path().join("src");
-        let dst = temp_dir.path().join("dst");
-
-        // Create source structure
-        fs::create_dir_all(src.join("subdir")).unwrap();
-        fs::write(src.join("file1.txt"), "content1").unwrap();
-        fs::write(src.join("subdir").join("file2.txt"), "content2").unwrap();
-
-        // Copy
-        super::copy_dir_recursive(&src, &dst).unwrap();
-
-        // Verify
-        assert!(dst.exists());
-        assert!(dst.join("file1.txt").exists());
-        assert!(dst.join("subdir").exists());
-        assert!(dst.join("subdir").join("file2.txt").exists());
+    #[test]
+    fn test_parse_symref_head() {
+        let output =
+            "ref: refs/heads/trunk\tHEAD\n3f2a9c1d5e7b8a0c4d6e2f1a3b5c7d9e0f1a2b3c\tHEAD\n";
+        assert_eq!(parse_symref_head(output).as_deref(), Some("trunk"));
         assert_eq!(
-            fs::read_to_string(dst.join("file1.txt")).unwrap(),
-            "content1"
+            parse_symref_head("3f2a9c1d5e7b8a0c4d6e2f1a3b5c7d9e0f1a2b3c\tHEAD\n"),
+            None
         );
+        assert_eq!(parse_symref_head(""), None);
+    }
+
+    #[test]
+    fn test_parse_numstat() {
+        let output = "3\t1\tstyles/base.css\n-\t-\ticons/logo.png\n0\t12\tREADME.md\n";
+        let stats = parse_numstat(output);
+
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats[0].path, "styles/base.css");
+        assert_eq!(stats[0].insertions, Some(3));
+        assert_eq!(stats[0].deletions, Some(1));
+        // Binary files have no line counts
+        assert_eq!(stats[1].path, "icons/logo.png");
+        assert_eq!(stats[1].insertions, None);
+        assert_eq!(stats[2].deletions, Some(12));
+    }
+
+    #[test]
+    fn test_parse_commit_author() {
         assert_eq!(
-            fs::read_to_string(dst.join("subdir").join("file2.txt")).unwrap(),
-            "content2"
+            CommitAuthor::parse(" Assets Bot <assets-bot@example.com> ").unwrap(),
+            CommitAuthor {
+                name: "Assets Bot".to_string(),
+                email: "assets-bot@example.com".to_string(),
+            }
+        );
+        assert!(CommitAuthor::parse("assets-bot@example.com").is_err());
+        assert!(CommitAuthor::parse("<assets-bot@example.com>").is_err());
+        assert!(CommitAuthor::parse("Assets Bot <bot>").is_err());
+    }
+
+    #[test]
+    fn test_parse_status() {
+        let output = " M styles/base.css\0R  icons/new.svg\0icons/old.svg\0?? fonts/a b.ttf\0";
+        assert_eq!(
+            parse_status(output),
+            vec!["styles/base.css", "icons/new.svg", "fonts/a b.ttf"]
+        );
+        assert!(parse_status("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_ahead_behind() {
+        assert_eq!(parse_ahead_behind("2\t5\n").unwrap(), (2, 5));
+        assert_eq!(parse_ahead_behind("0\t0").unwrap(), (0, 0));
+        assert!(parse_ahead_behind("fatal: bad revision\n").is_err());
+        assert!(parse_ahead_behind("3\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_log() {
+        let output = "\x1eabc123\x1fJane Doe\x1f1760000000\x1fUpdate logo\n\n\
+            icons/logo.svg\nREADME.md\n\
+            \x1edef456\x1fJohn Roe\x1f1750000000\x1fInitial commit\n";
+
+        let commits = parse_log(output).unwrap();
+        assert_eq!(
+            commits,
+            vec![
+                CommitSummary {
+                    id: "abc123".to_string(),
+                    author: "Jane Doe".to_string(),
+                    time: 1760000000,
+                    summary: "Update logo".to_string(),
+                    files: vec!["icons/logo.svg".to_string(), "README.md".to_string()],
+                },
+                CommitSummary {
+                    id: "def456".to_string(),
+                    author: "John Roe".to_string(),
+                    time: 1750000000,
+                    summary: "Initial commit".to_string(),
+                    files: Vec::new(),
+                },
+            ]
+        );
+        assert!(parse_log("\x1eabc123\n").is_err());
+    }
+
+    #[test]
+    fn test_commit_all_signs_as_author() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let key = temp_dir.path().join("id_ed25519");
+        let generated = std::process::Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-f"])
+            .arg(&key)
+            .status();
+        if !generated.is_ok_and(|status| status.success()) {
+            // Needs OpenSSH
+            return;
+        }
+        let public_key = fs::read_to_string(key.with_extension("pub")).unwrap();
+        let allowed_signers = temp_dir.path().join("allowed_signers");
+        fs::write(&allowed_signers, format!("* {}", public_key)).unwrap();
+        let git = |repo: &Path, args: &[&str]| {
+            std::process::Command::new("git")
+                .args(args)
+                .current_dir(repo)
+                .output()
+                .unwrap()
+        };
+
+        let signing = Settings {
+            signing_key: Some(key.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        let backends: Vec<(&str, Box<dyn GitOperations>)> = vec![
+            (
+                "cli",
+                Box::new(GitCliOperations::with_settings(signing.clone())),
+            ),
+            (
+                "git2",
+                Box::new(Git2Operations::new().with_signing(signing.commit_signing())),
+            ),
+        ];
+        for (name, git_ops) in backends {
+            let repo = temp_dir.path().join(name);
+            git_ops.init_repository(&repo).unwrap();
+            git(&repo, &["config", "user.name", "Bundle Bot"]);
+            git(&repo, &["config", "user.email", "bot@example.com"]);
+            fs::write(repo.join("logo.svg"), "<svg/>").unwrap();
+
+            let author = CommitAuthor::parse("Assets Bot <assets-bot@example.com>").unwrap();
+            git_ops
+                .commit_all(&repo, "Add logo", Some(&author))
+                .unwrap();
+
+            let identity = git(&repo, &["log", "-1", "--format=%an <%ae>|%cn <%ce>"]);
+            assert_eq!(
+                String::from_utf8_lossy(&identity.stdout).trim(),
+                "Assets Bot <assets-bot@example.com>|Assets Bot <assets-bot@example.com>",
+                "{} commit author",
+                name
+            );
+
+            let verified = git(
+                &repo,
+                &[
+                    "-c",
+                    &format!("gpg.ssh.allowedSignersFile={}", allowed_signers.display()),
+                    "verify-commit",
+                    "HEAD",
+                ],
+            );
+            assert!(
+                verified.status.success(),
+                "{} commit should verify: {}",
+                name,
+                String::from_utf8_lossy(&verified.stderr)
+            );
+        }
+    }
+
+    #[test]
+    fn test_create_branch_keeps_changes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let author = CommitAuthor::parse("Bundle Bot <bot@example.com>").unwrap();
+        let backends: Vec<(&str, Box<dyn GitOperations>)> = vec![
+            ("cli", Box::new(GitCliOperations::new())),
+            ("git2", Box::new(Git2Operations::new())),
+        ];
+        for (name, git_ops) in backends {
+            let repo = temp_dir.path().join(name);
+            git_ops.init_repository(&repo).unwrap();
+            fs::write(repo.join("logo.svg"), "<svg/>").unwrap();
+            git_ops
+                .commit_all(&repo, "Add logo", Some(&author))
+                .unwrap();
+            let base = git_ops.head_commit(&repo).unwrap();
+
+            fs::write(repo.join("logo.svg"), "<svg></svg>").unwrap();
+            git_ops.create_branch(&repo, "update-logo").unwrap();
+            assert_eq!(git_ops.head_commit(&repo).unwrap(), base, "{}", name);
+            assert_eq!(
+                git_ops.changed_files(&repo).unwrap(),
+                vec!["logo.svg".to_string()],
+                "{} keeps the changes",
+                name
+            );
+
+            assert_eq!(
+                git_ops.current_branch(&repo).unwrap().as_deref(),
+                Some("update-logo"),
+                "{}",
+                name
+            );
+            assert!(git_ops.create_branch(&repo, "update-logo").is_err());
+        }
+    }
+
+    #[test]
+    fn test_commit_paths_leaves_other_changes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let author = CommitAuthor::parse("Bundle Bot <bot@example.com>").unwrap();
+        let backends: Vec<(&str, Box<dyn GitOperations>)> = vec![
+            ("cli", Box::new(GitCliOperations::new())),
+            ("git2", Box::new(Git2Operations::new())),
+        ];
+        for (name, git_ops) in backends {
+            let repo = temp_dir.path().join(name);
+            git_ops.init_repository(&repo).unwrap();
+            fs::create_dir_all(repo.join("icons")).unwrap();
+            fs::write(repo.join("icons").join("old.svg"), "<svg/>").unwrap();
+            fs::write(repo.join("logo.svg"), "<svg/>").unwrap();
+            git_ops
+                .commit_all(&repo, "Add icons", Some(&author))
+                .unwrap();
+
+            fs::remove_file(repo.join("icons").join("old.svg")).unwrap();
+            fs::write(repo.join("icons").join("new.svg"), "<svg><g/></svg>").unwrap();
+            fs::write(repo.join("logo.svg"), "<svg></svg>").unwrap();
+            let git = |args: &[&str]| {
+                let output = std::process::Command::new("git")
+                    .args(args)
+                    .current_dir(&repo)
+                    .output()
+                    .unwrap();
+                String::from_utf8_lossy(&output.stdout).trim().to_string()
+            };
+            git(&["add", "logo.svg"]);
+            let paths = vec!["icons/new.svg".to_string(), "icons/old.svg".to_string()];
+            git_ops
+                .commit_paths(&repo, &paths, "Replace icon", Some(&author))
+                .unwrap();
+
+            assert_eq!(
+                git_ops.changed_files(&repo).unwrap(),
+                vec!["logo.svg".to_string()],
+                "{} leaves the other changes",
+                name
+            );
+            assert_eq!(
+                git(&["diff", "--cached", "--name-only"]),
+                "logo.svg",
+                "{} leaves staged changes staged",
+                name
+            );
+            let mut files = git_ops.log(&repo, 1).unwrap().remove(0).files;
+            files.sort();
+            assert_eq!(files, paths, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_remote_urls_include_configured_remotes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let git_ops = GitCliOperations::new();
+        let repo = temp_dir.path().join("repo");
+        git_ops.init_repository(&repo).unwrap();
+        git_ops
+            .add_remote(&repo, "origin", "https://github.com/example/icons.git")
+            .unwrap();
+
+        assert_eq!(
+            git_ops.remote_urls(&["fetch", "origin"], Some(&repo)),
+            vec!["https://github.com/example/icons.git".to_string()]
+        );
+        assert_eq!(
+            git_ops.remote_urls(&["ls-remote", "git@github.com:example/fonts.git"], None),
+            vec!["git@github.com:example/fonts.git".to_string()]
         );
     }
+
+    #[test]
+    fn test_create_tag_is_annotated() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let author = CommitAuthor::parse("Bundle Bot <bot@example.com>").unwrap();
+        let backends: Vec<(&str, Box<dyn GitOperations>)> = vec![
+            ("cli", Box::new(GitCliOperations::new())),
+            ("git2", Box::new(Git2Operations::new())),
+        ];
+        for (name, git_ops) in backends {
+            let repo = temp_dir.path().join(name);
+            git_ops.init_repository(&repo).unwrap();
+            fs::write(repo.join("logo.svg"), "<svg/>").unwrap();
+            git_ops
+                .commit_all(&repo, "fpm publish v1.0.0", Some(&author))
+                .unwrap();
+
+            git_ops
+                .create_tag(&repo, "v1.0.0", "fpm publish v1.0.0", Some(&author), false)
+                .unwrap();
+            let git = |args: &[&str]| {
+                let output = std::process::Command::new("git")
+                    .args(args)
+                    .current_dir(&repo)
+                    .output()
+                    .unwrap();
+                String::from_utf8_lossy(&output.stdout).trim().to_string()
+            };
+            assert_eq!(git(&["cat-file", "-t", "v1.0.0"]), "tag", "{}", name);
+            assert_eq!(
+                git(&["rev-parse", "v1.0.0^{commit}"]),
+                git_ops.head_commit(&repo).unwrap(),
+                "{}",
+                name
+            );
+            assert!(git_ops
+                .create_tag(&repo, "v1.0.0", "again", Some(&author), false)
+                .is_err());
+            git_ops
+                .create_tag(&repo, "v1.0.0", "again", Some(&author), true)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_list_remote_tags_of_empty_repository() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backends: Vec<(&str, Box<dyn GitOperations>)> = vec![
+            ("cli", Box::new(GitCliOperations::new())),
+            ("git2", Box::new(Git2Operations::new())),
+        ];
+        for (name, git_ops) in backends {
+            let remote = temp_dir.path().join(name);
+            git_ops.init_repository(&remote).unwrap();
+            let url = remote.to_string_lossy();
+
+            assert!(git_ops.list_remote_tags(&url, None).unwrap().is_empty());
+            assert_eq!(
+                git_ops.remote_branch_commit(&url, "main", None).unwrap(),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn test_has_local_changes_ignores_ignored_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let author = CommitAuthor::parse("Bundle Bot <bot@example.com>").unwrap();
+        let backends: Vec<(&str, Box<dyn GitOperations>)> = vec![
+            ("cli", Box::new(GitCliOperations::new())),
+            ("git2", Box::new(Git2Operations::new())),
+        ];
+        for (name, git_ops) in backends {
+            let repo = temp_dir.path().join(name);
+            git_ops.init_repository(&repo).unwrap();
+            fs::write(repo.join(".gitignore"), ".fpm/\n").unwrap();
+            git_ops
+                .commit_all(&repo, "Ignore bundles", Some(&author))
+                .unwrap();
+
+            fs::create_dir_all(repo.join(".fpm").join("fonts")).unwrap();
+            fs::write(repo.join(".fpm").join("fonts").join("a.ttf"), "font").unwrap();
+            assert!(!git_ops.has_local_changes(&repo).unwrap(), "{}", name);
+
+            fs::write(repo.join("logo.svg"), "<svg/>").unwrap();
+            assert!(git_ops.has_local_changes(&repo).unwrap(), "{}", name);
+        }
+    }
 }
@@ -0,0 +1,215 @@
+//! The dependency graph formed by a manifest and the manifests of its installed bundles.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cache::normalize_url;
+use crate::config::load_manifest;
+use crate::lockfile::lock_key;
+use crate::types::{BundleDependency, BUNDLE_DIR};
+
+/// A bundle declared by a manifest, with the bundles its own manifest declares
+#[derive(Debug, Clone)]
+pub struct DependencyNode {
+    /// Name of the bundle in the declaring manifest
+    pub name: String,
+    /// Install path below the top-level bundle directory, e.g. "ui-kit/base-styles"
+    pub key: String,
+    pub dependency: BundleDependency,
+    /// Where the bundle is (or would be) installed
+    pub path: PathBuf,
+    /// Bundles declared by this bundle's manifest (empty if it isn't installed)
+    pub children: Vec<DependencyNode>,
+}
+
+impl DependencyNode {
+    /// Returns true if the bundle's directory exists
+    pub fn is_installed(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// Describes what the bundle asks for: a pinned rev (`@3f2a9c1`), a version
+    /// requirement or a branch
+    pub fn requested_ref(&self) -> String {
+        self.dependency.requested_ref()
+    }
+}
+
+/// Builds the dependency graph of a manifest by following the manifests of
+/// installed bundles. Bundles are sorted by name at every level.
+pub fn build_graph(manifest_path: &Path) -> Result<Vec<DependencyNode>> {
+    collect_nodes(manifest_path, "")
+}
+
+fn collect_nodes(manifest_path: &Path, prefix: &str) -> Result<Vec<DependencyNode>> {
+    let manifest = load_manifest(manifest_path)?;
+    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+    let bundle_dir = parent_dir.join(BUNDLE_DIR);
+
+    let mut names: Vec<&String> = manifest.bundles.keys().collect();
+    names.sort();
+
+    let mut nodes = Vec::new();
+    for name in names {
+        let key = lock_key(prefix, name);
+        let path = bundle_dir.join(name);
+
+        let nested_manifest_path = path.join("bundle.toml");
+        let children = if nested_manifest_path.exists() {
+            collect_nodes(&nested_manifest_path, &key)
+                .with_context(|| format!("Failed to read the manifest of bundle: {}", key))?
+        } else {
+            Vec::new()
+        };
+
+        nodes.push(DependencyNode {
+            name: name.clone(),
+            key,
+            dependency: manifest.bundles[name].clone(),
+            path,
+            children,
+        });
+    }
+
+    Ok(nodes)
+}
+
+/// A bundle on the way from the top-level manifest to the bundle being visited:
+/// what it installs (see `source_identity`) and its install path
+pub type Ancestor = (String, String);
+
+/// Identifies what a bundle installs, to recognize it further down the tree: its
+/// source with the requested ref. Local directories are resolved against
+/// `manifest_dir`, the directory of the manifest declaring the bundle.
+pub fn source_identity(dependency: &BundleDependency, manifest_dir: &Path) -> String {
+    let source = match dependency.local_path() {
+        Some(path) => {
+            let path = manifest_dir.join(path);
+            fs::canonicalize(&path)
+                .unwrap_or(path)
+                .display()
+                .to_string()
+        }
+        None => normalize_url(&dependency.source()),
+    };
+    format!("{}@{}", source, dependency.requested_ref())
+}
+
+/// Returns the dependency cycle closed by visiting the bundle with `identity` as
+/// `key` below `ancestors` (outermost first), e.g. "ui-kit -> base-styles -> ui-kit"
+pub fn find_cycle(ancestors: &[Ancestor], identity: &str, key: &str) -> Option<String> {
+    let (_, start) = ancestors
+        .iter()
+        .find(|(ancestor, _)| ancestor == identity)?;
+    let skipped = start.split('/').count() - 1;
+    let names: Vec<&str> = key.split('/').skip(skipped).collect();
+    Some(names.join(" -> "))
+}
+
+/// Finds every path from the top level of the graph to a bundle named `name`.
+/// Each path lists the nodes from the top-level bundle down to the match.
+pub fn find_paths<'a>(nodes: &'a [DependencyNode], name: &str) -> Vec<Vec<&'a DependencyNode>> {
+    let mut paths = Vec::new();
+    collect_paths(nodes, name, &mut Vec::new(), &mut paths);
+    paths
+}
+
+fn collect_paths<'a>(
+    nodes: &'a [DependencyNode],
+    name: &str,
+    current: &mut Vec<&'a DependencyNode>,
+    paths: &mut Vec<Vec<&'a DependencyNode>>,
+) {
+    for node in nodes {
+        current.push(node);
+        if node.name == name || node.key == name {
+            paths.push(current.clone());
+        }
+        collect_paths(&node.children, name, current, paths);
+        current.pop();
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn node(name: &str, key: &str, children: Vec<DependencyNode>) -> DependencyNode {
+        DependencyNode {
+            name: name.to_string(),
+            key: key.to_string(),
+            dependency: BundleDependency {
+                version: "*".to_string(),
+                git: format!("https://github.com/example/{}.git", name),
+                ..Default::default()
+            },
+            path: PathBuf::from(key),
+            children,
+        }
+    }
+
+    #[test]
+    fn test_find_paths_lists_every_parent_chain() {
+        let graph = vec![
+            node("base-styles", "base-styles", vec![]),
+            node(
+                "ui-kit",
+                "ui-kit",
+                vec![node(
+                    "theme",
+                    "ui-kit/theme",
+                    vec![node("base-styles", "ui-kit/theme/base-styles", vec![])],
+                )],
+            ),
+        ];
+
+        let keys = |paths: Vec<Vec<&DependencyNode>>| -> Vec<Vec<String>> {
+            paths
+                .into_iter()
+                .map(|path| path.into_iter().map(|n| n.key.clone()).collect())
+                .collect()
+        };
+
+        assert_eq!(
+            keys(find_paths(&graph, "base-styles")),
+            vec![
+                vec!["base-styles"],
+                vec!["ui-kit", "ui-kit/theme", "ui-kit/theme/base-styles"],
+            ]
+        );
+        // Install paths select a single occurrence
+        assert_eq!(
+            keys(find_paths(&graph, "ui-kit/theme")),
+            vec![vec!["ui-kit", "ui-kit/theme"]]
+        );
+        assert!(find_paths(&graph, "missing").is_empty());
+    }
+
+    #[test]
+    fn test_find_cycle() {
+        let ui_kit = node("ui-kit", "ui-kit", vec![]).dependency;
+        let styles = node("base-styles", "ui-kit/base-styles", vec![]).dependency;
+        let identity = |dependency| source_identity(dependency, Path::new("."));
+        let ancestors = vec![
+            (identity(&ui_kit), "ui-kit".to_string()),
+            (identity(&styles), "ui-kit/base-styles".to_string()),
+        ];
+
+        assert_eq!(
+            find_cycle(&ancestors, &identity(&ui_kit), "ui-kit/base-styles/ui-kit"),
+            Some("ui-kit -> base-styles -> ui-kit".to_string())
+        );
+        assert_eq!(
+            find_cycle(&ancestors, &identity(&styles), "ui-kit/base-styles/styles"),
+            Some("base-styles -> styles".to_string())
+        );
+        // The same repository at another ref is a different bundle
+        let mut next = ui_kit.clone();
+        next.branch = Some("next".to_string());
+        assert_eq!(
+            find_cycle(&ancestors, &identity(&next), "ui-kit/base-styles/ui-kit"),
+            None
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Install hooks, and the shell commands they and `fpm run` scripts run.
+//!
+//! The `[hooks]` of the top-level bundle.toml are the project's own commands and
+//! run as they are. The `post_install` hooks of installed bundles come from the
+//! bundles' authors, so they only run when the `bundle_hooks` setting allows it,
+//! and in an environment stripped of everything but a few basic variables, so
+//! tokens and other secrets of the user's environment don't reach them.
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::ffi::OsString;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+
+use crate::report;
+use crate::settings::HookPolicy;
+
+/// Environment variable naming the hook being run, e.g. "post_install"
+pub const HOOK_EVENT_ENV: &str = "FPM_HOOK_EVENT";
+
+/// Environment variable holding the install path of the bundle a hook runs for,
+/// e.g. "ui-kit/base-styles"
+pub const HOOK_BUNDLE_ENV: &str = "FPM_HOOK_BUNDLE";
+
+/// Environment variable holding the directory of the bundle a hook runs for
+pub const HOOK_BUNDLE_PATH_ENV: &str = "FPM_HOOK_BUNDLE_PATH";
+
+/// Environment variable holding the top-level .fpm directory
+pub const BUNDLE_DIR_ENV: &str = "FPM_BUNDLE_DIR";
+
+/// Variables of the user's environment the hooks of installed bundles still see
+const SANDBOX_ENV: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USERPROFILE",
+    "SYSTEMROOT",
+    "TEMP",
+    "TMP",
+    "TMPDIR",
+    "LANG",
+];
+
+/// Builds the command running `command` with the platform's shell
+pub fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut process = Command::new("cmd");
+        process.args(["/C", command]);
+        process
+    } else {
+        let mut process = Command::new("sh");
+        process.args(["-c", command]);
+        process
+    }
+}
+
+/// Quotes an argument for the shell `shell_command` runs
+pub fn quote_arg(arg: &str) -> String {
+    if cfg!(windows) {
+        format!("\"{}\"", arg.replace('"', "\"\""))
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Runs a shell command in `dir` with the variables of `env` added. A `sandboxed`
+/// command only sees the variables of `env` and `SANDBOX_ENV`. Its output goes to
+/// stderr when the results are printed as JSON, keeping stdout to the document.
+pub fn run_command(
+    command: &str,
+    dir: &Path,
+    env: &[(&str, String)],
+    sandboxed: bool,
+) -> Result<()> {
+    let inherited: Vec<(OsString, OsString)> = std::env::vars_os().collect();
+    let mut process = command_with_env(command, dir, env, sandboxed, &inherited);
+    let status = run(&mut process).with_context(|| format!("Failed to run '{}'", command))?;
+    if !status.success() {
+        anyhow::bail!("'{}' failed ({})", command, status);
+    }
+    Ok(())
+}
+
+/// Builds the process `run_command` runs, passing it the variables of `inherited`
+/// (the user's environment), or for a `sandboxed` command only those named in
+/// `SANDBOX_ENV`, and then those of `env`
+fn command_with_env(
+    command: &str,
+    dir: &Path,
+    env: &[(&str, String)],
+    sandboxed: bool,
+    inherited: &[(OsString, OsString)],
+) -> Command {
+    let mut process = shell_command(command);
+    process.current_dir(dir);
+    process.env_clear();
+    process.envs(
+        inherited
+            .iter()
+            .filter(|(name, _)| !sandboxed || SANDBOX_ENV.iter().any(|kept| name == kept))
+            .map(|(name, value)| (name, value)),
+    );
+    process.envs(env.iter().map(|(name, value)| (name, value)));
+    process
+}
+
+fn run(process: &mut Command) -> std::io::Result<ExitStatus> {
+    if !report::is_json() {
+        return process.status();
+    }
+
+    let output = process.output()?;
+    let mut stderr = std::io::stderr();
+    stderr.write_all(&output.stdout)?;
+    stderr.write_all(&output.stderr)?;
+    Ok(output.status)
+}
+
+/// Whether the `post_install` hook of the installed bundle `key` may run under
+/// `policy`. Hooks that don't get to run are reported.
+pub fn bundle_hook_allowed(policy: HookPolicy, key: &str, command: &str) -> Result<bool> {
+    match policy {
+        HookPolicy::Always => Ok(true),
+        HookPolicy::Ask if std::io::stdin().is_terminal() => {
+            eprint!(
+                "Run the post_install hook of bundle '{}': {}? [y/N] ",
+                key, command
+            );
+            std::io::stderr().flush()?;
+            let mut answer = String::new();
+            std::io::stdin()
+                .lock()
+                .read_line(&mut answer)
+                .context("Failed to read the answer")?;
+            Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+        }
+        HookPolicy::Ask | HookPolicy::Never => {
+            report::progress(format_args!(
+                "{} bundle '{}' has a post_install hook ({}), which was not run. Set \
+                bundle_hooks to \"ask\" or \"always\" to run the hooks of bundles.",
+                "Note:".cyan(),
+                key,
+                command
+            ));
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sandboxed_commands_only_see_given_variables() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let inherited: Vec<(OsString, OsString)> = [
+            ("PATH", std::env::var_os("PATH").unwrap_or_default()),
+            ("FPM_TEST_SECRET", "secret".into()),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.into(), value))
+        .collect();
+
+        let env = [(HOOK_BUNDLE_ENV, "icons".to_string())];
+        let command = "printf '%s:%s' \"$FPM_HOOK_BUNDLE\" \"$FPM_TEST_SECRET\" > env.txt";
+        let status = command_with_env(command, dir, &env, true, &inherited)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("env.txt")).unwrap(),
+            "icons:"
+        );
+
+        let status = command_with_env(command, dir, &env, false, &inherited)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("env.txt")).unwrap(),
+            "icons:secret"
+        );
+
+        let error = run_command("exit 3", dir, &[], false).unwrap_err();
+        assert!(error.to_string().contains("exit 3"));
+    }
+}
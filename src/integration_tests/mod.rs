@@ -81,6 +81,7 @@ fn test_install_from_real_git_repository() -> Result<()> {
             branch: Some("main".to_string()),
             ssh_key: None,
             include: None,
+            ..Default::default()
         },
     );
 
@@ -188,6 +189,7 @@ fn test_install_with_specific_branch() -> Result<()> {
             branch: Some("main".to_string()),
             ssh_key: None,
             include: None,
+            ..Default::default()
         },
     );
 
@@ -235,6 +237,7 @@ fn test_status_shows_correct_state_after_install() -> Result<()> {
             branch: Some("main".to_string()),
             ssh_key: None,
             include: None,
+            ..Default::default()
         },
     );
 
@@ -305,6 +308,7 @@ fn test_install_nested_bundles() -> Result<()> {
             branch: Some("main".to_string()),
             ssh_key: None,
             include: None,
+            ..Default::default()
         },
     );
 
@@ -318,6 +322,7 @@ fn test_install_nested_bundles() -> Result<()> {
             branch: Some("main".to_string()),
             ssh_key: None,
             include: None,
+            ..Default::default()
         },
     );
 
@@ -514,6 +519,7 @@ fn test_push_counter_to_real_repo() -> Result<()> {
             branch: Some("main".to_string()),
             ssh_key: None,
             include: None,
+            ..Default::default()
         },
     );
 
@@ -623,6 +629,7 @@ fn test_push_nested_bundles_to_real_repos() -> Result<()> {
             branch: Some("main".to_string()),
             ssh_key: None,
             include: None,
+            ..Default::default()
         },
     );
 
@@ -635,6 +642,7 @@ fn test_push_nested_bundles_to_real_repos() -> Result<()> {
             branch: Some("main".to_string()),
             ssh_key: None,
             include: None,
+            ..Default::default()
         },
     );
 
@@ -0,0 +1,101 @@
+//! Environment variable interpolation in manifest values.
+//!
+//! The `git`, `branch` and `ssh_key` fields of a bundle may reference environment
+//! variables as `${VAR}`, e.g. `git = "https://${GIT_HOST}/org/repo.git"`, so one
+//! manifest can serve several environments. `$$` stands for a literal `$`.
+
+use anyhow::Result;
+
+/// Replaces every `${VAR}` in `value` with the value of the environment variable.
+/// Fails if a variable is not set or a reference is malformed.
+pub fn interpolate(value: &str) -> Result<String> {
+    interpolate_with(value, |name| std::env::var(name).ok())
+}
+
+/// Replaces every `${VAR}` in `value` with what `lookup` returns for it
+fn interpolate_with(value: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        if let Some(after) = after.strip_prefix('$') {
+            result.push('$');
+            rest = after;
+            continue;
+        }
+        let Some(reference) = after.strip_prefix('{') else {
+            anyhow::bail!(
+                "Invalid variable reference in '{}': write ${{VAR}}, or $$ for a literal $",
+                value
+            );
+        };
+        let Some(end) = reference.find('}') else {
+            anyhow::bail!("Unterminated variable reference in '{}'", value);
+        };
+
+        let name = &reference[..end];
+        if !is_variable_name(name) {
+            anyhow::bail!("Invalid variable name '{}' in '{}'", name, value);
+        }
+        match lookup(name) {
+            Some(variable) => result.push_str(&variable),
+            None => anyhow::bail!(
+                "Environment variable '{}' is not set (used in '{}')",
+                name,
+                value
+            ),
+        }
+        rest = &reference[end + 1..];
+    }
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+/// Returns true for names made of ASCII letters, digits and underscores that don't
+/// start with a digit
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "GIT_HOST" => Some("git.example.com".to_string()),
+            "TEAM" => Some("design".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_interpolate_variables() {
+        let expand = |value: &str| interpolate_with(value, lookup).unwrap();
+
+        assert_eq!(
+            expand("https://${GIT_HOST}/${TEAM}/icons.git"),
+            "https://git.example.com/design/icons.git"
+        );
+        assert_eq!(expand("main"), "main");
+        assert_eq!(expand("cost$$"), "cost$");
+    }
+
+    #[test]
+    fn test_interpolate_errors() {
+        let error = |value: &str| interpolate_with(value, lookup).unwrap_err().to_string();
+
+        assert!(error("https://${MISSING}/icons.git").contains("'MISSING' is not set"));
+        assert!(error("${GIT_HOST").contains("Unterminated"));
+        assert!(error("$GIT_HOST").contains("${VAR}"));
+        assert!(error("${1HOST}").contains("Invalid variable name"));
+    }
+}
@@ -6,10 +6,37 @@
 // Allow format!("{}", var) style - this is a stylistic preference
 #![allow(clippy::uninlined_format_args)]
 
+pub mod archive;
+pub mod auth;
+pub mod cache;
+pub mod checksum;
 pub mod cli;
 pub mod commands;
 pub mod config;
+pub mod conflicts;
+pub mod date;
+pub mod discover;
+pub mod filter;
+pub mod flatten;
+pub mod forge;
 pub mod git;
+pub mod graph;
+pub mod hooks;
+pub mod interpolate;
+pub mod lockfile;
+pub mod locking;
+pub mod oci;
+pub mod registry;
+pub mod report;
+pub mod resolve;
+pub mod retry;
+pub mod s3;
+pub mod settings;
+pub mod shorthand;
+pub mod signature;
+pub mod source;
+pub mod ssh;
+pub mod state;
 pub mod types;
 pub mod version;
 
@@ -16,11 +16,12 @@ use anyhow::Result;
 use std::collections::HashMap;
 use std::fs;
 
+use crate::cache::CACHE_DIR_ENV;
 use crate::test_utils::{
     cleanup_test_env, create_bundle_manifest, create_sample_project, get_fpm_binary_path,
-    is_git_available, run_fpm, setup_test_env,
+    is_git_available, run_fpm, run_fpm_with_env, setup_test_env,
 };
-use crate::types::{BundleDependency, BUNDLE_DIR};
+use crate::types::{BundleDependency, BUNDLE_DIR, LOCKFILE_NAME};
 
 const TEST_CATEGORY: &str = "local_integration";
 
@@ -182,6 +183,7 @@ description = "Test bundle for push command"
             branch: Some("main".to_string()),
             ssh_key: None,
             include: None,
+            ..Default::default()
         },
     );
 
@@ -342,6 +344,7 @@ branch = "main"
             branch: Some("main".to_string()),
             ssh_key: None,
             include: None,
+            ..Default::default()
         },
     );
     create_bundle_manifest(&design_dir, Some("Nested push test"), None, bundles)?;
@@ -473,6 +476,7 @@ description = "Test bundle"
             branch: Some("main".to_string()),
             ssh_key: None,
             include: None,
+            ..Default::default()
         },
     );
     create_bundle_manifest(&design_dir, Some("Test"), None, bundles)?;
@@ -545,3 +549,1103 @@ description = "Test bundle"
 
     Ok(())
 }
+
+#[test]
+fn test_diff_remote_shows_upstream_changes() -> Result<()> {
+    check_preconditions()?;
+
+    let test_name = "diff_remote";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    let remote_dir = test_dir.join("remote");
+    let setup_clone = test_dir.join("setup_clone");
+
+    let bundle_manifest = r#"fpm_version = "0.1.0"
+identifier = "fpm-bundle"
+version = "0.0.1"
+description = "Test bundle for diff --remote"
+
+[bundles]
+"#;
+    setup_local_bare_repo(&remote_dir, &setup_clone, bundle_manifest)?;
+
+    create_sample_project(&test_dir.join("project"))?;
+    let design_dir = test_dir.join("project").join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "diff-test".to_string(),
+        BundleDependency {
+            version: "1.0.0".to_string(),
+            git: remote_dir.to_str().unwrap().to_string(),
+            branch: Some("main".to_string()),
+            ..Default::default()
+        },
+    );
+    create_bundle_manifest(&design_dir, Some("Diff test"), None, bundles)?;
+
+    let install_output = run_fpm(&["install"], &design_dir)?;
+    assert!(install_output.status.success(), "Install should succeed");
+
+    // Publish a new upstream commit after the bundle was installed
+    fs::write(setup_clone.join("upstream.txt"), "line 1\nline 2\n")?;
+    for args in [
+        vec!["add", "."],
+        vec!["commit", "-m", "Upstream change"],
+        vec!["push", "origin", "main"],
+    ] {
+        let output = std::process::Command::new("git")
+            .args(&args)
+            .current_dir(&setup_clone)
+            .output()?;
+        assert!(
+            output.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    // Local diff does not know about the upstream commit
+    let local_output = run_fpm(&["diff", "diff-test"], &design_dir)?;
+    let local_stdout = String::from_utf8_lossy(&local_output.stdout);
+    assert!(local_output.status.success(), "Local diff should succeed");
+    assert!(
+        !local_stdout.contains("upstream.txt"),
+        "Local diff should not show upstream changes. Got: {}",
+        local_stdout
+    );
+
+    // Remote diff fetches and reports the incoming file as added, leaving out local
+    // edits
+    let bundle_path = design_dir.join(BUNDLE_DIR).join("diff-test");
+    fs::write(bundle_path.join("README.md"), "# Edited locally\n")?;
+    let remote_output = run_fpm(
+        &["diff", "diff-test", "--remote", "--color", "never"],
+        &design_dir,
+    )?;
+    let remote_stdout = String::from_utf8_lossy(&remote_output.stdout);
+    println!("Diff stdout: {}", remote_stdout);
+    assert!(
+        remote_output.status.success(),
+        "Remote diff should succeed: {}",
+        String::from_utf8_lossy(&remote_output.stderr)
+    );
+    assert!(
+        remote_stdout.contains("upstream.txt | +2 -0"),
+        "Remote diff should list the upstream file as added. Got: {}",
+        remote_stdout
+    );
+    assert!(
+        !remote_stdout.contains("README.md"),
+        "Remote diff should leave out local edits. Got: {}",
+        remote_stdout
+    );
+
+    // The working tree is left untouched
+    assert!(!bundle_path.join("upstream.txt").exists());
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_aliased_bundles_share_objects_local() -> Result<()> {
+    check_preconditions()?;
+
+    let test_name = "aliased_bundles_local";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    let remote_dir = test_dir.join("remote");
+    let bundle_manifest = r#"fpm_version = "0.1.0"
+identifier = "fpm-bundle"
+version = "0.0.1"
+
+[bundles]
+"#;
+    setup_local_bare_repo(&remote_dir, &test_dir.join("setup_clone"), bundle_manifest)?;
+
+    create_sample_project(&test_dir.join("project"))?;
+    let design_dir = test_dir.join("project").join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    for name in ["icons-a", "icons-b"] {
+        bundles.insert(
+            name.to_string(),
+            BundleDependency {
+                version: "1.0.0".to_string(),
+                git: remote_dir.to_str().unwrap().to_string(),
+                branch: Some("main".to_string()),
+                ..Default::default()
+            },
+        );
+    }
+    create_bundle_manifest(&design_dir, Some("Aliases"), None, bundles)?;
+
+    let cache_dir = test_dir.join("cache");
+    let install_output = run_fpm_with_env(
+        &["install"],
+        &design_dir,
+        &[(CACHE_DIR_ENV, cache_dir.as_path())],
+    )?;
+    assert!(
+        install_output.status.success(),
+        "Install should succeed: {}",
+        String::from_utf8_lossy(&install_output.stderr)
+    );
+
+    // A single mirror is created in the cache
+    let mirrors: Vec<_> = fs::read_dir(cache_dir.join("repos"))?.collect();
+    assert_eq!(mirrors.len(), 1, "Expected exactly one cached mirror");
+
+    // Both clones borrow objects from the mirror
+    for name in ["icons-a", "icons-b"] {
+        let bundle_path = design_dir.join(BUNDLE_DIR).join(name);
+        assert!(bundle_path.join("README.md").exists());
+
+        let alternates = bundle_path
+            .join(".git")
+            .join("objects")
+            .join("info")
+            .join("alternates");
+        assert!(
+            alternates.exists(),
+            "{} should borrow objects from the shared store",
+            name
+        );
+    }
+
+    // Maintenance runs on both clones and the shared mirror
+    let maintain_output = run_fpm_with_env(
+        &["maintain"],
+        &design_dir,
+        &[(CACHE_DIR_ENV, cache_dir.as_path())],
+    )?;
+    assert!(
+        maintain_output.status.success(),
+        "Maintain should succeed: {}{}",
+        String::from_utf8_lossy(&maintain_output.stdout),
+        String::from_utf8_lossy(&maintain_output.stderr)
+    );
+
+    let status_output = run_fpm(&["status"], &design_dir)?;
+    let status_stdout = String::from_utf8_lossy(&status_output.stdout);
+    assert!(
+        status_stdout.contains("alias of icons-a"),
+        "Status should label the alias. Got: {}",
+        status_stdout
+    );
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_aliased_bundles_survive_cache_clean_local() -> Result<()> {
+    check_preconditions()?;
+
+    let test_name = "aliased_cache_clean_local";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    let remote_dir = test_dir.join("remote");
+    let bundle_manifest = r#"fpm_version = "0.1.0"
+identifier = "fpm-bundle"
+version = "0.0.1"
+
+[bundles]
+"#;
+    setup_local_bare_repo(&remote_dir, &test_dir.join("setup_clone"), bundle_manifest)?;
+
+    create_sample_project(&test_dir.join("project"))?;
+    let design_dir = test_dir.join("project").join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    for name in ["icons-a", "icons-b"] {
+        bundles.insert(
+            name.to_string(),
+            BundleDependency {
+                version: "1.0.0".to_string(),
+                git: remote_dir.to_str().unwrap().to_string(),
+                branch: Some("main".to_string()),
+                ..Default::default()
+            },
+        );
+    }
+    create_bundle_manifest(&design_dir, Some("Aliases"), None, bundles)?;
+
+    let cache_dir = test_dir.join("cache");
+    let env = [(CACHE_DIR_ENV, cache_dir.as_path())];
+    let install_output = run_fpm_with_env(&["install"], &design_dir, &env)?;
+    assert!(
+        install_output.status.success(),
+        "Install should succeed: {}",
+        String::from_utf8_lossy(&install_output.stderr)
+    );
+
+    let clean_output = run_fpm_with_env(&["cache", "clean"], &design_dir, &env)?;
+    assert!(
+        clean_output.status.success(),
+        "Cache clean should succeed: {}",
+        String::from_utf8_lossy(&clean_output.stderr)
+    );
+    assert_eq!(fs::read_dir(cache_dir.join("repos"))?.count(), 0);
+
+    // The clones no longer borrow from the removed mirror and have every object
+    for name in ["icons-a", "icons-b"] {
+        let bundle_path = design_dir.join(BUNDLE_DIR).join(name);
+        assert!(
+            !bundle_path
+                .join(".git")
+                .join("objects")
+                .join("info")
+                .join("alternates")
+                .exists(),
+            "{} should have its own objects",
+            name
+        );
+
+        let fsck_output = std::process::Command::new("git")
+            .args(["fsck", "--full"])
+            .current_dir(&bundle_path)
+            .output()?;
+        assert!(
+            fsck_output.status.success(),
+            "{} should be intact: {}",
+            name,
+            String::from_utf8_lossy(&fsck_output.stderr)
+        );
+    }
+
+    let status_output = run_fpm(&["status"], &design_dir)?;
+    assert!(
+        status_output.status.success(),
+        "Status should succeed: {}",
+        String::from_utf8_lossy(&status_output.stderr)
+    );
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_adopt_publishes_existing_directory() -> Result<()> {
+    check_preconditions()?;
+
+    let test_name = "adopt_directory";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    // An empty bare repository plays the role of the new remote
+    let remote_dir = test_dir.join("remote.git");
+    fs::create_dir_all(&remote_dir)?;
+    let init_output = std::process::Command::new("git")
+        .args(["init", "--bare", "--initial-branch=main"])
+        .current_dir(&remote_dir)
+        .output()?;
+    assert!(init_output.status.success());
+
+    // Existing ad-hoc shared folder
+    let project_dir = test_dir.join("project");
+    let assets_dir = project_dir.join("shared-assets");
+    fs::create_dir_all(assets_dir.join("icons"))?;
+    fs::write(assets_dir.join("icons").join("logo.svg"), "<svg/>")?;
+    fs::write(assets_dir.join("palette.css"), ":root { --brand: #f60; }")?;
+
+    // The adopted directory gets a brand-new repository, so provide a commit identity
+    let output = run_fpm_with_env(
+        &[
+            "adopt",
+            "shared-assets",
+            "--remote",
+            remote_dir.to_str().unwrap(),
+            "--description",
+            "Shared brand assets",
+        ],
+        &project_dir,
+        &[
+            ("GIT_AUTHOR_NAME", "Test User"),
+            ("GIT_AUTHOR_EMAIL", "test@test.com"),
+            ("GIT_COMMITTER_NAME", "Test User"),
+            ("GIT_COMMITTER_EMAIL", "test@test.com"),
+        ],
+    )?;
+    assert!(
+        output.status.success(),
+        "Adopt should succeed: {}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // The manifest points at the adopted directory
+    let manifest = crate::config::load_manifest(&project_dir.join("bundle.toml"))?;
+    assert_eq!(
+        manifest.root,
+        Some(std::path::PathBuf::from("shared-assets"))
+    );
+    assert_eq!(manifest.description.as_deref(), Some("Shared brand assets"));
+
+    // The initial publish reached the remote
+    let ls_output = std::process::Command::new("git")
+        .args(["ls-tree", "-r", "--name-only", "main"])
+        .current_dir(&remote_dir)
+        .output()?;
+    let files = String::from_utf8_lossy(&ls_output.stdout);
+    assert!(
+        ls_output.status.success(),
+        "Remote should have a main branch"
+    );
+    assert!(files.contains("icons/logo.svg"), "Got: {}", files);
+    assert!(files.contains("palette.css"), "Got: {}", files);
+
+    // Adopting again is refused
+    let again = run_fpm(
+        &[
+            "adopt",
+            "shared-assets",
+            "--remote",
+            remote_dir.to_str().unwrap(),
+        ],
+        &project_dir,
+    )?;
+    assert!(!again.status.success(), "Second adopt should fail");
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_lockfile_pins_installed_commit() -> Result<()> {
+    check_preconditions()?;
+
+    let test_name = "lockfile_pins";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    let remote_dir = test_dir.join("remote");
+    let setup_clone = test_dir.join("setup_clone");
+    let bundle_manifest = r#"fpm_version = "0.1.0"
+identifier = "fpm-bundle"
+version = "0.0.1"
+
+[bundles]
+"#;
+    setup_local_bare_repo(&remote_dir, &setup_clone, bundle_manifest)?;
+
+    create_sample_project(&test_dir.join("project"))?;
+    let design_dir = test_dir.join("project").join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "pinned".to_string(),
+        BundleDependency {
+            version: "1.0.0".to_string(),
+            git: remote_dir.to_str().unwrap().to_string(),
+            branch: Some("main".to_string()),
+            ..Default::default()
+        },
+    );
+    create_bundle_manifest(&design_dir, Some("Lockfile test"), None, bundles)?;
+
+    let install_output = run_fpm(&["install"], &design_dir)?;
+    assert!(install_output.status.success(), "Install should succeed");
+
+    let lock_content = fs::read_to_string(design_dir.join(LOCKFILE_NAME))?;
+    assert!(
+        lock_content.contains("[bundles.pinned]"),
+        "Got: {}",
+        lock_content
+    );
+
+    // Upstream moves on after the lockfile was written
+    fs::write(setup_clone.join("upstream.txt"), "new\n")?;
+    for args in [
+        vec!["add", "."],
+        vec!["commit", "-m", "Upstream change"],
+        vec!["push", "origin", "main"],
+    ] {
+        let output = std::process::Command::new("git")
+            .args(&args)
+            .current_dir(&setup_clone)
+            .output()?;
+        assert!(output.status.success(), "git {:?} failed", args);
+    }
+
+    // A fresh install honours the lockfile
+    let bundle_path = design_dir.join(BUNDLE_DIR).join("pinned");
+    fs::remove_dir_all(&bundle_path)?;
+    let locked_install = run_fpm(&["install"], &design_dir)?;
+    assert!(
+        locked_install.status.success(),
+        "Locked install should succeed"
+    );
+    assert!(
+        !bundle_path.join("upstream.txt").exists(),
+        "Locked install must not pick up the new upstream commit"
+    );
+    assert_eq!(
+        fs::read_to_string(design_dir.join(LOCKFILE_NAME))?,
+        lock_content
+    );
+
+    // Without a lockfile the branch tip is installed again
+    fs::remove_file(design_dir.join(LOCKFILE_NAME))?;
+    let unlocked_install = run_fpm(&["install"], &design_dir)?;
+    assert!(unlocked_install.status.success(), "Install should succeed");
+    assert!(bundle_path.join("upstream.txt").exists());
+    assert_ne!(
+        fs::read_to_string(design_dir.join(LOCKFILE_NAME))?,
+        lock_content
+    );
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+/// Test that a bundle's version is installed from its tag, not the branch tip
+#[test]
+fn test_install_resolves_version_tag() -> Result<()> {
+    check_preconditions()?;
+
+    let test_name = "version_tag";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    let remote_dir = test_dir.join("remote");
+    let setup_clone = test_dir.join("setup_clone");
+    let bundle_manifest = r#"fpm_version = "0.1.0"
+identifier = "fpm-bundle"
+version = "1.0.0"
+
+[bundles]
+"#;
+    setup_local_bare_repo(&remote_dir, &setup_clone, bundle_manifest)?;
+
+    // Tag the current state as v1.0.0, then move main past it
+    fs::write(setup_clone.join("upstream.txt"), "unreleased\n")?;
+    for args in [
+        vec!["tag", "v1.0.0"],
+        vec!["add", "."],
+        vec!["commit", "-m", "Unreleased change"],
+        vec!["push", "origin", "main", "--tags"],
+    ] {
+        let output = std::process::Command::new("git")
+            .args(&args)
+            .current_dir(&setup_clone)
+            .output()?;
+        assert!(output.status.success(), "git {:?} failed", args);
+    }
+
+    create_sample_project(&test_dir.join("project"))?;
+    let design_dir = test_dir.join("project").join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "released".to_string(),
+        BundleDependency {
+            version: "1.0.0".to_string(),
+            git: remote_dir.to_str().unwrap().to_string(),
+            ..Default::default()
+        },
+    );
+    create_bundle_manifest(&design_dir, Some("Version tag test"), None, bundles)?;
+
+    let install_output = run_fpm(&["install"], &design_dir)?;
+    assert!(
+        install_output.status.success(),
+        "Install should succeed: {}",
+        String::from_utf8_lossy(&install_output.stderr)
+    );
+
+    let bundle_path = design_dir.join(BUNDLE_DIR).join("released");
+    assert!(bundle_path.join("bundle.toml").exists());
+    assert!(
+        !bundle_path.join("upstream.txt").exists(),
+        "Install must check out the tag, not the branch tip"
+    );
+
+    let lock_content = fs::read_to_string(design_dir.join(LOCKFILE_NAME))?;
+    assert!(
+        lock_content.contains("branch = \"v1.0.0\""),
+        "Got: {}",
+        lock_content
+    );
+
+    // A version without a matching tag fails
+    let mut manifest = crate::config::load_manifest(&design_dir.join("bundle.toml"))?;
+    manifest.bundles.get_mut("released").unwrap().version = "2.0.0".to_string();
+    crate::config::save_manifest(&manifest, &design_dir.join("bundle.toml"))?;
+
+    let missing_output = run_fpm(&["install"], &design_dir)?;
+    assert!(!missing_output.status.success(), "Missing tag should fail");
+    assert!(
+        String::from_utf8_lossy(&missing_output.stderr).contains("Version 2.0.0 not found"),
+        "Got: {}",
+        String::from_utf8_lossy(&missing_output.stderr)
+    );
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+/// Test that outdated compares installed commits with the remote branch tip
+#[test]
+fn test_outdated_detects_new_upstream_commits() -> Result<()> {
+    check_preconditions()?;
+
+    let test_name = "outdated";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    let remote_dir = test_dir.join("remote");
+    let setup_clone = test_dir.join("setup_clone");
+    let bundle_manifest = r#"fpm_version = "0.1.0"
+identifier = "fpm-bundle"
+version = "0.0.1"
+
+[bundles]
+"#;
+    setup_local_bare_repo(&remote_dir, &setup_clone, bundle_manifest)?;
+
+    create_sample_project(&test_dir.join("project"))?;
+    let design_dir = test_dir.join("project").join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "tracked".to_string(),
+        BundleDependency {
+            version: "*".to_string(),
+            git: remote_dir.to_str().unwrap().to_string(),
+            branch: Some("main".to_string()),
+            ..Default::default()
+        },
+    );
+    create_bundle_manifest(&design_dir, Some("Outdated test"), None, bundles)?;
+
+    let install_output = run_fpm(&["install"], &design_dir)?;
+    assert!(install_output.status.success(), "Install should succeed");
+
+    let up_to_date = run_fpm(&["outdated"], &design_dir)?;
+    assert!(
+        up_to_date.status.success(),
+        "Freshly installed bundle should be up to date: {}",
+        String::from_utf8_lossy(&up_to_date.stderr)
+    );
+
+    fs::write(setup_clone.join("upstream.txt"), "new\n")?;
+    for args in [
+        vec!["add", "."],
+        vec!["commit", "-m", "Upstream change"],
+        vec!["push", "origin", "main"],
+    ] {
+        let output = std::process::Command::new("git")
+            .args(&args)
+            .current_dir(&setup_clone)
+            .output()?;
+        assert!(output.status.success(), "git {:?} failed", args);
+    }
+
+    let outdated = run_fpm(&["outdated"], &design_dir)?;
+    assert!(
+        !outdated.status.success(),
+        "A new upstream commit should fail the check"
+    );
+    assert!(String::from_utf8_lossy(&outdated.stderr).contains("1 bundle(s) are outdated"));
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+/// Test that `depth` makes shallow clones that stay shallow on updates and can
+/// still check out a locked commit
+#[test]
+fn test_shallow_clone_depth() -> Result<()> {
+    check_preconditions()?;
+
+    let test_name = "shallow_clone";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    let remote_dir = test_dir.join("remote");
+    let setup_clone = test_dir.join("setup_clone");
+    let bundle_manifest = r#"fpm_version = "0.1.0"
+identifier = "fpm-bundle"
+version = "0.0.1"
+
+[bundles]
+"#;
+    setup_local_bare_repo(&remote_dir, &setup_clone, bundle_manifest)?;
+
+    let commit_upstream = |file: &str| -> Result<()> {
+        fs::write(setup_clone.join(file), "history\n")?;
+        for args in [
+            vec!["add", "."],
+            vec!["commit", "-m", "Upstream change"],
+            vec!["push", "origin", "main"],
+        ] {
+            let output = std::process::Command::new("git")
+                .args(&args)
+                .current_dir(&setup_clone)
+                .output()?;
+            assert!(output.status.success(), "git {:?} failed", args);
+        }
+        Ok(())
+    };
+    let git_output = |args: &[&str], dir: &std::path::Path| -> Result<String> {
+        let output = std::process::Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()?;
+        assert!(output.status.success(), "git {:?} failed", args);
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+
+    commit_upstream("second.txt")?;
+    let old_commit = git_output(&["rev-parse", "HEAD"], &setup_clone)?;
+    commit_upstream("third.txt")?;
+
+    create_sample_project(&test_dir.join("project"))?;
+    let design_dir = test_dir.join("project").join("src").join("design");
+
+    // Local paths ignore --depth; a file:// URL goes through the transport like a real remote
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "shallow".to_string(),
+        BundleDependency {
+            version: "*".to_string(),
+            git: format!("file://{}", remote_dir.to_str().unwrap()),
+            depth: Some(1),
+            ..Default::default()
+        },
+    );
+    create_bundle_manifest(&design_dir, Some("Shallow clone test"), None, bundles)?;
+
+    let install_output = run_fpm(&["install"], &design_dir)?;
+    assert!(
+        install_output.status.success(),
+        "Install should succeed: {}",
+        String::from_utf8_lossy(&install_output.stderr)
+    );
+
+    let bundle_path = design_dir.join(BUNDLE_DIR).join("shallow");
+    assert!(bundle_path.join("third.txt").exists());
+    assert_eq!(
+        git_output(&["rev-list", "--count", "HEAD"], &bundle_path)?,
+        "1"
+    );
+
+    // Updating fetches the new tip without deepening the clone
+    commit_upstream("fourth.txt")?;
+    let update_output = run_fpm(&["update"], &design_dir)?;
+    assert!(
+        update_output.status.success(),
+        "Update should succeed: {}",
+        String::from_utf8_lossy(&update_output.stderr)
+    );
+    assert!(bundle_path.join("fourth.txt").exists());
+    assert_eq!(
+        git_output(&["rev-parse", "--is-shallow-repository"], &bundle_path)?,
+        "true"
+    );
+    assert_eq!(
+        git_output(&["rev-list", "--count", "HEAD"], &bundle_path)?,
+        "1"
+    );
+
+    // A locked commit below the shallow boundary is fetched on demand
+    let lock_path = design_dir.join(LOCKFILE_NAME);
+    let mut lockfile = crate::lockfile::load_lockfile(&lock_path)?.expect("fpm.lock");
+    lockfile.bundles.get_mut("shallow").unwrap().commit = old_commit.clone();
+    crate::lockfile::save_lockfile(&lock_path, &lockfile)?;
+
+    let pinned_output = run_fpm(&["install"], &design_dir)?;
+    assert!(
+        pinned_output.status.success(),
+        "Install of the locked commit should succeed: {}",
+        String::from_utf8_lossy(&pinned_output.stderr)
+    );
+    assert_eq!(
+        git_output(&["rev-parse", "HEAD"], &bundle_path)?,
+        old_commit
+    );
+    assert!(!bundle_path.join("third.txt").exists());
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+/// Test that `filter` makes a partial clone that later fetches keep using
+#[test]
+fn test_partial_clone_filter() -> Result<()> {
+    check_preconditions()?;
+
+    let test_name = "partial_clone";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    let remote_dir = test_dir.join("remote");
+    let setup_clone = test_dir.join("setup_clone");
+    let bundle_manifest = r#"fpm_version = "0.1.0"
+identifier = "fpm-bundle"
+version = "0.0.1"
+
+[bundles]
+"#;
+    setup_local_bare_repo(&remote_dir, &setup_clone, bundle_manifest)?;
+
+    // Servers must opt in to serving filtered packs
+    let allow_output = std::process::Command::new("git")
+        .args(["config", "uploadpack.allowFilter", "true"])
+        .current_dir(&remote_dir)
+        .output()?;
+    assert!(allow_output.status.success());
+
+    create_sample_project(&test_dir.join("project"))?;
+    let design_dir = test_dir.join("project").join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "textures".to_string(),
+        BundleDependency {
+            version: "*".to_string(),
+            git: format!("file://{}", remote_dir.to_str().unwrap()),
+            filter: Some("blob:none".to_string()),
+            ..Default::default()
+        },
+    );
+    create_bundle_manifest(&design_dir, Some("Partial clone test"), None, bundles)?;
+
+    let install_output = run_fpm(&["install"], &design_dir)?;
+    assert!(
+        install_output.status.success(),
+        "Install should succeed: {}",
+        String::from_utf8_lossy(&install_output.stderr)
+    );
+
+    let bundle_path = design_dir.join(BUNDLE_DIR).join("textures");
+    assert!(
+        bundle_path.join("README.md").exists(),
+        "Blobs of the checked-out commit are fetched on demand"
+    );
+
+    let filter_output = std::process::Command::new("git")
+        .args(["config", "remote.origin.partialclonefilter"])
+        .current_dir(&bundle_path)
+        .output()?;
+    assert_eq!(
+        String::from_utf8_lossy(&filter_output.stdout).trim(),
+        "blob:none"
+    );
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+/// Test that the include list is applied as a sparse checkout that survives updates
+#[test]
+fn test_include_filter_sparse_checkout() -> Result<()> {
+    check_preconditions()?;
+
+    let test_name = "sparse_include";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    let remote_dir = test_dir.join("remote");
+    let setup_clone = test_dir.join("setup_clone");
+    let bundle_manifest = r#"fpm_version = "0.1.0"
+identifier = "fpm-bundle"
+version = "0.0.1"
+
+[bundles]
+"#;
+    setup_local_bare_repo(&remote_dir, &setup_clone, bundle_manifest)?;
+
+    let commit_upstream = |files: &[&str]| -> Result<()> {
+        for file in files {
+            let path = setup_clone.join(file);
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(path, "content\n")?;
+        }
+        for args in [
+            vec!["add", "."],
+            vec!["commit", "-m", "Upstream change"],
+            vec!["push", "origin", "main"],
+        ] {
+            let output = std::process::Command::new("git")
+                .args(&args)
+                .current_dir(&setup_clone)
+                .output()?;
+            assert!(output.status.success(), "git {:?} failed", args);
+        }
+        Ok(())
+    };
+    commit_upstream(&["icons/home.svg", "raw/huge.psd"])?;
+
+    create_sample_project(&test_dir.join("project"))?;
+    let design_dir = test_dir.join("project").join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "icons".to_string(),
+        BundleDependency {
+            version: "*".to_string(),
+            git: remote_dir.to_str().unwrap().to_string(),
+            include: Some(vec!["icons".to_string()]),
+            ..Default::default()
+        },
+    );
+    create_bundle_manifest(&design_dir, Some("Sparse include test"), None, bundles)?;
+
+    let install_output = run_fpm(&["install"], &design_dir)?;
+    assert!(
+        install_output.status.success(),
+        "Install should succeed: {}",
+        String::from_utf8_lossy(&install_output.stderr)
+    );
+
+    let bundle_path = design_dir.join(BUNDLE_DIR).join("icons");
+    assert!(bundle_path.join("icons").join("home.svg").exists());
+    assert!(!bundle_path.join("raw").exists());
+    assert!(!bundle_path.join("README.md").exists());
+
+    // Excluded paths aren't reported as deleted files
+    let status_output = std::process::Command::new("git")
+        .args(["status", "--porcelain"])
+        .current_dir(&bundle_path)
+        .output()?;
+    let status_stdout = String::from_utf8_lossy(&status_output.stdout);
+    assert!(
+        !status_stdout.contains("README.md") && !status_stdout.contains("raw/"),
+        "Excluded paths should not show up as changes: {}",
+        status_stdout
+    );
+
+    // Updates bring in new included files and keep excluded paths out
+    commit_upstream(&["icons/search.svg", "raw/other.psd"])?;
+    let update_output = run_fpm(&["update"], &design_dir)?;
+    assert!(
+        update_output.status.success(),
+        "Update should succeed: {}",
+        String::from_utf8_lossy(&update_output.stderr)
+    );
+    assert!(bundle_path.join("icons").join("search.svg").exists());
+    assert!(!bundle_path.join("raw").exists());
+
+    // Globs select files at any depth; the new list replaces the old one on install
+    let manifest_path = design_dir.join("bundle.toml");
+    let mut manifest = crate::config::load_manifest(&manifest_path)?;
+    manifest.bundles.get_mut("icons").unwrap().include = Some(vec!["**/*.psd".to_string()]);
+    crate::config::save_manifest(&manifest, &manifest_path)?;
+
+    let glob_output = run_fpm(&["install"], &design_dir)?;
+    assert!(
+        glob_output.status.success(),
+        "Install should succeed: {}",
+        String::from_utf8_lossy(&glob_output.stderr)
+    );
+    assert!(bundle_path.join("raw").join("huge.psd").exists());
+    assert!(bundle_path.join("raw").join("other.psd").exists());
+    assert!(!bundle_path.join("icons").exists());
+
+    // Without an include list, exclude removes paths from the full checkout
+    let mut manifest = crate::config::load_manifest(&manifest_path)?;
+    let icons = manifest.bundles.get_mut("icons").unwrap();
+    icons.include = None;
+    icons.exclude = Some(vec!["raw".to_string(), "search.svg".to_string()]);
+    crate::config::save_manifest(&manifest, &manifest_path)?;
+
+    let exclude_output = run_fpm(&["install"], &design_dir)?;
+    assert!(
+        exclude_output.status.success(),
+        "Install should succeed: {}",
+        String::from_utf8_lossy(&exclude_output.stderr)
+    );
+    assert!(bundle_path.join("README.md").exists());
+    assert!(bundle_path.join("icons").join("home.svg").exists());
+    assert!(!bundle_path.join("icons").join("search.svg").exists());
+    assert!(!bundle_path.join("raw").exists());
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+/// Test that a bundle with `path` installs only that subdirectory and pushes back into it
+#[test]
+fn test_subdirectory_bundle_install_and_push() -> Result<()> {
+    check_preconditions()?;
+
+    let test_name = "subdirectory_path";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    let remote_dir = test_dir.join("remote");
+    let setup_clone = test_dir.join("setup_clone");
+    let bundle_manifest = r#"fpm_version = "0.1.0"
+identifier = "fpm-bundle"
+version = "0.0.1"
+
+[bundles]
+"#;
+    setup_local_bare_repo(&remote_dir, &setup_clone, bundle_manifest)?;
+
+    for file in [
+        "assets/icons/home.svg",
+        "assets/icons/raw/home.psd",
+        "docs/guide.md",
+    ] {
+        let path = setup_clone.join(file);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, "content\n")?;
+    }
+    for args in [
+        vec!["add", "."],
+        vec!["commit", "-m", "Add assets"],
+        vec!["push", "origin", "main"],
+    ] {
+        let output = std::process::Command::new("git")
+            .args(&args)
+            .current_dir(&setup_clone)
+            .output()?;
+        assert!(output.status.success(), "git {:?} failed", args);
+    }
+
+    create_sample_project(&test_dir.join("project"))?;
+    let design_dir = test_dir.join("project").join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "icons".to_string(),
+        BundleDependency {
+            version: "*".to_string(),
+            git: remote_dir.to_str().unwrap().to_string(),
+            path: Some("assets/icons".into()),
+            branch: Some("main".to_string()),
+            exclude: Some(vec!["*.psd".to_string()]),
+            ..Default::default()
+        },
+    );
+    create_bundle_manifest(&design_dir, Some("Subdirectory test"), None, bundles)?;
+
+    let install_output = run_fpm(&["install"], &design_dir)?;
+    assert!(
+        install_output.status.success(),
+        "Install should succeed: {}",
+        String::from_utf8_lossy(&install_output.stderr)
+    );
+
+    // Only the subdirectory's contents end up in the bundle, minus excluded files
+    let bundle_path = design_dir.join(BUNDLE_DIR).join("icons");
+    assert!(bundle_path.join("home.svg").exists());
+    assert!(!bundle_path.join("raw").join("home.psd").exists());
+    assert!(!bundle_path.join("assets").exists());
+    assert!(!bundle_path.join("docs").exists());
+    assert!(!bundle_path.join("bundle.toml").exists());
+    assert!(!bundle_path.join(".git").exists());
+
+    let status_output = run_fpm(&["status"], &design_dir)?;
+    let status_stdout = String::from_utf8_lossy(&status_output.stdout);
+    assert!(
+        status_stdout.contains("1 synced, 0 unsynced"),
+        "Freshly installed bundle should be synced: {}",
+        status_stdout
+    );
+
+    // Edits are pushed into the subdirectory of the source repository
+    fs::write(bundle_path.join("home.svg"), "changed\n")?;
+    fs::write(bundle_path.join("search.svg"), "new\n")?;
+    configure_git_user(
+        &design_dir
+            .join(BUNDLE_DIR)
+            .join(crate::flatten::CHECKOUT_DIR)
+            .join("icons"),
+    )?;
+
+    let push_output = run_fpm(&["push", "-m", "Update icons"], &design_dir)?;
+    assert!(
+        push_output.status.success(),
+        "Push should succeed: {}",
+        String::from_utf8_lossy(&push_output.stdout)
+    );
+
+    let verify_clone = test_dir.join("verify_clone");
+    let verify_output = std::process::Command::new("git")
+        .args([
+            "clone",
+            remote_dir.to_str().unwrap(),
+            verify_clone.to_str().unwrap(),
+        ])
+        .output()?;
+    assert!(verify_output.status.success());
+
+    let icons = verify_clone.join("assets").join("icons");
+    assert_eq!(fs::read_to_string(icons.join("home.svg"))?, "changed\n");
+    assert_eq!(fs::read_to_string(icons.join("search.svg"))?, "new\n");
+    assert!(icons.join("raw").join("home.psd").exists());
+    assert!(verify_clone.join("docs").join("guide.md").exists());
+    assert!(!verify_clone.join("home.svg").exists());
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_broken_global_config_can_be_repaired_local() -> Result<()> {
+    check_preconditions()?;
+
+    let test_name = "broken_global_config_local";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+    create_bundle_manifest(&design_dir, Some("Broken config"), None, HashMap::new())?;
+
+    let config_home = test_dir.join("config");
+    fs::create_dir_all(config_home.join("fpm"))?;
+    fs::write(
+        config_home.join("fpm").join("config.toml"),
+        "jobs = \"many\"\n",
+    )?;
+    let env = [("XDG_CONFIG_HOME", config_home.to_str().unwrap())];
+
+    // Commands that need the settings stop at the broken file
+    let output = run_fpm_with_env(&["status"], &design_dir, &env)?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("config.toml"));
+
+    // doctor reports it, and config set repairs it
+    let output = run_fpm_with_env(&["doctor"], &design_dir, &env)?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("settings") && stdout.contains("Failed to parse config file"),
+        "doctor output: {}",
+        stdout
+    );
+
+    let output = run_fpm_with_env(
+        &["config", "set", "--global", "jobs", "4"],
+        &design_dir,
+        &env,
+    )?;
+    assert!(
+        output.status.success(),
+        "config set failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = run_fpm_with_env(&["config", "get", "jobs"], &design_dir, &env)?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "4");
+
+    let output = run_fpm_with_env(&["status"], &design_dir, &env)?;
+    assert!(
+        output.status.success(),
+        "status failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
@@ -0,0 +1,274 @@
+//! The fpm.lock file.
+//!
+//! Records the exact commit every bundle (including nested ones) was installed at,
+//! so later installs on other machines check out the same files.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::resolve::ResolvedRef;
+use crate::types::{
+    BundleDependency, LockedBundle, Lockfile, BUNDLE_DIR, LOCKFILE_NAME, LOCKFILE_VERSION,
+};
+
+/// Header written at the top of every lockfile
+const LOCKFILE_HEADER: &str =
+    "# This file is generated by fpm install. Do not edit it by hand.\n\n";
+
+/// Returns the lockfile location for a manifest
+pub fn lockfile_path(manifest_path: &Path) -> Result<PathBuf> {
+    let parent_dir = manifest_path.parent().context("Invalid manifest path")?;
+    Ok(parent_dir.join(LOCKFILE_NAME))
+}
+
+/// Loads a lockfile, returning None if it doesn't exist
+pub fn load_lockfile(path: &Path) -> Result<Option<Lockfile>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read lockfile: {}", path.display()))?;
+    let lockfile: Lockfile = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse lockfile: {}", path.display()))?;
+
+    if lockfile.version > LOCKFILE_VERSION {
+        anyhow::bail!(
+            "{} uses lockfile version {}, but this fpm only understands version {}. \
+            Please upgrade fpm.",
+            path.display(),
+            lockfile.version,
+            LOCKFILE_VERSION
+        );
+    }
+
+    Ok(Some(lockfile))
+}
+
+/// Saves a lockfile
+pub fn save_lockfile(path: &Path, lockfile: &Lockfile) -> Result<()> {
+    let content = toml::to_string_pretty(lockfile).context("Failed to serialize lockfile")?;
+
+    fs::write(path, format!("{}{}", LOCKFILE_HEADER, content))
+        .with_context(|| format!("Failed to write lockfile: {}", path.display()))
+}
+
+/// Key of a bundle in the lockfile: its install path below the top-level bundle directory
+pub fn lock_key(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", prefix, name)
+    }
+}
+
+/// Returns where the bundle with install path `key` is installed, e.g.
+/// `.fpm/ui-kit/.fpm/base-styles` for `ui-kit/base-styles`
+pub fn installed_path(bundle_dir: &Path, key: &str) -> PathBuf {
+    let mut segments = key.split('/');
+    let mut path = bundle_dir.join(segments.next().unwrap_or_default());
+    for segment in segments {
+        path = path.join(BUNDLE_DIR).join(segment);
+    }
+    path
+}
+
+/// Returns the lock entry for a bundle if it still matches the manifest.
+/// A changed source URL, branch, version requirement or rev invalidates the entry.
+/// Without a `branch`, any branch locked matches: it's the remote's default branch.
+pub fn locked_bundle<'a>(
+    lockfile: &'a Lockfile,
+    key: &str,
+    dependency: &BundleDependency,
+) -> Option<&'a LockedBundle> {
+    let locked = lockfile.bundles.get(key)?;
+    if locked.git != dependency.git {
+        return None;
+    }
+
+    if let Some(rev) = &dependency.rev {
+        if !locked.commit.starts_with(rev.as_str()) {
+            return None;
+        }
+    }
+
+    let matches = match dependency.version_requirement() {
+        Some(version) => locked.version.as_deref() == Some(version),
+        None => {
+            locked.version.is_none()
+                && dependency
+                    .branch
+                    .as_ref()
+                    .is_none_or(|branch| *branch == locked.branch)
+        }
+    };
+
+    matches.then_some(locked)
+}
+
+impl LockedBundle {
+    /// The ref this lock entry was resolved to
+    pub fn resolved_ref(&self) -> ResolvedRef {
+        ResolvedRef {
+            name: self.branch.clone(),
+            version: self.version.clone(),
+            yanked: false,
+        }
+    }
+}
+
+/// Builds the lock entry for a bundle installed at `commit`
+pub fn lock_entry(
+    dependency: &BundleDependency,
+    resolved: &ResolvedRef,
+    commit: String,
+) -> LockedBundle {
+    LockedBundle {
+        git: dependency.git.clone(),
+        branch: resolved.name.clone(),
+        version: resolved.version.clone(),
+        commit,
+        checksum: None,
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn dependency(branch: Option<&str>) -> BundleDependency {
+        BundleDependency {
+            version: "*".to_string(),
+            git: "https://github.com/example/ui-kit.git".to_string(),
+            branch: branch.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_lock_key_nests_with_slashes() {
+        assert_eq!(lock_key("", "ui-kit"), "ui-kit");
+        assert_eq!(lock_key("ui-kit", "base-styles"), "ui-kit/base-styles");
+    }
+
+    fn commit_of<'a>(
+        lockfile: &'a Lockfile,
+        key: &str,
+        dependency: &BundleDependency,
+    ) -> Option<&'a str> {
+        locked_bundle(lockfile, key, dependency).map(|locked| locked.commit.as_str())
+    }
+
+    #[test]
+    fn test_locked_bundle_ignores_stale_entries() {
+        let mut lockfile = Lockfile::default();
+        let tracking_main = dependency(None);
+        lockfile.bundles.insert(
+            "ui-kit".to_string(),
+            lock_entry(
+                &tracking_main,
+                &ResolvedRef::branch(&tracking_main),
+                "abc123".to_string(),
+            ),
+        );
+
+        assert_eq!(
+            commit_of(&lockfile, "ui-kit", &dependency(Some("main"))),
+            Some("abc123")
+        );
+        // Branch changed in the manifest since the lock was written
+        assert_eq!(
+            commit_of(&lockfile, "ui-kit", &dependency(Some("develop"))),
+            None
+        );
+        assert_eq!(commit_of(&lockfile, "other", &tracking_main), None);
+
+        // Without a branch, the remote's default branch was locked, whatever its name
+        let on_master = dependency(Some("master"));
+        lockfile.bundles.insert(
+            "ui-kit".to_string(),
+            lock_entry(
+                &on_master,
+                &ResolvedRef::branch(&on_master),
+                "def456".to_string(),
+            ),
+        );
+        assert_eq!(
+            commit_of(&lockfile, "ui-kit", &tracking_main),
+            Some("def456")
+        );
+        assert_eq!(
+            commit_of(&lockfile, "ui-kit", &dependency(Some("main"))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_locked_bundle_matches_version_requirement() {
+        let mut versioned = dependency(None);
+        versioned.version = "1.2.3".to_string();
+
+        let mut lockfile = Lockfile::default();
+        let resolved = ResolvedRef {
+            name: "v1.2.3".to_string(),
+            version: Some("1.2.3".to_string()),
+            yanked: false,
+        };
+        lockfile.bundles.insert(
+            "ui-kit".to_string(),
+            lock_entry(&versioned, &resolved, "abc123".to_string()),
+        );
+
+        assert_eq!(commit_of(&lockfile, "ui-kit", &versioned), Some("abc123"));
+        assert_eq!(lockfile.bundles["ui-kit"].resolved_ref(), resolved);
+
+        // The version requirement changed since the lock was written
+        versioned.version = "1.3.0".to_string();
+        assert_eq!(commit_of(&lockfile, "ui-kit", &versioned), None);
+    }
+
+    #[test]
+    fn test_locked_bundle_matches_rev() {
+        let mut pinned = dependency(None);
+        pinned.rev = Some("abc1".to_string());
+
+        let mut lockfile = Lockfile::default();
+        lockfile.bundles.insert(
+            "ui-kit".to_string(),
+            lock_entry(&pinned, &ResolvedRef::branch(&pinned), "abc123".to_string()),
+        );
+
+        // An abbreviated rev matches the full commit
+        assert_eq!(commit_of(&lockfile, "ui-kit", &pinned), Some("abc123"));
+
+        pinned.rev = Some("def456".to_string());
+        assert_eq!(commit_of(&lockfile, "ui-kit", &pinned), None);
+    }
+
+    #[test]
+    fn test_lockfile_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(LOCKFILE_NAME);
+
+        assert!(load_lockfile(&path).unwrap().is_none());
+
+        let mut lockfile = Lockfile::default();
+        let base_styles = dependency(None);
+        lockfile.bundles.insert(
+            "ui-kit/base-styles".to_string(),
+            lock_entry(
+                &base_styles,
+                &ResolvedRef::branch(&base_styles),
+                "abc123".to_string(),
+            ),
+        );
+        save_lockfile(&path, &lockfile).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# This file is generated by fpm"));
+        assert!(content.contains("[bundles.\"ui-kit/base-styles\"]"));
+        assert_eq!(load_lockfile(&path).unwrap(), Some(lockfile));
+    }
+}
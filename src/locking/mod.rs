@@ -0,0 +1,463 @@
+//! Advisory locking of a project's .fpm directory.
+//!
+//! Commands that change installed bundles take `.fpm/.lock` before they start, so
+//! two fpm runs in the same project (say an IDE task and a terminal) don't write
+//! to the same clones at once. The lock file records who holds it; a second run
+//! waits for it to be released, up to the `lock_timeout` setting, and names the
+//! holder while it waits. Locks left behind by a process that no longer runs on
+//! this host are taken over, as are lock files that stay unreadable (a run that
+//! crashed while writing one). A stale lock file is renamed out of the way before
+//! it is deleted, so of several runs finding it only one takes it over. Whether a process runs is read from /proc on Linux,
+//! and asked of `ps` on other Unix systems and of `tasklist` on Windows; where that
+//! fails, the holder is assumed to be running, and its lock file has to be deleted
+//! by hand.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::report;
+use crate::settings::Settings;
+use crate::types::BUNDLE_DIR;
+
+/// Name of the lock file inside a project's .fpm directory
+pub const LOCK_FILE: &str = ".lock";
+
+/// Seconds to wait for a lock when `lock_timeout` isn't set
+pub const DEFAULT_LOCK_TIMEOUT: u64 = 300;
+
+/// How often a waiting run checks whether the lock was released
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How long a lock file may stay unreadable before it is considered left behind by
+/// a run that crashed while writing it
+const UNREADABLE_GRACE: Duration = Duration::from_secs(5);
+
+/// Lock files this process holds
+static HELD: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+/// Numbers the names stale lock files are moved to
+static TAKEOVERS: AtomicU64 = AtomicU64::new(0);
+
+/// The process holding a lock, as recorded in the lock file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub pid: u32,
+    pub host: String,
+    /// Command line of the holding process, e.g. "fpm install"
+    pub command: String,
+    /// Unix time the lock was taken
+    pub since: u64,
+}
+
+impl LockHolder {
+    fn current() -> Self {
+        Self {
+            pid: std::process::id(),
+            host: host_name(),
+            command: command_line(),
+            since: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Describes the holder for messages, e.g. "fpm install (pid 4711 on build-01)"
+    pub fn describe(&self) -> String {
+        format!("{} (pid {} on {})", self.command, self.pid, self.host)
+    }
+
+    /// Returns true if the holder is known to have exited without releasing the lock.
+    /// A lock recorded for this process's id but not held by it was left by an
+    /// earlier process that had the same id.
+    fn is_stale(&self) -> bool {
+        self.host == host_name()
+            && (self.pid == std::process::id() || !process_is_running(self.pid))
+    }
+}
+
+/// A held project lock, released when dropped
+#[derive(Debug)]
+pub struct ProjectLock {
+    /// Lock file to remove on release; None if this process already held the lock
+    path: Option<PathBuf>,
+    /// What the lock file records, so a lock file another run replaced it with
+    /// isn't removed
+    holder: LockHolder,
+    /// The .fpm directory was created for the lock, and is removed with it if the
+    /// command left it empty
+    created_dir: bool,
+}
+
+impl Drop for ProjectLock {
+    fn drop(&mut self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if read_holder(path).as_ref() == Some(&self.holder) {
+            let _ = fs::remove_file(path);
+        }
+        HELD.lock().unwrap().remove(path);
+        if let Some(dir) = path.parent().filter(|_| self.created_dir) {
+            let _ = fs::remove_dir(dir);
+        }
+    }
+}
+
+/// Takes the lock of the project owning `manifest_path`, waiting for another fpm
+/// run to release it for up to the `lock_timeout` of `settings`. Taking a lock this
+/// process already holds succeeds right away, so commands can run other commands.
+pub fn lock_project(manifest_path: &Path, settings: &Settings) -> Result<ProjectLock> {
+    let timeout = settings.lock_timeout.unwrap_or(DEFAULT_LOCK_TIMEOUT);
+    let bundle_dir = manifest_path
+        .parent()
+        .context("Invalid manifest path")?
+        .join(BUNDLE_DIR);
+
+    acquire(&bundle_dir, Duration::from_secs(timeout))
+}
+
+/// Takes the lock of a .fpm directory, waiting up to `timeout` for its holder
+pub fn acquire(bundle_dir: &Path, timeout: Duration) -> Result<ProjectLock> {
+    let created_dir = !bundle_dir.exists();
+    fs::create_dir_all(bundle_dir).with_context(|| {
+        format!(
+            "Failed to create bundle directory: {}",
+            bundle_dir.display()
+        )
+    })?;
+    let path = fs::canonicalize(bundle_dir)
+        .with_context(|| format!("Failed to resolve {}", bundle_dir.display()))?
+        .join(LOCK_FILE);
+    let current = LockHolder::current();
+    if HELD.lock().unwrap().contains(&path) {
+        return Ok(ProjectLock {
+            path: None,
+            holder: current,
+            created_dir: false,
+        });
+    }
+    let started = Instant::now();
+    let mut announced = false;
+
+    loop {
+        if try_create(&path, &current)? {
+            HELD.lock().unwrap().insert(path.clone());
+            return Ok(ProjectLock {
+                path: Some(path),
+                holder: current,
+                created_dir,
+            });
+        }
+
+        // The holder may be taking or releasing the lock right now; a file that can't
+        // be read yet is checked again on the next round
+        let Some(holder) = read_holder(&path) else {
+            if unreadable_for(&path).is_some_and(|age| age >= UNREADABLE_GRACE) {
+                report::progress(format_args!("Removing unreadable lock {}", path.display()));
+                remove_stale(&path, None)?;
+                continue;
+            }
+            if started.elapsed() >= timeout {
+                anyhow::bail!(
+                    "Timed out after {}s waiting for {}, which can't be read. If no other \
+                    fpm run is using this project, delete the lock file.",
+                    timeout.as_secs(),
+                    path.display()
+                );
+            }
+            std::thread::sleep(POLL_INTERVAL);
+            continue;
+        };
+
+        if holder.is_stale() {
+            report::progress(format_args!(
+                "Removing stale lock {} left by {}",
+                path.display(),
+                holder.describe()
+            ));
+            remove_stale(&path, Some(&holder))?;
+            continue;
+        }
+
+        if started.elapsed() >= timeout {
+            anyhow::bail!(
+                "Timed out after {}s waiting for {}, held by {}. If that process is no \
+                longer running (fpm can't tell for processes of other hosts, or where \
+                the process list can't be read), delete the lock file.",
+                timeout.as_secs(),
+                path.display(),
+                holder.describe()
+            );
+        }
+        if !announced {
+            report::progress(format_args!(
+                "Waiting for {} to finish (lock {})",
+                holder.describe(),
+                path.display()
+            ));
+            announced = true;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Creates the lock file for `holder`. Returns false if it already exists.
+fn try_create(path: &Path, holder: &LockHolder) -> Result<bool> {
+    let mut file = match OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == ErrorKind::AlreadyExists => return Ok(false),
+        Err(error) => {
+            return Err(error).with_context(|| format!("Failed to create {}", path.display()))
+        }
+    };
+
+    let content = toml::to_string(holder).context("Failed to serialize lock holder")?;
+    if let Err(error) = file.write_all(content.as_bytes()) {
+        drop(file);
+        let _ = fs::remove_file(path);
+        return Err(error).with_context(|| format!("Failed to write {}", path.display()));
+    }
+    Ok(true)
+}
+
+/// Deletes a lock file found stale, recording `stale` (None for an unreadable one).
+/// The file is renamed to a name of this run's own first, so of several runs
+/// finding the same stale lock only one gets it. If what it got is no longer that
+/// lock (another run took over and locked in the meantime), it is put back.
+fn remove_stale(path: &Path, stale: Option<&LockHolder>) -> Result<()> {
+    let moved = path.with_file_name(format!(
+        "{}.stale-{}-{}",
+        LOCK_FILE,
+        std::process::id(),
+        TAKEOVERS.fetch_add(1, Ordering::Relaxed)
+    ));
+    match fs::rename(path, &moved) {
+        Ok(()) => {}
+        // Another run moved it first
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(error) => {
+            return Err(error).with_context(|| format!("Failed to remove {}", path.display()))
+        }
+    }
+
+    if read_holder(&moved).as_ref() != stale {
+        // Linking fails if yet another run has locked since; that lock stands then
+        let _ = fs::hard_link(&moved, path);
+    }
+    fs::remove_file(&moved).with_context(|| format!("Failed to remove {}", moved.display()))
+}
+
+/// Reads who holds a lock. Returns None if the file is gone or not written yet.
+fn read_holder(path: &Path) -> Option<LockHolder> {
+    let content = fs::read_to_string(path).ok()?;
+    toml::from_str(&content).ok()
+}
+
+/// How long ago a lock file that can't be read was last written. Returns None if
+/// the file is gone.
+fn unreadable_for(path: &Path) -> Option<Duration> {
+    let modified = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+    Some(
+        SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default(),
+    )
+}
+
+/// Name of this host, for telling locks of other machines (on shared file
+/// systems) from those of processes that died here
+fn host_name() -> String {
+    ["HOSTNAME", "COMPUTERNAME"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .chain(fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .find(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+/// The command line this process was started with, with the program's file name
+fn command_line() -> String {
+    let mut args = std::env::args();
+    let program = args
+        .next()
+        .and_then(|program| {
+            Path::new(&program)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "fpm".to_string());
+
+    std::iter::once(program)
+        .chain(args)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Returns false if no process with this id runs on this host. Where that can't be
+/// told, processes are assumed to be running.
+fn process_is_running(pid: u32) -> bool {
+    let proc_dir = Path::new("/proc");
+    if proc_dir.is_dir() {
+        return proc_dir.join(pid.to_string()).exists();
+    }
+
+    if cfg!(windows) {
+        // Lists the process as a CSV row quoting its id, or prints an info message
+        let output = std::process::Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/NH", "/FO", "CSV"])
+            .output();
+        match output {
+            Ok(output) if output.status.success() => {
+                String::from_utf8_lossy(&output.stdout).contains(&format!("\"{}\"", pid))
+            }
+            _ => true,
+        }
+    } else {
+        // macOS and the BSDs have no /proc; `ps -p` fails when the process is gone
+        match std::process::Command::new("ps")
+            .args(["-p", &pid.to_string()])
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+        {
+            Ok(status) => status.success(),
+            Err(_) => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn holder(pid: u32) -> LockHolder {
+        LockHolder {
+            pid,
+            host: host_name(),
+            command: "fpm install".to_string(),
+            since: 0,
+        }
+    }
+
+    #[test]
+    fn test_lock_is_released_on_drop() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(LOCK_FILE);
+
+        let lock = acquire(temp_dir.path(), Duration::ZERO).unwrap();
+        assert_eq!(read_holder(&path).unwrap().pid, std::process::id());
+
+        // The holding process may take the lock again
+        drop(acquire(temp_dir.path(), Duration::ZERO).unwrap());
+        assert!(path.exists());
+
+        drop(lock);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_lock_held_by_another_process_times_out() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(LOCK_FILE);
+
+        // Held by a process on another machine, which can't be checked
+        let other = LockHolder {
+            host: "build-01".to_string(),
+            ..holder(1)
+        };
+        assert!(try_create(&path, &other).unwrap());
+
+        let error = acquire(temp_dir.path(), Duration::ZERO).unwrap_err();
+        let message = error.to_string();
+        assert!(
+            message.contains("fpm install (pid 1 on build-01)"),
+            "{}",
+            message
+        );
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn test_unreadable_lock_times_out_then_is_taken_over() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(LOCK_FILE);
+
+        // Left empty by a run that crashed between creating and writing it
+        let file = fs::File::create(&path).unwrap();
+        let error = acquire(temp_dir.path(), Duration::ZERO).unwrap_err();
+        assert!(error.to_string().contains("can't be read"), "{}", error);
+
+        file.set_modified(SystemTime::now() - UNREADABLE_GRACE * 2)
+            .unwrap();
+        drop(file);
+        let _lock = acquire(temp_dir.path(), Duration::ZERO).unwrap();
+        assert_eq!(read_holder(&path).unwrap().pid, std::process::id());
+    }
+
+    #[test]
+    fn test_lock_recorded_for_this_process_id_is_taken_over() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(LOCK_FILE);
+
+        // Left by an earlier process with the same id, such as pid 1 in a container
+        let earlier = LockHolder {
+            command: "fpm update".to_string(),
+            ..holder(std::process::id())
+        };
+        assert!(try_create(&path, &earlier).unwrap());
+
+        let _lock = acquire(temp_dir.path(), Duration::ZERO).unwrap();
+        assert_eq!(read_holder(&path).unwrap().command, command_line());
+    }
+
+    #[test]
+    fn test_stale_lock_is_only_removed_while_unchanged() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(LOCK_FILE);
+        let stale = holder(u32::MAX);
+
+        // Another run took over the stale lock and holds a fresh one
+        let fresh = holder(7);
+        assert!(try_create(&path, &fresh).unwrap());
+        remove_stale(&path, Some(&stale)).unwrap();
+        assert_eq!(read_holder(&path), Some(fresh));
+
+        fs::remove_file(&path).unwrap();
+        assert!(try_create(&path, &stale).unwrap());
+        remove_stale(&path, Some(&stale)).unwrap();
+        assert!(!path.exists());
+
+        // Nothing else is left behind
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_process_is_running() {
+        assert!(process_is_running(std::process::id()));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_stale_lock_is_taken_over() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(LOCK_FILE);
+
+        // Process ids are below 2^22 on Linux
+        assert!(try_create(&path, &holder(u32::MAX)).unwrap());
+
+        let _lock = acquire(temp_dir.path(), Duration::ZERO).unwrap();
+        assert_eq!(read_holder(&path).unwrap().pid, std::process::id());
+    }
+}
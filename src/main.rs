@@ -1,27 +1,347 @@
 use anyhow::Result;
-use clap::Parser;
+use clap::{CommandFactory, Parser};
+use clap_complete::CompleteEnv;
+use colored::Colorize;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use fpm::cli::{Cli, Commands};
-use fpm::commands::{install, publish, push, status};
+use fpm::cli::{CacheCommand, Cli, Commands, ConfigCommand};
+use fpm::commands::{
+    add, adopt, cache, commit, completions, config, diff, doctor, env, fetch, graph, info, install,
+    licenses, link, log, login, maintain, outdated, publish, push, remove, run, search,
+    self_update, sign, status, tree, update, validate, verify, why, yank,
+};
+use fpm::discover;
+use fpm::report;
+use fpm::settings::{self, ColorMode, Settings};
+use fpm::ssh;
+use fpm::types::BumpLevel;
 
 fn main() -> Result<()> {
+    // ssh runs fpm as its SSH_ASKPASS program to get the passphrase of a key
+    if let Some(passphrase) = ssh::askpass_reply() {
+        println!("{}", passphrase);
+        return Ok(());
+    }
+
+    // The script of `fpm completions` runs fpm with COMPLETE set to get completions
+    CompleteEnv::with_factory(Cli::command)
+        .var(completions::COMPLETE_ENV)
+        .complete();
+
+    let cli = Cli::parse();
+    let manifest_path = cli.manifest_path();
+
+    let mut config = match settings::load_settings(&manifest_path) {
+        Ok(config) => config,
+        // These commands repair or diagnose the settings, so broken settings files
+        // mustn't stop them
+        Err(error)
+            if matches!(
+                cli.command,
+                Commands::Config { .. } | Commands::Doctor | Commands::Completions { .. }
+            ) =>
+        {
+            eprintln!("{} {:#}", "Warning:".yellow(), error);
+            Settings::default()
+        }
+        Err(error) => return Err(error),
+    };
+    config.merge(cli.settings());
+    match config.color.unwrap_or_default() {
+        ColorMode::Auto => {}
+        ColorMode::Always => colored::control::set_override(true),
+        ColorMode::Never => colored::control::set_override(false),
+    }
+    report::set_output(config.output);
+
+    // Keep stdout to the JSON document when results are printed as JSON
+    let log_writer = if report::is_json() {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
         )
-        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_subscriber::fmt::layer().with_writer(log_writer))
         .init();
-
-    let cli = Cli::parse();
+    let jobs = |jobs: Option<usize>| jobs.or(config.jobs).unwrap_or(install::DEFAULT_JOBS);
 
     match cli.command {
-        Commands::Install => install::execute(&cli.manifest_path)?,
-        Commands::Publish => publish::execute(&cli.manifest_path)?,
-        Commands::Push { bundle, message } => {
-            push::execute(&cli.manifest_path, bundle.as_deref(), message.as_deref())?
+        Commands::Install {
+            bundle,
+            frozen,
+            jobs: jobs_flag,
+            keep_going,
+            force,
+            refresh,
+            autostash,
+            groups,
+            exclude_groups,
+            with,
+            all,
+            dedupe,
+            allow_duplicates,
+            no_hooks,
+        } => {
+            let options = install::InstallOptions {
+                frozen,
+                only: bundle,
+                jobs: jobs(jobs_flag),
+                keep_going,
+                force,
+                refresh,
+                autostash,
+                groups,
+                exclude_groups,
+                with,
+                dedupe,
+                allow_duplicates,
+                no_hooks,
+                settings: config.clone(),
+                ..Default::default()
+            };
+            if all {
+                discover::for_each_manifest(&std::env::current_dir()?, |manifest_path| {
+                    install::execute(manifest_path, &options)
+                })?
+            } else {
+                install::execute(&manifest_path, &options)?
+            }
+        }
+        Commands::Add {
+            name,
+            git,
+            version,
+            branch,
+            path,
+            ssh_key,
+            install,
+        } => {
+            let options = add::AddOptions {
+                version: version.as_deref(),
+                branch: branch.as_deref(),
+                path: path.as_deref(),
+                ssh_key: ssh_key.as_deref(),
+                install,
+                settings: config.clone(),
+            };
+            add::execute(&manifest_path, &name, git.as_deref(), &options)?
+        }
+        Commands::Search { term } => search::execute(&term, &config)?,
+        Commands::Remove { name, force } => remove::execute(&manifest_path, &name, force, &config)?,
+        Commands::Link {
+            path,
+            bundle,
+            force,
+        } => link::link(
+            &manifest_path,
+            path.as_deref(),
+            bundle.as_deref(),
+            force,
+            &config,
+        )?,
+        Commands::Unlink { bundle } => link::unlink(&manifest_path, bundle.as_deref(), &config)?,
+        Commands::Ci {
+            jobs: jobs_flag,
+            keep_going,
+            groups,
+            exclude_groups,
+            with,
+            dedupe,
+            allow_duplicates,
+            no_hooks,
+        } => install::execute(
+            &manifest_path,
+            &install::InstallOptions {
+                frozen: true,
+                jobs: jobs(jobs_flag),
+                keep_going,
+                groups,
+                exclude_groups,
+                with,
+                dedupe,
+                allow_duplicates,
+                no_hooks,
+                settings: config.clone(),
+                ..Default::default()
+            },
+        )?,
+        Commands::Update {
+            bundle,
+            jobs: jobs_flag,
+            keep_going,
+            force,
+            autostash,
+            dedupe,
+            allow_duplicates,
+            no_hooks,
+        } => update::execute(
+            &manifest_path,
+            bundle.as_deref(),
+            &update::UpdateOptions {
+                jobs: jobs(jobs_flag),
+                keep_going,
+                force,
+                autostash,
+                dedupe,
+                allow_duplicates,
+                no_hooks,
+                settings: config.clone(),
+            },
+        )?,
+        Commands::Outdated => outdated::execute(&manifest_path, &config)?,
+        Commands::Fetch { jobs: jobs_flag } => {
+            fetch::execute(&manifest_path, jobs(jobs_flag), &config)?
+        }
+        Commands::Publish {
+            oci,
+            force,
+            create_remote,
+            private,
+        } => publish::execute(
+            &manifest_path,
+            &publish::PublishOptions {
+                oci,
+                force,
+                create_remote,
+                private,
+                settings: config.clone(),
+            },
+        )?,
+        Commands::Yank { version, reason } => {
+            yank::execute(&manifest_path, &version, reason.as_deref(), &config)?
+        }
+        Commands::Commit {
+            bundle,
+            message,
+            bump,
+            no_bump,
+            paths,
+        } => commit::execute(
+            &manifest_path,
+            &push::PushOptions {
+                bundle,
+                message,
+                bump: if no_bump { Some(BumpLevel::Off) } else { bump },
+                paths,
+                settings: config.clone(),
+                ..Default::default()
+            },
+        )?,
+        Commands::Push {
+            bundle,
+            message,
+            dry_run,
+            bump,
+            no_bump,
+            branch,
+            pr,
+            interactive,
+            paths,
+        } => push::execute(
+            &manifest_path,
+            &push::PushOptions {
+                bundle,
+                message,
+                dry_run,
+                bump: if no_bump { Some(BumpLevel::Off) } else { bump },
+                branch,
+                pull_request: pr,
+                interactive,
+                commit_only: false,
+                paths,
+                settings: config.clone(),
+            },
+        )?,
+        Commands::Status {
+            json,
+            check,
+            remote,
+            depth,
+            bundle,
+            jobs: jobs_flag,
+            all,
+        } => {
+            let options = status::StatusOptions {
+                json,
+                check,
+                remote,
+                depth,
+                bundle,
+                jobs: jobs(jobs_flag),
+                settings: config.clone(),
+            };
+            if all {
+                discover::for_each_manifest(&std::env::current_dir()?, |manifest_path| {
+                    status::execute(manifest_path, &options)
+                })?
+            } else {
+                status::execute(&manifest_path, &options)?
+            }
+        }
+        Commands::Tree => tree::execute(&manifest_path, &config)?,
+        Commands::Why { bundle } => why::execute(&manifest_path, &bundle)?,
+        Commands::Info { bundle } => info::execute(&manifest_path, &bundle, &config)?,
+        Commands::Graph { format, output } => {
+            graph::execute(&manifest_path, format, output.as_deref())?
+        }
+        Commands::Verify => verify::execute(&manifest_path)?,
+        Commands::Doctor => doctor::execute(&manifest_path, &config)?,
+        Commands::Validate { online } => validate::execute(&manifest_path, online, &config)?,
+        Commands::Sign { key } => sign::execute(&manifest_path, key.as_deref(), &config)?,
+        Commands::Licenses { json, deny } => {
+            licenses::execute(&manifest_path, &licenses::LicensesOptions { json, deny })?
+        }
+        Commands::Diff {
+            bundle,
+            remote,
+            patch,
+        } => diff::execute(&manifest_path, bundle.as_deref(), remote, patch, &config)?,
+        Commands::Log { bundle, count } => log::execute(&manifest_path, &bundle, count, &config)?,
+        Commands::Maintain { no_cache } => maintain::execute(&manifest_path, !no_cache, &config)?,
+        Commands::Login { host, ssh_key } => login::login(host.as_deref(), ssh_key.as_deref())?,
+        Commands::Logout { host, ssh_key } => login::logout(host.as_deref(), ssh_key.as_deref())?,
+        Commands::Adopt {
+            dir,
+            remote,
+            create_remote,
+            private,
+            description,
+        } => {
+            let options = adopt::AdoptOptions {
+                remote: &remote,
+                create_remote,
+                private,
+                description: description.as_deref(),
+                settings: config.clone(),
+            };
+            adopt::execute(&manifest_path, &dir, &options)?
+        }
+        Commands::Cache { command } => match command {
+            CacheCommand::Stats => cache::stats(&config)?,
+            CacheCommand::Clean => cache::clean(&config)?,
+            CacheCommand::Gc { max_age, max_size } => {
+                cache::gc(&cache::GcOptions { max_age, max_size }, &config)?
+            }
+        },
+        Commands::Config { command } => match command {
+            ConfigCommand::Get { key, global } => config::get(&key, global, &config)?,
+            ConfigCommand::Set { key, value, global } => {
+                config::set(&manifest_path, &key, &value, global)?
+            }
+            ConfigCommand::List { global } => config::list(global, &config)?,
+        },
+        Commands::Env { format, output } => {
+            env::execute(&manifest_path, format, output.as_deref())?
+        }
+        Commands::Run { name, args } => run::execute(&manifest_path, name.as_deref(), &args)?,
+        Commands::SelfUpdate { version, check } => {
+            self_update::execute(version.as_deref(), check, &config)?
         }
-        Commands::Status => status::execute(&cli.manifest_path)?,
+        Commands::Completions { shell } => completions::execute(shell)?,
     }
 
     Ok(())
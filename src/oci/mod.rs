@@ -0,0 +1,568 @@
+//! Bundles stored as artifacts in an OCI registry (`oci`), such as GHCR or an
+//! internal registry.
+//!
+//! A bundle is an OCI image manifest with an empty config and a single
+//! `tar+gzip` layer holding its files. Installing downloads every layer, checks
+//! it against its digest and unpacks it; publishing uploads the layer and tags
+//! the manifest. Credentials are read from `FPM_OCI_USERNAME` and
+//! `FPM_OCI_PASSWORD`; without them registries are accessed anonymously.
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+use tracing::info;
+
+use crate::archive::{
+    install_unpacked, is_sha256, save_hashed, sha256_hex, unpack, with_work_dir, ArchiveFormat,
+};
+use crate::types::BUNDLE_DIR;
+
+/// Artifact type of the manifests fpm publishes
+pub const BUNDLE_ARTIFACT_TYPE: &str = "application/vnd.fpm.bundle.v1";
+
+const MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+const EMPTY_CONFIG_MEDIA_TYPE: &str = "application/vnd.oci.empty.v1+json";
+const LAYER_MEDIA_TYPE: &str = "application/vnd.oci.image.layer.v1.tar+gzip";
+
+/// Environment variables holding registry credentials
+const USERNAME_VAR: &str = "FPM_OCI_USERNAME";
+const PASSWORD_VAR: &str = "FPM_OCI_PASSWORD";
+
+/// A parsed `registry/repository:tag` or `registry/repository@sha256:<digest>` reference
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OciReference {
+    pub registry: String,
+    pub repository: String,
+    /// The tag, or the digest (`sha256:<hex>`) for pinned references
+    pub reference: String,
+}
+
+impl OciReference {
+    /// Parses a reference. The registry host is required; a missing tag means "latest".
+    pub fn parse(value: &str) -> Result<Self> {
+        let (registry, rest) = value.split_once('/').with_context(|| {
+            format!(
+                "Invalid OCI reference '{}': expected registry/repository:tag",
+                value
+            )
+        })?;
+        if !(registry.contains('.') || registry.contains(':') || registry == "localhost") {
+            anyhow::bail!(
+                "Invalid OCI reference '{}': it must start with a registry host, e.g. ghcr.io/{}",
+                value,
+                value
+            );
+        }
+
+        let (repository, reference) = if let Some((repository, digest)) = rest.split_once('@') {
+            let valid = digest.strip_prefix("sha256:").is_some_and(is_sha256);
+            if !valid {
+                anyhow::bail!(
+                    "Invalid OCI reference '{}': the digest must be sha256:<64 hex digits>",
+                    value
+                );
+            }
+            (repository, digest.to_lowercase())
+        } else {
+            // A ':' after the last '/' starts the tag
+            match rest.rsplit_once(':') {
+                Some((repository, tag)) if !tag.contains('/') => (repository, tag.to_string()),
+                _ => (rest, "latest".to_string()),
+            }
+        };
+
+        if repository.is_empty() || reference.is_empty() {
+            anyhow::bail!(
+                "Invalid OCI reference '{}': expected registry/repository:tag",
+                value
+            );
+        }
+
+        Ok(OciReference {
+            registry: registry.to_string(),
+            repository: repository.to_string(),
+            reference,
+        })
+    }
+
+    /// Returns the pinned digest, if the reference is one
+    pub fn digest(&self) -> Option<&str> {
+        self.reference
+            .starts_with("sha256:")
+            .then_some(self.reference.as_str())
+    }
+}
+
+impl fmt::Display for OciReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = if self.digest().is_some() { '@' } else { ':' };
+        write!(
+            f,
+            "{}/{}{}{}",
+            self.registry, self.repository, separator, self.reference
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImageManifest {
+    schema_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    media_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    artifact_type: Option<String>,
+    config: Descriptor,
+    layers: Vec<Descriptor>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Descriptor {
+    media_type: String,
+    digest: String,
+    size: u64,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    annotations: BTreeMap<String, String>,
+}
+
+impl Descriptor {
+    fn of(media_type: &str, content: &[u8]) -> Self {
+        Descriptor {
+            media_type: media_type.to_string(),
+            digest: digest_of(content),
+            size: content.len() as u64,
+            annotations: BTreeMap::new(),
+        }
+    }
+}
+
+/// Downloads the artifact `reference` points at and copies its files (or those of
+/// `subdirectory` inside it) into `target`. `work_dir` is a scratch directory,
+/// removed afterwards. Returns the digest of the artifact's manifest.
+pub fn pull_bundle(
+    reference: &OciReference,
+    subdirectory: Option<&Path>,
+    work_dir: &Path,
+    target: &Path,
+) -> Result<String> {
+    with_work_dir(work_dir, |work_dir| {
+        let mut registry = Registry::new(reference);
+        let manifest_bytes = registry
+            .get_manifest(&reference.reference)?
+            .with_context(|| format!("{} not found in the registry", reference))?;
+
+        let digest = digest_of(&manifest_bytes);
+        if let Some(expected) = reference.digest() {
+            if digest != expected {
+                anyhow::bail!(
+                    "Digest mismatch for {}: the registry returned {}",
+                    reference,
+                    digest
+                );
+            }
+        }
+
+        let manifest: ImageManifest = serde_json::from_slice(&manifest_bytes)
+            .with_context(|| format!("{} is not an OCI image manifest", reference))?;
+        if manifest.layers.is_empty() {
+            anyhow::bail!("{} has no layers", reference);
+        }
+
+        let unpacked = work_dir.join("unpacked");
+        for (index, layer) in manifest.layers.iter().enumerate() {
+            let format = layer_format(&layer.media_type).with_context(|| {
+                format!(
+                    "{} has a layer of unsupported type '{}'",
+                    reference, layer.media_type
+                )
+            })?;
+
+            let layer_path = work_dir.join(format!("layer-{}", index));
+            let checksum = registry.download_blob(&layer.digest, &layer_path)?;
+            if format!("sha256:{}", checksum) != layer.digest {
+                anyhow::bail!(
+                    "Digest mismatch for layer {} of {}: got sha256:{}",
+                    layer.digest,
+                    reference,
+                    checksum
+                );
+            }
+            unpack(&layer_path, format, &unpacked)?;
+        }
+
+        install_unpacked(&unpacked, subdirectory, &reference.to_string(), target)?;
+        Ok(digest)
+    })
+}
+
+/// Uploads the files in `dir` (without .git and .fpm) as an artifact and tags it
+/// with `reference`. Returns the digest of the uploaded manifest.
+pub fn push_bundle(reference: &OciReference, dir: &Path) -> Result<String> {
+    if reference.digest().is_some() {
+        anyhow::bail!(
+            "Cannot publish to {}: use a tag rather than a digest",
+            reference
+        );
+    }
+
+    let layer_bytes = pack_layer(dir)?;
+    let config_bytes = b"{}";
+
+    let mut registry = Registry::new(reference);
+    registry.upload_blob(&layer_bytes)?;
+    registry.upload_blob(config_bytes)?;
+
+    let mut layer = Descriptor::of(LAYER_MEDIA_TYPE, &layer_bytes);
+    layer.annotations.insert(
+        "org.opencontainers.image.title".to_string(),
+        "bundle.tar.gz".to_string(),
+    );
+    let manifest = ImageManifest {
+        schema_version: 2,
+        media_type: Some(MANIFEST_MEDIA_TYPE.to_string()),
+        artifact_type: Some(BUNDLE_ARTIFACT_TYPE.to_string()),
+        config: Descriptor::of(EMPTY_CONFIG_MEDIA_TYPE, config_bytes),
+        layers: vec![layer],
+    };
+    let manifest_bytes = serde_json::to_vec(&manifest)?;
+
+    registry.put_manifest(&reference.reference, &manifest_bytes)?;
+    Ok(digest_of(&manifest_bytes))
+}
+
+/// Packs the files in `dir` into a gzipped tarball, leaving out the .git and .fpm
+/// directories at its top
+fn pack_layer(dir: &Path) -> Result<Vec<u8>> {
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Default::default()));
+    builder.follow_symlinks(false);
+
+    for entry in walkdir::WalkDir::new(dir)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            let relative = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+            let first = relative.components().next().map(|c| c.as_os_str());
+            first != Some(".git".as_ref()) && first != Some(BUNDLE_DIR.as_ref())
+        })
+    {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(dir)?;
+        builder
+            .append_path_with_name(entry.path(), relative)
+            .with_context(|| format!("Failed to pack {}", entry.path().display()))?;
+    }
+
+    Ok(builder.into_inner()?.finish()?)
+}
+
+fn layer_format(media_type: &str) -> Option<ArchiveFormat> {
+    match media_type {
+        "application/vnd.oci.image.layer.v1.tar+gzip"
+        | "application/vnd.docker.image.rootfs.diff.tar.gzip" => Some(ArchiveFormat::TarGz),
+        "application/vnd.oci.image.layer.v1.tar" => Some(ArchiveFormat::Tar),
+        _ => None,
+    }
+}
+
+fn digest_of(content: &[u8]) -> String {
+    format!("sha256:{}", sha256_hex(content))
+}
+
+/// A repository in a registry, speaking the OCI distribution API
+struct Registry {
+    agent: ureq::Agent,
+    base_url: String,
+    repository: String,
+    authorization: Option<String>,
+}
+
+impl Registry {
+    fn new(reference: &OciReference) -> Self {
+        // Registries on this machine usually run without TLS
+        let host = reference.registry.split(':').next().unwrap_or_default();
+        let scheme = if matches!(host, "localhost" | "127.0.0.1") {
+            "http"
+        } else {
+            "https"
+        };
+
+        Registry {
+            agent: ureq::agent(),
+            base_url: format!(
+                "{}://{}/v2/{}",
+                scheme, reference.registry, reference.repository
+            ),
+            repository: reference.repository.clone(),
+            authorization: None,
+        }
+    }
+
+    fn get_manifest(&mut self, reference: &str) -> Result<Option<Vec<u8>>> {
+        let url = format!("{}/manifests/{}", self.base_url, reference);
+        let accept = [("Accept", MANIFEST_MEDIA_TYPE)];
+        let Some(response) = self.send("GET", &url, &accept, None)? else {
+            return Ok(None);
+        };
+
+        let mut bytes = Vec::new();
+        response.into_reader().read_to_end(&mut bytes)?;
+        Ok(Some(bytes))
+    }
+
+    fn put_manifest(&mut self, reference: &str, manifest: &[u8]) -> Result<()> {
+        let url = format!("{}/manifests/{}", self.base_url, reference);
+        let content_type = [("Content-Type", MANIFEST_MEDIA_TYPE)];
+        self.send("PUT", &url, &content_type, Some(manifest))?
+            .with_context(|| format!("Repository {} not found", self.repository))?;
+        Ok(())
+    }
+
+    /// Downloads a blob to `destination`, returning the SHA-256 checksum (hex) of its content
+    fn download_blob(&mut self, digest: &str, destination: &Path) -> Result<String> {
+        info!("Downloading blob {}", digest);
+
+        let url = format!("{}/blobs/{}", self.base_url, digest);
+        let response = self
+            .send("GET", &url, &[], None)?
+            .with_context(|| format!("Blob {} not found in {}", digest, self.repository))?;
+        save_hashed(response.into_reader(), destination)
+            .with_context(|| format!("Failed to download blob {}", digest))
+    }
+
+    /// Uploads a blob unless the registry already has it
+    fn upload_blob(&mut self, content: &[u8]) -> Result<()> {
+        let digest = digest_of(content);
+        let blob_url = format!("{}/blobs/{}", self.base_url, digest);
+        if self.send("HEAD", &blob_url, &[], None)?.is_some() {
+            return Ok(());
+        }
+
+        info!("Uploading blob {}", digest);
+        let uploads_url = format!("{}/blobs/uploads/", self.base_url);
+        let response = self
+            .send("POST", &uploads_url, &[], Some(&[]))?
+            .with_context(|| format!("Repository {} not found", self.repository))?;
+        let location = response
+            .header("Location")
+            .context("The registry did not return an upload location")?;
+
+        // The location may be relative to the registry
+        let location = url::Url::parse(&self.base_url)?.join(location)?;
+        let separator = if location.query().is_some() { '&' } else { '?' };
+        let upload_url = format!("{}{}digest={}", location, separator, digest);
+
+        let content_type = [("Content-Type", "application/octet-stream")];
+        self.send("PUT", &upload_url, &content_type, Some(content))?
+            .context("The registry rejected the upload")?;
+        Ok(())
+    }
+
+    /// Sends a request, authenticating when the registry asks to. Returns None for 404.
+    fn send(
+        &mut self,
+        method: &str,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: Option<&[u8]>,
+    ) -> Result<Option<ureq::Response>> {
+        let mut authenticated = false;
+
+        loop {
+            let mut request = self.agent.request(method, url);
+            for (name, value) in headers {
+                request = request.set(name, value);
+            }
+            if let Some(authorization) = &self.authorization {
+                request = request.set("Authorization", authorization);
+            }
+
+            let result = match body {
+                Some(body) => request.send_bytes(body),
+                None => request.call(),
+            };
+
+            match result {
+                Ok(response) => return Ok(Some(response)),
+                Err(ureq::Error::Status(401, response)) if !authenticated => {
+                    let challenge = response
+                        .header("WWW-Authenticate")
+                        .unwrap_or_default()
+                        .to_string();
+                    self.authorization = Some(self.authenticate(&challenge)?);
+                    authenticated = true;
+                }
+                Err(ureq::Error::Status(404, _)) => return Ok(None),
+                Err(ureq::Error::Status(401 | 403, _)) => anyhow::bail!(
+                    "Access to {} was denied; set {} and {} to the registry credentials",
+                    self.repository,
+                    USERNAME_VAR,
+                    PASSWORD_VAR
+                ),
+                Err(ureq::Error::Status(code, response)) => anyhow::bail!(
+                    "{} {} failed with HTTP {}: {}",
+                    method,
+                    url,
+                    code,
+                    response.into_string().unwrap_or_default().trim()
+                ),
+                Err(error) => {
+                    return Err(error).with_context(|| format!("Failed to reach {}", url))
+                }
+            }
+        }
+    }
+
+    /// Answers a `WWW-Authenticate` challenge with the value of an Authorization header
+    fn authenticate(&self, challenge: &str) -> Result<String> {
+        let basic = credentials().map(|(username, password)| {
+            let encoded = base64::engine::general_purpose::STANDARD
+                .encode(format!("{}:{}", username, password));
+            format!("Basic {}", encoded)
+        });
+
+        let (scheme, params) = parse_challenge(challenge);
+        if !scheme.eq_ignore_ascii_case("bearer") {
+            return basic.with_context(|| {
+                format!(
+                    "The registry requires credentials in {} and {}",
+                    USERNAME_VAR, PASSWORD_VAR
+                )
+            });
+        }
+
+        let realm = params
+            .get("realm")
+            .context("The registry's authentication challenge has no realm")?;
+        let mut request = self.agent.get(realm);
+        for param in ["service", "scope"] {
+            if let Some(value) = params.get(param) {
+                request = request.query(param, value);
+            }
+        }
+        if let Some(basic) = &basic {
+            request = request.set("Authorization", basic);
+        }
+
+        #[derive(Deserialize)]
+        struct TokenResponse {
+            token: Option<String>,
+            access_token: Option<String>,
+        }
+
+        let response: TokenResponse = request
+            .call()
+            .with_context(|| format!("Failed to get a registry token from {}", realm))?
+            .into_json()
+            .context("Invalid registry token response")?;
+        let token = response
+            .token
+            .or(response.access_token)
+            .context("The registry did not return a token")?;
+
+        Ok(format!("Bearer {}", token))
+    }
+}
+
+fn credentials() -> Option<(String, String)> {
+    let username = std::env::var(USERNAME_VAR).ok().filter(|u| !u.is_empty())?;
+    let password = std::env::var(PASSWORD_VAR).unwrap_or_default();
+    Some((username, password))
+}
+
+/// Splits a `WWW-Authenticate` header into its scheme and parameters, e.g.
+/// `Bearer realm="https://ghcr.io/token",scope="repository:org/x:pull,push"`
+fn parse_challenge(challenge: &str) -> (String, BTreeMap<String, String>) {
+    let challenge = challenge.trim();
+    let (scheme, rest) = challenge.split_once(' ').unwrap_or((challenge, ""));
+
+    let mut params = BTreeMap::new();
+    let mut rest = rest.trim_start();
+    while let Some((key, after)) = rest.split_once('=') {
+        let key = key.trim().to_lowercase();
+        let (value, remaining) = if let Some(quoted) = after.strip_prefix('"') {
+            quoted.split_once('"').unwrap_or((quoted, ""))
+        } else {
+            after.split_once(',').unwrap_or((after, ""))
+        };
+        params.insert(key, value.to_string());
+        rest = remaining.trim_start_matches([',', ' ']);
+    }
+
+    (scheme.to_string(), params)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_reference() {
+        let reference = OciReference::parse("ghcr.io/org/bundle:1.2.0").unwrap();
+        assert_eq!(reference.registry, "ghcr.io");
+        assert_eq!(reference.repository, "org/bundle");
+        assert_eq!(reference.reference, "1.2.0");
+        assert_eq!(reference.digest(), None);
+        assert_eq!(reference.to_string(), "ghcr.io/org/bundle:1.2.0");
+
+        let local = OciReference::parse("localhost:5000/assets").unwrap();
+        assert_eq!(local.registry, "localhost:5000");
+        assert_eq!(local.repository, "assets");
+        assert_eq!(local.reference, "latest");
+
+        let digest = format!("sha256:{}", "a".repeat(64));
+        let pinned = OciReference::parse(&format!("registry.example.com/a/b@{}", digest)).unwrap();
+        assert_eq!(pinned.digest(), Some(digest.as_str()));
+        assert_eq!(
+            pinned.to_string(),
+            format!("registry.example.com/a/b@{}", digest)
+        );
+
+        assert!(OciReference::parse("org/bundle:1.0.0").is_err());
+        assert!(OciReference::parse("ghcr.io").is_err());
+        assert!(OciReference::parse("ghcr.io/org/bundle@sha256:abc").is_err());
+    }
+
+    #[test]
+    fn test_parse_challenge() {
+        let (scheme, params) = parse_challenge(
+            r#"Bearer realm="https://ghcr.io/token",service="ghcr.io",scope="repository:org/x:pull,push""#,
+        );
+        assert_eq!(scheme, "Bearer");
+        assert_eq!(params["realm"], "https://ghcr.io/token");
+        assert_eq!(params["service"], "ghcr.io");
+        assert_eq!(params["scope"], "repository:org/x:pull,push");
+
+        let (scheme, params) = parse_challenge(r#"Basic realm="Registry""#);
+        assert_eq!(scheme, "Basic");
+        assert_eq!(params["realm"], "Registry");
+    }
+
+    #[test]
+    fn test_pack_layer_skips_managed_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("bundle");
+        fs::create_dir_all(dir.join("icons")).unwrap();
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        fs::create_dir_all(dir.join(BUNDLE_DIR).join("nested")).unwrap();
+        fs::write(dir.join("icons").join("home.svg"), "<svg/>").unwrap();
+        fs::write(dir.join(".git").join("HEAD"), "ref").unwrap();
+
+        let layer = temp_dir.path().join("layer.tar.gz");
+        fs::write(&layer, pack_layer(&dir).unwrap()).unwrap();
+        let unpacked = temp_dir.path().join("unpacked");
+        unpack(&layer, ArchiveFormat::TarGz, &unpacked).unwrap();
+
+        assert!(unpacked.join("icons").join("home.svg").exists());
+        assert!(!unpacked.join(".git").exists());
+        assert!(!unpacked.join(BUNDLE_DIR).exists());
+    }
+}
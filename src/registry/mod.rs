@@ -0,0 +1,226 @@
+//! Registry index of bundles.
+//!
+//! A registry is a git repository with an `index.toml` at its root that maps
+//! bundle names to the repositories they are published to, like cargo's index
+//! maps crate names to their releases. `fpm search` searches it, and `fpm add`
+//! looks up bundles given by name only.
+
+use anyhow::{Context, Result};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tracing::warn;
+
+use crate::cache::registry_index_path;
+use crate::git::{CloneOptions, GitOperations};
+use crate::settings::Settings;
+use crate::types::DEFAULT_BRANCH;
+
+/// File at the root of a registry repository listing its bundles
+pub const INDEX_FILE: &str = "index.toml";
+
+/// Contents of a registry's index.toml
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RegistryIndex {
+    /// Bundles keyed by name
+    #[serde(default)]
+    pub bundles: BTreeMap<String, IndexEntry>,
+}
+
+/// A bundle listed in a registry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// Git repository URL (or shorthand) the bundle is published to
+    pub git: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    /// Published versions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub versions: Vec<String>,
+}
+
+impl IndexEntry {
+    /// The highest published version that isn't a pre-release, or else the last one
+    /// listed
+    pub fn latest_version(&self) -> Option<&str> {
+        self.versions
+            .iter()
+            .filter_map(|version| Some((Version::parse(version).ok()?, version)))
+            .filter(|(parsed, _)| parsed.pre.is_empty())
+            .max_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, version)| version.as_str())
+            .or(self.versions.last().map(String::as_str))
+    }
+}
+
+impl RegistryIndex {
+    /// Bundles whose name, description or keywords contain `term` (ignoring case).
+    /// A bundle named exactly `term` comes first, then those whose name contains it,
+    /// each group sorted by name.
+    pub fn search(&self, term: &str) -> Vec<(&str, &IndexEntry)> {
+        let term = term.trim().to_lowercase();
+        let mut matches: Vec<(u8, &str, &IndexEntry)> = self
+            .bundles
+            .iter()
+            .filter_map(|(name, entry)| {
+                let lowercase = name.to_lowercase();
+                let rank = if lowercase == term {
+                    0
+                } else if lowercase.contains(&term) {
+                    1
+                } else if entry
+                    .description
+                    .as_ref()
+                    .is_some_and(|description| description.to_lowercase().contains(&term))
+                    || entry
+                        .keywords
+                        .iter()
+                        .any(|keyword| keyword.to_lowercase().contains(&term))
+                {
+                    2
+                } else {
+                    return None;
+                };
+                Some((rank, name.as_str(), entry))
+            })
+            .collect();
+        matches.sort_by_key(|(rank, name, _)| (*rank, *name));
+
+        matches
+            .into_iter()
+            .map(|(_, name, entry)| (name, entry))
+            .collect()
+    }
+}
+
+/// Parses the contents of an index.toml
+pub fn parse_index(content: &str) -> Result<RegistryIndex> {
+    let index: RegistryIndex = toml::from_str(content).context("Failed to parse index.toml")?;
+
+    for (name, entry) in &index.bundles {
+        if entry.git.trim().is_empty() {
+            anyhow::bail!("Invalid registry index: bundle '{}' has no git URL", name);
+        }
+    }
+
+    Ok(index)
+}
+
+/// Returns the URL of the registry `settings` configure
+pub fn registry_url(settings: &Settings) -> Result<&str> {
+    settings.registry.as_deref().context(
+        "No registry configured. Set one with 'fpm config set registry <git URL>' \
+        or the FPM_REGISTRY environment variable.",
+    )
+}
+
+/// Fetches the index of the configured registry into the cache and loads it
+pub fn load_registry(git_ops: &dyn GitOperations, settings: &Settings) -> Result<RegistryIndex> {
+    let url = registry_url(settings)?;
+    fetch_index(git_ops, url, &registry_index_path(settings, url)?)
+}
+
+/// Brings the checkout of the registry index at `url` in `checkout` up to date and
+/// loads its index.toml. When the registry can't be reached, the index fetched
+/// last time is used.
+pub fn fetch_index(
+    git_ops: &dyn GitOperations,
+    url: &str,
+    checkout: &Path,
+) -> Result<RegistryIndex> {
+    let branch = match git_ops.remote_default_branch(url, None) {
+        Ok(Some(branch)) => branch,
+        _ => DEFAULT_BRANCH.to_string(),
+    };
+
+    let updated = if git_ops.is_repository(checkout) {
+        git_ops.fetch_repository(checkout, &branch, Some(1))
+    } else {
+        // Left over from an interrupted clone
+        if checkout.exists() {
+            fs::remove_dir_all(checkout)
+                .with_context(|| format!("Failed to remove {}", checkout.display()))?;
+        }
+        let options = CloneOptions {
+            branch: &branch,
+            depth: Some(1),
+            ..Default::default()
+        };
+        git_ops.clone_repository(url, checkout, &options)
+    };
+
+    if let Err(error) = updated {
+        if !git_ops.is_repository(checkout) {
+            return Err(error.context(format!("Failed to fetch the registry index {}", url)));
+        }
+        warn!(
+            "Failed to update the registry index {}, using the copy fetched before: {:#}",
+            url, error
+        );
+    }
+
+    let path = checkout.join(INDEX_FILE);
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Registry {} has no {}", url, INDEX_FILE))?;
+    parse_index(&content).with_context(|| format!("Invalid index of registry {}", url))
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn index() -> RegistryIndex {
+        parse_index(
+            r#"
+            [bundles.icons]
+            git = "github:example/icons"
+            description = "Icon set of the design system"
+            keywords = ["svg"]
+            versions = ["1.0.0", "1.10.0", "1.2.0", "2.0.0-beta.1"]
+
+            [bundles.brand-icons]
+            git = "github:example/brand-icons"
+            description = "Logos"
+
+            [bundles.fonts]
+            git = "github:example/fonts"
+            description = "Web fonts, icons excluded"
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_search_ranks_name_matches_first() {
+        let index = index();
+        let names = |term: &str| -> Vec<&str> {
+            index
+                .search(term)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect()
+        };
+
+        assert_eq!(names("ICONS"), vec!["icons", "brand-icons", "fonts"]);
+        assert_eq!(names("svg"), vec!["icons"]);
+        assert_eq!(names("logos"), vec!["brand-icons"]);
+        assert!(names("sounds").is_empty());
+    }
+
+    #[test]
+    fn test_latest_version_skips_prereleases() {
+        let index = index();
+        assert_eq!(index.bundles["icons"].latest_version(), Some("1.10.0"));
+        assert_eq!(index.bundles["fonts"].latest_version(), None);
+    }
+
+    #[test]
+    fn test_parse_index_requires_git_urls() {
+        let error = parse_index("[bundles.icons]\ngit = \"\"\n").unwrap_err();
+        assert!(error.to_string().contains("icons"));
+    }
+}
@@ -0,0 +1,209 @@
+//! Results of install and push for CI pipelines and wrapper tools. With
+//! `--output json` these commands print one JSON document with a record per
+//! bundle instead of progress lines.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::settings::OutputFormat;
+
+/// What a command did with one bundle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BundleAction {
+    /// Installed for the first time, or again after its files went missing
+    Fetched,
+    /// Moved to another commit
+    Updated,
+    /// Changes committed and pushed
+    Pushed,
+    /// Changes committed with `fpm commit`, but not pushed
+    Committed,
+    /// Left as it was: already at the commit, nothing to push or can't be pushed
+    Skipped,
+    /// Failed; the record's error says why
+    Failed,
+}
+
+/// Result of one bundle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BundleResult {
+    /// Install path of the bundle, e.g. `ui-kit/base-styles` for a nested bundle
+    pub name: String,
+    pub action: BundleAction,
+    /// Commit before the command ran
+    pub old_commit: Option<String>,
+    /// Commit after the command ran
+    pub new_commit: Option<String>,
+    /// Error message of a failed bundle, or why a bundle was skipped when it
+    /// wasn't simply up to date
+    pub error: Option<String>,
+}
+
+/// Results collected while a command runs, possibly from several threads
+#[derive(Debug, Default)]
+pub struct BundleResults {
+    results: Mutex<Vec<BundleResult>>,
+}
+
+impl BundleResults {
+    pub fn record(&self, result: BundleResult) {
+        self.results.lock().unwrap().push(result);
+    }
+
+    /// Records a result in place of the one recorded for the same bundle
+    pub fn replace(&self, result: BundleResult) {
+        let mut results = self.results.lock().unwrap();
+        results.retain(|existing| existing.name != result.name);
+        results.push(result);
+    }
+
+    /// Returns true if a result was recorded for the bundle
+    pub fn contains(&self, name: &str) -> bool {
+        self.results
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|result| result.name == name)
+    }
+
+    /// The recorded results, sorted by bundle name
+    pub fn into_sorted(self) -> Vec<BundleResult> {
+        let mut results = self.results.into_inner().unwrap();
+        results.sort_by(|a, b| a.name.cmp(&b.name));
+        results
+    }
+}
+
+/// Document printed by a command run with `--output json`
+#[derive(Serialize)]
+struct Report<'a> {
+    command: &'a str,
+    manifest: String,
+    success: bool,
+    bundles: Vec<BundleResult>,
+    error: Option<String>,
+}
+
+/// Whether results are printed as JSON; set once the settings of the command are
+/// known, like the color override
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Chooses how the commands of this process report their results
+pub fn set_output(output: Option<OutputFormat>) {
+    JSON.store(output == Some(OutputFormat::Json), Ordering::Relaxed);
+}
+
+/// Returns true if results are printed as JSON rather than progress lines
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Prints a progress line, unless results are printed as JSON
+pub fn progress(line: std::fmt::Arguments) {
+    if !is_json() {
+        println!("{}", line);
+    }
+}
+
+/// Renders the JSON document of a command. `error` is the error the command
+/// failed with, if any; the document reports success only without one and
+/// without failed bundles.
+pub fn render(
+    command: &str,
+    manifest_path: &Path,
+    results: BundleResults,
+    error: Option<&anyhow::Error>,
+) -> Result<String> {
+    let manifest_path = if manifest_path.is_relative() {
+        std::env::current_dir()?.join(manifest_path)
+    } else {
+        manifest_path.to_path_buf()
+    };
+
+    let bundles = results.into_sorted();
+    let report = Report {
+        command,
+        manifest: manifest_path.to_string_lossy().to_string(),
+        success: error.is_none()
+            && bundles
+                .iter()
+                .all(|result| result.action != BundleAction::Failed),
+        bundles,
+        error: error.map(|error| format!("{:#}", error)),
+    };
+    serde_json::to_string_pretty(&report).context("Failed to serialize the results")
+}
+
+/// Prints the JSON document of a command
+pub fn print(
+    command: &str,
+    manifest_path: &Path,
+    results: BundleResults,
+    error: Option<&anyhow::Error>,
+) -> Result<()> {
+    println!("{}", render(command, manifest_path, results, error)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let results = BundleResults::default();
+        results.record(BundleResult {
+            name: "logos".to_string(),
+            action: BundleAction::Failed,
+            old_commit: Some("abc123".to_string()),
+            new_commit: None,
+            error: Some("Repository not found".to_string()),
+        });
+        results.record(BundleResult {
+            name: "icons".to_string(),
+            action: BundleAction::Updated,
+            old_commit: Some("abc123".to_string()),
+            new_commit: Some("def456".to_string()),
+            error: None,
+        });
+
+        let error = anyhow::anyhow!("1 bundle(s) failed to install");
+        let json = render(
+            "install",
+            Path::new("/work/bundle.toml"),
+            results,
+            Some(&error),
+        )
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "command": "install",
+                "manifest": "/work/bundle.toml",
+                "success": false,
+                "bundles": [
+                    {
+                        "name": "icons",
+                        "action": "updated",
+                        "old_commit": "abc123",
+                        "new_commit": "def456",
+                        "error": null
+                    },
+                    {
+                        "name": "logos",
+                        "action": "failed",
+                        "old_commit": "abc123",
+                        "new_commit": null,
+                        "error": "Repository not found"
+                    }
+                ],
+                "error": "1 bundle(s) failed to install"
+            })
+        );
+    }
+}
@@ -0,0 +1,391 @@
+//! Resolution of a dependency's requested branch or version to a git ref.
+
+use anyhow::{Context, Result};
+use semver::{Comparator, Op, Prerelease, Version, VersionReq};
+use tracing::debug;
+
+use crate::git::GitOperations;
+use crate::types::{BundleDependency, DEFAULT_BRANCH};
+
+/// A dependency's branch or version resolved to a ref git can check out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedRef {
+    /// Branch or tag name
+    pub name: String,
+    /// Version requirement the tag was resolved from (None when tracking a branch)
+    pub version: Option<String>,
+    /// The tag's version was yanked by the bundle's author (see `fpm yank`)
+    pub yanked: bool,
+}
+
+impl ResolvedRef {
+    /// A ref tracking the dependency's branch
+    pub fn branch(dependency: &BundleDependency) -> Self {
+        Self {
+            name: dependency.branch().to_string(),
+            version: None,
+            yanked: false,
+        }
+    }
+}
+
+/// Resolves the ref to install for a dependency. Versions are looked up among the
+/// remote's tags, which requires network access; branches are used as they are.
+///
+/// A plain version (`1.2.0`) selects exactly that tag, while a requirement such as
+/// `^1.2`, `~1.2.0` or `>=1.0, <2.0` selects the highest matching tag. Pre-release
+/// tags are only selected when asked for, see [`select_tag`].
+pub fn resolve_ref(
+    git_ops: &dyn GitOperations,
+    dependency: &BundleDependency,
+) -> Result<ResolvedRef> {
+    let Some(version) = dependency.version_requirement() else {
+        return Ok(ResolvedRef {
+            name: tracked_branch(git_ops, dependency),
+            version: None,
+            yanked: false,
+        });
+    };
+
+    let tags = git_ops
+        .list_remote_tags(&dependency.git, dependency.ssh_key.as_deref())
+        .with_context(|| {
+            format!(
+                "Failed to resolve version {} of {}",
+                version, dependency.git
+            )
+        })?;
+
+    let tag = select_tag(&tags, version, dependency.allow_prerelease).with_context(|| {
+        format!(
+            "Failed to resolve version {} of {}",
+            version, dependency.git
+        )
+    })?;
+
+    Ok(ResolvedRef {
+        yanked: tags.contains(&yank_tag(&tag)),
+        name: tag,
+        version: Some(version.to_string()),
+    })
+}
+
+/// Name of the tag marking the version tagged `tag` as yanked
+/// (`v1.2.0` -> `yanked/v1.2.0`)
+pub fn yank_tag(tag: &str) -> String {
+    format!("yanked/{}", tag)
+}
+
+/// The branch a dependency tracks: its `branch`, or else the default branch of its
+/// remote, which is `main` when the remote can't be asked
+pub fn tracked_branch(git_ops: &dyn GitOperations, dependency: &BundleDependency) -> String {
+    if let Some(branch) = &dependency.branch {
+        return branch.clone();
+    }
+
+    match git_ops.remote_default_branch(&dependency.git, dependency.ssh_key.as_deref()) {
+        Ok(Some(branch)) => branch,
+        Ok(None) => DEFAULT_BRANCH.to_string(),
+        Err(error) => {
+            debug!(
+                "Failed to look up the default branch of {}: {:#}",
+                dependency.git, error
+            );
+            DEFAULT_BRANCH.to_string()
+        }
+    }
+}
+
+/// Selects the tag for a version or version requirement.
+///
+/// Pre-releases (`v1.2.0-beta.1`) are skipped unless the version opts in: a plain
+/// pre-release version such as `1.2.0-beta` selects its own tag, or else the
+/// highest tag of that channel (`v1.2.0-beta.3`), and `allow_prerelease` lets
+/// requirements match them.
+pub fn select_tag(tags: &[String], version: &str, allow_prerelease: bool) -> Result<String> {
+    if let Ok(exact) = Version::parse(version) {
+        let channel = find_version_tag(tags, version).or_else(|| {
+            (!exact.pre.is_empty())
+                .then(|| find_highest_tag(tags, |candidate| in_channel(candidate, &exact)))
+                .flatten()
+        });
+        return channel.with_context(|| {
+            format!(
+                "Version {} not found: no tag 'v{}' or '{}'. \
+                Set `branch` to track a branch instead, or `version = \"*\"` for the default branch.",
+                version, version, version
+            )
+        });
+    }
+
+    let requirement = VersionReq::parse(version)
+        .with_context(|| format!("Invalid version requirement '{}'", version))?;
+
+    find_highest_matching_tag(tags, &requirement, allow_prerelease).with_context(|| {
+        format!(
+            "No tag matches version requirement {}. Available versions: {}",
+            version,
+            describe_versions(tags)
+        )
+    })
+}
+
+/// Finds the tag for an exact version, preferring the `v`-prefixed spelling
+fn find_version_tag(tags: &[String], version: &str) -> Option<String> {
+    let prefixed = format!("v{}", version);
+
+    tags.iter()
+        .find(|tag| **tag == prefixed)
+        .or_else(|| tags.iter().find(|tag| *tag == version))
+        .cloned()
+}
+
+/// Finds the tag with the highest version matching a requirement.
+/// Tags that aren't semantic versions are ignored.
+fn find_highest_matching_tag(
+    tags: &[String],
+    requirement: &VersionReq,
+    allow_prerelease: bool,
+) -> Option<String> {
+    find_highest_tag(tags, |version| {
+        requirement_matches(requirement, version, allow_prerelease)
+    })
+}
+
+/// Finds the tag with the highest version accepted by `accept`
+fn find_highest_tag(tags: &[String], accept: impl Fn(&Version) -> bool) -> Option<String> {
+    tags.iter()
+        .filter_map(|tag| tag_version(tag).map(|version| (version, tag)))
+        .filter(|(version, _)| accept(version))
+        // On equal versions prefer the `v`-prefixed tag, as for exact versions
+        .max_by(|(a, a_tag), (b, b_tag)| {
+            a.cmp(b)
+                .then_with(|| a_tag.starts_with('v').cmp(&b_tag.starts_with('v')))
+        })
+        .map(|(_, tag)| tag.clone())
+}
+
+/// Finds the tag with the highest released version, or the highest of any version
+/// with `allow_prerelease`
+pub fn latest_tag(tags: &[String], allow_prerelease: bool) -> Option<String> {
+    find_highest_matching_tag(tags, &VersionReq::STAR, allow_prerelease)
+}
+
+/// Checks if a bundle's `version` satisfies the version or requirement a manifest
+/// asks for. A plain version must match exactly, like when selecting a tag, or be
+/// in its channel for a pre-release. None when either can't be parsed.
+pub fn version_matches(version: &str, requirement: &str, allow_prerelease: bool) -> Option<bool> {
+    let version = tag_version(version.trim())?;
+    if let Ok(exact) = Version::parse(requirement) {
+        return Some(version == exact || (!exact.pre.is_empty() && in_channel(&version, &exact)));
+    }
+
+    let requirement = VersionReq::parse(requirement).ok()?;
+    Some(requirement_matches(
+        &requirement,
+        &version,
+        allow_prerelease,
+    ))
+}
+
+/// Checks a version against a requirement. Semver only lets pre-releases match
+/// requirements naming a pre-release of the same `major.minor.patch`; with
+/// `allow_prerelease` they are compared like any other version.
+fn requirement_matches(
+    requirement: &VersionReq,
+    version: &Version,
+    allow_prerelease: bool,
+) -> bool {
+    if !allow_prerelease || version.pre.is_empty() {
+        return requirement.matches(version);
+    }
+
+    // Naming the lowest pre-release of the version's own `major.minor.patch` lets
+    // the version through semver's pre-release check without excluding anything
+    let mut opted_in = requirement.clone();
+    opted_in.comparators.push(Comparator {
+        op: Op::GreaterEq,
+        major: version.major,
+        minor: Some(version.minor),
+        patch: Some(version.patch),
+        pre: Prerelease::new("0").expect("0 is a valid pre-release"),
+    });
+    opted_in.matches(version)
+}
+
+/// Checks if `version` is a pre-release of the channel `channel` names: the same
+/// `major.minor.patch` with pre-release identifiers starting with the channel's
+/// (`1.2.0-beta.2` is in `1.2.0-beta`, `1.2.0-rc.1` is not)
+fn in_channel(version: &Version, channel: &Version) -> bool {
+    let identifiers = |pre: &Prerelease| {
+        pre.as_str()
+            .split('.')
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+
+    (version.major, version.minor, version.patch) == (channel.major, channel.minor, channel.patch)
+        && !version.pre.is_empty()
+        && identifiers(&version.pre).starts_with(&identifiers(&channel.pre))
+}
+
+/// Parses a tag name (`v1.2.3` or `1.2.3`) as a semantic version
+fn tag_version(tag: &str) -> Option<Version> {
+    Version::parse(tag.strip_prefix('v').unwrap_or(tag)).ok()
+}
+
+/// Lists the versions found among tags, for error messages
+fn describe_versions(tags: &[String]) -> String {
+    let mut versions: Vec<Version> = tags.iter().filter_map(|tag| tag_version(tag)).collect();
+    if versions.is_empty() {
+        return "none".to_string();
+    }
+
+    versions.sort();
+    versions.dedup();
+    versions
+        .iter()
+        .map(Version::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn tags(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_find_version_tag_accepts_both_spellings() {
+        assert_eq!(
+            find_version_tag(&tags(&["v1.0.0", "v1.2.3"]), "1.2.3"),
+            Some("v1.2.3".to_string())
+        );
+        assert_eq!(
+            find_version_tag(&tags(&["1.2.3"]), "1.2.3"),
+            Some("1.2.3".to_string())
+        );
+        assert_eq!(
+            find_version_tag(&tags(&["1.2.3", "v1.2.3"]), "1.2.3"),
+            Some("v1.2.3".to_string())
+        );
+        assert_eq!(find_version_tag(&tags(&["v1.2.30"]), "1.2.3"), None);
+    }
+
+    #[test]
+    fn test_select_tag_picks_highest_match() {
+        let available = tags(&[
+            "v1.1.0",
+            "v1.2.0",
+            "v1.2.5",
+            "v1.3.0-beta.1",
+            "1.4.0",
+            "v2.0.0",
+            "latest",
+        ]);
+
+        assert_eq!(select_tag(&available, "^1.2", false).unwrap(), "1.4.0");
+        assert_eq!(select_tag(&available, "~1.2.0", false).unwrap(), "v1.2.5");
+        assert_eq!(
+            select_tag(&available, ">=1.0, <1.2", false).unwrap(),
+            "v1.1.0"
+        );
+        assert_eq!(select_tag(&available, ">=1.0", false).unwrap(), "v2.0.0");
+        // Plain versions stay exact
+        assert_eq!(select_tag(&available, "1.2.0", false).unwrap(), "v1.2.0");
+
+        assert_eq!(latest_tag(&available, false).as_deref(), Some("v2.0.0"));
+        assert_eq!(latest_tag(&tags(&["latest", "v3.0.0-rc.1"]), false), None);
+    }
+
+    #[test]
+    fn test_select_tag_reports_available_versions() {
+        let available = tags(&["v1.0.0", "v1.1.0"]);
+
+        let error = select_tag(&available, "^2.0", false)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("1.0.0, 1.1.0"), "Got: {}", error);
+
+        assert!(select_tag(&available, "not a version", false).is_err());
+        assert!(select_tag(&available, "1.2.0", false).is_err());
+    }
+
+    #[test]
+    fn test_version_matches() {
+        assert_eq!(version_matches("1.2.0", "1.2.0", false), Some(true));
+        assert_eq!(version_matches("v1.2.0", "1.2.0", false), Some(true));
+        // A plain version is exact, not a caret requirement
+        assert_eq!(version_matches("1.3.0", "1.2.0", false), Some(false));
+        assert_eq!(version_matches("1.3.0", "^1.2", false), Some(true));
+        assert_eq!(version_matches("2.0.0", "^1.2", false), Some(false));
+        assert_eq!(version_matches("latest", "^1.2", false), None);
+        assert_eq!(version_matches("1.2.0", "not a version", false), None);
+    }
+
+    #[test]
+    fn test_select_tag_skips_prereleases_unless_asked() {
+        let available = tags(&[
+            "v1.1.0",
+            "v1.2.0-alpha.1",
+            "v1.2.0-beta.1",
+            "v1.2.0-beta.2",
+            "v1.2.0-rc.1",
+            "v1.3.0-beta.1",
+        ]);
+
+        assert_eq!(select_tag(&available, "^1.1", false).unwrap(), "v1.1.0");
+        assert_eq!(
+            select_tag(&available, "^1.1", true).unwrap(),
+            "v1.3.0-beta.1"
+        );
+        // A pre-release version selects its tag, or else the highest of its channel
+        assert_eq!(
+            select_tag(&available, "1.2.0-beta.1", false).unwrap(),
+            "v1.2.0-beta.1"
+        );
+        assert_eq!(
+            select_tag(&available, "1.2.0-beta", false).unwrap(),
+            "v1.2.0-beta.2"
+        );
+        assert_eq!(
+            select_tag(&available, "1.2.0-rc", false).unwrap(),
+            "v1.2.0-rc.1"
+        );
+        assert!(select_tag(&available, "1.2.0-gamma", false).is_err());
+        // Semver lets requirements naming a pre-release match its version's pre-releases
+        assert_eq!(
+            select_tag(&available, "^1.2.0-beta", false).unwrap(),
+            "v1.2.0-rc.1"
+        );
+        // Pre-releases stay below their release
+        assert_eq!(
+            select_tag(&tags(&["v1.2.0-beta.1", "v1.1.0"]), ">=1.2.0", true).ok(),
+            None
+        );
+
+        assert_eq!(latest_tag(&available, false).as_deref(), Some("v1.1.0"));
+        assert_eq!(
+            latest_tag(&available, true).as_deref(),
+            Some("v1.3.0-beta.1")
+        );
+    }
+
+    #[test]
+    fn test_version_matches_prereleases() {
+        assert_eq!(
+            version_matches("1.2.0-beta.2", "1.2.0-beta", false),
+            Some(true)
+        );
+        assert_eq!(
+            version_matches("1.2.0-rc.1", "1.2.0-beta", false),
+            Some(false)
+        );
+        assert_eq!(version_matches("1.3.0-beta.1", "^1.2", false), Some(false));
+        assert_eq!(version_matches("1.3.0-beta.1", "^1.2", true), Some(true));
+        assert_eq!(version_matches("2.0.0-beta.1", "^1.2", true), Some(false));
+    }
+}
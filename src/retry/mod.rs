@@ -0,0 +1,240 @@
+//! Retries for network operations that fail with transient errors, such as a
+//! DNS lookup or TLS handshake that fails once and works a moment later.
+
+use anyhow::Result;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::settings::Settings;
+
+/// Retries after the first attempt when the `retries` setting is not set
+pub const DEFAULT_RETRIES: u32 = 2;
+
+/// Wait before the first retry when the `retry_backoff` setting is not set
+pub const DEFAULT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest wait between two attempts
+pub const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Parts of error messages (lowercase) that mark an error as transient: failures
+/// to reach or talk to the remote, as opposed to missing repositories, rejected
+/// credentials or pushes
+const TRANSIENT_ERRORS: &[&str] = &[
+    "could not resolve",
+    "failed to resolve address",
+    "temporary failure in name resolution",
+    "timed out",
+    "timeout",
+    "connection reset",
+    "connection refused",
+    "connection closed",
+    "failed to connect",
+    "network is unreachable",
+    "unexpected disconnect",
+    "the remote end hung up",
+    "early eof",
+    "rpc failed",
+    "failed to send request",
+    "ssl_connect",
+    "ssl_read",
+    "ssl error",
+    "tls handshake",
+    "tls connection",
+    "gnutls_handshake",
+    "gnutls recv error",
+    "http 502",
+    "http 503",
+    "http 504",
+    "returned error: 502",
+    "returned error: 503",
+    "returned error: 504",
+];
+
+/// How often and how fast failed operations are retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt
+    pub retries: u32,
+    /// Wait before the first retry, doubled for every later one
+    pub backoff: Duration,
+    /// Randomize each wait between half and all of its length
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: DEFAULT_RETRIES,
+            backoff: DEFAULT_BACKOFF,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy from the `retries`, `retry_backoff` and `retry_jitter` settings
+    pub fn from_settings(settings: &Settings) -> Self {
+        let default = Self::default();
+        Self {
+            retries: settings.retries.unwrap_or(default.retries),
+            backoff: settings
+                .retry_backoff
+                .map(Duration::from_millis)
+                .unwrap_or(default.backoff),
+            jitter: settings.retry_jitter.unwrap_or(default.jitter),
+        }
+    }
+
+    /// Wait before retry number `retry` (starting at 1)
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        let delay = self.backoff.saturating_mul(factor).min(MAX_BACKOFF);
+        if !self.jitter {
+            return delay;
+        }
+
+        // Anywhere between half and all of the delay
+        let random = RandomState::new().build_hasher().finish();
+        delay / 2 + delay.mul_f64((random % 1000) as f64 / 2000.0)
+    }
+
+    /// Runs `operation`, retrying it while it fails with a transient error. When
+    /// every attempt fails, the error lists the failure of each attempt.
+    pub fn run<T>(&self, description: &str, mut operation: impl FnMut() -> Result<T>) -> Result<T> {
+        let mut failures = Vec::new();
+        loop {
+            let error = match operation() {
+                Ok(value) => return Ok(value),
+                Err(error) => error,
+            };
+            let attempt = failures.len() as u32 + 1;
+
+            if !is_transient(&error) {
+                if failures.is_empty() {
+                    return Err(error);
+                }
+                failures.push(format!("{:#}", error));
+                break;
+            }
+            failures.push(format!("{:#}", error));
+            if attempt > self.retries {
+                break;
+            }
+
+            let delay = self.delay(attempt);
+            warn!(
+                "{} failed (attempt {} of {}), retrying in {:.1}s: {:#}",
+                description,
+                attempt,
+                self.retries + 1,
+                delay.as_secs_f64(),
+                error
+            );
+            std::thread::sleep(delay);
+        }
+
+        let attempts: Vec<String> = failures
+            .iter()
+            .enumerate()
+            .map(|(i, failure)| format!("  attempt {}: {}", i + 1, failure.trim_end()))
+            .collect();
+        anyhow::bail!(
+            "{} failed after {} attempts:\n{}",
+            description,
+            failures.len(),
+            attempts.join("\n")
+        )
+    }
+}
+
+/// Checks if an error looks transient, so the operation may succeed when retried
+pub fn is_transient(error: &anyhow::Error) -> bool {
+    let message = format!("{:#}", error).to_lowercase();
+    TRANSIENT_ERRORS
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    fn policy(retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            retries,
+            backoff: Duration::ZERO,
+            jitter: false,
+        }
+    }
+
+    #[test]
+    fn test_run_retries_transient_errors() {
+        let mut calls = 0;
+        let result = policy(2).run("Clone", || {
+            calls += 1;
+            if calls < 3 {
+                anyhow::bail!("fatal: unable to access: Could not resolve host: github.com");
+            }
+            Ok(calls)
+        });
+
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[test]
+    fn test_run_reports_every_attempt() {
+        let mut calls = 0;
+        let error = policy(1)
+            .run(
+                "Clone of https://github.com/example/icons.git",
+                || -> Result<()> {
+                    calls += 1;
+                    anyhow::bail!("gnutls_handshake() failed (call {})", calls)
+                },
+            )
+            .unwrap_err();
+
+        assert_eq!(calls, 2);
+        assert_eq!(
+            error.to_string(),
+            "Clone of https://github.com/example/icons.git failed after 2 attempts:\n  \
+            attempt 1: gnutls_handshake() failed (call 1)\n  \
+            attempt 2: gnutls_handshake() failed (call 2)"
+        );
+    }
+
+    #[test]
+    fn test_run_does_not_retry_permanent_errors() {
+        let mut calls = 0;
+        let error = policy(3)
+            .run("Clone", || -> Result<()> {
+                calls += 1;
+                anyhow::bail!("remote: Repository not found.")
+            })
+            .unwrap_err();
+
+        assert_eq!(calls, 1);
+        assert_eq!(error.to_string(), "remote: Repository not found.");
+    }
+
+    #[test]
+    fn test_delay_doubles_up_to_the_maximum() {
+        let policy = RetryPolicy {
+            retries: 10,
+            backoff: Duration::from_secs(1),
+            jitter: false,
+        };
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+        assert_eq!(policy.delay(3), Duration::from_secs(4));
+        assert_eq!(policy.delay(10), MAX_BACKOFF);
+
+        let jittered = RetryPolicy {
+            jitter: true,
+            ..policy
+        }
+        .delay(3);
+        assert!(jittered >= Duration::from_secs(2) && jittered <= Duration::from_secs(4));
+    }
+}
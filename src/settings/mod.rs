@@ -0,0 +1,715 @@
+//! User and project settings.
+//!
+//! Settings are layered: the global `~/.config/fpm/config.toml`, then the
+//! project's `.fpm/config.toml`, then `FPM_*` environment variables and finally
+//! command line flags. Each layer overrides the values set by the ones before it.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::cache::{home_dir, CACHE_DIR_ENV};
+use crate::shorthand::GitProtocol;
+use crate::types::{BumpLevel, BUNDLE_DIR};
+
+/// Name of the settings file, both globally and inside a project
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Environment variable overriding `git_backend`
+pub const GIT_BACKEND_ENV: &str = "FPM_GIT_BACKEND";
+
+/// Environment variable overriding `ssh_key`
+pub const SSH_KEY_ENV: &str = "FPM_SSH_KEY";
+
+/// Environment variable overriding `depth`
+pub const DEPTH_ENV: &str = "FPM_DEPTH";
+
+/// Environment variable overriding `protocol`
+pub const PROTOCOL_ENV: &str = "FPM_PROTOCOL";
+
+/// Environment variable overriding `color`
+pub const COLOR_ENV: &str = "FPM_COLOR";
+
+/// Environment variable overriding `output`
+pub const OUTPUT_ENV: &str = "FPM_OUTPUT";
+
+/// Environment variable overriding `jobs`
+pub const JOBS_ENV: &str = "FPM_JOBS";
+
+/// Environment variable overriding `retries`
+pub const RETRIES_ENV: &str = "FPM_RETRIES";
+
+/// Environment variable overriding `retry_backoff`
+pub const RETRY_BACKOFF_ENV: &str = "FPM_RETRY_BACKOFF";
+
+/// Environment variable overriding `retry_jitter`
+pub const RETRY_JITTER_ENV: &str = "FPM_RETRY_JITTER";
+
+/// Environment variable overriding `connect_timeout`
+pub const CONNECT_TIMEOUT_ENV: &str = "FPM_CONNECT_TIMEOUT";
+
+/// Environment variable overriding `timeout`
+pub const TIMEOUT_ENV: &str = "FPM_TIMEOUT";
+
+/// Environment variable overriding `lock_timeout`
+pub const LOCK_TIMEOUT_ENV: &str = "FPM_LOCK_TIMEOUT";
+
+/// Environment variable overriding `signing_key`
+pub const SIGNING_KEY_ENV: &str = "FPM_SIGNING_KEY";
+
+/// Environment variable overriding `author`
+pub const AUTHOR_ENV: &str = "FPM_AUTHOR";
+
+/// Environment variable overriding `bump`
+pub const BUMP_ENV: &str = "FPM_BUMP";
+
+/// Environment variable overriding `signatures`
+pub const SIGNATURES_ENV: &str = "FPM_SIGNATURES";
+
+/// Environment variable overriding `registry`
+pub const REGISTRY_ENV: &str = "FPM_REGISTRY";
+
+/// Environment variable overriding `bundle_hooks`
+pub const BUNDLE_HOOKS_ENV: &str = "FPM_BUNDLE_HOOKS";
+
+/// Implementation used for git operations
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum GitBackend {
+    /// The git command line, using the user's git configuration
+    #[default]
+    Cli,
+    /// The built-in libgit2
+    Git2,
+}
+
+/// When to color the output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Color when writing to a terminal
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// How install and push report their results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Progress lines for people
+    #[default]
+    Text,
+    /// A JSON document with a result record per bundle
+    Json,
+}
+
+/// How the commits fpm creates are signed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SigningFormat {
+    /// OpenPGP signatures made with gpg
+    Gpg,
+    /// SSH signatures made with ssh-keygen
+    Ssh,
+}
+
+/// The key signing the commits fpm creates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitSigning {
+    pub format: SigningFormat,
+    /// A GPG key ID, or the path of an SSH key (`~` expanded)
+    pub key: String,
+}
+
+/// Whether installs run the hooks of installed bundles' own manifests
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum HookPolicy {
+    /// Bundle hooks are skipped with a note
+    #[default]
+    Never,
+    /// Each bundle hook runs once confirmed on the terminal; skipped without one
+    Ask,
+    /// Bundle hooks run without asking
+    Always,
+}
+
+/// What an install does with bundles whose signature doesn't verify
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SignaturePolicy {
+    /// Signatures aren't checked
+    Off,
+    /// Bundles that are unsigned or fail verification are installed with a warning
+    Warn,
+    /// Bundles that are unsigned or fail verification fail the install
+    Require,
+}
+
+/// Settings loaded from the config files, the environment and the command line
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    /// Git implementation, the git command line by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_backend: Option<GitBackend>,
+
+    /// SSH private key used for repositories whose bundles don't set their own
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ssh_key: Option<PathBuf>,
+
+    /// Clone depth for bundles whose bundle and manifest don't set one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<u32>,
+
+    /// Protocol for shorthand sources in manifests that don't set one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<GitProtocol>,
+
+    /// Location of the global cache
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// When to color the output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<ColorMode>,
+
+    /// How install and push report their results
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<OutputFormat>,
+
+    /// Number of bundles fetched or checked concurrently
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<usize>,
+
+    /// Times a network operation failing with a transient error is retried
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+
+    /// Milliseconds to wait before the first retry, doubled for every later one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_backoff: Option<u64>,
+
+    /// Randomize the wait between retries, so parallel fetches don't retry in step
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_jitter: Option<bool>,
+
+    /// Seconds a remote may take to accept a connection or stay silent during a
+    /// transfer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connect_timeout: Option<u64>,
+
+    /// Seconds a single clone, fetch or push may take in total
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+
+    /// Seconds to wait for another fpm run in the same project to finish
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lock_timeout: Option<u64>,
+
+    /// Key signing the commits of push and publish: a GPG key ID, or the path of
+    /// an SSH key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signing_key: Option<String>,
+
+    /// Whether `signing_key` is a GPG or an SSH key; guessed from the key if not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signing_format: Option<SigningFormat>,
+
+    /// Template of the commit messages `fpm push` writes when no message is given,
+    /// for bundles whose manifest doesn't set one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_message: Option<String>,
+
+    /// Author of the commits of push and publish, as "Name <email>", for bundles that
+    /// don't set their own; the repository's git identity by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+
+    /// Part of the version `fpm push` increments for bundles that don't set their
+    /// own `bump`; `off` pushes the version as it is
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bump: Option<BumpLevel>,
+
+    /// Whether installs check bundle signatures; `require` by default once
+    /// `trusted_keys` lists a key, `off` otherwise
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signatures: Option<SignaturePolicy>,
+
+    /// Public keys whose bundle signatures are trusted: SSH public keys
+    /// (`ssh-ed25519 AAAA...`) or minisign public keys (`RWQ...`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub trusted_keys: Vec<String>,
+
+    /// Git repository holding the registry index that `fpm search` searches and
+    /// `fpm add <name>` looks bundles up in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+
+    /// Whether installs run the `[hooks]` of installed bundles, `never` by default.
+    /// The top-level manifest's hooks always run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bundle_hooks: Option<HookPolicy>,
+
+    /// URL rewrite rules keyed by the base that replaces the matched prefix
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub url: BTreeMap<String, UrlRule>,
+
+    /// SSH settings keyed by host name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub ssh: BTreeMap<String, SshHost>,
+}
+
+/// SSH settings for the repositories on one host
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SshHost {
+    /// SSH private key used for the host's repositories whose bundles don't set
+    /// their own; takes precedence over the global `ssh_key`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<PathBuf>,
+
+    /// Extra ssh options for the host, such as `Port=2222`, passed as `-o` flags
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+}
+
+/// A rewrite rule, like git's `url.<base>.insteadOf`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct UrlRule {
+    /// URL prefixes replaced by the rule's base
+    pub instead_of: Vec<String>,
+}
+
+impl Settings {
+    /// Reads the settings given by `FPM_*` environment variables
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            git_backend: env_value(GIT_BACKEND_ENV, |v| GitBackend::from_str(v, true))?,
+            ssh_key: env_value(SSH_KEY_ENV, |v| Ok::<_, String>(expand_home(Path::new(v))))?,
+            depth: env_value(DEPTH_ENV, u32::from_str)?,
+            protocol: env_value(PROTOCOL_ENV, |v| GitProtocol::from_str(v, true))?,
+            cache_dir: env_value(CACHE_DIR_ENV, |v| Ok::<_, String>(PathBuf::from(v)))?,
+            color: env_value(COLOR_ENV, |v| ColorMode::from_str(v, true))?,
+            output: env_value(OUTPUT_ENV, |v| OutputFormat::from_str(v, true))?,
+            jobs: env_value(JOBS_ENV, usize::from_str)?,
+            retries: env_value(RETRIES_ENV, u32::from_str)?,
+            retry_backoff: env_value(RETRY_BACKOFF_ENV, u64::from_str)?,
+            retry_jitter: env_value(RETRY_JITTER_ENV, bool::from_str)?,
+            connect_timeout: env_value(CONNECT_TIMEOUT_ENV, u64::from_str)?,
+            timeout: env_value(TIMEOUT_ENV, u64::from_str)?,
+            lock_timeout: env_value(LOCK_TIMEOUT_ENV, u64::from_str)?,
+            signing_key: env_value(SIGNING_KEY_ENV, |v| Ok::<_, String>(v.to_string()))?,
+            signing_format: None,
+            commit_message: None,
+            author: env_value(AUTHOR_ENV, |v| Ok::<_, String>(v.to_string()))?,
+            bump: env_value(BUMP_ENV, |v| BumpLevel::from_str(v, true))?,
+            signatures: env_value(SIGNATURES_ENV, |v| SignaturePolicy::from_str(v, true))?,
+            trusted_keys: Vec::new(),
+            registry: env_value(REGISTRY_ENV, |v| Ok::<_, String>(v.to_string()))?,
+            bundle_hooks: env_value(BUNDLE_HOOKS_ENV, |v| HookPolicy::from_str(v, true))?,
+            url: BTreeMap::new(),
+            ssh: BTreeMap::new(),
+        })
+    }
+
+    /// Overlays `other` on these settings: values it sets replace the current ones,
+    /// its rewrite rules and SSH hosts replace those with the same base or host, and
+    /// its trusted keys are trusted too
+    pub fn merge(&mut self, other: Settings) {
+        self.git_backend = other.git_backend.or(self.git_backend);
+        self.ssh_key = other.ssh_key.or(self.ssh_key.take());
+        self.depth = other.depth.or(self.depth);
+        self.protocol = other.protocol.or(self.protocol);
+        self.cache_dir = other.cache_dir.or(self.cache_dir.take());
+        self.color = other.color.or(self.color);
+        self.output = other.output.or(self.output);
+        self.jobs = other.jobs.or(self.jobs);
+        self.retries = other.retries.or(self.retries);
+        self.retry_backoff = other.retry_backoff.or(self.retry_backoff);
+        self.retry_jitter = other.retry_jitter.or(self.retry_jitter);
+        self.connect_timeout = other.connect_timeout.or(self.connect_timeout);
+        self.timeout = other.timeout.or(self.timeout);
+        self.lock_timeout = other.lock_timeout.or(self.lock_timeout);
+        self.signing_key = other.signing_key.or(self.signing_key.take());
+        self.signing_format = other.signing_format.or(self.signing_format);
+        self.commit_message = other.commit_message.or(self.commit_message.take());
+        self.author = other.author.or(self.author.take());
+        self.bump = other.bump.or(self.bump);
+        self.signatures = other.signatures.or(self.signatures);
+        for key in other.trusted_keys {
+            if !self.trusted_keys.contains(&key) {
+                self.trusted_keys.push(key);
+            }
+        }
+        self.registry = other.registry.or(self.registry.take());
+        self.bundle_hooks = other.bundle_hooks.or(self.bundle_hooks);
+        self.url.extend(other.url);
+        self.ssh.extend(other.ssh);
+    }
+
+    /// Returns the key signing commits, if one is set. Keys given as paths are
+    /// SSH keys unless `signing_format` says otherwise.
+    pub fn commit_signing(&self) -> Option<CommitSigning> {
+        let key = self.signing_key.as_deref()?;
+        let is_path = key.contains('/') || key.contains('\\') || key.starts_with('~');
+        let format = self.signing_format.unwrap_or(if is_path {
+            SigningFormat::Ssh
+        } else {
+            SigningFormat::Gpg
+        });
+        let key = match format {
+            SigningFormat::Ssh => expand_home(Path::new(key)).to_string_lossy().into_owned(),
+            SigningFormat::Gpg => key.to_string(),
+        };
+        Some(CommitSigning { format, key })
+    }
+
+    /// Returns how installs treat bundle signatures
+    pub fn signature_policy(&self) -> SignaturePolicy {
+        self.signatures.unwrap_or(if self.trusted_keys.is_empty() {
+            SignaturePolicy::Off
+        } else {
+            SignaturePolicy::Require
+        })
+    }
+
+    /// Returns the SSH settings for the host of an SSH `url`, if any are configured.
+    /// Host names match regardless of case.
+    pub fn ssh_host(&self, url: &str) -> Option<&SshHost> {
+        let host = ssh_url_host(url)?;
+        self.ssh
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&host))
+            .map(|(_, settings)| settings)
+    }
+
+    /// Applies the URL rewrite rules to `url`. As with git, the longest matching
+    /// prefix wins; URLs no rule matches are returned unchanged.
+    pub fn rewrite_url(&self, url: &str) -> String {
+        let best = self
+            .url
+            .iter()
+            .flat_map(|(base, rule)| rule.instead_of.iter().map(move |prefix| (base, prefix)))
+            .filter(|(_, prefix)| !prefix.is_empty() && url.starts_with(prefix.as_str()))
+            .max_by_key(|(_, prefix)| prefix.len());
+
+        match best {
+            Some((base, prefix)) => format!("{}{}", base, &url[prefix.len()..]),
+            None => url.to_string(),
+        }
+    }
+}
+
+/// Parses the environment variable `name` if it is set and not empty
+fn env_value<T, E: std::fmt::Display>(
+    name: &str,
+    parse: impl FnOnce(&str) -> std::result::Result<T, E>,
+) -> Result<Option<T>> {
+    let Some(value) = std::env::var(name).ok().filter(|v| !v.is_empty()) else {
+        return Ok(None);
+    };
+
+    parse(&value)
+        .map(Some)
+        .map_err(|e| anyhow::anyhow!("Invalid value '{}' in {}: {}", value, name, e))
+}
+
+/// Returns the host of an SSH URL: `ssh://[user@]host[:port]/path` or the scp-like
+/// `[user@]host:path`. Returns None for other URLs and local paths.
+pub fn ssh_url_host(url: &str) -> Option<String> {
+    if let Some(rest) = url.strip_prefix("ssh://") {
+        let authority = rest.split('/').next()?;
+        let host = authority.rsplit('@').next()?;
+        let host = match host.strip_prefix('[') {
+            Some(bracketed) => bracketed.split(']').next()?,
+            None => host.split(':').next()?,
+        };
+        return Some(host.to_string()).filter(|host| !host.is_empty());
+    }
+    if url.contains("://") {
+        return None;
+    }
+
+    // scp-like syntax: a colon before any slash, and not a Windows drive letter
+    let (authority, _) = url.split_once(':')?;
+    if authority.contains('/') || authority.len() < 2 {
+        return None;
+    }
+    let host = authority.rsplit('@').next()?;
+    Some(host.to_string()).filter(|host| !host.is_empty())
+}
+
+/// Replaces a leading `~` with the user's home directory
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Returns the location of the global settings file
+pub fn global_config_path() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+        return Some(PathBuf::from(dir).join("fpm").join(CONFIG_FILE));
+    }
+
+    if let Some(dir) = std::env::var_os("APPDATA").filter(|d| !d.is_empty()) {
+        return Some(PathBuf::from(dir).join("fpm").join(CONFIG_FILE));
+    }
+
+    home_dir().map(|home| home.join(".config").join("fpm").join(CONFIG_FILE))
+}
+
+/// Returns the location of the settings file of the project owning `manifest_path`
+pub fn project_config_path(manifest_path: &Path) -> PathBuf {
+    manifest_path
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(BUNDLE_DIR)
+        .join(CONFIG_FILE)
+}
+
+/// Loads the global settings overlaid with the settings of the project owning
+/// `manifest_path` and the environment. Missing files are skipped.
+pub fn load_settings(manifest_path: &Path) -> Result<Settings> {
+    let mut settings = Settings::default();
+    let paths = global_config_path()
+        .into_iter()
+        .chain([project_config_path(manifest_path)]);
+
+    for path in paths {
+        if let Some(layer) = load_settings_file(&path)? {
+            settings.merge(layer);
+        }
+    }
+    settings.merge(Settings::from_env()?);
+
+    Ok(settings)
+}
+
+/// Parses a single settings file, returning None if it doesn't exist
+pub fn load_settings_file(path: &Path) -> Result<Option<Settings>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    let mut settings: Settings = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+    settings.ssh_key = settings.ssh_key.as_deref().map(expand_home);
+    settings.cache_dir = settings.cache_dir.as_deref().map(expand_home);
+    for host in settings.ssh.values_mut() {
+        host.key = host.key.as_deref().map(expand_home);
+    }
+
+    Ok(Some(settings))
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_url_longest_prefix_wins() {
+        let settings: Settings = toml::from_str(
+            r#"
+            [url."https://mirror.example.com/github/"]
+            instead_of = ["https://github.com/", "git@github.com:"]
+
+            [url."https://mirror.example.com/design/"]
+            instead_of = ["https://github.com/company/design-"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            settings.rewrite_url("https://github.com/example/icons.git"),
+            "https://mirror.example.com/github/example/icons.git"
+        );
+        assert_eq!(
+            settings.rewrite_url("git@github.com:example/icons.git"),
+            "https://mirror.example.com/github/example/icons.git"
+        );
+        assert_eq!(
+            settings.rewrite_url("https://github.com/company/design-tokens.git"),
+            "https://mirror.example.com/design/tokens.git"
+        );
+        assert_eq!(
+            settings.rewrite_url("https://gitlab.com/example/icons.git"),
+            "https://gitlab.com/example/icons.git"
+        );
+    }
+
+    #[test]
+    fn test_later_layers_override_earlier_ones() {
+        let mut settings: Settings = toml::from_str(
+            r#"
+            git_backend = "git2"
+            depth = 1
+            jobs = 8
+            color = "never"
+            "#,
+        )
+        .unwrap();
+        let project: Settings = toml::from_str("depth = 20\nprotocol = \"ssh\"").unwrap();
+        let flags = Settings {
+            git_backend: Some(GitBackend::Cli),
+            ..Default::default()
+        };
+
+        settings.merge(project);
+        settings.merge(flags);
+
+        assert_eq!(settings.git_backend, Some(GitBackend::Cli));
+        assert_eq!(settings.depth, Some(20));
+        assert_eq!(settings.protocol, Some(GitProtocol::Ssh));
+        assert_eq!(settings.jobs, Some(8));
+        assert_eq!(settings.color, Some(ColorMode::Never));
+        assert_eq!(settings.cache_dir, None);
+    }
+
+    #[test]
+    fn test_commit_signing_format_follows_the_key() {
+        let signing = |key: &str, format| {
+            Settings {
+                signing_key: Some(key.to_string()),
+                signing_format: format,
+                ..Default::default()
+            }
+            .commit_signing()
+            .unwrap()
+            .format
+        };
+
+        assert_eq!(signing("3AA5C34371567BD2", None), SigningFormat::Gpg);
+        assert_eq!(signing("/keys/id_ed25519", None), SigningFormat::Ssh);
+        assert_eq!(
+            signing("release-key", Some(SigningFormat::Ssh)),
+            SigningFormat::Ssh
+        );
+        assert_eq!(Settings::default().commit_signing(), None);
+    }
+
+    #[test]
+    fn test_trusted_keys_add_up_and_require_signatures() {
+        let mut settings: Settings =
+            toml::from_str("trusted_keys = [\"ssh-ed25519 AAAA1 ci\"]").unwrap();
+        assert_eq!(settings.signature_policy(), SignaturePolicy::Require);
+
+        let project: Settings = toml::from_str(
+            r#"
+            signatures = "warn"
+            trusted_keys = ["ssh-ed25519 AAAA1 ci", "RWQexample"]
+            "#,
+        )
+        .unwrap();
+        settings.merge(project);
+
+        assert_eq!(
+            settings.trusted_keys,
+            vec!["ssh-ed25519 AAAA1 ci", "RWQexample"]
+        );
+        assert_eq!(settings.signature_policy(), SignaturePolicy::Warn);
+        assert_eq!(Settings::default().signature_policy(), SignaturePolicy::Off);
+    }
+
+    #[test]
+    fn test_load_settings_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(CONFIG_FILE);
+        assert_eq!(load_settings_file(&path).unwrap(), None);
+
+        fs::write(&path, "ssh_key = \"/keys/deploy\"\ncache_dir = \"cache\"\n").unwrap();
+        let settings = load_settings_file(&path).unwrap().unwrap();
+        assert_eq!(settings.ssh_key, Some(PathBuf::from("/keys/deploy")));
+        assert_eq!(settings.cache_dir, Some(PathBuf::from("cache")));
+
+        fs::write(&path, "git_backend = \"svn\"\n").unwrap();
+        let error = format!("{:#}", load_settings_file(&path).unwrap_err());
+        assert!(
+            error.contains("Failed to parse config file"),
+            "Got: {}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_project_settings_override_global() {
+        let mut settings: Settings = toml::from_str(
+            r#"
+            [url."https://mirror.example.com/"]
+            instead_of = ["https://github.com/"]
+            "#,
+        )
+        .unwrap();
+        let project: Settings = toml::from_str(
+            r#"
+            [url."https://mirror.example.com/"]
+            instead_of = ["https://gitlab.com/"]
+            "#,
+        )
+        .unwrap();
+
+        settings.merge(project);
+
+        assert_eq!(
+            settings.rewrite_url("https://github.com/example/icons.git"),
+            "https://github.com/example/icons.git"
+        );
+        assert_eq!(
+            settings.rewrite_url("https://gitlab.com/example/icons.git"),
+            "https://mirror.example.com/example/icons.git"
+        );
+    }
+
+    #[test]
+    fn test_project_config_path() {
+        assert_eq!(
+            project_config_path(Path::new("/work/app/bundle.toml")),
+            Path::new("/work/app/.fpm/config.toml")
+        );
+        assert_eq!(
+            project_config_path(Path::new("bundle.toml")),
+            Path::new(".fpm/config.toml")
+        );
+    }
+
+    #[test]
+    fn test_ssh_host_matches_url_host() {
+        let settings: Settings = toml::from_str(
+            r#"
+            [ssh."github.com"]
+            key = "/keys/work_ed25519"
+
+            [ssh."git.example.com"]
+            options = ["Port=2222"]
+            "#,
+        )
+        .unwrap();
+
+        let github = settings
+            .ssh_host("git@GitHub.com:company/icons.git")
+            .unwrap();
+        assert_eq!(github.key, Some(PathBuf::from("/keys/work_ed25519")));
+        assert_eq!(
+            settings.ssh_host("ssh://git@git.example.com:2222/design/icons.git"),
+            settings.ssh.get("git.example.com")
+        );
+        // HTTPS URLs and local paths never use SSH settings
+        assert_eq!(
+            settings.ssh_host("https://github.com/company/icons.git"),
+            None
+        );
+        assert_eq!(settings.ssh_host("C:/bundles/icons"), None);
+        assert_eq!(settings.ssh_host("../icons"), None);
+    }
+}
@@ -0,0 +1,102 @@
+//! Shorthand git sources.
+//!
+//! Manifests may write `git = "github:org/repo"` instead of a full URL. The
+//! shorthand expands to an HTTPS or SSH URL depending on the manifest's
+//! `protocol` setting.
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Hosts that can be written as `<prefix>:<path>`
+const HOSTS: &[(&str, &str)] = &[
+    ("github", "github.com"),
+    ("gitlab", "gitlab.com"),
+    ("bitbucket", "bitbucket.org"),
+];
+
+/// Protocol used when expanding shorthand sources
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum GitProtocol {
+    #[default]
+    Https,
+    Ssh,
+}
+
+impl fmt::Display for GitProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitProtocol::Https => write!(f, "https"),
+            GitProtocol::Ssh => write!(f, "ssh"),
+        }
+    }
+}
+
+/// Returns the host and repository path of a shorthand source,
+/// or None if `git` is not a shorthand
+fn split(git: &str) -> Option<(&'static str, &str)> {
+    let (prefix, path) = git.split_once(':')?;
+    let host = HOSTS
+        .iter()
+        .find(|(name, _)| *name == prefix)
+        .map(|(_, host)| *host)?;
+    Some((host, path))
+}
+
+/// Expands a shorthand source to a full repository URL.
+/// Returns Ok(None) for sources that are not shorthands.
+pub fn expand(git: &str, protocol: GitProtocol) -> anyhow::Result<Option<String>> {
+    let Some((host, path)) = split(git) else {
+        return Ok(None);
+    };
+
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let segments: Vec<&str> = path.split('/').collect();
+    if segments.len() < 2 || segments.iter().any(|segment| segment.is_empty()) {
+        anyhow::bail!(
+            "Invalid shorthand source '{}': expected <owner>/<repository>",
+            git
+        );
+    }
+
+    let url = match protocol {
+        GitProtocol::Https => format!("https://{}/{}.git", host, path),
+        GitProtocol::Ssh => format!("git@{}:{}.git", host, path),
+    };
+    Ok(Some(url))
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_shorthands() {
+        assert_eq!(
+            expand("github:DragonAxeSoftware/fpm-example-1", GitProtocol::Https).unwrap(),
+            Some("https://github.com/DragonAxeSoftware/fpm-example-1.git".to_string())
+        );
+        assert_eq!(
+            expand("gitlab:group/subgroup/project.git", GitProtocol::Ssh).unwrap(),
+            Some("git@gitlab.com:group/subgroup/project.git".to_string())
+        );
+        assert_eq!(
+            expand("bitbucket:team/repo", GitProtocol::Https).unwrap(),
+            Some("https://bitbucket.org/team/repo.git".to_string())
+        );
+
+        assert_eq!(
+            expand("https://github.com/org/repo.git", GitProtocol::Ssh).unwrap(),
+            None
+        );
+        assert_eq!(
+            expand("git@github.com:org/repo.git", GitProtocol::Https).unwrap(),
+            None
+        );
+
+        assert!(expand("github:repo", GitProtocol::Https).is_err());
+        assert!(expand("github:org//repo", GitProtocol::Https).is_err());
+    }
+}
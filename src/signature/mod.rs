@@ -0,0 +1,351 @@
+//! Detached signatures of bundle contents.
+//!
+//! `fpm sign` lists the hash of every file of a source bundle's root in
+//! `.fpm-contents` and signs that listing with an SSH key (`ssh-keygen -Y sign`)
+//! or a minisign key. Installs check the signature against the `trusted_keys`
+//! setting and the installed files against the listing, so a bundle can't be
+//! changed without the publisher's key.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::cache::fnv1a_64;
+use crate::checksum::{hash_tree, FileHashes};
+
+/// Listing of the signed files, in the format of `sha256sum`
+pub const CONTENTS_FILE: &str = ".fpm-contents";
+
+/// Signature of the listing made with `ssh-keygen -Y sign`
+pub const SSH_SIGNATURE_FILE: &str = ".fpm-contents.sig";
+
+/// Signature of the listing made with minisign
+pub const MINISIGN_SIGNATURE_FILE: &str = ".fpm-contents.minisig";
+
+/// Namespace of SSH signatures, so signatures made for other purposes don't verify
+const SSH_NAMESPACE: &str = "fpm-bundle";
+
+/// Principal the trusted SSH keys are listed under in the allowed signers file
+const SSH_PRINCIPAL: &str = "fpm";
+
+/// The result of checking a bundle with a signature policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureCheck {
+    /// A trusted key signed the listing, and the files match it
+    Verified,
+    /// The bundle has no signature
+    Unsigned,
+    /// The signature or the files are wrong; says what is
+    Invalid(String),
+}
+
+/// Files left out of the listing: the signature files themselves, and the
+/// .gitignore an install adds .fpm to
+fn is_unsigned_file(path: &str) -> bool {
+    [
+        CONTENTS_FILE,
+        SSH_SIGNATURE_FILE,
+        MINISIGN_SIGNATURE_FILE,
+        ".gitignore",
+    ]
+    .contains(&path)
+}
+
+/// Hashes the files a signature covers
+fn signed_files(root: &Path) -> Result<FileHashes> {
+    let mut files = hash_tree(root)?;
+    files.retain(|path, _| !is_unsigned_file(path));
+    Ok(files)
+}
+
+/// Writes the listing of `root`'s files and signs it with `key`: a minisign secret
+/// key, or else an SSH private key. Returns the signature file.
+pub fn sign_bundle(root: &Path, key: &Path) -> Result<PathBuf> {
+    let files = signed_files(root)?;
+    let listing: String = files
+        .iter()
+        .map(|(path, hash)| format!("{}  {}\n", hash, path))
+        .collect();
+    let contents_path = root.join(CONTENTS_FILE);
+    fs::write(&contents_path, listing)
+        .with_context(|| format!("Failed to write {}", contents_path.display()))?;
+
+    let key_content = fs::read_to_string(key)
+        .with_context(|| format!("Failed to read signing key: {}", key.display()))?;
+    let (signature, stale) = if key_content.starts_with("untrusted comment:") {
+        let signature = root.join(MINISIGN_SIGNATURE_FILE);
+        let status = Command::new("minisign")
+            .arg("-S")
+            .arg("-s")
+            .arg(key)
+            .arg("-m")
+            .arg(&contents_path)
+            .arg("-x")
+            .arg(&signature)
+            .status()
+            .context("Failed to run minisign. Is it installed?")?;
+        if !status.success() {
+            anyhow::bail!("minisign failed to sign {}", contents_path.display());
+        }
+        (signature, root.join(SSH_SIGNATURE_FILE))
+    } else {
+        let signature = root.join(SSH_SIGNATURE_FILE);
+        // ssh-keygen refuses to overwrite an existing signature
+        let _ = fs::remove_file(&signature);
+        let status = Command::new("ssh-keygen")
+            .args(["-q", "-Y", "sign", "-n", SSH_NAMESPACE, "-f"])
+            .arg(key)
+            .arg(&contents_path)
+            .status()
+            .context("Failed to run ssh-keygen. Is OpenSSH installed?")?;
+        if !status.success() {
+            anyhow::bail!("ssh-keygen failed to sign {}", contents_path.display());
+        }
+        (signature, root.join(MINISIGN_SIGNATURE_FILE))
+    };
+
+    // A signature made with the other kind of key no longer matches the listing
+    let _ = fs::remove_file(stale);
+    Ok(signature)
+}
+
+/// Checks the signature of the bundle installed at `path` against the trusted
+/// keys, and its files against the signed listing. `filtered` bundles (include,
+/// exclude or sparse filters) may lack files of the listing.
+pub fn check_bundle(
+    path: &Path,
+    trusted_keys: &[String],
+    filtered: bool,
+) -> Result<SignatureCheck> {
+    let contents_path = path.join(CONTENTS_FILE);
+    let ssh_signature = path.join(SSH_SIGNATURE_FILE);
+    let minisign_signature = path.join(MINISIGN_SIGNATURE_FILE);
+    if !contents_path.exists() || !(ssh_signature.exists() || minisign_signature.exists()) {
+        return Ok(SignatureCheck::Unsigned);
+    }
+
+    let verified = if ssh_signature.exists() {
+        verify_ssh(&contents_path, &ssh_signature, trusted_keys)?
+    } else {
+        verify_minisign(&contents_path, &minisign_signature, trusted_keys)?
+    };
+    if !verified {
+        return Ok(SignatureCheck::Invalid(
+            "the signature doesn't verify with any trusted key".to_string(),
+        ));
+    }
+
+    let listing = fs::read_to_string(&contents_path)
+        .with_context(|| format!("Failed to read {}", contents_path.display()))?;
+    let signed = parse_listing(&listing)?;
+    let files = signed_files(path)?;
+    Ok(match compare_with_listing(&signed, &files, filtered) {
+        Some(problem) => SignatureCheck::Invalid(problem),
+        None => SignatureCheck::Verified,
+    })
+}
+
+/// Parses a listing written by `sign_bundle`
+fn parse_listing(listing: &str) -> Result<FileHashes> {
+    listing
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (hash, path) = line
+                .split_once("  ")
+                .with_context(|| format!("Invalid line in {}: {}", CONTENTS_FILE, line))?;
+            Ok((path.to_string(), hash.to_string()))
+        })
+        .collect()
+}
+
+/// Describes the first file that differs from the signed listing, if any
+fn compare_with_listing(signed: &FileHashes, files: &FileHashes, filtered: bool) -> Option<String> {
+    for (path, hash) in files {
+        match signed.get(path) {
+            None => return Some(format!("{} isn't in the signed contents", path)),
+            Some(signed) if signed != hash => {
+                return Some(format!("{} doesn't match the signed contents", path))
+            }
+            Some(_) => {}
+        }
+    }
+    if !filtered {
+        if let Some(path) = signed.keys().find(|path| !files.contains_key(*path)) {
+            return Some(format!("{} of the signed contents is missing", path));
+        }
+    }
+    None
+}
+
+/// Returns true for SSH public keys, as opposed to minisign ones
+fn is_ssh_key(key: &str) -> bool {
+    ["ssh-", "ecdsa-", "sk-"]
+        .iter()
+        .any(|prefix| key.starts_with(prefix))
+}
+
+/// Verifies an `ssh-keygen -Y sign` signature with the trusted SSH keys
+fn verify_ssh(contents: &Path, signature: &Path, trusted_keys: &[String]) -> Result<bool> {
+    let allowed: Vec<String> = trusted_keys
+        .iter()
+        .filter(|key| is_ssh_key(key))
+        .map(|key| format!("{} namespaces=\"{}\" {}", SSH_PRINCIPAL, SSH_NAMESPACE, key))
+        .collect();
+    if allowed.is_empty() {
+        return Ok(false);
+    }
+
+    // ssh-keygen reads the trusted keys from a file
+    let allowed_signers = std::env::temp_dir().join(format!(
+        "fpm-allowed-signers-{}-{:x}",
+        std::process::id(),
+        fnv1a_64(contents.to_string_lossy().as_bytes())
+    ));
+    fs::write(&allowed_signers, allowed.join("\n") + "\n")
+        .with_context(|| format!("Failed to write {}", allowed_signers.display()))?;
+
+    let result = (|| {
+        let mut child = Command::new("ssh-keygen")
+            .args([
+                "-Y",
+                "verify",
+                "-I",
+                SSH_PRINCIPAL,
+                "-n",
+                SSH_NAMESPACE,
+                "-f",
+            ])
+            .arg(&allowed_signers)
+            .arg("-s")
+            .arg(signature)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Failed to run ssh-keygen. Is OpenSSH installed?")?;
+        let message = fs::read(contents)?;
+        child
+            .stdin
+            .take()
+            .context("Failed to pass the contents to ssh-keygen")?
+            .write_all(&message)?;
+        Ok(child.wait()?.success())
+    })();
+
+    let _ = fs::remove_file(&allowed_signers);
+    result
+}
+
+/// Verifies a minisign signature with the trusted minisign keys
+fn verify_minisign(contents: &Path, signature: &Path, trusted_keys: &[String]) -> Result<bool> {
+    for key in trusted_keys.iter().filter(|key| !is_ssh_key(key)) {
+        let status = Command::new("minisign")
+            .args(["-V", "-q", "-P", key, "-m"])
+            .arg(contents)
+            .arg("-x")
+            .arg(signature)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .context("Failed to run minisign. Is it installed?")?;
+        if status.success() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn hashes(entries: &[(&str, &str)]) -> FileHashes {
+        entries
+            .iter()
+            .map(|(path, hash)| (path.to_string(), hash.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_listing() {
+        let listing = "aa11  README.md\nbb22  svg/logo one.svg\n";
+        assert_eq!(
+            parse_listing(listing).unwrap(),
+            hashes(&[("README.md", "aa11"), ("svg/logo one.svg", "bb22")])
+        );
+        assert!(parse_listing("aa11 README.md\n").is_err());
+    }
+
+    #[test]
+    fn test_compare_with_listing() {
+        let signed = hashes(&[("a.svg", "1"), ("b.svg", "2")]);
+
+        assert_eq!(compare_with_listing(&signed, &signed, false), None);
+        assert_eq!(
+            compare_with_listing(&signed, &hashes(&[("a.svg", "1"), ("b.svg", "9")]), false),
+            Some("b.svg doesn't match the signed contents".to_string())
+        );
+        assert_eq!(
+            compare_with_listing(&signed, &hashes(&[("a.svg", "1"), ("c.svg", "3")]), true),
+            Some("c.svg isn't in the signed contents".to_string())
+        );
+        // Filtered bundles may leave signed files out
+        let partial = hashes(&[("a.svg", "1")]);
+        assert_eq!(compare_with_listing(&signed, &partial, true), None);
+        assert_eq!(
+            compare_with_listing(&signed, &partial, false),
+            Some("b.svg of the signed contents is missing".to_string())
+        );
+    }
+
+    #[test]
+    fn test_ssh_signature_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let key = temp_dir.path().join("id_ed25519");
+        let generated = Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-C", "publisher", "-f"])
+            .arg(&key)
+            .status();
+        if !generated.is_ok_and(|status| status.success()) {
+            // Needs OpenSSH
+            return;
+        }
+        let public_key = fs::read_to_string(key.with_extension("pub")).unwrap();
+        let trusted = vec![public_key.trim().to_string()];
+
+        let root = temp_dir.path().join("bundle");
+        fs::create_dir_all(root.join("svg")).unwrap();
+        fs::write(root.join("svg").join("logo.svg"), "<svg/>").unwrap();
+        assert_eq!(
+            check_bundle(&root, &trusted, false).unwrap(),
+            SignatureCheck::Unsigned
+        );
+
+        let signature = sign_bundle(&root, &key).unwrap();
+        assert_eq!(signature, root.join(SSH_SIGNATURE_FILE));
+        assert_eq!(
+            check_bundle(&root, &trusted, false).unwrap(),
+            SignatureCheck::Verified
+        );
+        // An install adds .gitignore, which isn't signed
+        fs::write(root.join(".gitignore"), ".fpm\n").unwrap();
+        assert_eq!(
+            check_bundle(&root, &trusted, false).unwrap(),
+            SignatureCheck::Verified
+        );
+
+        assert!(matches!(
+            check_bundle(&root, &[], false).unwrap(),
+            SignatureCheck::Invalid(_)
+        ));
+        fs::write(root.join("svg").join("logo.svg"), "<svg>changed</svg>").unwrap();
+        assert_eq!(
+            check_bundle(&root, &trusted, false).unwrap(),
+            SignatureCheck::Invalid("svg/logo.svg doesn't match the signed contents".to_string())
+        );
+    }
+}
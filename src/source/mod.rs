@@ -0,0 +1,499 @@
+//! Where bundles come from.
+//!
+//! Every kind of bundle source (git repositories, local directories, archives,
+//! OCI artifacts, S3 prefixes) implements [`BundleSource`], and the commands look
+//! the source of each dependency up with [`source_for`] instead of matching on its
+//! fields. Library users can add their own sources with [`register`]; registered
+//! sources are asked before the built-in ones, so they can also take over
+//! dependencies a built-in source would handle.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use crate::archive::{install_archive, DOWNLOAD_DIR};
+use crate::config::load_manifest;
+use crate::flatten::{self, checkout_path, flattened_source, mirror};
+use crate::git::{fetch_bundle, GitOperations, LocalChanges};
+use crate::oci::{self, OciReference};
+use crate::resolve::{resolve_ref, ResolvedRef};
+use crate::s3::{self, S3Location};
+use crate::types::{
+    BundleDependency, BundleState, BundleStatus, InstallStrategy, LockedBundle, SourceKind,
+};
+
+/// A kind of place bundles are installed from
+pub trait BundleSource: Send + Sync {
+    /// Short name used in messages, e.g. "git"
+    fn kind(&self) -> &str;
+
+    /// Returns true if this source installs `dependency`
+    fn handles(&self, dependency: &BundleDependency) -> bool;
+
+    /// Puts the files of the requested bundle into its directory
+    fn fetch(&self, request: &FetchRequest) -> Result<Fetched>;
+
+    /// Determines the status of the installed bundle at `path`
+    fn status(&self, path: &Path, dependency: &BundleDependency) -> Result<BundleStatus>;
+
+    /// Returns true if local changes can be pushed back to the source, which also
+    /// makes `fpm diff` apply to its bundles
+    fn can_push(&self) -> bool {
+        false
+    }
+
+    /// Returns true if the revisions this source installs are pinned in fpm.lock,
+    /// which also makes `fpm outdated` look for newer ones
+    fn is_locked(&self) -> bool {
+        false
+    }
+}
+
+/// Everything a source gets to install one bundle
+#[derive(Debug, Clone, Copy)]
+pub struct FetchRequest<'a> {
+    /// Name of the bundle in its manifest, and of its directory
+    pub name: &'a str,
+    pub dependency: &'a BundleDependency,
+    /// The .fpm directory the bundle is fetched into. A bundle installed for the
+    /// first time is fetched into a staging directory and moved into place afterwards.
+    pub bundle_dir: &'a Path,
+    /// Directory of the manifest declaring the bundle
+    pub manifest_dir: &'a Path,
+    /// What the last install recorded for the bundle, if anything
+    pub previous: Option<&'a BundleState>,
+    /// Lock entry to install instead of resolving the dependency again
+    /// (only for sources that are locked)
+    pub locked: Option<&'a LockedBundle>,
+    /// Repository to borrow git objects from, for bundles sharing a repository
+    pub shared_store: Option<&'a Path>,
+    /// Fetch again even if what the last install put there is still wanted
+    pub refresh: bool,
+    /// Delete the installed bundle, with any local changes, and install it from
+    /// scratch. Without it, git bundles fail with [`LocalChanges`] rather than
+    /// discard changes.
+    pub force: bool,
+}
+
+impl FetchRequest<'_> {
+    /// Directory the bundle's files go into
+    pub fn target(&self) -> PathBuf {
+        self.bundle_dir.join(self.name)
+    }
+}
+
+/// What a source installed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fetched {
+    /// Ref the dependency was resolved to (an empty name for sources without refs)
+    pub resolved: ResolvedRef,
+    /// Commit, checksum or digest identifying what was installed, if known
+    pub rev: Option<String>,
+    pub strategy: InstallStrategy,
+}
+
+static REGISTERED: RwLock<Vec<Arc<dyn BundleSource>>> = RwLock::new(Vec::new());
+
+/// Adds a source for every command to use. Sources registered later are asked first.
+pub fn register(source: Arc<dyn BundleSource>) {
+    REGISTERED.write().unwrap().insert(0, source);
+}
+
+/// Returns the source installing `dependency`: the first registered source that
+/// handles it, otherwise the built-in source for its fields
+pub fn source_for<'a>(
+    dependency: &BundleDependency,
+    git_ops: &'a dyn GitOperations,
+) -> Arc<dyn BundleSource + 'a> {
+    let registered = REGISTERED
+        .read()
+        .unwrap()
+        .iter()
+        .find(|source| source.handles(dependency))
+        .cloned();
+    if let Some(source) = registered {
+        return source;
+    }
+
+    match dependency.source_kind() {
+        SourceKind::Git => Arc::new(GitSource { git_ops }),
+        SourceKind::Local => Arc::new(LocalSource),
+        SourceKind::Archive => Arc::new(ArchiveSource),
+        SourceKind::Oci => Arc::new(OciSource),
+        SourceKind::S3 => Arc::new(S3Source),
+    }
+}
+
+/// Git repositories (`git`), cloned into the bundle directory, or into a hidden
+/// checkout when only a subdirectory is installed
+pub struct GitSource<'a> {
+    pub git_ops: &'a dyn GitOperations,
+}
+
+impl BundleSource for GitSource<'_> {
+    fn kind(&self) -> &str {
+        "git"
+    }
+
+    fn handles(&self, dependency: &BundleDependency) -> bool {
+        dependency.source_kind() == SourceKind::Git
+    }
+
+    fn fetch(&self, request: &FetchRequest) -> Result<Fetched> {
+        let git_ops = self.git_ops;
+        let dependency = request.dependency;
+        let name = request.name;
+        let target_path = request.target();
+
+        // A bundle installed from a subdirectory is cloned into a hidden checkout and
+        // the subdirectory is copied out of it
+        let subdirectory = dependency.subdirectory();
+        let (clone_path, strategy) = match subdirectory {
+            Some(_) => (
+                checkout_path(request.bundle_dir, name),
+                InstallStrategy::Flatten,
+            ),
+            None => (target_path.clone(), InstallStrategy::Clone),
+        };
+        if request.force
+            || request
+                .previous
+                .is_some_and(|previous| previous.strategy != strategy)
+        {
+            // Forced, or the bundle switched between a whole repository and a
+            // subdirectory: clone it from scratch
+            remove_installed_bundle(request.bundle_dir, name)?;
+        }
+        let is_new_clone = !git_ops.is_repository(&clone_path);
+
+        let (resolved, locked_commit) = match request.locked {
+            Some(locked) => (locked.resolved_ref(), Some(locked.commit.as_str())),
+            None => (
+                resolve_ref(git_ops, dependency)
+                    .with_context(|| format!("Failed to resolve bundle: {}", name))?,
+                None,
+            ),
+        };
+
+        // Copying the subdirectory out again would overwrite edits made in the bundle
+        if let Some(source) = flattened_source(&target_path) {
+            let source_dir = source.source_dir();
+            if target_path.exists()
+                && source_dir.exists()
+                && flatten::differs(&source_dir, &target_path)?
+            {
+                return Err(LocalChanges {
+                    path: target_path,
+                    files: Vec::new(),
+                }
+                .into());
+            }
+        }
+
+        fetch_bundle(
+            git_ops,
+            dependency,
+            &clone_path,
+            &resolved.name,
+            request.shared_store,
+            locked_commit,
+        )?;
+
+        // The lock kept the clone where it was; a refresh still brings its
+        // remote-tracking branch up to date (tags don't move)
+        if request.refresh && !is_new_clone && locked_commit.is_some() && resolved.version.is_none()
+        {
+            git_ops
+                .fetch_remote_branch(&clone_path, &resolved.name)
+                .with_context(|| format!("Failed to fetch bundle: {}", name))?;
+        }
+
+        if let Some(subdirectory) = subdirectory {
+            let source_dir = clone_path.join(subdirectory);
+            if !source_dir.is_dir() {
+                anyhow::bail!(
+                    "Bundle '{}': path '{}' does not exist in {}",
+                    name,
+                    subdirectory.display(),
+                    dependency.git
+                );
+            }
+            mirror(&source_dir, &target_path)
+                .with_context(|| format!("Failed to copy bundle: {}", name))?;
+        }
+
+        Ok(Fetched {
+            resolved,
+            rev: git_ops.head_commit(&clone_path).ok(),
+            strategy,
+        })
+    }
+
+    fn status(&self, path: &Path, dependency: &BundleDependency) -> Result<BundleStatus> {
+        git_bundle_status(self.git_ops, path, Some(dependency))
+    }
+
+    fn can_push(&self) -> bool {
+        true
+    }
+
+    fn is_locked(&self) -> bool {
+        true
+    }
+}
+
+/// Determines the status of a bundle cloned from git. `dependency` is its entry in
+/// the manifest declaring it, if the manifest could be read.
+pub fn git_bundle_status(
+    git_ops: &dyn GitOperations,
+    path: &Path,
+    dependency: Option<&BundleDependency>,
+) -> Result<BundleStatus> {
+    // Check if it has a manifest with root (making it a source)
+    let manifest_path = path.join("bundle.toml");
+    if manifest_path.exists() {
+        if let Ok(manifest) = load_manifest(&manifest_path) {
+            if manifest.is_source_bundle() {
+                return Ok(BundleStatus::Source);
+            }
+        }
+    }
+
+    // A flattened bundle is compared against the checkout it was copied from
+    let source = flattened_source(path);
+    let repo_path = source
+        .as_ref()
+        .map_or(path, |source| source.checkout.as_path());
+
+    if !git_ops.is_repository(repo_path) {
+        return Ok(BundleStatus::Unsynced);
+    }
+
+    let has_local_changes = match &source {
+        Some(source) => flatten::has_local_changes(git_ops, path, source)?,
+        None => git_ops.has_local_changes(path)?,
+    };
+    if has_local_changes {
+        return Ok(BundleStatus::Unsynced);
+    }
+
+    if let Some(rev) = dependency.and_then(|dependency| dependency.rev.as_deref()) {
+        let head = git_ops.head_commit(repo_path)?;
+        if !head.starts_with(rev) {
+            return Ok(BundleStatus::Drifted);
+        }
+    }
+
+    Ok(BundleStatus::Synced)
+}
+
+/// Local directories (`path` without another source), linked into the bundle directory
+pub struct LocalSource;
+
+impl BundleSource for LocalSource {
+    fn kind(&self) -> &str {
+        "local"
+    }
+
+    fn handles(&self, dependency: &BundleDependency) -> bool {
+        dependency.is_local()
+    }
+
+    fn fetch(&self, request: &FetchRequest) -> Result<Fetched> {
+        let local_path = request.dependency.local_path().unwrap_or(Path::new(""));
+        let source = request.manifest_dir.join(local_path);
+        if !source.is_dir() {
+            anyhow::bail!(
+                "Bundle '{}': local path '{}' is not a directory",
+                request.name,
+                source.display()
+            );
+        }
+
+        let strategy = link_local_bundle(&source, &request.target())
+            .with_context(|| format!("Failed to link bundle: {}", request.name))?;
+
+        Ok(Fetched {
+            resolved: no_ref(),
+            rev: None,
+            strategy,
+        })
+    }
+
+    fn status(&self, _path: &Path, _dependency: &BundleDependency) -> Result<BundleStatus> {
+        // Local bundles have no remote to be in sync with
+        Ok(BundleStatus::Local)
+    }
+}
+
+/// Points `target` at a local directory with a symbolic link. Where links can't be
+/// created (Windows without Developer Mode), the directory is copied instead.
+fn link_local_bundle(source: &Path, target: &Path) -> Result<InstallStrategy> {
+    // Replace an earlier link, or a clone from before the bundle became local
+    if target.is_symlink() || target.join(".git").exists() {
+        fs::remove_dir_all(target)
+            .with_context(|| format!("Failed to remove {}", target.display()))?;
+    }
+
+    if !target.exists() && symlink_dir(source, target).is_ok() {
+        return Ok(InstallStrategy::Link);
+    }
+
+    mirror(source, target)?;
+    Ok(InstallStrategy::Flatten)
+}
+
+#[cfg(unix)]
+pub fn symlink_dir(source: &Path, target: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(source, target)
+}
+
+#[cfg(windows)]
+pub fn symlink_dir(source: &Path, target: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_dir(source, target)
+}
+
+/// Archives (`url`): downloaded, checked against their `sha256` and unpacked
+pub struct ArchiveSource;
+
+impl BundleSource for ArchiveSource {
+    fn kind(&self) -> &str {
+        "archive"
+    }
+
+    fn handles(&self, dependency: &BundleDependency) -> bool {
+        dependency.source_kind() == SourceKind::Archive
+    }
+
+    fn fetch(&self, request: &FetchRequest) -> Result<Fetched> {
+        let dependency = request.dependency;
+        let sha256 = dependency.sha256.as_deref();
+        fetch_download(request, sha256, |work_dir, target| {
+            install_archive(
+                &dependency.source(),
+                sha256,
+                dependency.subdirectory(),
+                work_dir,
+                target,
+            )
+        })
+    }
+
+    fn status(&self, _path: &Path, _dependency: &BundleDependency) -> Result<BundleStatus> {
+        Ok(downloaded_status())
+    }
+}
+
+/// Artifacts in an OCI registry (`oci`), pinned by a digest in the reference
+pub struct OciSource;
+
+impl BundleSource for OciSource {
+    fn kind(&self) -> &str {
+        "oci"
+    }
+
+    fn handles(&self, dependency: &BundleDependency) -> bool {
+        dependency.source_kind() == SourceKind::Oci
+    }
+
+    fn fetch(&self, request: &FetchRequest) -> Result<Fetched> {
+        let dependency = request.dependency;
+        let reference = OciReference::parse(&dependency.source())?;
+        fetch_download(request, reference.digest(), |work_dir, target| {
+            oci::pull_bundle(&reference, dependency.subdirectory(), work_dir, target)
+        })
+    }
+
+    fn status(&self, _path: &Path, _dependency: &BundleDependency) -> Result<BundleStatus> {
+        Ok(downloaded_status())
+    }
+}
+
+/// Objects under a prefix in S3-compatible storage (`s3`)
+pub struct S3Source;
+
+impl BundleSource for S3Source {
+    fn kind(&self) -> &str {
+        "s3"
+    }
+
+    fn handles(&self, dependency: &BundleDependency) -> bool {
+        dependency.source_kind() == SourceKind::S3
+    }
+
+    fn fetch(&self, request: &FetchRequest) -> Result<Fetched> {
+        let dependency = request.dependency;
+        let location = S3Location::parse(&dependency.source())?;
+        fetch_download(request, None, |work_dir, target| {
+            s3::pull_bundle(&location, dependency.subdirectory(), work_dir, target)
+        })
+    }
+
+    fn status(&self, _path: &Path, _dependency: &BundleDependency) -> Result<BundleStatus> {
+        Ok(downloaded_status())
+    }
+}
+
+/// Runs `download` (given a scratch directory and the bundle directory, returning the
+/// checksum or digest of what it installed), unless the last install downloaded the
+/// same source and it still matches `expected`
+fn fetch_download(
+    request: &FetchRequest,
+    expected: Option<&str>,
+    download: impl FnOnce(&Path, &Path) -> Result<String>,
+) -> Result<Fetched> {
+    let dependency = request.dependency;
+    let target_path = request.target();
+
+    let unchanged = request.previous.filter(|previous| {
+        previous.source == dependency.source()
+            && previous.path.as_deref() == dependency.subdirectory()
+            && expected.is_none_or(|expected| {
+                previous
+                    .rev
+                    .as_ref()
+                    .is_some_and(|rev| rev.eq_ignore_ascii_case(expected))
+            })
+    });
+
+    let rev = match unchanged {
+        Some(previous) if target_path.exists() && !request.refresh => previous.rev.clone(),
+        _ => {
+            let work_dir = request.bundle_dir.join(DOWNLOAD_DIR).join(request.name);
+            Some(download(&work_dir, &target_path)?)
+        }
+    };
+
+    Ok(Fetched {
+        resolved: no_ref(),
+        rev,
+        strategy: InstallStrategy::Flatten,
+    })
+}
+
+/// Downloaded bundles are installed as plain files; edits to them aren't tracked
+fn downloaded_status() -> BundleStatus {
+    BundleStatus::Synced
+}
+
+/// Deletes an installed bundle and the checkout it was flattened from, if any
+pub fn remove_installed_bundle(bundle_dir: &Path, name: &str) -> Result<()> {
+    for path in [bundle_dir.join(name), checkout_path(bundle_dir, name)] {
+        if path.exists() {
+            fs::remove_dir_all(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The ref of a bundle whose source has no branches or tags
+fn no_ref() -> ResolvedRef {
+    ResolvedRef {
+        name: String::new(),
+        version: None,
+        yanked: false,
+    }
+}
@@ -0,0 +1,162 @@
+//! Persistent install state (.fpm/state.toml).
+//!
+//! Each bundle directory records how its bundles were installed so commands and
+//! external tooling don't have to re-derive it from the file system.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::resolve::ResolvedRef;
+use crate::types::{BundleDependency, BundleState, InstallState, InstallStrategy, STATE_FILE};
+
+/// Loads the install state of a bundle directory. A missing file yields an empty state.
+pub fn load_state(bundle_dir: &Path) -> Result<InstallState> {
+    let path = bundle_dir.join(STATE_FILE);
+    if !path.exists() {
+        return Ok(InstallState::default());
+    }
+
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read install state: {}", path.display()))?;
+
+    toml::from_str(&content)
+        .with_context(|| format!("Failed to parse install state: {}", path.display()))
+}
+
+/// Saves the install state of a bundle directory
+pub fn save_state(bundle_dir: &Path, state: &InstallState) -> Result<()> {
+    let path = bundle_dir.join(STATE_FILE);
+    let content = toml::to_string_pretty(state).context("Failed to serialize install state")?;
+
+    fs::write(&path, content)
+        .with_context(|| format!("Failed to write install state: {}", path.display()))
+}
+
+/// Records an install or update of a bundle, keeping the original install time
+pub fn record_install(
+    state: &mut InstallState,
+    name: &str,
+    dependency: &BundleDependency,
+    resolved: &ResolvedRef,
+    rev: Option<String>,
+    strategy: InstallStrategy,
+) {
+    record_install_at(state, name, dependency, resolved, rev, strategy, now_unix());
+}
+
+/// Like `record_install`, with `now` (seconds since the Unix epoch) as the
+/// time of the install
+fn record_install_at(
+    state: &mut InstallState,
+    name: &str,
+    dependency: &BundleDependency,
+    resolved: &ResolvedRef,
+    rev: Option<String>,
+    strategy: InstallStrategy,
+    now: u64,
+) {
+    let installed_at = state
+        .bundles
+        .get(name)
+        .map(|existing| existing.installed_at)
+        .unwrap_or(now);
+
+    state.bundles.insert(
+        name.to_string(),
+        BundleState {
+            source: dependency.source(),
+            rev,
+            branch: resolved.name.clone(),
+            version: resolved.version.clone(),
+            include: dependency.include.clone(),
+            exclude: dependency.exclude.clone(),
+            path: dependency.subdirectory().map(Path::to_path_buf),
+            strategy,
+            installed_at,
+            updated_at: now,
+        },
+    );
+}
+
+fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn dependency() -> BundleDependency {
+        BundleDependency {
+            version: "1.0.0".to_string(),
+            git: "https://github.com/example/icons.git".to_string(),
+            branch: Some("develop".to_string()),
+            include: Some(vec!["svg".to_string()]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_record_install_keeps_first_install_time() {
+        let mut state = InstallState::default();
+
+        record_install_at(
+            &mut state,
+            "icons",
+            &dependency(),
+            &ResolvedRef::branch(&dependency()),
+            None,
+            InstallStrategy::Clone,
+            100,
+        );
+        record_install_at(
+            &mut state,
+            "icons",
+            &dependency(),
+            &ResolvedRef::branch(&dependency()),
+            Some("abc123".to_string()),
+            InstallStrategy::Clone,
+            200,
+        );
+
+        let recorded = &state.bundles["icons"];
+        assert_eq!(recorded.installed_at, 100);
+        assert_eq!(recorded.updated_at, 200);
+        assert_eq!(recorded.rev.as_deref(), Some("abc123"));
+        assert_eq!(recorded.branch, "develop");
+        assert_eq!(recorded.include, Some(vec!["svg".to_string()]));
+    }
+
+    #[test]
+    fn test_state_roundtrip_and_missing_file() {
+        let temp_dir = TempDir::new().unwrap();
+
+        // Missing file is an empty state
+        assert_eq!(
+            load_state(temp_dir.path()).unwrap(),
+            InstallState::default()
+        );
+
+        let mut state = InstallState::default();
+        record_install_at(
+            &mut state,
+            "icons",
+            &dependency(),
+            &ResolvedRef::branch(&dependency()),
+            None,
+            InstallStrategy::Clone,
+            1,
+        );
+        save_state(temp_dir.path(), &state).unwrap();
+
+        let content = fs::read_to_string(temp_dir.path().join(STATE_FILE)).unwrap();
+        assert!(content.contains("strategy = \"clone\""));
+        assert_eq!(load_state(temp_dir.path()).unwrap(), state);
+    }
+}
@@ -82,6 +82,7 @@ pub fn create_bundle_manifest(
         description: description.map(String::from),
         root: root.map(PathBuf::from),
         bundles,
+        ..Default::default()
     };
 
     let manifest_path = dir.join("bundle.toml");
@@ -116,6 +117,15 @@ pub fn get_fpm_binary_path() -> PathBuf {
 
 /// Runs the fpm binary with the given arguments
 pub fn run_fpm(args: &[&str], working_dir: &Path) -> Result<std::process::Output> {
+    run_fpm_with_env::<&str>(args, working_dir, &[])
+}
+
+/// Runs the fpm binary with the given arguments and extra environment variables
+pub fn run_fpm_with_env<V: AsRef<std::ffi::OsStr>>(
+    args: &[&str],
+    working_dir: &Path,
+    envs: &[(&str, V)],
+) -> Result<std::process::Output> {
     let binary_path = get_fpm_binary_path();
 
     if !binary_path.exists() {
@@ -127,6 +137,7 @@ pub fn run_fpm(args: &[&str], working_dir: &Path) -> Result<std::process::Output
 
     let output = std::process::Command::new(&binary_path)
         .args(args)
+        .envs(envs.iter().map(|(key, value)| (key, value)))
         .current_dir(working_dir)
         .output()?;
 
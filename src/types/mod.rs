@@ -1,6 +1,10 @@
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path, PathBuf};
+
+use crate::settings::Settings;
+use crate::shorthand::GitProtocol;
 
 /// The fpm manifest file identifier
 pub const FPM_IDENTIFIER: &str = "fpm-bundle";
@@ -14,8 +18,23 @@ pub const DEFAULT_REMOTE: &str = "fpm";
 /// Directory name where bundles are stored
 pub const BUNDLE_DIR: &str = ".fpm";
 
+/// Lockfile written next to the top-level bundle.toml
+pub const LOCKFILE_NAME: &str = "fpm.lock";
+
+/// Current lockfile format version
+pub const LOCKFILE_VERSION: u32 = 1;
+
+/// File inside each bundle directory recording how its bundles were installed
+pub const STATE_FILE: &str = "state.toml";
+
+/// File inside each bundle directory recording the hash of every file of its bundles
+pub const CHECKSUM_FILE: &str = "checksums.toml";
+
+/// Length of abbreviated commit hashes in descriptions
+const SHORT_COMMIT_LEN: usize = 7;
+
 /// The bundle manifest structure (bundle.toml)
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct BundleManifest {
     /// The fpm version that created this manifest
     pub fpm_version: String,
@@ -36,14 +55,86 @@ pub struct BundleManifest {
     #[serde(default)]
     pub description: Option<String>,
 
+    /// License of the bundle's assets as an SPDX expression, e.g. "MIT OR Apache-2.0"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+
+    /// Authors of the bundle, e.g. "Jane Doe <jane@example.com>"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+
+    /// Keywords describing the bundle, used to find it in a registry
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+
+    /// Website of the bundle
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+
+    /// Repository the bundle's sources are developed in, shown to consumers. Unlike
+    /// `publish_url`, it isn't used by fpm itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+
+    /// Path of the bundle's readme, relative to bundle.toml
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub readme: Option<PathBuf>,
+
     /// Root directory where artifacts are stored (relative to bundle.toml)
     /// If None, this is a purely consuming bundle (assembling-only)
     #[serde(default)]
     pub root: Option<PathBuf>,
 
+    /// Repository `fpm publish` pushes the root directory to, e.g.
+    /// "git@github.com:example/icons.git". Overrides the remotes of the root
+    /// directory's repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publish_url: Option<String>,
+
+    /// Branch `fpm publish` pushes to (main if not given)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publish_branch: Option<String>,
+
+    /// Further repositories `fpm publish` pushes the same branch and tag to, e.g. a
+    /// GitHub mirror of a bundle published to an internal server
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub publish_mirrors: Vec<String>,
+
+    /// Template of the commit messages `fpm push` writes for this bundle when no
+    /// message is given, e.g. "assets: update {bundle} to {version}"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_message: Option<String>,
+
+    /// Default clone depth for bundles that don't set their own `depth`
+    #[serde(default)]
+    pub depth: Option<u32>,
+
+    /// Protocol used to expand shorthand sources such as `github:org/repo`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<GitProtocol>,
+
+    /// Install nested bundles that several bundles depend on once and link them into
+    /// each, as with `fpm install --dedupe`. Only read from the top-level manifest.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dedupe: bool,
+
     /// List of bundles to fetch
     #[serde(default)]
     pub bundles: HashMap<String, BundleDependency>,
+
+    /// Replacement sources for repositories, keyed by repository URL. Only the
+    /// top-level manifest's patches are used, and they apply to nested bundles too.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub patch: HashMap<String, BundlePatch>,
+
+    /// Commands run around installs
+    #[serde(default, skip_serializing_if = "BundleHooks::is_empty")]
+    pub hooks: BundleHooks,
+
+    /// Shell commands `fpm run <name>` runs, keyed by name, e.g.
+    /// `optimize = "svgo -r assets"`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub scripts: BTreeMap<String, String>,
 }
 
 fn default_identifier() -> String {
@@ -60,6 +151,7 @@ impl BundleManifest {
             description: None,
             root: None,
             bundles: HashMap::new(),
+            ..Default::default()
         }
     }
 
@@ -71,18 +163,36 @@ impl BundleManifest {
     pub fn is_source_bundle(&self) -> bool {
         self.root.is_some()
     }
+
+    /// Fills in bundle settings left unset with the manifest-wide defaults, falling
+    /// back to the ones of `settings`
+    pub fn apply_bundle_defaults(&mut self, settings: &Settings) {
+        let depth = self.depth.or(settings.depth);
+        for dependency in self.bundles.values_mut() {
+            if dependency.depth.is_none() {
+                dependency.depth = depth;
+            }
+        }
+    }
 }
 
 /// A bundle dependency specification
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct BundleDependency {
     /// Version of the bundle to fetch
+    #[serde(default)]
     pub version: String,
 
-    /// Git repository URL (SSH or HTTPS)
+    /// Git repository URL (SSH or HTTPS). Empty for a local bundle (see `path`).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub git: String,
 
-    /// Optional subdirectory within the git repository
+    /// Optional subdirectory within the git repository (or archive, artifact or S3 prefix). Only its
+    /// contents are installed into the bundle directory, and `fpm push` copies changes
+    /// back into it. `include` and `exclude` are relative to this subdirectory.
+    ///
+    /// Without `git`, a local directory (relative to the manifest) that is linked into
+    /// the bundle directory instead of cloned, e.g. `path = "../shared-assets"`.
     #[serde(default)]
     pub path: Option<PathBuf>,
 
@@ -90,6 +200,11 @@ pub struct BundleDependency {
     #[serde(default)]
     pub branch: Option<String>,
 
+    /// Optional commit to pin the bundle to, regardless of where its branch moves.
+    /// Takes precedence over `version`; `branch` is still used to fetch the commit.
+    #[serde(default)]
+    pub rev: Option<String>,
+
     /// Optional path to SSH private key for authentication.
     /// If provided, SSH authentication will be used instead of HTTPS.
     /// The path can be absolute or relative to the user's home directory (e.g., "~/.ssh/id_rsa").
@@ -100,13 +215,74 @@ pub struct BundleDependency {
     pub ssh_key: Option<PathBuf>,
 
     /// Optional list of directories/files to include from the bundle.
-    /// If specified, only these paths will be copied from the cloned repository.
+    /// If specified, only these paths will be checked out from the cloned repository.
     /// If not specified, all files and directories are included (default behavior).
-    /// Paths are relative to the bundle root.
+    /// Paths are relative to the bundle root and may be globs (`assets/**/*.png`).
     ///
-    /// Example: `include = ["folder2", "folder3"]` will only copy folder2 and folder3
+    /// Example: `include = ["folder2", "folder3"]` will only check out folder2 and folder3
     #[serde(default)]
     pub include: Option<Vec<String>>,
+
+    /// Optional list of paths or globs to leave out of the bundle, following .gitignore
+    /// rules (`*.psd` matches at any depth, `tests/**` is relative to the bundle root).
+    /// Applied together with `include`; a path matching both is excluded.
+    #[serde(default)]
+    pub exclude: Option<Vec<String>>,
+
+    /// Optional number of commits to fetch (a shallow clone). Only the tip is
+    /// needed to install a bundle, so `depth = 1` saves time on long histories.
+    #[serde(default)]
+    pub depth: Option<u32>,
+
+    /// Optional partial clone filter, e.g. "blob:none" or "tree:0". Only the objects
+    /// needed for the checked-out commit are downloaded, which keeps large binary
+    /// repositories small. Supported by the git CLI backend.
+    #[serde(default)]
+    pub filter: Option<String>,
+
+    /// URL of a .tar.gz, .tgz, .tar or .zip archive to install instead of a git
+    /// repository. `file://` URLs read a local archive.
+    #[serde(default)]
+    pub url: Option<String>,
+
+    /// Optional SHA-256 checksum (hex) the downloaded archive must match
+    #[serde(default)]
+    pub sha256: Option<String>,
+
+    /// OCI artifact to install instead of a git repository, as `registry/repository:tag`
+    /// or `registry/repository@sha256:<digest>`, e.g. `ghcr.io/org/bundle:1.2.0`
+    #[serde(default)]
+    pub oci: Option<String>,
+
+    /// Object storage prefix to install instead of a git repository, as
+    /// `s3://bucket/prefix`. Needs fpm built with the `s3` feature.
+    #[serde(default)]
+    pub s3: Option<String>,
+
+    /// Optional group the bundle belongs to, e.g. "dev" for bundles only needed while
+    /// designing. `fpm install --group` and `--exclude-group` select bundles by group.
+    #[serde(default)]
+    pub group: Option<String>,
+
+    /// An optional bundle isn't installed by `fpm install` unless it is asked for with
+    /// `--with <name>`. Once installed, it is kept up to date like any other bundle.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub optional: bool,
+
+    /// Author of the commits `fpm push` makes in this bundle, as "Name <email>",
+    /// e.g. a team bot identity; takes precedence over the global `author`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+
+    /// Part of the version `fpm push` increments when the version wasn't changed by
+    /// hand; `patch` if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bump: Option<BumpLevel>,
+
+    /// Lets a version requirement such as `^1.2` select pre-release tags
+    /// (`v1.3.0-beta.1`), which are skipped otherwise
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_prerelease: bool,
 }
 
 impl BundleDependency {
@@ -114,14 +290,205 @@ impl BundleDependency {
         self.branch.as_deref().unwrap_or(DEFAULT_BRANCH)
     }
 
+    /// Returns the version to resolve against the remote's tags, if any.
+    /// An explicit branch or rev takes precedence; "*" or an empty version tracks the branch.
+    pub fn version_requirement(&self) -> Option<&str> {
+        if self.branch.is_some() || self.rev.is_some() {
+            return None;
+        }
+
+        let version = self.version.trim();
+        if version.is_empty() || version == "*" {
+            None
+        } else {
+            Some(version)
+        }
+    }
+
+    /// Describes what the bundle asks for: a pinned rev (`@3f2a9c1`), a version
+    /// requirement or a branch
+    pub fn requested_ref(&self) -> String {
+        if let Some(rev) = &self.rev {
+            let short = rev.get(..SHORT_COMMIT_LEN).unwrap_or(rev);
+            return format!("@{}", short);
+        }
+
+        match self.version_requirement() {
+            Some(version) => version.to_string(),
+            None => self.branch().to_string(),
+        }
+    }
+
+    /// Returns the subdirectory installed as the bundle, or None if the whole
+    /// repository is ("", "." and "./" select the repository root)
+    pub fn subdirectory(&self) -> Option<&Path> {
+        if self.is_local() {
+            return None;
+        }
+
+        self.path
+            .as_deref()
+            .filter(|path| path.components().any(|c| c != Component::CurDir))
+    }
+
+    /// Returns where the bundle's files come from
+    pub fn source_kind(&self) -> SourceKind {
+        if !self.git.is_empty() {
+            SourceKind::Git
+        } else if self.url.is_some() {
+            SourceKind::Archive
+        } else if self.oci.is_some() {
+            SourceKind::Oci
+        } else if self.s3.is_some() {
+            SourceKind::S3
+        } else {
+            SourceKind::Local
+        }
+    }
+
+    /// Returns true if the bundle is a local directory rather than a git repository
+    pub fn is_local(&self) -> bool {
+        self.source_kind() == SourceKind::Local
+    }
+
+    /// Returns the local directory of a local bundle, relative to its manifest
+    pub fn local_path(&self) -> Option<&Path> {
+        if self.is_local() {
+            self.path.as_deref()
+        } else {
+            None
+        }
+    }
+
+    /// Describes where the bundle comes from: its git URL, archive URL, OCI reference,
+    /// S3 location or local directory
+    pub fn source(&self) -> String {
+        match self.source_kind() {
+            SourceKind::Git => self.git.clone(),
+            SourceKind::Archive => self.url.clone().unwrap_or_default(),
+            SourceKind::Oci => self.oci.clone().unwrap_or_default(),
+            SourceKind::S3 => self.s3.clone().unwrap_or_default(),
+            SourceKind::Local => self
+                .path
+                .as_deref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default(),
+        }
+    }
+
     /// Returns true if this dependency should use SSH authentication
     pub fn use_ssh(&self) -> bool {
         self.ssh_key.is_some()
     }
 }
 
-/// Status of a bundle
+/// The `[hooks]` table: shell commands run around installs. Those of the top-level
+/// manifest run in its directory; an installed bundle's `post_install` runs in the
+/// bundle's directory, if the `bundle_hooks` setting allows it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BundleHooks {
+    /// Run once before an install fetches any bundle (top-level manifest only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_install: Option<String>,
+
+    /// Run after every bundle an install fetched or moved to another commit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_install: Option<String>,
+}
+
+impl BundleHooks {
+    pub fn is_empty(&self) -> bool {
+        self.pre_install.is_none() && self.post_install.is_none()
+    }
+}
+
+/// A `[patch."<url>"]` entry: where to get a repository from instead, e.g. a fork
+/// or a local checkout
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BundlePatch {
+    /// Git repository to fetch instead
+    #[serde(default)]
+    pub git: Option<String>,
+
+    /// Branch of `git` to fetch
+    #[serde(default)]
+    pub branch: Option<String>,
+
+    /// Commit of `git` to pin the patched bundles to
+    #[serde(default)]
+    pub rev: Option<String>,
+
+    /// Local checkout of the repository to link instead, relative to the manifest
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+}
+
+impl BundlePatch {
+    /// Points a bundle installed from the patched repository at the replacement
+    /// source. A local `path` must already be resolved against the manifest.
+    pub fn apply(&self, dependency: &mut BundleDependency) {
+        if let Some(path) = &self.path {
+            dependency.path = Some(match dependency.subdirectory() {
+                Some(subdirectory) => path.join(subdirectory),
+                None => path.clone(),
+            });
+            dependency.git.clear();
+            return;
+        }
+
+        if let Some(git) = &self.git {
+            dependency.git = git.clone();
+        }
+        if self.branch.is_some() {
+            dependency.branch = self.branch.clone();
+            dependency.rev = None;
+        }
+        if self.rev.is_some() {
+            dependency.rev = self.rev.clone();
+        }
+    }
+}
+
+/// Returns true if two repository URLs name the same repository, ignoring a
+/// trailing slash or `.git`
+pub fn same_repository(a: &str, b: &str) -> bool {
+    let normalize = |url: &str| {
+        let trimmed = url.trim().trim_end_matches('/');
+        trimmed.strip_suffix(".git").unwrap_or(trimmed).to_string()
+    };
+    normalize(a) == normalize(b)
+}
+
+/// Where a bundle's files come from
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceKind {
+    /// A git repository (`git`)
+    Git,
+    /// A downloaded archive (`url`)
+    Archive,
+    /// An artifact in an OCI registry (`oci`)
+    Oci,
+    /// Objects under a prefix in S3-compatible storage (`s3`)
+    S3,
+    /// A local directory (`path` without `git`)
+    Local,
+}
+
+impl std::fmt::Display for SourceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceKind::Git => write!(f, "git"),
+            SourceKind::Archive => write!(f, "archive"),
+            SourceKind::Oci => write!(f, "oci"),
+            SourceKind::S3 => write!(f, "s3"),
+            SourceKind::Local => write!(f, "local"),
+        }
+    }
+}
+
+/// Status of a bundle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum BundleStatus {
     /// Bundle is synchronized with its remote source
     Synced,
@@ -129,6 +496,14 @@ pub enum BundleStatus {
     Unsynced,
     /// This is a source bundle (has artifacts to publish)
     Source,
+    /// Bundle is pinned to a `rev` but its working tree is on another commit
+    Drifted,
+    /// Bundle is linked from a local directory rather than installed from git
+    Local,
+    /// Version in the installed bundle's manifest doesn't satisfy the version the
+    /// manifest declaring it asks for
+    #[serde(rename = "mismatch")]
+    VersionMismatch,
 }
 
 impl std::fmt::Display for BundleStatus {
@@ -137,6 +512,9 @@ impl std::fmt::Display for BundleStatus {
             BundleStatus::Synced => write!(f, "synced"),
             BundleStatus::Unsynced => write!(f, "unsynced"),
             BundleStatus::Source => write!(f, "source"),
+            BundleStatus::Drifted => write!(f, "drifted"),
+            BundleStatus::Local => write!(f, "local"),
+            BundleStatus::VersionMismatch => write!(f, "mismatch"),
         }
     }
 }
@@ -154,6 +532,150 @@ pub struct ResolvedBundle {
     pub status: BundleStatus,
 }
 
+/// How a bundle was materialized in its bundle directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallStrategy {
+    /// A git clone of the source repository
+    Clone,
+    /// A link to a local working copy
+    Link,
+    /// A plain copy of a subdirectory of the source
+    Flatten,
+    /// A link to a copy shared by every bundle depending on the same source
+    /// (`fpm install --dedupe`)
+    Shared,
+}
+
+impl std::fmt::Display for InstallStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstallStrategy::Clone => write!(f, "clone"),
+            InstallStrategy::Link => write!(f, "link"),
+            InstallStrategy::Flatten => write!(f, "flatten"),
+            InstallStrategy::Shared => write!(f, "shared"),
+        }
+    }
+}
+
+/// Part of a semantic version a push increments
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum BumpLevel {
+    /// 1.2.3 -> 2.0.0, for breaking changes
+    Major,
+    /// 1.2.3 -> 1.3.0
+    Minor,
+    /// 1.2.3 -> 1.2.4
+    #[default]
+    Patch,
+    /// Leave the version alone, e.g. when versions are managed outside fpm
+    Off,
+}
+
+/// Install state of the bundles in one bundle directory (.fpm/state.toml)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InstallState {
+    /// Recorded state per bundle name
+    #[serde(default)]
+    pub bundles: BTreeMap<String, BundleState>,
+
+    /// Working copies linked in place of bundles with `fpm link`, keyed by install
+    /// path. Only recorded in the project's own .fpm directory.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub links: BTreeMap<String, PathBuf>,
+}
+
+/// Recorded install state of a single bundle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleState {
+    /// Source URL the bundle was installed from
+    pub source: String,
+    /// Commit checked out by the last install, if it could be resolved
+    #[serde(default)]
+    pub rev: Option<String>,
+    /// Branch (or tag) the bundle was installed from
+    pub branch: String,
+    /// Version requirement `branch` (a tag) was resolved from, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Include filter in effect when the bundle was installed
+    #[serde(default)]
+    pub include: Option<Vec<String>>,
+    /// Exclude filter in effect when the bundle was installed
+    #[serde(default)]
+    pub exclude: Option<Vec<String>>,
+    /// Subdirectory of the source installed as the bundle, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    /// How the bundle was materialized
+    pub strategy: InstallStrategy,
+    /// Unix timestamp (seconds) of the first install
+    pub installed_at: u64,
+    /// Unix timestamp (seconds) of the last install or update
+    pub updated_at: u64,
+}
+
+/// Resolved commits of all installed bundles (fpm.lock)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lockfile {
+    /// Lockfile format version
+    pub version: u32,
+    /// Locked bundles keyed by install path, e.g. "ui-kit" or "ui-kit/base-styles"
+    #[serde(default)]
+    pub bundles: BTreeMap<String, LockedBundle>,
+}
+
+impl Default for Lockfile {
+    fn default() -> Self {
+        Self {
+            version: LOCKFILE_VERSION,
+            bundles: BTreeMap::new(),
+        }
+    }
+}
+
+/// A bundle pinned to an exact commit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedBundle {
+    /// Source URL the commit was resolved from
+    pub git: String,
+    /// Branch (or tag) the commit was resolved from
+    pub branch: String,
+    /// Version requirement `branch` (a tag) was resolved from, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Full commit hash
+    pub commit: String,
+    /// Checksum of the installed files, e.g. "sha256:9f86d0…" (see `fpm verify`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+}
+
+/// Per-file change summary produced by a diff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDiffStat {
+    /// Path of the file relative to the bundle root
+    pub path: String,
+    /// Number of added lines (None for binary files)
+    pub insertions: Option<usize>,
+    /// Number of removed lines (None for binary files)
+    pub deletions: Option<usize>,
+}
+
+/// One commit of a bundle's history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitSummary {
+    pub id: String,
+    pub author: String,
+    /// Commit time in seconds since the Unix epoch
+    pub time: i64,
+    /// First line of the commit message
+    pub summary: String,
+    /// Paths (relative to the repository root) the commit changed
+    pub files: Vec<String>,
+}
+
 #[cfg(test)]
 mod unit_tests {
     use super::*;
@@ -193,6 +715,31 @@ mod unit_tests {
         assert_eq!(format!("{}", BundleStatus::Synced), "synced");
         assert_eq!(format!("{}", BundleStatus::Unsynced), "unsynced");
         assert_eq!(format!("{}", BundleStatus::Source), "source");
+        assert_eq!(format!("{}", BundleStatus::Drifted), "drifted");
+        assert_eq!(format!("{}", BundleStatus::Local), "local");
+        assert_eq!(format!("{}", BundleStatus::VersionMismatch), "mismatch");
+    }
+
+    #[test]
+    fn test_version_requirement() {
+        let mut dependency = BundleDependency {
+            version: "1.2.3".to_string(),
+            git: "https://github.com/example/icons.git".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(dependency.version_requirement(), Some("1.2.3"));
+
+        dependency.version = "*".to_string();
+        assert_eq!(dependency.version_requirement(), None);
+
+        // An explicit branch wins over the version
+        dependency.version = "1.2.3".to_string();
+        dependency.branch = Some("develop".to_string());
+        assert_eq!(dependency.version_requirement(), None);
+
+        dependency.branch = None;
+        dependency.rev = Some("0123456789abcdef".to_string());
+        assert_eq!(dependency.version_requirement(), None);
     }
 
     #[test]
@@ -10,8 +10,12 @@ use std::path::{Path, PathBuf};
 use std::sync::RwLock;
 
 use crate::config::save_manifest;
-use crate::git::GitOperations;
-use crate::types::{BundleDependency, BundleManifest, FPM_IDENTIFIER};
+use crate::filter::PathPatterns;
+use crate::git::{CloneOptions, CommitAuthor, GitOperations};
+use crate::types::{BundleDependency, BundleManifest, CommitSummary, FileDiffStat, FPM_IDENTIFIER};
+
+/// Commit reported as HEAD of every mock repository
+pub const MOCK_HEAD_COMMIT: &str = "0123456789abcdef0123456789abcdef01234567";
 
 /// Content for a mock bundle
 pub struct MockBundleContent {
@@ -47,6 +51,76 @@ pub struct MockGitOperations {
 
     /// Simulated local changes (path -> has changes)
     _local_changes: RwLock<HashMap<PathBuf, bool>>,
+
+    /// Mirrors that have been synced (url, path)
+    _mirrors: RwLock<Vec<(String, PathBuf)>>,
+
+    /// Commits explicitly checked out (path -> commit)
+    _checked_out: RwLock<HashMap<PathBuf, String>>,
+
+    /// Tags available on remotes (url -> tag names)
+    _tags: RwLock<HashMap<String, Vec<String>>>,
+
+    /// Sparse checkout patterns set on repositories (path -> sparse-checkout patterns)
+    _sparse: RwLock<HashMap<PathBuf, Vec<String>>>,
+
+    /// Commits repositories are ahead of and behind their remote (path -> counts)
+    _ahead_behind: RwLock<HashMap<PathBuf, (usize, usize)>>,
+
+    /// Branches fetched into remote-tracking refs (path, branch)
+    _fetched_branches: RwLock<Vec<(PathBuf, String)>>,
+
+    /// Uncommitted changes of repositories compared to HEAD (path -> file stats)
+    _diff_stats: RwLock<HashMap<PathBuf, Vec<FileDiffStat>>>,
+
+    /// Stashed changes of repositories (path -> stashes, latest last)
+    _stashes: RwLock<HashMap<PathBuf, Vec<Vec<FileDiffStat>>>>,
+
+    /// Files whose stashed changes conflict when applied (path -> files)
+    _stash_conflicts: RwLock<HashMap<PathBuf, Vec<String>>>,
+
+    /// Commit history of repositories, newest first (path -> commits)
+    _log: RwLock<HashMap<PathBuf, Vec<CommitSummary>>>,
+
+    /// Commits made with `commit_all`, oldest first
+    _commits: RwLock<Vec<MockCommit>>,
+
+    /// Branches pushed to origin, oldest first (path, branch)
+    _pushes: RwLock<Vec<(PathBuf, String)>>,
+
+    /// Branches created with `create_branch` (path, branch)
+    _created_branches: RwLock<Vec<(PathBuf, String)>>,
+
+    /// Branches checked out in repositories (path -> branch)
+    _current_branches: RwLock<HashMap<PathBuf, String>>,
+
+    /// Branches remotes point HEAD at (url -> branch)
+    _default_branches: RwLock<HashMap<String, String>>,
+
+    /// Remotes added to repositories, oldest first (path, name, url)
+    _added_remotes: RwLock<Vec<(PathBuf, String, String)>>,
+
+    /// Tags created with `create_tag` (path, tag)
+    _created_tags: RwLock<Vec<(PathBuf, String)>>,
+
+    /// Tags pushed to remotes, oldest first (path, tag)
+    _pushed_tags: RwLock<Vec<(PathBuf, String)>>,
+
+    /// Remotes rejecting every push
+    _rejecting_urls: RwLock<Vec<String>>,
+
+    /// Remotes that can't be reached
+    _unreachable_urls: RwLock<Vec<String>>,
+}
+
+/// A commit made with `commit_all` or `commit_paths`
+#[derive(Debug, Clone)]
+pub struct MockCommit {
+    pub path: PathBuf,
+    pub message: String,
+    pub author: Option<CommitAuthor>,
+    /// The paths committed; None if every change was
+    pub paths: Option<Vec<String>>,
 }
 
 #[derive(Clone)]
@@ -55,6 +129,9 @@ pub struct ClonedRepo {
     pub url: String,
     pub path: PathBuf,
     pub branch: String,
+    pub reference: Option<PathBuf>,
+    pub depth: Option<u32>,
+    pub filter: Option<String>,
 }
 
 impl MockGitOperations {
@@ -64,6 +141,26 @@ impl MockGitOperations {
             _cloned_repos: RwLock::new(Vec::new()),
             _initialized_repos: RwLock::new(Vec::new()),
             _local_changes: RwLock::new(HashMap::new()),
+            _mirrors: RwLock::new(Vec::new()),
+            _checked_out: RwLock::new(HashMap::new()),
+            _tags: RwLock::new(HashMap::new()),
+            _sparse: RwLock::new(HashMap::new()),
+            _ahead_behind: RwLock::new(HashMap::new()),
+            _fetched_branches: RwLock::new(Vec::new()),
+            _diff_stats: RwLock::new(HashMap::new()),
+            _stashes: RwLock::new(HashMap::new()),
+            _stash_conflicts: RwLock::new(HashMap::new()),
+            _log: RwLock::new(HashMap::new()),
+            _commits: RwLock::new(Vec::new()),
+            _pushes: RwLock::new(Vec::new()),
+            _created_branches: RwLock::new(Vec::new()),
+            _current_branches: RwLock::new(HashMap::new()),
+            _default_branches: RwLock::new(HashMap::new()),
+            _added_remotes: RwLock::new(Vec::new()),
+            _created_tags: RwLock::new(Vec::new()),
+            _pushed_tags: RwLock::new(Vec::new()),
+            _rejecting_urls: RwLock::new(Vec::new()),
+            _unreachable_urls: RwLock::new(Vec::new()),
         }
     }
 
@@ -92,11 +189,140 @@ impl MockGitOperations {
         );
     }
 
+    /// Sets the tags a remote reports when listing its refs
+    pub fn set_remote_tags(&self, url: &str, tags: &[&str]) {
+        let mut remote_tags = self._tags.write().unwrap();
+        remote_tags.insert(
+            url.to_string(),
+            tags.iter().map(|tag| tag.to_string()).collect(),
+        );
+    }
+
+    /// Sets the branch a remote's HEAD points to
+    pub fn set_default_branch(&self, url: &str, branch: &str) {
+        let mut branches = self._default_branches.write().unwrap();
+        branches.insert(url.to_string(), branch.to_string());
+    }
+
     /// Returns the list of cloned repositories
     pub fn get_cloned_repos(&self) -> Vec<ClonedRepo> {
         self._cloned_repos.read().unwrap().clone()
     }
 
+    /// Returns the list of synced mirrors as (url, path)
+    pub fn get_mirrors(&self) -> Vec<(String, PathBuf)> {
+        self._mirrors.read().unwrap().clone()
+    }
+
+    /// Returns the commit explicitly checked out at a path, if any
+    pub fn get_checked_out_commit(&self, path: &Path) -> Option<String> {
+        self._checked_out.read().unwrap().get(path).cloned()
+    }
+
+    /// Returns the branches fetched into remote-tracking refs, as (path, branch)
+    pub fn get_fetched_branches(&self) -> Vec<(PathBuf, String)> {
+        self._fetched_branches.read().unwrap().clone()
+    }
+
+    /// Returns the sparse-checkout patterns set on a repository, if any
+    pub fn get_sparse_checkout(&self, path: &Path) -> Option<Vec<String>> {
+        self._sparse.read().unwrap().get(path).cloned()
+    }
+
+    /// Simulates commits a repository is ahead of and behind its remote
+    pub fn set_ahead_behind(&self, path: &Path, ahead: usize, behind: usize) {
+        let mut counts = self._ahead_behind.write().unwrap();
+        counts.insert(path.to_path_buf(), (ahead, behind));
+    }
+
+    /// Simulates uncommitted changes to files of a repository
+    pub fn set_changed_files(&self, path: &Path, files: &[&str]) {
+        let stats = files
+            .iter()
+            .map(|file| FileDiffStat {
+                path: file.to_string(),
+                insertions: Some(1),
+                deletions: Some(1),
+            })
+            .collect();
+        let mut diff_stats = self._diff_stats.write().unwrap();
+        diff_stats.insert(path.to_path_buf(), stats);
+    }
+
+    /// Makes applying the stash of a repository conflict in these files
+    pub fn set_stash_conflicts(&self, path: &Path, files: &[&str]) {
+        let mut conflicts = self._stash_conflicts.write().unwrap();
+        conflicts.insert(
+            path.to_path_buf(),
+            files.iter().map(|file| file.to_string()).collect(),
+        );
+    }
+
+    /// Returns the changed files of a repository (see `set_changed_files`)
+    pub fn get_changed_files(&self, path: &Path) -> Vec<String> {
+        let diff_stats = self._diff_stats.read().unwrap();
+        diff_stats
+            .get(path)
+            .map(|stats| stats.iter().map(|stat| stat.path.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the commits made with `commit_all`, oldest first
+    pub fn get_commits(&self) -> Vec<MockCommit> {
+        self._commits.read().unwrap().clone()
+    }
+
+    /// Returns the branches pushed to origin as (path, branch), oldest first
+    pub fn get_pushes(&self) -> Vec<(PathBuf, String)> {
+        self._pushes.read().unwrap().clone()
+    }
+
+    /// Simulates a branch checked out in a repository
+    pub fn set_current_branch(&self, path: &Path, branch: &str) {
+        let mut branches = self._current_branches.write().unwrap();
+        branches.insert(path.to_path_buf(), branch.to_string());
+    }
+
+    /// Returns the tags pushed as (path, tag), oldest first
+    pub fn get_pushed_tags(&self) -> Vec<(PathBuf, String)> {
+        self._pushed_tags.read().unwrap().clone()
+    }
+
+    /// Simulates a remote repository that rejects every push
+    pub fn set_push_rejected(&self, url: &str) {
+        self._rejecting_urls.write().unwrap().push(url.to_string());
+    }
+
+    /// Simulates a remote repository that can't be reached, failing tag lookups
+    pub fn set_unreachable(&self, url: &str) {
+        self._unreachable_urls
+            .write()
+            .unwrap()
+            .push(url.to_string());
+    }
+
+    /// Returns the remotes added to repositories as (path, name, url)
+    pub fn get_added_remotes(&self) -> Vec<(PathBuf, String, String)> {
+        self._added_remotes.read().unwrap().clone()
+    }
+
+    /// Returns the branches created with `create_branch` as (path, branch)
+    pub fn get_created_branches(&self) -> Vec<(PathBuf, String)> {
+        self._created_branches.read().unwrap().clone()
+    }
+
+    /// Returns the number of stashes of a repository
+    pub fn get_stash_count(&self, path: &Path) -> usize {
+        let stashes = self._stashes.read().unwrap();
+        stashes.get(path).map_or(0, Vec::len)
+    }
+
+    /// Sets the commit history of a repository, newest first
+    pub fn set_log(&self, path: &Path, commits: Vec<CommitSummary>) {
+        let mut log = self._log.write().unwrap();
+        log.insert(path.to_path_buf(), commits);
+    }
+
     /// Simulates local changes for a path
     #[allow(dead_code)]
     pub fn set_local_changes(&self, path: &Path, has_changes: bool) {
@@ -104,17 +330,19 @@ impl MockGitOperations {
         changes.insert(path.to_path_buf(), has_changes);
     }
 
-    /// Creates mock bundle files at the target path
+    /// Creates mock bundle files at the target path, inside the registered
+    /// subdirectory of the repository
     fn create_mock_bundle_files(
         &self,
         target_path: &Path,
         registration: &RemoteBundleRegistration,
     ) -> Result<()> {
-        fs::create_dir_all(target_path)?;
+        let bundle_root = target_path.join(&registration.path);
+        fs::create_dir_all(&bundle_root)?;
 
         // Write content files
         for (filename, content) in &registration.content.files {
-            let file_path = target_path.join(filename);
+            let file_path = bundle_root.join(filename);
             if let Some(parent) = file_path.parent() {
                 fs::create_dir_all(parent)?;
             }
@@ -130,9 +358,10 @@ impl MockGitOperations {
             description: Some(registration.content.description.clone()),
             root: None,
             bundles: registration.nested_bundles.clone(),
+            ..Default::default()
         };
 
-        let manifest_path = target_path.join("bundle.toml");
+        let manifest_path = bundle_root.join("bundle.toml");
         save_manifest(&manifest, &manifest_path)?;
 
         // Mark as initialized repo
@@ -144,20 +373,17 @@ impl MockGitOperations {
 }
 
 impl GitOperations for MockGitOperations {
-    fn clone_repository(
-        &self,
-        url: &str,
-        path: &Path,
-        branch: &str,
-        _ssh_key: Option<&Path>,
-    ) -> Result<()> {
+    fn clone_repository(&self, url: &str, path: &Path, options: &CloneOptions) -> Result<()> {
         // Record the clone operation
         {
             let mut cloned = self._cloned_repos.write().unwrap();
             cloned.push(ClonedRepo {
                 url: url.to_string(),
                 path: path.to_path_buf(),
-                branch: branch.to_string(),
+                branch: options.branch.to_string(),
+                reference: options.reference.map(Path::to_path_buf),
+                depth: options.depth,
+                filter: options.filter.map(str::to_string),
             });
         }
 
@@ -180,6 +406,7 @@ impl GitOperations for MockGitOperations {
                 description: Some(format!("Mock bundle from {}", url)),
                 root: None,
                 bundles: HashMap::new(),
+                ..Default::default()
             };
 
             let manifest_path = path.join("bundle.toml");
@@ -189,7 +416,7 @@ impl GitOperations for MockGitOperations {
         Ok(())
     }
 
-    fn fetch_repository(&self, _path: &Path, _branch: &str) -> Result<()> {
+    fn fetch_repository(&self, _path: &Path, _branch: &str, _depth: Option<u32>) -> Result<()> {
         // Mock: do nothing, consider it fetched
         Ok(())
     }
@@ -203,18 +430,100 @@ impl GitOperations for MockGitOperations {
         Ok(())
     }
 
-    fn add_remote(&self, _path: &Path, _name: &str, _url: &str) -> Result<()> {
-        // Mock: do nothing
+    fn add_remote(&self, path: &Path, name: &str, url: &str) -> Result<()> {
+        // Mock: record the remote
+        self._added_remotes.write().unwrap().push((
+            path.to_path_buf(),
+            name.to_string(),
+            url.to_string(),
+        ));
         Ok(())
     }
 
-    fn commit_all(&self, _path: &Path, _message: &str) -> Result<()> {
-        // Mock: do nothing
+    fn commit_all(&self, path: &Path, message: &str, author: Option<&CommitAuthor>) -> Result<()> {
+        // Mock: record the commit
+        self._commits.write().unwrap().push(MockCommit {
+            path: path.to_path_buf(),
+            message: message.to_string(),
+            author: author.cloned(),
+            paths: None,
+        });
         Ok(())
     }
 
-    fn push(&self, _path: &Path, _remote: &str, _branch: &str) -> Result<()> {
-        // Mock: do nothing
+    fn commit_paths(
+        &self,
+        path: &Path,
+        paths: &[String],
+        message: &str,
+        author: Option<&CommitAuthor>,
+    ) -> Result<()> {
+        // Mock: record the commit
+        self._commits.write().unwrap().push(MockCommit {
+            path: path.to_path_buf(),
+            message: message.to_string(),
+            author: author.cloned(),
+            paths: Some(paths.to_vec()),
+        });
+        Ok(())
+    }
+
+    fn push(&self, path: &Path, remote: &str, branch: &str) -> Result<()> {
+        // Mock: fail for remotes set up with a rejecting URL, record the branch pushed
+        let remote_url = self
+            ._added_remotes
+            .read()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|(repo, name, _)| repo == path && name == remote)
+            .map(|(_, _, url)| url.clone());
+        if let Some(url) = remote_url {
+            if self._rejecting_urls.read().unwrap().contains(&url) {
+                anyhow::bail!("Mock: {} rejected the push", url);
+            }
+        }
+        self._pushes
+            .write()
+            .unwrap()
+            .push((path.to_path_buf(), branch.to_string()));
+        Ok(())
+    }
+
+    fn create_tag(
+        &self,
+        path: &Path,
+        name: &str,
+        _message: &str,
+        _author: Option<&CommitAuthor>,
+        force: bool,
+    ) -> Result<()> {
+        // Mock: record the tag, failing like git if it exists
+        let mut tags = self._created_tags.write().unwrap();
+        let tag = (path.to_path_buf(), name.to_string());
+        if tags.contains(&tag) && !force {
+            anyhow::bail!("Mock: tag '{}' already exists", name);
+        }
+        tags.push(tag);
+        Ok(())
+    }
+
+    fn push_tag(&self, path: &Path, _remote: &str, name: &str, _force: bool) -> Result<()> {
+        // Mock: record the tag pushed
+        self._pushed_tags
+            .write()
+            .unwrap()
+            .push((path.to_path_buf(), name.to_string()));
+        Ok(())
+    }
+
+    fn create_branch(&self, path: &Path, name: &str) -> Result<()> {
+        // Mock: record the branch created
+        self._created_branches
+            .write()
+            .unwrap()
+            .push((path.to_path_buf(), name.to_string()));
+        self.set_current_branch(path, name);
         Ok(())
     }
 
@@ -223,9 +532,15 @@ impl GitOperations for MockGitOperations {
         Ok(changes.get(path).copied().unwrap_or(false))
     }
 
+    fn changed_files(&self, path: &Path) -> Result<Vec<String>> {
+        // Mock: the files set with `set_changed_files`
+        Ok(self.get_changed_files(path))
+    }
+
     fn is_repository(&self, path: &Path) -> bool {
+        // A deleted directory is no repository any more
         let initialized = self._initialized_repos.read().unwrap();
-        initialized.contains(&path.to_path_buf())
+        initialized.contains(&path.to_path_buf()) && path.exists()
     }
 
     fn get_file_from_head(&self, repo_path: &Path, file_path: &str) -> Result<String> {
@@ -234,6 +549,170 @@ impl GitOperations for MockGitOperations {
         std::fs::read_to_string(&full_path)
             .with_context(|| format!("Mock: file '{}' not found", full_path.display()))
     }
+
+    fn head_commit(&self, path: &Path) -> Result<String> {
+        if !self.is_repository(path) {
+            anyhow::bail!("Mock: {} is not a repository", path.display());
+        }
+        Ok(self
+            .get_checked_out_commit(path)
+            .unwrap_or_else(|| MOCK_HEAD_COMMIT.to_string()))
+    }
+
+    fn current_branch(&self, path: &Path) -> Result<Option<String>> {
+        // Mock: detached unless a branch was set or created
+        Ok(self._current_branches.read().unwrap().get(path).cloned())
+    }
+
+    fn checkout_commit(&self, path: &Path, _branch: &str, commit: &str) -> Result<()> {
+        let mut checked_out = self._checked_out.write().unwrap();
+        checked_out.insert(path.to_path_buf(), commit.to_string());
+        Ok(())
+    }
+
+    fn list_remote_tags(&self, url: &str, _ssh_key: Option<&Path>) -> Result<Vec<String>> {
+        if self
+            ._unreachable_urls
+            .read()
+            .unwrap()
+            .iter()
+            .any(|u| u == url)
+        {
+            anyhow::bail!("Mock: could not resolve the host of {}", url);
+        }
+        let tags = self._tags.read().unwrap();
+        Ok(tags.get(url).cloned().unwrap_or_default())
+    }
+
+    fn remote_branch_commit(
+        &self,
+        url: &str,
+        _branch: &str,
+        _ssh_key: Option<&Path>,
+    ) -> Result<Option<String>> {
+        let remotes = self._remotes.read().unwrap();
+        Ok(remotes
+            .contains_key(url)
+            .then(|| MOCK_HEAD_COMMIT.to_string()))
+    }
+
+    fn remote_default_branch(&self, url: &str, _ssh_key: Option<&Path>) -> Result<Option<String>> {
+        // Mock: the branch set with `set_default_branch`, if any
+        Ok(self._default_branches.read().unwrap().get(url).cloned())
+    }
+
+    fn set_sparse_checkout(&self, path: &Path, patterns: &PathPatterns) -> Result<()> {
+        // Mock: record the patterns without touching the file system
+        let mut sparse = self._sparse.write().unwrap();
+        sparse.insert(path.to_path_buf(), patterns.sparse_patterns());
+        Ok(())
+    }
+
+    fn fetch_remote_branch(&self, path: &Path, branch: &str) -> Result<()> {
+        // Mock: record the fetch, consider it done
+        let mut fetched = self._fetched_branches.write().unwrap();
+        fetched.push((path.to_path_buf(), branch.to_string()));
+        Ok(())
+    }
+
+    fn diff_stat(&self, path: &Path, _from: &str, _to: Option<&str>) -> Result<Vec<FileDiffStat>> {
+        // Mock: no differences unless changes were set
+        let diff_stats = self._diff_stats.read().unwrap();
+        Ok(diff_stats.get(path).cloned().unwrap_or_default())
+    }
+
+    fn diff_patch(&self, _path: &Path, _from: &str, _to: Option<&str>) -> Result<String> {
+        // Mock: no differences
+        Ok(String::new())
+    }
+
+    fn ahead_behind(&self, path: &Path, _revision: &str) -> Result<(usize, usize)> {
+        // Mock: up to date unless counts were set
+        let counts = self._ahead_behind.read().unwrap();
+        Ok(counts.get(path).copied().unwrap_or((0, 0)))
+    }
+
+    fn stash_changes(&self, path: &Path) -> Result<bool> {
+        // Mock: move the changed files into a stash
+        let Some(changes) = self._diff_stats.write().unwrap().remove(path) else {
+            return Ok(false);
+        };
+        let mut stashes = self._stashes.write().unwrap();
+        stashes.entry(path.to_path_buf()).or_default().push(changes);
+        Ok(true)
+    }
+
+    fn apply_stash(&self, path: &Path) -> Result<Vec<String>> {
+        // Mock: conflicting stashes are kept, the others restore the changed files
+        if let Some(conflicts) = self._stash_conflicts.read().unwrap().get(path) {
+            return Ok(conflicts.clone());
+        }
+        let changes = self
+            ._stashes
+            .write()
+            .unwrap()
+            .get_mut(path)
+            .and_then(Vec::pop)
+            .context("Mock: no stash to apply")?;
+        self._diff_stats
+            .write()
+            .unwrap()
+            .insert(path.to_path_buf(), changes);
+        Ok(Vec::new())
+    }
+
+    fn move_repository(&self, from: &Path, to: &Path) -> Result<()> {
+        // Mock: move the files, and everything recorded about the repository with them
+        fs::rename(from, to)?;
+        for repo in self._initialized_repos.write().unwrap().iter_mut() {
+            if repo == from {
+                *repo = to.to_path_buf();
+            }
+        }
+        move_entry(&self._local_changes, from, to);
+        move_entry(&self._checked_out, from, to);
+        move_entry(&self._sparse, from, to);
+        move_entry(&self._ahead_behind, from, to);
+        move_entry(&self._diff_stats, from, to);
+        move_entry(&self._stashes, from, to);
+        move_entry(&self._stash_conflicts, from, to);
+        move_entry(&self._log, from, to);
+        Ok(())
+    }
+
+    fn log(&self, path: &Path, max_count: usize) -> Result<Vec<CommitSummary>> {
+        // Mock: no history unless one was set
+        let log = self._log.read().unwrap();
+        Ok(log
+            .get(path)
+            .map(|commits| commits.iter().take(max_count).cloned().collect())
+            .unwrap_or_default())
+    }
+
+    fn sync_mirror(&self, url: &str, path: &Path, _ssh_key: Option<&Path>) -> Result<()> {
+        // Mock: record the mirror without touching the file system
+        let mut mirrors = self._mirrors.write().unwrap();
+        mirrors.push((url.to_string(), path.to_path_buf()));
+        Ok(())
+    }
+
+    fn maintain_repository(&self, _path: &Path, _keep_unreachable: bool) -> Result<()> {
+        // Mock: do nothing
+        Ok(())
+    }
+
+    fn dissociate_repository(&self, _path: &Path) -> Result<()> {
+        // Mock: clones never borrow objects on disk
+        Ok(())
+    }
+}
+
+/// Re-keys what a mock map records for the repository at `from` to `to`
+fn move_entry<T>(map: &RwLock<HashMap<PathBuf, T>>, from: &Path, to: &Path) {
+    let mut map = map.write().unwrap();
+    if let Some(value) = map.remove(from) {
+        map.insert(to.to_path_buf(), value);
+    }
 }
 
 #[cfg(test)]
@@ -247,7 +726,11 @@ mod tests {
         let temp_dir = std::env::temp_dir().join("fpm_mock_test");
         let _ = fs::remove_dir_all(&temp_dir);
 
-        mock.clone_repository("https://github.com/test/repo.git", &temp_dir, "main", None)
+        let options = CloneOptions {
+            branch: "main",
+            ..Default::default()
+        };
+        mock.clone_repository("https://github.com/test/repo.git", &temp_dir, &options)
             .unwrap();
 
         let cloned = mock.get_cloned_repos();
@@ -274,13 +757,12 @@ mod tests {
         let temp_dir = std::env::temp_dir().join("fpm_mock_manifest_test");
         let _ = fs::remove_dir_all(&temp_dir);
 
-        mock.clone_repository(
-            "https://github.com/test/bundle.git",
-            &temp_dir,
-            "main",
-            None,
-        )
-        .unwrap();
+        let options = CloneOptions {
+            branch: "main",
+            ..Default::default()
+        };
+        mock.clone_repository("https://github.com/test/bundle.git", &temp_dir, &options)
+            .unwrap();
 
         assert!(temp_dir.join("bundle.toml").exists());
         assert!(temp_dir.join("test.txt").exists());
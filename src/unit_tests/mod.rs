@@ -11,14 +11,29 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use crate::commands::{install, status};
-use crate::config::load_manifest;
+use crate::checksum::FileChange;
+use crate::commands::{
+    add, doctor, fetch, info, install, link, log, maintain, outdated, publish, push, remove,
+    status, tree, update, validate, verify, yank,
+};
+use crate::config::{load_manifest, save_manifest};
+use crate::git::CommitAuthor;
+use crate::lockfile::{load_lockfile, save_lockfile};
+use crate::registry::fetch_index;
+use crate::report::{BundleAction, BundleResult, BundleResults};
+use crate::resolve::{resolve_ref, ResolvedRef};
+use crate::settings::Settings;
+use crate::source::{self, BundleSource, FetchRequest, Fetched};
+use crate::state::load_state;
 use crate::test_utils::{
     cleanup_test_env, create_bundle_manifest, create_sample_project, setup_test_env,
 };
-use crate::types::{BundleDependency, BundleStatus, BUNDLE_DIR};
+use crate::types::{
+    BumpLevel, BundleDependency, BundlePatch, BundleStatus, CommitSummary, InstallStrategy,
+    BUNDLE_DIR, DEFAULT_REMOTE, LOCKFILE_NAME, STATE_FILE,
+};
 
-use self::mock_git::{MockBundleContent, MockGitOperations};
+use self::mock_git::{MockBundleContent, MockGitOperations, MOCK_HEAD_COMMIT};
 
 const TEST_CATEGORY: &str = "unit";
 
@@ -43,6 +58,7 @@ fn test_full_install_workflow() -> Result<()> {
             branch: None,
             ssh_key: None,
             include: None,
+            ..Default::default()
         },
     );
 
@@ -55,6 +71,7 @@ fn test_full_install_workflow() -> Result<()> {
             branch: Some("main".to_string()),
             ssh_key: None,
             include: None,
+            ..Default::default()
         },
     );
 
@@ -81,6 +98,10 @@ fn test_full_install_workflow() -> Result<()> {
         "assets",
         create_mock_bundle_content("Martha's amazing designs"),
     );
+    mock_git.set_remote_tags(
+        "https://github.com/martha/designs.git",
+        &["v0.9.0", "v1.0.0"],
+    );
 
     mock_git.register_remote_bundle(
         "git@github.com:company/icons.git",
@@ -111,6 +132,18 @@ fn test_full_install_workflow() -> Result<()> {
     let cloned = mock_git.get_cloned_repos();
     assert_eq!(cloned.len(), 2, "Should have cloned 2 repositories");
 
+    // The version resolves to its tag; an explicit branch is used as-is
+    let martha_clone = cloned
+        .iter()
+        .find(|repo| repo.url == "https://github.com/martha/designs.git")
+        .unwrap();
+    assert_eq!(martha_clone.branch, "v1.0.0");
+    let icons_clone = cloned
+        .iter()
+        .find(|repo| repo.url == "git@github.com:company/icons.git")
+        .unwrap();
+    assert_eq!(icons_clone.branch, "main");
+
     // Step 7: Verify the bundle.toml files were created in installed bundles
     let martha_manifest_path = martha_bundle_dir.join("bundle.toml");
     assert!(
@@ -118,6 +151,16 @@ fn test_full_install_workflow() -> Result<()> {
         "Bundle should have its own manifest"
     );
 
+    // Bundles with a path are copied out of a hidden checkout of the repository
+    let martha_checkout = crate::flatten::checkout_path(&bundle_dir, "design-from-martha");
+    assert!(martha_checkout.join("assets").join("bundle.toml").exists());
+    assert!(!martha_bundle_dir.join("assets").exists());
+    let state = crate::state::load_state(&bundle_dir)?;
+    assert_eq!(
+        state.bundles["design-from-martha"].strategy,
+        InstallStrategy::Flatten
+    );
+
     // Step 8: Verify status can be checked
     let statuses = get_bundle_statuses_with_mock(&manifest_path, mock_git.clone())?;
     assert!(!statuses.is_empty(), "Should have bundle statuses");
@@ -148,6 +191,7 @@ fn test_nested_bundles_workflow() -> Result<()> {
             branch: None,
             ssh_key: None,
             include: None,
+            ..Default::default()
         },
     );
 
@@ -172,6 +216,7 @@ fn test_nested_bundles_workflow() -> Result<()> {
             branch: None,
             ssh_key: None,
             include: None,
+            ..Default::default()
         },
     );
 
@@ -187,6 +232,8 @@ fn test_nested_bundles_workflow() -> Result<()> {
         "",
         create_mock_bundle_content("Base CSS styles"),
     );
+    mock_git.set_remote_tags("https://github.com/example/ui-kit.git", &["v1.0.0"]);
+    mock_git.set_remote_tags("https://github.com/example/base-styles.git", &["1.0.0"]);
 
     // Execute install
     execute_install_with_mock(&manifest_path, mock_git.clone())?;
@@ -204,6 +251,51 @@ fn test_nested_bundles_workflow() -> Result<()> {
         "Nested bundle should be installed"
     );
 
+    // Maintenance covers both the top-level and the nested clone
+    let repositories = maintain::collect_installed_repositories(&manifest_path, mock_git.as_ref())?;
+    assert_eq!(
+        repositories,
+        vec![ui_kit_dir.clone(), nested_bundle_dir.clone()]
+    );
+
+    // Each bundle directory records the install state of its own bundles
+    let top_state = load_state(&bundle_dir)?;
+    let recorded = &top_state.bundles["ui-kit"];
+    assert_eq!(recorded.source, "https://github.com/example/ui-kit.git");
+    assert_eq!(recorded.branch, "v1.0.0");
+    assert_eq!(recorded.version.as_deref(), Some("1.0.0"));
+    assert_eq!(recorded.rev.as_deref(), Some(MOCK_HEAD_COMMIT));
+    assert_eq!(recorded.strategy, InstallStrategy::Clone);
+
+    let nested_state = load_state(&ui_kit_dir.join(BUNDLE_DIR))?;
+    assert!(nested_state.bundles.contains_key("base-styles"));
+    assert!(!nested_state.bundles.contains_key("ui-kit"));
+
+    // The lockfile pins both the top-level and the nested bundle
+    let lock_path = design_dir.join(LOCKFILE_NAME);
+    let mut lockfile = load_lockfile(&lock_path)?.expect("install should write fpm.lock");
+    assert_eq!(
+        lockfile.bundles.keys().collect::<Vec<_>>(),
+        vec!["ui-kit", "ui-kit/base-styles"]
+    );
+    assert_eq!(lockfile.bundles["ui-kit"].commit, MOCK_HEAD_COMMIT);
+
+    // Reinstalling checks out the locked commit instead of the branch tip
+    let pinned = "fedcba9876543210fedcba9876543210fedcba98".to_string();
+    lockfile.bundles.get_mut("ui-kit").unwrap().commit = pinned.clone();
+    save_lockfile(&lock_path, &lockfile)?;
+
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+    assert_eq!(mock_git.get_checked_out_commit(&ui_kit_dir), Some(pinned));
+
+    // A recorded bundle that disappeared from disk is reported as unsynced
+    fs::remove_dir_all(&nested_bundle_dir)?;
+    let statuses = get_bundle_statuses_with_mock(&manifest_path, mock_git.clone())?;
+    assert!(
+        statuses.contains(&("base-styles".to_string(), BundleStatus::Unsynced)),
+        "Missing bundle should still be listed as unsynced"
+    );
+
     cleanup_test_env(TEST_CATEGORY, test_name)?;
 
     Ok(())
@@ -230,6 +322,7 @@ fn test_duplicate_bundle_names_error() -> Result<()> {
             branch: None,
             ssh_key: None,
             include: None,
+            ..Default::default()
         },
     );
 
@@ -246,6 +339,7 @@ fn test_duplicate_bundle_names_error() -> Result<()> {
         "",
         create_mock_bundle_content("Test bundle"),
     );
+    mock_git.set_remote_tags("https://github.com/example/bundle.git", &["v1.0.0"]);
 
     // This should succeed (no actual duplicates in this test case)
     let result = execute_install_with_mock(&manifest_path, mock_git);
@@ -298,7 +392,3361 @@ fn test_source_bundle_status() -> Result<()> {
     Ok(())
 }
 
-// === Helper functions for mock-based execution ===
+#[test]
+fn test_aliased_bundles_share_store() -> Result<()> {
+    let test_name = "aliased_bundles";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    // Same repository installed twice: one pinned to a tag, one tracking main
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "icons-v1".to_string(),
+        BundleDependency {
+            version: "1.0.0".to_string(),
+            git: "https://github.com/example/icons.git".to_string(),
+            branch: Some("v1.0.0".to_string()),
+            ..Default::default()
+        },
+    );
+    bundles.insert(
+        "icons-next".to_string(),
+        BundleDependency {
+            version: "2.0.0".to_string(),
+            git: "https://github.com/example/icons.git".to_string(),
+            branch: Some("main".to_string()),
+            ..Default::default()
+        },
+    );
+
+    let manifest_path =
+        create_bundle_manifest(&design_dir, Some("Aliased bundles"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(
+        "https://github.com/example/icons.git",
+        "",
+        create_mock_bundle_content("Icons"),
+    );
+
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    // Both aliases get their own directory
+    let bundle_dir = design_dir.join(BUNDLE_DIR);
+    assert!(bundle_dir.join("icons-v1").exists());
+    assert!(bundle_dir.join("icons-next").exists());
+
+    // The repository is mirrored once and both clones borrow from it
+    let mirrors = mock_git.get_mirrors();
+    assert_eq!(
+        mirrors.len(),
+        1,
+        "Shared repository should be mirrored once"
+    );
+    let cloned = mock_git.get_cloned_repos();
+    assert_eq!(cloned.len(), 2);
+    assert!(cloned
+        .iter()
+        .all(|repo| repo.reference.as_ref() == Some(&mirrors[0].1)));
+
+    // Status labels the alias
+    let entries = status::collect_all_statuses(&manifest_path, mock_git)?;
+    let alias = entries.iter().find(|e| e.name == "icons-v1").unwrap();
+    assert_eq!(alias.alias_of.as_deref(), Some("icons-next"));
+    let primary = entries.iter().find(|e| e.name == "icons-next").unwrap();
+    assert!(primary.alias_of.is_none());
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_status_check_reports_missing_bundles() -> Result<()> {
+    let test_name = "status_check";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "icons".to_string(),
+        bundle_dependency("https://github.com/example/icons.git", "*"),
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Status check"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(
+        "https://github.com/example/icons.git",
+        "",
+        create_mock_bundle_content("Icons"),
+    );
+
+    // Before the install, the declared bundle is reported as unsynced
+    let entries = status::collect_all_statuses(&manifest_path, mock_git.clone())?;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].name, "icons");
+    assert_eq!(entries[0].status, BundleStatus::Unsynced);
+    let error = status::check_statuses(&entries).unwrap_err();
+    assert!(
+        error.to_string().contains("icons (unsynced)"),
+        "Got: {}",
+        error
+    );
+
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let entries = status::collect_all_statuses(&manifest_path, mock_git)?;
+    assert_eq!(entries[0].status, BundleStatus::Synced);
+    status::check_statuses(&entries)?;
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_status_compares_with_remote() -> Result<()> {
+    let test_name = "status_remote";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "icons".to_string(),
+        bundle_dependency("https://github.com/example/icons.git", "*"),
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Status remote"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(
+        "https://github.com/example/icons.git",
+        "",
+        create_mock_bundle_content("Icons"),
+    );
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+    mock_git.set_ahead_behind(&design_dir.join(BUNDLE_DIR).join("icons"), 1, 3);
+
+    // Without --remote, the remote isn't consulted
+    let entries = status::collect_all_statuses(&manifest_path, mock_git.clone())?;
+    assert_eq!((entries[0].ahead, entries[0].behind), (None, None));
+
+    let options = status::StatusOptions {
+        remote: true,
+        ..Default::default()
+    };
+    let entries = status::collect_statuses(&manifest_path, mock_git, &options)?;
+    assert_eq!((entries[0].ahead, entries[0].behind), (Some(1), Some(3)));
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_fetch_reports_new_upstream_commits() -> Result<()> {
+    let test_name = "fetch_updates";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    let mut bundles = HashMap::new();
+    for name in ["icons", "logos"] {
+        let url = format!("https://github.com/example/{}.git", name);
+        bundles.insert(name.to_string(), bundle_dependency(&url, "*"));
+        mock_git.register_remote_bundle(&url, "", create_mock_bundle_content(name));
+    }
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Fetch"), None, bundles)?;
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let icons_path = design_dir.join(BUNDLE_DIR).join("icons");
+    let logos = design_dir.join(BUNDLE_DIR).join("logos");
+    fs::write(logos.join("logo.svg"), "<svg/>")?;
+    mock_git.set_ahead_behind(&icons_path, 0, 2);
+
+    let mut entries =
+        fetch::fetch_bundles(&manifest_path, 1, &Settings::default(), mock_git.clone())?;
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    let counts: Vec<(&str, Option<usize>)> = entries
+        .iter()
+        .map(|entry| (entry.name.as_str(), entry.behind))
+        .collect();
+    assert_eq!(counts, vec![("icons", Some(2)), ("logos", Some(0))]);
+
+    // Fetching leaves installed files alone
+    assert_eq!(fs::read_to_string(logos.join("logo.svg"))?, "<svg/>");
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_status_flags_version_mismatch() -> Result<()> {
+    let test_name = "status_version_mismatch";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "icons".to_string(),
+        bundle_dependency("https://github.com/example/icons.git", "^2.0"),
+    );
+    let manifest_path =
+        create_bundle_manifest(&design_dir, Some("Version mismatch"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(
+        "https://github.com/example/icons.git",
+        "",
+        create_mock_bundle_content("Icons"),
+    );
+    mock_git.set_remote_tags("https://github.com/example/icons.git", &["v2.1.0"]);
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    // The installed bundle declares a version matching the requirement
+    let installed_manifest_path = design_dir
+        .join(BUNDLE_DIR)
+        .join("icons")
+        .join("bundle.toml");
+    let mut installed = load_manifest(&installed_manifest_path)?;
+    installed.version = Some("2.1.0".to_string());
+    save_manifest(&installed, &installed_manifest_path)?;
+
+    let entries = status::collect_all_statuses(&manifest_path, mock_git.clone())?;
+    assert_eq!(entries[0].status, BundleStatus::Synced);
+    assert_eq!(entries[0].version.as_deref(), Some("2.1.0"));
+    assert_eq!(entries[0].required_version.as_deref(), Some("^2.0"));
+
+    // ...until it declares one that doesn't
+    installed.version = Some("1.4.0".to_string());
+    save_manifest(&installed, &installed_manifest_path)?;
+
+    let entries = status::collect_all_statuses(&manifest_path, mock_git)?;
+    assert_eq!(entries[0].status, BundleStatus::VersionMismatch);
+    assert!(status::check_statuses(&entries).is_err());
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_status_limits_depth_and_bundle() -> Result<()> {
+    let test_name = "status_filters";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "ui-kit".to_string(),
+        bundle_dependency("https://github.com/example/ui-kit.git", "*"),
+    );
+    bundles.insert(
+        "icons".to_string(),
+        bundle_dependency("https://github.com/example/icons.git", "*"),
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Filters"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    let mut nested_bundles = HashMap::new();
+    nested_bundles.insert(
+        "base-styles".to_string(),
+        bundle_dependency("https://github.com/example/base-styles.git", "*"),
+    );
+    mock_git.register_remote_bundle_with_deps(
+        "https://github.com/example/ui-kit.git",
+        "",
+        create_mock_bundle_content("UI Kit"),
+        nested_bundles,
+    );
+    for name in ["base-styles", "icons"] {
+        let url = format!("https://github.com/example/{}.git", name);
+        mock_git.register_remote_bundle(&url, "", create_mock_bundle_content(name));
+    }
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let names = |options: status::StatusOptions| -> Result<Vec<String>> {
+        let entries = status::collect_statuses(&manifest_path, mock_git.clone(), &options)?;
+        let mut names: Vec<String> = entries.into_iter().map(|entry| entry.name).collect();
+        names.sort();
+        Ok(names)
+    };
+
+    assert_eq!(
+        names(Default::default())?,
+        vec!["base-styles", "icons", "ui-kit"]
+    );
+    assert_eq!(
+        names(status::StatusOptions {
+            depth: Some(0),
+            ..Default::default()
+        })?,
+        vec!["icons", "ui-kit"]
+    );
+    assert_eq!(
+        names(status::StatusOptions {
+            bundle: Some("ui-kit".to_string()),
+            ..Default::default()
+        })?,
+        vec!["base-styles", "ui-kit"]
+    );
+    assert_eq!(
+        names(status::StatusOptions {
+            bundle: Some("ui-kit/base-styles".to_string()),
+            ..Default::default()
+        })?,
+        vec!["base-styles"]
+    );
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_status_checks_bundles_concurrently() -> Result<()> {
+    let test_name = "status_concurrent";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    let mut bundles = HashMap::new();
+    for index in 0..12 {
+        let url = format!("https://github.com/example/bundle-{}.git", index);
+        bundles.insert(format!("bundle-{}", index), bundle_dependency(&url, "*"));
+        mock_git.register_remote_bundle(&url, "", create_mock_bundle_content("Bundle"));
+    }
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Concurrent"), None, bundles)?;
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let statuses = |jobs: usize| -> Result<serde_json::Value> {
+        let options = status::StatusOptions {
+            jobs,
+            ..Default::default()
+        };
+        let entries = status::collect_statuses(&manifest_path, mock_git.clone(), &options)?;
+        Ok(serde_json::to_value(entries)?)
+    };
+
+    let sequential = statuses(1)?;
+    assert_eq!(sequential.as_array().map(Vec::len), Some(12));
+    assert_eq!(statuses(8)?, sequential);
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_frozen_install_requires_matching_lockfile() -> Result<()> {
+    let test_name = "frozen_install";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let icons = BundleDependency {
+        version: "1.0.0".to_string(),
+        git: "https://github.com/example/icons.git".to_string(),
+        ..Default::default()
+    };
+    let mut bundles = HashMap::new();
+    bundles.insert("icons".to_string(), icons.clone());
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Frozen"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(
+        "https://github.com/example/icons.git",
+        "",
+        create_mock_bundle_content("Icons"),
+    );
+    mock_git.set_remote_tags("https://github.com/example/icons.git", &["v1.0.0"]);
+    let frozen = install::InstallOptions {
+        frozen: true,
+        ..Default::default()
+    };
+
+    // No lockfile yet
+    let result = install::execute_with_git(&manifest_path, &frozen, mock_git.clone());
+    assert!(result.unwrap_err().to_string().contains("does not exist"));
+
+    // A regular install writes the lockfile, after which frozen installs succeed
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+    let lock_before = fs::read_to_string(design_dir.join(LOCKFILE_NAME))?;
+    install::execute_with_git(&manifest_path, &frozen, mock_git.clone())?;
+    assert_eq!(
+        fs::read_to_string(design_dir.join(LOCKFILE_NAME))?,
+        lock_before
+    );
+
+    // A bundle added to the manifest but not to the lockfile is out of sync
+    let mut manifest = load_manifest(&manifest_path)?;
+    manifest.bundles.insert("icons-2".to_string(), icons);
+    save_manifest(&manifest, &manifest_path)?;
+    let result = install::execute_with_git(&manifest_path, &frozen, mock_git.clone());
+    assert!(result.unwrap_err().to_string().contains("out of sync"));
+
+    // So is a lockfile entry whose bundle was removed from the manifest
+    manifest.bundles.clear();
+    save_manifest(&manifest, &manifest_path)?;
+    let result = install::execute_with_git(&manifest_path, &frozen, mock_git);
+    assert!(result.unwrap_err().to_string().contains("out of sync"));
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_frozen_install_checks_nested_lock_entries_before_fetching() -> Result<()> {
+    let test_name = "frozen_install_nested";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let dependency = |git: &str| BundleDependency {
+        version: "1.0.0".to_string(),
+        git: git.to_string(),
+        ..Default::default()
+    };
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "icons".to_string(),
+        dependency("https://github.com/example/icons.git"),
+    );
+    bundles.insert(
+        "ui-kit".to_string(),
+        dependency("https://github.com/example/ui-kit.git"),
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Frozen"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(
+        "https://github.com/example/icons.git",
+        "",
+        create_mock_bundle_content("Icons"),
+    );
+    let mut nested_bundles = HashMap::new();
+    nested_bundles.insert(
+        "base-styles".to_string(),
+        dependency("https://github.com/example/base-styles.git"),
+    );
+    mock_git.register_remote_bundle_with_deps(
+        "https://github.com/example/ui-kit.git",
+        "",
+        create_mock_bundle_content("UI Kit"),
+        nested_bundles,
+    );
+    mock_git.register_remote_bundle(
+        "https://github.com/example/base-styles.git",
+        "",
+        create_mock_bundle_content("Base styles"),
+    );
+    for url in [
+        "https://github.com/example/icons.git",
+        "https://github.com/example/ui-kit.git",
+        "https://github.com/example/base-styles.git",
+    ] {
+        mock_git.set_remote_tags(url, &["v1.0.0"]);
+    }
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    // The lockfile misses the nested bundle, and icons has to be fetched again
+    let lock_path = design_dir.join(LOCKFILE_NAME);
+    let mut lockfile = load_lockfile(&lock_path)?.expect("install should write fpm.lock");
+    lockfile.bundles.remove("ui-kit/base-styles");
+    save_lockfile(&lock_path, &lockfile)?;
+    let lock_before = fs::read_to_string(&lock_path)?;
+    let bundle_dir = design_dir.join(BUNDLE_DIR);
+    fs::remove_dir_all(bundle_dir.join("icons"))?;
+    let state_before = fs::read_to_string(bundle_dir.join(STATE_FILE))?;
+    let clones_before = mock_git.get_cloned_repos().len();
+
+    let frozen = install::InstallOptions {
+        frozen: true,
+        jobs: 1,
+        ..Default::default()
+    };
+    let error = install::execute_with_git(&manifest_path, &frozen, mock_git.clone())
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("out of sync"), "{}", error);
+    assert!(error.contains("'ui-kit/base-styles'"), "{}", error);
+
+    // Nothing was fetched or changed
+    assert_eq!(mock_git.get_cloned_repos().len(), clones_before);
+    assert!(mock_git.get_mirrors().is_empty());
+    assert!(!bundle_dir.join("icons").exists());
+    assert_eq!(fs::read_to_string(&lock_path)?, lock_before);
+    assert_eq!(
+        fs::read_to_string(bundle_dir.join(STATE_FILE))?,
+        state_before
+    );
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_install_fails_for_missing_version_tag() -> Result<()> {
+    let test_name = "missing_version_tag";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "icons".to_string(),
+        BundleDependency {
+            version: "2.0.0".to_string(),
+            git: "https://github.com/example/icons.git".to_string(),
+            ..Default::default()
+        },
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Missing tag"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(
+        "https://github.com/example/icons.git",
+        "",
+        create_mock_bundle_content("Icons"),
+    );
+    mock_git.set_remote_tags("https://github.com/example/icons.git", &["v1.0.0"]);
+
+    let error = execute_install_with_mock(&manifest_path, mock_git.clone()).unwrap_err();
+    assert!(
+        format!("{:#}", error).contains("Version 2.0.0 not found"),
+        "Got: {:#}",
+        error
+    );
+    assert!(
+        mock_git.get_cloned_repos().is_empty(),
+        "Nothing should be cloned"
+    );
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_keep_going_installs_remaining_bundles() -> Result<()> {
+    let test_name = "keep_going";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "icons".to_string(),
+        bundle_dependency("https://github.com/example/icons.git", "2.0.0"),
+    );
+    bundles.insert(
+        "logos".to_string(),
+        bundle_dependency("https://github.com/example/logos.git", "*"),
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Keep going"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(
+        "https://github.com/example/icons.git",
+        "",
+        create_mock_bundle_content("Icons"),
+    );
+    mock_git.set_remote_tags("https://github.com/example/icons.git", &["v1.0.0"]);
+    mock_git.register_remote_bundle(
+        "https://github.com/example/logos.git",
+        "",
+        create_mock_bundle_content("Logos"),
+    );
+
+    let options = install::InstallOptions {
+        keep_going: true,
+        ..Default::default()
+    };
+    let error = install::execute_with_git(&manifest_path, &options, mock_git.clone()).unwrap_err();
+    let message = format!("{:#}", error);
+    assert!(
+        message.contains("1 bundle(s) failed to install") && message.contains("icons:"),
+        "Got: {}",
+        message
+    );
+
+    // The failure didn't stop the other bundle, which is installed and locked
+    assert!(design_dir.join(BUNDLE_DIR).join("logos").exists());
+    let lock = load_lockfile(&design_dir.join(LOCKFILE_NAME))?.expect("fpm.lock written");
+    assert_eq!(lock.bundles.keys().collect::<Vec<_>>(), vec!["logos"]);
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_install_tracks_remote_default_branch() -> Result<()> {
+    let test_name = "install_default_branch";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let url = "https://git.example.com/design/icons.git";
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "icons".to_string(),
+        BundleDependency {
+            version: "*".to_string(),
+            git: url.to_string(),
+            ..Default::default()
+        },
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Trunk"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(url, "", create_mock_bundle_content("icons"));
+    mock_git.set_default_branch(url, "trunk");
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let cloned = mock_git.get_cloned_repos();
+    assert_eq!(cloned.len(), 1);
+    assert_eq!(cloned[0].branch, "trunk");
+    let lock = load_lockfile(&design_dir.join(LOCKFILE_NAME))?.expect("fpm.lock written");
+    assert_eq!(lock.bundles["icons"].branch, "trunk");
+    let state = load_state(&design_dir.join(BUNDLE_DIR))?;
+    assert_eq!(state.bundles["icons"].branch, "trunk");
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_install_records_bundle_results() -> Result<()> {
+    let test_name = "install_results";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "icons".to_string(),
+        bundle_dependency("https://github.com/example/icons.git", "2.0.0"),
+    );
+    bundles.insert(
+        "logos".to_string(),
+        bundle_dependency("https://github.com/example/logos.git", "*"),
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Results"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(
+        "https://github.com/example/icons.git",
+        "",
+        create_mock_bundle_content("Icons"),
+    );
+    mock_git.set_remote_tags("https://github.com/example/icons.git", &["v1.0.0"]);
+    mock_git.register_remote_bundle(
+        "https://github.com/example/logos.git",
+        "",
+        create_mock_bundle_content("Logos"),
+    );
+
+    let options = install::InstallOptions {
+        keep_going: true,
+        ..Default::default()
+    };
+    let install = || -> Vec<BundleResult> {
+        let results = BundleResults::default();
+        let outcome =
+            install::install_with_results(&manifest_path, &options, mock_git.clone(), &results);
+        assert!(outcome.is_err());
+        results.into_sorted()
+    };
+
+    let results = install();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].name, "icons");
+    assert_eq!(results[0].action, BundleAction::Failed);
+    assert!(results[0].error.as_ref().unwrap().contains("2.0.0"));
+    assert_eq!(
+        results[1],
+        BundleResult {
+            name: "logos".to_string(),
+            action: BundleAction::Fetched,
+            old_commit: None,
+            new_commit: Some(MOCK_HEAD_COMMIT.to_string()),
+            error: None,
+        }
+    );
+
+    // Installing again leaves the installed bundle at its commit
+    let results = install();
+    assert_eq!(results[1].action, BundleAction::Skipped);
+    assert_eq!(results[1].old_commit, results[1].new_commit);
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_rev_pins_commit_and_reports_drift() -> Result<()> {
+    let test_name = "rev_pin";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let pinned_rev = "fedcba9876543210fedcba9876543210fedcba98";
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "icons".to_string(),
+        BundleDependency {
+            version: "1.0.0".to_string(),
+            git: "https://github.com/example/icons.git".to_string(),
+            rev: Some(pinned_rev.to_string()),
+            ..Default::default()
+        },
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Pinned rev"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(
+        "https://github.com/example/icons.git",
+        "",
+        create_mock_bundle_content("Icons"),
+    );
+
+    // No tags are needed: the rev takes precedence over the version
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let icons_dir = design_dir.join(BUNDLE_DIR).join("icons");
+    assert_eq!(
+        mock_git.get_checked_out_commit(&icons_dir).as_deref(),
+        Some(pinned_rev)
+    );
+    let lockfile = load_lockfile(&design_dir.join(LOCKFILE_NAME))?.unwrap();
+    assert_eq!(lockfile.bundles["icons"].commit, pinned_rev);
+
+    let statuses = get_bundle_statuses_with_mock(&manifest_path, mock_git.clone())?;
+    assert_eq!(statuses, vec![("icons".to_string(), BundleStatus::Synced)]);
+
+    // Moving the pin without reinstalling leaves the working tree behind
+    let mut manifest = load_manifest(&manifest_path)?;
+    manifest.bundles.get_mut("icons").unwrap().rev = Some("0a1b2c3d".to_string());
+    save_manifest(&manifest, &manifest_path)?;
+
+    let statuses = get_bundle_statuses_with_mock(&manifest_path, mock_git.clone())?;
+    assert_eq!(statuses, vec![("icons".to_string(), BundleStatus::Drifted)]);
+
+    // Reinstalling checks out the new rev
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+    assert_eq!(
+        mock_git.get_checked_out_commit(&icons_dir).as_deref(),
+        Some("0a1b2c3d")
+    );
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_update_moves_locked_bundles_forward() -> Result<()> {
+    let test_name = "update";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    for name in ["icons", "fonts"] {
+        bundles.insert(
+            name.to_string(),
+            BundleDependency {
+                version: "^1.0".to_string(),
+                git: format!("https://github.com/example/{}.git", name),
+                ..Default::default()
+            },
+        );
+    }
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Update"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    for name in ["icons", "fonts"] {
+        let url = format!("https://github.com/example/{}.git", name);
+        mock_git.register_remote_bundle(&url, "", create_mock_bundle_content(name));
+        mock_git.set_remote_tags(&url, &["v1.0.0"]);
+    }
+
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    // New releases don't change what a locked install checks out
+    for name in ["icons", "fonts"] {
+        let url = format!("https://github.com/example/{}.git", name);
+        mock_git.set_remote_tags(&url, &["v1.0.0", "v1.1.0", "v2.0.0"]);
+    }
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+    let lockfile = load_lockfile(&design_dir.join(LOCKFILE_NAME))?.unwrap();
+    assert_eq!(lockfile.bundles["icons"].branch, "v1.0.0");
+
+    // Updating a single bundle leaves the others locked
+    let options = update::UpdateOptions {
+        jobs: 1,
+        ..Default::default()
+    };
+    let changes =
+        update::execute_with_git(&manifest_path, Some("icons"), &options, mock_git.clone())?;
+    assert_eq!(changes.len(), 1);
+    assert!(matches!(
+        &changes[0],
+        (key, update::BundleChange::Updated { from, to })
+            if key == "icons" && from.branch == "v1.0.0" && to.branch == "v1.1.0"
+    ));
+    let lockfile = load_lockfile(&design_dir.join(LOCKFILE_NAME))?.unwrap();
+    assert_eq!(lockfile.bundles["icons"].branch, "v1.1.0");
+    assert_eq!(lockfile.bundles["fonts"].branch, "v1.0.0");
+
+    let changes = update::execute_with_git(&manifest_path, None, &options, mock_git.clone())?;
+    assert!(matches!(
+        &changes[..],
+        [
+            (_, update::BundleChange::Updated { .. }),
+            (_, update::BundleChange::Unchanged(_))
+        ]
+    ));
+
+    assert!(
+        update::execute_with_git(&manifest_path, Some("missing"), &options, mock_git.clone())
+            .is_err()
+    );
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_outdated_reports_newer_tags() -> Result<()> {
+    let test_name = "outdated";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "icons".to_string(),
+        BundleDependency {
+            version: "^1.0".to_string(),
+            git: "https://github.com/example/icons.git".to_string(),
+            ..Default::default()
+        },
+    );
+    bundles.insert(
+        "fonts".to_string(),
+        BundleDependency {
+            version: "*".to_string(),
+            git: "https://github.com/example/fonts.git".to_string(),
+            ..Default::default()
+        },
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Outdated"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    for name in ["icons", "fonts"] {
+        let url = format!("https://github.com/example/{}.git", name);
+        mock_git.register_remote_bundle(&url, "", create_mock_bundle_content(name));
+    }
+    mock_git.set_remote_tags("https://github.com/example/icons.git", &["v1.0.0"]);
+
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let entries =
+        outdated::execute_with_git(&manifest_path, &Settings::default(), mock_git.clone())?;
+    assert!(entries.iter().all(|entry| !entry.is_outdated()));
+
+    mock_git.set_remote_tags(
+        "https://github.com/example/icons.git",
+        &["v1.0.0", "v1.1.0", "v2.0.0"],
+    );
+    let entries =
+        outdated::execute_with_git(&manifest_path, &Settings::default(), mock_git.clone())?;
+
+    let icons = entries.iter().find(|entry| entry.key == "icons").unwrap();
+    assert!(icons.is_outdated());
+    assert_eq!(icons.current.as_ref().unwrap().name, "v1.0.0");
+    assert_eq!(icons.wanted.as_ref().unwrap().name, "v1.1.0");
+    assert_eq!(icons.latest.as_ref().unwrap().name, "v2.0.0");
+
+    // The branch-tracking bundle is at the remote tip
+    let fonts = entries.iter().find(|entry| entry.key == "fonts").unwrap();
+    assert!(!fonts.is_outdated());
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_add_looks_up_bundles_in_registry() -> Result<()> {
+    let test_name = "add-registry";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+    let manifest_path =
+        create_bundle_manifest(&design_dir, Some("Registry"), None, HashMap::new())?;
+
+    let registry = "https://github.com/example/fpm-index.git";
+    let icons = "https://github.com/example/icons.git";
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(
+        registry,
+        "",
+        MockBundleContent {
+            description: "Registry".to_string(),
+            files: vec![(
+                "index.toml".to_string(),
+                format!(
+                    "[bundles.icons]\ngit = \"{}\"\ndescription = \"Icon set\"\n\
+                    versions = [\"1.0.0\", \"1.2.0\"]\n",
+                    icons
+                ),
+            )],
+        },
+    );
+    mock_git.register_remote_bundle(icons, "", create_mock_bundle_content("Icons"));
+    mock_git.set_remote_tags(icons, &["v1.0.0", "v1.2.0"]);
+
+    let index = fetch_index(mock_git.as_ref(), registry, &test_dir.join("registry"))?;
+    let found: Vec<&str> = index
+        .search("icon")
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    assert_eq!(found, vec!["icons"]);
+
+    // The latest listed version is required unless one is given
+    add::add_from_registry(
+        &manifest_path,
+        "icons",
+        &index,
+        &add::AddOptions::default(),
+        mock_git.clone(),
+    )?;
+    let manifest = load_manifest(&manifest_path)?;
+    assert_eq!(manifest.bundles["icons"].git, icons);
+    assert_eq!(manifest.bundles["icons"].version, "^1.2.0");
+
+    let error = add::add_from_registry(
+        &manifest_path,
+        "fonts",
+        &index,
+        &add::AddOptions::default(),
+        mock_git.clone(),
+    )
+    .unwrap_err()
+    .to_string();
+    assert!(error.contains("not in the registry"), "Got: {}", error);
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_add_appends_bundle_and_installs() -> Result<()> {
+    let test_name = "add";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Add"), None, HashMap::new())?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(
+        "https://github.com/example/icons.git",
+        "",
+        create_mock_bundle_content("Icons"),
+    );
+    mock_git.set_remote_tags(
+        "https://github.com/example/icons.git",
+        &["v1.0.0", "v1.2.0"],
+    );
+
+    let options = add::AddOptions {
+        version: Some("^1.0"),
+        install: true,
+        ..Default::default()
+    };
+    add::execute_with_git(
+        &manifest_path,
+        "icons",
+        "https://github.com/example/icons.git",
+        &options,
+        mock_git.clone(),
+    )?;
+
+    let manifest = load_manifest(&manifest_path)?;
+    assert_eq!(manifest.bundles["icons"].version, "^1.0");
+    assert!(design_dir.join(BUNDLE_DIR).join("icons").exists());
+    let lockfile = load_lockfile(&design_dir.join(LOCKFILE_NAME))?.unwrap();
+    assert_eq!(lockfile.bundles["icons"].branch, "v1.2.0");
+
+    // Duplicates, unreachable remotes and unknown versions are rejected
+    let defaults = add::AddOptions::default();
+    assert!(add::execute_with_git(
+        &manifest_path,
+        "icons",
+        "https://github.com/example/icons.git",
+        &defaults,
+        mock_git.clone(),
+    )
+    .is_err());
+    assert!(add::execute_with_git(
+        &manifest_path,
+        "fonts",
+        "https://github.com/example/unknown.git",
+        &defaults,
+        mock_git.clone(),
+    )
+    .is_err());
+    let missing_version = add::AddOptions {
+        version: Some("2.0.0"),
+        ..Default::default()
+    };
+    assert!(add::execute_with_git(
+        &manifest_path,
+        "icons-v2",
+        "https://github.com/example/icons.git",
+        &missing_version,
+        mock_git.clone(),
+    )
+    .is_err());
+    assert_eq!(load_manifest(&manifest_path)?.bundles.len(), 1);
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_remove_deletes_bundle_and_lock_entries() -> Result<()> {
+    let test_name = "remove";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    for name in ["icons", "fonts"] {
+        bundles.insert(
+            name.to_string(),
+            BundleDependency {
+                version: "*".to_string(),
+                git: format!("https://github.com/example/{}.git", name),
+                ..Default::default()
+            },
+        );
+    }
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Remove"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    for name in ["icons", "fonts"] {
+        let url = format!("https://github.com/example/{}.git", name);
+        mock_git.register_remote_bundle(&url, "", create_mock_bundle_content(name));
+    }
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    // Local changes block the removal
+    let icons_dir = design_dir.join(BUNDLE_DIR).join("icons");
+    mock_git.set_local_changes(&icons_dir, true);
+    let result = remove::execute_with_git(
+        &manifest_path,
+        "icons",
+        false,
+        &Settings::default(),
+        mock_git.clone(),
+    );
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("uncommitted local changes"));
+    assert!(icons_dir.exists());
+    assert!(load_manifest(&manifest_path)?.bundles.contains_key("icons"));
+
+    remove::execute_with_git(
+        &manifest_path,
+        "icons",
+        true,
+        &Settings::default(),
+        mock_git.clone(),
+    )?;
+
+    assert!(!icons_dir.exists());
+    let manifest = load_manifest(&manifest_path)?;
+    assert_eq!(manifest.bundles.keys().collect::<Vec<_>>(), vec!["fonts"]);
+    let lockfile = load_lockfile(&design_dir.join(LOCKFILE_NAME))?.unwrap();
+    assert_eq!(lockfile.bundles.keys().collect::<Vec<_>>(), vec!["fonts"]);
+    let state = load_state(&design_dir.join(BUNDLE_DIR))?;
+    assert!(!state.bundles.contains_key("icons"));
+
+    assert!(remove::execute_with_git(
+        &manifest_path,
+        "icons",
+        false,
+        &Settings::default(),
+        mock_git.clone()
+    )
+    .is_err());
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_reports_modified_bundles() -> Result<()> {
+    let test_name = "verify";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    for name in ["icons", "fonts"] {
+        bundles.insert(
+            name.to_string(),
+            BundleDependency {
+                version: "*".to_string(),
+                git: format!("https://github.com/example/{}.git", name),
+                ..Default::default()
+            },
+        );
+    }
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Verify"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    for name in ["icons", "fonts"] {
+        let url = format!("https://github.com/example/{}.git", name);
+        mock_git.register_remote_bundle(&url, "", create_mock_bundle_content(name));
+    }
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let lockfile = load_lockfile(&design_dir.join(LOCKFILE_NAME))?.unwrap();
+    assert!(lockfile.bundles.values().all(|locked| locked
+        .checksum
+        .as_deref()
+        .is_some_and(|c| c.starts_with("sha256:"))));
+    let entries = verify::verify_bundles(&manifest_path)?;
+    assert!(entries
+        .iter()
+        .all(|entry| entry.status == verify::VerifyStatus::Ok));
+
+    let icons_dir = design_dir.join(BUNDLE_DIR).join("icons");
+    fs::write(icons_dir.join("README.md"), "tampered")?;
+    fs::write(icons_dir.join("extra.svg"), "<svg/>")?;
+
+    let entries = verify::verify_bundles(&manifest_path)?;
+    let icons = entries
+        .iter()
+        .find(|entry| entry.bundle == "icons")
+        .unwrap();
+    assert_eq!(icons.status, verify::VerifyStatus::Modified);
+    assert_eq!(
+        icons.changes,
+        Some(vec![
+            ("README.md".to_string(), FileChange::Modified),
+            ("extra.svg".to_string(), FileChange::Added),
+        ])
+    );
+    let fonts = entries
+        .iter()
+        .find(|entry| entry.bundle == "fonts")
+        .unwrap();
+    assert_eq!(fonts.status, verify::VerifyStatus::Ok);
+    assert!(verify::execute(&manifest_path)
+        .unwrap_err()
+        .to_string()
+        .contains("1 bundle(s) differ"));
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_publish_to_publish_url() -> Result<()> {
+    let test_name = "publish_url";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+    let root_dir = design_dir.join("assets");
+    fs::create_dir_all(&root_dir)?;
+    fs::write(root_dir.join("logo.svg"), "<svg/>")?;
+
+    let manifest_path =
+        create_bundle_manifest(&design_dir, Some("Assets"), Some("assets"), HashMap::new())?;
+    let mut manifest = load_manifest(&manifest_path)?;
+    manifest.publish_url = Some("https://git.example.com/design/assets.git".to_string());
+    manifest.publish_branch = Some("release".to_string());
+    manifest.version = Some("1.2.0".to_string());
+    save_manifest(&manifest, &manifest_path)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    publish::execute_with_git(
+        &manifest_path,
+        &publish::PublishOptions::default(),
+        mock_git.clone(),
+    )?;
+
+    assert_eq!(
+        mock_git.get_added_remotes(),
+        vec![(
+            root_dir.clone(),
+            DEFAULT_REMOTE.to_string(),
+            "https://git.example.com/design/assets.git".to_string()
+        )]
+    );
+    assert_eq!(mock_git.get_commits().len(), 1);
+    assert_eq!(
+        mock_git.get_pushes(),
+        vec![(root_dir.clone(), "release".to_string())]
+    );
+    assert_eq!(
+        mock_git.get_pushed_tags(),
+        vec![(root_dir.clone(), "v1.2.0".to_string())]
+    );
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_publish_to_mirrors_reports_each_remote() -> Result<()> {
+    let test_name = "publish_mirrors";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+    let root_dir = design_dir.join("assets");
+    fs::create_dir_all(&root_dir)?;
+    fs::write(root_dir.join("logo.svg"), "<svg/>")?;
+
+    let primary = "https://git.internal.example.com/design/assets.git";
+    let github = "https://github.com/example/assets.git";
+    let gitlab = "https://gitlab.com/example/assets.git";
+    let manifest_path =
+        create_bundle_manifest(&design_dir, Some("Assets"), Some("assets"), HashMap::new())?;
+    let mut manifest = load_manifest(&manifest_path)?;
+    manifest.publish_url = Some(primary.to_string());
+    manifest.publish_mirrors = vec![github.to_string(), gitlab.to_string()];
+    manifest.version = Some("1.2.0".to_string());
+    save_manifest(&manifest, &manifest_path)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.set_push_rejected(github);
+
+    let error = publish::execute_with_git(
+        &manifest_path,
+        &publish::PublishOptions::default(),
+        mock_git.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        format!("Failed to publish to 1 of 3 remote(s): {}", github)
+    );
+
+    // Every mirror gets a remote, and the failed one doesn't stop the others
+    let remotes: Vec<(String, String)> = mock_git
+        .get_added_remotes()
+        .into_iter()
+        .map(|(_, name, url)| (name, url))
+        .collect();
+    assert_eq!(
+        remotes,
+        vec![
+            (DEFAULT_REMOTE.to_string(), primary.to_string()),
+            ("fpm-mirror-1".to_string(), github.to_string()),
+            ("fpm-mirror-2".to_string(), gitlab.to_string()),
+        ]
+    );
+    assert_eq!(mock_git.get_commits().len(), 1);
+    assert_eq!(mock_git.get_pushes().len(), 2);
+    assert_eq!(mock_git.get_pushed_tags().len(), 2);
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_yank_tags_published_version() -> Result<()> {
+    let test_name = "yank";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+    let root_dir = design_dir.join("assets");
+    fs::create_dir_all(&root_dir)?;
+    fs::write(root_dir.join("logo.svg"), "<svg/>")?;
+
+    let url = "https://git.example.com/design/assets.git";
+    let mirror = "https://github.com/example/assets.git";
+    let manifest_path =
+        create_bundle_manifest(&design_dir, Some("Assets"), Some("assets"), HashMap::new())?;
+    let mut manifest = load_manifest(&manifest_path)?;
+    manifest.publish_url = Some(url.to_string());
+    manifest.publish_mirrors = vec![mirror.to_string()];
+    manifest.version = Some("1.2.0".to_string());
+    save_manifest(&manifest, &manifest_path)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    publish::execute_with_git(
+        &manifest_path,
+        &publish::PublishOptions::default(),
+        mock_git.clone(),
+    )?;
+    mock_git.set_remote_tags(url, &["v1.1.0", "v1.2.0"]);
+
+    let error = yank::execute_with_git(
+        &manifest_path,
+        "1.3.0",
+        None,
+        &Settings::default(),
+        mock_git.clone(),
+    )
+    .unwrap_err()
+    .to_string();
+    assert!(error.contains("not published"), "Got: {}", error);
+
+    yank::execute_with_git(
+        &manifest_path,
+        "v1.1.0",
+        Some("broken icons"),
+        &Settings::default(),
+        mock_git.clone(),
+    )?;
+    let yank_pushes: Vec<(PathBuf, String)> = mock_git
+        .get_pushed_tags()
+        .into_iter()
+        .filter(|(_, tag)| tag.starts_with("yanked/"))
+        .collect();
+    // Pushed to the primary remote and the mirror
+    assert_eq!(
+        yank_pushes,
+        vec![(root_dir.clone(), "yanked/v1.1.0".to_string()); 2]
+    );
+
+    // Installs resolving to the version see it yanked
+    mock_git.set_remote_tags(url, &["v1.1.0", "v1.2.0", "yanked/v1.1.0"]);
+    let resolved = |version: &str| resolve_ref(mock_git.as_ref(), &bundle_dependency(url, version));
+    assert!(resolved("1.1.0")?.yanked);
+    assert!(!resolved("^1.1")?.yanked);
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_publish_refuses_published_version() -> Result<()> {
+    let test_name = "publish_existing_version";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+    let root_dir = design_dir.join("assets");
+    fs::create_dir_all(&root_dir)?;
+    fs::write(root_dir.join("logo.svg"), "<svg/>")?;
+
+    let manifest_path =
+        create_bundle_manifest(&design_dir, Some("Assets"), Some("assets"), HashMap::new())?;
+    let mut manifest = load_manifest(&manifest_path)?;
+    manifest.publish_url = Some("https://git.example.com/design/assets.git".to_string());
+    manifest.version = Some("1.2.0".to_string());
+    save_manifest(&manifest, &manifest_path)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.set_remote_tags("https://git.example.com/design/assets.git", &["v1.2.0"]);
+
+    let error = publish::execute_with_git(
+        &manifest_path,
+        &publish::PublishOptions::default(),
+        mock_git.clone(),
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("already published"));
+    assert!(mock_git.get_commits().is_empty());
+    assert!(mock_git.get_pushes().is_empty());
+
+    // --force publishes it anyway
+    publish::execute_with_git(
+        &manifest_path,
+        &publish::PublishOptions {
+            force: true,
+            ..Default::default()
+        },
+        mock_git.clone(),
+    )?;
+    assert_eq!(
+        mock_git.get_pushed_tags(),
+        vec![(root_dir.clone(), "v1.2.0".to_string())]
+    );
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_push_dry_run_changes_nothing() -> Result<()> {
+    let test_name = "push_dry_run";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "icons".to_string(),
+        BundleDependency {
+            version: "*".to_string(),
+            git: "https://github.com/example/icons.git".to_string(),
+            branch: Some("main".to_string()),
+            bump: Some(BumpLevel::Minor),
+            ..Default::default()
+        },
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Push"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(
+        "https://github.com/example/icons.git",
+        "",
+        create_mock_bundle_content("icons"),
+    );
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let icons_dir = design_dir.join(BUNDLE_DIR).join("icons");
+    mock_git.set_local_changes(&icons_dir, true);
+    mock_git.set_changed_files(&icons_dir, &["logo.svg"]);
+    let options = push::PushOptions {
+        dry_run: true,
+        ..Default::default()
+    };
+    push::execute_with_git(&manifest_path, &options, mock_git.clone())?;
+
+    assert!(mock_git.get_commits().is_empty());
+    let icons_manifest = load_manifest(&icons_dir.join("bundle.toml"))?;
+    assert_eq!(icons_manifest.version, None);
+
+    // The actual push bumps the version the dry run reported, by the bundle's level
+    push::execute_with_git(
+        &manifest_path,
+        &push::PushOptions::default(),
+        mock_git.clone(),
+    )?;
+    assert_eq!(mock_git.get_commits().len(), 1);
+    let icons_manifest = load_manifest(&icons_dir.join("bundle.toml"))?;
+    assert_eq!(icons_manifest.version.as_deref(), Some("0.1.0"));
+
+    // With bumping off, the changes are pushed at the version they are
+    mock_git.set_local_changes(&icons_dir, true);
+    let options = push::PushOptions {
+        bump: Some(BumpLevel::Off),
+        ..Default::default()
+    };
+    push::execute_with_git(&manifest_path, &options, mock_git.clone())?;
+    assert_eq!(mock_git.get_commits().len(), 2);
+    let icons_manifest = load_manifest(&icons_dir.join("bundle.toml"))?;
+    assert_eq!(icons_manifest.version.as_deref(), Some("0.1.0"));
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_push_to_new_branch() -> Result<()> {
+    let test_name = "push_new_branch";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let url = "https://git.example.com/design/icons.git";
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "icons".to_string(),
+        BundleDependency {
+            version: "*".to_string(),
+            git: url.to_string(),
+            branch: Some("main".to_string()),
+            ..Default::default()
+        },
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Push"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(url, "", create_mock_bundle_content("icons"));
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let icons_dir = design_dir.join(BUNDLE_DIR).join("icons");
+    mock_git.set_local_changes(&icons_dir, true);
+    let options = push::PushOptions {
+        branch: Some("update-icons".to_string()),
+        ..Default::default()
+    };
+    push::execute_with_git(&manifest_path, &options, mock_git.clone())?;
+
+    let update_branch = vec![(icons_dir.clone(), "update-icons".to_string())];
+    assert_eq!(mock_git.get_created_branches(), update_branch);
+    assert_eq!(mock_git.get_pushes(), update_branch);
+    assert_eq!(mock_git.get_commits().len(), 1);
+
+    // --pr needs a branch to open the pull request from
+    let options = push::PushOptions {
+        pull_request: true,
+        ..Default::default()
+    };
+    let result = push::execute_with_git(&manifest_path, &options, mock_git.clone());
+    assert!(result.unwrap_err().to_string().contains("--branch"));
+    assert_eq!(mock_git.get_commits().len(), 1);
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_commit_leaves_pushing_for_later() -> Result<()> {
+    let test_name = "push_commit_only";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let url = "https://git.example.com/design/icons.git";
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "icons".to_string(),
+        BundleDependency {
+            version: "*".to_string(),
+            git: url.to_string(),
+            branch: Some("main".to_string()),
+            ..Default::default()
+        },
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Commit"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(url, "", create_mock_bundle_content("icons"));
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let icons_dir = design_dir.join(BUNDLE_DIR).join("icons");
+    mock_git.set_local_changes(&icons_dir, true);
+    let options = push::PushOptions {
+        message: Some("Update icons".to_string()),
+        commit_only: true,
+        ..Default::default()
+    };
+    push::execute_with_git(&manifest_path, &options, mock_git.clone())?;
+
+    let commits = mock_git.get_commits();
+    assert_eq!(commits.len(), 1);
+    assert_eq!(commits[0].message, "Update icons");
+    assert!(mock_git.get_pushes().is_empty());
+
+    // A later push sends the commit without making another one
+    mock_git.set_local_changes(&icons_dir, false);
+    mock_git.set_ahead_behind(&icons_dir, 1, 0);
+    push::execute_with_git(
+        &manifest_path,
+        &push::PushOptions::default(),
+        mock_git.clone(),
+    )?;
+    assert_eq!(mock_git.get_commits().len(), 1);
+    assert_eq!(
+        mock_git.get_pushes(),
+        vec![(icons_dir.clone(), "main".to_string())]
+    );
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_push_tagged_bundle_to_checked_out_branch() -> Result<()> {
+    let test_name = "push_checked_out_branch";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let url = "https://git.example.com/design/icons.git";
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "icons".to_string(),
+        BundleDependency {
+            version: "^1.0".to_string(),
+            git: url.to_string(),
+            ..Default::default()
+        },
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Tagged"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(url, "", create_mock_bundle_content("icons"));
+    mock_git.set_remote_tags(url, &["v1.0.0", "v1.2.0"]);
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    // A tag has no branch to push to
+    let icons_dir = design_dir.join(BUNDLE_DIR).join("icons");
+    mock_git.set_local_changes(&icons_dir, true);
+    let options = push::PushOptions::default();
+    push::execute_with_git(&manifest_path, &options, mock_git.clone())?;
+    assert!(mock_git.get_pushes().is_empty());
+
+    // Unless one was checked out in the bundle
+    mock_git.set_current_branch(&icons_dir, "fix-icons");
+    push::execute_with_git(&manifest_path, &options, mock_git.clone())?;
+    assert_eq!(
+        mock_git.get_pushes(),
+        vec![(icons_dir.clone(), "fix-icons".to_string())]
+    );
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_push_selected_paths() -> Result<()> {
+    let test_name = "push_paths";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let url = "https://git.example.com/design/icons.git";
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "icons".to_string(),
+        BundleDependency {
+            version: "*".to_string(),
+            git: url.to_string(),
+            branch: Some("main".to_string()),
+            ..Default::default()
+        },
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Paths"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(url, "", create_mock_bundle_content("icons"));
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let icons_dir = design_dir.join(BUNDLE_DIR).join("icons");
+    mock_git.set_local_changes(&icons_dir, true);
+    mock_git.set_changed_files(&icons_dir, &["README.md", "svg/a.svg", "svg/b.svg"]);
+
+    // --paths belong to the bundle given with --bundle
+    let mut options = push::PushOptions {
+        paths: vec!["./svg/".to_string()],
+        ..Default::default()
+    };
+    let result = push::execute_with_git(&manifest_path, &options, mock_git.clone());
+    assert!(result.unwrap_err().to_string().contains("--bundle"));
+
+    // No changes in the selected paths
+    options.bundle = Some("icons".to_string());
+    options.paths = vec!["fonts".to_string()];
+    push::execute_with_git(&manifest_path, &options, mock_git.clone())?;
+    assert!(mock_git.get_commits().is_empty());
+
+    options.paths = vec!["./svg/".to_string()];
+    push::execute_with_git(&manifest_path, &options, mock_git.clone())?;
+    let commits = mock_git.get_commits();
+    assert_eq!(commits.len(), 1);
+    // The version bump is committed along with the selected files
+    assert_eq!(
+        commits[0].paths,
+        Some(vec![
+            "bundle.toml".to_string(),
+            "svg/a.svg".to_string(),
+            "svg/b.svg".to_string()
+        ])
+    );
+    assert_eq!(mock_git.get_pushes().len(), 1);
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_interactive_push_asks_per_bundle() -> Result<()> {
+    let test_name = "push_interactive";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    for name in ["icons", "fonts"] {
+        bundles.insert(
+            name.to_string(),
+            BundleDependency {
+                version: "*".to_string(),
+                git: format!("https://github.com/example/{}.git", name),
+                branch: Some("main".to_string()),
+                bump: Some(BumpLevel::Off),
+                ..Default::default()
+            },
+        );
+    }
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Push"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    for name in ["icons", "fonts"] {
+        let url = format!("https://github.com/example/{}.git", name);
+        mock_git.register_remote_bundle(&url, "", create_mock_bundle_content(name));
+    }
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let bundle_dir = design_dir.join(BUNDLE_DIR);
+    for name in ["icons", "fonts"] {
+        mock_git.set_local_changes(&bundle_dir.join(name), true);
+        mock_git.set_changed_files(&bundle_dir.join(name), &["logo.svg"]);
+    }
+    let options = push::PushOptions {
+        interactive: true,
+        ..Default::default()
+    };
+
+    // An unknown answer is asked again; quitting leaves the other bundle alone
+    let mut answers = "maybe\ny\nShip new assets\nq\n".as_bytes();
+    push::execute_with_input(&manifest_path, &options, mock_git.clone(), &mut answers)?;
+    let commits = mock_git.get_commits();
+    assert_eq!(commits.len(), 1);
+    assert_eq!(commits[0].message, "Ship new assets");
+
+    // Without answers, nothing is pushed
+    push::execute_with_input(
+        &manifest_path,
+        &options,
+        mock_git.clone(),
+        &mut "".as_bytes(),
+    )?;
+    assert_eq!(mock_git.get_commits().len(), 1);
+
+    // An empty message keeps the default one
+    let mut answers = "y\n\ny\n\n".as_bytes();
+    push::execute_with_input(&manifest_path, &options, mock_git.clone(), &mut answers)?;
+    let commits = mock_git.get_commits();
+    assert_eq!(commits.len(), 3);
+    assert!(commits[1..]
+        .iter()
+        .all(|commit| commit.message == "fpm push: Update bundle"));
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_push_commits_as_bundle_author() -> Result<()> {
+    let test_name = "push_author";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    for (name, author) in [
+        ("icons", Some("Assets Bot <assets-bot@example.com>")),
+        ("fonts", None),
+    ] {
+        bundles.insert(
+            name.to_string(),
+            BundleDependency {
+                version: "*".to_string(),
+                git: format!("https://github.com/example/{}.git", name),
+                branch: Some("main".to_string()),
+                author: author.map(str::to_string),
+                ..Default::default()
+            },
+        );
+    }
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Push"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    for name in ["icons", "fonts"] {
+        let url = format!("https://github.com/example/{}.git", name);
+        mock_git.register_remote_bundle(&url, "", create_mock_bundle_content(name));
+    }
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let bundle_dir = design_dir.join(BUNDLE_DIR);
+    for name in ["icons", "fonts"] {
+        mock_git.set_local_changes(&bundle_dir.join(name), true);
+    }
+    push::execute_with_git(
+        &manifest_path,
+        &push::PushOptions::default(),
+        mock_git.clone(),
+    )?;
+
+    let commits = mock_git.get_commits();
+    assert_eq!(commits.len(), 2);
+    let author_of = |name: &str| {
+        commits
+            .iter()
+            .find(|commit| commit.path == bundle_dir.join(name))
+            .map(|commit| commit.author.clone())
+            .unwrap()
+    };
+    assert_eq!(
+        author_of("icons"),
+        Some(CommitAuthor {
+            name: "Assets Bot".to_string(),
+            email: "assets-bot@example.com".to_string(),
+        })
+    );
+    // Without an author of its own, the repository's identity is used
+    assert_eq!(author_of("fonts"), None);
+    assert!(commits
+        .iter()
+        .all(|commit| commit.message == "fpm push: Update bundle"));
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_install_runs_manifest_hooks() -> Result<()> {
+    let test_name = "hooks";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let url = "https://github.com/example/icons.git";
+    let mut bundles = HashMap::new();
+    bundles.insert("icons".to_string(), bundle_dependency(url, "*"));
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Hooks"), None, bundles)?;
+    let mut manifest = load_manifest(&manifest_path)?;
+    manifest.hooks.pre_install = Some("echo \"$FPM_HOOK_EVENT\" > pre.log".to_string());
+    manifest.hooks.post_install = Some(
+        "echo \"$FPM_HOOK_BUNDLE $(basename \"$FPM_HOOK_BUNDLE_PATH\")\" >> post.log".to_string(),
+    );
+    save_manifest(&manifest, &manifest_path)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(url, "", create_mock_bundle_content("Icons"));
+
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+    assert_eq!(
+        fs::read_to_string(design_dir.join("pre.log"))?,
+        "pre_install\n"
+    );
+    assert_eq!(
+        fs::read_to_string(design_dir.join("post.log"))?,
+        "icons icons\n"
+    );
+
+    // Bundles left as they are don't run post_install again
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+    assert_eq!(
+        fs::read_to_string(design_dir.join("post.log"))?,
+        "icons icons\n"
+    );
+
+    fs::remove_file(design_dir.join("pre.log"))?;
+    let options = install::InstallOptions {
+        no_hooks: true,
+        ..Default::default()
+    };
+    install::execute_with_git(&manifest_path, &options, mock_git.clone())?;
+    assert!(!design_dir.join("pre.log").exists());
+
+    // A failing hook fails the install
+    let mut manifest = load_manifest(&manifest_path)?;
+    manifest.hooks.pre_install = Some("exit 1".to_string());
+    save_manifest(&manifest, &manifest_path)?;
+    let error = execute_install_with_mock(&manifest_path, mock_git.clone()).unwrap_err();
+    assert!(format!("{:#}", error).contains("pre_install hook failed"));
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_info_describes_installed_bundle() -> Result<()> {
+    let test_name = "info";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "ui-kit".to_string(),
+        bundle_dependency("https://github.com/example/ui-kit.git", "^1.0"),
+    );
+    bundles.insert(
+        "base-styles".to_string(),
+        bundle_dependency("https://github.com/example/base-styles.git", "*"),
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Info"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    let mut nested_bundles = HashMap::new();
+    nested_bundles.insert(
+        "base-styles".to_string(),
+        bundle_dependency("https://github.com/example/base-styles.git", "1.0.0"),
+    );
+    mock_git.register_remote_bundle_with_deps(
+        "https://github.com/example/ui-kit.git",
+        "",
+        create_mock_bundle_content("UI Kit"),
+        nested_bundles,
+    );
+    mock_git.register_remote_bundle(
+        "https://github.com/example/base-styles.git",
+        "",
+        create_mock_bundle_content("Base Styles"),
+    );
+    mock_git.set_remote_tags("https://github.com/example/ui-kit.git", &["v1.0.0"]);
+    mock_git.set_remote_tags("https://github.com/example/base-styles.git", &["v1.0.0"]);
+
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let info = info::collect_info(&manifest_path, "ui-kit", mock_git.clone())?;
+    assert_eq!(info.key, "ui-kit");
+    assert_eq!(info.source, "https://github.com/example/ui-kit.git");
+    assert_eq!(info.requested, "^1.0");
+    assert_eq!(info.status, Some(BundleStatus::Synced));
+    assert!(info.commit.is_some());
+    assert_eq!(info.locked_commit, info.commit);
+    assert!(info.size.is_some_and(|size| size > 0));
+    assert!(info.manifest.is_some());
+    assert_eq!(
+        info.dependencies,
+        vec![info::DependencyInfo {
+            name: "base-styles".to_string(),
+            requested: "1.0.0".to_string(),
+            status: Some(BundleStatus::Synced),
+        }]
+    );
+
+    // base-styles is declared twice, so it has to be named by install path
+    let error = info::collect_info(&manifest_path, "base-styles", mock_git.clone())
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("several paths"), "Got: {}", error);
+    let nested = info::collect_info(&manifest_path, "ui-kit/base-styles", mock_git.clone())?;
+    assert_eq!(nested.name, "base-styles");
+    assert!(nested.dependencies.is_empty());
+
+    assert!(info::collect_info(&manifest_path, "missing", mock_git.clone()).is_err());
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_doctor_checks_project() -> Result<()> {
+    let test_name = "doctor";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "ui-kit".to_string(),
+        bundle_dependency("https://github.com/example/ui-kit.git", "^1.0"),
+    );
+    bundles.insert(
+        "private".to_string(),
+        bundle_dependency("https://github.com/example/private.git", "^1.0"),
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Doctor"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.set_remote_tags("https://github.com/example/ui-kit.git", &["v1.0.0"]);
+    mock_git.set_unreachable("https://github.com/example/private.git");
+
+    let settings = Settings::default();
+    let checks = doctor::run_checks(&manifest_path, &settings, mock_git.clone())?;
+    let check = |name: &str| {
+        checks
+            .iter()
+            .find(|check| check.name == name)
+            .unwrap_or_else(|| panic!("no check {}", name))
+    };
+
+    assert_eq!(check("manifest").status, doctor::CheckStatus::Ok);
+    // Nothing was installed yet
+    assert_eq!(check("lockfile").status, doctor::CheckStatus::Warning);
+    assert_eq!(check("remote ui-kit").status, doctor::CheckStatus::Ok);
+    let private = check("remote private");
+    assert_eq!(private.status, doctor::CheckStatus::Failed);
+    assert!(private.detail.contains("could not resolve"));
+    assert!(private.fix.as_deref().unwrap().contains("github.com"));
+    assert_eq!(check("project").status, doctor::CheckStatus::Ok);
+
+    // A manifest that doesn't parse leaves out the checks that need it, and a
+    // settings file that doesn't parse is reported
+    fs::write(&manifest_path, "[bundles.broken\n")?;
+    fs::create_dir_all(design_dir.join(".fpm"))?;
+    fs::write(
+        design_dir.join(".fpm").join("config.toml"),
+        "jobs = \"many\"\n",
+    )?;
+    let checks = doctor::run_checks(&manifest_path, &settings, mock_git.clone())?;
+    let find = |name: &str| checks.iter().find(|check| check.name == name).unwrap();
+    assert_eq!(find("manifest").status, doctor::CheckStatus::Failed);
+    assert!(!checks.iter().any(|check| check.name.starts_with("remote")));
+    let settings_check = find("settings");
+    assert_eq!(settings_check.status, doctor::CheckStatus::Failed);
+    assert!(settings_check.detail.contains("config.toml"));
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_validate_online_checks_remotes() -> Result<()> {
+    let test_name = "validate_online";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "ui-kit".to_string(),
+        bundle_dependency("https://github.com/example/ui-kit.git", "^1.0"),
+    );
+    bundles.insert(
+        "icons".to_string(),
+        bundle_dependency("https://github.com/example/icons.git", "^2.0"),
+    );
+    bundles.insert(
+        "private".to_string(),
+        bundle_dependency("https://github.com/example/private.git", "^1.0"),
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Validate"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.set_remote_tags("https://github.com/example/ui-kit.git", &["v1.0.0"]);
+    mock_git.set_remote_tags("https://github.com/example/icons.git", &["v1.4.0"]);
+    mock_git.set_unreachable("https://github.com/example/private.git");
+
+    // Offline, the manifest is fine
+    let problems = validate::validate_manifest(
+        &manifest_path,
+        false,
+        &Settings::default(),
+        mock_git.clone(),
+    )?;
+    assert!(problems.is_empty(), "{:?}", problems);
+
+    let problems =
+        validate::validate_manifest(&manifest_path, true, &Settings::default(), mock_git.clone())?;
+    let bundles: Vec<&str> = problems
+        .iter()
+        .map(|problem| problem.bundle.as_deref().unwrap())
+        .collect();
+    assert_eq!(bundles, vec!["icons", "private"]);
+    assert!(problems[0].message.contains("^2.0"));
+    assert!(problems[1].message.starts_with("can't reach"));
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_tree_renders_nested_bundles() -> Result<()> {
+    let test_name = "tree";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "ui-kit".to_string(),
+        bundle_dependency("https://github.com/example/ui-kit.git", "^1.0"),
+    );
+    bundles.insert(
+        "base-styles".to_string(),
+        bundle_dependency("https://github.com/example/base-styles.git", "*"),
+    );
+    bundles.insert(
+        "icons".to_string(),
+        bundle_dependency("https://github.com/example/icons.git", "*"),
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Tree"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    let mut nested_bundles = HashMap::new();
+    nested_bundles.insert(
+        "base-styles".to_string(),
+        bundle_dependency("https://github.com/example/base-styles.git", "1.0.0"),
+    );
+    mock_git.register_remote_bundle_with_deps(
+        "https://github.com/example/ui-kit.git",
+        "",
+        create_mock_bundle_content("UI Kit"),
+        nested_bundles,
+    );
+    for name in ["base-styles", "icons"] {
+        let url = format!("https://github.com/example/{}.git", name);
+        mock_git.register_remote_bundle(&url, "", create_mock_bundle_content(name));
+    }
+    mock_git.set_remote_tags("https://github.com/example/ui-kit.git", &["v1.0.0"]);
+    mock_git.set_remote_tags("https://github.com/example/base-styles.git", &["v1.0.0"]);
+
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+    fs::remove_dir_all(design_dir.join(BUNDLE_DIR).join("icons"))?;
+    mock_git.set_local_changes(&design_dir.join(BUNDLE_DIR).join("ui-kit"), true);
+
+    let lines = tree::execute_with_git(&manifest_path, mock_git.clone())?;
+    assert_eq!(
+        lines,
+        vec![
+            "design",
+            "├── base-styles main (https://github.com/example/base-styles.git)",
+            "├── icons main (https://github.com/example/icons.git) [not installed]",
+            "└── ui-kit ^1.0 (https://github.com/example/ui-kit.git) [unsynced]",
+            "    └── base-styles 1.0.0 (https://github.com/example/base-styles.git) (*)",
+        ]
+    );
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_parallel_install_matches_sequential_install() -> Result<()> {
+    let test_name = "parallel_install";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let names = ["fonts", "icons", "logos", "ui-kit"];
+    let mut bundles = HashMap::new();
+    for name in names {
+        let url = format!("https://github.com/example/{}.git", name);
+        bundles.insert(name.to_string(), bundle_dependency(&url, "*"));
+    }
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Parallel"), None, bundles)?;
+
+    let remotes = || {
+        let mock_git = Arc::new(MockGitOperations::new());
+        for name in ["fonts", "icons", "logos", "base-styles"] {
+            let url = format!("https://github.com/example/{}.git", name);
+            mock_git.register_remote_bundle(&url, "", create_mock_bundle_content(name));
+        }
+        let mut nested_bundles = HashMap::new();
+        nested_bundles.insert(
+            "base-styles".to_string(),
+            bundle_dependency("https://github.com/example/base-styles.git", "*"),
+        );
+        mock_git.register_remote_bundle_with_deps(
+            "https://github.com/example/ui-kit.git",
+            "",
+            create_mock_bundle_content("UI Kit"),
+            nested_bundles,
+        );
+        mock_git
+    };
+
+    let options = install::InstallOptions {
+        jobs: 3,
+        ..Default::default()
+    };
+    install::execute_with_git(&manifest_path, &options, remotes())?;
+
+    let bundle_dir = design_dir.join(BUNDLE_DIR);
+    for name in names {
+        assert!(
+            bundle_dir.join(name).exists(),
+            "{} should be installed",
+            name
+        );
+    }
+    assert!(bundle_dir
+        .join("ui-kit")
+        .join(BUNDLE_DIR)
+        .join("base-styles")
+        .exists());
+
+    // Every concurrently installed bundle is recorded, exactly as a sequential install would
+    let lock_path = design_dir.join(LOCKFILE_NAME);
+    let parallel_lock = load_lockfile(&lock_path)?.expect("install should write fpm.lock");
+    assert_eq!(
+        parallel_lock.bundles.keys().collect::<Vec<_>>(),
+        vec!["fonts", "icons", "logos", "ui-kit", "ui-kit/base-styles"]
+    );
+    let parallel_state = load_state(&bundle_dir)?;
+    assert_eq!(parallel_state.bundles.len(), names.len());
+
+    fs::remove_dir_all(&bundle_dir)?;
+    fs::remove_file(&lock_path)?;
+    execute_install_with_mock(&manifest_path, remotes())?;
+
+    assert_eq!(load_lockfile(&lock_path)?, Some(parallel_lock));
+    assert_eq!(load_state(&bundle_dir)?, parallel_state);
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_include_filter_uses_sparse_checkout() -> Result<()> {
+    let test_name = "sparse_checkout";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let url = "https://github.com/example/large-repo.git";
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "filtered".to_string(),
+        BundleDependency {
+            include: Some(vec!["folder2".to_string()]),
+            ..bundle_dependency(url, "*")
+        },
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Sparse"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(url, "", create_mock_bundle_content("Large repo"));
+
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let bundle_path = design_dir.join(BUNDLE_DIR).join("filtered");
+    assert_eq!(
+        mock_git.get_sparse_checkout(&bundle_path),
+        Some(vec!["/folder2".to_string()])
+    );
+
+    // A changed include list is applied to the existing clone on the next install
+    let mut manifest = load_manifest(&manifest_path)?;
+    let filtered = manifest.bundles.get_mut("filtered").unwrap();
+    filtered.include = Some(vec!["folder2".to_string(), "folder3".to_string()]);
+    filtered.exclude = Some(vec!["*.psd".to_string()]);
+    save_manifest(&manifest, &manifest_path)?;
+
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+    assert_eq!(mock_git.get_cloned_repos().len(), 1);
+    assert_eq!(
+        mock_git.get_sparse_checkout(&bundle_path),
+        Some(vec![
+            "/folder2".to_string(),
+            "/folder3".to_string(),
+            "!*.psd".to_string()
+        ])
+    );
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_install_skips_bundles_with_local_changes() -> Result<()> {
+    let test_name = "install_local_changes";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let url = "https://github.com/example/icons.git";
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "icons".to_string(),
+        BundleDependency {
+            include: Some(vec!["svg".to_string()]),
+            ..bundle_dependency(url, "*")
+        },
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Local changes"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(url, "", create_mock_bundle_content("Icons"));
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+    let lock = load_lockfile(&design_dir.join(LOCKFILE_NAME))?;
+
+    // Re-resolving the branch resets the clone to its tip
+    let update = |force: bool| -> Result<BundleResult> {
+        let options = install::InstallOptions {
+            update: install::UpdateScope::All,
+            force,
+            ..Default::default()
+        };
+        let results = BundleResults::default();
+        install::install_with_results(&manifest_path, &options, mock_git.clone(), &results)?;
+        Ok(results.into_sorted().remove(0))
+    };
+
+    // Changes install makes itself, and files outside the include list, don't count
+    let bundle_path = design_dir.join(BUNDLE_DIR).join("icons");
+    mock_git.set_changed_files(&bundle_path, &[".gitignore", "psd/logo.psd"]);
+    assert_eq!(update(false)?.error, None);
+
+    mock_git.set_changed_files(&bundle_path, &[".gitignore", "svg/logo.svg"]);
+    let skipped = update(false)?;
+    assert_eq!(skipped.action, BundleAction::Skipped);
+    let message = skipped.error.unwrap();
+    assert!(message.contains("local changes"), "{}", message);
+    assert!(message.contains("svg/logo.svg"), "{}", message);
+    assert!(!message.contains(".gitignore"), "{}", message);
+    // The skipped bundle keeps its lock entry
+    assert_eq!(load_lockfile(&design_dir.join(LOCKFILE_NAME))?, lock);
+
+    // Forced, the bundle is cloned again from scratch
+    assert_eq!(update(true)?.error, None);
+    assert_eq!(mock_git.get_cloned_repos().len(), 2);
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_autostash_updates_bundles_with_local_changes() -> Result<()> {
+    let test_name = "install_autostash";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let url = "https://github.com/example/icons.git";
+    let mut bundles = HashMap::new();
+    bundles.insert("icons".to_string(), bundle_dependency(url, "*"));
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Autostash"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(url, "", create_mock_bundle_content("Icons"));
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let update = || -> Result<BundleResult> {
+        let options = install::InstallOptions {
+            update: install::UpdateScope::All,
+            autostash: true,
+            ..Default::default()
+        };
+        let results = BundleResults::default();
+        install::install_with_results(&manifest_path, &options, mock_git.clone(), &results)?;
+        Ok(results.into_sorted().remove(0))
+    };
+
+    // The changes are applied again after the update
+    let bundle_path = design_dir.join(BUNDLE_DIR).join("icons");
+    mock_git.set_changed_files(&bundle_path, &["svg/logo.svg"]);
+    assert_eq!(update()?.error, None);
+    assert_eq!(
+        mock_git.get_changed_files(&bundle_path),
+        vec!["svg/logo.svg"]
+    );
+    assert_eq!(mock_git.get_stash_count(&bundle_path), 0);
+
+    // Conflicts are reported per file, and the stash is kept
+    mock_git.set_stash_conflicts(&bundle_path, &["svg/logo.svg"]);
+    let message = update()?.error.unwrap();
+    assert!(message.contains("svg/logo.svg"), "{}", message);
+    assert_eq!(mock_git.get_stash_count(&bundle_path), 1);
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_failed_install_rolls_back_installed_bundles() -> Result<()> {
+    let test_name = "install_rollback";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let icons_url = "https://github.com/example/icons.git";
+    let fonts_url = "https://github.com/example/fonts.git";
+    let mut bundles = HashMap::new();
+    bundles.insert("icons".to_string(), bundle_dependency(icons_url, "*"));
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Rollback"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(icons_url, "", create_mock_bundle_content("Icons"));
+    mock_git.register_remote_bundle(fonts_url, "", create_mock_bundle_content("Fonts"));
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    // The lockfile moves icons to another commit, a new bundle is added, and the
+    // last bundle fails to resolve (no tags match its version)
+    let lock_path = design_dir.join(LOCKFILE_NAME);
+    let mut lock = load_lockfile(&lock_path)?.unwrap();
+    let locked_commit = "fedcba9876543210fedcba9876543210fedcba98";
+    lock.bundles.get_mut("icons").unwrap().commit = locked_commit.to_string();
+    save_lockfile(&lock_path, &lock)?;
+
+    let mut bundles = HashMap::new();
+    bundles.insert("icons".to_string(), bundle_dependency(icons_url, "*"));
+    bundles.insert("fonts".to_string(), bundle_dependency(fonts_url, "*"));
+    bundles.insert(
+        "zz-broken".to_string(),
+        bundle_dependency("https://github.com/example/broken.git", "^9.0.0"),
+    );
+    create_bundle_manifest(&design_dir, Some("Rollback"), None, bundles)?;
+
+    let results = BundleResults::default();
+    let outcome = install::install_with_results(
+        &manifest_path,
+        &install::InstallOptions::default(),
+        mock_git.clone(),
+        &results,
+    );
+    assert!(outcome.is_err());
+
+    // The new bundle is gone and icons is back at its previous commit
+    let bundle_dir = design_dir.join(BUNDLE_DIR);
+    assert!(!bundle_dir.join("fonts").exists());
+    assert!(!bundle_dir.join(install::STAGING_DIR).exists());
+    let icons_path = bundle_dir.join("icons");
+    assert!(icons_path.join("bundle.toml").exists());
+    assert_eq!(
+        mock_git.get_checked_out_commit(&icons_path).as_deref(),
+        Some(MOCK_HEAD_COMMIT)
+    );
+
+    let state = load_state(&bundle_dir)?;
+    assert!(!state.bundles.contains_key("fonts"));
+    assert_eq!(
+        state.bundles["icons"].rev.as_deref(),
+        Some(MOCK_HEAD_COMMIT)
+    );
+    assert_eq!(load_lockfile(&lock_path)?, Some(lock));
+
+    let results = results.into_sorted();
+    assert!(results
+        .iter()
+        .filter(|result| result.name != "zz-broken")
+        .all(|result| result.action == BundleAction::Skipped));
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_install_single_bundle_leaves_others_alone() -> Result<()> {
+    let test_name = "install_single_bundle";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let icons_url = "https://github.com/example/icons.git";
+    let fonts_url = "https://github.com/example/fonts.git";
+    let mut bundles = HashMap::new();
+    bundles.insert("icons".to_string(), bundle_dependency(icons_url, "*"));
+    bundles.insert("fonts".to_string(), bundle_dependency(fonts_url, "*"));
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Single"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(icons_url, "", create_mock_bundle_content("Icons"));
+    mock_git.register_remote_bundle(fonts_url, "", create_mock_bundle_content("Fonts"));
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    // Both lock entries move to another commit, but only icons is installed
+    let lock_path = design_dir.join(LOCKFILE_NAME);
+    let mut lock = load_lockfile(&lock_path)?.unwrap();
+    let locked_commit = "fedcba9876543210fedcba9876543210fedcba98";
+    for entry in lock.bundles.values_mut() {
+        entry.commit = locked_commit.to_string();
+    }
+    save_lockfile(&lock_path, &lock)?;
+
+    let options = install::InstallOptions {
+        only: Some("icons".to_string()),
+        ..Default::default()
+    };
+    let results = BundleResults::default();
+    install::install_with_results(&manifest_path, &options, mock_git.clone(), &results)?;
+
+    let bundle_dir = design_dir.join(BUNDLE_DIR);
+    assert_eq!(
+        mock_git
+            .get_checked_out_commit(&bundle_dir.join("icons"))
+            .as_deref(),
+        Some(locked_commit)
+    );
+    assert_ne!(
+        mock_git
+            .get_checked_out_commit(&bundle_dir.join("fonts"))
+            .as_deref(),
+        Some(locked_commit)
+    );
+    let names: Vec<String> = results.into_sorted().into_iter().map(|r| r.name).collect();
+    assert_eq!(names, vec!["icons"]);
+
+    // The bundle left alone keeps its lock entry
+    assert_eq!(load_lockfile(&lock_path)?, Some(lock));
+
+    let missing = install::InstallOptions {
+        only: Some("missing".to_string()),
+        ..Default::default()
+    };
+    assert!(install::install_with_git(&manifest_path, &missing, mock_git.clone()).is_err());
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_install_resolves_prereleases_when_asked() -> Result<()> {
+    let test_name = "install_prereleases";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let icons_url = "https://github.com/example/icons.git";
+    let mockups_url = "https://github.com/example/mockups.git";
+    let fonts_url = "https://github.com/example/fonts.git";
+    let mut bundles = HashMap::new();
+    bundles.insert("icons".to_string(), bundle_dependency(icons_url, "^1.2"));
+    bundles.insert(
+        "mockups".to_string(),
+        BundleDependency {
+            allow_prerelease: true,
+            ..bundle_dependency(mockups_url, "^1.2")
+        },
+    );
+    bundles.insert(
+        "fonts".to_string(),
+        bundle_dependency(fonts_url, "1.3.0-beta"),
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Channels"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    for url in [icons_url, mockups_url, fonts_url] {
+        mock_git.register_remote_bundle(url, "", create_mock_bundle_content("Assets"));
+        mock_git.set_remote_tags(url, &["v1.2.0", "v1.3.0-beta.1", "v1.3.0-beta.2"]);
+    }
+
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let installed = |url: &str| {
+        mock_git
+            .get_cloned_repos()
+            .into_iter()
+            .find(|repo| repo.url == url)
+            .map(|repo| repo.branch)
+    };
+    assert_eq!(installed(icons_url).as_deref(), Some("v1.2.0"));
+    assert_eq!(installed(mockups_url).as_deref(), Some("v1.3.0-beta.2"));
+    assert_eq!(installed(fonts_url).as_deref(), Some("v1.3.0-beta.2"));
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_install_selects_bundles_by_group() -> Result<()> {
+    let test_name = "install_groups";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let icons_url = "https://github.com/example/icons.git";
+    let mockups_url = "https://github.com/example/mockups.git";
+    let fonts_url = "https://github.com/example/fonts.git";
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "icons".to_string(),
+        BundleDependency {
+            group: Some("prod".to_string()),
+            ..bundle_dependency(icons_url, "*")
+        },
+    );
+    bundles.insert(
+        "mockups".to_string(),
+        BundleDependency {
+            group: Some("dev".to_string()),
+            ..bundle_dependency(mockups_url, "*")
+        },
+    );
+    bundles.insert("fonts".to_string(), bundle_dependency(fonts_url, "*"));
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Groups"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(icons_url, "", create_mock_bundle_content("Icons"));
+    mock_git.register_remote_bundle(mockups_url, "", create_mock_bundle_content("Mockups"));
+    mock_git.register_remote_bundle(fonts_url, "", create_mock_bundle_content("Fonts"));
+
+    let bundle_dir = design_dir.join(BUNDLE_DIR);
+    let installed = |names: &[&str]| {
+        ["fonts", "icons", "mockups"]
+            .iter()
+            .all(|name| bundle_dir.join(name).exists() == names.contains(name))
+    };
+
+    // Bundles without a group are installed unless groups are selected
+    let without_dev = install::InstallOptions {
+        exclude_groups: vec!["dev".to_string()],
+        ..Default::default()
+    };
+    install::install_with_git(&manifest_path, &without_dev, mock_git.clone())?;
+    assert!(installed(&["fonts", "icons"]));
+
+    let prod = install::InstallOptions {
+        groups: vec!["prod".to_string()],
+        ..Default::default()
+    };
+    // Installed bundles outside the selected groups are left as they are
+    install::install_with_git(&manifest_path, &prod, mock_git.clone())?;
+    assert!(installed(&["fonts", "icons"]));
+
+    // A group no bundle is in is most likely misspelled
+    let misspelled = install::InstallOptions {
+        groups: vec!["prd".to_string()],
+        ..Default::default()
+    };
+    assert!(install::install_with_git(&manifest_path, &misspelled, mock_git.clone()).is_err());
+
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+    assert!(installed(&["fonts", "icons", "mockups"]));
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_optional_bundles_are_installed_on_demand() -> Result<()> {
+    let test_name = "install_optional";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let icons_url = "https://github.com/example/icons.git";
+    let textures_url = "https://github.com/example/textures.git";
+    let mut bundles = HashMap::new();
+    bundles.insert("icons".to_string(), bundle_dependency(icons_url, "*"));
+    bundles.insert(
+        "textures".to_string(),
+        BundleDependency {
+            optional: true,
+            ..bundle_dependency(textures_url, "*")
+        },
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Optional"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(icons_url, "", create_mock_bundle_content("Icons"));
+    mock_git.register_remote_bundle(textures_url, "", create_mock_bundle_content("Textures"));
+
+    let bundle_dir = design_dir.join(BUNDLE_DIR);
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+    assert!(bundle_dir.join("icons").exists());
+    assert!(!bundle_dir.join("textures").exists());
+    let lock = load_lockfile(&design_dir.join(LOCKFILE_NAME))?.unwrap();
+    assert!(!lock.bundles.contains_key("textures"));
+
+    let with_textures = install::InstallOptions {
+        with: vec!["textures".to_string()],
+        ..Default::default()
+    };
+    install::install_with_git(&manifest_path, &with_textures, mock_git.clone())?;
+    assert!(bundle_dir.join("textures").exists());
+
+    // Once installed, the optional bundle stays in the lockfile
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+    assert!(bundle_dir.join("textures").exists());
+    let lock = load_lockfile(&design_dir.join(LOCKFILE_NAME))?.unwrap();
+    assert!(lock.bundles.contains_key("textures"));
+
+    let not_optional = install::InstallOptions {
+        with: vec!["icons".to_string()],
+        ..Default::default()
+    };
+    assert!(install::install_with_git(&manifest_path, &not_optional, mock_git.clone()).is_err());
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_patch_replaces_nested_bundle_source() -> Result<()> {
+    let test_name = "install_patch";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let ui_kit_url = "https://github.com/example/ui-kit.git";
+    let styles_url = "https://github.com/example/base-styles.git";
+    let fork_url = "https://github.com/me/base-styles.git";
+    let mut bundles = HashMap::new();
+    bundles.insert("ui-kit".to_string(), bundle_dependency(ui_kit_url, "*"));
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Patch"), None, bundles)?;
+
+    // The patch names the repository without .git, as people often do
+    let mut manifest = load_manifest(&manifest_path)?;
+    manifest.patch.insert(
+        "https://github.com/example/base-styles".to_string(),
+        BundlePatch {
+            git: Some(fork_url.to_string()),
+            branch: Some("fix-colors".to_string()),
+            ..Default::default()
+        },
+    );
+    save_manifest(&manifest, &manifest_path)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    let mut nested_bundles = HashMap::new();
+    nested_bundles.insert(
+        "base-styles".to_string(),
+        bundle_dependency(styles_url, "*"),
+    );
+    mock_git.register_remote_bundle_with_deps(
+        ui_kit_url,
+        "",
+        create_mock_bundle_content("UI Kit"),
+        nested_bundles,
+    );
+    mock_git.register_remote_bundle(styles_url, "", create_mock_bundle_content("Styles"));
+    mock_git.register_remote_bundle(fork_url, "", create_mock_bundle_content("Fork"));
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let lock = load_lockfile(&design_dir.join(LOCKFILE_NAME))?.unwrap();
+    let nested = &lock.bundles["ui-kit/base-styles"];
+    assert_eq!(nested.git, fork_url);
+    assert_eq!(nested.branch, "fix-colors");
+    assert_eq!(lock.bundles["ui-kit"].git, ui_kit_url);
+
+    // A local checkout is linked in place of the repository
+    let checkout = test_dir.join("base-styles");
+    fs::create_dir_all(&checkout)?;
+    fs::write(checkout.join("colors.css"), "body {}")?;
+    manifest.patch.insert(
+        "https://github.com/example/base-styles".to_string(),
+        BundlePatch {
+            path: Some(PathBuf::from("../../base-styles")),
+            ..Default::default()
+        },
+    );
+    save_manifest(&manifest, &manifest_path)?;
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let nested_dir = design_dir
+        .join(BUNDLE_DIR)
+        .join("ui-kit")
+        .join(BUNDLE_DIR)
+        .join("base-styles");
+    assert!(nested_dir.join("colors.css").exists());
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_install_breaks_dependency_cycles() -> Result<()> {
+    let test_name = "install_cycle";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let ui_kit_url = "https://github.com/example/ui-kit.git";
+    let styles_url = "https://github.com/example/base-styles.git";
+    let mut bundles = HashMap::new();
+    bundles.insert("ui-kit".to_string(), bundle_dependency(ui_kit_url, "*"));
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Cycle"), None, bundles)?;
+
+    // ui-kit needs base-styles, which needs ui-kit again
+    let mock_git = Arc::new(MockGitOperations::new());
+    for (url, content, nested_name, nested_url) in [
+        (ui_kit_url, "UI Kit", "base-styles", styles_url),
+        (styles_url, "Styles", "ui-kit", ui_kit_url),
+    ] {
+        let mut nested_bundles = HashMap::new();
+        nested_bundles.insert(nested_name.to_string(), bundle_dependency(nested_url, "*"));
+        mock_git.register_remote_bundle_with_deps(
+            url,
+            "",
+            create_mock_bundle_content(content),
+            nested_bundles,
+        );
+    }
+
+    let results = BundleResults::default();
+    let options = install::InstallOptions::default();
+    install::install_with_results(&manifest_path, &options, mock_git.clone(), &results)?;
+
+    let styles_dir = design_dir
+        .join(BUNDLE_DIR)
+        .join("ui-kit")
+        .join(BUNDLE_DIR)
+        .join("base-styles");
+    assert!(styles_dir.join("README.md").exists());
+    assert!(!styles_dir.join(BUNDLE_DIR).join("ui-kit").exists());
+
+    let skipped = results
+        .into_sorted()
+        .into_iter()
+        .find(|result| result.name == "ui-kit/base-styles/ui-kit")
+        .unwrap();
+    assert_eq!(skipped.action, BundleAction::Skipped);
+    assert_eq!(
+        skipped.error.as_deref(),
+        Some("dependency cycle ui-kit -> base-styles -> ui-kit")
+    );
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_install_detects_version_conflicts() -> Result<()> {
+    let test_name = "install_version_conflicts";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let ui_kit_url = "https://github.com/example/ui-kit.git";
+    let icons_url = "https://github.com/example/icons.git";
+    let styles_url = "https://github.com/example/base-styles.git";
+    let mut bundles = HashMap::new();
+    bundles.insert("ui-kit".to_string(), bundle_dependency(ui_kit_url, "*"));
+    bundles.insert("icons".to_string(), bundle_dependency(icons_url, "*"));
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Conflicts"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    for (url, content, version) in [(ui_kit_url, "UI Kit", "^1"), (icons_url, "Icons", "^2")] {
+        let mut nested_bundles = HashMap::new();
+        nested_bundles.insert(
+            "base-styles".to_string(),
+            bundle_dependency(styles_url, version),
+        );
+        mock_git.register_remote_bundle_with_deps(
+            url,
+            "",
+            create_mock_bundle_content(content),
+            nested_bundles,
+        );
+    }
+    mock_git.register_remote_bundle(styles_url, "", create_mock_bundle_content("Styles"));
+    mock_git.set_remote_tags(styles_url, &["v1.2.0", "v2.0.0"]);
+
+    let error = execute_install_with_mock(&manifest_path, mock_git.clone()).unwrap_err();
+    let message = error.to_string();
+    assert!(message.contains("incompatible versions of https://github.com/example/base-styles"));
+    assert!(message.contains("ui-kit -> base-styles requires ^1 (installed v1.2.0)"));
+    assert!(message.contains("icons -> base-styles requires ^2 (installed v2.0.0)"));
+    // The install is rolled back
+    assert!(!design_dir.join(BUNDLE_DIR).join("ui-kit").exists());
+    assert!(!design_dir.join(LOCKFILE_NAME).exists());
+
+    let options = install::InstallOptions {
+        allow_duplicates: true,
+        ..Default::default()
+    };
+    install::install_with_git(&manifest_path, &options, mock_git.clone())?;
+    let lock = load_lockfile(&design_dir.join(LOCKFILE_NAME))?.unwrap();
+    assert_eq!(lock.bundles["ui-kit/base-styles"].branch, "v1.2.0");
+    assert_eq!(lock.bundles["icons/base-styles"].branch, "v2.0.0");
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_dedupe_shares_nested_bundles() -> Result<()> {
+    let test_name = "install_dedupe";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let ui_kit_url = "https://github.com/example/ui-kit.git";
+    let icons_url = "https://github.com/example/icons.git";
+    let styles_url = "https://github.com/example/base-styles.git";
+    let mut bundles = HashMap::new();
+    bundles.insert("ui-kit".to_string(), bundle_dependency(ui_kit_url, "*"));
+    bundles.insert("icons".to_string(), bundle_dependency(icons_url, "*"));
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Dedupe"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    for (url, content) in [(ui_kit_url, "UI Kit"), (icons_url, "Icons")] {
+        let mut nested_bundles = HashMap::new();
+        nested_bundles.insert(
+            "base-styles".to_string(),
+            bundle_dependency(styles_url, "*"),
+        );
+        mock_git.register_remote_bundle_with_deps(
+            url,
+            "",
+            create_mock_bundle_content(content),
+            nested_bundles,
+        );
+    }
+    mock_git.register_remote_bundle(styles_url, "", create_mock_bundle_content("Styles"));
+
+    let options = install::InstallOptions {
+        dedupe: true,
+        ..Default::default()
+    };
+    install::install_with_git(&manifest_path, &options, mock_git.clone())?;
+
+    // base-styles is cloned once and linked into both bundles
+    let bundle_dir = design_dir.join(BUNDLE_DIR);
+    let nested = |name: &str| bundle_dir.join(name).join(BUNDLE_DIR).join("base-styles");
+    let clones = mock_git
+        .get_cloned_repos()
+        .into_iter()
+        .filter(|repo| repo.url == styles_url)
+        .count();
+    assert_eq!(clones, 1);
+    assert!(nested("ui-kit").is_symlink());
+    assert!(nested("icons").is_symlink());
+    assert_eq!(
+        fs::read_link(nested("ui-kit"))?,
+        fs::read_link(nested("icons"))?
+    );
+    assert!(nested("icons").join("README.md").exists());
+
+    let lock = load_lockfile(&design_dir.join(LOCKFILE_NAME))?.unwrap();
+    assert_eq!(
+        lock.bundles["ui-kit/base-styles"],
+        lock.bundles["icons/base-styles"]
+    );
+    let state = load_state(&bundle_dir.join("icons").join(BUNDLE_DIR))?;
+    assert_eq!(
+        state.bundles["base-styles"].strategy,
+        InstallStrategy::Shared
+    );
+
+    // Without --dedupe, every bundle gets its own copy again
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+    assert!(!nested("ui-kit").is_symlink());
+    assert!(nested("ui-kit").join("README.md").exists());
+    assert!(!bundle_dir.join(install::SHARED_DIR).exists());
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_link_replaces_bundle_with_working_copy() -> Result<()> {
+    let test_name = "link_working_copy";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let url = "https://github.com/example/icons.git";
+    let mut bundles = HashMap::new();
+    bundles.insert("icons".to_string(), bundle_dependency(url, "*"));
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Link"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(url, "", create_mock_bundle_content("Icons"));
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+    let lock_path = design_dir.join(LOCKFILE_NAME);
+    let lock = load_lockfile(&lock_path)?;
+
+    // The working copy's directory name tells which bundle it is
+    let working_copy = test_dir.join("icons");
+    fs::create_dir_all(&working_copy)?;
+    fs::write(working_copy.join("logo.svg"), "<svg/>")?;
+    link::link_with_git(
+        &manifest_path,
+        Some(&working_copy),
+        None,
+        false,
+        &Settings::default(),
+        mock_git.clone(),
+    )?;
+
+    let bundle_path = design_dir.join(BUNDLE_DIR).join("icons");
+    assert!(bundle_path.is_symlink());
+    assert!(bundle_path.join("logo.svg").exists());
+    assert_eq!(load_lockfile(&lock_path)?, lock);
+
+    // Installs keep the link
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+    assert!(bundle_path.is_symlink());
+    assert_eq!(load_lockfile(&lock_path)?, lock);
+
+    link::unlink_with_git(&manifest_path, None, &Settings::default(), mock_git.clone())?;
+    assert!(!bundle_path.is_symlink());
+    assert!(bundle_path.exists());
+    assert!(working_copy.join("logo.svg").exists());
+    assert!(load_state(&design_dir.join(BUNDLE_DIR))?.links.is_empty());
+
+    let missing = link::link_with_git(
+        &manifest_path,
+        Some(&test_dir.join("src")),
+        None,
+        false,
+        &Settings::default(),
+        mock_git.clone(),
+    );
+    assert!(missing.is_err());
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_refresh_fetches_locked_bundles() -> Result<()> {
+    let test_name = "install_refresh";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let url = "https://github.com/example/icons.git";
+    let mut bundles = HashMap::new();
+    bundles.insert("icons".to_string(), bundle_dependency(url, "*"));
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Refresh"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(url, "", create_mock_bundle_content("Icons"));
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    // The lock keeps the clone where it is without touching the network
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+    assert!(mock_git.get_fetched_branches().is_empty());
+
+    let options = install::InstallOptions {
+        refresh: true,
+        ..Default::default()
+    };
+    install::install_with_git(&manifest_path, &options, mock_git.clone())?;
+    assert_eq!(
+        mock_git.get_fetched_branches(),
+        vec![(
+            design_dir.join(BUNDLE_DIR).join("icons"),
+            "main".to_string()
+        )]
+    );
+    assert_eq!(mock_git.get_cloned_repos().len(), 1);
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_log_marks_commits_outside_include_filter() -> Result<()> {
+    let test_name = "log_include_filter";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    let url = "https://github.com/example/large-repo.git";
+    let mut bundles = HashMap::new();
+    bundles.insert(
+        "filtered".to_string(),
+        BundleDependency {
+            include: Some(vec!["folder2".to_string()]),
+            ..bundle_dependency(url, "*")
+        },
+    );
+    let manifest_path = create_bundle_manifest(&design_dir, Some("Log"), None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    mock_git.register_remote_bundle(url, "", create_mock_bundle_content("Large repo"));
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    let commit = |id: &str, files: &[&str]| CommitSummary {
+        id: id.to_string(),
+        author: "Jane Doe".to_string(),
+        time: 1_760_000_000,
+        summary: format!("Commit {}", id),
+        files: files.iter().map(|file| file.to_string()).collect(),
+    };
+    mock_git.set_log(
+        &design_dir.join(BUNDLE_DIR).join("filtered"),
+        vec![
+            commit("c3", &["folder1/notes.txt"]),
+            commit("c2", &["folder1/a.txt", "folder2/b.txt"]),
+            commit("c1", &["folder2/c.txt"]),
+        ],
+    );
+
+    let (entries, filtered) = log::collect_log(&manifest_path, "filtered", 2, mock_git.clone())?;
+    assert!(filtered);
+    let touched: Vec<(&str, bool)> = entries
+        .iter()
+        .map(|entry| (entry.commit.id.as_str(), entry.touches_installed))
+        .collect();
+    assert_eq!(touched, vec![("c3", false), ("c2", true)]);
+
+    assert!(log::collect_log(&manifest_path, "missing", 2, mock_git).is_err());
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_local_path_bundle_workflow() -> Result<()> {
+    let test_name = "local_path_bundle";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    // A local directory next to the project, not yet published anywhere
+    let shared_dir = test_dir.join("shared-assets");
+    fs::create_dir_all(&shared_dir)?;
+    fs::write(shared_dir.join("logo.svg"), "<svg/>")?;
+
+    let mut local = bundle_dependency("", "");
+    local.path = Some(PathBuf::from("../../shared-assets"));
+    let mut bundles = HashMap::new();
+    bundles.insert("shared".to_string(), local);
+    let manifest_path = create_bundle_manifest(&design_dir, None, None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    // Nothing is cloned; the bundle shows the local directory's files
+    assert!(mock_git.get_cloned_repos().is_empty());
+    let bundle_path = design_dir.join(BUNDLE_DIR).join("shared");
+    assert_eq!(fs::read_to_string(bundle_path.join("logo.svg"))?, "<svg/>");
+
+    // Edits to the local directory show up without reinstalling
+    fs::write(shared_dir.join("icon.svg"), "<svg/>")?;
+    assert!(bundle_path.join("icon.svg").exists());
+
+    let state = load_state(&design_dir.join(BUNDLE_DIR))?;
+    assert_eq!(state.bundles["shared"].strategy, InstallStrategy::Link);
+
+    // Local bundles have no commit to lock, and are reported as local
+    let lockfile = load_lockfile(&design_dir.join(LOCKFILE_NAME))?.unwrap();
+    assert!(lockfile.bundles.is_empty());
+    let statuses = get_bundle_statuses_with_mock(&manifest_path, mock_git.clone())?;
+    assert_eq!(statuses, vec![("shared".to_string(), BundleStatus::Local)]);
+
+    // Removing the bundle deletes the link, not the local directory
+    remove::execute_with_git(
+        &manifest_path,
+        "shared",
+        false,
+        &Settings::default(),
+        mock_git,
+    )?;
+    assert!(!bundle_path.exists());
+    assert!(shared_dir.join("logo.svg").exists());
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_archive_bundle_workflow() -> Result<()> {
+    let test_name = "archive_bundle";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    // A release tarball with everything below a single top-level directory
+    let archive_path = test_dir.join("assets-1.0.0.tar.gz");
+    let encoder = flate2::write::GzEncoder::new(
+        fs::File::create(&archive_path)?,
+        flate2::Compression::default(),
+    );
+    let mut builder = tar::Builder::new(encoder);
+    let mut header = tar::Header::new_gnu();
+    header.set_size(6);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, "assets-1.0.0/logo.svg", "<svg/>".as_bytes())?;
+    builder.into_inner()?.finish()?;
+
+    let mut archive = bundle_dependency("", "");
+    archive.url = Some(url::Url::from_file_path(&archive_path).unwrap().to_string());
+    let mut bundles = HashMap::new();
+    bundles.insert("assets".to_string(), archive);
+    let manifest_path = create_bundle_manifest(&design_dir, None, None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    // Nothing is cloned; the archive's files are unpacked into the bundle
+    assert!(mock_git.get_cloned_repos().is_empty());
+    let bundle_path = design_dir.join(BUNDLE_DIR).join("assets");
+    assert_eq!(fs::read_to_string(bundle_path.join("logo.svg"))?, "<svg/>");
+
+    // The checksum of the archive is recorded, but nothing is locked
+    let state = load_state(&design_dir.join(BUNDLE_DIR))?;
+    assert_eq!(
+        state.bundles["assets"].rev.as_ref().map(String::len),
+        Some(64)
+    );
+    let lockfile = load_lockfile(&design_dir.join(LOCKFILE_NAME))?.unwrap();
+    assert!(lockfile.bundles.is_empty());
+    let statuses = get_bundle_statuses_with_mock(&manifest_path, mock_git.clone())?;
+    assert_eq!(statuses, vec![("assets".to_string(), BundleStatus::Synced)]);
+
+    // Pinning a different checksum fails the install
+    let mut manifest = load_manifest(&manifest_path)?;
+    manifest.bundles.get_mut("assets").unwrap().sha256 = Some("0".repeat(64));
+    save_manifest(&manifest, &manifest_path)?;
+    let error = execute_install_with_mock(&manifest_path, mock_git).unwrap_err();
+    assert!(
+        format!("{:#}", error).contains("Checksum mismatch"),
+        "Got: {:#}",
+        error
+    );
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+/// A source serving one made-up archive URL from memory, as a library user might add
+struct InMemorySource;
+
+const IN_MEMORY_URL: &str = "https://artifacts.example.invalid/in-memory.tar.gz";
+
+impl BundleSource for InMemorySource {
+    fn kind(&self) -> &str {
+        "in-memory"
+    }
+
+    fn handles(&self, dependency: &BundleDependency) -> bool {
+        dependency.url.as_deref() == Some(IN_MEMORY_URL)
+    }
+
+    fn fetch(&self, request: &FetchRequest) -> Result<Fetched> {
+        fs::create_dir_all(request.target())?;
+        fs::write(request.target().join("from-memory.txt"), request.name)?;
+        Ok(Fetched {
+            resolved: ResolvedRef::branch(request.dependency),
+            rev: Some("v1".to_string()),
+            strategy: InstallStrategy::Flatten,
+        })
+    }
+
+    fn status(&self, path: &Path, _dependency: &BundleDependency) -> Result<BundleStatus> {
+        Ok(if path.join("from-memory.txt").exists() {
+            BundleStatus::Synced
+        } else {
+            BundleStatus::Unsynced
+        })
+    }
+}
+
+#[test]
+fn test_registered_bundle_source() -> Result<()> {
+    let test_name = "registered_bundle_source";
+    let test_dir = setup_test_env(TEST_CATEGORY, test_name)?;
+
+    create_sample_project(&test_dir)?;
+    let design_dir = test_dir.join("src").join("design");
+
+    source::register(Arc::new(InMemorySource));
+
+    let mut dependency = bundle_dependency("", "");
+    dependency.url = Some(IN_MEMORY_URL.to_string());
+    let mut bundles = HashMap::new();
+    bundles.insert("generated".to_string(), dependency);
+    let manifest_path = create_bundle_manifest(&design_dir, None, None, bundles)?;
+
+    let mock_git = Arc::new(MockGitOperations::new());
+    execute_install_with_mock(&manifest_path, mock_git.clone())?;
+
+    // The registered source takes the bundle over from the built-in archive source
+    let bundle_path = design_dir.join(BUNDLE_DIR).join("generated");
+    assert_eq!(
+        fs::read_to_string(bundle_path.join("from-memory.txt"))?,
+        "generated"
+    );
+    let state = load_state(&design_dir.join(BUNDLE_DIR))?;
+    assert_eq!(state.bundles["generated"].rev.as_deref(), Some("v1"));
+    assert!(mock_git.get_cloned_repos().is_empty());
+
+    let statuses = get_bundle_statuses_with_mock(&manifest_path, mock_git)?;
+    assert_eq!(
+        statuses,
+        vec![("generated".to_string(), BundleStatus::Synced)]
+    );
+
+    cleanup_test_env(TEST_CATEGORY, test_name)?;
+
+    Ok(())
+}
+
+// === Helper functions for mock-based execution ===
+
+/// Creates a dependency on `git` with the given version and default settings
+fn bundle_dependency(git: &str, version: &str) -> BundleDependency {
+    BundleDependency {
+        version: version.to_string(),
+        git: git.to_string(),
+        ..Default::default()
+    }
+}
 
 fn create_mock_bundle_content(description: &str) -> MockBundleContent {
     MockBundleContent {
@@ -313,7 +3761,7 @@ fn create_mock_bundle_content(description: &str) -> MockBundleContent {
 /// Executes install command using mock git operations
 /// Now delegates to the actual install command with DI
 fn execute_install_with_mock(manifest_path: &Path, mock_git: Arc<MockGitOperations>) -> Result<()> {
-    install::execute_with_git(manifest_path, mock_git)
+    install::execute_with_git(manifest_path, &install::InstallOptions::default(), mock_git)
 }
 
 /// Gets bundle statuses using mock git operations